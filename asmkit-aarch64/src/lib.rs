@@ -0,0 +1,2 @@
+pub mod stream;
+pub mod register;
\ No newline at end of file
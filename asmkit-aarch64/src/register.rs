@@ -0,0 +1,159 @@
+//! Registers in AArch64 assembly.
+
+/// A 64-bit general purpose register.
+///
+/// Instructions which operate on a narrower width (such as the byte-sized exclusive loads) use the 32-bit `w` view of the provided register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reg64 {
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30,
+
+    /// The zero register.  Shares its encoding with [`Reg64::Sp`], the instruction decides which of the two is meant.
+    Xzr,
+
+    /// The stack pointer.  Shares its encoding with [`Reg64::Xzr`], the instruction decides which of the two is meant.
+    Sp,
+}
+
+impl Reg64 {
+    /// The offset of this register, for instruction encoding.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_aarch64::register::Reg64;
+    ///
+    /// assert_eq!(Reg64::X30.offset(), 30);
+    /// assert_eq!(Reg64::Xzr.offset(), Reg64::Sp.offset());
+    /// ```
+    pub fn offset(&self) -> u32 {
+        match self {
+            Self::X0 => 0,
+            Self::X1 => 1,
+            Self::X2 => 2,
+            Self::X3 => 3,
+            Self::X4 => 4,
+            Self::X5 => 5,
+            Self::X6 => 6,
+            Self::X7 => 7,
+            Self::X8 => 8,
+            Self::X9 => 9,
+            Self::X10 => 10,
+            Self::X11 => 11,
+            Self::X12 => 12,
+            Self::X13 => 13,
+            Self::X14 => 14,
+            Self::X15 => 15,
+            Self::X16 => 16,
+            Self::X17 => 17,
+            Self::X18 => 18,
+            Self::X19 => 19,
+            Self::X20 => 20,
+            Self::X21 => 21,
+            Self::X22 => 22,
+            Self::X23 => 23,
+            Self::X24 => 24,
+            Self::X25 => 25,
+            Self::X26 => 26,
+            Self::X27 => 27,
+            Self::X28 => 28,
+            Self::X29 => 29,
+            Self::X30 => 30,
+            Self::Xzr | Self::Sp => 31,
+        }
+    }
+}
+
+/// A system register, accessed through `mrs` and `msr`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemReg {
+    /// Condition flags.
+    Nzcv,
+
+    /// Interrupt mask bits.
+    Daif,
+
+    /// Floating-point control register.
+    Fpcr,
+
+    /// Floating-point status register.
+    Fpsr,
+
+    /// EL0 read/write software thread ID register, used as the thread pointer on most operating systems.
+    TpidrEl0,
+
+    /// EL0 read-only software thread ID register.
+    TpidrroEl0,
+
+    /// Virtual count register.
+    CntvctEl0,
+
+    /// Counter-timer frequency register.
+    CntfrqEl0,
+
+    /// Current exception level.
+    CurrentEl,
+
+    /// EL1 software thread ID register.
+    TpidrEl1,
+
+    /// EL1 vector base address register.
+    VbarEl1,
+
+    /// Main ID register.
+    MidrEl1,
+}
+
+impl SystemReg {
+    /// The `(op0, op1, CRn, CRm, op2)` tuple identifying this system register.
+    pub fn encoding(&self) -> (u32, u32, u32, u32, u32) {
+        match self {
+            Self::Nzcv => (3, 3, 4, 2, 0),
+            Self::Daif => (3, 3, 4, 2, 1),
+            Self::Fpcr => (3, 3, 4, 4, 0),
+            Self::Fpsr => (3, 3, 4, 4, 1),
+            Self::TpidrEl0 => (3, 3, 13, 0, 2),
+            Self::TpidrroEl0 => (3, 3, 13, 0, 3),
+            Self::CntvctEl0 => (3, 3, 14, 0, 2),
+            Self::CntfrqEl0 => (3, 3, 14, 0, 0),
+            Self::CurrentEl => (3, 0, 4, 2, 2),
+            Self::TpidrEl1 => (3, 0, 13, 0, 4),
+            Self::VbarEl1 => (3, 0, 12, 0, 0),
+            Self::MidrEl1 => (3, 0, 0, 0, 0),
+        }
+    }
+
+    /// The 16-bit system register field, as it appears in bits 5 to 20 of `mrs` and `msr`.
+    pub fn field(&self) -> u32 {
+        let (op0, op1, crn, crm, op2) = self.encoding();
+        (op0 << 14) | (op1 << 11) | (crn << 7) | (crm << 3) | op2
+    }
+}
@@ -0,0 +1,536 @@
+//! Instruction streaming implementation.
+
+use asmkit_core::{
+    entity::{EntityList, Label, LabelRef, SymRef, Symbol},
+    raw::Endianness,
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+use crate::register::{Reg64, SystemReg};
+
+/// The width of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Width {
+    /// An 8-bit access, the `b` suffixed instructions.
+    Byte,
+
+    /// A 16-bit access, the `h` suffixed instructions.
+    Halfword,
+
+    /// A 32-bit access, using the `w` view of the data registers.
+    Word,
+
+    /// A 64-bit access, using the `x` view of the data registers.
+    Doubleword,
+}
+
+impl Width {
+    /// The `size` field of this width, for instruction encoding.
+    pub fn size(&self) -> u32 {
+        match self {
+            Self::Byte => 0b00,
+            Self::Halfword => 0b01,
+            Self::Word => 0b10,
+            Self::Doubleword => 0b11,
+        }
+    }
+}
+
+/// The memory ordering of an atomic or exclusive instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ordering {
+    /// No ordering constraints, the plain form of the instruction.
+    Relaxed,
+
+    /// Acquire semantics, the `a` suffixed form of the instruction.
+    Acquire,
+
+    /// Release semantics, the `l` suffixed form of the instruction.
+    Release,
+
+    /// Both acquire and release semantics, the `al` suffixed form of the instruction.
+    AcqRel,
+}
+
+impl Ordering {
+    /// Returns true if this ordering has acquire semantics.
+    pub fn is_acquire(&self) -> bool {
+        matches!(self, Self::Acquire | Self::AcqRel)
+    }
+
+    /// Returns true if this ordering has release semantics.
+    pub fn is_release(&self) -> bool {
+        matches!(self, Self::Release | Self::AcqRel)
+    }
+}
+
+/// The option field of the `dmb` and `dsb` barriers, selecting the shareability domain and the accesses which are ordered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BarrierOption {
+    /// Outer shareable, loads only.
+    OshLd,
+
+    /// Outer shareable, stores only.
+    OshSt,
+
+    /// Outer shareable, loads and stores.
+    Osh,
+
+    /// Non-shareable, loads only.
+    NshLd,
+
+    /// Non-shareable, stores only.
+    NshSt,
+
+    /// Non-shareable, loads and stores.
+    Nsh,
+
+    /// Inner shareable, loads only.
+    IshLd,
+
+    /// Inner shareable, stores only.
+    IshSt,
+
+    /// Inner shareable, loads and stores.
+    Ish,
+
+    /// Full system, loads only.
+    Ld,
+
+    /// Full system, stores only.
+    St,
+
+    /// Full system, loads and stores.
+    Sy,
+}
+
+impl BarrierOption {
+    /// The `CRm` field of this option, for instruction encoding.
+    pub fn crm(&self) -> u32 {
+        match self {
+            Self::OshLd => 0b0001,
+            Self::OshSt => 0b0010,
+            Self::Osh => 0b0011,
+            Self::NshLd => 0b0101,
+            Self::NshSt => 0b0110,
+            Self::Nsh => 0b0111,
+            Self::IshLd => 0b1001,
+            Self::IshSt => 0b1010,
+            Self::Ish => 0b1011,
+            Self::Ld => 0b1101,
+            Self::St => 0b1110,
+            Self::Sy => 0b1111,
+        }
+    }
+}
+
+/// An instruction stream for the AArch64 target.
+pub struct Aarch64InstructionStream {
+    /// The bytes written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream.
+    labels: EntityList<Label, LabelRef>,
+
+    /// The external symbols referred to by the instruction stream.
+    symbols: EntityList<Symbol, SymRef>,
+
+    /// The relocations against the symbols.
+    relocations: Vec<Relocation>,
+
+    /// The symbols defined by the instruction stream, and the labels they are attached to.
+    definitions: Vec<(String, LabelRef)>,
+
+    /// The holes of the templates written to the instruction stream.
+    holes: TemplateHoles,
+}
+
+impl InstructionStream for Aarch64InstructionStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.push(Label::Unattached)
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.push(Label::Attached(self.bytes.len()))
+    }
+
+    #[inline(always)]
+    fn attach_label(&mut self, label: LabelRef) {
+        *self.labels.get_mut(label) = Label::Attached(self.bytes.len());
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, filled when the stream is finished, or left to a relocation for a symbol or the absolute address of a
+    /// label.
+    ///
+    /// # Example
+    /// A template holding the 32-bit offset of a label from the end of its hole, and the address of a symbol:
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    /// use asmkit_aarch64::stream::Aarch64InstructionStream;
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// let handler = stream.create_symbol("handler");
+    /// let data = stream.create_label();
+    /// stream.ret();
+    /// stream.emit_template(&[0; 12], &[
+    ///     TemplateFixup::rel32(0, TemplateTarget::Label(data)),
+    ///     TemplateFixup::abs64(4, TemplateTarget::Symbol(handler)),
+    /// ]);
+    /// stream.attach_label(data);
+    /// stream.write_double_word(42);
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 8, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(handler), addend: 0 },
+    /// ]);
+    /// assert_eq!(product.emit()[4..8], [0x08, 0x00, 0x00, 0x00]);
+    /// ```
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push(fixup);
+    }
+
+    /// Finishes writing to the instruction stream, filling the holes of the templates, and returns the produced bytes.
+    ///
+    /// # Panics
+    /// Panics if the label of a defined symbol or of a template hole is never attached, or if the displacement of a label doesn't fit in its
+    /// template hole.
+    fn finish(mut self) -> Product {
+        let holes = self.holes.resolve(&mut self.bytes, Endianness::Little, |label| self.labels.get(label).offset()).unwrap_or_else(|error| panic!("{}", error));
+        self.relocations.extend(holes);
+        self.relocations.sort_by_key(|relocation| relocation.offset);
+
+        let mut product = Product::with_relocations(self.bytes, self.relocations, self.symbols);
+        for (name, label) in &self.definitions {
+            match self.labels.get(*label) {
+                Label::Attached(offset) => product.define(name, *offset),
+                _ => panic!("the label of symbol `{}` is never attached", name),
+            }
+        }
+
+        product
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
+
+impl Default for Aarch64InstructionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aarch64InstructionStream {
+    /// Creates a new, empty instruction stream.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), labels: EntityList::new(), symbols: EntityList::new(), relocations: Vec::new(), definitions: Vec::new(), holes: TemplateHoles::new() }
+    }
+
+    /// Creates a reference to the external symbol `name`, whose address is resolved when the product is relocated or linked.
+    pub fn create_symbol(&mut self, name: &str) -> SymRef {
+        self.symbols.push(Symbol { name: name.to_string() })
+    }
+
+    /// Exports `label` as the symbol `name`, defined in the product for other products to branch to with
+    /// [`Aarch64InstructionStream::bl_symbol`] once they are linked together by [`asmkit_core::link()`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if the label is never attached.
+    pub fn define_symbol(&mut self, name: &str, label: LabelRef) {
+        self.definitions.push((name.to_string(), label));
+    }
+
+    /// Writes a single 32-bit instruction into the instruction stream.
+    #[inline(always)]
+    pub fn write_instruction(&mut self, instruction: u32) {
+        self.write_double_word(instruction);
+    }
+
+    /// Move *system register* to *Xt*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::{Reg64, SystemReg}, stream::Aarch64InstructionStream};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.mrs(Reg64::X0, SystemReg::TpidrEl0); // mrs x0, tpidr_el0
+    /// stream.mrs(Reg64::X3, SystemReg::CntvctEl0); // mrs x3, cntvct_el0
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x40, 0xd0, 0x3b, 0xd5, 0x43, 0xe0, 0x3b, 0xd5]);
+    /// ```
+    pub fn mrs(&mut self, dest: Reg64, src: SystemReg) {
+        self.write_instruction(0xd5200000 | (src.field() << 5) | dest.offset());
+    }
+
+    /// Move *Xt* to *system register*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::{Reg64, SystemReg}, stream::Aarch64InstructionStream};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.msr(SystemReg::Nzcv, Reg64::X1); // msr nzcv, x1
+    /// stream.msr(SystemReg::Fpcr, Reg64::X2); // msr fpcr, x2
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x01, 0x42, 0x1b, 0xd5, 0x02, 0x44, 0x1b, 0xd5]);
+    /// ```
+    pub fn msr(&mut self, dest: SystemReg, src: Reg64) {
+        self.write_instruction(0xd5000000 | (dest.field() << 5) | src.offset());
+    }
+
+    /// Branch with link to *symbol*, reaching ±128 MiB, `bl symbol`.  The displacement is left to a [`RelocationKind::Branch26`] relocation.
+    ///
+    /// # Example
+    /// Two functions assembled apart, the first calling the second, and linked together:
+    /// ```
+    /// use asmkit_core::{link, reloc::{RelocationKind, RelocationTarget}, InstructionStream, LinkOptions};
+    /// use asmkit_aarch64::stream::Aarch64InstructionStream;
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// let answer = stream.create_symbol("answer");
+    /// let caller = stream.create_label_attached();
+    /// stream.define_symbol("caller", caller);
+    /// stream.write_instruction(0xa9bf7bfd); // stp x29, x30, [sp, #-16]!
+    /// stream.bl_symbol(answer);
+    /// stream.write_instruction(0xa8c17bfd); // ldp x29, x30, [sp], #16
+    /// stream.ret();
+    ///
+    /// let caller = stream.finish();
+    /// assert_eq!(caller.relocations()[0].offset, 4);
+    /// assert_eq!(caller.relocations()[0].kind, RelocationKind::Branch26);
+    /// assert_eq!(caller.relocations()[0].target, RelocationTarget::Symbol(answer));
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// let answer = stream.create_label_attached();
+    /// stream.define_symbol("answer", answer);
+    /// stream.write_instruction(0x52800540); // mov w0, #42
+    /// stream.ret();
+    ///
+    /// let product = link(vec![caller, stream.finish()], LinkOptions::default()).unwrap();
+    /// assert!(product.relocations().is_empty());
+    /// assert_eq!(product.emit()[..20], [
+    ///     0xfd, 0x7b, 0xbf, 0xa9, // caller: stp x29, x30, [sp, #-16]!
+    ///     0x03, 0x00, 0x00, 0x94, // bl answer
+    ///     0xfd, 0x7b, 0xc1, 0xa8, // ldp x29, x30, [sp], #16
+    ///     0xc0, 0x03, 0x5f, 0xd6, // ret
+    ///     0x40, 0x05, 0x80, 0x52, // answer: mov w0, #42
+    /// ]);
+    ///
+    /// # #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    /// # {
+    /// use asmkit_core::exec::ExecutableBuffer;
+    ///
+    /// let offset = product.definition("caller").unwrap();
+    /// let buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+    /// let caller: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr().add(offset)) };
+    /// assert_eq!(caller(), 42);
+    /// # }
+    /// ```
+    pub fn bl_symbol(&mut self, symbol: SymRef) {
+        self.write_branch_symbol(0x94000000, symbol);
+    }
+
+    /// Branch to *symbol*, reaching ±128 MiB, `b symbol`.  The displacement is left to a [`RelocationKind::Branch26`] relocation.
+    pub fn b_symbol(&mut self, symbol: SymRef) {
+        self.write_branch_symbol(0x14000000, symbol);
+    }
+
+    /// Writes the branch `opcode` to `symbol`, with a relocation for its displacement.
+    fn write_branch_symbol(&mut self, opcode: u32, symbol: SymRef) {
+        self.relocations.push(Relocation { offset: self.bytes.len(), kind: RelocationKind::Branch26, target: RelocationTarget::Symbol(symbol), addend: 0 });
+        self.write_instruction(opcode);
+    }
+
+    /// Return from subroutine, branching to the address in `x30`, `ret`.
+    pub fn ret(&mut self) {
+        self.write_instruction(0xd65f03c0);
+    }
+
+    /// Data memory barrier.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::stream::{Aarch64InstructionStream, BarrierOption};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.dmb(BarrierOption::Ish); // dmb ish
+    /// stream.dsb(BarrierOption::Sy); // dsb sy
+    /// stream.isb(); // isb
+    ///
+    /// assert_eq!(stream.finish().emit(), [0xbf, 0x3b, 0x03, 0xd5, 0x9f, 0x3f, 0x03, 0xd5, 0xdf, 0x3f, 0x03, 0xd5]);
+    /// ```
+    pub fn dmb(&mut self, option: BarrierOption) {
+        self.write_instruction(0xd50330bf | (option.crm() << 8));
+    }
+
+    /// Data synchronization barrier.
+    pub fn dsb(&mut self, option: BarrierOption) {
+        self.write_instruction(0xd503309f | (option.crm() << 8));
+    }
+
+    /// Instruction synchronization barrier.
+    pub fn isb(&mut self) {
+        self.write_instruction(0xd5033fdf);
+    }
+
+    /// Load exclusive register from *[Xn]* into *Rt*.
+    ///
+    /// Emits `ldxr` for [`Ordering::Relaxed`] and `ldaxr` for [`Ordering::Acquire`].
+    ///
+    /// # Panics
+    /// Panics if `ordering` has release semantics, which exclusive loads cannot express.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::Reg64, stream::{Aarch64InstructionStream, Ordering, Width}};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.ldxr(Width::Doubleword, Ordering::Relaxed, Reg64::X0, Reg64::X1); // ldxr x0, [x1]
+    /// stream.ldxr(Width::Byte, Ordering::Acquire, Reg64::X0, Reg64::X1); // ldaxrb w0, [x1]
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x20, 0x7c, 0x5f, 0xc8, 0x20, 0xfc, 0x5f, 0x08]);
+    /// ```
+    pub fn ldxr(&mut self, width: Width, ordering: Ordering, dest: Reg64, address: Reg64) {
+        assert!(!ordering.is_release(), "exclusive loads cannot have release semantics");
+
+        let o0 = ordering.is_acquire() as u32;
+        self.write_instruction((width.size() << 30) | 0x085f7c00 | (o0 << 15) | (address.offset() << 5) | dest.offset());
+    }
+
+    /// Store exclusive register *Rt* to *[Xn]*, writing the status to *Ws*.
+    ///
+    /// Emits `stxr` for [`Ordering::Relaxed`] and `stlxr` for [`Ordering::Release`].
+    ///
+    /// # Panics
+    /// Panics if `ordering` has acquire semantics, which exclusive stores cannot express.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::Reg64, stream::{Aarch64InstructionStream, Ordering, Width}};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.stxr(Width::Doubleword, Ordering::Relaxed, Reg64::X2, Reg64::X0, Reg64::X1); // stxr w2, x0, [x1]
+    /// stream.stxr(Width::Word, Ordering::Release, Reg64::X2, Reg64::X0, Reg64::X1); // stlxr w2, w0, [x1]
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x20, 0x7c, 0x02, 0xc8, 0x20, 0xfc, 0x02, 0x88]);
+    /// ```
+    pub fn stxr(&mut self, width: Width, ordering: Ordering, status: Reg64, src: Reg64, address: Reg64) {
+        assert!(!ordering.is_acquire(), "exclusive stores cannot have acquire semantics");
+
+        let o0 = ordering.is_release() as u32;
+        self.write_instruction((width.size() << 30) | 0x08007c00 | (status.offset() << 16) | (o0 << 15) | (address.offset() << 5) | src.offset());
+    }
+
+    /// Encodes one of the LSE atomic memory operations, `opcode` holding the `o3` and `opc` fields in place.
+    fn atomic_memory_op(&mut self, width: Width, ordering: Ordering, opcode: u32, src: Reg64, dest: Reg64, address: Reg64) {
+        let a = ordering.is_acquire() as u32;
+        let r = ordering.is_release() as u32;
+        self.write_instruction(
+            (width.size() << 30) | 0x38200000 | (a << 23) | (r << 22) | (src.offset() << 16) | opcode | (address.offset() << 5) | dest.offset()
+        );
+    }
+
+    /// Atomically add *Rs* to *[Xn]*, loading the previous value into *Rt*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::Reg64, stream::{Aarch64InstructionStream, Ordering, Width}};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.ldadd(Width::Doubleword, Ordering::Relaxed, Reg64::X0, Reg64::X1, Reg64::X2); // ldadd x0, x1, [x2]
+    /// stream.ldadd(Width::Doubleword, Ordering::AcqRel, Reg64::X0, Reg64::X1, Reg64::X2); // ldaddal x0, x1, [x2]
+    /// stream.ldadd(Width::Halfword, Ordering::Release, Reg64::X0, Reg64::X1, Reg64::X2); // ldaddlh w0, w1, [x2]
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x41, 0x00, 0x20, 0xf8, 0x41, 0x00, 0xe0, 0xf8, 0x41, 0x00, 0x60, 0x78]);
+    /// ```
+    pub fn ldadd(&mut self, width: Width, ordering: Ordering, src: Reg64, dest: Reg64, address: Reg64) {
+        self.atomic_memory_op(width, ordering, 0x0000, src, dest, address);
+    }
+
+    /// Atomically swap *Rs* with *[Xn]*, loading the previous value into *Rt*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::Reg64, stream::{Aarch64InstructionStream, Ordering, Width}};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.swp(Width::Doubleword, Ordering::Relaxed, Reg64::X0, Reg64::X1, Reg64::X2); // swp x0, x1, [x2]
+    /// stream.swp(Width::Word, Ordering::Acquire, Reg64::X0, Reg64::X1, Reg64::X2); // swpa w0, w1, [x2]
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x41, 0x80, 0x20, 0xf8, 0x41, 0x80, 0xa0, 0xb8]);
+    /// ```
+    pub fn swp(&mut self, width: Width, ordering: Ordering, src: Reg64, dest: Reg64, address: Reg64) {
+        self.atomic_memory_op(width, ordering, 0x8000, src, dest, address);
+    }
+
+    /// Compare *Rs* with *[Xn]*, storing *Rt* if they are equal.  The previous value is loaded into *Rs*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_aarch64::{register::Reg64, stream::{Aarch64InstructionStream, Ordering, Width}};
+    ///
+    /// let mut stream = Aarch64InstructionStream::new();
+    /// stream.cas(Width::Doubleword, Ordering::Relaxed, Reg64::X0, Reg64::X1, Reg64::X2); // cas x0, x1, [x2]
+    /// stream.cas(Width::Doubleword, Ordering::Acquire, Reg64::X0, Reg64::X1, Reg64::X2); // casa x0, x1, [x2]
+    /// stream.cas(Width::Doubleword, Ordering::Release, Reg64::X0, Reg64::X1, Reg64::X2); // casl x0, x1, [x2]
+    /// stream.cas(Width::Word, Ordering::AcqRel, Reg64::X0, Reg64::X1, Reg64::X2); // casal w0, w1, [x2]
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x41, 0x7c, 0xa0, 0xc8,
+    ///     0x41, 0x7c, 0xe0, 0xc8,
+    ///     0x41, 0xfc, 0xa0, 0xc8,
+    ///     0x41, 0xfc, 0xe0, 0x88,
+    /// ]);
+    /// ```
+    pub fn cas(&mut self, width: Width, ordering: Ordering, compare: Reg64, src: Reg64, address: Reg64) {
+        let l = ordering.is_acquire() as u32;
+        let o0 = ordering.is_release() as u32;
+        self.write_instruction((width.size() << 30) | 0x08a07c00 | (l << 22) | (compare.offset() << 16) | (o0 << 15) | (address.offset() << 5) | src.offset());
+    }
+}
@@ -0,0 +1,156 @@
+//! Instruction fields of the Thumb-2 encoding.
+//!
+//! A 32-bit Thumb instruction is handled as a single `u32` holding its first halfword in the upper 16 bits and its second halfword in the lower 16 bits, the
+//! same way the architecture reference manual writes them.  The instruction stream takes care of writing the two halfwords in order.
+//!
+//! The modified immediate constants and the split branch offsets are the error-prone parts of the encoding, so each of them has a decoder next to its encoder.
+
+/// Encodes `value` as a modified immediate constant, returning its `i:imm3:imm8` bits in place, or `None` if the value is not representable.
+///
+/// A modified immediate is either a byte, a byte repeated in a fixed pattern (`0x00XY00XY`, `0xXY00XY00` or `0xXYXYXYXY`), or an 8-bit value with its top
+/// bit set rotated right by 8 to 31 bits.
+///
+/// # Example
+/// ```
+/// use asmkit_arm::encoding::{decode_modified_immediate, modified_immediate};
+///
+/// assert_eq!(modified_immediate(0xff), Some(0x000000ff));
+/// assert_eq!(modified_immediate(0xff00ff00), Some(0x000020ff));
+/// assert_eq!(modified_immediate(0x0003fc00), Some(0x0400307f));
+/// assert_eq!(modified_immediate(0x80000000), Some(0x00004000));
+/// assert_eq!(modified_immediate(0x101), None);
+///
+/// for value in [0, 1, 0xab, 0x00ab00ab, 0xab00ab00, 0xabababab, 0x3fc00, 0x80000000, 0xff000000, 0x1fe] {
+///     assert_eq!(decode_modified_immediate(modified_immediate(value).unwrap()), value);
+/// }
+/// ```
+pub fn modified_immediate(value: u32) -> Option<u32> {
+    let [b0, b1, b2, b3] = value.to_le_bytes();
+    let imm12 = if value <= 0xff {
+        value
+    } else if b1 == 0 && b3 == 0 && b0 == b2 {
+        0x100 | b0 as u32
+    } else if b0 == 0 && b2 == 0 && b1 == b3 {
+        0x200 | b1 as u32
+    } else if b0 == b1 && b0 == b2 && b0 == b3 {
+        0x300 | b0 as u32
+    } else {
+        let rotation = (8..32).find(|rotation| (0x80..0x100).contains(&value.rotate_left(*rotation)))?;
+        (rotation << 7) | (value.rotate_left(rotation) & 0x7f)
+    };
+
+    Some(((imm12 >> 11) << 26) | (((imm12 >> 8) & 0x7) << 12) | (imm12 & 0xff))
+}
+
+/// Expands the modified immediate constant held in the `i:imm3:imm8` bits of an instruction.
+pub fn decode_modified_immediate(instruction: u32) -> u32 {
+    let imm12 = (((instruction >> 26) & 0x1) << 11) | (((instruction >> 12) & 0x7) << 8) | (instruction & 0xff);
+    let byte = imm12 & 0xff;
+
+    match imm12 >> 8 {
+        0b0000 => byte,
+        0b0001 => (byte << 16) | byte,
+        0b0010 => (byte << 24) | (byte << 8),
+        0b0011 => byte * 0x01010101,
+        _ => (0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7),
+    }
+}
+
+/// Splits a 16-bit immediate into the `imm4:i:imm3:imm8` fields of `movw` and `movt`.
+#[inline(always)]
+pub fn split_imm16(imm: u16) -> u32 {
+    let imm = imm as u32;
+    ((imm >> 12) << 16) | (((imm >> 11) & 0x1) << 26) | (((imm >> 8) & 0x7) << 12) | (imm & 0xff)
+}
+
+/// Splits a 12-bit immediate into the `i:imm3:imm8` fields of `addw` and `subw`.
+#[inline(always)]
+pub fn split_imm12(imm: u32) -> u32 {
+    (((imm >> 11) & 0x1) << 26) | (((imm >> 8) & 0x7) << 12) | (imm & 0xff)
+}
+
+/// Scatters a branch offset into the `imm8` field of a 16-bit conditional branch.
+#[inline(always)]
+pub fn cond_branch_imm(imm: i32) -> u16 {
+    ((imm >> 1) & 0xff) as u16
+}
+
+/// Scatters a branch offset into the `imm11` field of a 16-bit unconditional branch.
+#[inline(always)]
+pub fn branch_imm(imm: i32) -> u16 {
+    ((imm >> 1) & 0x7ff) as u16
+}
+
+/// Scatters a branch offset into the `S:imm6` and `J1:J2:imm11` fields of a 32-bit conditional branch.
+#[inline(always)]
+pub fn wide_cond_branch_imm(imm: i32) -> u32 {
+    let imm = imm as u32;
+    (((imm >> 20) & 0x1) << 26) | (((imm >> 12) & 0x3f) << 16) | (((imm >> 18) & 0x1) << 13) | (((imm >> 19) & 0x1) << 11) | ((imm >> 1) & 0x7ff)
+}
+
+/// Scatters a branch offset into the `S:imm10` and `J1:J2:imm11` fields of a 32-bit unconditional branch or a `bl`.
+///
+/// The `J1` and `J2` bits don't hold the offset bits directly: they are the inverted exclusive or of the sign bit with bits 23 and 22 of the offset, which
+/// keeps the encoding compatible with the older two-instruction `bl` pair.
+#[inline(always)]
+pub fn wide_branch_imm(imm: i32) -> u32 {
+    let imm = imm as u32;
+    let s = (imm >> 24) & 0x1;
+    let j1 = !(((imm >> 23) & 0x1) ^ s) & 0x1;
+    let j2 = !(((imm >> 22) & 0x1) ^ s) & 0x1;
+    (s << 26) | (((imm >> 12) & 0x3ff) << 16) | (j1 << 13) | (j2 << 11) | ((imm >> 1) & 0x7ff)
+}
+
+/// Extracts the sign-extended offset of a 32-bit conditional branch.
+///
+/// # Example
+/// ```
+/// use asmkit_arm::encoding::{decode_wide_cond_branch_imm, wide_cond_branch_imm};
+///
+/// // every representable branch offset survives a round trip, without disturbing the condition and opcode bits.
+/// for imm in (-(1 << 20)..(1 << 20)).step_by(2) {
+///     let instruction = 0xf0008000 | (0b0001 << 22) | wide_cond_branch_imm(imm);
+///
+///     assert_eq!(decode_wide_cond_branch_imm(instruction), imm);
+///     assert_eq!(instruction & 0xfbc0d000, 0xf0008000 | (0b0001 << 22));
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_wide_cond_branch_imm(instruction: u32) -> i32 {
+    let imm = (((instruction >> 26) & 0x1) << 20)
+        | (((instruction >> 11) & 0x1) << 19)
+        | (((instruction >> 13) & 0x1) << 18)
+        | (((instruction >> 16) & 0x3f) << 12)
+        | ((instruction & 0x7ff) << 1);
+    ((imm << 11) as i32) >> 11
+}
+
+/// Extracts the sign-extended offset of a 32-bit unconditional branch or a `bl`.
+///
+/// # Example
+/// ```
+/// use asmkit_arm::encoding::{decode_wide_branch_imm, wide_branch_imm};
+///
+/// for imm in (-(1 << 24)..(1 << 24)).step_by(254).chain([-(1 << 24), (1 << 24) - 2, -2, 0, 2]) {
+///     let instruction = 0xf000d000 | wide_branch_imm(imm);
+///
+///     assert_eq!(decode_wide_branch_imm(instruction), imm);
+///     assert_eq!(instruction & 0xf800d000, 0xf000d000);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_wide_branch_imm(instruction: u32) -> i32 {
+    let s = (instruction >> 26) & 0x1;
+    let i1 = !(((instruction >> 13) & 0x1) ^ s) & 0x1;
+    let i2 = !(((instruction >> 11) & 0x1) ^ s) & 0x1;
+    let imm = (s << 24) | (i1 << 23) | (i2 << 22) | (((instruction >> 16) & 0x3ff) << 12) | ((instruction & 0x7ff) << 1);
+    ((imm << 7) as i32) >> 7
+}
+
+/// Returns true if `imm` fits in a signed immediate of `bits` bits.
+#[inline(always)]
+pub fn fits_signed(imm: i64, bits: u32) -> bool {
+    let min = -(1i64 << (bits - 1));
+    let max = (1i64 << (bits - 1)) - 1;
+    imm >= min && imm <= max
+}
@@ -0,0 +1,56 @@
+//! Registers in ARM assembly.
+
+/// A general purpose register.
+///
+/// The registers are named by their number; the special-purpose registers are available through constructors such as [`Reg::sp`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reg {
+    R0,
+    R1,
+    R2,
+    R3,
+    R4,
+    R5,
+    R6,
+    R7,
+    R8,
+    R9,
+    R10,
+    R11,
+    R12,
+    R13,
+    R14,
+    R15,
+}
+
+impl Reg {
+    /// The offset of this register, for instruction encoding.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::register::Reg;
+    ///
+    /// assert_eq!(Reg::R3.offset(), 3);
+    /// assert_eq!(Reg::lr().offset(), 14);
+    /// ```
+    pub fn offset(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Returns true if this register is one of `r0` to `r7`, the only registers addressable by the 3-bit register fields of most 16-bit Thumb instructions.
+    pub fn is_low(&self) -> bool {
+        self.offset() < 8
+    }
+
+    /// Intra-procedure-call scratch register.
+    pub const fn ip() -> Self { Self::R12 }
+
+    /// Stack pointer.
+    pub const fn sp() -> Self { Self::R13 }
+
+    /// Link register.
+    pub const fn lr() -> Self { Self::R14 }
+
+    /// Program counter.
+    pub const fn pc() -> Self { Self::R15 }
+}
@@ -0,0 +1,801 @@
+//! Instruction streaming implementation.
+
+use asmkit_core::{
+    displacement::{DisplacementBias, DisplacementForm, DisplacementUnit},
+    entity::{EntityList, Label, LabelRef},
+    labels::LabelTable,
+    raw::Endianness,
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+use crate::{encoding::{branch_imm, cond_branch_imm, fits_signed, modified_immediate, split_imm12, split_imm16, wide_branch_imm, wide_cond_branch_imm}, register::Reg};
+
+/// A condition code, deciding whether a conditional instruction executes based on the condition flags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    /// Equal, `Z` set.
+    Eq,
+
+    /// Not equal, `Z` clear.
+    Ne,
+
+    /// Unsigned higher or same, `C` set.  Also known as `cs`.
+    Hs,
+
+    /// Unsigned lower, `C` clear.  Also known as `cc`.
+    Lo,
+
+    /// Negative, `N` set.
+    Mi,
+
+    /// Positive or zero, `N` clear.
+    Pl,
+
+    /// Overflow, `V` set.
+    Vs,
+
+    /// No overflow, `V` clear.
+    Vc,
+
+    /// Unsigned higher.
+    Hi,
+
+    /// Unsigned lower or same.
+    Ls,
+
+    /// Signed greater than or equal.
+    Ge,
+
+    /// Signed less than.
+    Lt,
+
+    /// Signed greater than.
+    Gt,
+
+    /// Signed less than or equal.
+    Le,
+
+    /// Always.
+    Al,
+}
+
+impl Condition {
+    /// The 4-bit encoding of this condition code.
+    pub fn bits(&self) -> u32 {
+        *self as u32
+    }
+
+    /// The condition code which holds exactly when this one doesn't.
+    ///
+    /// # Panics
+    /// Panics if this condition is [`Condition::Al`], which has no inverse.
+    pub fn invert(&self) -> Self {
+        match self {
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::Hs => Self::Lo,
+            Self::Lo => Self::Hs,
+            Self::Mi => Self::Pl,
+            Self::Pl => Self::Mi,
+            Self::Vs => Self::Vc,
+            Self::Vc => Self::Vs,
+            Self::Hi => Self::Ls,
+            Self::Ls => Self::Hi,
+            Self::Ge => Self::Lt,
+            Self::Lt => Self::Ge,
+            Self::Gt => Self::Le,
+            Self::Le => Self::Gt,
+            Self::Al => panic!("the `al` condition has no inverse"),
+        }
+    }
+}
+
+/// The kind of a label fixup, deciding how the displacement is scattered into the instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixupKind {
+    /// A 9-bit displacement of a 16-bit conditional branch.
+    CondBranch,
+
+    /// A 12-bit displacement of a 16-bit unconditional branch.
+    Branch,
+
+    /// A 21-bit displacement of a 32-bit conditional branch.
+    WideCondBranch,
+
+    /// A 25-bit displacement of a 32-bit unconditional branch or a `bl`.
+    WideBranch,
+}
+
+impl FixupKind {
+    /// How the displacement of this kind is measured: in halfwords, from the Thumb PC, which reads as the address of the instruction plus 4.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::stream::FixupKind;
+    ///
+    /// assert_eq!(FixupKind::CondBranch.form().resolve(0, 4 + 254), Ok(127));
+    /// assert!(FixupKind::CondBranch.form().resolve(0, 4 + 256).is_err());
+    /// assert_eq!(FixupKind::WideBranch.form().resolve(0x100, 0), Ok(-0x82));
+    /// ```
+    pub fn form(&self) -> DisplacementForm {
+        let bits = match self {
+            Self::CondBranch => 8,
+            Self::Branch => 11,
+            Self::WideCondBranch => 20,
+            Self::WideBranch => 24,
+        };
+
+        DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::End(4), bits)
+    }
+}
+
+/// A use of a label, patched once the label is attached, or once the instruction stream is finished if the label was attached first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fixup {
+    /// The offset of the instruction which uses the label.
+    pub offset: usize,
+
+    /// The label used by the instruction.
+    pub label: LabelRef,
+
+    /// How the displacement is encoded into the instruction.
+    pub kind: FixupKind,
+}
+
+/// Computes the register mask of a register list.
+fn register_mask(registers: &[Reg]) -> u32 {
+    assert!(!registers.is_empty(), "register list is empty");
+    registers.iter().fold(0, |mask, register| mask | (1 << register.offset()))
+}
+
+/// An instruction stream for the Thumb-2 instruction set, as run by the Cortex-M microcontrollers.
+///
+/// Instructions are halfword aligned and either 16 or 32 bits wide.  Wherever gas would pick the 16-bit form of an instruction, the emitters do as well.
+///
+/// A branch to Thumb code through a register (see [`ThumbInstructionStream::bx`]) switches instruction sets based on the lowest bit of the target address,
+/// which must be set to stay in the Thumb state; the address of the finished code should be given this interworking bit before it is called.
+pub struct ThumbInstructionStream {
+    /// The bytes written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream, and their pending uses.
+    labels: LabelTable<Fixup>,
+
+    /// The holes of the templates written to the instruction stream.
+    holes: TemplateHoles,
+}
+
+impl InstructionStream for ThumbInstructionStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.create()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.create_attached(self.bytes.len())
+    }
+
+    /// Attaches `label` to the current offset, patching the instructions which used it before.
+    ///
+    /// # Panics
+    /// Panics if the displacement from one of these instructions doesn't fit it.
+    fn attach_label(&mut self, label: LabelRef) {
+        let mut pending = self.labels.attach(label, self.bytes.len());
+        while let Some(fixup) = pending.next(&self.labels) {
+            self.resolve(fixup);
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, filled when the stream is finished.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol, which the stream has none of.
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push_label(fixup);
+    }
+
+    /// Finishes writing to the instruction stream, resolving the label uses recorded after their label was attached, and returns the produced
+    /// bytes.
+    ///
+    /// # Panics
+    /// Panics if a used label was never attached, or if its displacement doesn't fit the instruction which uses it.
+    fn finish(mut self) -> Product {
+        for label in self.labels.refs() {
+            let mut pending = self.labels.take_pending(label);
+            while let Some(fixup) = pending.next(&self.labels) {
+                self.resolve(fixup);
+            }
+        }
+
+        let relocations = self.holes.resolve(&mut self.bytes, Endianness::Little, |label| self.labels.offset(label)).unwrap_or_else(|error| panic!("{}", error));
+        Product::with_relocations(self.bytes, relocations, EntityList::new())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
+
+impl Default for ThumbInstructionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThumbInstructionStream {
+    /// Creates a new, empty instruction stream.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), labels: LabelTable::new(), holes: TemplateHoles::new() }
+    }
+
+    /// Writes a single 16-bit instruction into the instruction stream.
+    #[inline(always)]
+    pub fn write_instruction(&mut self, instruction: u16) {
+        self.write_word(instruction);
+    }
+
+    /// Writes a single 32-bit instruction into the instruction stream, its first halfword being held in the upper 16 bits of `instruction`.
+    #[inline(always)]
+    pub fn write_wide_instruction(&mut self, instruction: u32) {
+        self.write_word((instruction >> 16) as u16);
+        self.write_word(instruction as u16);
+    }
+
+    /// Returns the offset of `label`, if it is attached.
+    fn label_offset(&self, label: LabelRef) -> Option<usize> {
+        self.labels.offset(label)
+    }
+
+    /// Patches the displacement of `fixup` to its attached label into the instruction using it.
+    ///
+    /// # Panics
+    /// Panics if the label isn't attached, or if its displacement doesn't fit the instruction.
+    fn resolve(&mut self, fixup: Fixup) {
+        let target = match self.labels.get(fixup.label).state {
+            Label::Attached(target) => target,
+            Label::Unattached => panic!("label {:?} is used but never attached", fixup.label),
+            Label::Absolute(_) => unreachable!("labels are only bound to absolute addresses by the x86-64 stream"),
+        };
+
+        // the encoders take the displacement in bytes.
+        let halfwords = fixup.kind.form().resolve(fixup.offset, target);
+        let displacement = halfwords.unwrap_or_else(|error| panic!("{} to label {:?}", error, fixup.label)) * 2;
+
+        match fixup.kind {
+            FixupKind::CondBranch => self.patch_instruction(fixup.offset, cond_branch_imm(displacement as i32)),
+            FixupKind::Branch => self.patch_instruction(fixup.offset, branch_imm(displacement as i32)),
+            FixupKind::WideCondBranch => self.patch_wide_instruction(fixup.offset, wide_cond_branch_imm(displacement as i32)),
+            FixupKind::WideBranch => self.patch_wide_instruction(fixup.offset, wide_branch_imm(displacement as i32)),
+        }
+    }
+
+    /// Returns true if `label` is already attached and its displacement from the instruction about to be written fits in a field of `kind`.
+    fn label_in_range(&self, label: LabelRef, kind: FixupKind) -> bool {
+        self.label_offset(label).is_some_and(|target| kind.form().resolve(self.bytes.len(), target).is_ok())
+    }
+
+    /// Merges `bits` into the 16-bit instruction at the provided offset.
+    fn patch_instruction(&mut self, offset: usize, bits: u16) {
+        let slot = &mut self.bytes[offset..offset + 2];
+        let instruction = u16::from_le_bytes([slot[0], slot[1]]) | bits;
+        slot.copy_from_slice(&instruction.to_le_bytes());
+    }
+
+    /// Merges `bits` into the 32-bit instruction at the provided offset.
+    fn patch_wide_instruction(&mut self, offset: usize, bits: u32) {
+        self.patch_instruction(offset, (bits >> 16) as u16);
+        self.patch_instruction(offset + 2, bits as u16);
+    }
+
+    /// Records a use of `label` by the instruction which is about to be written.
+    fn use_label(&mut self, label: LabelRef, kind: FixupKind) {
+        self.labels.use_label(label, Fixup { offset: self.bytes.len(), label, kind });
+    }
+
+    /// Writes an instruction taking a modified immediate constant.
+    ///
+    /// # Panics
+    /// Panics if `imm` is not representable as a modified immediate constant.
+    fn write_modified_immediate(&mut self, opcode: u32, rn: u32, rd: u32, imm: u32) {
+        let bits = modified_immediate(imm).unwrap_or_else(|| panic!("immediate {:#x} is not encodable as a modified immediate", imm));
+        self.write_wide_instruction(opcode | (rn << 16) | (rd << 8) | bits);
+    }
+
+    /// No operation.
+    pub fn nop(&mut self) {
+        self.write_instruction(0xbf00);
+    }
+
+    /// Move register: *rd* = *rm*.  Doesn't affect the condition flags.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::ThumbInstructionStream};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.mov(Reg::R0, Reg::R8); // mov r0, r8
+    /// stream.movs(Reg::R3, 200); // movs r3, #200
+    /// stream.mov_imm(Reg::R1, 0xff00ff00); // mov.w r1, #0xff00ff00
+    /// stream.mov_imm(Reg::R3, 0x3fc00); // mov.w r3, #0x3fc00
+    /// stream.movw(Reg::R4, 0xbeef); // movw r4, #0xbeef
+    /// stream.movt(Reg::R4, 0xdead); // movt r4, #0xdead
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x40, 0x46,
+    ///     0xc8, 0x23,
+    ///     0x4f, 0xf0, 0xff, 0x21,
+    ///     0x4f, 0xf4, 0x7f, 0x33,
+    ///     0x4b, 0xf6, 0xef, 0x64,
+    ///     0xcd, 0xf6, 0xad, 0x64,
+    /// ]);
+    /// ```
+    pub fn mov(&mut self, rd: Reg, rm: Reg) {
+        let rd = rd.offset();
+        self.write_instruction((0x4600 | ((rd >> 3) << 7) | (rm.offset() << 3) | (rd & 0x7)) as u16);
+    }
+
+    /// Move immediate, setting the condition flags: *rd* = *imm8*.
+    ///
+    /// # Panics
+    /// Panics if `rd` is not one of `r0` to `r7`.
+    pub fn movs(&mut self, rd: Reg, imm8: u8) {
+        assert!(rd.is_low(), "movs requires a low register");
+        self.write_instruction((0x2000 | (rd.offset() << 8) | imm8 as u32) as u16);
+    }
+
+    /// Move immediate: *rd* = *imm*.  Doesn't affect the condition flags.
+    ///
+    /// Uses a modified immediate constant when `imm` is representable as one, and a `movw` otherwise.
+    ///
+    /// # Panics
+    /// Panics if `imm` is neither a modified immediate constant nor a 16-bit value, see [`ThumbInstructionStream::movt`] for loading arbitrary constants.
+    pub fn mov_imm(&mut self, rd: Reg, imm: u32) {
+        if modified_immediate(imm).is_none() && imm <= 0xffff {
+            return self.movw(rd, imm as u16);
+        }
+
+        self.write_modified_immediate(0xf04f0000, 0, rd.offset(), imm);
+    }
+
+    /// Move wide: *rd* = *imm16*, clearing the upper halfword.
+    pub fn movw(&mut self, rd: Reg, imm16: u16) {
+        self.write_wide_instruction(0xf2400000 | (rd.offset() << 8) | split_imm16(imm16));
+    }
+
+    /// Move top: writes *imm16* to the upper halfword of *rd*, keeping its lower halfword.  Loads any 32-bit constant when paired with a `movw`.
+    pub fn movt(&mut self, rd: Reg, imm16: u16) {
+        self.write_wide_instruction(0xf2c00000 | (rd.offset() << 8) | split_imm16(imm16));
+    }
+
+    /// Add register: *rd* = *rn* + *rm*.  Doesn't affect the condition flags.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::ThumbInstructionStream};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.add(Reg::R8, Reg::R8, Reg::R9); // add r8, r9
+    /// stream.add(Reg::R0, Reg::R1, Reg::R2); // add.w r0, r1, r2
+    /// stream.sub(Reg::R0, Reg::R1, Reg::R2); // sub.w r0, r1, r2
+    /// stream.add_imm(Reg::R0, Reg::R1, 0x10000); // add.w r0, r1, #0x10000
+    /// stream.add_imm(Reg::R0, Reg::R1, 4095); // addw r0, r1, #4095
+    /// stream.sub_imm(Reg::R2, Reg::R3, 1); // sub.w r2, r3, #1
+    /// stream.sub_imm(Reg::sp(), Reg::sp(), 16); // sub sp, #16
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc8, 0x44,
+    ///     0x01, 0xeb, 0x02, 0x00,
+    ///     0xa1, 0xeb, 0x02, 0x00,
+    ///     0x01, 0xf5, 0x80, 0x30,
+    ///     0x01, 0xf6, 0xff, 0x70,
+    ///     0xa3, 0xf1, 0x01, 0x02,
+    ///     0x84, 0xb0,
+    /// ]);
+    /// ```
+    pub fn add(&mut self, rd: Reg, rn: Reg, rm: Reg) {
+        if rd == rn && !(rd == Reg::pc() && rm == Reg::pc()) {
+            let rd = rd.offset();
+            return self.write_instruction((0x4400 | ((rd >> 3) << 7) | (rm.offset() << 3) | (rd & 0x7)) as u16);
+        }
+
+        self.write_wide_instruction(0xeb000000 | (rn.offset() << 16) | (rd.offset() << 8) | rm.offset());
+    }
+
+    /// Subtract register: *rd* = *rn* - *rm*.  Doesn't affect the condition flags.
+    pub fn sub(&mut self, rd: Reg, rn: Reg, rm: Reg) {
+        self.write_wide_instruction(0xeba00000 | (rn.offset() << 16) | (rd.offset() << 8) | rm.offset());
+    }
+
+    /// Add immediate: *rd* = *rn* + *imm*.  Doesn't affect the condition flags.
+    ///
+    /// # Panics
+    /// Panics if `imm` is neither a modified immediate constant nor a 12-bit value.
+    pub fn add_imm(&mut self, rd: Reg, rn: Reg, imm: u32) {
+        self.write_add_sub_imm(0xb000, 0xf1000000, 0xf2000000, rd, rn, imm);
+    }
+
+    /// Subtract immediate: *rd* = *rn* - *imm*.  Doesn't affect the condition flags.
+    ///
+    /// # Panics
+    /// Panics if `imm` is neither a modified immediate constant nor a 12-bit value.
+    pub fn sub_imm(&mut self, rd: Reg, rn: Reg, imm: u32) {
+        self.write_add_sub_imm(0xb080, 0xf1a00000, 0xf2a00000, rd, rn, imm);
+    }
+
+    /// Writes an immediate addition or subtraction, picking the 16-bit stack pointer adjustment, the modified immediate form or the plain 12-bit form.
+    fn write_add_sub_imm(&mut self, sp_opcode: u16, modified_opcode: u32, plain_opcode: u32, rd: Reg, rn: Reg, imm: u32) {
+        if rd == Reg::sp() && rn == Reg::sp() && imm.is_multiple_of(4) && imm < 512 {
+            return self.write_instruction(sp_opcode | (imm >> 2) as u16);
+        }
+
+        if modified_immediate(imm).is_none() && imm < 4096 {
+            return self.write_wide_instruction(plain_opcode | (rn.offset() << 16) | (rd.offset() << 8) | split_imm12(imm));
+        }
+
+        self.write_modified_immediate(modified_opcode, rn.offset(), rd.offset(), imm);
+    }
+
+    /// Compare register: sets the condition flags on *rn* - *rm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::ThumbInstructionStream};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.cmp(Reg::R0, Reg::R1); // cmp r0, r1
+    /// stream.cmp(Reg::R1, Reg::R8); // cmp r1, r8
+    /// stream.cmp_imm(Reg::R0, 255); // cmp r0, #255
+    /// stream.cmp_imm(Reg::R8, 1); // cmp.w r8, #1
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x88, 0x42, 0x41, 0x45, 0xff, 0x28, 0xb8, 0xf1, 0x01, 0x0f]);
+    /// ```
+    pub fn cmp(&mut self, rn: Reg, rm: Reg) {
+        if rn.is_low() && rm.is_low() {
+            return self.write_instruction((0x4280 | (rm.offset() << 3) | rn.offset()) as u16);
+        }
+
+        let rn = rn.offset();
+        self.write_instruction((0x4500 | ((rn >> 3) << 7) | (rm.offset() << 3) | (rn & 0x7)) as u16);
+    }
+
+    /// Compare immediate: sets the condition flags on *rn* - *imm*.
+    ///
+    /// # Panics
+    /// Panics if `imm` is not representable as a modified immediate constant.
+    pub fn cmp_imm(&mut self, rn: Reg, imm: u32) {
+        if rn.is_low() && imm < 256 {
+            return self.write_instruction((0x2800 | (rn.offset() << 8) | imm) as u16);
+        }
+
+        self.write_modified_immediate(0xf1b00f00, rn.offset(), 0, imm);
+    }
+
+    /// Writes a load or store with an immediate offset, picking the 16-bit forms when the operands qualify.
+    ///
+    /// # Panics
+    /// Panics if `base` is the program counter, or if `offset` is not within -255 to 4095.
+    fn write_load_store(&mut self, opcode16: u16, sp_opcode16: u16, opcode32: u32, rt: Reg, base: Reg, offset: i32) {
+        assert!(base != Reg::pc(), "pc-relative loads and stores are not supported");
+
+        let aligned = offset % 4 == 0 && offset >= 0;
+        if rt.is_low() && base.is_low() && aligned && offset < 128 {
+            return self.write_instruction(opcode16 | ((offset as u16 >> 2) << 6) | ((base.offset() as u16) << 3) | rt.offset() as u16);
+        }
+
+        if rt.is_low() && base == Reg::sp() && aligned && offset < 1024 {
+            return self.write_instruction(sp_opcode16 | ((rt.offset() as u16) << 8) | (offset as u16 >> 2));
+        }
+
+        let fields = (base.offset() << 16) | (rt.offset() << 12);
+        match offset {
+            0..=4095 => self.write_wide_instruction(opcode32 | (1 << 23) | fields | offset as u32),
+            -255..=-1 => self.write_wide_instruction(opcode32 | 0xc00 | fields | (-offset) as u32),
+            _ => panic!("offset {} is not within -255 to 4095", offset),
+        }
+    }
+
+    /// Load register: *rt* = *\[base + offset\]*.
+    ///
+    /// # Panics
+    /// Panics if `base` is the program counter, or if `offset` is not within -255 to 4095.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::ThumbInstructionStream};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.ldr(Reg::R0, Reg::R1, 124); // ldr r0, [r1, #124]
+    /// stream.ldr(Reg::R2, Reg::sp(), 1020); // ldr r2, [sp, #1020]
+    /// stream.ldr(Reg::R8, Reg::R1, 4); // ldr.w r8, [r1, #4]
+    /// stream.ldr(Reg::R0, Reg::R1, -4); // ldr r0, [r1, #-4]
+    /// stream.str(Reg::R0, Reg::sp(), 8); // str r0, [sp, #8]
+    /// stream.str(Reg::R9, Reg::R1, 4); // str.w r9, [r1, #4]
+    /// stream.str(Reg::R0, Reg::R1, -255); // str r0, [r1, #-255]
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc8, 0x6f,
+    ///     0xff, 0x9a,
+    ///     0xd1, 0xf8, 0x04, 0x80,
+    ///     0x51, 0xf8, 0x04, 0x0c,
+    ///     0x02, 0x90,
+    ///     0xc1, 0xf8, 0x04, 0x90,
+    ///     0x41, 0xf8, 0xff, 0x0c,
+    /// ]);
+    /// ```
+    pub fn ldr(&mut self, rt: Reg, base: Reg, offset: i32) {
+        self.write_load_store(0x6800, 0x9800, 0xf8500000, rt, base, offset);
+    }
+
+    /// Store register: *\[base + offset\]* = *rt*.
+    ///
+    /// # Panics
+    /// Panics if `base` is the program counter, or if `offset` is not within -255 to 4095.
+    pub fn str(&mut self, rt: Reg, base: Reg, offset: i32) {
+        self.write_load_store(0x6000, 0x9000, 0xf8400000, rt, base, offset);
+    }
+
+    /// Pushes a list of registers onto the stack, the lowest-numbered register ending up at the lowest address.
+    ///
+    /// # Panics
+    /// Panics if the list is empty, or if it contains the stack pointer or the program counter.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::ThumbInstructionStream};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.push(&[Reg::R4, Reg::R5, Reg::R7, Reg::lr()]); // push {r4, r5, r7, lr}
+    /// stream.push(&[Reg::R4, Reg::R8, Reg::lr()]); // push.w {r4, r8, lr}
+    /// stream.push(&[Reg::R8]); // str r8, [sp, #-4]!
+    /// stream.pop(&[Reg::R8]); // ldr r8, [sp], #4
+    /// stream.pop(&[Reg::R4, Reg::R8, Reg::pc()]); // pop.w {r4, r8, pc}
+    /// stream.pop(&[Reg::R4, Reg::R5, Reg::R7, Reg::pc()]); // pop {r4, r5, r7, pc}
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xb0, 0xb5,
+    ///     0x2d, 0xe9, 0x10, 0x41,
+    ///     0x4d, 0xf8, 0x04, 0x8d,
+    ///     0x5d, 0xf8, 0x04, 0x8b,
+    ///     0xbd, 0xe8, 0x10, 0x81,
+    ///     0xb0, 0xbd,
+    /// ]);
+    /// ```
+    pub fn push(&mut self, registers: &[Reg]) {
+        let mask = register_mask(registers);
+        assert!(mask & 0xa000 == 0, "push can not store sp or pc");
+
+        if mask & !0x40ff == 0 {
+            return self.write_instruction((0xb400 | ((mask >> 14) << 8) | (mask & 0xff)) as u16);
+        }
+
+        // a single high register is stored with a pre-indexed `str`, as `stmdb` requires at least two registers.
+        match mask.count_ones() {
+            1 => self.write_wide_instruction(0xf84d0d04 | (mask.trailing_zeros() << 12)),
+            _ => self.write_wide_instruction(0xe92d0000 | mask),
+        }
+    }
+
+    /// Pops a list of registers off the stack.  Popping into the program counter returns, interworking on the lowest bit of the popped address.
+    ///
+    /// # Panics
+    /// Panics if the list is empty, if it contains the stack pointer, or if it contains both the link register and the program counter.
+    pub fn pop(&mut self, registers: &[Reg]) {
+        let mask = register_mask(registers);
+        assert!(mask & 0x2000 == 0, "pop can not load sp");
+        assert!(mask & 0xc000 != 0xc000, "pop can not load both lr and pc");
+
+        if mask & !0x80ff == 0 {
+            return self.write_instruction((0xbc00 | ((mask >> 15) << 8) | (mask & 0xff)) as u16);
+        }
+
+        match mask.count_ones() {
+            1 => self.write_wide_instruction(0xf85d0b04 | (mask.trailing_zeros() << 12)),
+            _ => self.write_wide_instruction(0xe8bd0000 | mask),
+        }
+    }
+
+    /// Branch to the Thumb PC + *offset*, the Thumb PC being the address of the branch plus 4.
+    ///
+    /// # Panics
+    /// Panics if `offset` is odd or doesn't fit in 25 bits.
+    pub fn b(&mut self, offset: i32) {
+        assert!(offset % 2 == 0, "branch offset {} is not halfword aligned", offset);
+
+        if fits_signed(offset as i64, 12) {
+            return self.write_instruction(0xe000 | branch_imm(offset));
+        }
+
+        assert!(fits_signed(offset as i64, 25), "branch offset {} does not fit in 25 bits", offset);
+        self.write_wide_instruction(0xf0009000 | wide_branch_imm(offset));
+    }
+
+    /// Branch to the Thumb PC + *offset* if `condition` holds.
+    ///
+    /// # Panics
+    /// Panics if `offset` is odd or doesn't fit in 21 bits.
+    pub fn b_cond(&mut self, condition: Condition, offset: i32) {
+        if condition == Condition::Al {
+            return self.b(offset);
+        }
+
+        assert!(offset % 2 == 0, "branch offset {} is not halfword aligned", offset);
+
+        if fits_signed(offset as i64, 9) {
+            return self.write_instruction((0xd000 | (condition.bits() << 8)) as u16 | cond_branch_imm(offset));
+        }
+
+        assert!(fits_signed(offset as i64, 21), "branch offset {} does not fit in 21 bits", offset);
+        self.write_wide_instruction(0xf0008000 | (condition.bits() << 22) | wide_cond_branch_imm(offset));
+    }
+
+    /// Branch with link to the Thumb PC + *offset*, storing the return address (with the interworking bit set) in `lr`.
+    ///
+    /// # Panics
+    /// Panics if `offset` is odd or doesn't fit in 25 bits.
+    pub fn bl(&mut self, offset: i32) {
+        assert!(offset % 2 == 0, "branch offset {} is not halfword aligned", offset);
+        assert!(fits_signed(offset as i64, 25), "branch offset {} does not fit in 25 bits", offset);
+        self.write_wide_instruction(0xf000d000 | wide_branch_imm(offset));
+    }
+
+    /// Branch to a label.
+    ///
+    /// The 16-bit form is only used when the label is already attached and within its range, so forward branches always take 32 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::{Condition, ThumbInstructionStream}};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// let top = stream.create_label_attached();
+    /// let end = stream.create_label();
+    ///
+    /// stream.nop(); // top: nop
+    /// stream.b_label(top); // b top
+    /// stream.b_cond_label(Condition::Eq, top); // beq top
+    /// stream.bl_label(top); // bl top
+    /// stream.b_label(end); // b.w end
+    /// stream.b_cond_label(Condition::Gt, end); // bgt.w end
+    /// stream.bl_label(end); // bl end
+    /// stream.attach_label(end);
+    /// stream.bx(Reg::lr()); // end: bx lr
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x00, 0xbf,
+    ///     0xfd, 0xe7,
+    ///     0xfc, 0xd0,
+    ///     0xff, 0xf7, 0xfb, 0xff,
+    ///     0x00, 0xf0, 0x04, 0xb8,
+    ///     0x00, 0xf3, 0x02, 0x80,
+    ///     0x00, 0xf0, 0x00, 0xf8,
+    ///     0x70, 0x47,
+    /// ]);
+    /// ```
+    pub fn b_label(&mut self, label: LabelRef) {
+        if self.label_in_range(label, FixupKind::Branch) {
+            self.use_label(label, FixupKind::Branch);
+            return self.write_instruction(0xe000);
+        }
+
+        self.use_label(label, FixupKind::WideBranch);
+        self.write_wide_instruction(0xf0009000);
+    }
+
+    /// Branch to a label if `condition` holds.
+    pub fn b_cond_label(&mut self, condition: Condition, label: LabelRef) {
+        if condition == Condition::Al {
+            return self.b_label(label);
+        }
+
+        if self.label_in_range(label, FixupKind::CondBranch) {
+            self.use_label(label, FixupKind::CondBranch);
+            return self.write_instruction((0xd000 | (condition.bits() << 8)) as u16);
+        }
+
+        self.use_label(label, FixupKind::WideCondBranch);
+        self.write_wide_instruction(0xf0008000 | (condition.bits() << 22));
+    }
+
+    /// Branch with link to a label.
+    pub fn bl_label(&mut self, label: LabelRef) {
+        self.use_label(label, FixupKind::WideBranch);
+        self.write_wide_instruction(0xf000d000);
+    }
+
+    /// Branch and exchange: jumps to the address in *rm*, switching to the Thumb state if its lowest bit is set, or to the ARM state otherwise.  `bx lr`
+    /// returns from a subroutine.
+    ///
+    /// Cortex-M processors only implement the Thumb state, so the lowest bit of the target address must always be set.
+    pub fn bx(&mut self, rm: Reg) {
+        self.write_instruction((0x4700 | (rm.offset() << 3)) as u16);
+    }
+
+    /// Branch with link and exchange: calls the address in *rm*, interworking like [`ThumbInstructionStream::bx`].
+    pub fn blx(&mut self, rm: Reg) {
+        self.write_instruction((0x4780 | (rm.offset() << 3)) as u16);
+    }
+
+    /// If-then: makes up to four following instructions conditional.  The first instruction executes if `condition` holds, and each character of
+    /// `then_else` adds an instruction executing if `condition` holds (`t`) or doesn't (`e`).
+    ///
+    /// The emitters don't track the block, so the instructions inside of it must be the ones which are valid within an IT block.
+    ///
+    /// # Panics
+    /// Panics if `then_else` holds more than three characters or any character other than `t` and `e`, or if an `e` is used with [`Condition::Al`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_arm::{register::Reg, stream::{Condition, ThumbInstructionStream}};
+    /// use asmkit_core::InstructionStream;
+    ///
+    /// let mut stream = ThumbInstructionStream::new();
+    /// stream.it(Condition::Eq, ""); // it eq
+    /// stream.it(Condition::Gt, "te"); // itte gt
+    /// stream.it(Condition::Ne, "e"); // ite ne
+    /// stream.it(Condition::Lt, "ete"); // itete lt
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x08, 0xbf, 0xc6, 0xbf, 0x14, 0xbf, 0xb5, 0xbf]);
+    /// ```
+    pub fn it(&mut self, condition: Condition, then_else: &str) {
+        assert!(then_else.len() <= 3, "an IT block holds at most four instructions");
+
+        let low_bit = condition.bits() & 0x1;
+        let mut mask = 0;
+        for (index, character) in then_else.chars().enumerate() {
+            let bit = match character {
+                't' => low_bit,
+                'e' => {
+                    assert!(condition != Condition::Al, "the `al` condition can not have an else branch");
+                    low_bit ^ 1
+                },
+                _ => panic!("unexpected character {:?} in IT block pattern, expected `t` or `e`", character),
+            };
+
+            mask |= bit << (3 - index);
+        }
+
+        mask |= 1 << (3 - then_else.len());
+        self.write_instruction((0xbf00 | (condition.bits() << 4) | mask) as u16);
+    }
+}
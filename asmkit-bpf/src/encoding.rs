@@ -0,0 +1,53 @@
+//! The instruction format of eBPF.
+//!
+//! Every instruction is a 64-bit slot: an 8-bit opcode, the 4-bit destination and source registers, a 16-bit signed offset and a 32-bit signed
+//! immediate, little endian.  The opcode is made of the class of the instruction in its low 3 bits, and of fields depending on the class above
+//! them: the operation and the source of the ALU and jump classes, or the size and the mode of the loads and stores.  `ld_imm64` is the only
+//! instruction taking two slots, the second holding the upper half of its immediate.
+
+/// The class of the 64-bit immediate load, `ld_imm64`.
+pub const CLASS_LD: u8 = 0x00;
+
+/// The class of the loads from memory into a register.
+pub const CLASS_LDX: u8 = 0x01;
+
+/// The class of the stores of an immediate to memory.
+pub const CLASS_ST: u8 = 0x02;
+
+/// The class of the stores of a register to memory.
+pub const CLASS_STX: u8 = 0x03;
+
+/// The class of the 32-bit arithmetic instructions.
+pub const CLASS_ALU: u8 = 0x04;
+
+/// The class of the jumps comparing 64-bit operands, and of `call` and `exit`.
+pub const CLASS_JMP: u8 = 0x05;
+
+/// The class of the 64-bit arithmetic instructions.
+pub const CLASS_ALU64: u8 = 0x07;
+
+/// The source of the ALU and jump classes taking their operand from the immediate.
+pub const SOURCE_K: u8 = 0x00;
+
+/// The source of the ALU and jump classes taking their operand from the source register.
+pub const SOURCE_X: u8 = 0x08;
+
+/// The mode of `ld_imm64`.
+pub const MODE_IMM: u8 = 0x00;
+
+/// The mode of the regular loads and stores.
+pub const MODE_MEM: u8 = 0x60;
+
+/// Encodes an instruction slot.
+///
+/// # Example
+/// ```
+/// use asmkit_bpf::encoding::{encode, CLASS_ALU64, SOURCE_X};
+///
+/// // r1 += r2
+/// assert_eq!(encode(CLASS_ALU64 | SOURCE_X, 1, 2, 0, 0).to_le_bytes(), [0x0f, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+/// ```
+#[inline(always)]
+pub fn encode(opcode: u8, dst: u8, src: u8, off: i16, imm: i32) -> u64 {
+    (opcode as u64) | ((dst as u64 & 0xf) << 8) | ((src as u64 & 0xf) << 12) | ((off as u16 as u64) << 16) | ((imm as u32 as u64) << 32)
+}
@@ -0,0 +1,3 @@
+pub mod encoding;
+pub mod stream;
+pub mod register;
\ No newline at end of file
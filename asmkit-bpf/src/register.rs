@@ -0,0 +1,53 @@
+//! Registers in eBPF assembly.
+
+/// A 64-bit register of the eBPF virtual machine.
+///
+/// The instructions of the 32-bit ALU class operate on the low 32 bits of the provided register, zeroing its upper half.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BpfReg {
+    /// The return value of the helpers and of the program.
+    R0,
+
+    /// The first argument of the helpers, and the context of the program on entry.
+    R1,
+    R2,
+    R3,
+    R4,
+    R5,
+
+    /// Preserved across the calls to the helpers.
+    R6,
+    R7,
+    R8,
+    R9,
+
+    /// The read-only frame pointer.
+    R10,
+}
+
+impl BpfReg {
+    /// The offset of this register, for instruction encoding.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_bpf::register::BpfReg;
+    ///
+    /// assert_eq!(BpfReg::R0.offset(), 0);
+    /// assert_eq!(BpfReg::R10.offset(), 10);
+    /// ```
+    pub fn offset(&self) -> u8 {
+        match self {
+            Self::R0 => 0,
+            Self::R1 => 1,
+            Self::R2 => 2,
+            Self::R3 => 3,
+            Self::R4 => 4,
+            Self::R5 => 5,
+            Self::R6 => 6,
+            Self::R7 => 7,
+            Self::R8 => 8,
+            Self::R9 => 9,
+            Self::R10 => 10,
+        }
+    }
+}
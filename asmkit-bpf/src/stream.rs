@@ -0,0 +1,553 @@
+//! Instruction streaming implementation.
+//!
+//! The jumps of eBPF are measured in instructions rather than bytes: the offset of a jump counts the 8-byte slots from the instruction following
+//! it to its target, and `ld_imm64` counts as two.  The label uses are recorded as [`Fixup`]s, resolved into slots when the stream is finished.
+//!
+//! # Example
+//! A filter accepting the packets whose length, the second word of their context, is at most 1500 bytes:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_bpf::{register::BpfReg, stream::{BpfInstructionStream, Condition, Size}};
+//!
+//! let mut stream = BpfInstructionStream::new();
+//! let out = stream.create_label();
+//!
+//! stream.mov64_imm(BpfReg::R0, 0);
+//! stream.ldx(Size::Word, BpfReg::R2, BpfReg::R1, 4);
+//! stream.jcc_imm_label(Condition::Gt, BpfReg::R2, 1500, out);
+//! stream.mov64_imm(BpfReg::R0, 1);
+//! stream.attach_label(out);
+//! stream.exit();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r0 = 0
+//!     0x61, 0x12, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, // r2 = *(u32 *)(r1 + 4)
+//!     0x25, 0x02, 0x01, 0x00, 0xdc, 0x05, 0x00, 0x00, // if r2 > 1500 goto out
+//!     0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r0 = 1
+//!     0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // out: exit
+//! ]);
+//! ```
+
+use asmkit_core::{
+    displacement::{DisplacementBias, DisplacementForm, DisplacementUnit},
+    entity::{EntityList, Label, LabelRef},
+    labels::LabelTable,
+    raw::Endianness,
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+use crate::{
+    encoding::{encode, CLASS_ALU, CLASS_ALU64, CLASS_JMP, CLASS_LD, CLASS_LDX, CLASS_ST, CLASS_STX, MODE_IMM, MODE_MEM, SOURCE_K, SOURCE_X},
+    register::BpfReg,
+};
+
+/// The size of an instruction slot, in bytes.
+pub const SLOT_SIZE: usize = 8;
+
+/// How the offsets of the jumps are measured: in slots, from the slot following the jump.
+pub const JUMP_FORM: DisplacementForm = DisplacementForm::new(DisplacementUnit::Slots, DisplacementBias::End(SLOT_SIZE), 16);
+
+/// An operation of the ALU classes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AluOp {
+    /// `dst += src`.
+    Add,
+
+    /// `dst -= src`.
+    Sub,
+
+    /// `dst *= src`.
+    Mul,
+
+    /// `dst /= src`, unsigned.
+    Div,
+
+    /// `dst |= src`.
+    Or,
+
+    /// `dst &= src`.
+    And,
+
+    /// `dst <<= src`.
+    Lsh,
+
+    /// `dst >>= src`, logical.
+    Rsh,
+
+    /// `dst %= src`, unsigned.
+    Mod,
+
+    /// `dst ^= src`.
+    Xor,
+
+    /// `dst s>>= src`, arithmetic.
+    Arsh,
+}
+
+impl AluOp {
+    /// The operation field of this operation, for instruction encoding.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Add => 0x00,
+            Self::Sub => 0x10,
+            Self::Mul => 0x20,
+            Self::Div => 0x30,
+            Self::Or => 0x40,
+            Self::And => 0x50,
+            Self::Lsh => 0x60,
+            Self::Rsh => 0x70,
+            Self::Mod => 0x90,
+            Self::Xor => 0xa0,
+            Self::Arsh => 0xc0,
+        }
+    }
+}
+
+/// The condition of a conditional jump, comparing its 64-bit operands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    /// Equal, `jeq`.
+    Eq,
+
+    /// Greater, unsigned, `jgt`.
+    Gt,
+
+    /// Greater or equal, unsigned, `jge`.
+    Ge,
+
+    /// Less, unsigned, `jlt`.
+    Lt,
+
+    /// Less or equal, unsigned, `jle`.
+    Le,
+
+    /// Any bit in common, `jset`.
+    Set,
+
+    /// Not equal, `jne`.
+    Ne,
+
+    /// Greater, signed, `jsgt`.
+    Sgt,
+
+    /// Greater or equal, signed, `jsge`.
+    Sge,
+
+    /// Less, signed, `jslt`.
+    Slt,
+
+    /// Less or equal, signed, `jsle`.
+    Sle,
+}
+
+impl Condition {
+    /// The operation field of this condition, for instruction encoding.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Eq => 0x10,
+            Self::Gt => 0x20,
+            Self::Ge => 0x30,
+            Self::Set => 0x40,
+            Self::Ne => 0x50,
+            Self::Sgt => 0x60,
+            Self::Sge => 0x70,
+            Self::Lt => 0xa0,
+            Self::Le => 0xb0,
+            Self::Slt => 0xc0,
+            Self::Sle => 0xd0,
+        }
+    }
+}
+
+/// The size of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    /// An 8-bit access, `u8`.
+    Byte,
+
+    /// A 16-bit access, `u16`.
+    Half,
+
+    /// A 32-bit access, `u32`.
+    Word,
+
+    /// A 64-bit access, `u64`.
+    DoubleWord,
+}
+
+impl Size {
+    /// The size field of this size, for instruction encoding.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Word => 0x00,
+            Self::Half => 0x08,
+            Self::Byte => 0x10,
+            Self::DoubleWord => 0x18,
+        }
+    }
+}
+
+/// A use of a label by a jump, patched once the label is attached, or once the instruction stream is finished if the label was attached first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fixup {
+    /// The offset of the jump, in bytes.
+    pub offset: usize,
+
+    /// The label used by the jump.
+    pub label: LabelRef,
+}
+
+/// An instruction stream for eBPF programs.
+pub struct BpfInstructionStream {
+    /// The bytes written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream, and their pending uses.
+    labels: LabelTable<Fixup>,
+
+    /// The holes of the templates written to the instruction stream.
+    holes: TemplateHoles,
+}
+
+impl InstructionStream for BpfInstructionStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.create()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.create_attached(self.bytes.len())
+    }
+
+    /// Attaches `label` to the current offset, patching the jumps which used it before.
+    ///
+    /// # Panics
+    /// Panics if the label is attached within a slot while a jump used it before, or if the offset from one of these jumps doesn't fit in
+    /// their 16 bits.
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::stream::BpfInstructionStream;
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// let label = stream.create_label();
+    /// stream.ja_label(label);
+    /// stream.write_byte(0);
+    /// stream.attach_label(label);
+    /// ```
+    fn attach_label(&mut self, label: LabelRef) {
+        let mut pending = self.labels.attach(label, self.bytes.len());
+        while let Some(fixup) = pending.next(&self.labels) {
+            self.resolve(fixup);
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, filled when the stream is finished.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol, which the stream has none of.
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push_label(fixup);
+    }
+
+    /// Finishes writing to the instruction stream, resolving the label uses recorded after their label was attached into numbers of instruction
+    /// slots, and returns the produced bytes.
+    ///
+    /// # Panics
+    /// Panics if a used label was never attached or is attached within a slot, or if its offset doesn't fit in the 16 bits of the jump.
+    fn finish(mut self) -> Product {
+        for label in self.labels.refs() {
+            let mut pending = self.labels.take_pending(label);
+            while let Some(fixup) = pending.next(&self.labels) {
+                self.resolve(fixup);
+            }
+        }
+
+        let relocations = self.holes.resolve(&mut self.bytes, Endianness::Little, |label| self.labels.offset(label)).unwrap_or_else(|error| panic!("{}", error));
+        Product::with_relocations(self.bytes, relocations, EntityList::new())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
+
+impl Default for BpfInstructionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BpfInstructionStream {
+    /// Creates a new, empty instruction stream.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), labels: LabelTable::new(), holes: TemplateHoles::new() }
+    }
+
+    /// Patches the offset of `fixup` to its attached label, in instruction slots, into the jump using it.
+    ///
+    /// # Panics
+    /// Panics if the label isn't attached or is attached within a slot, or if its offset doesn't fit in the 16 bits of the jump.
+    fn resolve(&mut self, fixup: Fixup) {
+        let target = match self.labels.get(fixup.label).state {
+            Label::Attached(target) => target,
+            Label::Unattached => panic!("label {:?} is used but never attached", fixup.label),
+            Label::Absolute(_) => unreachable!("labels are only bound to absolute addresses by the x86-64 stream"),
+        };
+
+        let slots = JUMP_FORM.resolve(fixup.offset, target).unwrap_or_else(|error| panic!("{} to label {:?}", error, fixup.label));
+        self.bytes[fixup.offset + 2..fixup.offset + 4].copy_from_slice(&(slots as i16).to_le_bytes());
+    }
+
+    /// Writes a single instruction slot into the instruction stream, see [`encode`].
+    #[inline(always)]
+    pub fn write_instruction(&mut self, opcode: u8, dst: u8, src: u8, off: i16, imm: i32) {
+        self.write_quad_word(encode(opcode, dst, src, off, imm));
+    }
+
+    /// Apply `op` to *dst* and *src*, `dst op= src`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::{register::BpfReg, stream::{AluOp, BpfInstructionStream}};
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// stream.alu64_reg(AluOp::Sub, BpfReg::R1, BpfReg::R2);
+    /// stream.alu64_imm(AluOp::Mul, BpfReg::R1, 3);
+    /// stream.alu64_imm(AluOp::Arsh, BpfReg::R1, 2);
+    /// stream.alu32_reg(AluOp::Xor, BpfReg::R1, BpfReg::R2);
+    /// stream.neg64(BpfReg::R1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x1f, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r1 -= r2
+    ///     0x27, 0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, // r1 *= 3
+    ///     0xc7, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // r1 s>>= 2
+    ///     0xac, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // w1 ^= w2
+    ///     0x87, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r1 = -r1
+    /// ]);
+    /// ```
+    pub fn alu64_reg(&mut self, op: AluOp, dst: BpfReg, src: BpfReg) {
+        self.write_instruction(CLASS_ALU64 | SOURCE_X | op.code(), dst.offset(), src.offset(), 0, 0);
+    }
+
+    /// Apply `op` to *dst* and the sign-extended *imm32*, `dst op= imm32`.
+    pub fn alu64_imm(&mut self, op: AluOp, dst: BpfReg, imm: i32) {
+        self.write_instruction(CLASS_ALU64 | SOURCE_K | op.code(), dst.offset(), 0, 0, imm);
+    }
+
+    /// Apply `op` to the low halves of *dst* and *src*, zeroing the upper half of *dst*, `wdst op= wsrc`.
+    pub fn alu32_reg(&mut self, op: AluOp, dst: BpfReg, src: BpfReg) {
+        self.write_instruction(CLASS_ALU | SOURCE_X | op.code(), dst.offset(), src.offset(), 0, 0);
+    }
+
+    /// Apply `op` to the low half of *dst* and *imm32*, zeroing the upper half of *dst*, `wdst op= imm32`.
+    pub fn alu32_imm(&mut self, op: AluOp, dst: BpfReg, imm: i32) {
+        self.write_instruction(CLASS_ALU | SOURCE_K | op.code(), dst.offset(), 0, 0, imm);
+    }
+
+    /// Negate *dst*, `dst = -dst`.
+    pub fn neg64(&mut self, dst: BpfReg) {
+        self.write_instruction(CLASS_ALU64 | 0x80, dst.offset(), 0, 0, 0);
+    }
+
+    /// Negate the low half of *dst*, zeroing its upper half, `wdst = -wdst`.
+    pub fn neg32(&mut self, dst: BpfReg) {
+        self.write_instruction(CLASS_ALU | 0x80, dst.offset(), 0, 0, 0);
+    }
+
+    /// Move *src* to *dst*, `dst = src`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::{register::BpfReg, stream::BpfInstructionStream};
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// stream.mov64_reg(BpfReg::R3, BpfReg::R4);
+    /// stream.mov32_reg(BpfReg::R3, BpfReg::R4);
+    /// stream.mov32_imm(BpfReg::R3, -1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xbf, 0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r3 = r4
+    ///     0xbc, 0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // w3 = w4
+    ///     0xb4, 0x03, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, // w3 = -1
+    /// ]);
+    /// ```
+    pub fn mov64_reg(&mut self, dst: BpfReg, src: BpfReg) {
+        self.write_instruction(CLASS_ALU64 | SOURCE_X | 0xb0, dst.offset(), src.offset(), 0, 0);
+    }
+
+    /// Move the sign-extended *imm32* to *dst*, `dst = imm32`.
+    pub fn mov64_imm(&mut self, dst: BpfReg, imm: i32) {
+        self.write_instruction(CLASS_ALU64 | SOURCE_K | 0xb0, dst.offset(), 0, 0, imm);
+    }
+
+    /// Move the low half of *src* to *dst*, zeroing its upper half, `wdst = wsrc`.
+    pub fn mov32_reg(&mut self, dst: BpfReg, src: BpfReg) {
+        self.write_instruction(CLASS_ALU | SOURCE_X | 0xb0, dst.offset(), src.offset(), 0, 0);
+    }
+
+    /// Move *imm32* to *dst*, zeroing its upper half, `wdst = imm32`.
+    pub fn mov32_imm(&mut self, dst: BpfReg, imm: i32) {
+        self.write_instruction(CLASS_ALU | SOURCE_K | 0xb0, dst.offset(), 0, 0, imm);
+    }
+
+    /// Move the 64-bit *imm64* to *dst*, taking two instruction slots, `dst = imm64 ll`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::{register::BpfReg, stream::BpfInstructionStream};
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// stream.ld_imm64(BpfReg::R2, 0x1122334455667788);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x18, 0x02, 0x00, 0x00, 0x88, 0x77, 0x66, 0x55, // r2 = 0x1122334455667788 ll
+    ///     0x00, 0x00, 0x00, 0x00, 0x44, 0x33, 0x22, 0x11,
+    /// ]);
+    /// ```
+    pub fn ld_imm64(&mut self, dst: BpfReg, imm: u64) {
+        self.write_instruction(CLASS_LD | MODE_IMM | Size::DoubleWord.code(), dst.offset(), 0, 0, imm as i32);
+        self.write_instruction(0, 0, 0, 0, (imm >> 32) as i32);
+    }
+
+    /// Load `size` bytes at *[src + off]* into *dst*, zero extended, `dst = *(size *)(src + off)`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::{register::BpfReg, stream::{BpfInstructionStream, Size}};
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// stream.ldx(Size::DoubleWord, BpfReg::R2, BpfReg::R1, 8);
+    /// stream.stx(Size::DoubleWord, BpfReg::R10, -8, BpfReg::R1);
+    /// stream.stx(Size::Byte, BpfReg::R10, -1, BpfReg::R2);
+    /// stream.st(Size::Word, BpfReg::R10, -4, 7);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x79, 0x12, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, // r2 = *(u64 *)(r1 + 8)
+    ///     0x7b, 0x1a, 0xf8, 0xff, 0x00, 0x00, 0x00, 0x00, // *(u64 *)(r10 - 8) = r1
+    ///     0x73, 0x2a, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, // *(u8 *)(r10 - 1) = r2
+    ///     0x62, 0x0a, 0xfc, 0xff, 0x07, 0x00, 0x00, 0x00, // *(u32 *)(r10 - 4) = 7
+    /// ]);
+    /// ```
+    pub fn ldx(&mut self, size: Size, dst: BpfReg, src: BpfReg, off: i16) {
+        self.write_instruction(CLASS_LDX | MODE_MEM | size.code(), dst.offset(), src.offset(), off, 0);
+    }
+
+    /// Store the low `size` bytes of *src* at *[dst + off]*, `*(size *)(dst + off) = src`.
+    pub fn stx(&mut self, size: Size, dst: BpfReg, off: i16, src: BpfReg) {
+        self.write_instruction(CLASS_STX | MODE_MEM | size.code(), dst.offset(), src.offset(), off, 0);
+    }
+
+    /// Store the low `size` bytes of the sign-extended *imm32* at *[dst + off]*, `*(size *)(dst + off) = imm32`.
+    pub fn st(&mut self, size: Size, dst: BpfReg, off: i16, imm: i32) {
+        self.write_instruction(CLASS_ST | MODE_MEM | size.code(), dst.offset(), 0, off, imm);
+    }
+
+    /// Writes a jump to `label`, with its offset left to a fixup.
+    fn write_jump_label(&mut self, opcode: u8, dst: BpfReg, src: BpfReg, imm: i32, label: LabelRef) {
+        self.labels.use_label(label, Fixup { offset: self.bytes.len(), label });
+        self.write_instruction(opcode, dst.offset(), src.offset(), 0, imm);
+    }
+
+    /// Jump to `label`, `goto label`.
+    ///
+    /// # Example
+    /// The offsets count instruction slots, two for `ld_imm64`:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::{register::BpfReg, stream::{BpfInstructionStream, Condition}};
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// let top = stream.create_label_attached();
+    /// let out = stream.create_label();
+    ///
+    /// stream.ld_imm64(BpfReg::R2, 0);
+    /// stream.jcc_reg_label(Condition::Ne, BpfReg::R1, BpfReg::R2, top);
+    /// stream.jcc_imm_label(Condition::Set, BpfReg::R1, 4, out);
+    /// stream.jcc_reg_label(Condition::Slt, BpfReg::R1, BpfReg::R2, out);
+    /// stream.ja_label(top);
+    /// stream.attach_label(out);
+    /// stream.exit();
+    ///
+    /// assert_eq!(stream.finish().emit()[16..], [
+    ///     0x5d, 0x21, 0xfd, 0xff, 0x00, 0x00, 0x00, 0x00, // if r1 != r2 goto top
+    ///     0x45, 0x01, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, // if r1 & 4 goto out
+    ///     0xcd, 0x21, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // if r1 s< r2 goto out
+    ///     0x05, 0x00, 0xfa, 0xff, 0x00, 0x00, 0x00, 0x00, // goto top
+    ///     0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // out: exit
+    /// ]);
+    /// ```
+    pub fn ja_label(&mut self, label: LabelRef) {
+        self.write_jump_label(CLASS_JMP, BpfReg::R0, BpfReg::R0, 0, label);
+    }
+
+    /// Jump to `label` if *dst* and *src* satisfy `condition`, `if dst cond src goto label`.
+    pub fn jcc_reg_label(&mut self, condition: Condition, dst: BpfReg, src: BpfReg, label: LabelRef) {
+        self.write_jump_label(CLASS_JMP | SOURCE_X | condition.code(), dst, src, 0, label);
+    }
+
+    /// Jump to `label` if *dst* and the sign-extended *imm32* satisfy `condition`, `if dst cond imm32 goto label`.
+    pub fn jcc_imm_label(&mut self, condition: Condition, dst: BpfReg, imm: i32, label: LabelRef) {
+        self.write_jump_label(CLASS_JMP | SOURCE_K | condition.code(), dst, BpfReg::R0, imm, label);
+    }
+
+    /// Call the helper function `helper`, by its id, with its arguments in `r1` to `r5` and its result in `r0`, `call helper`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_bpf::stream::BpfInstructionStream;
+    ///
+    /// let mut stream = BpfInstructionStream::new();
+    /// stream.call(12); // bpf_tail_call
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x85, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00]); // call 12
+    /// ```
+    pub fn call(&mut self, helper: i32) {
+        self.write_instruction(CLASS_JMP | 0x80, 0, 0, 0, helper);
+    }
+
+    /// Return from the program with the value of `r0`, `exit`.
+    pub fn exit(&mut self) {
+        self.write_instruction(CLASS_JMP | 0x90, 0, 0, 0, 0);
+    }
+}
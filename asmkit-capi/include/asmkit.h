@@ -0,0 +1,116 @@
+/*
+ * The C API of AsmKit, exported by the `asmkit-capi` crate.
+ *
+ * The functions returning an `asmkit_status` never unwind: a panic of the stream is caught and returned as `ASMKIT_STATUS_PANIC`, and the message
+ * of the last error of the thread is read by `asmkit_last_error`.
+ */
+
+#ifndef ASMKIT_H
+#define ASMKIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The status returned by the functions of the API. */
+typedef enum asmkit_status {
+    /* The call succeeded. */
+    ASMKIT_STATUS_OK = 0,
+    /* A handle or an output pointer is null. */
+    ASMKIT_STATUS_NULL_POINTER = 1,
+    /* A register number isn't that of a register of its kind. */
+    ASMKIT_STATUS_INVALID_REGISTER = 2,
+    /* A label handle wasn't created by the stream. */
+    ASMKIT_STATUS_INVALID_LABEL = 3,
+    /* The stream panicked, such as when finished with a label used but never attached. */
+    ASMKIT_STATUS_PANIC = 4,
+} asmkit_status;
+
+/* The 64-bit registers, numbered as they are encoded. */
+enum {
+    ASMKIT_X64_RAX = 0,
+    ASMKIT_X64_RCX = 1,
+    ASMKIT_X64_RDX = 2,
+    ASMKIT_X64_RBX = 3,
+    ASMKIT_X64_RSP = 4,
+    ASMKIT_X64_RBP = 5,
+    ASMKIT_X64_RSI = 6,
+    ASMKIT_X64_RDI = 7,
+    ASMKIT_X64_R8 = 8,
+    ASMKIT_X64_R9 = 9,
+    ASMKIT_X64_R10 = 10,
+    ASMKIT_X64_R11 = 11,
+    ASMKIT_X64_R12 = 12,
+    ASMKIT_X64_R13 = 13,
+    ASMKIT_X64_R14 = 14,
+    ASMKIT_X64_R15 = 15,
+};
+
+/* An x86-64 instruction stream. */
+typedef struct asmkit_x64_stream asmkit_x64_stream;
+
+/* The bytes and the relocations produced by a finished stream. */
+typedef struct asmkit_product asmkit_product;
+
+/* A label of a stream, numbered from 0 in the order the stream creates them. */
+typedef uint32_t asmkit_label;
+
+/* The message of the last error of the calling thread, or an empty string.  Valid until the next failing call of the thread. */
+const char *asmkit_last_error(void);
+
+/* Creates an x86-64 instruction stream, to be freed by `asmkit_x64_stream_free` or finished by `asmkit_stream_finish`. */
+asmkit_x64_stream *asmkit_x64_stream_new(void);
+
+/* Frees `stream` without finishing it.  Does nothing if `stream` is null. */
+void asmkit_x64_stream_free(asmkit_x64_stream *stream);
+
+/* Creates a label, unattached, and writes its handle to `label`. */
+asmkit_status asmkit_x64_label_create(asmkit_x64_stream *stream, asmkit_label *label);
+
+/* Attaches `label` to the current offset of `stream`. */
+asmkit_status asmkit_x64_label_attach(asmkit_x64_stream *stream, asmkit_label label);
+
+/* mov dest, imm */
+asmkit_status asmkit_x64_mov_reg64_imm64(asmkit_x64_stream *stream, uint32_t dest_id, uint64_t imm);
+
+/* mov dest, src */
+asmkit_status asmkit_x64_mov_reg64_reg64(asmkit_x64_stream *stream, uint32_t dest_id, uint32_t src_id);
+
+/* add dest, src */
+asmkit_status asmkit_x64_add_reg64_reg64(asmkit_x64_stream *stream, uint32_t dest_id, uint32_t src_id);
+
+/* push reg */
+asmkit_status asmkit_x64_push_reg64(asmkit_x64_stream *stream, uint32_t reg_id);
+
+/* pop reg */
+asmkit_status asmkit_x64_pop_reg64(asmkit_x64_stream *stream, uint32_t reg_id);
+
+/* jmp label */
+asmkit_status asmkit_x64_jmp_label(asmkit_x64_stream *stream, asmkit_label label);
+
+/* call label */
+asmkit_status asmkit_x64_call_label(asmkit_x64_stream *stream, asmkit_label label);
+
+/* ret */
+asmkit_status asmkit_x64_ret_near(asmkit_x64_stream *stream);
+
+/*
+ * Finishes `stream`, resolving its labels, and writes the product to `product`, to be freed by `asmkit_product_free`.  The stream is consumed even
+ * if finishing it fails, and `product` is then set to null.
+ */
+asmkit_status asmkit_stream_finish(asmkit_x64_stream *stream, asmkit_product **product);
+
+/* The bytes of `product`, writing their length to `len`, or null if `product` is null.  Valid until the product is freed. */
+const uint8_t *asmkit_product_bytes(const asmkit_product *product, size_t *len);
+
+/* Frees `product`.  Does nothing if `product` is null. */
+void asmkit_product_free(asmkit_product *product);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
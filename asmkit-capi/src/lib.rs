@@ -0,0 +1,114 @@
+//! A C API for AsmKit, to drive the instruction streams from a frontend written in another language.
+//!
+//! The crate builds a shared and a static library exporting the functions of `include/asmkit.h`.  The streams and the products are opaque
+//! handles, created and freed through the API.  The labels are integer handles, numbered from 0 in the order a stream creates them, and the
+//! registers are the numbers they are encoded with, `rax` to `r15` being 0 to 15.
+//!
+//! The functions return a [`Status`] rather than panicking across the boundary: a panic of the stream, such as finishing it with a label used but
+//! never attached, is caught and returned as [`Status::Panic`].  The message of the last error of the thread is read by [`asmkit_last_error`].
+//!
+//! # Example
+//! The API, called from Rust as a C frontend would:
+//! ```
+//! use asmkit_capi::{
+//!     asmkit_last_error,
+//!     asmkit_product_bytes,
+//!     asmkit_product_free,
+//!     asmkit_stream_finish,
+//!     x86_64::*,
+//!     Status,
+//! };
+//! use std::{ffi::CStr, ptr};
+//!
+//! unsafe {
+//!     let stream = asmkit_x64_stream_new();
+//!     let mut done = 0;
+//!     assert_eq!(asmkit_x64_label_create(stream, &mut done), Status::Ok);
+//!     assert_eq!(asmkit_x64_mov_reg64_imm64(stream, 0, 0x1122_3344_5566_7788), Status::Ok);
+//!     assert_eq!(asmkit_x64_jmp_label(stream, done), Status::Ok);
+//!     assert_eq!(asmkit_x64_label_attach(stream, done), Status::Ok);
+//!     assert_eq!(asmkit_x64_ret_near(stream), Status::Ok);
+//!
+//!     // `r16` isn't a register.
+//!     assert_eq!(asmkit_x64_push_reg64(stream, 16), Status::InvalidRegister);
+//!     assert_eq!(CStr::from_ptr(asmkit_last_error()).to_str(), Ok("no 64-bit register is numbered 16"));
+//!
+//!     let mut product = ptr::null_mut();
+//!     assert_eq!(asmkit_stream_finish(stream, &mut product), Status::Ok);
+//!
+//!     let mut len = 0;
+//!     let bytes = asmkit_product_bytes(product, &mut len);
+//!     assert_eq!(std::slice::from_raw_parts(bytes, len), [
+//!         0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, // movabs rax, 0x1122334455667788
+//!         0xe9, 0x00, 0x00, 0x00, 0x00,                               // jmp done
+//!         0xc3,                                                       // done: ret
+//!     ]);
+//!
+//!     asmkit_product_free(product);
+//! }
+//! ```
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+};
+
+pub mod product;
+pub mod x86_64;
+
+pub use product::{asmkit_product_bytes, asmkit_product_free, asmkit_stream_finish};
+
+/// The status returned by the functions of the C API, `ASMKIT_STATUS_*` in the header.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// The call succeeded.
+    Ok = 0,
+
+    /// A handle or an output pointer is null.
+    NullPointer = 1,
+
+    /// A register number isn't that of a register of its kind.
+    InvalidRegister = 2,
+
+    /// A label handle wasn't created by the stream.
+    InvalidLabel = 3,
+
+    /// The stream panicked, such as when finished with a label used but never attached.
+    Panic = 4,
+}
+
+thread_local! {
+    /// The message of the last error of the thread, see [`asmkit_last_error`].
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Records `message` as the last error of the thread, and returns `status`.
+pub(crate) fn fail(status: Status, message: &str) -> Status {
+    // an interior nul would end the message early, rather than fail to record it.
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+/// Runs `call` at the boundary of the C API, returning [`Status::Panic`] if it panics, with the message of the panic as the last error.
+pub(crate) fn guard(call: impl FnOnce() -> Status) -> Status {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|payload: Box<dyn Any + Send>| {
+        let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(message), _) => message,
+            (_, Some(message)) => message.as_str(),
+            _ => "the stream panicked",
+        };
+
+        fail(Status::Panic, message)
+    })
+}
+
+/// The message of the last error of the calling thread, or an empty string if no call failed yet.  The string is owned by the library, and valid
+/// until the next failing call of the thread.
+#[no_mangle]
+pub extern "C" fn asmkit_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
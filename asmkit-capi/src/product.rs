@@ -0,0 +1,81 @@
+//! The products of the C API, `asmkit_product` in the header.
+
+use std::ptr;
+
+use asmkit_core::{InstructionStream, Product};
+
+use crate::{fail, guard, x86_64::X64Stream, Status};
+
+/// Finishes `stream`, resolving its labels, and writes the product to `product`, to be freed by [`asmkit_product_free`].
+///
+/// The stream is consumed even if finishing it fails, such as with [`Status::Panic`] when a label is used but never attached, and `product` is
+/// then set to null.
+///
+/// # Safety
+/// `stream` must be null or a stream of [`asmkit_x64_stream_new`](crate::x86_64::asmkit_x64_stream_new) which wasn't freed or finished, and
+/// `product` null or valid for writes.
+///
+/// # Example
+/// ```
+/// use asmkit_capi::{asmkit_last_error, asmkit_stream_finish, x86_64::*, Status};
+/// use std::{ffi::CStr, ptr};
+///
+/// unsafe {
+///     let stream = asmkit_x64_stream_new();
+///     let mut label = 0;
+///     asmkit_x64_label_create(stream, &mut label);
+///     asmkit_x64_call_label(stream, label);
+///
+///     let mut product = ptr::null_mut();
+///     assert_eq!(asmkit_stream_finish(stream, &mut product), Status::Panic);
+///     assert!(product.is_null());
+///     assert!(CStr::from_ptr(asmkit_last_error()).to_str().unwrap().contains("never attached"));
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_stream_finish(stream: *mut X64Stream, product: *mut *mut Product) -> Status {
+    if stream.is_null() {
+        return fail(Status::NullPointer, "the stream is null");
+    }
+
+    let stream = Box::from_raw(stream);
+    if product.is_null() {
+        stream.stream.abandon();
+        return fail(Status::NullPointer, "the product output is null");
+    }
+
+    *product = ptr::null_mut();
+    guard(|| {
+        *product = Box::into_raw(Box::new(stream.stream.finish()));
+        Status::Ok
+    })
+}
+
+/// The bytes of `product`, writing their length to `len`, or null if `product` is null.  The bytes are owned by the product, and valid until it
+/// is freed.
+///
+/// # Safety
+/// `product` must be null or a product of [`asmkit_stream_finish`] which wasn't freed, and `len` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_product_bytes(product: *const Product, len: *mut usize) -> *const u8 {
+    let bytes = product.as_ref().map_or(&[][..], Product::bytes);
+    if let Some(len) = len.as_mut() {
+        *len = bytes.len();
+    }
+
+    match product.is_null() {
+        true => ptr::null(),
+        false => bytes.as_ptr(),
+    }
+}
+
+/// Frees `product`.  Does nothing if `product` is null.
+///
+/// # Safety
+/// `product` must be null or a product of [`asmkit_stream_finish`] which wasn't freed.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_product_free(product: *mut Product) {
+    if !product.is_null() {
+        drop(Box::from_raw(product));
+    }
+}
@@ -0,0 +1,216 @@
+//! The x86-64 instruction streams of the C API, `asmkit_x64_*` in the header.
+//!
+//! A stream is created by [`asmkit_x64_stream_new`], and either finished into a product by
+//! [`asmkit_stream_finish`](crate::asmkit_stream_finish) or freed by [`asmkit_x64_stream_free`].  The emitters return [`Status::Ok`], or an
+//! error status without emitting anything.
+
+use asmkit_core::{entity::LabelRef, InstructionStream};
+use asmkit_x86_64::{register::{GpReg, Reg64}, stream::x86_64InstructionStream};
+
+use crate::{fail, guard, Status};
+
+/// An x86-64 instruction stream, `asmkit_x64_stream` in the header.
+pub struct X64Stream {
+    /// The stream the calls emit to.
+    pub(crate) stream: x86_64InstructionStream,
+
+    /// The labels created through the C API, indexed by handle.
+    labels: Vec<LabelRef>,
+}
+
+/// Runs `call` on the stream behind `stream`, at the boundary of the C API.
+///
+/// # Safety
+/// `stream` must be null or a stream of [`asmkit_x64_stream_new`] which wasn't freed or finished.
+unsafe fn with_stream(stream: *mut X64Stream, call: impl FnOnce(&mut X64Stream) -> Status) -> Status {
+    match stream.as_mut() {
+        Some(stream) => guard(|| call(stream)),
+        None => fail(Status::NullPointer, "the stream is null"),
+    }
+}
+
+/// The 64-bit register encoded as `id`, or the failing status if there is none.
+fn reg64(id: u32) -> Result<Reg64, Status> {
+    match id {
+        0..=15 => Ok(Reg64::from_number(id as u8)),
+        _ => Err(fail(Status::InvalidRegister, &format!("no 64-bit register is numbered {}", id))),
+    }
+}
+
+impl X64Stream {
+    /// The label of the handle `label`, or the failing status if the stream didn't create it.
+    fn label(&self, label: u32) -> Result<LabelRef, Status> {
+        match self.labels.get(label as usize) {
+            Some(&label) => Ok(label),
+            None => Err(fail(Status::InvalidLabel, &format!("the stream has no label {}, it created {}", label, self.labels.len()))),
+        }
+    }
+}
+
+/// Creates an x86-64 instruction stream, to be freed by [`asmkit_x64_stream_free`] or finished by
+/// [`asmkit_stream_finish`](crate::asmkit_stream_finish).
+#[no_mangle]
+pub extern "C" fn asmkit_x64_stream_new() -> *mut X64Stream {
+    Box::into_raw(Box::new(X64Stream { stream: x86_64InstructionStream::new(), labels: Vec::new() }))
+}
+
+/// Frees `stream` without finishing it.  Does nothing if `stream` is null.
+///
+/// # Safety
+/// `stream` must be null or a stream of [`asmkit_x64_stream_new`] which wasn't freed or finished.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_stream_free(stream: *mut X64Stream) {
+    if !stream.is_null() {
+        Box::from_raw(stream).stream.abandon();
+    }
+}
+
+/// Creates a label, unattached, and writes its handle to `label`.
+///
+/// # Safety
+/// `stream` must be null or a live stream, and `label` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_label_create(stream: *mut X64Stream, label: *mut u32) -> Status {
+    if label.is_null() {
+        return fail(Status::NullPointer, "the label output is null");
+    }
+
+    with_stream(stream, |stream| {
+        *label = stream.labels.len() as u32;
+        let created = stream.stream.create_label();
+        stream.labels.push(created);
+        Status::Ok
+    })
+}
+
+/// Attaches `label` to the current offset of `stream`.
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_label_attach(stream: *mut X64Stream, label: u32) -> Status {
+    with_stream(stream, |stream| match stream.label(label) {
+        Ok(label) => {
+            stream.stream.attach_label(label);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits `mov dest, imm`, with the 64-bit immediate `imm`, see [`x86_64InstructionStream::mov_reg64_imm64`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_mov_reg64_imm64(stream: *mut X64Stream, dest_id: u32, imm: u64) -> Status {
+    with_stream(stream, |stream| match reg64(dest_id) {
+        Ok(dest) => {
+            stream.stream.mov_reg64_imm64(dest, imm);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits `mov dest, src`, see [`x86_64InstructionStream::mov_reg64_reg64`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_mov_reg64_reg64(stream: *mut X64Stream, dest_id: u32, src_id: u32) -> Status {
+    with_stream(stream, |stream| match (reg64(dest_id), reg64(src_id)) {
+        (Ok(dest), Ok(src)) => {
+            stream.stream.mov_reg64_reg64(dest, src);
+            Status::Ok
+        },
+        (Err(status), _) | (_, Err(status)) => status,
+    })
+}
+
+/// Emits `add dest, src`, see [`x86_64InstructionStream::add_reg64_reg64`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_add_reg64_reg64(stream: *mut X64Stream, dest_id: u32, src_id: u32) -> Status {
+    with_stream(stream, |stream| match (reg64(dest_id), reg64(src_id)) {
+        (Ok(dest), Ok(src)) => {
+            stream.stream.add_reg64_reg64(dest, src);
+            Status::Ok
+        },
+        (Err(status), _) | (_, Err(status)) => status,
+    })
+}
+
+/// Emits `push reg`, see [`x86_64InstructionStream::push_reg64`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_push_reg64(stream: *mut X64Stream, reg_id: u32) -> Status {
+    with_stream(stream, |stream| match reg64(reg_id) {
+        Ok(reg) => {
+            stream.stream.push_reg64(reg);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits `pop reg`, see [`x86_64InstructionStream::pop_reg64`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_pop_reg64(stream: *mut X64Stream, reg_id: u32) -> Status {
+    with_stream(stream, |stream| match reg64(reg_id) {
+        Ok(reg) => {
+            stream.stream.pop_reg64(reg);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits a near jump to `label`, see [`x86_64InstructionStream::jmp_label`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_jmp_label(stream: *mut X64Stream, label: u32) -> Status {
+    with_stream(stream, |stream| match stream.label(label) {
+        Ok(label) => {
+            stream.stream.jmp_label(label);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits a near call to `label`, see [`x86_64InstructionStream::call_label`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_call_label(stream: *mut X64Stream, label: u32) -> Status {
+    with_stream(stream, |stream| match stream.label(label) {
+        Ok(label) => {
+            stream.stream.call_label(label);
+            Status::Ok
+        },
+        Err(status) => status,
+    })
+}
+
+/// Emits a near return, see [`x86_64InstructionStream::ret_near`].
+///
+/// # Safety
+/// `stream` must be null or a live stream.
+#[no_mangle]
+pub unsafe extern "C" fn asmkit_x64_ret_near(stream: *mut X64Stream) -> Status {
+    with_stream(stream, |stream| {
+        stream.stream.ret_near();
+        Status::Ok
+    })
+}
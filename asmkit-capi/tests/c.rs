@@ -0,0 +1,35 @@
+//! Compiles the C program of `tests/c` against the header and the static library of the crate, with the C compiler of `CC` or `cc`, and runs it.
+
+#![cfg(target_os = "linux")]
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn smoke() {
+    // the test runs from `target/<profile>/deps`, next to the libraries of the crate.
+    let executable = env::current_exe().unwrap();
+    let deps = executable.parent().unwrap();
+    let profile = deps.parent().unwrap();
+    let library = profile.join("libasmkit_capi.a");
+    assert!(library.exists(), "the static library isn't built at {}", library.display());
+
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let program = deps.join("asmkit-capi-smoke");
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg(manifest.join("tests/c/smoke.c"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("the C compiler can't be run");
+    assert!(status.success(), "the C program doesn't compile");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(output.status.success(), "the C program failed: {}", String::from_utf8_lossy(&output.stderr));
+}
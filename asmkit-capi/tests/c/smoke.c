@@ -0,0 +1,73 @@
+/* Drives an x86-64 stream through the C API, and checks the bytes of its product and the error statuses. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "asmkit.h"
+
+#define CHECK(condition)                                                          \
+    do {                                                                          \
+        if (!(condition)) {                                                       \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+            return 1;                                                             \
+        }                                                                         \
+    } while (0)
+
+int main(void) {
+    asmkit_x64_stream *stream = asmkit_x64_stream_new();
+    asmkit_label add, done;
+    CHECK(asmkit_x64_label_create(stream, &add) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_label_create(stream, &done) == ASMKIT_STATUS_OK);
+    CHECK(add == 0 && done == 1);
+
+    CHECK(asmkit_x64_mov_reg64_imm64(stream, ASMKIT_X64_RAX, 0x1122334455667788) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_call_label(stream, add) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_jmp_label(stream, done) == ASMKIT_STATUS_OK);
+
+    CHECK(asmkit_x64_label_attach(stream, add) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_push_reg64(stream, ASMKIT_X64_R12) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_mov_reg64_reg64(stream, ASMKIT_X64_R12, ASMKIT_X64_RDI) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_add_reg64_reg64(stream, ASMKIT_X64_RAX, ASMKIT_X64_R12) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_pop_reg64(stream, ASMKIT_X64_R12) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_label_attach(stream, done) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_ret_near(stream) == ASMKIT_STATUS_OK);
+
+    /* the errors are returned without emitting anything. */
+    CHECK(asmkit_x64_push_reg64(stream, 16) == ASMKIT_STATUS_INVALID_REGISTER);
+    CHECK(strcmp(asmkit_last_error(), "no 64-bit register is numbered 16") == 0);
+    CHECK(asmkit_x64_jmp_label(stream, 2) == ASMKIT_STATUS_INVALID_LABEL);
+    CHECK(asmkit_x64_ret_near(NULL) == ASMKIT_STATUS_NULL_POINTER);
+
+    asmkit_product *product;
+    CHECK(asmkit_stream_finish(stream, &product) == ASMKIT_STATUS_OK);
+
+    static const uint8_t expected[] = {
+        0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, /* movabs rax, 0x1122334455667788 */
+        0xe8, 0x05, 0x00, 0x00, 0x00,                               /* call add */
+        0xe9, 0x0a, 0x00, 0x00, 0x00,                               /* jmp done */
+        0x41, 0x54,                                                 /* add: push r12 */
+        0x49, 0x89, 0xfc,                                           /* mov r12, rdi */
+        0x4c, 0x01, 0xe0,                                           /* add rax, r12 */
+        0x41, 0x5c,                                                 /* pop r12 */
+        0xc3,                                                       /* done: ret */
+    };
+
+    size_t len;
+    const uint8_t *bytes = asmkit_product_bytes(product, &len);
+    CHECK(len == sizeof(expected));
+    CHECK(memcmp(bytes, expected, len) == 0);
+    asmkit_product_free(product);
+
+    /* a label used but never attached fails to finish, rather than aborting. */
+    stream = asmkit_x64_stream_new();
+    CHECK(asmkit_x64_label_create(stream, &done) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_x64_jmp_label(stream, done) == ASMKIT_STATUS_OK);
+    CHECK(asmkit_stream_finish(stream, &product) == ASMKIT_STATUS_PANIC);
+    CHECK(product == NULL);
+    CHECK(strstr(asmkit_last_error(), "never attached") != NULL);
+
+    asmkit_x64_stream_free(asmkit_x64_stream_new());
+    asmkit_x64_stream_free(NULL);
+    asmkit_product_free(NULL);
+    return 0;
+}
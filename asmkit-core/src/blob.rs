@@ -0,0 +1,588 @@
+//! Blobs, a container carrying a product between processes, to be loaded without an object file or a linker.
+//!
+//! A blob holds the code of a [`Product`], the symbols it exports, the symbols it imports with the relocation sites referring to each of them,
+//! and the relocations against its load address.  [`Product::write_blob`] writes one, [`Blob::parse`] reads it back, and [`Blob::load`] maps it
+//! into executable memory, resolving its imports through a callback.  The call stubs, patch points, source maps and other metadata of the
+//! product aren't carried, nor the binding of its definitions.
+//!
+//! # Format
+//! Every integer is little endian, and every string is a `u32` length followed by that many bytes of UTF-8.
+//!
+//! | Field       | Contents                                                                                                                |
+//! |-------------|-------------------------------------------------------------------------------------------------------------------------|
+//! | magic       | the bytes [`MAGIC`], `AKBL`                                                                                             |
+//! | version     | a `u16`, [`VERSION`]                                                                                                    |
+//! | target      | the target triple, a string                                                                                             |
+//! | sections    | a `u32` count, then for each section a `u8` [`SectionKind`], a `u64` offset and a `u64` size                            |
+//! | exports     | a `u32` count, then for each export its name and a `u64` offset                                                         |
+//! | imports     | a `u32` count, then for each import its name, a `u32` count of sites and for each site a `u64` offset, a `u8` kind and  |
+//! |             | an `i64` addend                                                                                                         |
+//! | relocations | a `u32` count, then for each relocation a `u64` offset, a `u8` kind, an `i64` addend and a `u8` target: `0` for the     |
+//! |             | load address, or `1` followed by a `u64` absolute address                                                               |
+//! | payload     | a `u64` length, then the bytes of the code                                                                              |
+//!
+//! The kinds of the relocations are numbered `Abs32` (0), `Abs64` (1), `Rel32` (2), `GotTpOff` (3), `Abs16` (4), `GotPcRel` (5),
+//! `RexGotPcRelX` (6) and `Branch26` (7).  The code section always starts at offset 0 and spans the payload; the uninitialized data section,
+//! present if the product reserves any, starts at [`Product::bss_offset`] and takes no bytes of the payload.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{blob::{Blob, BlobError}, entity::EntityList, entity::Symbol, reloc::{Relocation, RelocationKind, RelocationTarget}, target::Target, Product};
+//!
+//! let mut symbols = EntityList::new();
+//! let puts = symbols.push(Symbol { name: "puts".to_string() });
+//! let mut product = Product::with_relocations(vec![0xe8, 0, 0, 0, 0, 0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0xc3], vec![
+//!     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 }, // call puts
+//!     Relocation { offset: 7, kind: RelocationKind::Abs64, target: RelocationTarget::Base, addend: 15 },         // movabs rax, offset ret
+//! ], symbols);
+//! product.define("main", 0);
+//!
+//! let bytes = product.write_blob(&Target::parse("x86_64-unknown-linux-gnu").unwrap());
+//! let blob = Blob::parse(&bytes).unwrap();
+//! assert_eq!(blob.triple, "x86_64-unknown-linux-gnu");
+//! assert_eq!(blob.exports[0].name, "main");
+//! assert_eq!(blob.imports[0].name, "puts");
+//! assert_eq!(blob.imports[0].sites[0].offset, 1);
+//! assert_eq!(blob.relocations[0].target, RelocationTarget::Base);
+//!
+//! // the product read back writes the same blob.
+//! assert_eq!(blob.to_product().write_blob(&blob.target().unwrap()), bytes);
+//!
+//! assert_eq!(Blob::parse(&bytes[..bytes.len() - 1]), Err(BlobError::Truncated { offset: bytes.len() - 16 }));
+//! assert_eq!(Blob::parse(b"ELF\x7f"), Err(BlobError::BadMagic));
+//! ```
+
+use std::{error::Error, fmt};
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use std::{collections::HashMap, io};
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use crate::{exec::ExecutableBuffer, Architecture};
+use crate::{
+    bss::Bss,
+    entity::{EntityList, Symbol},
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    target::{Target, UnknownTarget},
+    Product,
+};
+
+/// The bytes starting every blob.
+pub const MAGIC: [u8; 4] = *b"AKBL";
+
+/// The version of the format written by [`Product::write_blob`], and the only one read by [`Blob::parse`].
+pub const VERSION: u16 = 1;
+
+/// The kind of a section of a blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// The code and the initialized data, held by the payload.
+    Code,
+
+    /// The uninitialized data, zeroed by the loader.
+    Bss,
+}
+
+/// A section of a blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlobSection {
+    /// The kind of the section.
+    pub kind: SectionKind,
+
+    /// The offset of the section from the load address.
+    pub offset: usize,
+
+    /// The size of the section.
+    pub size: usize,
+}
+
+/// A symbol exported by a blob.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlobExport {
+    /// The name of the symbol.
+    pub name: String,
+
+    /// The offset of the symbol.
+    pub offset: usize,
+}
+
+/// A field of a blob relocated against one of its imports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlobSite {
+    /// The offset of the relocated field.
+    pub offset: usize,
+
+    /// How the address of the import is written into the field.
+    pub kind: RelocationKind,
+
+    /// The constant added to the address of the import.
+    pub addend: i64,
+}
+
+/// A symbol imported by a blob, and the fields referring to it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlobImport {
+    /// The name of the symbol.
+    pub name: String,
+
+    /// The fields relocated against the symbol.
+    pub sites: Vec<BlobSite>,
+}
+
+/// A product in the container format of the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Blob {
+    /// The target triple the code was generated for.
+    pub triple: String,
+
+    /// The sections of the blob.
+    pub sections: Vec<BlobSection>,
+
+    /// The symbols exported by the blob.
+    pub exports: Vec<BlobExport>,
+
+    /// The symbols imported by the blob.
+    pub imports: Vec<BlobImport>,
+
+    /// The relocations against the load address or an absolute address, never against a symbol.
+    pub relocations: Vec<Relocation>,
+
+    /// The bytes of the code section.
+    pub payload: Vec<u8>,
+}
+
+/// An error while parsing a blob, see [`Blob::parse`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlobError {
+    /// The bytes don't start with [`MAGIC`].
+    BadMagic,
+
+    /// The version of the blob isn't [`VERSION`].
+    UnsupportedVersion(u16),
+
+    /// The bytes end within the field at the offset.
+    Truncated {
+        /// The offset of the field.
+        offset: usize,
+    },
+
+    /// The string at the offset isn't valid UTF-8.
+    InvalidString {
+        /// The offset of the string.
+        offset: usize,
+    },
+
+    /// The section kind, relocation kind or relocation target at the offset is unknown.
+    UnknownCode {
+        /// The offset of the code.
+        offset: usize,
+
+        /// The unknown code.
+        code: u8,
+    },
+
+    /// A relocated field at the offset lies past the end of the payload.
+    OutOfBounds {
+        /// The offset of the field.
+        offset: usize,
+    },
+
+    /// Bytes follow the payload.
+    TrailingBytes {
+        /// The offset of the first byte past the payload.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => f.write_str("not a blob"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported blob version {}", version),
+            Self::Truncated { offset } => write!(f, "blob truncated within the field at {:#x}", offset),
+            Self::InvalidString { offset } => write!(f, "string at {:#x} isn't valid UTF-8", offset),
+            Self::UnknownCode { offset, code } => write!(f, "unknown code {} at {:#x}", code, offset),
+            Self::OutOfBounds { offset } => write!(f, "relocated field at {:#x} is past the end of the payload", offset),
+            Self::TrailingBytes { offset } => write!(f, "trailing bytes at {:#x}", offset),
+        }
+    }
+}
+
+impl Error for BlobError {}
+
+impl Blob {
+    /// Creates the blob of `product`, generated for `target`.
+    pub fn from_product(product: &Product, target: &Target) -> Self {
+        let mut sections = vec![BlobSection { kind: SectionKind::Code, offset: 0, size: product.bytes().len() }];
+        if product.bss_size() != 0 {
+            sections.push(BlobSection { kind: SectionKind::Bss, offset: product.bss_offset(), size: product.bss_size() });
+        }
+
+        let exports = product.definitions().iter().map(|definition| BlobExport { name: definition.name.clone(), offset: definition.offset }).collect();
+
+        let mut imports: Vec<BlobImport> = Vec::new();
+        let mut relocations = Vec::new();
+        for relocation in product.unresolved_relocations() {
+            let RelocationTarget::Symbol(symbol) = relocation.target else {
+                relocations.push(*relocation);
+                continue;
+            };
+
+            let name = &product.symbol(symbol).name;
+            let site = BlobSite { offset: relocation.offset, kind: relocation.kind, addend: relocation.addend };
+            match imports.iter_mut().find(|import| import.name == *name) {
+                Some(import) => import.sites.push(site),
+                None => imports.push(BlobImport { name: name.clone(), sites: vec![site] }),
+            }
+        }
+
+        Self { triple: target.to_string(), sections, exports, imports, relocations, payload: product.bytes().to_vec() }
+    }
+
+    /// The target the code was generated for.
+    ///
+    /// # Errors
+    /// Returns [`UnknownTarget`] if the triple of the blob is unknown.
+    pub fn target(&self) -> Result<Target, UnknownTarget> {
+        Target::parse(&self.triple)
+    }
+
+    /// The size of the uninitialized data.
+    pub fn bss_size(&self) -> usize {
+        self.sections.iter().filter(|section| section.kind == SectionKind::Bss).map(|section| section.size).sum()
+    }
+
+    /// Writes the blob in the format of the [module](self) documentation.
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        write_str(&mut bytes, &self.triple);
+
+        bytes.extend((self.sections.len() as u32).to_le_bytes());
+        for section in &self.sections {
+            bytes.push(section.kind as u8);
+            bytes.extend((section.offset as u64).to_le_bytes());
+            bytes.extend((section.size as u64).to_le_bytes());
+        }
+
+        bytes.extend((self.exports.len() as u32).to_le_bytes());
+        for export in &self.exports {
+            write_str(&mut bytes, &export.name);
+            bytes.extend((export.offset as u64).to_le_bytes());
+        }
+
+        bytes.extend((self.imports.len() as u32).to_le_bytes());
+        for import in &self.imports {
+            write_str(&mut bytes, &import.name);
+            bytes.extend((import.sites.len() as u32).to_le_bytes());
+            for site in &import.sites {
+                bytes.extend((site.offset as u64).to_le_bytes());
+                bytes.push(site.kind.code());
+                bytes.extend(site.addend.to_le_bytes());
+            }
+        }
+
+        bytes.extend((self.relocations.len() as u32).to_le_bytes());
+        for relocation in &self.relocations {
+            bytes.extend((relocation.offset as u64).to_le_bytes());
+            bytes.push(relocation.kind.code());
+            bytes.extend(relocation.addend.to_le_bytes());
+            match relocation.target {
+                RelocationTarget::Base => bytes.push(0),
+                RelocationTarget::Absolute(address) => {
+                    bytes.push(1);
+                    bytes.extend(address.to_le_bytes());
+                },
+                RelocationTarget::Symbol(_) => panic!("relocations against symbols are imports"),
+            }
+        }
+
+        bytes.extend((self.payload.len() as u64).to_le_bytes());
+        bytes.extend(&self.payload);
+        bytes
+    }
+
+    /// Parses a blob written by [`Blob::write`].
+    ///
+    /// # Errors
+    /// Returns a [`BlobError`] if the bytes aren't a well-formed blob of [`VERSION`], or if a relocated field lies past the end of the payload.
+    pub fn parse(bytes: &[u8]) -> Result<Self, BlobError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(BlobError::BadMagic);
+        }
+
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(BlobError::UnsupportedVersion(version));
+        }
+
+        let triple = reader.str()?;
+
+        let mut sections = Vec::new();
+        for _ in 0..reader.u32()? {
+            let offset = reader.offset;
+            let kind = match reader.u8()? {
+                0 => SectionKind::Code,
+                1 => SectionKind::Bss,
+                code => return Err(BlobError::UnknownCode { offset, code }),
+            };
+
+            sections.push(BlobSection { kind, offset: reader.u64()? as usize, size: reader.u64()? as usize });
+        }
+
+        let mut exports = Vec::new();
+        for _ in 0..reader.u32()? {
+            exports.push(BlobExport { name: reader.str()?, offset: reader.u64()? as usize });
+        }
+
+        let mut imports = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?;
+            let mut sites = Vec::new();
+            for _ in 0..reader.u32()? {
+                sites.push(BlobSite { offset: reader.u64()? as usize, kind: reader.kind()?, addend: reader.i64()? });
+            }
+
+            imports.push(BlobImport { name, sites });
+        }
+
+        let mut relocations = Vec::new();
+        for _ in 0..reader.u32()? {
+            let (offset, kind, addend) = (reader.u64()? as usize, reader.kind()?, reader.i64()?);
+            let code_offset = reader.offset;
+            let target = match reader.u8()? {
+                0 => RelocationTarget::Base,
+                1 => RelocationTarget::Absolute(reader.u64()?),
+                code => return Err(BlobError::UnknownCode { offset: code_offset, code }),
+            };
+
+            relocations.push(Relocation { offset, kind, target, addend });
+        }
+
+        let len = reader.u64()? as usize;
+        let payload = reader.take(len)?.to_vec();
+        if reader.offset != bytes.len() {
+            return Err(BlobError::TrailingBytes { offset: reader.offset });
+        }
+
+        let fields = imports.iter().flat_map(|import: &BlobImport| import.sites.iter().map(|site| (site.offset, site.kind)));
+        let fields = fields.chain(relocations.iter().map(|relocation| (relocation.offset, relocation.kind)));
+        for (offset, kind) in fields {
+            if offset.checked_add(kind.size()).is_none_or(|end| end > payload.len()) {
+                return Err(BlobError::OutOfBounds { offset });
+            }
+        }
+
+        Ok(Self { triple, sections, exports, imports, relocations, payload })
+    }
+
+    /// Turns the blob back into a product, its imports becoming the external symbols of its relocations.
+    pub fn to_product(&self) -> Product {
+        let mut symbols = EntityList::new();
+        let mut relocations = self.relocations.clone();
+        for import in &self.imports {
+            let symbol = symbols.push(Symbol { name: import.name.clone() });
+            relocations.extend(import.sites.iter().map(|site| {
+                Relocation { offset: site.offset, kind: site.kind, target: RelocationTarget::Symbol(symbol), addend: site.addend }
+            }));
+        }
+
+        relocations.sort_by_key(|relocation| relocation.offset);
+
+        let mut product = Product::with_relocations(self.payload.clone(), relocations, symbols);
+        for export in &self.exports {
+            product.define(&export.name, export.offset);
+        }
+
+        product.set_bss(Bss::with_size(self.bss_size()));
+        product
+    }
+}
+
+/// Writes the length of `value` and its bytes.
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend((value.len() as u32).to_le_bytes());
+    bytes.extend(value.as_bytes());
+}
+
+/// Reads the fields of a blob.
+struct Reader<'a> {
+    /// The bytes of the blob.
+    bytes: &'a [u8],
+
+    /// The offset of the next field.
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next `len` bytes.
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BlobError> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(BlobError::Truncated { offset: self.offset })?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, BlobError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BlobError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, BlobError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, BlobError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, BlobError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a length and a string of that many bytes.
+    fn str(&mut self) -> Result<String, BlobError> {
+        let len = self.u32()? as usize;
+        let offset = self.offset;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| BlobError::InvalidString { offset })
+    }
+
+    /// Reads the code of a relocation kind.
+    fn kind(&mut self) -> Result<RelocationKind, BlobError> {
+        let offset = self.offset;
+        let code = self.u8()?;
+        RelocationKind::from_code(code).ok_or(BlobError::UnknownCode { offset, code })
+    }
+}
+
+/// An error while loading a blob, see [`Blob::load`].
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[derive(Debug)]
+pub enum LoadError {
+    /// The triple of the blob is unknown.
+    UnknownTarget(UnknownTarget),
+
+    /// The blob was generated for another architecture than the host's.
+    WrongArchitecture(Architecture),
+
+    /// The memory couldn't be mapped or protected.
+    Io(io::Error),
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTarget(error) => error.fmt(f),
+            Self::WrongArchitecture(architecture) => write!(f, "blob generated for {}, not for the host", architecture),
+            Self::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::UnknownTarget(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::WrongArchitecture(_) => None,
+        }
+    }
+}
+
+/// A blob loaded into executable memory by [`Blob::load`], with the addresses of its exports.
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[derive(Debug)]
+pub struct LoadedBlob {
+    /// The executable memory holding the code.
+    buffer: ExecutableBuffer,
+
+    /// The offsets of the exports, by name.
+    exports: HashMap<String, usize>,
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+impl LoadedBlob {
+    /// The executable memory holding the code.
+    pub fn buffer(&self) -> &ExecutableBuffer {
+        &self.buffer
+    }
+
+    /// The executable memory holding the code, to patch it.
+    pub fn buffer_mut(&mut self) -> &mut ExecutableBuffer {
+        &mut self.buffer
+    }
+
+    /// The address of the export `name`, if the blob exports it.
+    pub fn export(&self, name: &str) -> Option<*const u8> {
+        // SAFETY: the exports lie within the code, see `Blob::from_product`.
+        self.exports.get(name).map(|offset| unsafe { self.buffer.as_ptr().add(*offset) })
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+impl Blob {
+    /// Loads the blob into executable memory as [`ExecutableBuffer::new`] does, resolving the address of each import by name through `resolve`.
+    ///
+    /// # Errors
+    /// Returns [`LoadError::UnknownTarget`] or [`LoadError::WrongArchitecture`] if the blob wasn't generated for the architecture of the host,
+    /// and [`LoadError::Io`] if the memory can't be mapped or protected.
+    ///
+    /// # Panics
+    /// Panics if a relocated value doesn't fit in its field, as [`Product::relocate`] does.
+    ///
+    /// # Example
+    /// A blob calling an import resolved to a Rust function, written, parsed and loaded in the same process:
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_core::{blob::Blob, entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, target::Target, Product};
+    ///
+    /// extern "C" fn double(value: u64) -> u64 {
+    ///     value * 2
+    /// }
+    ///
+    /// // fn(u64) -> u64, returning double(value) + 1.
+    /// let mut symbols = EntityList::new();
+    /// let import = symbols.push(Symbol { name: "double".to_string() });
+    /// let mut product = Product::with_relocations(vec![
+    ///     0x48, 0x83, 0xec, 0x08,                                     // sub rsp, 8
+    ///     0x48, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // movabs rax, double
+    ///     0xff, 0xd0,                                                 // call rax
+    ///     0x48, 0x83, 0xc0, 0x01,                                     // add rax, 1
+    ///     0x48, 0x83, 0xc4, 0x08,                                     // add rsp, 8
+    ///     0xc3,                                                       // ret
+    /// ], vec![
+    ///     Relocation { offset: 6, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(import), addend: 0 },
+    /// ], symbols);
+    /// product.define("double_plus_one", 0);
+    ///
+    /// let bytes = product.write_blob(&Target::host());
+    /// let blob = Blob::parse(&bytes).unwrap();
+    /// let loaded = blob.load(|name| match name {
+    ///     "double" => double as usize as u64,
+    ///     _ => unreachable!(),
+    /// }).unwrap();
+    ///
+    /// let function: extern "C" fn(u64) -> u64 = unsafe { std::mem::transmute(loaded.export("double_plus_one").unwrap()) };
+    /// assert_eq!(function(20), 41);
+    /// assert!(loaded.export("main").is_none());
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    pub fn load(&self, resolve: impl FnMut(&str) -> u64) -> Result<LoadedBlob, LoadError> {
+        let target = self.target().map_err(LoadError::UnknownTarget)?;
+        if target.architecture != Target::host().architecture {
+            return Err(LoadError::WrongArchitecture(target.architecture));
+        }
+
+        let buffer = ExecutableBuffer::new(self.to_product(), resolve).map_err(LoadError::Io)?;
+        let exports = self.exports.iter().map(|export| (export.name.clone(), export.offset)).collect();
+        Ok(LoadedBlob { buffer, exports })
+    }
+}
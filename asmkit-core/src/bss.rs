@@ -0,0 +1,97 @@
+//! Uninitialized data, reserved past the bytes of a product without occupying any of them.
+//!
+//! The uninitialized data of a product starts at [`Product::bss_offset`](crate::Product::bss_offset), the end of its bytes rounded up to
+//! [`BSS_ALIGN`], so it never shares a page with the code and can be mapped writable while the code is executable.  The code addresses it through
+//! labels, resolved against that offset when the instruction stream is finished; the loader only has to reserve and zero
+//! [`Product::bss_size`](crate::Product::bss_size) bytes there, as [`ExecutableBuffer`](crate::exec::ExecutableBuffer) does.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{bss::{Bss, BssLabel}, entity::{EntityRef, LabelRef}};
+//!
+//! let mut bss = Bss::new();
+//! assert_eq!(bss.reserve(LabelRef::new(0), 3, 1), 0);
+//! assert_eq!(bss.reserve(LabelRef::new(1), 16, 8), 8);
+//! assert_eq!(bss.size(), 24);
+//! assert_eq!(bss.labels()[1], BssLabel { label: LabelRef::new(1), offset: 8, size: 16 });
+//! ```
+
+use crate::entity::LabelRef;
+
+/// The alignment of the start of the uninitialized data, 64 KiB, the largest page size of the hosts supported by
+/// [`ExecutableBuffer`](crate::exec::ExecutableBuffer).
+pub const BSS_ALIGN: usize = 0x10000;
+
+/// The offset of the uninitialized data of a product holding `len` bytes.
+#[inline(always)]
+pub fn bss_offset(len: usize) -> usize {
+    (len + BSS_ALIGN - 1) & !(BSS_ALIGN - 1)
+}
+
+/// A label of the uninitialized data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BssLabel {
+    /// The label.
+    pub label: LabelRef,
+
+    /// The offset of the label from the start of the uninitialized data.
+    pub offset: usize,
+
+    /// The number of bytes reserved at the label.
+    pub size: usize,
+}
+
+/// The uninitialized data reserved by an instruction stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bss {
+    /// The reserved labels, in the order they were reserved.
+    labels: Vec<BssLabel>,
+
+    /// The size of the uninitialized data.
+    size: usize,
+}
+
+impl Bss {
+    /// Creates new, empty uninitialized data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates uninitialized data of `size` bytes without labels, such as read back from a blob.
+    pub fn with_size(size: usize) -> Self {
+        Self { labels: Vec::new(), size }
+    }
+
+    /// Reserves `size` bytes aligned to `align` at `label`, and returns their offset from the start of the uninitialized data.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or is larger than [`BSS_ALIGN`].
+    pub fn reserve(&mut self, label: LabelRef, size: usize, align: usize) -> usize {
+        assert!(align.is_power_of_two() && align <= BSS_ALIGN, "bss alignment must be a power of two no greater than {:#x}", BSS_ALIGN);
+
+        let offset = (self.size + align - 1) & !(align - 1);
+        self.labels.push(BssLabel { label, offset, size });
+        self.size = offset + size;
+        offset
+    }
+
+    /// The reserved labels, in the order they were reserved.
+    pub fn labels(&self) -> &[BssLabel] {
+        &self.labels
+    }
+
+    /// The size of the uninitialized data.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns true if no data is reserved.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns true if `label` is a label of the uninitialized data.
+    pub fn contains(&self, label: LabelRef) -> bool {
+        self.labels.iter().any(|reserved| reserved.label == label)
+    }
+}
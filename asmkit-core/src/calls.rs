@@ -0,0 +1,63 @@
+//! The call sites of a product, for tooling which patches or instruments the calls to a function without a disassembler.
+//!
+//! The instruction streams record a [`CallSite`] for every call to a label or to a symbol, directly or through a pointer in memory, with
+//! [`Product::add_call_site`](crate::Product::add_call_site).  The calls through a register have no known target, and aren't recorded.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{
+//!     calls::{CallKind, CallSite, CallSiteTarget},
+//!     entity::{EntityList, Symbol},
+//!     Product,
+//! };
+//!
+//! let mut symbols = EntityList::new();
+//! let puts = symbols.push(Symbol { name: "puts".to_string() });
+//!
+//! let mut product = Product::with_relocations(vec![0; 11], Vec::new(), symbols);
+//! product.add_call_site(CallSite { offset: 5, len: 6, target: CallSiteTarget::Symbol(puts), kind: CallKind::Indirect });
+//! product.add_call_site(CallSite { offset: 0, len: 5, target: CallSiteTarget::Symbol(puts), kind: CallKind::Direct });
+//!
+//! // the call sites are ordered by offset.
+//! assert_eq!(product.call_sites().iter().map(|site| site.offset).collect::<Vec<_>>(), [0, 5]);
+//! assert_eq!(product.call_sites_to("puts").count(), 2);
+//! assert_eq!(product.call_sites_to("exit").count(), 0);
+//! ```
+
+use crate::entity::{LabelRef, SymRef};
+
+/// How a call site reaches its target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    /// A call with a displacement to the target, such as the x86-64 `call rel32`.
+    Direct,
+
+    /// A call through a pointer to the target stored in memory, such as a global offset table entry.
+    Indirect,
+}
+
+/// The target of a call site: the function called for a [`CallKind::Direct`] call, and the pointer to it for a [`CallKind::Indirect`] call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallSiteTarget {
+    /// A label of the instruction stream which emitted the call.
+    Label(LabelRef),
+
+    /// A symbol of the product.
+    Symbol(SymRef),
+}
+
+/// A call instruction of a product.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallSite {
+    /// The offset of the instruction.
+    pub offset: usize,
+
+    /// The length of the instruction, in bytes.
+    pub len: usize,
+
+    /// The target of the call.
+    pub target: CallSiteTarget,
+
+    /// How the call reaches its target.
+    pub kind: CallKind,
+}
@@ -0,0 +1,84 @@
+//! Comparing products, see [`Product::diff`](crate::Product::diff).
+
+use crate::reloc::{Relocation, RelocationTarget};
+
+/// A relocation present in only one of two products, or differing between them, at the same offset.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RelocationDiff {
+    /// The offset of the relocated field.
+    pub offset: usize,
+
+    /// The relocation of the first product, if any.
+    pub left: Option<Relocation>,
+
+    /// The relocation of the second product, if any.
+    pub right: Option<Relocation>,
+}
+
+/// The differences between two products, returned by [`Product::diff`](crate::Product::diff).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ProductDiff {
+    /// The offset of the first differing byte, or the length of the shorter product if one is a prefix of the other.
+    pub first_byte: Option<usize>,
+
+    /// The lengths of both products, if they differ.
+    pub lengths: Option<(usize, usize)>,
+
+    /// The differing relocations, by offset.  Symbol targets are compared by name.
+    pub relocations: Vec<RelocationDiff>,
+}
+
+impl ProductDiff {
+    /// Returns true if the products are identical, in bytes and relocations.
+    pub fn is_empty(&self) -> bool {
+        self.first_byte.is_none() && self.relocations.is_empty()
+    }
+}
+
+/// A relocation of a product, with its symbol target replaced by the symbol name so relocations of different products can be compared.
+pub(crate) type NamedRelocation<'a> = (Relocation, Option<&'a str>);
+
+/// Compares two lists of relocations sorted by offset, pairing them by offset.
+pub(crate) fn diff_relocations(left: &[NamedRelocation], right: &[NamedRelocation]) -> Vec<RelocationDiff> {
+    let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+    let mut diffs = Vec::new();
+
+    loop {
+        let diff = match (left.peek(), right.peek()) {
+            (None, None) => break,
+            (Some((l, _)), Some((r, _))) if l.offset < r.offset => RelocationDiff { offset: l.offset, left: Some(*l), right: None },
+            (Some((l, _)), Some((r, _))) if l.offset > r.offset => RelocationDiff { offset: r.offset, left: None, right: Some(*r) },
+            (Some((l, l_name)), Some((r, r_name))) => {
+                let same_target = match (l.target, r.target) {
+                    (RelocationTarget::Base, RelocationTarget::Base) => true,
+                    (RelocationTarget::Absolute(l_address), RelocationTarget::Absolute(r_address)) => l_address == r_address,
+                    (RelocationTarget::Symbol(_), RelocationTarget::Symbol(_)) => l_name == r_name,
+                    _ => false,
+                };
+
+                let (l, r) = (*l, *r);
+                left.next();
+                right.next();
+
+                if same_target && l.kind == r.kind && l.addend == r.addend {
+                    continue;
+                }
+
+                diffs.push(RelocationDiff { offset: l.offset, left: Some(l), right: Some(r) });
+                continue;
+            },
+            (Some((l, _)), None) => RelocationDiff { offset: l.offset, left: Some(*l), right: None },
+            (None, Some((r, _))) => RelocationDiff { offset: r.offset, left: None, right: Some(*r) },
+        };
+
+        if diff.left.is_some() {
+            left.next();
+        } else {
+            right.next();
+        }
+
+        diffs.push(diff);
+    }
+
+    diffs
+}
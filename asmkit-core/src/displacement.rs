@@ -0,0 +1,194 @@
+//! Displacements, the distances from the instructions to the labels they refer to, measured the way each instruction set encodes them.
+//!
+//! The instruction sets differ in two ways when measuring a displacement:
+//!
+//! - its unit: x86-64 counts bytes, Thumb and the RISC-V branches count halfwords, AArch64 counts words, and eBPF counts its 8-byte instruction
+//!   slots.  A target which isn't a whole number of units away can't be reached.
+//! - its bias, where it's measured from: the start of the instruction on AArch64 and RISC-V, or past it, from the end of the displacement field
+//!   which ends an x86-64 instruction, from the next slot on eBPF, or from the Thumb PC, reading 4 bytes ahead of the instruction.
+//!
+//! A [`DisplacementForm`] holds both along with the width of the field, and [`DisplacementForm::resolve`] turns the offsets of an instruction
+//! and of its target into the value of the field, checking it in the declared unit.  The instruction streams of every backend resolve their
+//! label uses through it.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::displacement::{DisplacementBias, DisplacementError, DisplacementForm, DisplacementUnit};
+//!
+//! // an x86-64 `jmp rel8`, whose field is at offset 1.
+//! let rel8 = DisplacementForm::new(DisplacementUnit::Bytes, DisplacementBias::End(1), 8);
+//! assert_eq!(rel8.resolve(1, 0x20), Ok(0x1e));
+//! assert_eq!(rel8.resolve(1, 0x100), Err(DisplacementError::OutOfRange { value: 0xfe, bits: 8 }));
+//!
+//! // an AArch64 `b`, counting words from the branch.
+//! let branch26 = DisplacementForm::new(DisplacementUnit::Words, DisplacementBias::Start, 26);
+//! assert_eq!(branch26.resolve(0x10, 0), Ok(-4));
+//! assert_eq!(branch26.resolve(0x10, 2), Err(DisplacementError::Misaligned { displacement: -14, unit: DisplacementUnit::Words }));
+//!
+//! // an eBPF jump, counting slots from the next one.
+//! let jump = DisplacementForm::new(DisplacementUnit::Slots, DisplacementBias::End(8), 16);
+//! assert_eq!(jump.resolve(8, 16), Ok(0));
+//! assert_eq!(jump.resolve(8, 0), Ok(-2));
+//!
+//! // a Thumb `b`, counting halfwords from the PC.
+//! let branch = DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::End(4), 11);
+//! assert_eq!(branch.resolve(0, 4 + 2046), Ok(1023));
+//! assert_eq!(branch.resolve(0, 4 + 2048), Err(DisplacementError::OutOfRange { value: 1024, bits: 11 }));
+//! ```
+
+use std::{error::Error, fmt};
+
+/// The unit a displacement is counted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisplacementUnit {
+    /// Bytes, as on x86-64.
+    Bytes,
+
+    /// 2-byte halfwords, as the Thumb and RISC-V branches.
+    Halfwords,
+
+    /// 4-byte words, as the AArch64 branches.
+    Words,
+
+    /// 8-byte instruction slots, as the eBPF jumps.
+    Slots,
+}
+
+impl DisplacementUnit {
+    /// The size of the unit, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Bytes => 1,
+            Self::Halfwords => 2,
+            Self::Words => 4,
+            Self::Slots => 8,
+        }
+    }
+}
+
+/// Where a displacement is measured from, relative to the offset its use is recorded at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisplacementBias {
+    /// The recorded offset itself, the start of the instruction.
+    Start,
+
+    /// The given number of bytes past the recorded offset: the end of the instruction, or of the field ending it, which is where the next
+    /// instruction starts.
+    End(usize),
+}
+
+/// How the displacement field of an instruction is measured and how wide it is, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DisplacementForm {
+    /// The unit the displacement is counted in.
+    pub unit: DisplacementUnit,
+
+    /// Where the displacement is measured from.
+    pub bias: DisplacementBias,
+
+    /// The width of the field, in bits, holding a signed number of units.
+    pub bits: u32,
+}
+
+impl DisplacementForm {
+    /// Creates a new form of displacement field.
+    pub const fn new(unit: DisplacementUnit, bias: DisplacementBias, bits: u32) -> Self {
+        Self { unit, bias, bits }
+    }
+
+    /// The offset the displacement of a use recorded at `offset` is measured from.
+    pub fn origin(&self, offset: usize) -> usize {
+        match self.bias {
+            DisplacementBias::Start => offset,
+            DisplacementBias::End(len) => offset + len,
+        }
+    }
+
+    /// The value of the field of a use recorded at `offset`, in units, for a target at `target`.
+    ///
+    /// # Errors
+    /// Returns [`DisplacementError::Misaligned`] if the target isn't a whole number of units away, and [`DisplacementError::OutOfRange`] if the
+    /// value doesn't fit in the field.
+    ///
+    /// # Example
+    /// Every unit and bias, for a use at 0x1000:
+    /// ```
+    /// use asmkit_core::displacement::{DisplacementBias, DisplacementError, DisplacementForm, DisplacementUnit};
+    ///
+    /// let units = [DisplacementUnit::Bytes, DisplacementUnit::Halfwords, DisplacementUnit::Words, DisplacementUnit::Slots];
+    /// for unit in units {
+    ///     for (bias, origin) in [(DisplacementBias::Start, 0x1000), (DisplacementBias::End(8), 0x1008)] {
+    ///         let form = DisplacementForm::new(unit, bias, 8);
+    ///         let size = unit.size();
+    ///         assert_eq!(form.origin(0x1000), origin);
+    ///
+    ///         // the ends of the range, backward and forward.
+    ///         assert_eq!(form.resolve(0x1000, origin), Ok(0));
+    ///         assert_eq!(form.resolve(0x1000, origin - 0x80 * size), Ok(-0x80));
+    ///         assert_eq!(form.resolve(0x1000, origin + 0x7f * size), Ok(0x7f));
+    ///         assert_eq!(form.resolve(0x1000, origin - 0x81 * size), Err(DisplacementError::OutOfRange { value: -0x81, bits: 8 }));
+    ///         assert_eq!(form.resolve(0x1000, origin + 0x80 * size), Err(DisplacementError::OutOfRange { value: 0x80, bits: 8 }));
+    ///
+    ///         if size > 1 {
+    ///             assert_eq!(form.resolve(0x1000, origin + 1), Err(DisplacementError::Misaligned { displacement: 1, unit }));
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn resolve(&self, offset: usize, target: usize) -> Result<i64, DisplacementError> {
+        self.measure(target as i64 - self.origin(offset) as i64)
+    }
+
+    /// The value of the field for a `displacement` in bytes from the origin, in units.
+    ///
+    /// # Errors
+    /// Returns [`DisplacementError::Misaligned`] if the displacement isn't a whole number of units, and [`DisplacementError::OutOfRange`] if the
+    /// value doesn't fit in the field.
+    pub fn measure(&self, displacement: i64) -> Result<i64, DisplacementError> {
+        let size = self.unit.size() as i64;
+        if displacement % size != 0 {
+            return Err(DisplacementError::Misaligned { displacement, unit: self.unit });
+        }
+
+        let value = displacement / size;
+        let bound = 1i64 << (self.bits - 1);
+        if !(-bound..bound).contains(&value) {
+            return Err(DisplacementError::OutOfRange { value, bits: self.bits });
+        }
+
+        Ok(value)
+    }
+}
+
+/// An error while resolving a displacement, see [`DisplacementForm::resolve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplacementError {
+    /// The target isn't a whole number of units away.
+    Misaligned {
+        /// The displacement, in bytes.
+        displacement: i64,
+
+        /// The unit of the field.
+        unit: DisplacementUnit,
+    },
+
+    /// The value doesn't fit in the field.
+    OutOfRange {
+        /// The value, in units.
+        value: i64,
+
+        /// The width of the field, in bits.
+        bits: u32,
+    },
+}
+
+impl fmt::Display for DisplacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Misaligned { displacement, unit } => write!(f, "displacement {} is not a whole number of {:?}", displacement, unit),
+            Self::OutOfRange { value, bits } => write!(f, "displacement {} doesn't fit in {} bits", value, bits),
+        }
+    }
+}
+
+impl Error for DisplacementError {}
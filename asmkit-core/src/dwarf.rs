@@ -0,0 +1,200 @@
+//! DWARF debug info generated from the [`SourceMap`] of a product, so debuggers show the source lines of generated code.
+//!
+//! [`debug_line`] generates the `.debug_line` section of a product: a version 4 line number program with 64-bit addresses, holding a single
+//! sequence which covers the product.  [`write_object`](crate::write_object) carries it in the ELF objects of the products with a source map.
+
+use std::collections::BTreeSet;
+
+use crate::{source_map::SourceMap, Product};
+
+/// The smallest line advance of a special opcode.
+const LINE_BASE: i64 = -5;
+
+/// The number of line advances of the special opcodes.
+const LINE_RANGE: u64 = 14;
+
+/// The first special opcode, following the 12 standard opcodes.
+const OPCODE_BASE: u8 = 13;
+
+/// The number of operands of each standard opcode.
+const STANDARD_OPCODE_LENGTHS: [u8; 12] = [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+/// Writes `value` as an unsigned LEB128 number.
+fn write_uleb128(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Writes `value` as a signed LEB128 number.
+fn write_sleb128(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        // the sign bit of the last byte extends to the rest of the number.
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// The numbers of the files in the file table: the registered files and the files of the locations, in increasing order.
+fn file_ids(source_map: &SourceMap) -> Vec<u32> {
+    let mut file_ids = source_map.files().map(|(file_id, _)| file_id).collect::<BTreeSet<_>>();
+    file_ids.extend(source_map.entries().iter().filter_map(|entry| entry.location).map(|location| location.file_id));
+    file_ids.into_iter().collect()
+}
+
+/// The offset of the address of the sequence in a `.debug_line` section generated by [`debug_line`], which the object writers relocate
+/// against the code.
+pub(crate) fn address_offset(section: &[u8]) -> usize {
+    let header_length = u32::from_le_bytes(section[6..10].try_into().unwrap()) as usize;
+
+    // the address follows the `unit_length`, `version` and `header_length` fields, the header, and the opcode setting it.
+    10 + header_length + 3
+}
+
+/// Generates the `.debug_line` section of `product`, loaded at `address`.
+///
+/// The file table lists the files registered in the [`SourceMap`] of the product, and the files which are used without being registered, named
+/// by their number.  Each run of the source map becomes a row of the line table, and the runs without a location become rows of line 0, which
+/// DWARF reserves for code without a source line.  The sequence ends at the end of the product.
+///
+/// # Example
+/// ```
+/// use asmkit_core::{dwarf::debug_line, source_map::Location, Product};
+///
+/// let mut product = Product::new(vec![0x90; 64]);
+/// let map = product.source_map_mut();
+/// map.register_file(1, "main.src");
+/// map.record(0, Some(Location { file_id: 1, line: 3, col: 1 }));
+/// map.record(4, Some(Location { file_id: 1, line: 4, col: 1 }));
+/// map.record(8, Some(Location { file_id: 1, line: 2, col: 7 }));
+/// map.record(60, None);
+///
+/// let section = debug_line(&product, 0x1000);
+/// assert_eq!(section[..4], (section.len() as u32 - 4).to_le_bytes()); // unit_length
+/// assert_eq!(section[4..6], [0x04, 0x00]);                            // version
+/// assert_eq!(section[10..], [
+///     0x01, 0x01, 0x01, 0xfb, 0x0e, 0x0d,                   // minimum_instruction_length ..= opcode_base
+///     0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, // standard_opcode_lengths
+///     0x00,                                                 // include_directories
+///     b'm', b'a', b'i', b'n', b'.', b's', b'r', b'c', 0x00, 0x00, 0x00, 0x00, // file_names[1]
+///     0x00,
+///     0x00, 0x09, 0x02, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DW_LNE_set_address 0x1000
+///     0x05, 0x01,                                           // DW_LNS_set_column 1
+///     0x14,                                                 // line 3, address +0
+///     0x4b,                                                 // line 4, address +4
+///     0x05, 0x07,                                           // DW_LNS_set_column 7
+///     0x48,                                                 // line 2, address +4
+///     0x05, 0x00,                                           // DW_LNS_set_column 0
+///     0x03, 0x7e, 0x02, 0x34, 0x01,                         // line 0, address +52
+///     0x02, 0x04,                                           // DW_LNS_advance_pc 4
+///     0x00, 0x01, 0x01,                                     // DW_LNE_end_sequence
+/// ]);
+/// ```
+pub fn debug_line(product: &Product, address: u64) -> Vec<u8> {
+    let source_map = product.source_map();
+    let file_ids = file_ids(source_map);
+
+    // the header, past the `header_length` field.
+    let mut header = vec![1, 1, 1, LINE_BASE as u8, LINE_RANGE as u8, OPCODE_BASE];
+    header.extend_from_slice(&STANDARD_OPCODE_LENGTHS);
+    header.push(0); // no include directories
+
+    for file_id in &file_ids {
+        match source_map.file(*file_id) {
+            Some(name) => header.extend_from_slice(name.as_bytes()),
+            None => header.extend_from_slice(file_id.to_string().as_bytes()),
+        }
+
+        header.extend_from_slice(&[0, 0, 0, 0]); // the directory, modification time and length are unknown
+    }
+    header.push(0);
+
+    let mut program = vec![0, 9, DW_LNE_SET_ADDRESS];
+    program.extend_from_slice(&address.to_le_bytes());
+
+    // the registers of the state machine.
+    let (mut offset, mut file, mut line, mut column) = (0u64, 1u64, 1i64, 0u64);
+
+    for entry in source_map.entries().iter().filter(|entry| entry.offset < product.bytes().len()) {
+        let (entry_file, entry_line, entry_column) = match entry.location {
+            Some(location) => {
+                let index = file_ids.binary_search(&location.file_id).unwrap() as u64 + 1;
+                (index, location.line as i64, location.col as u64)
+            },
+            None => (file, 0, 0),
+        };
+
+        if entry_file != file {
+            program.push(DW_LNS_SET_FILE);
+            write_uleb128(&mut program, entry_file);
+            file = entry_file;
+        }
+
+        if entry_column != column {
+            program.push(DW_LNS_SET_COLUMN);
+            write_uleb128(&mut program, entry_column);
+            column = entry_column;
+        }
+
+        let address_advance = entry.offset as u64 - offset;
+        let line_advance = entry_line - line;
+        let special = (line_advance - LINE_BASE) as u64 + LINE_RANGE * address_advance + OPCODE_BASE as u64;
+
+        if (LINE_BASE..LINE_BASE + LINE_RANGE as i64).contains(&line_advance) && special <= u8::MAX as u64 {
+            program.push(special as u8);
+        } else {
+            if line_advance != 0 {
+                program.push(DW_LNS_ADVANCE_LINE);
+                write_sleb128(&mut program, line_advance);
+            }
+
+            if address_advance != 0 {
+                program.push(DW_LNS_ADVANCE_PC);
+                write_uleb128(&mut program, address_advance);
+            }
+
+            program.push(DW_LNS_COPY);
+        }
+
+        (offset, line) = (entry.offset as u64, entry_line);
+    }
+
+    let end = product.bytes().len() as u64;
+    if end != offset {
+        program.push(DW_LNS_ADVANCE_PC);
+        write_uleb128(&mut program, end - offset);
+    }
+    program.extend_from_slice(&[0, 1, DW_LNE_END_SEQUENCE]);
+
+    let unit_length = 2 + 4 + header.len() + program.len();
+    let mut section = Vec::with_capacity(4 + unit_length);
+    section.extend_from_slice(&(unit_length as u32).to_le_bytes());
+    section.extend_from_slice(&4u16.to_le_bytes());
+    section.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    section.extend_from_slice(&header);
+    section.extend_from_slice(&program);
+    section
+}
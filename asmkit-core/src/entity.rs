@@ -25,8 +25,8 @@ pub trait EntityRef: Clone + Copy + Debug + Hash + PartialEq + PartialOrd {
 /// A list of entities.
 /// 
 /// This implementation is essentially a glorified [`Vec<T>`], which returns a `Ref` when pushed to.  Of course, since it is indexed by [`EntityRef`]s, it can only index
-/// [`u32::MAX`] items.  It can hold more items than this (if there is enough space in memory), but the items past the limit will be un-indexable by [`EntityRef`]s.
-#[derive(Clone, PartialEq)]
+/// `u32::MAX + 1` items, and [`EntityList::push`] panics past the limit rather than handing out a truncated reference.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct EntityList<T, Ref: EntityRef> {
     /// The raw [`Vec`] that this list wraps.
     private: Vec<T>,
@@ -43,8 +43,25 @@ impl<T, Ref: EntityRef> EntityList<T, Ref> {
     }
 
     /// Pushes an item onto the end of the entity list, and returns a reference to it.
+    ///
+    /// # Panics
+    /// Panics if the list already holds `u32::MAX + 1` items, the most an entity reference can index.
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_core::entity::{EntityList, LabelRef};
+    ///
+    /// let mut list: EntityList<(), LabelRef> = EntityList::new();
+    ///
+    /// // zero-sized items take no memory, so the list can be filled without allocating.
+    /// unsafe { list.entities_mut().set_len(u32::MAX as usize + 1) };
+    /// list.push(());
+    /// ```
     pub fn push(&mut self, item: T) -> Ref {
-        let r = Ref::new(self.private.len());
+        let index = self.private.len();
+        assert!(index <= u32::MAX as usize, "entity list is full, entity references can't index more than u32::MAX + 1 entities");
+
+        let r = Ref::new(index);
         self.private.push(item);
         r
     }
@@ -64,6 +81,11 @@ impl<T, Ref: EntityRef> EntityList<T, Ref> {
         self.private.len()
     }
 
+    /// Returns true if the entity list contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.private.is_empty()
+    }
+
     /// Returns the entities which are currently stored in this entity list.
     pub fn entities(&self) -> &Vec<T> {
         &self.private
@@ -75,14 +97,96 @@ impl<T, Ref: EntityRef> EntityList<T, Ref> {
     }
 }
 
+impl<T, Ref: EntityRef> Default for EntityList<T, Ref> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Debug, Ref: EntityRef> Debug for EntityList<T, Ref> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.private.fmt(f)
     }
 }
 
+/// A map from entity references to values, holding a default value for the entities which were given none, such as the weights of the basic
+/// blocks of an instruction stream.
+///
+/// # Example
+/// ```
+/// use asmkit_core::entity::{BlockRef, EntityRef, SecondaryMap};
+///
+/// let mut weights: SecondaryMap<BlockRef, u32> = SecondaryMap::new();
+/// weights.set(BlockRef::new(2), 90);
+/// *weights.get_mut(BlockRef::new(0)) += 10;
+///
+/// assert_eq!(*weights.get(BlockRef::new(0)), 10);
+/// assert_eq!(*weights.get(BlockRef::new(1)), 0);
+/// assert_eq!(*weights.get(BlockRef::new(2)), 90);
+/// assert_eq!(*weights.get(BlockRef::new(7)), 0);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct SecondaryMap<Ref: EntityRef, T: Clone> {
+    /// The values, by reference, up to the last reference given one.
+    private: Vec<T>,
+
+    /// The value of the references given none.
+    default: T,
+
+    /// Phantom data so the compiler isn't mad about `Ref` being unused.
+    phantom: PhantomData<Ref>,
+}
+
+impl<Ref: EntityRef, T: Clone + Default> SecondaryMap<Ref, T> {
+    /// Creates a new empty map, whose values default to `T::default()`.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_default(T::default())
+    }
+}
+
+impl<Ref: EntityRef, T: Clone> SecondaryMap<Ref, T> {
+    /// Creates a new empty map, whose values default to `default`.
+    #[inline(always)]
+    pub fn with_default(default: T) -> Self {
+        Self { private: Vec::new(), default, phantom: PhantomData }
+    }
+
+    /// Returns the value of `item`, or the default value if it was given none.
+    pub fn get(&self, item: Ref) -> &T {
+        self.private.get(item.as_usize()).unwrap_or(&self.default)
+    }
+
+    /// Returns a mutable reference to the value of `item`, giving it the default value if it had none.
+    pub fn get_mut(&mut self, item: Ref) -> &mut T {
+        let index = item.as_usize();
+        if index >= self.private.len() {
+            self.private.resize(index + 1, self.default.clone());
+        }
+
+        &mut self.private[index]
+    }
+
+    /// Sets the value of `item` to `value`.
+    pub fn set(&mut self, item: Ref, value: T) {
+        *self.get_mut(item) = value;
+    }
+}
+
+impl<Ref: EntityRef, T: Clone + Default> Default for SecondaryMap<Ref, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Ref: EntityRef, T: Clone + Debug> Debug for SecondaryMap<Ref, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.private.fmt(f)
+    }
+}
+
 /// An opaque reference to a [`Label`] in an instruction stream.
-#[derive(Clone, Copy, Debug, Hash, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
 pub struct LabelRef(u32);
 
 impl EntityRef for LabelRef {
@@ -110,4 +214,119 @@ pub enum Label {
     
     /// A label attached to no index yet.
     Unattached,
-}
\ No newline at end of file
+
+    /// A label bound to an absolute address outside the stream, such as a function or a variable of the running program, referred to through
+    /// relocations against the address rather than the load address.
+    Absolute(u64),
+}
+
+impl Label {
+    /// The index the label is attached to, if it is attached.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Attached(offset) => Some(*offset),
+            Self::Unattached | Self::Absolute(_) => None,
+        }
+    }
+}
+
+/// An opaque reference to a [`Symbol`] in an instruction stream.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct SymRef(u32);
+
+impl EntityRef for SymRef {
+    #[inline(always)]
+    fn new(value: usize) -> Self {
+        Self(value as u32)
+    }
+
+    #[inline(always)]
+    fn as_u32(&self) -> u32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn as_usize(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An opaque reference to a basic block of an instruction stream.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct BlockRef(u32);
+
+impl EntityRef for BlockRef {
+    #[inline(always)]
+    fn new(value: usize) -> Self {
+        Self(value as u32)
+    }
+
+    #[inline(always)]
+    fn as_u32(&self) -> u32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn as_usize(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A symbol defined outside of an instruction stream, whose address is only known once the produced code is relocated.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// The name of the symbol.
+    pub name: String,
+}
+
+/// A symbol defined by a product, for other products to refer to when they are linked together.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymbolDefinition {
+    /// The name of the symbol.
+    pub name: String,
+
+    /// The offset of the symbol within the product.
+    pub offset: usize,
+
+    /// What the linker does with the other definitions of the symbol.
+    pub binding: Binding,
+
+    /// The COMDAT group the symbol is defined in, if any.
+    pub comdat: Option<Comdat>,
+}
+
+/// The binding of a symbol definition, deciding what the linker does with the other definitions of the symbol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// A definition which must be the only one of the symbol (`STB_GLOBAL`).
+    #[default]
+    Global,
+
+    /// A definition overridden by a global definition of the symbol, and deduplicated with its other weak definitions, the first being kept
+    /// (`STB_WEAK`).
+    Weak,
+}
+
+/// How the linker selects the definition kept among those of a [`Comdat`] group, as the selection of a COFF COMDAT section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SelectionKind {
+    /// Any definition is kept (`IMAGE_COMDAT_SELECT_ANY`), the only selection of the ELF groups.
+    Any,
+
+    /// Any definition is kept, and the definitions must have the same size (`IMAGE_COMDAT_SELECT_SAME_SIZE`).
+    SameSize,
+
+    /// Any definition is kept, and the definitions must have the same contents (`IMAGE_COMDAT_SELECT_EXACT_MATCH`).
+    ExactMatch,
+}
+
+/// A COMDAT group, the code generated in several objects of which, such as a template instantiation or an outlined helper, is kept once at
+/// link time.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Comdat {
+    /// The name of the group, usually the name of its symbol.
+    pub name: String,
+
+    /// How the definition kept is selected.
+    pub selection: SelectionKind,
+}
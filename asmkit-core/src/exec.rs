@@ -0,0 +1,803 @@
+//! Executable memory, loading products to run them and patching them in place.
+//!
+//! An [`ExecutableBuffer`] maps a [`Product`] into memory, relocated for the address it is mapped at, and keeps the code executable but not
+//! writable (W^X).  The uninitialized data of the product, see [`crate::bss`], is mapped zeroed past the code, writable but not executable.  [`ExecutableBuffer::patch`] rewrites the code afterwards, with the [`PatchStrategy`] picked when the buffer is created, and
+//! flushes the instruction cache of the patched range, which x86-64 keeps coherent on its own but AArch64 doesn't.
+//!
+//! The buffer is available on Linux, for x86-64 and AArch64 hosts, and on macOS.  Apple Silicon doesn't let a mapping change from writable to
+//! executable, so there the code is mapped with `MAP_JIT`, and the thread writing it lifts the write protection of the mapping for itself with
+//! `pthread_jit_write_protect_np` meanwhile; [`PatchStrategy::DualMapping`] isn't available on macOS.
+//!
+//! # Example
+//! A function returning 1, patched to return 2:
+//! ```
+//! # #[cfg(target_arch = "x86_64")]
+//! # fn main() {
+//! use asmkit_core::{exec::{ExecutableBuffer, PatchStrategy}, Product};
+//!
+//! for strategy in [PatchStrategy::Protect, PatchStrategy::DualMapping] {
+//!     let product = Product::new(vec![
+//!         0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+//!         0xc3,                         // ret
+//!     ]);
+//!
+//!     let mut buffer = ExecutableBuffer::with_strategy(product, strategy, |_| unreachable!()).unwrap();
+//!     let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+//!     assert_eq!(function(), 1);
+//!
+//!     buffer.patch(1, &[0x02]).unwrap(); // mov eax, 2
+//!     assert_eq!(function(), 2);
+//!     assert_eq!(buffer.bytes(), [0xb8, 0x02, 0x00, 0x00, 0x00, 0xc3]);
+//! }
+//! # }
+//! # #[cfg(not(target_arch = "x86_64"))]
+//! # fn main() {}
+//! ```
+
+use std::{error::Error, fmt, io, ptr};
+
+use crate::{
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    patch::{PatchField, PatchFieldError, PatchPoint, PatchPointRef, PATCH_POINT_SIZES},
+    source_map::{Location, SourceMap},
+    Product,
+};
+
+const PROT_READ: i32 = 0x1;
+const PROT_WRITE: i32 = 0x2;
+const PROT_EXEC: i32 = 0x4;
+const MAP_SHARED: i32 = 0x1;
+const MAP_PRIVATE: i32 = 0x2;
+const MAP_FIXED: i32 = 0x10;
+#[cfg(not(target_os = "macos"))]
+const MAP_ANONYMOUS: i32 = 0x20;
+#[cfg(target_os = "macos")]
+const MAP_ANONYMOUS: i32 = 0x1000;
+const MAP_FAILED: *mut u8 = usize::MAX as *mut u8;
+#[cfg(not(target_os = "macos"))]
+const SC_PAGESIZE: i32 = 30;
+#[cfg(target_os = "macos")]
+const SC_PAGESIZE: i32 = 29;
+
+/// The flag mapping the anonymous memory of the code, `MAP_JIT` on Apple Silicon.
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+const MAP_JIT: i32 = 0;
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+const MAP_JIT: i32 = 0x800;
+
+/// Whether the code is mapped with `MAP_JIT`, readable, writable and executable, and written to while the thread lifts its write protection,
+/// rather than by changing the protection of its pages.
+const JIT_WRITE_PROTECT: bool = MAP_JIT != 0;
+
+/// The size of the cache lines within which an 8-byte store is atomic.
+const CACHE_LINE: usize = 64;
+
+/// The `nop`s following the 5-byte call or jump written to a patch point, by length.
+const NOP_TAILS: [&[u8]; 4] = [
+    &[],
+    &[0x90],             // nop
+    &[0x66, 0x90],       // xchg ax, ax
+    &[0x0f, 0x1f, 0x00], // nop dword ptr [rax]
+];
+
+extern "C" {
+    fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+    fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+    fn munmap(address: *mut u8, length: usize) -> i32;
+    #[cfg(not(target_os = "macos"))]
+    fn memfd_create(name: *const u8, flags: u32) -> i32;
+    fn ftruncate(fd: i32, length: i64) -> i32;
+    fn close(fd: i32) -> i32;
+    fn sysconf(name: i32) -> i64;
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    fn pthread_jit_write_protect_np(enabled: i32);
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    fn sys_icache_invalidate(start: *const u8, len: usize);
+}
+
+/// Turns the return code of a system call into its error.
+fn check(code: i32) -> io::Result<()> {
+    if code < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Maps `size` bytes, shared with the file `fd` or anonymous if `fd` is -1.
+fn map(size: usize, protection: i32, fd: i32) -> io::Result<*mut u8> {
+    let flags = if fd == -1 { MAP_PRIVATE | MAP_ANONYMOUS | MAP_JIT } else { MAP_SHARED };
+
+    // SAFETY: a new mapping, at an address picked by the kernel, aliases no memory.
+    let memory = unsafe { mmap(ptr::null_mut(), size, protection, flags, fd, 0) };
+    if memory == MAP_FAILED {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(memory)
+    }
+}
+
+/// Creates an empty file in memory, mapped twice under [`PatchStrategy::DualMapping`].
+#[cfg(not(target_os = "macos"))]
+fn memory_file() -> io::Result<i32> {
+    // SAFETY: the name is nul-terminated.
+    let fd = unsafe { memfd_create(c"asmkit".as_ptr().cast(), 0) };
+    check(fd).map(|()| fd)
+}
+
+/// Creates an empty file in memory, mapped twice under [`PatchStrategy::DualMapping`].
+#[cfg(target_os = "macos")]
+fn memory_file() -> io::Result<i32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "macOS has no `memfd_create` to map the code twice"))
+}
+
+/// Lifts the write protection of the `MAP_JIT` mappings for the current thread, or restores it if `enabled`, see [`JIT_WRITE_PROTECT`].
+#[cfg_attr(not(all(target_os = "macos", target_arch = "aarch64")), allow(unused_variables))]
+fn jit_write_protect(enabled: bool) {
+    // SAFETY: the protection only changes for the current thread, which doesn't run the code of the mapping until it is restored.
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    unsafe {
+        pthread_jit_write_protect_np(enabled as i32);
+    }
+}
+
+/// Makes the instructions written to `len` bytes at `start` visible to the instruction fetches of every core.
+#[cfg_attr(not(target_arch = "aarch64"), allow(unused_variables))]
+fn flush_instruction_cache(start: *const u8, len: usize) {
+    // x86-64 snoops its instruction cache on stores, but AArch64 needs the data cache cleaned and the instruction cache invalidated to the point
+    // of unification, line by line, as `__clear_cache` does; macOS does it in `sys_icache_invalidate`.
+    #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+    unsafe {
+        sys_icache_invalidate(start, len);
+    }
+
+    #[cfg(all(target_arch = "aarch64", not(target_os = "macos")))]
+    unsafe {
+        use core::arch::asm;
+
+        let ctr: u64;
+        asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack, preserves_flags));
+
+        let data_line = 4usize << ((ctr >> 16) & 0xf);
+        let instruction_line = 4usize << (ctr & 0xf);
+        let (start, end) = (start as usize, start as usize + len);
+
+        let mut line = start & !(data_line - 1);
+        while line < end {
+            asm!("dc cvau, {}", in(reg) line, options(nostack, preserves_flags));
+            line += data_line;
+        }
+        asm!("dsb ish", options(nostack, preserves_flags));
+
+        let mut line = start & !(instruction_line - 1);
+        while line < end {
+            asm!("ic ivau, {}", in(reg) line, options(nostack, preserves_flags));
+            line += instruction_line;
+        }
+        asm!("dsb ish", "isb", options(nostack, preserves_flags));
+    }
+}
+
+/// How an [`ExecutableBuffer`] is written to once its code is executable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatchStrategy {
+    /// The pages holding the patched bytes are made writable and not executable while they are patched.  Threads running code on those pages in
+    /// the meantime fault, so the buffer must not be running while it is patched.
+    ///
+    /// On Apple Silicon the pages never change protection: they are mapped with `MAP_JIT`, and only the patching thread lifts their write
+    /// protection, so the other threads may keep running the buffer while it is patched.
+    Protect,
+
+    /// The code is mapped twice, once executable and once writable, and patched through the writable mapping; the executable mapping never
+    /// changes protection, so the buffer may keep running while it is patched.  The writable mapping never leaves the buffer.
+    ///
+    /// Not available on macOS, where loading a buffer with it returns an [`io::ErrorKind::Unsupported`] error.
+    DualMapping,
+}
+
+/// An error while patching an [`ExecutableBuffer`], see [`ExecutableBuffer::patch_rel32_at`], [`ExecutableBuffer::patch_field`] and
+/// [`ExecutableBuffer::patch_point_to_call`].
+#[derive(Debug)]
+pub enum PatchError {
+    /// No relocation of the product is recorded at the offset.
+    Unrecorded {
+        /// The offset of the field.
+        offset: usize,
+    },
+
+    /// The relocation recorded at the offset isn't a 32-bit displacement.
+    NotRelative {
+        /// The offset of the field.
+        offset: usize,
+
+        /// The kind of the recorded relocation.
+        kind: RelocationKind,
+    },
+
+    /// The new target lies past the end of the buffer.
+    TargetOutOfBounds {
+        /// The offset of the new target.
+        target: usize,
+
+        /// The length of the buffer.
+        len: usize,
+    },
+
+    /// The patchable field couldn't be rewritten, see [`ExecutableBuffer::patch_field`].
+    Field(PatchFieldError),
+
+    /// The product has no patch point with the id.
+    UnrecordedPatchPoint {
+        /// The id of the patch point.
+        id: u64,
+    },
+
+    /// The target of a patch point is out of the reach of a 32-bit displacement.
+    TargetOutOfReach {
+        /// The offset of the patch point.
+        offset: usize,
+
+        /// The address of the target.
+        target: usize,
+    },
+
+    /// The system refused to change the protection of the patched pages.
+    Io(io::Error),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unrecorded { offset } => write!(f, "no relocation is recorded at {:#x}", offset),
+            Self::NotRelative { offset, kind } => write!(f, "relocation at {:#x} is {:?}, not a 32-bit displacement", offset, kind),
+            Self::TargetOutOfBounds { target, len } => write!(f, "target {:#x} is out of the buffer of {:#x} bytes", target, len),
+            Self::Field(error) => write!(f, "{}", error),
+            Self::UnrecordedPatchPoint { id } => write!(f, "no patch point {} is recorded", id),
+            Self::TargetOutOfReach { offset, target } => write!(f, "target {:#x} is out of the reach of the patch point at {:#x}", target, offset),
+            Self::Io(error) => write!(f, "failed to patch the buffer: {}", error),
+        }
+    }
+}
+
+impl Error for PatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Field(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A product loaded into executable memory, see the [module documentation](self).
+#[derive(Debug)]
+pub struct ExecutableBuffer {
+    /// The executable mapping of the code.
+    code: *mut u8,
+
+    /// The writable mapping of the code under [`PatchStrategy::DualMapping`], and the executable one otherwise.
+    view: *mut u8,
+
+    /// The length of the code.
+    len: usize,
+
+    /// The length of the mappings, never zero.
+    size: usize,
+
+    /// The offset of the uninitialized data, mapped writable and not executable.
+    bss_offset: usize,
+
+    /// The size of the uninitialized data.
+    bss_size: usize,
+
+    /// How the code is patched.
+    strategy: PatchStrategy,
+
+    /// The relocations of the product, kept to validate [`ExecutableBuffer::patch_rel32_at`].
+    relocations: Vec<Relocation>,
+
+    /// The source map of the product, see [`ExecutableBuffer::lookup_location`].
+    source_map: SourceMap,
+
+    /// The patchable fields of the product, see [`ExecutableBuffer::patch_field`].
+    patch_fields: Vec<PatchField>,
+
+    /// The patch points of the product, with the `nop`s they were loaded with, see [`ExecutableBuffer::patch_point_to_call`].
+    patch_points: Vec<(PatchPoint, Vec<u8>)>,
+}
+
+// SAFETY: the buffer owns its mappings, and only writes to them through `&mut self`.
+unsafe impl Send for ExecutableBuffer {}
+unsafe impl Sync for ExecutableBuffer {}
+
+impl ExecutableBuffer {
+    /// Loads `product` into executable memory, patched with [`PatchStrategy::Protect`], applying its relocations for the address it is loaded at
+    /// and resolving the address of each external symbol by name through `resolve`.
+    ///
+    /// # Errors
+    /// Returns the error of the system if the memory can't be mapped or protected.
+    ///
+    /// # Panics
+    /// Panics if a relocated value doesn't fit in its field, as [`Product::relocate`] does.
+    pub fn new(product: Product, resolve: impl FnMut(&str) -> u64) -> io::Result<Self> {
+        Self::with_strategy(product, PatchStrategy::Protect, resolve)
+    }
+
+    /// Loads `product` into executable memory as [`ExecutableBuffer::new`] does, patched with `strategy`.
+    ///
+    /// # Errors
+    /// Returns the error of the system if the memory can't be mapped or protected.
+    ///
+    /// # Panics
+    /// Panics if a relocated value doesn't fit in its field, as [`Product::relocate`] does.
+    pub fn with_strategy(product: Product, strategy: PatchStrategy, resolve: impl FnMut(&str) -> u64) -> io::Result<Self> {
+        let len = product.bytes().len();
+        let size = product.image_size().max(1);
+        let (bss_offset, bss_size) = (product.bss_offset(), product.bss_size());
+
+        // the uninitialized data starts on its own page, so only the pages of the code are executable.
+        let code_size = if bss_size == 0 { size } else { bss_offset };
+        let relocations = product.relocations().to_vec();
+        let source_map = product.source_map().clone();
+        let patch_fields = product.patch_fields().to_vec();
+        let patch_points = product.patch_points().iter().map(|point| (*point, product.bytes()[point.range()].to_vec())).collect();
+
+        let buffer = match strategy {
+            PatchStrategy::Protect => {
+                let protection = if JIT_WRITE_PROTECT { PROT_READ | PROT_WRITE | PROT_EXEC } else { PROT_READ | PROT_WRITE };
+                let code = map(size, protection, -1)?;
+                Self { code, view: code, len, size, bss_offset, bss_size, strategy, relocations, source_map, patch_fields, patch_points }
+            },
+            PatchStrategy::DualMapping => {
+                let fd = memory_file()?;
+
+                // the file only lives as long as its mappings.
+                let mappings = check(unsafe { ftruncate(fd, size as i64) }).and_then(|()| {
+                    let view = map(size, PROT_READ | PROT_WRITE, fd)?;
+                    match map(size, PROT_READ | PROT_EXEC, fd) {
+                        Ok(code) => Ok((code, view)),
+                        Err(error) => {
+                            unsafe { munmap(view, size) };
+                            Err(error)
+                        },
+                    }
+                });
+                unsafe { close(fd) };
+
+                let (code, view) = mappings?;
+                Self { code, view, len, size, bss_offset, bss_size, strategy, relocations, source_map, patch_fields, patch_points }
+            },
+        };
+
+        let bytes = product.relocate(buffer.code as u64, resolve);
+
+        jit_write_protect(false);
+        // SAFETY: the writable view holds `size >= len` bytes, and isn't running yet.
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.view, len) };
+        jit_write_protect(true);
+
+        match strategy {
+            PatchStrategy::Protect if !JIT_WRITE_PROTECT => check(unsafe { mprotect(buffer.code, code_size, PROT_READ | PROT_EXEC) })?,
+            PatchStrategy::Protect if bss_size != 0 => {
+                // the uninitialized data is mapped anew over the tail of the `MAP_JIT` mapping, writable by every thread.
+                let flags = MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED;
+                if unsafe { mmap(buffer.code.add(bss_offset), size - bss_offset, PROT_READ | PROT_WRITE, flags, -1, 0) } == MAP_FAILED {
+                    return Err(io::Error::last_os_error());
+                }
+            },
+            _ if bss_size != 0 => {
+                check(unsafe { mprotect(buffer.code.add(bss_offset), size - bss_offset, PROT_READ | PROT_WRITE) })?;
+            },
+            _ => {},
+        }
+
+        flush_instruction_cache(buffer.code, len);
+        Ok(buffer)
+    }
+
+    /// The address of the code.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.code
+    }
+
+    /// The length of the code.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer holds no code.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The code, as it is currently patched.
+    pub fn bytes(&self) -> &[u8] {
+        // SAFETY: the code is readable, and only written to through `&mut self`.
+        unsafe { std::slice::from_raw_parts(self.code, self.len) }
+    }
+
+    /// The address of the uninitialized data of the product, zeroed when it is loaded and writable, see [`Product::bss_offset`].  Dangling if the
+    /// product has no uninitialized data.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_core::{bss::{Bss, BSS_ALIGN}, entity::{EntityRef, LabelRef}, exec::{ExecutableBuffer, PatchStrategy}, Product};
+    ///
+    /// for strategy in [PatchStrategy::Protect, PatchStrategy::DualMapping] {
+    ///     let mut bss = Bss::new();
+    ///     bss.reserve(LabelRef::new(0), 8, 8);
+    ///
+    ///     let mut product = Product::new(vec![
+    ///         0x48, 0x8b, 0x05, 0xf9, 0xff, 0x00, 0x00, // mov rax, qword ptr [rip + bss]
+    ///         0xc3,                                     // ret
+    ///     ]);
+    ///     product.set_bss(bss);
+    ///
+    ///     let buffer = ExecutableBuffer::with_strategy(product, strategy, |_| unreachable!()).unwrap();
+    ///     let function: extern "C" fn() -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///     assert_eq!(buffer.bss_ptr() as usize - buffer.as_ptr() as usize, BSS_ALIGN);
+    ///     assert_eq!(function(), 0);
+    ///
+    ///     unsafe { *buffer.bss_ptr().cast::<u64>() = 42 };
+    ///     assert_eq!(function(), 42);
+    /// }
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    pub fn bss_ptr(&self) -> *mut u8 {
+        self.code.wrapping_add(self.bss_offset)
+    }
+
+    /// The size of the uninitialized data of the product.
+    pub fn bss_size(&self) -> usize {
+        self.bss_size
+    }
+
+    /// How the buffer is patched.
+    pub fn strategy(&self) -> PatchStrategy {
+        self.strategy
+    }
+
+    /// The relocations of the loaded product, with the call and jump sites retargeted by [`ExecutableBuffer::patch_rel32_at`].
+    pub fn relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+
+    /// The source map of the loaded product.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// The patchable fields of the loaded product, see [`ExecutableBuffer::patch_field`].
+    pub fn patch_fields(&self) -> &[PatchField] {
+        &self.patch_fields
+    }
+
+    /// The patch points of the loaded product, see [`ExecutableBuffer::patch_point_to_call`].
+    pub fn patch_points(&self) -> impl Iterator<Item = &PatchPoint> {
+        self.patch_points.iter().map(|(point, _)| point)
+    }
+
+    /// The location of the source code the instruction at `code_ptr` was generated from, such as the faulting address of a signal, or `None` if
+    /// it has none or lies outside the buffer.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{exec::ExecutableBuffer, source_map::Location, Product};
+    ///
+    /// let call = Location { file_id: 1, line: 12, col: 4 };
+    /// let mut product = Product::new(vec![0x90, 0x90, 0x90, 0xc3]); // nop; nop; nop; ret
+    /// product.source_map_mut().register_file(1, "lib.rs");
+    /// product.source_map_mut().record(1, Some(call));
+    /// product.source_map_mut().record(3, None);
+    ///
+    /// let buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+    /// let start = buffer.as_ptr();
+    ///
+    /// assert_eq!(buffer.lookup_location(start), None);
+    /// assert_eq!(buffer.lookup_location(start.wrapping_add(2)), Some(call));
+    /// assert_eq!(buffer.lookup_location(start.wrapping_add(3)), None);
+    /// assert_eq!(buffer.lookup_location(start.wrapping_add(4)), None);
+    /// assert_eq!(buffer.source_map().file(call.file_id), Some("lib.rs"));
+    /// ```
+    pub fn lookup_location(&self, code_ptr: *const u8) -> Option<Location> {
+        let offset = (code_ptr as usize).checked_sub(self.code as usize).filter(|&offset| offset < self.len)?;
+        self.source_map.lookup(offset)
+    }
+
+    /// Overwrites the code at `offset` with `bytes`, and flushes the instruction cache of the patched range.
+    ///
+    /// Under [`PatchStrategy::Protect`], the pages holding the patched range are writable and not executable until the patch is written, so the
+    /// buffer must not be running meanwhile.
+    ///
+    /// # Errors
+    /// Returns the error of the system if the protection of the pages can't be changed.
+    ///
+    /// # Panics
+    /// Panics if the patched range overruns the buffer.
+    pub fn patch(&mut self, offset: usize, bytes: &[u8]) -> io::Result<()> {
+        assert!(
+            offset.checked_add(bytes.len()).is_some_and(|end| end <= self.len),
+            "patch of {} bytes at {:#x} overruns the buffer of {:#x} bytes", bytes.len(), offset, self.len,
+        );
+
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        match self.strategy {
+            PatchStrategy::Protect if JIT_WRITE_PROTECT => {
+                jit_write_protect(false);
+                // SAFETY: the mapping is only written to through `&mut self`, and only this thread may write to it meanwhile.
+                unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), self.code.add(offset), bytes.len()) };
+                jit_write_protect(true);
+            },
+            PatchStrategy::Protect => {
+                let page = unsafe { sysconf(SC_PAGESIZE) } as usize;
+                let start = offset & !(page - 1);
+                let end = (offset + bytes.len()).next_multiple_of(page).min(self.size);
+
+                // SAFETY: the pages belong to the mapping, which is only written to through `&mut self`.
+                unsafe {
+                    check(mprotect(self.code.add(start), end - start, PROT_READ | PROT_WRITE))?;
+                    ptr::copy_nonoverlapping(bytes.as_ptr(), self.code.add(offset), bytes.len());
+                    check(mprotect(self.code.add(start), end - start, PROT_READ | PROT_EXEC))?;
+                }
+            },
+            PatchStrategy::DualMapping => {
+                // SAFETY: the writable view is only written to through `&mut self`.
+                unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), self.view.add(offset), bytes.len()) };
+            },
+        }
+
+        flush_instruction_cache(self.code.wrapping_add(offset), bytes.len());
+        Ok(())
+    }
+
+    /// Retargets the call or jump site whose 32-bit displacement is recorded by a relocation of the product at `offset`, to `new_target_offset`
+    /// in the buffer.  The displacement is relative to the end of the field, as for the x86-64 `call rel32` and `jmp rel32`, and the recorded
+    /// relocation becomes a relocation against the buffer.
+    ///
+    /// Only the sites recorded as relocations can be retargeted: the branches to labels are resolved when the instruction stream is finished,
+    /// and leave no record.
+    ///
+    /// # Errors
+    /// Returns [`PatchError::Unrecorded`] if no relocation is recorded at `offset`, [`PatchError::NotRelative`] if it isn't a
+    /// [`RelocationKind::Rel32`], [`PatchError::TargetOutOfBounds`] if `new_target_offset` is past the end of the buffer, and [`PatchError::Io`]
+    /// if the buffer can't be patched.
+    ///
+    /// # Panics
+    /// Panics if the displacement to the new target doesn't fit in 32 bits, in buffers larger than 2 GiB.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_core::{
+    ///     exec::{ExecutableBuffer, PatchError},
+    ///     reloc::{Relocation, RelocationKind, RelocationTarget},
+    ///     entity::EntityList,
+    ///     Product,
+    /// };
+    ///
+    /// let product = Product::with_relocations(vec![
+    ///     0xe9, 0x00, 0x00, 0x00, 0x00, // jmp one
+    ///     0xb8, 0x01, 0x00, 0x00, 0x00, // one: mov eax, 1
+    ///     0xc3,                         // ret
+    ///     0xb8, 0x02, 0x00, 0x00, 0x00, // two: mov eax, 2
+    ///     0xc3,                         // ret
+    /// ], vec![
+    ///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Base, addend: 5 - 4 },
+    /// ], EntityList::new());
+    ///
+    /// let mut buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+    /// let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    /// assert_eq!(function(), 1);
+    ///
+    /// buffer.patch_rel32_at(1, 11).unwrap();
+    /// assert_eq!(function(), 2);
+    /// assert_eq!(buffer.relocations(), [Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Base, addend: 7 }]);
+    ///
+    /// assert!(matches!(buffer.patch_rel32_at(6, 11), Err(PatchError::Unrecorded { offset: 6 })));
+    /// assert!(matches!(buffer.patch_rel32_at(1, 64), Err(PatchError::TargetOutOfBounds { target: 64, len: 17 })));
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    pub fn patch_rel32_at(&mut self, offset: usize, new_target_offset: usize) -> Result<(), PatchError> {
+        let index = self.relocations.iter().position(|relocation| relocation.offset == offset).ok_or(PatchError::Unrecorded { offset })?;
+
+        let kind = self.relocations[index].kind;
+        if kind != RelocationKind::Rel32 {
+            return Err(PatchError::NotRelative { offset, kind });
+        }
+
+        if new_target_offset > self.len {
+            return Err(PatchError::TargetOutOfBounds { target: new_target_offset, len: self.len });
+        }
+
+        let displacement = new_target_offset as i64 - (offset as i64 + 4);
+        let displacement = i32::try_from(displacement).unwrap_or_else(|_| panic!("displacement {:#x} doesn't fit in 32 bits", displacement));
+
+        self.patch(offset, &displacement.to_le_bytes()).map_err(PatchError::Io)?;
+        self.relocations[index] = Relocation { offset, kind, target: RelocationTarget::Base, addend: new_target_offset as i64 - 4 };
+
+        Ok(())
+    }
+
+    /// Rewrites the patchable field of the product at `offset` with `value`, as [`Product::patch_field`] does, using all the space recorded for
+    /// the field.
+    ///
+    /// # Errors
+    /// Returns [`PatchError::Field`] if no patchable field is recorded at `offset` or if `value` doesn't fit in it, and [`PatchError::Io`] if the
+    /// buffer can't be patched.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_core::{exec::{ExecutableBuffer, PatchError}, patch::{PatchField, PatchFieldError, PatchFieldKind}, Product};
+    ///
+    /// let mut product = Product::new(vec![
+    ///     0x8b, 0x87, 0x08, 0x00, 0x00, 0x00, // mov eax, dword ptr [rdi + 8], with a 32-bit displacement
+    ///     0xc3,                               // ret
+    /// ]);
+    /// product.add_patch_field(PatchField { offset: 2, size: 4, kind: PatchFieldKind::Displacement });
+    ///
+    /// let mut buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+    /// let function: extern "C" fn(*const u32) -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///
+    /// let table = (0..0x10000).collect::<Vec<u32>>();
+    /// assert_eq!(function(table.as_ptr()), 2);
+    ///
+    /// // the field reaches far past an 8-bit displacement.
+    /// buffer.patch_field(2, 0xc000 * 4).unwrap();
+    /// assert_eq!(function(table.as_ptr()), 0xc000);
+    ///
+    /// assert!(matches!(buffer.patch_field(1, 0), Err(PatchError::Field(PatchFieldError::Unrecorded { offset: 1 }))));
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    pub fn patch_field(&mut self, offset: usize, value: i64) -> Result<(), PatchError> {
+        let field = *self.patch_fields.iter().find(|field| field.offset == offset).ok_or(PatchError::Field(PatchFieldError::Unrecorded { offset }))?;
+        let bytes = field.encode(value).map_err(PatchError::Field)?;
+        self.patch(offset, &bytes).map_err(PatchError::Io)
+    }
+
+    /// Rewrites the patch point `point` of the product with a call to `target`, followed by `nop`s up to the end of the gap, so the call returns
+    /// past the gap.  The call is written as x86-64 code.
+    ///
+    /// Under [`PatchStrategy::DualMapping`], the gap is rewritten with a single 8-byte store if it lies within a 64-byte cache line, as the
+    /// instruction streams can ensure, so threads running the buffer meanwhile execute either the `nop`s or the call; a gap spanning cache lines
+    /// is only patched safely while the buffer isn't running, as under [`PatchStrategy::Protect`].
+    ///
+    /// # Errors
+    /// Returns [`PatchError::UnrecordedPatchPoint`] if the product has no patch point with the id of `point`, [`PatchError::TargetOutOfReach`]
+    /// if `target` is out of the reach of a 32-bit displacement, and [`PatchError::Io`] if the buffer can't be patched.
+    ///
+    /// # Panics
+    /// Panics if the size of the patch point isn't within [`PATCH_POINT_SIZES`].
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_core::{exec::{ExecutableBuffer, PatchError, PatchStrategy}, patch::{PatchPoint, PatchPointRef}, Product};
+    ///
+    /// let mut product = Product::new(vec![
+    ///     0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+    ///     0x0f, 0x1f, 0x44, 0x00, 0x00, // patch point: nop dword ptr [rax + rax + 0]
+    ///     0xc3,                         // ret
+    ///     0xb8, 0x02, 0x00, 0x00, 0x00, // slow: mov eax, 2
+    ///     0xc3,                         // ret
+    /// ]);
+    /// product.add_patch_point(PatchPoint { id: 7, offset: 5, size: 5 });
+    ///
+    /// for strategy in [PatchStrategy::Protect, PatchStrategy::DualMapping] {
+    ///     let mut buffer = ExecutableBuffer::with_strategy(product.clone(), strategy, |_| unreachable!()).unwrap();
+    ///     let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///     let slow = buffer.as_ptr().wrapping_add(11);
+    ///     assert_eq!(function(), 1);
+    ///
+    ///     buffer.patch_point_to_call(PatchPointRef(7), slow).unwrap();
+    ///     assert_eq!(buffer.bytes()[5..10], [0xe8, 0x01, 0x00, 0x00, 0x00]); // call slow
+    ///     assert_eq!(function(), 2);
+    ///
+    ///     // the jump leaves the function through `slow`, which returns to the caller.
+    ///     buffer.patch_point_to_jump(PatchPointRef(7), slow).unwrap();
+    ///     assert_eq!(buffer.bytes()[5..10], [0xe9, 0x01, 0x00, 0x00, 0x00]); // jmp slow
+    ///     assert_eq!(function(), 2);
+    ///
+    ///     buffer.patch_point_restore_nops(PatchPointRef(7)).unwrap();
+    ///     assert_eq!(buffer.bytes()[5..10], [0x0f, 0x1f, 0x44, 0x00, 0x00]);
+    ///     assert_eq!(function(), 1);
+    ///
+    ///     assert!(matches!(buffer.patch_point_to_call(PatchPointRef(8), slow), Err(PatchError::UnrecordedPatchPoint { id: 8 })));
+    ///     assert!(matches!(buffer.patch_point_to_call(PatchPointRef(7), buffer.as_ptr().wrapping_add(1 << 40)), Err(PatchError::TargetOutOfReach { offset: 5, .. })));
+    /// }
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    pub fn patch_point_to_call(&mut self, point: PatchPointRef, target: *const u8) -> Result<(), PatchError> {
+        self.patch_point_to_branch(point, 0xe8, target)
+    }
+
+    /// Rewrites the patch point `point` of the product with a jump to `target`, as [`ExecutableBuffer::patch_point_to_call`] does with a call.
+    ///
+    /// # Errors
+    /// Returns the errors of [`ExecutableBuffer::patch_point_to_call`].
+    ///
+    /// # Panics
+    /// Panics if the size of the patch point isn't within [`PATCH_POINT_SIZES`].
+    pub fn patch_point_to_jump(&mut self, point: PatchPointRef, target: *const u8) -> Result<(), PatchError> {
+        self.patch_point_to_branch(point, 0xe9, target)
+    }
+
+    /// Rewrites the patch point `point` of the product with the `nop`s it was loaded with, as [`ExecutableBuffer::patch_point_to_call`] rewrites
+    /// it with a call.
+    ///
+    /// # Errors
+    /// Returns [`PatchError::UnrecordedPatchPoint`] if the product has no patch point with the id of `point`, and [`PatchError::Io`] if the
+    /// buffer can't be patched.
+    pub fn patch_point_restore_nops(&mut self, point: PatchPointRef) -> Result<(), PatchError> {
+        let (point, nops) = self.patch_points.iter().find(|(other, _)| other.id == point.0).ok_or(PatchError::UnrecordedPatchPoint { id: point.0 })?;
+        let (point, nops) = (*point, nops.clone());
+        self.patch_gap(point, &nops)
+    }
+
+    /// Rewrites the patch point `point` with the 5-byte branch `opcode` to `target`, followed by `nop`s.
+    fn patch_point_to_branch(&mut self, point: PatchPointRef, opcode: u8, target: *const u8) -> Result<(), PatchError> {
+        let (point, _) = *self.patch_points.iter().find(|(other, _)| other.id == point.0).ok_or(PatchError::UnrecordedPatchPoint { id: point.0 })?;
+        assert!(PATCH_POINT_SIZES.contains(&point.size), "patch point {} of {} bytes can't hold a branch", point.id, point.size);
+
+        let end = self.code as usize + point.offset + 5;
+        let displacement = i32::try_from(target as i64 - end as i64)
+            .map_err(|_| PatchError::TargetOutOfReach { offset: point.offset, target: target as usize })?;
+
+        let mut bytes = vec![opcode];
+        bytes.extend_from_slice(&displacement.to_le_bytes());
+        bytes.extend_from_slice(NOP_TAILS[point.size - 5]);
+        self.patch_gap(point, &bytes)
+    }
+
+    /// Overwrites the gap of `point` with `bytes`, with a single 8-byte store under [`PatchStrategy::DualMapping`] if the gap lies within a cache
+    /// line.
+    fn patch_gap(&mut self, point: PatchPoint, bytes: &[u8]) -> Result<(), PatchError> {
+        let line = point.offset & !(CACHE_LINE - 1);
+        if self.strategy == PatchStrategy::Protect || point.offset + point.size > line + CACHE_LINE {
+            return self.patch(point.offset, bytes).map_err(PatchError::Io);
+        }
+
+        // the 8 bytes around the gap stay within its cache line, which lies within the mapping as the pages are larger than it.
+        let window = point.offset.min(line + CACHE_LINE - 8);
+
+        // SAFETY: the writable view is only written to through `&mut self`; x86-64 writes the 8 bytes with a single store, atomic within a
+        // cache line.
+        unsafe {
+            let mut word = ptr::read_unaligned(self.view.add(window).cast::<[u8; 8]>());
+            word[point.offset - window..][..bytes.len()].copy_from_slice(bytes);
+            ptr::write_unaligned(self.view.add(window).cast::<u64>(), u64::from_ne_bytes(word));
+        }
+
+        flush_instruction_cache(self.code.wrapping_add(point.offset), point.size);
+        Ok(())
+    }
+}
+
+impl Drop for ExecutableBuffer {
+    fn drop(&mut self) {
+        // SAFETY: the mappings are owned by the buffer, which is no longer borrowed.
+        unsafe {
+            munmap(self.code, self.size);
+
+            if self.view != self.code {
+                munmap(self.view, self.size);
+            }
+        }
+    }
+}
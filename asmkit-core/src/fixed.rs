@@ -0,0 +1,587 @@
+//! An instruction stream writing into a buffer provided by the caller, without allocating, for the embedded targets.
+//!
+//! [`FixedBufStream`] writes the bytes it's given into a `&mut [u8]`, and keeps its labels and their uses in arrays of `LABELS` and `FIXUPS`
+//! entries, so writing to it never allocates.  A stream never grows: once the buffer, the labels or the fixups are exhausted, the stream stops
+//! writing and records a [`FixedBufError`], returned by [`FixedBufStream::finish_in_place`], which resolves the label uses in the buffer and
+//! returns the number of bytes written.  [`InstructionStream::finish`] copies them into a [`Product`], for the hosts.
+//!
+//! The words are written in little-endian byte order, as on every target so far, and a label field holds the displacement of its label from
+//! the end of the field, as the x86-64 branches do.  A `FixedBufStream` only takes the bytes written through [`InstructionStream`]: the
+//! encoders of a backend write into a buffer of the caller through a [`FixedBuf`], the [`CodeStorage`] their instruction stream is generic
+//! over, such as with `x86_64InstructionStream::with_storage` of `asmkit-x86_64`.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{fixed::{FixedBufError, FixedBufStream}, InstructionStream};
+//!
+//! let mut buffer = [0; 64];
+//! let mut stream = FixedBufStream::<8, 8>::new(&mut buffer);
+//! for i in 0..16 {
+//!     stream.write_double_word(i);
+//! }
+//! assert_eq!(stream.remaining(), 0);
+//! assert_eq!(stream.finish_in_place(), Ok(64));
+//! assert_eq!(buffer[60..], [15, 0, 0, 0]);
+//!
+//! // the write past the end of the buffer is dropped, and fails the stream.
+//! let mut stream = FixedBufStream::<8, 8>::new(&mut buffer);
+//! stream.write_bytes(&[0x90; 63]);
+//! stream.write_word(0x1234);
+//! stream.write_byte(0xc3);
+//! assert_eq!(stream.len(), 63);
+//! assert_eq!(stream.error(), Some(FixedBufError::BufferFull { capacity: 64 }));
+//! assert_eq!(stream.finish_in_place(), Err(FixedBufError::BufferFull { capacity: 64 }));
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    entity::{EntityRef, Label, LabelRef},
+    template::{TemplateFixup, TemplateFixupKind, TemplateTarget},
+    InstructionStream, Product,
+};
+
+/// An error which occurred while writing to a [`FixedBufStream`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixedBufError {
+    /// A write doesn't fit in the rest of the buffer.
+    BufferFull {
+        /// The size of the buffer, in bytes.
+        capacity: usize,
+    },
+
+    /// A label was created past the capacity of the stream.
+    TooManyLabels {
+        /// The number of labels of the stream.
+        capacity: usize,
+    },
+
+    /// A label was used past the capacity of the stream.
+    TooManyFixups {
+        /// The number of label uses of the stream.
+        capacity: usize,
+    },
+
+    /// A label is used but never attached.
+    Unattached {
+        /// The label.
+        label: LabelRef,
+    },
+
+    /// The displacement of a label doesn't fit in the field using it.
+    OutOfRange {
+        /// The offset of the field.
+        offset: usize,
+
+        /// The displacement of the label from the end of the field.
+        displacement: i64,
+    },
+
+    /// The hole of a template holds the address of a symbol or the absolute address of a label, left to a relocation, which the stream has
+    /// none of.
+    Relocated {
+        /// The offset of the hole.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for FixedBufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferFull { capacity } => write!(f, "buffer of {} bytes is full", capacity),
+            Self::TooManyLabels { capacity } => write!(f, "more than {} labels are created", capacity),
+            Self::TooManyFixups { capacity } => write!(f, "labels are used more than {} times", capacity),
+            Self::Unattached { label } => write!(f, "label {:?} is used but never attached", label),
+            Self::OutOfRange { offset, displacement } => write!(f, "displacement {} of the label field at {:#x} is out of range", displacement, offset),
+            Self::Relocated { offset } => write!(f, "the template hole at {:#x} needs a relocation, which a fixed buffer has none of", offset),
+        }
+    }
+}
+
+impl Error for FixedBufError {}
+
+/// The storage of the bytes written by an instruction stream: a `Vec<u8>`, growing as needed, or a [`FixedBuf`] over a buffer of the caller,
+/// which never grows.
+///
+/// The storage derefs to the bytes written so far.  A write which doesn't fit in the rest of the storage is dropped whole, and recorded as the
+/// [`CodeStorage::error`] of the storage, after which nothing more is written.
+pub trait CodeStorage: Deref<Target = [u8]> + DerefMut {
+    /// Appends `bytes`, or records [`FixedBufError::BufferFull`] and appends none of them if they don't fit.
+    fn extend_from_slice(&mut self, bytes: &[u8]);
+
+    /// Resizes the bytes to `len`, appending copies of `byte` or truncating them, or records [`FixedBufError::BufferFull`] and leaves them as
+    /// they are if `len` doesn't fit.
+    fn resize(&mut self, len: usize, byte: u8);
+
+    /// Shortens the bytes to `len`, doing nothing if they are shorter.
+    fn truncate(&mut self, len: usize);
+
+    /// Swaps the bytes written with `bytes`, such as to write code aside and lay it out later, or records [`FixedBufError::BufferFull`] and
+    /// leaves the storage empty if `bytes` don't fit.
+    fn swap(&mut self, bytes: &mut Vec<u8>);
+
+    /// Takes the bytes written, leaving the storage empty.
+    fn take(&mut self) -> Vec<u8>;
+
+    /// The first write which didn't fit in the storage, if any.
+    fn error(&self) -> Option<FixedBufError>;
+}
+
+impl CodeStorage for Vec<u8> {
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        Vec::extend_from_slice(self, bytes);
+    }
+
+    #[inline(always)]
+    fn resize(&mut self, len: usize, byte: u8) {
+        Vec::resize(self, len, byte);
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len);
+    }
+
+    #[inline(always)]
+    fn swap(&mut self, bytes: &mut Vec<u8>) {
+        std::mem::swap(self, bytes);
+    }
+
+    #[inline(always)]
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(self)
+    }
+
+    #[inline(always)]
+    fn error(&self) -> Option<FixedBufError> {
+        None
+    }
+}
+
+/// A [`CodeStorage`] writing into a buffer borrowed from the caller, which never grows.
+///
+/// # Example
+/// ```
+/// use asmkit_core::fixed::{CodeStorage, FixedBuf, FixedBufError};
+///
+/// let mut buffer = [0; 4];
+/// let mut storage = FixedBuf::new(&mut buffer);
+/// storage.extend_from_slice(&[0x90, 0x90, 0x90]);
+/// assert_eq!((storage.len(), storage.remaining()), (3, 1));
+///
+/// // the write of two bytes doesn't fit in the last one, and is dropped.
+/// storage.extend_from_slice(&[0x0f, 0x0b]);
+/// assert_eq!(storage.error(), Some(FixedBufError::BufferFull { capacity: 4 }));
+/// assert_eq!(*storage, [0x90, 0x90, 0x90]);
+/// ```
+#[derive(Debug)]
+pub struct FixedBuf<'a> {
+    /// The buffer the bytes are written to.
+    buffer: &'a mut [u8],
+
+    /// The number of bytes written to the buffer.
+    len: usize,
+
+    /// The first write which didn't fit, after which nothing more is written.
+    error: Option<FixedBufError>,
+}
+
+impl<'a> FixedBuf<'a> {
+    /// Creates a new, empty storage writing into `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0, error: None }
+    }
+
+    /// The size of the buffer, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The number of bytes left in the buffer.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.len
+    }
+
+    /// Records that a write doesn't fit in the rest of the buffer.
+    fn full(&mut self) {
+        self.error = Some(FixedBufError::BufferFull { capacity: self.buffer.len() });
+    }
+}
+
+impl<'a> From<&'a mut [u8]> for FixedBuf<'a> {
+    fn from(buffer: &'a mut [u8]) -> Self {
+        Self::new(buffer)
+    }
+}
+
+impl Deref for FixedBuf<'_> {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl DerefMut for FixedBuf<'_> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer[..self.len]
+    }
+}
+
+impl CodeStorage for FixedBuf<'_> {
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.buffer.get_mut(self.len..self.len + bytes.len()) {
+            Some(field) => {
+                field.copy_from_slice(bytes);
+                self.len += bytes.len();
+            },
+            None => self.full(),
+        }
+    }
+
+    #[inline(always)]
+    fn resize(&mut self, len: usize, byte: u8) {
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.buffer.get_mut(self.len.min(len)..len) {
+            Some(field) => {
+                field.fill(byte);
+                self.len = len;
+            },
+            None => self.full(),
+        }
+    }
+
+    #[inline(always)]
+    fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Swaps the bytes written with `bytes` by copying them, as the buffer is borrowed.
+    fn swap(&mut self, bytes: &mut Vec<u8>) {
+        let written = self.take();
+        self.extend_from_slice(bytes);
+        *bytes = written;
+    }
+
+    fn take(&mut self) -> Vec<u8> {
+        let bytes = self.to_vec();
+        self.len = 0;
+        bytes
+    }
+
+    #[inline(always)]
+    fn error(&self) -> Option<FixedBufError> {
+        self.error
+    }
+}
+
+/// A use of a label by a [`FixedBufStream`], written once the stream is finished.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fixup {
+    /// The offset of the field holding the displacement.
+    offset: usize,
+
+    /// The size of the field, in bytes.
+    size: usize,
+
+    /// The label whose displacement the field holds.
+    label: LabelRef,
+}
+
+/// An instruction stream writing into a borrowed buffer, with room for `LABELS` labels and `FIXUPS` uses of labels.
+#[derive(Debug)]
+pub struct FixedBufStream<'a, const LABELS: usize, const FIXUPS: usize> {
+    /// The buffer the bytes are written to.
+    buffer: FixedBuf<'a>,
+
+    /// The labels created, the first `label_count` entries.
+    labels: [Label; LABELS],
+
+    /// The number of labels created.
+    label_count: usize,
+
+    /// The label uses which are resolved when the instruction stream is finished, the first `fixup_count` entries.
+    fixups: [Option<Fixup>; FIXUPS],
+
+    /// The number of label uses.
+    fixup_count: usize,
+
+    /// The first error, after which nothing more is written.
+    error: Option<FixedBufError>,
+}
+
+impl<'a, const LABELS: usize, const FIXUPS: usize> FixedBufStream<'a, LABELS, FIXUPS> {
+    /// Creates a new, empty instruction stream writing into `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer: FixedBuf::new(buffer),
+            labels: [Label::Unattached; LABELS],
+            label_count: 0,
+            fixups: [None; FIXUPS],
+            fixup_count: 0,
+            error: None,
+        }
+    }
+
+    /// The number of bytes written.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns true if no byte was written.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// The size of the buffer, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// The number of bytes left in the buffer.
+    pub fn remaining(&self) -> usize {
+        self.buffer.remaining()
+    }
+
+    /// The first error of the stream, after which nothing more is written.
+    pub fn error(&self) -> Option<FixedBufError> {
+        self.error
+    }
+
+    /// Writes an 8-bit field holding the displacement of `label` from the end of the field, as `jmp rel8` does.
+    pub fn write_label_rel8(&mut self, label: LabelRef) {
+        self.write_label_field(label, 1);
+    }
+
+    /// Writes a 32-bit field holding the displacement of `label` from the end of the field, as `jmp rel32` does.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{fixed::{FixedBufError, FixedBufStream}, InstructionStream};
+    ///
+    /// let mut buffer = [0; 16];
+    /// let mut stream = FixedBufStream::<2, 2>::new(&mut buffer);
+    /// let start = stream.create_label_attached();
+    /// let exit = stream.create_label();
+    ///
+    /// stream.write_byte(0xeb); // jmp exit
+    /// stream.write_label_rel8(exit);
+    /// stream.write_byte(0xe9); // jmp start
+    /// stream.write_label_rel32(start);
+    /// stream.attach_label(exit);
+    /// stream.write_byte(0xc3); // ret
+    ///
+    /// assert_eq!(stream.finish_in_place(), Ok(8));
+    /// assert_eq!(buffer[..8], [
+    ///     0xeb, 0x05,                   // jmp exit
+    ///     0xe9, 0xf9, 0xff, 0xff, 0xff, // jmp start
+    ///     0xc3,                         // exit: ret
+    /// ]);
+    ///
+    /// // a third label doesn't fit.
+    /// let mut stream = FixedBufStream::<2, 2>::new(&mut buffer);
+    /// let labels = [stream.create_label(), stream.create_label(), stream.create_label()];
+    /// stream.write_label_rel32(labels[2]);
+    /// assert_eq!(stream.finish_in_place(), Err(FixedBufError::TooManyLabels { capacity: 2 }));
+    /// ```
+    pub fn write_label_rel32(&mut self, label: LabelRef) {
+        self.write_label_field(label, 4);
+    }
+
+    /// Finishes writing to the instruction stream, writing the displacement of every label use into the buffer, and returns the number of bytes
+    /// written.
+    ///
+    /// # Errors
+    /// Returns the first error which stopped the stream, [`FixedBufError::Unattached`] if a used label was never attached, and
+    /// [`FixedBufError::OutOfRange`] if its displacement doesn't fit in its field.
+    pub fn finish_in_place(mut self) -> Result<usize, FixedBufError> {
+        self.resolve()
+    }
+
+    /// Writes the displacement of every label use into the buffer, and returns the number of bytes written.
+    fn resolve(&mut self) -> Result<usize, FixedBufError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        for fixup in self.fixups[..self.fixup_count].iter().flatten() {
+            let target = match self.labels.get(fixup.label.as_usize()) {
+                Some(Label::Attached(target)) => *target,
+                _ => return Err(FixedBufError::Unattached { label: fixup.label }),
+            };
+
+            let displacement = target as i64 - (fixup.offset + fixup.size) as i64;
+            let bits = fixup.size as u32 * 8;
+            if displacement >> (bits - 1) != 0 && displacement >> (bits - 1) != -1 {
+                return Err(FixedBufError::OutOfRange { offset: fixup.offset, displacement });
+            }
+
+            self.buffer[fixup.offset..fixup.offset + fixup.size].copy_from_slice(&displacement.to_le_bytes()[..fixup.size]);
+        }
+
+        Ok(self.buffer.len())
+    }
+
+    /// Records a use of `label` in a field of `size` bytes at the current index, and writes the field zeroed.
+    fn write_label_field(&mut self, label: LabelRef, size: usize) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if self.fixup_count == FIXUPS {
+            self.error = Some(FixedBufError::TooManyFixups { capacity: FIXUPS });
+            return;
+        }
+
+        let offset = self.buffer.len();
+        self.write_bytes(&[0; 4][..size]);
+        self.record_fixup(offset, size, label);
+    }
+
+    /// Records a use of `label` in the field of `size` bytes written at `offset`.
+    fn record_fixup(&mut self, offset: usize, size: usize, label: LabelRef) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if self.fixup_count == FIXUPS {
+            self.error = Some(FixedBufError::TooManyFixups { capacity: FIXUPS });
+            return;
+        }
+
+        self.fixups[self.fixup_count] = Some(Fixup { offset, size, label });
+        self.fixup_count += 1;
+    }
+}
+
+impl<const LABELS: usize, const FIXUPS: usize> InstructionStream for FixedBufStream<'_, LABELS, FIXUPS> {
+    /// Creates a new label, or records [`FixedBufError::TooManyLabels`] and returns a label past the capacity, ignored by the stream, if all
+    /// the labels are created.
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        if self.label_count == LABELS {
+            self.error.get_or_insert(FixedBufError::TooManyLabels { capacity: LABELS });
+            return LabelRef::new(LABELS);
+        }
+
+        self.label_count += 1;
+        LabelRef::new(self.label_count - 1)
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        let label = self.create_label();
+        self.attach_label(label);
+        label
+    }
+
+    #[inline(always)]
+    fn attach_label(&mut self, label: LabelRef) {
+        if let Some(entry) = self.labels.get_mut(label.as_usize()) {
+            *entry = Label::Attached(self.buffer.len());
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.write_bytes(&[byte]);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.write_bytes(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.write_bytes(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.write_bytes(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.write_bytes(&word.to_le_bytes());
+    }
+
+    /// Writes `bytes` at the current index, or records [`FixedBufError::BufferFull`] and writes none of them if they don't fit in the rest of
+    /// the buffer.
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        self.buffer.extend_from_slice(bytes);
+        self.error = self.buffer.error();
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Records the 32-bit displacement of a label held by the hole of a template as a use of the label, or records
+    /// [`FixedBufError::Relocated`] if the hole needs a relocation.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{fixed::{FixedBufError, FixedBufStream}, template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    ///
+    /// let mut buffer = [0; 16];
+    /// let mut stream = FixedBufStream::<2, 2>::new(&mut buffer);
+    /// let exit = stream.create_label();
+    /// stream.emit_template(&[0xe9, 0, 0, 0, 0, 0x90], &[TemplateFixup::rel32(1, TemplateTarget::Label(exit))]);
+    /// stream.attach_label(exit);
+    /// stream.write_byte(0xc3);
+    ///
+    /// assert_eq!(stream.finish_in_place(), Ok(7));
+    /// assert_eq!(buffer[..7], [0xe9, 0x01, 0x00, 0x00, 0x00, 0x90, 0xc3]);
+    ///
+    /// let mut stream = FixedBufStream::<2, 2>::new(&mut buffer);
+    /// let exit = stream.create_label_attached();
+    /// stream.emit_template(&[0; 8], &[TemplateFixup::abs64(0, TemplateTarget::Label(exit))]);
+    /// assert_eq!(stream.finish_in_place(), Err(FixedBufError::Relocated { offset: 0 }));
+    /// ```
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        match (fixup.target, fixup.kind) {
+            (TemplateTarget::Label(label), TemplateFixupKind::Rel32) => self.record_fixup(fixup.offset, fixup.width, label),
+            _ => {
+                self.error.get_or_insert(FixedBufError::Relocated { offset: fixup.offset });
+            },
+        }
+    }
+
+    /// Finishes writing to the instruction stream, as [`FixedBufStream::finish_in_place`] does, and returns a product holding a copy of the
+    /// bytes written.
+    ///
+    /// # Panics
+    /// Panics on the errors of [`FixedBufStream::finish_in_place`].
+    fn finish(mut self) -> Product {
+        self.resolve().unwrap_or_else(|error| panic!("{}", error));
+        Product::new(self.buffer.take())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
@@ -0,0 +1,225 @@
+//! Label tables, holding the labels of an instruction stream and the uses of each label still to be resolved.
+//!
+//! A [`LabelTable`] keeps a [`LabelData`] per label, and the uses recorded by [`LabelTable::use_label`] in an arena, chained per label as the
+//! fixups of a fragment are in LLVM's `MCAssembler`.  Attaching a label with [`LabelTable::attach`] hands back the chain of its own pending
+//! uses, so an instruction stream whose offsets are final once written can patch them right away, in time proportional to the uses of that
+//! label rather than to every use of the stream.  The uses recorded after the label is attached stay chained until
+//! [`LabelTable::take_pending`] collects them, usually when the stream is finished, as the instruction using the label isn't written yet when
+//! the use is recorded.
+//!
+//! [`RawStream`](crate::raw::RawStream) and the BPF, ARM and RISC-V streams patch the uses of a label as it is attached.  The x86-64 stream
+//! keeps its labels in a table too, but only counts their uses there: blocks, sections and alignment padding still move its code until it is
+//! finished, so it resolves every fixup then.  The AArch64 stream records no label uses.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{entity::Label, labels::LabelTable};
+//!
+//! let mut labels: LabelTable<usize> = LabelTable::new();
+//! let done = labels.create();
+//! labels.use_label(done, 0x10);
+//! labels.use_label(done, 0x20);
+//!
+//! // attaching the label yields its uses, in the order they were recorded.
+//! let mut pending = labels.attach(done, 0x40);
+//! assert_eq!(pending.next(&labels), Some(0x10));
+//! assert_eq!(pending.next(&labels), Some(0x20));
+//! assert_eq!(pending.next(&labels), None);
+//! assert_eq!(labels.get(done).state, Label::Attached(0x40));
+//! assert_eq!(labels.get(done).uses, 2);
+//! ```
+//!
+//! Attaching each of 100,000 labels only visits its own use, however many uses the other labels have, which is what keeps the streams
+//! patching on attach linear in their uses:
+//! ```
+//! use asmkit_core::labels::LabelTable;
+//!
+//! let mut labels: LabelTable<usize> = LabelTable::new();
+//! let created: Vec<_> = (0..100_000).map(|_| labels.create()).collect();
+//! for (index, label) in created.iter().enumerate() {
+//!     labels.use_label(*label, index);
+//! }
+//!
+//! for (index, label) in created.iter().enumerate() {
+//!     let mut pending = labels.attach(*label, index);
+//!     assert_eq!(pending.next(&labels), Some(index));
+//!     assert_eq!(pending.next(&labels), None);
+//! }
+//!
+//! assert!(created.iter().all(|label| labels.take_pending(*label).next(&labels).is_none()));
+//! ```
+
+use crate::entity::{EntityList, EntityRef, Label, LabelRef};
+
+/// An opaque reference to a use of a label in the arena of a [`LabelTable`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct FixupRef(u32);
+
+impl EntityRef for FixupRef {
+    #[inline(always)]
+    fn new(value: usize) -> Self {
+        Self(value as u32)
+    }
+
+    #[inline(always)]
+    fn as_u32(&self) -> u32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn as_usize(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A label of a [`LabelTable`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LabelData {
+    /// Where the label is attached or bound.
+    pub state: Label,
+
+    /// The number of uses recorded for the label.
+    pub uses: usize,
+
+    /// The first use still pending.
+    first: Option<FixupRef>,
+
+    /// The last use still pending, which the next use is chained to.
+    last: Option<FixupRef>,
+}
+
+impl LabelData {
+    /// Creates the data of a label in `state`, without uses.
+    pub fn new(state: Label) -> Self {
+        Self { state, uses: 0, first: None, last: None }
+    }
+}
+
+/// A use of a label in the arena, and the next use of the same label.
+#[derive(Clone, Debug)]
+struct FixupNode<F> {
+    /// The use.
+    fixup: F,
+
+    /// The next use of the same label.
+    next: Option<FixupRef>,
+}
+
+/// The labels of an instruction stream and their pending uses, see the [module](self) documentation.
+#[derive(Clone, Debug)]
+pub struct LabelTable<F> {
+    /// The labels.
+    labels: EntityList<LabelData, LabelRef>,
+
+    /// The uses of every label, chained per label.
+    fixups: EntityList<FixupNode<F>, FixupRef>,
+}
+
+impl<F: Copy> LabelTable<F> {
+    /// Creates a new, empty label table.
+    pub fn new() -> Self {
+        Self { labels: EntityList::new(), fixups: EntityList::new() }
+    }
+
+    /// Creates a label attached to no offset yet.
+    #[inline(always)]
+    pub fn create(&mut self) -> LabelRef {
+        self.labels.push(LabelData::new(Label::Unattached))
+    }
+
+    /// Creates a label attached to `offset`.
+    #[inline(always)]
+    pub fn create_attached(&mut self, offset: usize) -> LabelRef {
+        self.labels.push(LabelData::new(Label::Attached(offset)))
+    }
+
+    /// Attaches `label` to `offset`, and returns the chain of its pending uses, which [`LabelTable::take_pending`] won't return again.
+    pub fn attach(&mut self, label: LabelRef, offset: usize) -> PendingFixups {
+        self.labels.get_mut(label).state = Label::Attached(offset);
+        self.take_pending(label)
+    }
+
+    /// Returns the data of `label`.
+    #[inline(always)]
+    pub fn get(&self, label: LabelRef) -> &LabelData {
+        self.labels.get(label)
+    }
+
+    /// Returns a mutable reference to the data of `label`.
+    #[inline(always)]
+    pub fn get_mut(&mut self, label: LabelRef) -> &mut LabelData {
+        self.labels.get_mut(label)
+    }
+
+    /// Returns the offset of `label`, if it is attached.
+    pub fn offset(&self, label: LabelRef) -> Option<usize> {
+        self.labels.get(label).state.offset()
+    }
+
+    /// Records `fixup` as a use of `label`, pending until the label is attached or its uses are taken.
+    pub fn use_label(&mut self, label: LabelRef, fixup: F) -> FixupRef {
+        let node = self.fixups.push(FixupNode { fixup, next: None });
+        let data = self.labels.get_mut(label);
+        data.uses += 1;
+
+        match data.last.replace(node) {
+            Some(last) => self.fixups.get_mut(last).next = Some(node),
+            None => data.first = Some(node),
+        }
+
+        node
+    }
+
+    /// Returns the chain of the uses of `label` still pending, in the order they were recorded, and empties it.
+    pub fn take_pending(&mut self, label: LabelRef) -> PendingFixups {
+        let data = self.labels.get_mut(label);
+        data.last = None;
+        PendingFixups { next: data.first.take() }
+    }
+
+    /// Returns the use `fixup`.
+    #[inline(always)]
+    pub fn fixup(&self, fixup: FixupRef) -> F {
+        self.fixups.get(fixup).fixup
+    }
+
+    /// The labels of the table, in the order they were created.
+    pub fn refs(&self) -> impl Iterator<Item = LabelRef> {
+        (0..self.labels.len()).map(LabelRef::new)
+    }
+
+    /// Returns the number of labels.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns true if the table holds no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+impl<F: Copy> Default for LabelTable<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A chain of pending uses of a label, returned by [`LabelTable::attach`] and [`LabelTable::take_pending`].
+///
+/// The chain borrows nothing, so the instruction stream can patch its bytes while walking it, reading each use from the table with
+/// [`PendingFixups::next`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendingFixups {
+    /// The next use of the chain.
+    next: Option<FixupRef>,
+}
+
+impl PendingFixups {
+    /// Returns the next use of the chain, read from `table`.
+    pub fn next<F: Copy>(&mut self, table: &LabelTable<F>) -> Option<F> {
+        let node = table.fixups.get(self.next?);
+        self.next = node.next;
+        Some(node.fixup)
+    }
+}
@@ -1,38 +1,1088 @@
 //! The core behind AsmKit.
 
-use entity::LabelRef;
+use std::{error::Error, fmt, ops::Range, str::FromStr};
 
+use bss::Bss;
+use calls::{CallSite, CallSiteTarget};
+use diff::ProductDiff;
+use entity::{Binding, EntityList, EntityRef, LabelRef, SymRef, Symbol, SymbolDefinition};
+use listing::{Comment, Listing};
+use patch::{PatchField, PatchFieldError, PatchPoint, PatchPointRef};
+use size_report::{SizeRegions, SizeReport};
+use source_map::{Location, SourceMap};
+use stackmap::StackMaps;
+use stub::CallStub;
+use reloc::{RelocError, Relocation, RelocationKind, RelocationTarget};
+use template::TemplateFixup;
+
+#[cfg(feature = "blob")]
+pub mod blob;
+pub mod bss;
+pub mod calls;
+pub mod diff;
+pub mod displacement;
+pub mod dwarf;
 pub mod entity;
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub mod exec;
+pub mod fixed;
+pub mod labels;
+pub mod link;
+pub mod listing;
+pub mod mangle;
+pub mod object;
+pub mod patch;
+pub mod raw;
+pub mod reloc;
+pub mod size_report;
+pub mod source_map;
+pub mod stackmap;
+pub mod stub;
+pub mod target;
+pub mod template;
 
-/// The output of an instruction stream.  Keeps tracks of any relocations.
-/// 
-/// TODO: implement relocations.
+pub use link::{link, LinkError, LinkOptions, ProductSet};
+pub use object::{write_object, ObjectError};
+
+/// The size of a boot sector, the first sector of a disk loaded by the BIOS at `0x7c00`, see [`Product::to_boot_sector`].
+pub const BOOT_SECTOR_SIZE: usize = 512;
+
+/// A target architecture, for picking a backend at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Architecture {
+    /// 64-bit x86.
+    X86_64,
+
+    /// 64-bit ARM.
+    Aarch64,
+
+    /// 32-bit ARM, running the Thumb-2 instruction set.
+    Thumb,
+
+    /// 64-bit RISC-V.
+    Riscv64,
+}
+
+impl Architecture {
+    /// Every supported architecture.
+    pub const ALL: [Self; 4] = [Self::X86_64, Self::Aarch64, Self::Thumb, Self::Riscv64];
+
+    /// The name of this architecture, as used in target triples.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64",
+            Self::Aarch64 => "aarch64",
+            Self::Thumb => "thumb",
+            Self::Riscv64 => "riscv64",
+        }
+    }
+}
+
+impl fmt::Display for Architecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing an unknown [`Architecture`] name.
 #[derive(Clone, Debug, PartialEq)]
+pub struct UnknownArchitecture(pub String);
+
+impl fmt::Display for UnknownArchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown architecture `{}`", self.0)
+    }
+}
+
+impl Error for UnknownArchitecture {}
+
+impl FromStr for Architecture {
+    type Err = UnknownArchitecture;
+
+    /// Parses an architecture from its name, also accepting the common aliases `amd64`, `x86-64` and `arm64`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::Architecture;
+    ///
+    /// assert_eq!("x86_64".parse(), Ok(Architecture::X86_64));
+    /// assert_eq!("arm64".parse(), Ok(Architecture::Aarch64));
+    /// assert!("sparc".parse::<Architecture>().is_err());
+    ///
+    /// for architecture in Architecture::ALL {
+    ///     assert_eq!(architecture.name().parse(), Ok(architecture));
+    /// }
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "x86_64" | "x86-64" | "amd64" => Ok(Self::X86_64),
+            "aarch64" | "arm64" => Ok(Self::Aarch64),
+            "thumb" => Ok(Self::Thumb),
+            "riscv64" => Ok(Self::Riscv64),
+            _ => Err(UnknownArchitecture(name.to_string())),
+        }
+    }
+}
+
+/// The output of an instruction stream: the produced bytes, and the relocations still to be applied to them.
+///
+/// Instruction streams are deterministic: the same sequence of emitter calls always produces an equal product, with the relocations sorted by offset
+/// and the symbols in the order they were created.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Product {
     /// The bytes produce by an instruction stream.
     bytes: Vec<u8>,
+
+    /// The fields of the bytes which depend on the load address or on external symbols.
+    relocations: Vec<Relocation>,
+
+    /// The external symbols referred to by the relocations.
+    symbols: EntityList<Symbol, SymRef>,
+
+    /// The symbols defined by the product.
+    definitions: Vec<SymbolDefinition>,
+
+    /// The comments attached to offsets of the bytes.
+    comments: Vec<Comment>,
+
+    /// The locations of the source code the bytes were generated from.
+    source_map: SourceMap,
+
+    /// The regions the bytes are attributed to.
+    size_regions: SizeRegions,
+
+    /// The fields of the bytes recorded as patchable, by offset.
+    patch_fields: Vec<PatchField>,
+
+    /// Whether each relocation was applied in place, see [`Product::apply_relocation`].
+    resolved: Vec<bool>,
+
+    /// The address the relocations are applied in place for.
+    load_address: u64,
+
+    /// The uninitialized data reserved past the bytes.
+    bss: Bss,
+
+    /// The call instructions of the bytes, by offset.
+    call_sites: Vec<CallSite>,
+
+    /// The locations of the live managed pointers at the safepoints of the bytes.
+    stack_maps: StackMaps,
+
+    /// The gaps reserved to be patched, by offset.
+    patch_points: Vec<PatchPoint>,
+
+    /// The stubs jumping to external symbols, by offset.
+    stubs: Vec<CallStub>,
+
+    /// The offsets of the labels of the instruction stream which produced the product, indexed by label.
+    label_offsets: Vec<Option<usize>>,
+
+    /// The offsets of the named labels, by name.
+    named_labels: Vec<(String, usize)>,
+
+    /// The symbol names already mangled for the target or given raw, sorted.
+    mangled: Vec<String>,
 }
 
 impl Product {
     /// Creates a new product initialized with the provided bytes.
     pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes }
+        Self::with_relocations(bytes, Vec::new(), EntityList::new())
+    }
+
+    /// Creates a new product initialized with the provided bytes, relocations, and the symbols they refer to.
+    pub fn with_relocations(bytes: Vec<u8>, relocations: Vec<Relocation>, symbols: EntityList<Symbol, SymRef>) -> Self {
+        let resolved = vec![false; relocations.len()];
+        Self { bytes, relocations, symbols, definitions: Vec::new(), comments: Vec::new(), source_map: SourceMap::default(), size_regions: SizeRegions::default(), patch_fields: Vec::new(), resolved, load_address: 0, bss: Bss::new(), call_sites: Vec::new(), stack_maps: StackMaps::default(), patch_points: Vec::new(), stubs: Vec::new(), label_offsets: Vec::new(), named_labels: Vec::new(), mangled: Vec::new() }
+    }
+
+    /// Defines the symbol `name` at `offset`, for other products to refer to when they are linked together with [`link`].
+    pub fn define(&mut self, name: &str, offset: usize) {
+        self.definitions.push(SymbolDefinition { name: name.to_string(), offset, binding: Binding::Global, comdat: None });
+    }
+
+    /// Defines the weak symbol `name` at `offset`, overridden by a global definition of the symbol and deduplicated with its other weak
+    /// definitions when the products are linked together, see [`Binding::Weak`].
+    pub fn define_weak(&mut self, name: &str, offset: usize) {
+        self.definitions.push(SymbolDefinition { name: name.to_string(), offset, binding: Binding::Weak, comdat: None });
+    }
+
+    /// Defines the symbol `name` at `offset` in the COMDAT group `comdat`, deduplicated with the other definitions of the group when the
+    /// products are linked together.
+    ///
+    /// [`link`](link()) keeps the first definition of the symbol whatever the [`SelectionKind`](entity::SelectionKind) of the group, and keeps the code
+    /// of every product, as a product is a single section; the selection is for the linkers of object files.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{Binding, Comdat, SelectionKind}, link, LinkError, LinkOptions, Product};
+    ///
+    /// // the same helper, generated by two instruction streams.
+    /// let helper = |comdat: Option<Comdat>| {
+    ///     let mut product = Product::new(vec![0x48, 0x8d, 0x04, 0x3f, 0xc3]); // lea rax, [rdi + rdi]; ret
+    ///     match comdat {
+    ///         Some(comdat) => product.define_comdat("double", 0, comdat),
+    ///         None => product.define_weak("double", 0),
+    ///     }
+    ///     product
+    /// };
+    ///
+    /// let comdat = Comdat { name: "double".to_string(), selection: SelectionKind::Any };
+    /// let options = LinkOptions::default();
+    /// for (first, second) in [(helper(None), helper(None)), (helper(Some(comdat.clone())), helper(Some(comdat.clone())))] {
+    ///     let linked = link(vec![first, second], options).unwrap();
+    ///     assert_eq!(linked.definitions().len(), 1);
+    ///     assert_eq!(linked.definition("double"), Some(0));
+    /// }
+    ///
+    /// // a global definition overrides the weak ones, wherever it is.
+    /// let mut global = Product::new(vec![0x48, 0x01, 0xff, 0x48, 0x89, 0xf8, 0xc3]); // add rdi, rdi; mov rax, rdi; ret
+    /// global.define("double", 0);
+    /// let linked = link(vec![helper(None), global.clone(), helper(None)], options).unwrap();
+    /// assert_eq!(linked.definition("double"), Some(16));
+    /// assert_eq!(linked.definitions()[0].binding, Binding::Global);
+    ///
+    /// assert_eq!(link(vec![global.clone(), global], options), Err(LinkError::DuplicateSymbol {
+    ///     name: "double".to_string(),
+    ///     first: 0,
+    ///     second: 16,
+    /// }));
+    /// ```
+    pub fn define_comdat(&mut self, name: &str, offset: usize, comdat: entity::Comdat) {
+        self.definitions.push(SymbolDefinition { name: name.to_string(), offset, binding: Binding::Global, comdat: Some(comdat) });
+    }
+
+    /// The symbols defined by the product.
+    pub fn definitions(&self) -> &[SymbolDefinition] {
+        &self.definitions
+    }
+
+    /// The offset of the symbol `name`, if the product defines it.
+    pub fn definition(&self, name: &str) -> Option<usize> {
+        self.definitions.iter().find(|definition| definition.name == name).map(|definition| definition.offset)
+    }
+
+    /// Attaches the comment `text` to `offset`, printed before the bytes at `offset` in the [`Product::listing`].
+    pub fn comment(&mut self, offset: usize, text: impl Into<String>) {
+        self.comments.push(Comment { offset, text: text.into() });
+    }
+
+    /// The comments attached to offsets of the product, in the order they were attached.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// The locations of the source code the bytes were generated from.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// The locations of the source code the bytes were generated from, for the instruction streams to fill.
+    pub fn source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.source_map
+    }
+
+    /// The regions the bytes are attributed to.
+    pub fn size_regions(&self) -> &SizeRegions {
+        &self.size_regions
+    }
+
+    /// The regions the bytes are attributed to, to record them.
+    pub fn size_regions_mut(&mut self) -> &mut SizeRegions {
+        &mut self.size_regions
+    }
+
+    /// The bytes attributed to each region, see the [`size_report`] module.
+    pub fn size_report(&self) -> SizeReport {
+        self.size_regions.report(self.bytes.len())
+    }
+
+    /// The locations of the live managed pointers at the safepoints of the bytes, see the [`stackmap`] module.
+    pub fn stack_maps(&self) -> &StackMaps {
+        &self.stack_maps
+    }
+
+    /// The locations of the live managed pointers at the safepoints of the bytes, for the instruction streams to fill.
+    pub fn stack_maps_mut(&mut self) -> &mut StackMaps {
+        &mut self.stack_maps
+    }
+
+    /// Records `field` as patchable, for the instruction streams emitting deliberately wide displacements and immediates.
+    pub fn add_patch_field(&mut self, field: PatchField) {
+        let index = self.patch_fields.partition_point(|other| other.offset < field.offset);
+        self.patch_fields.insert(index, field);
+    }
+
+    /// The fields of the product recorded as patchable, by offset.
+    pub fn patch_fields(&self) -> &[PatchField] {
+        &self.patch_fields
+    }
+
+    /// The patchable field recorded at `offset`, if any.
+    pub fn patch_field_at(&self, offset: usize) -> Option<&PatchField> {
+        self.patch_fields.iter().find(|field| field.offset == offset)
+    }
+
+    /// Rewrites the patchable field at `offset` with `value`, using all the space recorded for the field.
+    ///
+    /// # Errors
+    /// Returns [`PatchFieldError::Unrecorded`] if no patchable field is recorded at `offset`, and [`PatchFieldError::OutOfRange`] if `value`
+    /// doesn't fit in the field, leaving it untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{patch::{PatchField, PatchFieldError, PatchFieldKind}, Product};
+    ///
+    /// let mut product = Product::new(vec![
+    ///     0x48, 0x8b, 0x47, 0x08,                   // mov rax, qword ptr [rdi + 8]
+    ///     0x48, 0x8b, 0x87, 0x08, 0x00, 0x00, 0x00, // mov rax, qword ptr [rdi + 8], with a 32-bit displacement
+    /// ]);
+    /// product.add_patch_field(PatchField { offset: 7, size: 4, kind: PatchFieldKind::Displacement });
+    ///
+    /// product.patch_field(7, 0x12345).unwrap();
+    /// assert_eq!(product.bytes()[7..], [0x45, 0x23, 0x01, 0x00]);
+    ///
+    /// assert_eq!(product.patch_field(3, 0x10), Err(PatchFieldError::Unrecorded { offset: 3 }));
+    /// assert_eq!(product.patch_field(7, 1 << 31), Err(PatchFieldError::OutOfRange { offset: 7, value: 1 << 31, size: 4 }));
+    /// ```
+    pub fn patch_field(&mut self, offset: usize, value: i64) -> Result<(), PatchFieldError> {
+        let field = *self.patch_field_at(offset).ok_or(PatchFieldError::Unrecorded { offset })?;
+        let bytes = field.encode(value)?;
+        self.bytes[field.range()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Records the call instruction `site`, for the instruction streams.
+    pub fn add_call_site(&mut self, site: CallSite) {
+        let index = self.call_sites.partition_point(|other| other.offset < site.offset);
+        self.call_sites.insert(index, site);
+    }
+
+    /// The call instructions of the product, by offset, see the [`calls`] module.
+    pub fn call_sites(&self) -> &[CallSite] {
+        &self.call_sites
+    }
+
+    /// The call instructions of the product whose target is the symbol `name`, by offset.
+    pub fn call_sites_to<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a CallSite> {
+        self.call_sites.iter().filter(move |site| matches!(site.target, CallSiteTarget::Symbol(symbol) if self.symbols.get(symbol).name == name))
+    }
+
+    /// Records the gap `point` as a patch point, for the instruction streams.
+    pub fn add_patch_point(&mut self, point: PatchPoint) {
+        let index = self.patch_points.partition_point(|other| other.offset < point.offset);
+        self.patch_points.insert(index, point);
+    }
+
+    /// The patch points of the product, by offset.
+    pub fn patch_points(&self) -> &[PatchPoint] {
+        &self.patch_points
+    }
+
+    /// The patch point `point`, or `None` if the product has no patch point with its id.
+    pub fn patch_point(&self, point: PatchPointRef) -> Option<&PatchPoint> {
+        self.patch_points.iter().find(|other| other.id == point.0)
+    }
+
+    /// Records `stub` as the stub jumping to its symbol, for the instruction streams, see the [`stub`] module.
+    pub fn add_stub(&mut self, stub: CallStub) {
+        let index = self.stubs.partition_point(|other| other.offset < stub.offset);
+        self.stubs.insert(index, stub);
+    }
+
+    /// The stubs jumping to external symbols, by offset.
+    pub fn stubs(&self) -> &[CallStub] {
+        &self.stubs
+    }
+
+    /// The offset of the stub jumping to the symbol `name`, if any.
+    pub fn stub(&self, name: &str) -> Option<usize> {
+        self.stubs.iter().find(|stub| self.symbols.get(stub.symbol).name == name).map(|stub| stub.offset)
+    }
+
+    /// Records the offsets of the labels of the instruction stream, indexed by label, `None` for the labels never attached, for the instruction
+    /// streams.
+    pub fn set_label_offsets(&mut self, offsets: Vec<Option<usize>>) {
+        self.label_offsets = offsets;
+    }
+
+    /// The offset `label` of the instruction stream which produced the product was attached to, if any.
+    pub fn label_offset(&self, label: LabelRef) -> Option<usize> {
+        self.label_offsets.get(label.as_usize()).copied().flatten()
+    }
+
+    /// Records that the label named `name` was attached to `offset`, for the instruction streams naming their labels.
+    pub fn add_named_label(&mut self, name: &str, offset: usize) {
+        let index = self.named_labels.partition_point(|(other, _)| other.as_str() < name);
+        self.named_labels.insert(index, (name.to_string(), offset));
+    }
+
+    /// The offset of the label named `name`, if it was attached.
+    pub fn named_label_offset(&self, name: &str) -> Option<usize> {
+        self.named_labels.binary_search_by(|(other, _)| other.as_str().cmp(name)).ok().map(|index| self.named_labels[index].1)
+    }
+
+    /// The named labels and their offsets, by name.
+    pub fn named_labels(&self) -> &[(String, usize)] {
+        &self.named_labels
+    }
+
+    /// Records that the symbol `name` is already mangled for the target, by the instruction stream or given raw, so [`write_object`] writes it as
+    /// is rather than mangling it with [`mangle_c`](mangle::mangle_c).
+    ///
+    /// ```
+    /// use asmkit_core::Product;
+    ///
+    /// let mut product = Product::new(vec![0xc3]);
+    /// product.mark_mangled("_main");
+    /// assert!(product.is_mangled("_main"));
+    /// assert!(!product.is_mangled("main"));
+    /// ```
+    pub fn mark_mangled(&mut self, name: &str) {
+        if let Err(index) = self.mangled.binary_search_by(|other| other.as_str().cmp(name)) {
+            self.mangled.insert(index, name.to_string());
+        }
+    }
+
+    /// Whether the symbol `name` was recorded by [`Product::mark_mangled`].
+    pub fn is_mangled(&self, name: &str) -> bool {
+        self.mangled.binary_search_by(|other| other.as_str().cmp(name)).is_ok()
+    }
+
+    /// The offset of the stub jumping to the target of `relocation`, if it is a relative relocation against a symbol with a stub.
+    fn veneer(&self, relocation: &Relocation) -> Option<usize> {
+        match (relocation.kind, relocation.target) {
+            (RelocationKind::Rel32 | RelocationKind::Branch26, RelocationTarget::Symbol(symbol)) => self.stubs.iter().find(|stub| stub.symbol == symbol).map(|stub| stub.offset),
+            _ => None,
+        }
+    }
+
+    /// The location of the source code the byte at `offset` was generated from, or `None` if it has none or is past the end of the product.
+    pub fn source_location(&self, offset: usize) -> Option<Location> {
+        if offset >= self.bytes.len() {
+            return None;
+        }
+
+        self.source_map.lookup(offset)
+    }
+
+    /// A hexdump of the product, with the symbols it defines and its comments on their own lines, see [`Listing`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::Product;
+    ///
+    /// let mut product = Product::new((0..24).collect());
+    /// product.define("data", 4);
+    /// product.comment(4, "a table\nof 20 bytes");
+    /// product.comment(24, "the end");
+    ///
+    /// assert_eq!(product.listing().to_string(), "\
+    /// 00000000  00 01 02 03
+    /// data:
+    /// ; a table
+    /// ; of 20 bytes
+    /// 00000004  04 05 06 07 08 09 0a 0b 0c 0d 0e 0f 10 11 12 13
+    /// 00000014  14 15 16 17
+    /// ; the end
+    /// ");
+    /// ```
+    pub fn listing(&self) -> Listing<'_> {
+        Listing::new(self)
+    }
+
+    /// The produced bytes, with the relocated fields left unresolved unless they were applied in place.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Sets the uninitialized data reserved past the bytes, whose labels the instruction stream resolved against [`Product::bss_offset`].
+    pub fn set_bss(&mut self, bss: Bss) {
+        self.bss = bss;
+    }
+
+    /// The uninitialized data reserved past the bytes, see the [`bss`] module.
+    pub fn bss(&self) -> &Bss {
+        &self.bss
+    }
+
+    /// The offset of the uninitialized data, the end of the bytes rounded up to [`bss::BSS_ALIGN`].
+    pub fn bss_offset(&self) -> usize {
+        bss::bss_offset(self.bytes.len())
+    }
+
+    /// The size of the uninitialized data, which takes no bytes in the product.
+    pub fn bss_size(&self) -> usize {
+        self.bss.size()
+    }
+
+    /// The offset of the uninitialized data reserved at `label`, if it is a label of the uninitialized data.
+    pub fn bss_label_offset(&self, label: LabelRef) -> Option<usize> {
+        let reserved = self.bss.labels().iter().find(|reserved| reserved.label == label)?;
+        Some(self.bss_offset() + reserved.offset)
+    }
+
+    /// The size of the memory the product is loaded into: its bytes, followed by its uninitialized data if it has any.
+    pub fn image_size(&self) -> usize {
+        if self.bss.is_empty() {
+            self.bytes.len()
+        } else {
+            self.bss_offset() + self.bss.size()
+        }
+    }
+
+    /// The relocations of the bytes, including the ones applied in place by [`Product::apply_relocation`].
+    pub fn relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+
+    /// Returns true if the relocation at `index` was applied in place by [`Product::apply_relocation`] or [`Product::resolve_all`].
+    ///
+    /// # Panics
+    /// Panics if the product has no relocation at `index`.
+    pub fn is_resolved(&self, index: usize) -> bool {
+        self.resolved[index]
+    }
+
+    /// The relocations still to be applied to the bytes, leaving out the ones applied in place.
+    pub fn unresolved_relocations(&self) -> impl Iterator<Item = &Relocation> {
+        self.relocations.iter().zip(&self.resolved).filter(|(_, resolved)| !**resolved).map(|(relocation, _)| relocation)
+    }
+
+    /// The address the relocations are applied in place for, `0` unless set by [`Product::set_load_address`] or [`Product::resolve_all`].
+    pub fn load_address(&self) -> u64 {
+        self.load_address
+    }
+
+    /// Sets the address the relocations are applied in place for by [`Product::apply_relocation`].
+    pub fn set_load_address(&mut self, load_address: u64) {
+        self.load_address = load_address;
+    }
+
+    /// Applies the relocation at `index` in place, for a target at the address `value` and the product loaded at [`Product::load_address`], and
+    /// marks it resolved.  The field is written as the [`Relocation`] describes: `value + addend` for the absolute kinds, and
+    /// `value + addend - place` for `Rel32`, where `place` is the address of the field; with the usual addend of `-4`, the displacement is
+    /// relative to the end of the field.
+    ///
+    /// A resolved relocation can be applied again, such as to retarget a call, and is left out by [`Product::relocate`],
+    /// [`Product::to_flat_binary`] and [`link`].
+    ///
+    /// # Errors
+    /// Returns [`RelocError::NoRelocation`] if the product has no relocation at `index`, and [`RelocError::OutOfRange`] if the value doesn't fit
+    /// in the field, leaving it untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityList, Symbol}, reloc::{RelocError, Relocation, RelocationKind, RelocationTarget}, Product};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut symbols = EntityList::new();
+    /// let puts = symbols.push(Symbol { name: "puts".to_string() });
+    /// let exit = symbols.push(Symbol { name: "exit".to_string() });
+    ///
+    /// let mut product = Product::with_relocations(vec![
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00, // call puts
+    ///     0x48, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // movabs rax, exit
+    ///     0xc3,                         // ret
+    /// ], vec![
+    ///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    ///     Relocation { offset: 7, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(exit), addend: 0 },
+    /// ], symbols);
+    ///
+    /// // `exit` is missing from the symbol map, so only `puts` is resolved.
+    /// let mut map = HashMap::from([("puts", 0x40_1000)]);
+    /// let error = RelocError::Unresolved { name: "exit".to_string(), offset: 7 };
+    /// assert_eq!(product.resolve_all(|name| map.get(name).copied(), 0x40_0000), Err(error));
+    /// assert_eq!(product.bytes()[1..5], (0x40_1000 - 0x40_0005u32).to_le_bytes());
+    /// assert!(product.is_resolved(0) && !product.is_resolved(1));
+    ///
+    /// map.insert("exit", 0x7fff_0000_1234);
+    /// product.resolve_all(|name| map.get(name).copied(), 0x40_0000).unwrap();
+    /// assert_eq!(product.bytes()[7..15], 0x7fff_0000_1234u64.to_le_bytes());
+    /// assert_eq!(product.unresolved_relocations().count(), 0);
+    ///
+    /// // retarget the call, within reach but not past 2 GiB.
+    /// product.apply_relocation(0, 0x40_2000).unwrap();
+    /// assert_eq!(product.bytes()[1..5], (0x40_2000 - 0x40_0005u32).to_le_bytes());
+    /// assert_eq!(product.apply_relocation(0, 0x1_0000_0000), Err(RelocError::OutOfRange { offset: 1, value: 0x1_0000_0000 - 0x40_0005 }));
+    /// assert_eq!(product.bytes()[1..5], (0x40_2000 - 0x40_0005u32).to_le_bytes());
+    ///
+    /// assert_eq!(product.apply_relocation(2, 0), Err(RelocError::NoRelocation { index: 2, len: 2 }));
+    /// ```
+    pub fn apply_relocation(&mut self, index: usize, value: u64) -> Result<(), RelocError> {
+        let relocation = *self.relocations.get(index).ok_or(RelocError::NoRelocation { index, len: self.relocations.len() })?;
+
+        write_relocation(&mut self.bytes, &relocation, relocation.offset, value, self.load_address)
+            .map_err(|value| RelocError::OutOfRange { offset: relocation.offset, value })?;
+
+        self.resolved[index] = true;
+        Ok(())
+    }
+
+    /// Applies the unresolved relocations in place for the product loaded at `load_address`, as [`Product::apply_relocation`] does, resolving the
+    /// address of each external symbol by name through `lookup`.  The relocations already resolved are left as they are, and a displacement to a
+    /// symbol which doesn't fit is retargeted to the [stub](stub) of the symbol, if the product has one.
+    ///
+    /// # Errors
+    /// Returns [`RelocError::Unresolved`] if `lookup` doesn't resolve a symbol, and [`RelocError::OutOfRange`] if a relocated value doesn't fit
+    /// in its field.  The relocations applied before the error stay resolved.
+    pub fn resolve_all(&mut self, mut lookup: impl FnMut(&str) -> Option<u64>, load_address: u64) -> Result<(), RelocError> {
+        self.load_address = load_address;
+
+        for index in 0..self.relocations.len() {
+            if self.resolved[index] {
+                continue;
+            }
+
+            let relocation = self.relocations[index];
+            let value = match relocation.target {
+                RelocationTarget::Base => load_address,
+                RelocationTarget::Absolute(address) => address,
+                RelocationTarget::Symbol(symbol) => {
+                    let name = &self.symbols.get(symbol).name;
+                    lookup(name).ok_or_else(|| RelocError::Unresolved { name: name.clone(), offset: relocation.offset })?
+                },
+            };
+
+            match (self.apply_relocation(index, value), self.veneer(&relocation)) {
+                (Err(RelocError::OutOfRange { .. }), Some(stub)) => self.apply_relocation(index, load_address + stub as u64)?,
+                (result, _) => result?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the symbol referred to by `symbol`.
+    pub fn symbol(&self, symbol: SymRef) -> &Symbol {
+        self.symbols.get(symbol)
+    }
+
+    /// The relocations, with the name of their symbol target, in a form comparable between products.
+    fn named_relocations(&self) -> Vec<diff::NamedRelocation<'_>> {
+        let mut relocations = self.relocations.iter().map(|relocation| {
+            let name = match relocation.target {
+                RelocationTarget::Base | RelocationTarget::Absolute(_) => None,
+                RelocationTarget::Symbol(symbol) => Some(self.symbols.get(symbol).name.as_str()),
+            };
+
+            (*relocation, name)
+        }).collect::<Vec<_>>();
+
+        relocations.sort_by_key(|(relocation, _)| relocation.offset);
+        relocations
+    }
+
+    /// A 64-bit FNV-1a hash of the bytes, relocations, symbol definitions and uninitialized data size of the product, for cheaply comparing products.  Comments, source maps,
+    /// patchable fields and label offsets are left out, as they don't change the code.
+    ///
+    /// Unlike the [`Hash`] implementation, the hash is stable across Rust versions and platforms, and doesn't depend on the order the symbols were
+    /// created in, as relocations are hashed by offset and with the names of their symbols.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, Product};
+    ///
+    /// let product = |names: &[&str], byte| {
+    ///     let mut symbols = EntityList::new();
+    ///     let refs = names.iter().map(|name| symbols.push(Symbol { name: name.to_string() })).collect::<Vec<_>>();
+    ///     let (puts, exit) = if names[0] == "puts" { (refs[0], refs[1]) } else { (refs[1], refs[0]) };
+    ///
+    ///     Product::with_relocations(vec![0xe8, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0, byte], vec![
+    ///         Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    ///         Relocation { offset: 6, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(exit), addend: -4 },
+    ///     ], symbols)
+    /// };
+    ///
+    /// assert_eq!(product(&["puts", "exit"], 0xc3).content_hash(), product(&["puts", "exit"], 0xc3).content_hash());
+    /// assert_eq!(product(&["puts", "exit"], 0xc3).content_hash(), product(&["exit", "puts"], 0xc3).content_hash());
+    /// assert_ne!(product(&["puts", "exit"], 0xc3).content_hash(), product(&["puts", "exit"], 0xcc).content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        struct Fnv(u64);
+
+        impl Fnv {
+            fn write(&mut self, bytes: &[u8]) {
+                for byte in bytes {
+                    self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+
+            fn write_usize(&mut self, value: usize) {
+                self.write(&(value as u64).to_le_bytes());
+            }
+
+            fn write_str(&mut self, value: &str) {
+                self.write_usize(value.len());
+                self.write(value.as_bytes());
+            }
+        }
+
+        let mut hash = Fnv(0xcbf29ce484222325);
+        hash.write_usize(self.bytes.len());
+        hash.write(&self.bytes);
+
+        let relocations = self.named_relocations();
+        hash.write_usize(relocations.len());
+        for (relocation, name) in relocations {
+            hash.write_usize(relocation.offset);
+            hash.write(&[relocation.kind.code()]);
+            hash.write(&relocation.addend.to_le_bytes());
+            match (name, relocation.target) {
+                (Some(name), _) => hash.write_str(name),
+                (None, RelocationTarget::Absolute(address)) => {
+                    hash.write(&[0xfe]);
+                    hash.write(&address.to_le_bytes());
+                },
+                (None, _) => hash.write(&[0xff]),
+            }
+        }
+
+        let mut definitions = self.definitions.iter().collect::<Vec<_>>();
+        definitions.sort_by(|a, b| (a.offset, &a.name).cmp(&(b.offset, &b.name)));
+        hash.write_usize(definitions.len());
+        for definition in definitions {
+            hash.write_usize(definition.offset);
+            hash.write_str(&definition.name);
+
+            // left out for the global definitions, so the hashes of the products without weak or COMDAT symbols are unchanged.
+            if definition.binding != Binding::Global || definition.comdat.is_some() {
+                hash.write(&[definition.binding as u8]);
+                if let Some(comdat) = &definition.comdat {
+                    hash.write_str(&comdat.name);
+                    hash.write(&[comdat.selection as u8]);
+                }
+            }
+        }
+
+        // left out when empty, so the hashes of the products without uninitialized data are unchanged.
+        if !self.bss.is_empty() {
+            hash.write_usize(self.bss.size());
+        }
+
+        hash.0
+    }
+
+    /// Compares the product with `other`, reporting the first differing byte and the differing relocations.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{diff::RelocationDiff, entity::EntityList, reloc::{Relocation, RelocationKind, RelocationTarget}, Product};
+    ///
+    /// let base = |offset, addend| Relocation { offset, kind: RelocationKind::Abs32, target: RelocationTarget::Base, addend };
+    /// let left = Product::with_relocations(vec![0x90, 0xb8, 0, 0, 0, 0, 0xc3], vec![base(2, 0)], EntityList::new());
+    /// let right = Product::with_relocations(vec![0x90, 0xb8, 0, 0, 0, 0, 0xcc], vec![base(2, 6)], EntityList::new());
+    ///
+    /// assert!(left.diff(&left.clone()).is_empty());
+    ///
+    /// let diff = left.diff(&right);
+    /// assert_eq!(diff.first_byte, Some(6));
+    /// assert_eq!(diff.lengths, None);
+    /// assert_eq!(diff.relocations, [RelocationDiff { offset: 2, left: Some(base(2, 0)), right: Some(base(2, 6)) }]);
+    /// ```
+    pub fn diff(&self, other: &Product) -> ProductDiff {
+        let first_byte = self.bytes.iter().zip(&other.bytes).position(|(a, b)| a != b);
+        let lengths = (self.bytes.len() != other.bytes.len()).then_some((self.bytes.len(), other.bytes.len()));
+
+        ProductDiff {
+            first_byte: first_byte.or(lengths.map(|(a, b)| a.min(b))),
+            lengths,
+            relocations: diff::diff_relocations(&self.named_relocations(), &other.named_relocations()),
+        }
+    }
+
+    /// Writes the product in the blob container format for `target`, see the [`blob`] module.
+    #[cfg(feature = "blob")]
+    pub fn write_blob(&self, target: &target::Target) -> Vec<u8> {
+        blob::Blob::from_product(self, target).write()
     }
 
     /// Finalizes the instruction stream output and returns the produced bytes.
+    ///
+    /// The relocations are dropped, leaving their fields unresolved; see [`Product::relocate`] to resolve them.  The uninitialized data takes no
+    /// bytes, and is left to the loader, see [`Product::bss_offset`].
     pub fn emit(self) -> Vec<u8> {
         self.bytes
     }
+
+    /// Applies the unresolved relocations for code loaded at `base`, resolving the address of each external symbol by name through `resolve`, and returns the
+    /// relocated bytes.  A displacement to a symbol which doesn't fit is retargeted to the [stub](stub) of the symbol, if the product has one.
+    ///
+    /// # Panics
+    /// Panics if a relocated value doesn't fit in its field, and no stub reaches the symbol.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, Product};
+    ///
+    /// let mut symbols = EntityList::new();
+    /// let puts = symbols.push(Symbol { name: "puts".to_string() });
+    ///
+    /// let product = Product::with_relocations(vec![0; 16], vec![
+    ///     Relocation { offset: 0, kind: RelocationKind::Abs32, target: RelocationTarget::Base, addend: 8 },
+    ///     Relocation { offset: 4, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    ///     Relocation { offset: 8, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(puts), addend: 0 },
+    /// ], symbols);
+    ///
+    /// let bytes = product.relocate(0x1000, |name| match name {
+    ///     "puts" => 0x2000,
+    ///     _ => unreachable!(),
+    /// });
+    ///
+    /// assert_eq!(bytes[0..4], 0x1008u32.to_le_bytes());
+    /// assert_eq!(bytes[4..8], (0x2000 - 4 - 0x1004u32).to_le_bytes());
+    /// assert_eq!(bytes[8..16], 0x2000u64.to_le_bytes());
+    /// ```
+    pub fn relocate(mut self, base: u64, mut resolve: impl FnMut(&str) -> u64) -> Vec<u8> {
+        let mut bytes = std::mem::take(&mut self.bytes);
+
+        for relocation in self.unresolved_relocations() {
+            let target = match relocation.target {
+                RelocationTarget::Base => base,
+                RelocationTarget::Absolute(address) => address,
+                RelocationTarget::Symbol(symbol) => resolve(&self.symbols.get(symbol).name),
+            };
+
+            let result = write_relocation(&mut bytes, relocation, relocation.offset, target, base).or_else(|value| match self.veneer(relocation) {
+                Some(stub) => write_relocation(&mut bytes, relocation, relocation.offset, base + stub as u64, base),
+                None => Err(value),
+            });
+
+            if let Err(value) = result {
+                match relocation.kind {
+                    RelocationKind::Rel32 | RelocationKind::GotTpOff | RelocationKind::GotPcRel | RelocationKind::RexGotPcRelX => {
+                        panic!("displacement {:#x} doesn't fit in 32 bits", value)
+                    },
+                    RelocationKind::Abs32 | RelocationKind::Abs64 => panic!("address {:#x} doesn't fit in 32 bits", value),
+                    RelocationKind::Abs16 => panic!("address {:#x} doesn't fit in 16 bits", value),
+                    RelocationKind::Branch26 => panic!("displacement {:#x} is out of the reach of the branch", value),
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Lays the product out as a flat binary image loaded at `origin`, as NASM's `org` does, for boot sectors and other freestanding blobs.
+    ///
+    /// Relocations against the load address and against the symbols the product defines are resolved for an image loaded at `origin`.  If `entry`
+    /// names a symbol defined past the start of the product, the image starts with an x86-64 `jmp rel32` to it, shifting the product by 5 bytes;
+    /// products are only relocated on x86-64 so far.
+    ///
+    /// A product holds a single section, so the image is the product itself: code and data are laid out in the order they were emitted.  The
+    /// uninitialized data isn't part of the image, see [`Product::to_flat_binary_with_bss`] for where the loader reserves it.
+    ///
+    /// # Errors
+    /// Returns [`LinkError::UndefinedEntry`] if the product doesn't define `entry`, [`LinkError::Unresolved`] if a relocation refers to a symbol
+    /// the product doesn't define or to the global offset table entry of a symbol, see [`RelocationKind::is_got`], and [`LinkError::OutOfRange`] or
+    /// [`LinkError::OriginOutOfRange`] if a relocated value doesn't fit in its field.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, LinkError, Product};
+    ///
+    /// let mut bytes = vec![
+    ///     0x8b, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00, // mov eax, dword ptr [data]
+    ///     0xf4,                                     // hlt
+    ///     0xeb, 0xfe,                               // jmp $
+    /// ];
+    /// bytes.resize(16, 0x00);                       // align 16
+    /// bytes.extend_from_slice(&[0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0]); // data: dd 0x12345678
+    /// bytes.extend_from_slice(&[0; 8]);             // pointer: dq data
+    ///
+    /// let mut symbols = EntityList::new();
+    /// let data = symbols.push(Symbol { name: "data".to_string() });
+    /// let mut product = Product::with_relocations(bytes, vec![
+    ///     Relocation { offset: 3, kind: RelocationKind::Abs32, target: RelocationTarget::Base, addend: 16 },
+    ///     Relocation { offset: 24, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(data), addend: 0 },
+    /// ], symbols);
+    /// product.define("start", 0);
+    /// product.define("data", 16);
+    ///
+    /// let image = product.to_flat_binary(0x7c00, Some("start")).unwrap();
+    /// assert_eq!(image.len(), 32);
+    /// assert_eq!(image[3..7], (0x7c00u32 + 16).to_le_bytes());
+    /// assert_eq!(image[24..32], (0x7c00u64 + 16).to_le_bytes());
+    ///
+    /// // an entry past the start is jumped to, shifting the product.
+    /// let image = product.to_flat_binary(0x7c00, Some("data")).unwrap();
+    /// assert_eq!(image[0..5], [0xe9, 0x10, 0x00, 0x00, 0x00]); // jmp data
+    /// assert_eq!(image[5 + 3..5 + 7], (0x7c05u32 + 16).to_le_bytes());
+    ///
+    /// assert_eq!(product.to_flat_binary(0x7c00, Some("main")), Err(LinkError::UndefinedEntry { name: "main".to_string() }));
+    /// assert_eq!(product.to_flat_binary(0x1_0000_0000, None), Err(LinkError::OriginOutOfRange { offset: 3 }));
+    ///
+    /// // a freestanding image has no loader to resolve external symbols.
+    /// let mut symbols = EntityList::new();
+    /// let puts = symbols.push(Symbol { name: "puts".to_string() });
+    /// let product = Product::with_relocations(vec![0xe8, 0, 0, 0, 0], vec![
+    ///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    /// ], symbols);
+    /// assert_eq!(product.to_flat_binary(0x7c00, None), Err(LinkError::Unresolved { name: "puts".to_string(), offset: 1 }));
+    /// ```
+    pub fn to_flat_binary(&self, origin: u64, entry: Option<&str>) -> Result<Vec<u8>, LinkError> {
+        self.to_flat_binary_with_bss(origin, entry).map(|(bytes, _)| bytes)
+    }
+
+    /// Lays the product out as a flat binary image loaded at `origin`, as [`Product::to_flat_binary`] does, and returns the range of the image
+    /// the loader reserves and zeroes for the uninitialized data, past the end of the image.  The range is empty if the product has no
+    /// uninitialized data.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Product::to_flat_binary`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{bss::{Bss, BSS_ALIGN}, entity::{EntityRef, LabelRef}, Product};
+    ///
+    /// let mut bss = Bss::new();
+    /// bss.reserve(LabelRef::new(0), 0x100, 8);
+    ///
+    /// let mut product = Product::new(vec![0x90; 16]);
+    /// product.set_bss(bss);
+    /// product.define("start", 0);
+    /// product.define("main", 4);
+    ///
+    /// let (image, bss) = product.to_flat_binary_with_bss(0x7c00, Some("start")).unwrap();
+    /// assert_eq!(image.len(), 16);
+    /// assert_eq!(bss, BSS_ALIGN..BSS_ALIGN + 0x100);
+    ///
+    /// // the entry jump shifts the uninitialized data along with the product.
+    /// let (image, bss) = product.to_flat_binary_with_bss(0x7c00, Some("main")).unwrap();
+    /// assert_eq!(image.len(), 5 + 16);
+    /// assert_eq!(bss, 5 + BSS_ALIGN..5 + BSS_ALIGN + 0x100);
+    /// ```
+    pub fn to_flat_binary_with_bss(&self, origin: u64, entry: Option<&str>) -> Result<(Vec<u8>, Range<usize>), LinkError> {
+        let entry = match entry {
+            Some(name) => {
+                let offset = self.definition(name).ok_or_else(|| LinkError::UndefinedEntry { name: name.to_string() })?;
+                Some((name, offset)).filter(|&(_, offset)| offset != 0)
+            },
+            None => None,
+        };
+
+        let mut bytes = Vec::with_capacity(self.bytes.len() + 5);
+        if let Some((name, offset)) = entry {
+            // the jump ends where the product starts.
+            let displacement = i32::try_from(offset).map_err(|_| LinkError::OutOfRange { name: name.to_string(), offset: 1 })?;
+            bytes.push(0xe9);
+            bytes.extend_from_slice(&displacement.to_le_bytes());
+        }
+
+        let start = bytes.len();
+        let base = origin + start as u64;
+        bytes.extend_from_slice(&self.bytes);
+
+        for relocation in self.unresolved_relocations() {
+            let offset = start + relocation.offset;
+
+            let (target, error) = match relocation.target {
+                RelocationTarget::Base => (base, LinkError::OriginOutOfRange { offset }),
+                RelocationTarget::Absolute(address) => (address, LinkError::OriginOutOfRange { offset }),
+                RelocationTarget::Symbol(symbol) => {
+                    // a flat binary has no global offset table.
+                    let name = &self.symbols.get(symbol).name;
+                    let definition = self.definition(name)
+                        .filter(|_| !relocation.kind.is_got())
+                        .ok_or_else(|| LinkError::Unresolved { name: name.clone(), offset })?;
+                    (base + definition as u64, LinkError::OutOfRange { name: name.clone(), offset })
+                },
+            };
+
+            write_relocation(&mut bytes, relocation, offset, target, origin).map_err(|_| error)?;
+        }
+
+        let bss = if self.bss.is_empty() {
+            bytes.len()..bytes.len()
+        } else {
+            start + self.bss_offset()..start + self.image_size()
+        };
+
+        Ok((bytes, bss))
+    }
+    /// Lays the product out as a boot sector loaded at `0x7c00`, padded with zeros to [`BOOT_SECTOR_SIZE`] bytes and ending with the `0x55 0xaa`
+    /// signature the BIOS checks before jumping to it.
+    ///
+    /// The product is laid out as by [`Product::to_flat_binary`], with no entry jump: the BIOS starts running the sector at its first byte, in
+    /// real mode.
+    ///
+    /// # Errors
+    /// Returns [`LinkError::BootSectorTooLarge`] if the product doesn't fit in the sector along with the signature, and the errors of
+    /// [`Product::to_flat_binary`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{LinkError, Product, BOOT_SECTOR_SIZE};
+    ///
+    /// let product = Product::new(vec![
+    ///     0xfa,       // cli
+    ///     0xf4,       // hlt
+    ///     0xeb, 0xfd, // jmp $ - 1
+    /// ]);
+    ///
+    /// let sector = product.to_boot_sector().unwrap();
+    /// assert_eq!(sector.len(), BOOT_SECTOR_SIZE);
+    /// assert_eq!(sector[..4], [0xfa, 0xf4, 0xeb, 0xfd]);
+    /// assert!(sector[4..510].iter().all(|&byte| byte == 0));
+    /// assert_eq!(sector[510..], [0x55, 0xaa]);
+    ///
+    /// assert_eq!(Product::new(vec![0xf4; 511]).to_boot_sector(), Err(LinkError::BootSectorTooLarge { size: 511 }));
+    /// ```
+    pub fn to_boot_sector(&self) -> Result<Vec<u8>, LinkError> {
+        let mut bytes = self.to_flat_binary(0x7c00, None)?;
+        if bytes.len() > BOOT_SECTOR_SIZE - 2 {
+            return Err(LinkError::BootSectorTooLarge { size: bytes.len() });
+        }
+
+        bytes.resize(BOOT_SECTOR_SIZE - 2, 0);
+        bytes.extend_from_slice(&[0x55, 0xaa]);
+        Ok(bytes)
+    }
+}
+
+/// Writes the value of `relocation` to its field at `offset` in `bytes`, for a `target` address and code loaded at `base`.  Returns the value if
+/// it doesn't fit in the field.
+fn write_relocation(bytes: &mut [u8], relocation: &Relocation, offset: usize, target: u64, base: u64) -> Result<(), i128> {
+    let value = (target as i128) + (relocation.addend as i128);
+    let field = &mut bytes[offset..offset + relocation.kind.size()];
+
+    match relocation.kind {
+        RelocationKind::Abs32 => field.copy_from_slice(&u32::try_from(value).map_err(|_| value)?.to_le_bytes()),
+        RelocationKind::Abs64 => field.copy_from_slice(&(value as u64).to_le_bytes()),
+        RelocationKind::Abs16 => field.copy_from_slice(&u16::try_from(value).map_err(|_| value)?.to_le_bytes()),
+        RelocationKind::Rel32 | RelocationKind::GotTpOff | RelocationKind::GotPcRel | RelocationKind::RexGotPcRelX => {
+            let value = value - (base as i128 + offset as i128);
+            field.copy_from_slice(&i32::try_from(value).map_err(|_| value)?.to_le_bytes());
+        },
+        RelocationKind::Branch26 => reloc::write_branch26(field, value - (base as i128 + offset as i128))?,
+    }
+
+    Ok(())
 }
 
+// products and their parts are assembled and combined across threads.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Product>();
+    assert_send_sync::<EntityList<Symbol, SymRef>>();
+    assert_send_sync::<Relocation>();
+    assert_send_sync::<link::ProductSet>();
+};
+
 /// A target-specific stream of instructions.
 /// 
 /// An instruction stream inputs instructions and immediately encodes them into machine code for its respective target architecture.  Relocation is performed later.
+///
+/// The trait is object safe, so a backend picked at runtime can be driven through a `Box<dyn InstructionStream>`.  Target-specific instructions are only
+/// available on the concrete types, but raw emission, labels and [`InstructionStream::finish`] work through the trait object as well.
 pub trait InstructionStream {
     /// Creates a new label.
     /// 
     /// The label will be created without being attached to any index.  If the label is used before it is attached, it will be used as a relocation rather than
     /// pre-calculating the offset.
+    #[must_use = "a label is only usable through the reference to it"]
     fn create_label(&mut self) -> LabelRef;
 
     /// Creates a new label and attaches it to the current index.
@@ -52,24 +1102,150 @@ pub trait InstructionStream {
 
     /// Writes a raw word into the instruction stream at the current index.
     /// 
-    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`].
+    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`], such as [`raw::RawStream`] in its [`raw::Endianness`].
     fn write_word(&mut self, word: u16);
 
     /// Writes a raw double word into the instruction stream at the current index.
     /// 
-    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`].
+    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`], such as [`raw::RawStream`] in its [`raw::Endianness`].
     fn write_double_word(&mut self, word: u32);
 
     /// Writes a raw quadruple word into the instruction stream at the current index.
     /// 
-    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`].
+    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`], such as [`raw::RawStream`] in its [`raw::Endianness`].
     fn write_quad_word(&mut self, word: u64);
 
     /// Writes a raw double quadruple word into the instruction stream at the current index.
     /// 
-    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`].
+    /// The value will be swapped into the endianness of the target, by the implementor of [`InstructionStream`], such as [`raw::RawStream`] in its [`raw::Endianness`].
     fn write_double_quad_word(&mut self, word: u128);
 
+    /// Writes a slice of raw bytes into the instruction stream at the current index.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// The current index, the number of bytes written to the instruction stream.
+    fn offset(&self) -> usize;
+
+    /// Copies the pre-encoded instruction bytes of a template into the instruction stream at the current index, and registers its holes
+    /// `fixups` at the offsets they land at with [`InstructionStream::record_template_fixup`], so they are resolved along with the other label
+    /// uses when the stream is finished.  See the [`template`] module.
+    ///
+    /// # Panics
+    /// Panics if the holes are invalid, see [`template::check_fixups`].
+    fn emit_template(&mut self, bytes: &[u8], fixups: &[TemplateFixup]) {
+        template::check_fixups(bytes, fixups);
+
+        let base = self.offset();
+        self.write_bytes(bytes);
+        for fixup in fixups {
+            self.record_template_fixup(TemplateFixup { offset: base + fixup.offset, ..*fixup });
+        }
+    }
+
+    /// Registers the hole `fixup` of a template written by [`InstructionStream::emit_template`], at its offset in the instruction stream, to be
+    /// filled when the stream is finished: the displacement of a label is written into the hole, and the address of a symbol or the absolute
+    /// address of a label are left to relocations.  The streams without label uses able to hold them keep them in a
+    /// [`template::TemplateHoles`].
+    fn record_template_fixup(&mut self, fixup: TemplateFixup);
+
     /// Finishes writing to the instruction stream and returns the produced bytes.
-    fn finish(self) -> Product;
+    #[must_use = "the product holds the generated code, see `InstructionStream::abandon` to discard it"]
+    fn finish(self) -> Product where Self: Sized;
+
+    /// Finishes writing to a boxed instruction stream and returns the produced bytes.  Implementors forward this to [`InstructionStream::finish`].
+    #[must_use = "the product holds the generated code, see `InstructionStream::abandon` to discard it"]
+    fn finish_boxed(self: Box<Self>) -> Product;
+
+    /// Discards the instruction stream without producing a product, such as when generating the code failed halfway.  Implementors which check
+    /// that a dropped stream was finished don't check an abandoned one.
+    fn abandon(self) where Self: Sized {}
+
+    /// Discards a boxed instruction stream without producing a product.  Implementors overriding [`InstructionStream::abandon`] forward this to it.
+    fn abandon_boxed(self: Box<Self>) {}
+}
+
+impl<S: InstructionStream + ?Sized> InstructionStream for Box<S> {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        (**self).create_label()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        (**self).create_label_attached()
+    }
+
+    #[inline(always)]
+    fn attach_label(&mut self, label: LabelRef) {
+        (**self).attach_label(label)
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        (**self).write_byte(byte)
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        (**self).write_word(word)
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        (**self).write_double_word(word)
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        (**self).write_quad_word(word)
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        (**self).write_double_quad_word(word)
+    }
+
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        (**self).write_bytes(bytes)
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        (**self).offset()
+    }
+
+    #[inline(always)]
+    fn emit_template(&mut self, bytes: &[u8], fixups: &[TemplateFixup]) {
+        (**self).emit_template(bytes, fixups)
+    }
+
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        (**self).record_template_fixup(fixup)
+    }
+
+    #[inline(always)]
+    fn finish(self) -> Product {
+        self.finish_boxed()
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish_boxed()
+    }
+
+    #[inline(always)]
+    fn abandon(self) {
+        self.abandon_boxed()
+    }
+
+    #[inline(always)]
+    fn abandon_boxed(self: Box<Self>) {
+        (*self).abandon_boxed()
+    }
 }
\ No newline at end of file
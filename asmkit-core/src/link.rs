@@ -0,0 +1,446 @@
+//! Linking products together in memory.
+//!
+//! [`link`] lays several products out one after the other in a single product, and resolves the relocations of each against the symbols defined by the
+//! others, so code generated by separate instruction streams can call into each other without going through an object file and a system linker.
+
+use std::{collections::HashMap, error::Error, fmt, sync::Mutex};
+
+use crate::{
+    calls::{CallSite, CallSiteTarget},
+    entity::{Binding, EntityList, SymRef, Symbol, SymbolDefinition},
+    listing::Comment,
+    patch::{PatchField, PatchPoint},
+    reloc::{write_branch26, Relocation, RelocationKind, RelocationTarget},
+    source_map::SourceMap,
+    stackmap::StackMaps,
+    stub::CallStub,
+    Product, BOOT_SECTOR_SIZE,
+};
+
+/// How [`link`] lays the products out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkOptions {
+    /// The alignment of the start of each product, a power of two.
+    pub alignment: usize,
+
+    /// The byte filling the gaps between products, such as `0xcc` (`int3`) on x86.
+    pub padding: u8,
+}
+
+impl Default for LinkOptions {
+    /// Aligns the products to 16 bytes, padding with zeroes.
+    fn default() -> Self {
+        Self { alignment: 16, padding: 0 }
+    }
+}
+
+/// An error which occurred while linking products together.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkError {
+    /// A symbol is defined more than once, at both offsets of the linked product.
+    DuplicateSymbol {
+        /// The name of the symbol.
+        name: String,
+
+        /// The offset of the first definition.
+        first: usize,
+
+        /// The offset of the second definition.
+        second: usize,
+    },
+
+    /// Two products of a [`ProductSet`] were inserted under the same name.
+    DuplicateProduct {
+        /// The name of the products.
+        name: String,
+    },
+
+    /// A relocation against a defined symbol doesn't fit in its field.
+    OutOfRange {
+        /// The name of the symbol.
+        name: String,
+
+        /// The offset of the relocated field in the linked product.
+        offset: usize,
+    },
+
+    /// A relocation refers to a symbol which no product defines, while laying out a flat binary with [`Product::to_flat_binary`].
+    Unresolved {
+        /// The name of the symbol.
+        name: String,
+
+        /// The offset of the relocated field in the flat binary.
+        offset: usize,
+    },
+
+    /// The entry symbol of a flat binary isn't defined by the product, see [`Product::to_flat_binary`].
+    UndefinedEntry {
+        /// The name of the symbol.
+        name: String,
+    },
+
+    /// A relocation against the origin of a flat binary, or a displacement from it to an absolute address, doesn't fit in its field, see
+    /// [`Product::to_flat_binary`].
+    OriginOutOfRange {
+        /// The offset of the relocated field in the flat binary.
+        offset: usize,
+    },
+
+    /// A patch point id is used by two products, at both offsets of the linked product.
+    DuplicatePatchPoint {
+        /// The id of the patch points.
+        id: u64,
+
+        /// The offset of the first patch point.
+        first: usize,
+
+        /// The offset of the second patch point.
+        second: usize,
+    },
+
+    /// A boot sector doesn't fit in the 510 bytes before its signature, see [`Product::to_boot_sector`].
+    BootSectorTooLarge {
+        /// The size of the flat binary image, in bytes.
+        size: usize,
+    },
+
+    /// A product reserves uninitialized data, whose labels are resolved against the end of the product and would overlap the next one.
+    Bss {
+        /// The index of the product among the linked ones.
+        index: usize,
+    },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateSymbol { name, first, second } => {
+                write!(f, "symbol `{}` is defined twice, at {:#x} and {:#x}", name, first, second)
+            },
+            Self::DuplicateProduct { name } => write!(f, "product `{}` is inserted twice", name),
+            Self::OutOfRange { name, offset } => write!(f, "relocation against `{}` at {:#x} is out of range", name, offset),
+            Self::Unresolved { name, offset } => write!(f, "relocation against `{}` at {:#x} is unresolved", name, offset),
+            Self::UndefinedEntry { name } => write!(f, "entry symbol `{}` is undefined", name),
+            Self::OriginOutOfRange { offset } => write!(f, "relocation against the origin at {:#x} is out of range", offset),
+            Self::DuplicatePatchPoint { id, first, second } => {
+                write!(f, "patch point {} is emitted twice, at {:#x} and {:#x}", id, first, second)
+            },
+            Self::BootSectorTooLarge { size } => write!(f, "boot sector of {} bytes doesn't fit in {} bytes", size, BOOT_SECTOR_SIZE - 2),
+            Self::Bss { index } => write!(f, "product #{} reserves uninitialized data, which can't be linked", index),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+/// Rounds `value` up to a multiple of `align`, which is a power of two.
+#[inline(always)]
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// Returns true if `definition` yields to the other definitions of its symbol, as a weak definition or one of a COMDAT group does.
+fn yields(definition: &SymbolDefinition) -> bool {
+    definition.binding == Binding::Weak || definition.comdat.is_some()
+}
+
+/// Links `products` into a single product, laid out in order and each aligned as requested by `options`.
+///
+/// The symbols defined by every product make up the symbol table of the linked product.  Relocations against them are resolved:
+///
+/// - relative ones are patched in place, as their value doesn't depend on the load address;
+/// - absolute ones become relocations against the load address of the linked product.
+///
+/// Relocations against symbols which no product defines are kept, to be resolved by [`Product::relocate`].  The comments, source maps,
+/// patchable fields, patch points, call sites, stubs and stack maps of the products are kept at the offsets they land at; the labels of the call sites are those of the instruction
+/// stream which emitted them.  A relative relocation against a symbol out of reach is retargeted to the [stub](crate::stub) of the symbol, if its
+/// product has one.
+///
+/// # Errors
+/// Returns [`LinkError::DuplicateSymbol`] if two products define the same symbol, [`LinkError::DuplicatePatchPoint`] if two products emit a
+/// patch point with the same id, [`LinkError::OutOfRange`] if a relative relocation doesn't fit in its field, and [`LinkError::Bss`] if a product
+/// reserves uninitialized data.
+///
+/// # Panics
+/// Panics if `options.alignment` is not a power of two.
+///
+/// # Example
+/// ```
+/// use asmkit_core::{
+///     entity::{EntityList, Symbol},
+///     link, LinkError, LinkOptions, Product,
+///     reloc::{Relocation, RelocationKind, RelocationTarget},
+/// };
+///
+/// // calls `b`, then `puts`.
+/// let mut symbols = EntityList::new();
+/// let b = symbols.push(Symbol { name: "b".to_string() });
+/// let puts = symbols.push(Symbol { name: "puts".to_string() });
+/// let mut first = Product::with_relocations(vec![0xe8, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0], vec![
+///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(b), addend: -4 },
+///     Relocation { offset: 6, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+/// ], symbols);
+/// first.define("a", 0);
+///
+/// let mut second = Product::new(vec![0xc3]);
+/// second.define("b", 0);
+///
+/// let options = LinkOptions { alignment: 16, padding: 0xcc };
+/// let linked = link(vec![first.clone(), second.clone()], options).unwrap();
+///
+/// assert_eq!(linked.bytes(), [
+///     0xe8, 0x0b, 0, 0, 0, // call b
+///     0xe8, 0, 0, 0, 0,    // call puts
+///     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+///     0xc3,                // b: ret
+/// ]);
+/// assert_eq!(linked.definition("b"), Some(16));
+///
+/// // `puts` is left for the loader.
+/// let relocation = linked.relocations()[0];
+/// assert_eq!(linked.relocations().len(), 1);
+/// assert_eq!(relocation.offset, 6);
+/// assert!(matches!(relocation.target, RelocationTarget::Symbol(symbol) if linked.symbol(symbol).name == "puts"));
+///
+/// assert_eq!(link(vec![second.clone(), first, second], options), Err(LinkError::DuplicateSymbol {
+///     name: "b".to_string(),
+///     first: 0,
+///     second: 32,
+/// }));
+/// ```
+pub fn link(products: Vec<Product>, options: LinkOptions) -> Result<Product, LinkError> {
+    assert!(options.alignment.is_power_of_two(), "link alignment must be a power of two");
+
+    let mut bytes = Vec::new();
+    let mut bases = Vec::with_capacity(products.len());
+    let mut definitions = HashMap::new();
+    let mut linked_definitions = Vec::new();
+    let mut comments = Vec::new();
+    let mut patch_fields = Vec::new();
+    let mut source_map = SourceMap::default();
+    let mut stack_maps = StackMaps::default();
+    let mut patch_points: Vec<PatchPoint> = Vec::new();
+
+    if let Some(index) = products.iter().position(|product| !product.bss().is_empty()) {
+        return Err(LinkError::Bss { index });
+    }
+
+    for product in &products {
+        bytes.resize(align_up(bytes.len(), options.alignment), options.padding);
+
+        let base = bytes.len();
+        bases.push(base);
+        bytes.extend_from_slice(product.bytes());
+
+        for definition in product.definitions() {
+            let offset = base + definition.offset;
+
+            // the weak definitions and those of the COMDAT groups yield to the other definitions of their symbol, the first being kept.
+            if let Some(&first) = definitions.get(&definition.name) {
+                let kept = linked_definitions.iter_mut().find(|kept: &&mut SymbolDefinition| kept.name == definition.name).unwrap();
+                match (yields(kept), yields(definition)) {
+                    (_, true) => continue,
+                    (true, false) => *kept = SymbolDefinition { offset, ..definition.clone() },
+                    (false, false) => return Err(LinkError::DuplicateSymbol { name: definition.name.clone(), first, second: offset }),
+                }
+
+                definitions.insert(definition.name.clone(), offset);
+                continue;
+            }
+
+            definitions.insert(definition.name.clone(), offset);
+            linked_definitions.push(SymbolDefinition { offset, ..definition.clone() });
+        }
+
+        source_map.append(base, product.source_map());
+        stack_maps.append(base, product.stack_maps());
+        comments.extend(product.comments().iter().map(|comment| Comment { offset: base + comment.offset, ..comment.clone() }));
+        patch_fields.extend(product.patch_fields().iter().map(|field| PatchField { offset: base + field.offset, ..*field }));
+
+        for point in product.patch_points() {
+            let offset = base + point.offset;
+
+            if let Some(first) = patch_points.iter().find(|other| other.id == point.id) {
+                return Err(LinkError::DuplicatePatchPoint { id: point.id, first: first.offset, second: offset });
+            }
+
+            patch_points.push(PatchPoint { offset, ..*point });
+        }
+    }
+
+    let mut relocations = Vec::new();
+    let mut symbols: EntityList<Symbol, SymRef> = EntityList::new();
+    let mut externals: HashMap<String, SymRef> = HashMap::new();
+
+    for (product, &base) in products.iter().zip(&bases) {
+        for relocation in product.unresolved_relocations() {
+            let offset = base + relocation.offset;
+
+            let symbol = match relocation.target {
+                // the addend of a label address is relative to the start of its product.
+                RelocationTarget::Base => {
+                    relocations.push(Relocation { offset, addend: relocation.addend + base as i64, ..*relocation });
+                    continue;
+                },
+                // the address of an absolute target is the same in every product.
+                RelocationTarget::Absolute(_) => {
+                    relocations.push(Relocation { offset, ..*relocation });
+                    continue;
+                },
+                RelocationTarget::Symbol(symbol) => &product.symbol(symbol).name,
+            };
+
+            // the entries of the global offset table are left to the loader.
+            let Some(&target) = definitions.get(symbol).filter(|_| !relocation.kind.is_got()) else {
+                let external = *externals.entry(symbol.clone()).or_insert_with(|| symbols.push(Symbol { name: symbol.clone() }));
+                relocations.push(Relocation { offset, target: RelocationTarget::Symbol(external), ..*relocation });
+                continue;
+            };
+
+            let addend = relocation.addend + target as i64;
+            match relocation.kind {
+                RelocationKind::Rel32 => {
+                    // a displacement out of reach goes through the stub of the symbol, if the product has one.
+                    let value = i32::try_from(addend - offset as i64)
+                        .or_else(|error| product.stub(symbol).map(|stub| (relocation.addend + (base + stub) as i64 - offset as i64) as i32).ok_or(error))
+                        .map_err(|_| LinkError::OutOfRange { name: symbol.clone(), offset })?;
+                    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+                },
+                RelocationKind::Branch26 => {
+                    let field = &mut bytes[offset..offset + 4];
+                    write_branch26(field, (addend - offset as i64) as i128)
+                        .or_else(|error| match product.stub(symbol) {
+                            Some(stub) => write_branch26(field, (relocation.addend + (base + stub) as i64 - offset as i64) as i128),
+                            None => Err(error),
+                        })
+                        .map_err(|_| LinkError::OutOfRange { name: symbol.clone(), offset })?;
+                },
+                RelocationKind::Abs16 | RelocationKind::Abs32 | RelocationKind::Abs64 => {
+                    relocations.push(Relocation { offset, target: RelocationTarget::Base, addend, ..*relocation });
+                },
+                RelocationKind::GotTpOff | RelocationKind::GotPcRel | RelocationKind::RexGotPcRelX => unreachable!(),
+            }
+        }
+    }
+
+    relocations.sort_by_key(|relocation| relocation.offset);
+
+    // the symbols of the call sites are kept once resolved, so the calls to a function can still be looked up by name.
+    let mut call_sites = Vec::new();
+    let mut stubs = Vec::new();
+    for (product, base) in products.iter().zip(&bases) {
+        for site in product.call_sites() {
+            let target = match site.target {
+                CallSiteTarget::Label(label) => CallSiteTarget::Label(label),
+                CallSiteTarget::Symbol(symbol) => {
+                    let name = &product.symbol(symbol).name;
+                    CallSiteTarget::Symbol(*externals.entry(name.clone()).or_insert_with(|| symbols.push(Symbol { name: name.clone() })))
+                },
+            };
+
+            call_sites.push(CallSite { offset: base + site.offset, target, ..*site });
+        }
+
+        for stub in product.stubs() {
+            let name = &product.symbol(stub.symbol).name;
+            let symbol = *externals.entry(name.clone()).or_insert_with(|| symbols.push(Symbol { name: name.clone() }));
+            stubs.push(CallStub { symbol, offset: base + stub.offset });
+        }
+    }
+
+    let mut linked = Product::with_relocations(bytes, relocations, symbols);
+    linked.definitions = linked_definitions;
+    linked.comments = comments;
+    linked.source_map = source_map;
+    linked.patch_fields = patch_fields;
+    linked.call_sites = call_sites;
+    linked.stack_maps = stack_maps;
+    linked.patch_points = patch_points;
+    linked.stubs = stubs;
+    for name in products.iter().flat_map(|product| &product.mangled) {
+        linked.mark_mangled(name);
+    }
+    Ok(linked)
+}
+
+/// A collection of named products, filled from any number of threads and linked together by [`ProductSet::combine`].
+///
+/// The products are linked in the order of their names, so the linked product doesn't depend on the order they were inserted in.
+///
+/// # Example
+/// ```
+/// use asmkit_core::{LinkOptions, Product, ProductSet};
+///
+/// let assemble = |reversed: bool| {
+///     let set = ProductSet::new();
+///
+///     std::thread::scope(|scope| {
+///         for thread in 0..4u8 {
+///             let set = &set;
+///             let thread = if reversed { 3 - thread } else { thread };
+///
+///             scope.spawn(move || {
+///                 for function in 0..8u8 {
+///                     let id = thread * 8 + function;
+///                     let mut product = Product::new(vec![id; 3]);
+///                     product.define(&format!("f{:02}", id), 0);
+///                     set.insert(&format!("f{:02}", id), product);
+///                 }
+///             });
+///         }
+///     });
+///
+///     set.combine(LinkOptions::default()).unwrap()
+/// };
+///
+/// let linked = assemble(false);
+/// assert_eq!(linked.bytes().len(), 31 * 16 + 3);
+/// assert_eq!(linked.definition("f05"), Some(5 * 16));
+/// assert_eq!(linked.bytes()[5 * 16..5 * 16 + 3], [5; 3]);
+///
+/// for _ in 0..8 {
+///     assert_eq!(assemble(true), linked);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ProductSet {
+    /// The products inserted so far, by name.
+    products: Mutex<Vec<(String, Product)>>,
+}
+
+impl ProductSet {
+    /// Creates a new, empty product set.
+    pub fn new() -> Self {
+        Self { products: Mutex::new(Vec::new()) }
+    }
+
+    /// Inserts `product` under `name`, which decides its position in the linked product.
+    pub fn insert(&self, name: &str, product: Product) {
+        self.products.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push((name.to_string(), product));
+    }
+
+    /// The number of products inserted so far.
+    pub fn len(&self) -> usize {
+        self.products.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    /// Returns true if no product was inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Links the products together with [`link`], ordered by name.
+    ///
+    /// # Errors
+    /// Returns [`LinkError::DuplicateProduct`] if two products were inserted under the same name, and any error returned by [`link`].
+    pub fn combine(self, options: LinkOptions) -> Result<Product, LinkError> {
+        let mut products = self.products.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        products.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        if let Some(pair) = products.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(LinkError::DuplicateProduct { name: pair[0].0.clone() });
+        }
+
+        link(products.into_iter().map(|(_, product)| product).collect(), options)
+    }
+}
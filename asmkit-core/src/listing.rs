@@ -0,0 +1,77 @@
+//! Listings of products, dumping their bytes along with the symbols they define and the comments left by the code generating them, see
+//! [`Product::listing`].
+
+use std::fmt;
+
+use crate::Product;
+
+/// A comment attached to an offset of a product, such as an annotation of the compiler generating the code.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Comment {
+    /// The offset the comment is attached to.
+    pub offset: usize,
+
+    /// The text of the comment.
+    pub text: String,
+}
+
+/// A hexdump of a product, returned by [`Product::listing`].
+///
+/// Each line holds the offset of its first byte and up to 16 bytes.  The symbols defined by the product and its comments are printed on their own
+/// lines, before the bytes at their offset: a line ends early at the next symbol or comment, so the bytes following a comment are those of the
+/// instruction it annotates.
+pub struct Listing<'a> {
+    product: &'a Product,
+}
+
+impl<'a> Listing<'a> {
+    /// The number of bytes on a line.
+    const BYTES_PER_LINE: usize = 16;
+
+    pub(crate) fn new(product: &'a Product) -> Self {
+        Self { product }
+    }
+}
+
+impl fmt::Display for Listing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.product.bytes();
+
+        // the sorts are stable, so annotations of the same offset keep their order.
+        let mut definitions = self.product.definitions().iter().collect::<Vec<_>>();
+        definitions.sort_by_key(|definition| definition.offset);
+        let mut comments = self.product.comments().iter().collect::<Vec<_>>();
+        comments.sort_by_key(|comment| comment.offset);
+
+        let mut definitions = definitions.into_iter().peekable();
+        let mut comments = comments.into_iter().peekable();
+
+        let mut offset = 0;
+        loop {
+            while let Some(definition) = definitions.next_if(|definition| definition.offset <= offset) {
+                writeln!(f, "{}:", definition.name)?;
+            }
+
+            while let Some(comment) = comments.next_if(|comment| comment.offset <= offset) {
+                for line in comment.text.lines() {
+                    writeln!(f, "; {}", line)?;
+                }
+            }
+
+            if offset >= bytes.len() {
+                return Ok(());
+            }
+
+            let next = [definitions.peek().map(|definition| definition.offset), comments.peek().map(|comment| comment.offset)];
+            let end = next.into_iter().flatten().fold(bytes.len().min(offset + Self::BYTES_PER_LINE), usize::min);
+
+            write!(f, "{:08x} ", offset)?;
+            for byte in &bytes[offset..end] {
+                write!(f, " {:02x}", byte)?;
+            }
+            writeln!(f)?;
+
+            offset = end;
+        }
+    }
+}
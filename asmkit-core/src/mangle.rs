@@ -0,0 +1,83 @@
+//! Name mangling, turning the names of functions into the symbols a toolchain expects for them.
+//!
+//! - [`mangle_c`] decorates a C function for the object format of a [`Target`], prefixing an underscore on Mach-O.
+//! - [`mangle_stdcall`] decorates a `__stdcall` function of 32-bit Windows, with a leading underscore and the size of its arguments.
+//! - [`mangle_rust_v0`] names an internal function in the v0 mangling scheme of Rust, so the tools demangle it as a Rust path.
+//!
+//! The instruction streams holding a [`Target`] mangle the names of the symbols they create and define with [`mangle_c`], and take the names
+//! already mangled by their `*_raw_symbol` variants, marking both in the product so [`write_object`](crate::write_object) mangles only the
+//! names left as given, see [`Product::mark_mangled`](crate::Product::mark_mangled).
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{mangle::{mangle_c, mangle_rust_v0, mangle_stdcall}, target::Target};
+//!
+//! assert_eq!(mangle_c("malloc", &Target::parse("x86_64-unknown-linux-gnu").unwrap()), "malloc");
+//! assert_eq!(mangle_c("malloc", &Target::parse("aarch64-apple-darwin").unwrap()), "_malloc");
+//! assert_eq!(mangle_c("malloc", &Target::parse("x86_64-pc-windows-msvc").unwrap()), "malloc");
+//! assert_eq!(mangle_stdcall("MessageBoxA", 16), "_MessageBoxA@16");
+//! assert_eq!(mangle_rust_v0("jit", &["module", "add"]), "_RNvNvC3jit6module3add");
+//! ```
+
+use crate::target::{ObjectFormat, Target};
+
+/// The symbol of the C function `name` on `target`: prefixed by an underscore on Mach-O, unchanged on ELF and on 64-bit COFF.
+pub fn mangle_c(name: &str, target: &Target) -> String {
+    match target.object_format {
+        ObjectFormat::MachO => format!("_{}", name),
+        ObjectFormat::Elf | ObjectFormat::Coff => name.to_string(),
+    }
+}
+
+/// The symbol of the `__stdcall` function `name` of 32-bit Windows, whose arguments take `bytes` bytes on the stack: `_name@bytes`.
+pub fn mangle_stdcall(name: &str, bytes: u16) -> String {
+    format!("_{}@{}", name, bytes)
+}
+
+/// The symbol of the function at `path` in the crate `crate_name`, in the v0 mangling scheme of Rust, without a disambiguator.
+///
+/// # Panics
+/// Panics if `crate_name` or an element of `path` is empty or isn't an ASCII identifier, as the identifiers needing Punycode aren't supported.
+///
+/// # Example
+/// ```
+/// use asmkit_core::mangle::mangle_rust_v0;
+///
+/// assert_eq!(mangle_rust_v0("jit", &[]), "_RC3jit");
+/// assert_eq!(mangle_rust_v0("jit", &["f"]), "_RNvC3jit1f");
+///
+/// // an identifier starting with a digit or an underscore is separated from its length.
+/// assert_eq!(mangle_rust_v0("jit", &["_start"]), "_RNvC3jit6__start");
+/// ```
+///
+/// ```should_panic
+/// use asmkit_core::mangle::mangle_rust_v0;
+///
+/// mangle_rust_v0("jit", &["größe"]);
+/// ```
+pub fn mangle_rust_v0(crate_name: &str, path: &[&str]) -> String {
+    let mut symbol = String::from("_R");
+    symbol.extend(path.iter().map(|_| "Nv"));
+
+    symbol.push('C');
+    push_identifier(&mut symbol, crate_name);
+    for name in path {
+        push_identifier(&mut symbol, name);
+    }
+
+    symbol
+}
+
+/// Writes `name` as an identifier of the v0 mangling scheme: its length, followed by the identifier.
+fn push_identifier(symbol: &mut String, name: &str) {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    assert!(valid, "`{}` isn't an ASCII identifier", name);
+
+    symbol.push_str(&name.len().to_string());
+    if name.starts_with(|c: char| c == '_' || c.is_ascii_digit()) {
+        symbol.push('_');
+    }
+
+    symbol.push_str(name);
+}
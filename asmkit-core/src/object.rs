@@ -0,0 +1,495 @@
+//! Object files, carrying a product to the linkers of the system.
+//!
+//! [`write_object`] writes a product in the object format of a [`Target`], for the system linker to link with the objects of other compilers.
+//! Only the relocatable ELF objects of x86-64 and AArch64 are written so far; the other formats and architectures return an [`ObjectError`].
+//!
+//! The code of a product becomes the `.text` section.  The symbols defined by the product are global or weak symbols of the section, as their
+//! [`Binding`], and the symbols it refers to without defining them are undefined, typed as thread-local variables if they are accessed
+//! through a [`RelocationKind::GotTpOff`].  As a product is a single section, its code is the member of the COMDAT group of its definitions if
+//! they are in one, whose signature is the symbol named after the group; the ELF groups only have the
+//! [`SelectionKind::Any`](crate::entity::SelectionKind::Any) selection.
+//!
+//! The names of the symbols are mangled for the target with [`mangle_c`], but for those the product marks as already mangled, see
+//! [`Product::mark_mangled`], such as the names an instruction stream with a target mangled itself or was given raw.
+//!
+//! A relocation against the load address refers to the symbol of the section, and a relocation against a fixed address to the null symbol,
+//! with the address in its addend.  The source map of the product, if it isn't empty, becomes the `.debug_line` section generated by
+//! [`debug_line`](crate::dwarf::debug_line), the address of its sequence relocated against the code.  A product reserving uninitialized data can't be written, as the instruction streams resolve the
+//! displacements to it in place, from the end of the code.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{target::{ObjectFormat, Target}, write_object, Architecture, ObjectError, Product};
+//!
+//! let mut product = Product::new(vec![0x31, 0xc0, 0xc3]); // xor eax, eax; ret
+//! product.define("main", 0);
+//!
+//! let object = write_object(&product, &Target::parse("x86_64-unknown-linux-gnu").unwrap()).unwrap();
+//! assert_eq!(object[..4], *b"\x7fELF");
+//! assert_eq!(object[16..20], [0x01, 0x00, 0x3e, 0x00]); // ET_REL, EM_X86_64
+//!
+//! assert_eq!(write_object(&product, &Target::parse("x86_64-pc-windows-msvc").unwrap()), Err(ObjectError::UnsupportedFormat(ObjectFormat::Coff)));
+//! assert_eq!(write_object(&product, &Target::parse("aarch64-apple-darwin").unwrap()), Err(ObjectError::UnsupportedFormat(ObjectFormat::MachO)));
+//! assert_eq!(write_object(&product, &Target::parse("riscv64gc-unknown-linux-gnu").unwrap()), Err(ObjectError::UnsupportedArchitecture {
+//!     format: ObjectFormat::Elf,
+//!     architecture: Architecture::Riscv64,
+//! }));
+//! ```
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{
+    calls::{CallKind, CallSiteTarget},
+    dwarf,
+    entity::{Binding, Comdat},
+    mangle::mangle_c,
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    target::{ObjectFormat, Target},
+    Architecture, Product,
+};
+
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_GROUP: u32 = 17;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+const SHF_GROUP: u64 = 0x200;
+
+const GRP_COMDAT: u32 = 1;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STT_TLS: u8 = 6;
+
+const R_X86_64_64: u32 = 1;
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+const R_X86_64_GOTPCREL: u32 = 9;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_16: u32 = 12;
+const R_X86_64_GOTTPOFF: u32 = 22;
+const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+const R_AARCH64_ABS64: u32 = 257;
+const R_AARCH64_ABS32: u32 = 258;
+const R_AARCH64_ABS16: u32 = 259;
+const R_AARCH64_PREL32: u32 = 261;
+const R_AARCH64_JUMP26: u32 = 282;
+const R_AARCH64_CALL26: u32 = 283;
+
+/// The size of an ELF header.
+const EHDR_SIZE: usize = 64;
+
+/// The size of a section header.
+const SHDR_SIZE: usize = 64;
+
+/// The size of a symbol table entry.
+const SYM_SIZE: usize = 24;
+
+/// The size of a relocation table entry with an addend.
+const RELA_SIZE: usize = 24;
+
+/// An error which occurred while writing an object file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectError {
+    /// No writer exists yet for the object format of the target.
+    UnsupportedFormat(ObjectFormat),
+
+    /// The writer of the object format doesn't support the architecture of the target.
+    UnsupportedArchitecture {
+        /// The object format of the target.
+        format: ObjectFormat,
+
+        /// The architecture of the target.
+        architecture: Architecture,
+    },
+
+    /// The object format has no relocation for the kind of a relocation of the product.
+    UnsupportedRelocation {
+        /// The offset of the relocated field.
+        offset: usize,
+
+        /// The kind of the relocation.
+        kind: RelocationKind,
+    },
+
+    /// The product defines symbols in two COMDAT groups, while its code is a single section, the member of a single group.
+    ComdatGroups {
+        /// The name of the first group.
+        first: String,
+
+        /// The name of the second group.
+        second: String,
+    },
+
+    /// The product reserves uninitialized data, which the object files can't place at the offset the code expects it at.
+    Bss,
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => write!(f, "no object writer exists for {:?}", format),
+            Self::UnsupportedArchitecture { format, architecture } => {
+                write!(f, "the {:?} object writer doesn't support {}", format, architecture)
+            },
+            Self::UnsupportedRelocation { offset, kind } => write!(f, "relocation {:?} at {:#x} has no equivalent in the object format", kind, offset),
+            Self::ComdatGroups { first, second } => {
+                write!(f, "the product defines symbols in the COMDAT groups `{}` and `{}`, but is a single section", first, second)
+            },
+            Self::Bss => write!(f, "the product reserves uninitialized data, which can't be written to an object file"),
+        }
+    }
+}
+
+impl Error for ObjectError {}
+
+/// Writes `product` as an object file of `target`, see the [module](self) documentation.
+///
+/// # Errors
+/// Returns [`ObjectError::UnsupportedFormat`] for the COFF and Mach-O targets, whose writers don't exist yet, and
+/// [`ObjectError::UnsupportedArchitecture`] for the ELF targets other than x86-64.  Returns [`ObjectError::UnsupportedRelocation`] if a
+/// relocation of the product has no equivalent in the object format, [`ObjectError::ComdatGroups`] if the product defines symbols in two COMDAT
+/// groups, and [`ObjectError::Bss`] if the product reserves uninitialized data.
+pub fn write_object(product: &Product, target: &Target) -> Result<Vec<u8>, ObjectError> {
+    match target.object_format {
+        ObjectFormat::Elf => write_elf(product, target),
+        format => Err(ObjectError::UnsupportedFormat(format)),
+    }
+}
+
+/// A string table, the names of the sections or of the symbols of an ELF object.
+struct StringTable {
+    /// The strings, each terminated by a null byte, after the empty string.
+    bytes: Vec<u8>,
+
+    /// The offsets of the strings added so far.
+    offsets: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn new() -> Self {
+        Self { bytes: vec![0], offsets: HashMap::new() }
+    }
+
+    /// Adds `string` to the table if it isn't yet, and returns its offset, 0 for the empty string.
+    fn add(&mut self, string: &str) -> u32 {
+        if string.is_empty() {
+            return 0;
+        }
+
+        if let Some(offset) = self.offsets.get(string) {
+            return *offset;
+        }
+
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(string.as_bytes());
+        self.bytes.push(0);
+        self.offsets.insert(string.to_string(), offset);
+        offset
+    }
+}
+
+/// A section of an ELF object.
+struct Section {
+    name: &'static str,
+    kind: u32,
+    flags: u64,
+    align: u64,
+    link: u32,
+    info: u32,
+    entsize: u64,
+    data: Vec<u8>,
+}
+
+impl Section {
+    fn new(name: &'static str, kind: u32, flags: u64, align: u64, data: Vec<u8>) -> Self {
+        Self { name, kind, flags, align, link: 0, info: 0, entsize: 0, data }
+    }
+}
+
+/// The symbol table of an ELF object, with its string table.
+struct SymbolTable {
+    entries: Vec<u8>,
+    names: StringTable,
+
+    /// The indices of the named symbols.
+    indices: HashMap<String, u32>,
+}
+
+impl SymbolTable {
+    /// Creates a symbol table holding the null symbol.
+    fn new() -> Self {
+        Self { entries: vec![0; SYM_SIZE], names: StringTable::new(), indices: HashMap::new() }
+    }
+
+    /// Adds a symbol, and returns its index.
+    fn push(&mut self, name: &str, binding: u8, kind: u8, section: u16, value: u64) -> u32 {
+        let index = (self.entries.len() / SYM_SIZE) as u32;
+        let name_offset = self.names.add(name);
+
+        self.entries.extend_from_slice(&name_offset.to_le_bytes());
+        self.entries.push(binding << 4 | kind);
+        self.entries.push(0); // STV_DEFAULT
+        self.entries.extend_from_slice(&section.to_le_bytes());
+        self.entries.extend_from_slice(&value.to_le_bytes());
+        self.entries.extend_from_slice(&0u64.to_le_bytes()); // the size is unknown
+
+        if !name.is_empty() {
+            self.indices.insert(name.to_string(), index);
+        }
+
+        index
+    }
+
+    fn len(&self) -> u32 {
+        (self.entries.len() / SYM_SIZE) as u32
+    }
+}
+
+/// Writes an entry of a relocation table with addends.
+fn write_rela(table: &mut Vec<u8>, offset: usize, symbol: u32, kind: u32, addend: i64) {
+    table.extend_from_slice(&(offset as u64).to_le_bytes());
+    table.extend_from_slice(&((symbol as u64) << 32 | kind as u64).to_le_bytes());
+    table.extend_from_slice(&addend.to_le_bytes());
+}
+
+/// Maps a relocation of a product to the type of the ELF relocation of an architecture, if it has one.
+type RelocationType = fn(&Product, &Relocation) -> Option<u32>;
+
+/// The type of the x86-64 ELF relocation of `relocation`.  A displacement to a symbol in a direct call is a `R_X86_64_PLT32`, so the linker
+/// may route the call through the procedure linkage table when the symbol is in a shared library.
+fn x86_64_relocation_type(product: &Product, relocation: &Relocation) -> Option<u32> {
+    match relocation.kind {
+        RelocationKind::Abs64 => Some(R_X86_64_64),
+        RelocationKind::Abs32 => Some(R_X86_64_32),
+        RelocationKind::Abs16 => Some(R_X86_64_16),
+        RelocationKind::GotPcRel => Some(R_X86_64_GOTPCREL),
+        RelocationKind::GotTpOff => Some(R_X86_64_GOTTPOFF),
+        RelocationKind::RexGotPcRelX => Some(R_X86_64_REX_GOTPCRELX),
+        RelocationKind::Rel32 => {
+            let call = product.call_sites().iter().any(|site| {
+                site.kind == CallKind::Direct && matches!(site.target, CallSiteTarget::Symbol(_)) && (site.offset..site.offset + site.len).contains(&relocation.offset)
+            });
+
+            Some(if call && matches!(relocation.target, RelocationTarget::Symbol(_)) { R_X86_64_PLT32 } else { R_X86_64_PC32 })
+        },
+        _ => None,
+    }
+}
+
+/// The type of the AArch64 ELF relocation of `relocation`.  A [`RelocationKind::Branch26`] of a `bl` is a `R_AARCH64_CALL26` and that of a `b`
+/// a `R_AARCH64_JUMP26`, as the linker only routes calls through the procedure linkage table.
+fn aarch64_relocation_type(product: &Product, relocation: &Relocation) -> Option<u32> {
+    match relocation.kind {
+        RelocationKind::Abs64 => Some(R_AARCH64_ABS64),
+        RelocationKind::Abs32 => Some(R_AARCH64_ABS32),
+        RelocationKind::Abs16 => Some(R_AARCH64_ABS16),
+        RelocationKind::Rel32 => Some(R_AARCH64_PREL32),
+        RelocationKind::Branch26 => {
+            let instruction = u32::from_le_bytes(product.bytes()[relocation.offset..relocation.offset + 4].try_into().unwrap());
+            Some(if instruction & 0xfc00_0000 == 0x9400_0000 { R_AARCH64_CALL26 } else { R_AARCH64_JUMP26 })
+        },
+        _ => None,
+    }
+}
+
+/// The name of the symbol `name` of `product` in an object for `target`, mangled with [`mangle_c`] unless the product marks it as already mangled.
+fn symbol_name(product: &Product, target: &Target, name: &str) -> String {
+    if product.is_mangled(name) {
+        name.to_string()
+    } else {
+        mangle_c(name, target)
+    }
+}
+
+/// Writes `product` as a relocatable ELF object of `target`.
+fn write_elf(product: &Product, target: &Target) -> Result<Vec<u8>, ObjectError> {
+    let (machine, relocation_type): (u16, RelocationType) = match target.architecture {
+        Architecture::X86_64 => (EM_X86_64, x86_64_relocation_type),
+        Architecture::Aarch64 => (EM_AARCH64, aarch64_relocation_type),
+        architecture => return Err(ObjectError::UnsupportedArchitecture { format: ObjectFormat::Elf, architecture }),
+    };
+
+    if !product.bss().is_empty() {
+        return Err(ObjectError::Bss);
+    }
+
+    let mut comdat: Option<&Comdat> = None;
+    for group in product.definitions().iter().filter_map(|definition| definition.comdat.as_ref()) {
+        match comdat {
+            Some(first) if first.name != group.name => {
+                return Err(ObjectError::ComdatGroups { first: first.name.clone(), second: group.name.clone() });
+            },
+            _ => comdat = Some(group),
+        }
+    }
+
+    let mut sections = vec![Section::new("", 0, 0, 0, Vec::new())];
+
+    // the group section precedes its members, and is filled once the symbols and the sections are known.
+    let group = sections.len();
+    if comdat.is_some() {
+        sections.push(Section::new(".group", SHT_GROUP, 0, 4, Vec::new()));
+    }
+
+    let group_flag = if comdat.is_some() { SHF_GROUP } else { 0 };
+    let text = sections.len() as u16;
+    sections.push(Section::new(".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR | group_flag, 16, product.bytes().to_vec()));
+
+    let debug_line = (!product.source_map().is_empty()).then(|| {
+        sections.push(Section::new(".debug_line", SHT_PROGBITS, 0, 1, dwarf::debug_line(product, 0)));
+        sections.len() - 1
+    });
+
+    let mut symbols = SymbolTable::new();
+    let text_symbol = symbols.push("", STB_LOCAL, STT_SECTION, text, 0);
+    if let Some(comdat) = comdat.filter(|comdat| product.definition(&comdat.name).is_none()) {
+        symbols.push(&symbol_name(product, target, &comdat.name), STB_LOCAL, STT_NOTYPE, text, 0);
+    }
+    let first_global = symbols.len();
+
+    for definition in product.definitions() {
+        let binding = match definition.binding {
+            Binding::Global => STB_GLOBAL,
+            Binding::Weak => STB_WEAK,
+        };
+        symbols.push(&symbol_name(product, target, &definition.name), binding, STT_FUNC, text, definition.offset as u64);
+    }
+
+    let mut relocations = Vec::with_capacity(product.relocations().len() * RELA_SIZE);
+    for relocation in product.relocations() {
+        let kind = relocation_type(product, relocation).ok_or(ObjectError::UnsupportedRelocation { offset: relocation.offset, kind: relocation.kind })?;
+
+        let (symbol, addend) = match relocation.target {
+            RelocationTarget::Base => (text_symbol, relocation.addend),
+            RelocationTarget::Absolute(address) => (0, (address as i64).wrapping_add(relocation.addend)),
+            RelocationTarget::Symbol(symbol) => {
+                let name = symbol_name(product, target, &product.symbol(symbol).name);
+                let index = match symbols.indices.get(&name) {
+                    Some(index) => *index,
+                    None => {
+                        // the linkers check that the thread-local variables are only accessed as such.
+                        let thread_local = product.relocations().iter().any(|other| other.target == relocation.target && other.kind == RelocationKind::GotTpOff);
+                        symbols.push(&name, STB_GLOBAL, if thread_local { STT_TLS } else { STT_NOTYPE }, 0, 0)
+                    },
+                };
+
+                (index, relocation.addend)
+            },
+        };
+
+        write_rela(&mut relocations, relocation.offset, symbol, kind, addend);
+    }
+
+    // the line number program starts at the address of the code.
+    let mut debug_relocations = Vec::new();
+    if let Some(debug_line) = debug_line {
+        let field = dwarf::address_offset(&sections[debug_line].data);
+        let address = Relocation { offset: field, kind: RelocationKind::Abs64, target: RelocationTarget::Base, addend: 0 };
+        let kind = relocation_type(product, &address).ok_or(ObjectError::UnsupportedRelocation { offset: field, kind: address.kind })?;
+        write_rela(&mut debug_relocations, field, text_symbol, kind, 0);
+    }
+
+    let signature = comdat.map_or(0, |comdat| symbols.indices[&symbol_name(product, target, &comdat.name)]);
+
+    let symtab = sections.len() as u32;
+    let mut section = Section::new(".symtab", SHT_SYMTAB, 0, 8, symbols.entries);
+    (section.link, section.info, section.entsize) = (symtab + 1, first_global, SYM_SIZE as u64);
+    sections.push(section);
+    sections.push(Section::new(".strtab", SHT_STRTAB, 0, 1, symbols.names.bytes));
+
+    let relocations_empty = relocations.is_empty();
+    if !relocations_empty {
+        let mut section = Section::new(".rela.text", SHT_RELA, SHF_INFO_LINK | group_flag, 8, relocations);
+        (section.link, section.info, section.entsize) = (symtab, text as u32, RELA_SIZE as u64);
+        sections.push(section);
+    }
+
+    if let Some(debug_line) = debug_line {
+        let mut section = Section::new(".rela.debug_line", SHT_RELA, SHF_INFO_LINK, 8, debug_relocations);
+        (section.link, section.info, section.entsize) = (symtab, debug_line as u32, RELA_SIZE as u64);
+        sections.push(section);
+    }
+
+    if comdat.is_some() {
+        let mut members = vec![GRP_COMDAT, text as u32];
+        if !relocations_empty {
+            members.push(symtab + 2);
+        }
+
+        let section = &mut sections[group];
+        section.data = members.iter().flat_map(|member| member.to_le_bytes()).collect();
+        (section.link, section.info, section.entsize) = (symtab, signature, 4);
+    }
+
+    // the code doesn't need an executable stack.
+    sections.push(Section::new(".note.GNU-stack", SHT_PROGBITS, 0, 1, Vec::new()));
+
+    let mut names = StringTable::new();
+    let name_offsets = sections.iter().map(|section| names.add(section.name)).collect::<Vec<_>>();
+    let shstrtab = sections.len() as u16;
+    let shstrtab_name = names.add(".shstrtab");
+    sections.push(Section::new(".shstrtab", SHT_STRTAB, 0, 1, names.bytes));
+
+    let mut object = Vec::new();
+    object.extend_from_slice(b"\x7fELF");
+    object.extend_from_slice(&[2, 1, 1, 0]); // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE
+    object.resize(16, 0);
+    object.extend_from_slice(&ET_REL.to_le_bytes());
+    object.extend_from_slice(&machine.to_le_bytes());
+    object.extend_from_slice(&1u32.to_le_bytes()); // EV_CURRENT
+    object.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    object.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+    let shoff_field = object.len();
+    object.extend_from_slice(&0u64.to_le_bytes()); // e_shoff, written once the sections are
+    object.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    object.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    object.extend_from_slice(&0u16.to_le_bytes()); // e_phentsize
+    object.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+    object.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+    object.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    object.extend_from_slice(&shstrtab.to_le_bytes());
+
+    let mut offsets = Vec::with_capacity(sections.len());
+    for section in &sections {
+        let align = section.align.max(1) as usize;
+        object.resize((object.len() + align - 1) & !(align - 1), 0);
+        offsets.push(object.len() as u64);
+        object.extend_from_slice(&section.data);
+    }
+
+    object.resize((object.len() + 7) & !7, 0);
+    let shoff = object.len() as u64;
+    object[shoff_field..shoff_field + 8].copy_from_slice(&shoff.to_le_bytes());
+
+    for (index, section) in sections.iter().enumerate() {
+        let name = if index == shstrtab as usize { shstrtab_name } else { name_offsets[index] };
+        let offset = if index == 0 { 0 } else { offsets[index] };
+
+        object.extend_from_slice(&name.to_le_bytes());
+        object.extend_from_slice(&section.kind.to_le_bytes());
+        object.extend_from_slice(&section.flags.to_le_bytes());
+        object.extend_from_slice(&0u64.to_le_bytes()); // sh_addr
+        object.extend_from_slice(&offset.to_le_bytes());
+        object.extend_from_slice(&(section.data.len() as u64).to_le_bytes());
+        object.extend_from_slice(&section.link.to_le_bytes());
+        object.extend_from_slice(&section.info.to_le_bytes());
+        object.extend_from_slice(&section.align.to_le_bytes());
+        object.extend_from_slice(&section.entsize.to_le_bytes());
+    }
+
+    Ok(object)
+}
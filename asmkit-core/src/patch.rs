@@ -0,0 +1,119 @@
+//! Patchable fields, the displacements and immediates of a [`Product`](crate::Product) which were emitted wider than needed so they can be
+//! rewritten once the code is generated, such as an offset growing past the reach of an 8-bit displacement.
+//!
+//! The fields are recorded by the instruction streams along with their size, so a patch knows the space it has.
+//!
+//! Patch points are whole gaps of `nop`s reserved in the code, such as for a tiered JIT to turn into a call or a jump once the code they guard is
+//! invalidated, see [`ExecutableBuffer::patch_point_to_call`](crate::exec::ExecutableBuffer::patch_point_to_call).
+
+use std::{error::Error, fmt, ops::{Range, RangeInclusive}};
+
+/// What a patchable field holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatchFieldKind {
+    /// The displacement of a memory operand.
+    Displacement,
+
+    /// An immediate operand.
+    Immediate,
+}
+
+/// A field of the code recorded as patchable, holding a signed little-endian value sign extended by the instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PatchField {
+    /// The offset of the field.
+    pub offset: usize,
+
+    /// The size of the field, in bytes.
+    pub size: usize,
+
+    /// What the field holds.
+    pub kind: PatchFieldKind,
+}
+
+impl PatchField {
+    /// The offsets the field spans.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+
+    /// Returns true if `value` fits in the field.
+    pub fn fits(&self, value: i64) -> bool {
+        let bits = self.size as u32 * 8;
+        bits >= 64 || (value >> (bits - 1)) == 0 || (value >> (bits - 1)) == -1
+    }
+
+    /// Encodes `value` as the bytes of the field.
+    ///
+    /// # Errors
+    /// Returns [`PatchFieldError::OutOfRange`] if `value` doesn't fit in the field.
+    pub fn encode(&self, value: i64) -> Result<Vec<u8>, PatchFieldError> {
+        if !self.fits(value) {
+            return Err(PatchFieldError::OutOfRange { offset: self.offset, value, size: self.size });
+        }
+
+        Ok(value.to_le_bytes()[..self.size].to_vec())
+    }
+}
+
+/// The sizes of a patch point: large enough for a 5-byte `call rel32` or `jmp rel32`, and small enough to be rewritten with a single 8-byte store.
+pub const PATCH_POINT_SIZES: RangeInclusive<usize> = 5..=8;
+
+/// A reference to a patch point, by the id it was emitted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PatchPointRef(pub u64);
+
+/// A gap of `nop`s reserved in the code to be rewritten once the code is loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PatchPoint {
+    /// The id the patch point was emitted with.
+    pub id: u64,
+
+    /// The offset of the gap.
+    pub offset: usize,
+
+    /// The size of the gap, in bytes, within [`PATCH_POINT_SIZES`].
+    pub size: usize,
+}
+
+impl PatchPoint {
+    /// The offsets the gap spans.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// An error while patching a patchable field, see [`Product::patch_field`](crate::Product::patch_field).
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchFieldError {
+    /// No patchable field is recorded at the offset.
+    Unrecorded {
+        /// The offset of the field.
+        offset: usize,
+    },
+
+    /// The value doesn't fit in the field.
+    OutOfRange {
+        /// The offset of the field.
+        offset: usize,
+
+        /// The value written to the field.
+        value: i64,
+
+        /// The size of the field, in bytes.
+        size: usize,
+    },
+}
+
+impl fmt::Display for PatchFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unrecorded { offset } => write!(f, "no patchable field is recorded at {:#x}", offset),
+            Self::OutOfRange { offset, value, size } => {
+                write!(f, "value {:#x} doesn't fit in the {}-byte field at {:#x}", value, size, offset)
+            },
+        }
+    }
+}
+
+impl Error for PatchFieldError {}
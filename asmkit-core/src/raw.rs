@@ -0,0 +1,301 @@
+//! A raw instruction stream of a chosen [`Endianness`], writing the words it's given and the displacements of labels without encoding any
+//! instruction.
+//!
+//! Every backend so far targets a little-endian architecture; [`RawStream`] keeps the byte order of the [`InstructionStream`] writes a property of
+//! the stream, as the big-endian backends will need, and serves as the stream of data tables and tests which only need labels.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{raw::{Endianness, RawStream}, InstructionStream};
+//!
+//! let mut stream = RawStream::new(Endianness::Big);
+//! stream.write_byte(0x01);
+//! stream.write_word(0x0203);
+//! stream.write_double_word(0x04050607);
+//! stream.write_quad_word(0x08090a0b0c0d0e0f);
+//! stream.write_double_quad_word(0x101112131415161718191a1b1c1d1e1f);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x01,
+//!     0x02, 0x03,
+//!     0x04, 0x05, 0x06, 0x07,
+//!     0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+//!     0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+//! ]);
+//!
+//! let mut stream = RawStream::new(Endianness::Little);
+//! stream.write_byte(0x01);
+//! stream.write_word(0x0203);
+//! stream.write_double_word(0x04050607);
+//! stream.write_quad_word(0x08090a0b0c0d0e0f);
+//! stream.write_double_quad_word(0x101112131415161718191a1b1c1d1e1f);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x01,
+//!     0x03, 0x02,
+//!     0x07, 0x06, 0x05, 0x04,
+//!     0x0f, 0x0e, 0x0d, 0x0c, 0x0b, 0x0a, 0x09, 0x08,
+//!     0x1f, 0x1e, 0x1d, 0x1c, 0x1b, 0x1a, 0x19, 0x18, 0x17, 0x16, 0x15, 0x14, 0x13, 0x12, 0x11, 0x10,
+//! ]);
+//! ```
+
+use crate::{
+    entity::{EntityList, Label, LabelRef},
+    labels::LabelTable,
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+/// The byte order of the words written to an instruction stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// The least significant byte first, as on x86-64, AArch64, Thumb and RISC-V.
+    #[default]
+    Little,
+
+    /// The most significant byte first, as on s390x and big-endian MIPS.
+    Big,
+}
+
+impl Endianness {
+    /// Writes the `size` least significant bytes of `value` to `bytes` in this byte order.
+    pub(crate) fn write(self, bytes: &mut [u8], value: u128, size: usize) {
+        match self {
+            Self::Little => bytes[..size].copy_from_slice(&value.to_le_bytes()[..size]),
+            Self::Big => bytes[..size].copy_from_slice(&value.to_be_bytes()[16 - size..]),
+        }
+    }
+}
+
+/// A use of a label by a [`RawStream`], written once the label is attached, or once the stream is finished if the label was attached first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fixup {
+    /// The offset of the field holding the displacement.
+    offset: usize,
+
+    /// The size of the field, in bytes.
+    size: usize,
+
+    /// The label whose displacement the field holds.
+    label: LabelRef,
+}
+
+/// An instruction stream writing raw words in a chosen [`Endianness`].
+#[derive(Clone, Debug)]
+pub struct RawStream {
+    /// The bytes written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream, and their pending uses.
+    labels: LabelTable<Fixup>,
+
+    /// The byte order of the words written to the stream.
+    endianness: Endianness,
+
+    /// The holes of the templates written to the stream.
+    holes: TemplateHoles,
+}
+
+impl RawStream {
+    /// Creates a new, empty instruction stream writing its words in the given byte order.
+    pub fn new(endianness: Endianness) -> Self {
+        Self { bytes: Vec::new(), labels: LabelTable::new(), endianness, holes: TemplateHoles::new() }
+    }
+
+    /// The byte order of the words written to the stream.
+    pub fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// Writes a 16-bit field holding the displacement of `label` from the start of the field, in the byte order of the stream.
+    ///
+    /// # Panics
+    /// [`InstructionStream::attach_label`] or [`InstructionStream::finish`] panics if the label is never attached, or if its displacement
+    /// doesn't fit in 16 bits.
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_core::{raw::{Endianness, RawStream}, InstructionStream};
+    ///
+    /// let mut stream = RawStream::new(Endianness::Big);
+    /// let label = stream.create_label();
+    ///
+    /// stream.write_label_rel16(label);
+    /// stream.write_bytes(&[0; 0x8000]);
+    /// stream.attach_label(label);
+    ///
+    /// let _ = stream.finish();
+    /// ```
+    pub fn write_label_rel16(&mut self, label: LabelRef) {
+        self.write_label_field(label, 2);
+    }
+
+    /// Writes a 32-bit field holding the displacement of `label` from the start of the field, in the byte order of the stream.
+    ///
+    /// # Panics
+    /// [`InstructionStream::attach_label`] or [`InstructionStream::finish`] panics if the label is never attached, or if its displacement
+    /// doesn't fit in 32 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{raw::{Endianness, RawStream}, InstructionStream};
+    ///
+    /// let mut stream = RawStream::new(Endianness::Big);
+    /// let back = stream.create_label_attached();
+    /// let forward = stream.create_label();
+    ///
+    /// stream.write_double_word(0xdeadbeef);
+    /// stream.write_label_rel32(forward);
+    /// stream.write_label_rel16(back);
+    /// stream.write_word(0);
+    /// stream.attach_label(forward);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xde, 0xad, 0xbe, 0xef,
+    ///     0x00, 0x00, 0x00, 0x08, // forward, from 0x4 to 0xc
+    ///     0xff, 0xf8,             // back, from 0x8 to 0x0
+    ///     0x00, 0x00,
+    /// ]);
+    /// ```
+    pub fn write_label_rel32(&mut self, label: LabelRef) {
+        self.write_label_field(label, 4);
+    }
+
+    /// Records a use of `label` in a field of `size` bytes at the current index, and writes the field zeroed.
+    fn write_label_field(&mut self, label: LabelRef, size: usize) {
+        self.labels.use_label(label, Fixup { offset: self.bytes.len(), size, label });
+        self.bytes.resize(self.bytes.len() + size, 0);
+    }
+
+    /// Writes the displacement of the label of `fixup` into its field.
+    ///
+    /// # Panics
+    /// Panics if the label isn't attached, or if its displacement doesn't fit in the field.
+    fn resolve(&mut self, fixup: Fixup) {
+        let target = match self.labels.get(fixup.label).state {
+            Label::Attached(target) => target,
+            Label::Unattached => panic!("label {:?} is used but never attached", fixup.label),
+            Label::Absolute(_) => unreachable!("labels are only bound to absolute addresses by the x86-64 stream"),
+        };
+
+        let displacement = target as i64 - fixup.offset as i64;
+        let bits = fixup.size as u32 * 8;
+        assert!(displacement >> (bits - 1) == 0 || displacement >> (bits - 1) == -1, "displacement {} to label {:?} is out of range", displacement, fixup.label);
+
+        self.endianness.write(&mut self.bytes[fixup.offset..], displacement as u128, fixup.size);
+    }
+
+    /// Writes the `size` least significant bytes of `value` at the current index, in the byte order of the stream.
+    fn write_value(&mut self, value: u128, size: usize) {
+        let offset = self.bytes.len();
+        self.bytes.resize(offset + size, 0);
+        self.endianness.write(&mut self.bytes[offset..], value, size);
+    }
+}
+
+impl InstructionStream for RawStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.create()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.create_attached(self.bytes.len())
+    }
+
+    /// Attaches `label` to the current index, writing the displacements of the fields which used it before.
+    ///
+    /// # Panics
+    /// Panics if the displacement from one of these fields doesn't fit in it.
+    fn attach_label(&mut self, label: LabelRef) {
+        let mut pending = self.labels.attach(label, self.bytes.len());
+        while let Some(fixup) = pending.next(&self.labels) {
+            self.resolve(fixup);
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.write_value(word as u128, 2);
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.write_value(word as u128, 4);
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.write_value(word as u128, 8);
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.write_value(word, 16);
+    }
+
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, whose displacement is written in the byte order of the stream when it is finished.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol, which the stream has none of.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{raw::{Endianness, RawStream}, template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    ///
+    /// let mut stream = RawStream::new(Endianness::Big);
+    /// let table = stream.create_label();
+    /// stream.write_word(0x1234);
+    /// stream.emit_template(&[0xaa, 0, 0, 0, 0], &[TemplateFixup::rel32(1, TemplateTarget::Label(table))]);
+    /// stream.write_word(0x5678);
+    /// stream.attach_label(table);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x12, 0x34,
+    ///     0xaa, 0x00, 0x00, 0x00, 0x02, // table, from 0x7 to 0x9
+    ///     0x56, 0x78,
+    /// ]);
+    /// ```
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push_label(fixup);
+    }
+
+    /// Finishes writing to the instruction stream, writing the displacements of the label uses recorded after their label was attached and of
+    /// the holes of the templates in the byte order of the stream, and returns the produced bytes.
+    ///
+    /// # Panics
+    /// Panics if a used label was never attached, or if its displacement doesn't fit in its field.
+    fn finish(mut self) -> Product {
+        for label in self.labels.refs() {
+            let mut pending = self.labels.take_pending(label);
+            while let Some(fixup) = pending.next(&self.labels) {
+                self.resolve(fixup);
+            }
+        }
+
+        let relocations = self.holes.resolve(&mut self.bytes, self.endianness, |label| self.labels.offset(label)).unwrap_or_else(|error| panic!("{}", error));
+        Product::with_relocations(self.bytes, relocations, EntityList::new())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
@@ -0,0 +1,196 @@
+//! Relocations, the fields of a [`Product`](crate::Product) which depend on addresses only known when the code is loaded.
+
+use std::{error::Error, fmt};
+
+use crate::{
+    displacement::{DisplacementBias, DisplacementForm, DisplacementUnit},
+    entity::SymRef,
+};
+
+/// The kind of a relocation, deciding how the address of its target is written into the field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RelocationKind {
+    /// A 32-bit absolute address, zero extended to 64 bits by the instruction (`R_X86_64_32`).
+    Abs32,
+
+    /// A 64-bit absolute address (`R_X86_64_64`).
+    Abs64,
+
+    /// A 32-bit signed displacement from the field to the target (`R_X86_64_PC32`).
+    Rel32,
+
+    /// A 32-bit signed displacement from the field to the global offset table entry holding the offset of a thread-local symbol from the thread
+    /// pointer, for the initial-exec TLS model (`R_X86_64_GOTTPOFF`).  The target resolved for the symbol is the address of the entry, not of the
+    /// thread-local variable, and the relocation is never resolved by [`link`](crate::link), as the linked product has no global offset table.
+    GotTpOff,
+
+    /// A 16-bit absolute address, as used by the real mode code (`R_X86_64_16`).
+    Abs16,
+
+    /// A 32-bit signed displacement from the field to the global offset table entry holding the address of a symbol (`R_X86_64_GOTPCREL`).  As
+    /// for [`RelocationKind::GotTpOff`], the target resolved for the symbol is the address of the entry, and the relocation is never resolved by
+    /// [`link`](crate::link()).
+    GotPcRel,
+
+    /// A [`RelocationKind::GotPcRel`] in the `rip`-relative operand of a `REX`-prefixed `mov`, which the linker may relax into a `lea` of the
+    /// symbol when it is defined in the same image (`R_X86_64_REX_GOTPCRELX`).
+    RexGotPcRelX,
+
+    /// The 26-bit displacement, in instructions, from an AArch64 `b` or `bl` to the target, reaching ±128 MiB (`R_AARCH64_JUMP26` and
+    /// `R_AARCH64_CALL26`, `ARM64_RELOC_BRANCH26` on Mach-O).  The field is the whole instruction, whose opcode is kept.
+    Branch26,
+}
+
+impl RelocationKind {
+    /// The size of the relocated field, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Abs32 | Self::Rel32 | Self::GotTpOff | Self::GotPcRel | Self::RexGotPcRelX | Self::Branch26 => 4,
+            Self::Abs64 => 8,
+            Self::Abs16 => 2,
+        }
+    }
+
+    /// The number of the kind, as written by the `blob` format and hashed by [`Product::content_hash`](crate::Product::content_hash).
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::reloc::RelocationKind;
+    ///
+    /// assert_eq!(RelocationKind::Rel32.code(), 2);
+    /// assert_eq!(RelocationKind::from_code(RelocationKind::Branch26.code()), Some(RelocationKind::Branch26));
+    /// assert_eq!(RelocationKind::from_code(8), None);
+    /// ```
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Abs32 => 0,
+            Self::Abs64 => 1,
+            Self::Rel32 => 2,
+            Self::GotTpOff => 3,
+            Self::Abs16 => 4,
+            Self::GotPcRel => 5,
+            Self::RexGotPcRelX => 6,
+            Self::Branch26 => 7,
+        }
+    }
+
+    /// The kind numbered `code`, see [`RelocationKind::code`].
+    pub fn from_code(code: u8) -> Option<Self> {
+        [Self::Abs32, Self::Abs64, Self::Rel32, Self::GotTpOff, Self::Abs16, Self::GotPcRel, Self::RexGotPcRelX, Self::Branch26].get(code as usize).copied()
+    }
+
+    /// Returns true if the relocation refers to the global offset table entry of its symbol rather than to the symbol, which only the loader
+    /// fills.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityList, Symbol}, link, reloc::{Relocation, RelocationKind, RelocationTarget}, LinkError, LinkOptions, Product};
+    ///
+    /// let mut symbols = EntityList::new();
+    /// let environ = symbols.push(Symbol { name: "environ".to_string() });
+    /// let mut product = Product::with_relocations(vec![0x48, 0x8b, 0x05, 0, 0, 0, 0, 0], vec![ // mov rax, qword ptr [rip + environ@GOTPCREL]
+    ///     Relocation { offset: 3, kind: RelocationKind::RexGotPcRelX, target: RelocationTarget::Symbol(environ), addend: -4 },
+    /// ], symbols);
+    /// product.define("environ", 7);
+    /// assert!(RelocationKind::RexGotPcRelX.is_got() && !RelocationKind::Rel32.is_got());
+    ///
+    /// // the entry is left to the loader, even for a symbol defined by the products.
+    /// assert_eq!(link(vec![product.clone()], LinkOptions::default()).unwrap().relocations().len(), 1);
+    /// assert_eq!(product.to_flat_binary(0x1000, None), Err(LinkError::Unresolved { name: "environ".to_string(), offset: 3 }));
+    ///
+    /// // the lookup resolves the address of the entry.
+    /// product.resolve_all(|name| (name == "environ").then_some(0x2000), 0x1000).unwrap();
+    /// assert_eq!(product.bytes()[3..7], (0x2000i32 - 0x1007).to_le_bytes());
+    /// ```
+    pub fn is_got(&self) -> bool {
+        matches!(self, Self::GotTpOff | Self::GotPcRel | Self::RexGotPcRelX)
+    }
+}
+
+/// The address a relocation refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RelocationTarget {
+    /// The address the code is loaded at, used for the absolute addresses of labels.
+    Base,
+
+    /// The address of an external symbol.
+    Symbol(SymRef),
+
+    /// A fixed address, known before the code is loaded, as the address of a label bound to it.  Only the relative relocations need the
+    /// address the code is loaded at.
+    Absolute(u64),
+}
+
+/// A relocation, computing the value of a field from the address of its target.
+///
+/// The value is `target + addend` for the absolute kinds, and `target + addend - field` for the relative ones, `Rel32`, `Branch26` and the global offset table kinds, where `field` is the address of the
+/// relocated field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Relocation {
+    /// The offset of the relocated field.
+    pub offset: usize,
+
+    /// How the value is written into the field.
+    pub kind: RelocationKind,
+
+    /// The address the relocation refers to.
+    pub target: RelocationTarget,
+
+    /// The constant added to the address of the target.
+    pub addend: i64,
+}
+
+/// An error which occurred while applying relocations in place, see [`Product::apply_relocation`](crate::Product::apply_relocation).
+#[derive(Clone, Debug, PartialEq)]
+pub enum RelocError {
+    /// The product has no relocation at the index.
+    NoRelocation {
+        /// The index of the relocation.
+        index: usize,
+
+        /// The number of relocations of the product.
+        len: usize,
+    },
+
+    /// A relocation refers to a symbol which the lookup couldn't resolve, see [`Product::resolve_all`](crate::Product::resolve_all).
+    Unresolved {
+        /// The name of the symbol.
+        name: String,
+
+        /// The offset of the relocated field.
+        offset: usize,
+    },
+
+    /// The relocated value doesn't fit in its field, such as a `Rel32` target more than 2 GiB away.
+    OutOfRange {
+        /// The offset of the relocated field.
+        offset: usize,
+
+        /// The value of the field.
+        value: i128,
+    },
+}
+
+impl fmt::Display for RelocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRelocation { index, len } => write!(f, "relocation {} is out of bounds, the product has {} relocations", index, len),
+            Self::Unresolved { name, offset } => write!(f, "unresolved symbol `{}`, referred to at offset {:#x}", name, offset),
+            Self::OutOfRange { offset, value } => write!(f, "relocated value {:#x} doesn't fit in the field at offset {:#x}", value, offset),
+        }
+    }
+}
+
+impl Error for RelocError {}
+
+/// Writes the displacement `value` into the AArch64 branch `field`, see [`RelocationKind::Branch26`].  Returns the value if it isn't a multiple of
+/// 4 or is out of the reach of the branch.
+pub(crate) fn write_branch26(field: &mut [u8], value: i128) -> Result<(), i128> {
+    const FORM: DisplacementForm = DisplacementForm::new(DisplacementUnit::Words, DisplacementBias::Start, 26);
+
+    let words = i64::try_from(value).ok().and_then(|value| FORM.measure(value).ok()).ok_or(value)?;
+    let instruction = u32::from_le_bytes(field.try_into().unwrap());
+    let instruction = (instruction & 0xfc00_0000) | (words as u32 & 0x03ff_ffff);
+    field.copy_from_slice(&instruction.to_le_bytes());
+    Ok(())
+}
@@ -0,0 +1,294 @@
+//! Code size reports, attributing the bytes of a product to nested regions named by the code generator, such as to tell how many bytes the
+//! inlining of a function cost.
+//!
+//! The regions are recorded in the [`SizeRegions`] of a product, as runs of bytes in the same innermost region, such as by the `push_region`
+//! and `pop_region` methods of the instruction streams, and [`Product::size_report`](crate::Product::size_report) sums them up in a [`SizeReport`].  The regions of the same
+//! name within the same region are the same: the bytes of every inlining of a function within a caller add up.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::Product;
+//!
+//! let mut product = Product::new(vec![0x90; 64]);
+//! let regions = product.size_regions_mut();
+//! let main = regions.region(None, "main");
+//! let inlined = regions.region(Some(main), "inline F");
+//! regions.record(0, Some(main));
+//! regions.record(8, Some(inlined));
+//! regions.record(20, Some(main));
+//! regions.record(32, Some(inlined));
+//! regions.record(40, Some(main));
+//! regions.record(48, None);
+//!
+//! let report = product.size_report();
+//! let main = report.region(&["main"]).unwrap();
+//! let inlined = report.region(&["main", "inline F"]).unwrap();
+//! assert_eq!((main.inclusive, main.exclusive), (48, 28));
+//! assert_eq!((inlined.inclusive, inlined.exclusive), (20, 20));
+//! assert_eq!((report.total(), report.unattributed()), (64, 16));
+//!
+//! assert_eq!(report.to_string().lines().collect::<Vec<_>>(), [
+//!     "      64         16  (all)",
+//!     "      48         28    main",
+//!     "      20         20      inline F",
+//! ]);
+//! assert_eq!(report.folded(), "main 28\nmain;inline F 20\n");
+//! ```
+
+use std::fmt;
+
+/// A region of a [`SizeRegions`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Region {
+    /// The name of the region.
+    name: String,
+
+    /// The region the region is nested in, if any.
+    parent: Option<usize>,
+}
+
+/// An entry of a [`SizeRegions`], starting a run of bytes in the same innermost region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SizeRegionEntry {
+    /// The offset of the first byte of the run.
+    pub offset: usize,
+
+    /// The innermost region of the run, or `None` if it's in none.
+    pub region: Option<usize>,
+}
+
+/// The nested regions the bytes of a product are attributed to, as runs of bytes in the same innermost region.
+///
+/// Each entry applies from its offset up to the next entry, and the last one up to the end of the product.  The entries are sorted by offset, and
+/// runs are compressed: consecutive entries are never in the same region.  The regions are numbered in the order they are created, each after the
+/// region it's nested in.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SizeRegions {
+    /// The regions, by number.
+    regions: Vec<Region>,
+
+    /// The runs of bytes, sorted by offset.
+    entries: Vec<SizeRegionEntry>,
+}
+
+impl SizeRegions {
+    /// The number of the region `name` nested in `parent`, or at the top if it is `None`, created if it doesn't exist yet.
+    ///
+    /// # Panics
+    /// Panics if `parent` isn't a region.
+    pub fn region(&mut self, parent: Option<usize>, name: &str) -> usize {
+        assert!(parent.is_none_or(|parent| parent < self.regions.len()), "no size region numbered {}", parent.unwrap_or_default());
+
+        if let Some(region) = self.regions.iter().position(|region| region.parent == parent && region.name == name) {
+            return region;
+        }
+
+        self.regions.push(Region { name: name.to_string(), parent });
+        self.regions.len() - 1
+    }
+
+    /// Records that the bytes from `offset` on are in `region`, or in no region if it is `None`.  The entries past `offset` are dropped, as the
+    /// bytes they described were overwritten.
+    pub fn record(&mut self, offset: usize, region: Option<usize>) {
+        while self.entries.last().is_some_and(|entry| entry.offset >= offset) {
+            self.entries.pop();
+        }
+
+        if self.entries.last().and_then(|entry| entry.region) != region {
+            self.entries.push(SizeRegionEntry { offset, region });
+        }
+    }
+
+    /// Appends the runs of `other`, shifted to `base`, and its regions, merged with those of the same names nested in the same regions.  The bytes
+    /// from `base` on are in no region up to the first entry of `other`.
+    pub fn append(&mut self, base: usize, other: &SizeRegions) {
+        let mut numbers = Vec::with_capacity(other.regions.len());
+        for region in &other.regions {
+            let number = self.region(region.parent.map(|parent| numbers[parent]), &region.name);
+            numbers.push(number);
+        }
+
+        self.record(base, None);
+        for entry in &other.entries {
+            self.record(base + entry.offset, entry.region.map(|region| numbers[region]));
+        }
+    }
+
+    /// Shifts the runs starting at or past `offset` by `len` bytes, as when `len` bytes are inserted at `offset`.  The inserted bytes are in the
+    /// region of the run before them.
+    pub fn shift(&mut self, offset: usize, len: usize) {
+        for entry in self.entries.iter_mut().filter(|entry| entry.offset >= offset) {
+            entry.offset += len;
+        }
+    }
+
+    /// The runs of bytes in the same innermost region, sorted by offset.
+    pub fn entries(&self) -> &[SizeRegionEntry] {
+        &self.entries
+    }
+
+    /// Returns true if no region was created.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The name of the region `region`.
+    ///
+    /// # Panics
+    /// Panics if `region` isn't a region.
+    pub fn name(&self, region: usize) -> &str {
+        &self.regions[region].name
+    }
+
+    /// The region `region` is nested in, if any.
+    ///
+    /// # Panics
+    /// Panics if `region` isn't a region.
+    pub fn parent(&self, region: usize) -> Option<usize> {
+        self.regions[region].parent
+    }
+
+    /// Sums up the bytes of each region, for a product of `len` bytes.
+    pub fn report(&self, len: usize) -> SizeReport {
+        let mut regions = self.regions.iter().map(|region| SizeReportRegion {
+            name: region.name.clone(),
+            parent: region.parent,
+            children: Vec::new(),
+            inclusive: 0,
+            exclusive: 0,
+        }).collect::<Vec<_>>();
+
+        let mut unattributed = 0;
+        for (index, entry) in self.entries.iter().enumerate().filter(|(_, entry)| entry.offset < len) {
+            let end = self.entries.get(index + 1).map_or(len, |next| next.offset.min(len));
+            match entry.region {
+                Some(region) => regions[region].exclusive += end - entry.offset,
+                None => unattributed += end - entry.offset,
+            }
+        }
+
+        // the bytes before the first run are in no region.
+        unattributed += self.entries.first().map_or(len, |first| first.offset.min(len));
+
+        // the regions are numbered after their parents, so the inner ones are summed up first.
+        for region in (0..regions.len()).rev() {
+            regions[region].inclusive += regions[region].exclusive;
+            if let Some(parent) = regions[region].parent {
+                regions[parent].inclusive += regions[region].inclusive;
+                regions[parent].children.insert(0, region);
+            }
+        }
+
+        SizeReport { regions, total: len, unattributed }
+    }
+}
+
+/// A region of a [`SizeReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SizeReportRegion {
+    /// The name of the region.
+    pub name: String,
+
+    /// The number of the region the region is nested in, if any.
+    pub parent: Option<usize>,
+
+    /// The numbers of the regions nested in the region, in the order they were created.
+    pub children: Vec<usize>,
+
+    /// The bytes of the region, including those of the regions nested in it.
+    pub inclusive: usize,
+
+    /// The bytes of the region outside of the regions nested in it.
+    pub exclusive: usize,
+}
+
+/// The bytes of a product attributed to each of its regions, see the [module](self) documentation.
+///
+/// Formatted with `{}`, the report is a tree of the regions, with their inclusive and exclusive bytes, under the whole product.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SizeReport {
+    /// The regions, numbered as those of the [`SizeRegions`].
+    regions: Vec<SizeReportRegion>,
+
+    /// The bytes of the product.
+    total: usize,
+
+    /// The bytes of the product in no region.
+    unattributed: usize,
+}
+
+impl SizeReport {
+    /// The regions, numbered in the order they were created, each after the region it's nested in.
+    pub fn regions(&self) -> &[SizeReportRegion] {
+        &self.regions
+    }
+
+    /// The regions nested in no region, in the order they were created.
+    pub fn roots(&self) -> impl Iterator<Item = &SizeReportRegion> {
+        self.regions.iter().filter(|region| region.parent.is_none())
+    }
+
+    /// The regions nested in `region`, in the order they were created.
+    pub fn children<'a>(&'a self, region: &'a SizeReportRegion) -> impl Iterator<Item = &'a SizeReportRegion> {
+        region.children.iter().map(|&child| &self.regions[child])
+    }
+
+    /// The region at the end of `path`, the names of the regions from the top, if it exists.
+    pub fn region(&self, path: &[&str]) -> Option<&SizeReportRegion> {
+        let mut parent = None;
+        let mut found = None;
+        for name in path {
+            let number = self.regions.iter().position(|region| region.parent == parent && region.name == *name)?;
+            (parent, found) = (Some(number), Some(&self.regions[number]));
+        }
+
+        found
+    }
+
+    /// The bytes of the product.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The bytes of the product in no region.
+    pub fn unattributed(&self) -> usize {
+        self.unattributed
+    }
+
+    /// The path of the region numbered `region`, the names of the regions from the top joined by `;`.
+    fn path(&self, region: usize) -> String {
+        match self.regions[region].parent {
+            Some(parent) => format!("{};{}", self.path(parent), self.regions[region].name),
+            None => self.regions[region].name.clone(),
+        }
+    }
+
+    /// The report in the folded format of the flame graph tools, a line per region with exclusive bytes: its path, the names of the regions from
+    /// the top joined by `;`, and its exclusive bytes.
+    pub fn folded(&self) -> String {
+        self.regions
+            .iter()
+            .enumerate()
+            .filter(|(_, region)| region.exclusive != 0)
+            .map(|(number, region)| format!("{} {}\n", self.path(number), region.exclusive))
+            .collect()
+    }
+
+    /// Writes the region numbered `region` and those nested in it, indented by `depth`.
+    fn write_region(&self, f: &mut fmt::Formatter<'_>, region: usize, depth: usize) -> fmt::Result {
+        let SizeReportRegion { name, children, inclusive, exclusive, .. } = &self.regions[region];
+        writeln!(f, "{:>8}   {:>8}  {:indent$}{}", inclusive, exclusive, "", name, indent = depth * 2)?;
+
+        children.iter().try_for_each(|&child| self.write_region(f, child, depth + 1))
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>8}   {:>8}  (all)", self.total, self.unattributed)?;
+
+        (0..self.regions.len())
+            .filter(|&region| self.regions[region].parent.is_none())
+            .try_for_each(|region| self.write_region(f, region, 1))
+    }
+}
@@ -0,0 +1,125 @@
+//! Source maps, mapping the offsets of a product to the locations of the source code they were generated from, such as for debug info or for
+//! reporting where a fault in generated code comes from.
+
+use std::collections::BTreeMap;
+
+/// A location in source code, in a file identified by a number, see [`SourceMap::register_file`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    /// The number of the file.
+    pub file_id: u32,
+
+    /// The line in the file.
+    pub line: u32,
+
+    /// The column in the line.
+    pub col: u32,
+}
+
+/// An entry of a [`SourceMap`], starting a run of bytes generated from the same location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SourceMapEntry {
+    /// The offset of the first byte of the run.
+    pub offset: usize,
+
+    /// The location the run was generated from, or `None` if it has none.
+    pub location: Option<Location>,
+}
+
+/// The locations of the source code the bytes of a product were generated from, as runs of bytes sharing a location.
+///
+/// Each entry applies from its offset up to the next entry, and the last one up to the end of the product.  The entries are sorted by offset, and
+/// runs are compressed: consecutive entries never share a location.
+///
+/// # Example
+/// ```
+/// use asmkit_core::source_map::{Location, SourceMap};
+///
+/// let main = Location { file_id: 0, line: 3, col: 5 };
+/// let mut map = SourceMap::default();
+/// map.register_file(0, "main.rs");
+/// map.record(0, Some(main));
+/// map.record(4, Some(main)); // the same location continues the run.
+/// map.record(8, None);
+///
+/// assert_eq!(map.entries().len(), 2);
+/// assert_eq!(map.lookup(6), Some(main));
+/// assert_eq!(map.lookup(8), None);
+/// assert_eq!(map.file(main.file_id), Some("main.rs"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SourceMap {
+    /// The runs of bytes, sorted by offset.
+    entries: Vec<SourceMapEntry>,
+
+    /// The names of the files, by number.
+    files: BTreeMap<u32, String>,
+}
+
+impl SourceMap {
+    /// Records that the bytes from `offset` on are generated from `location`, or from no location if it is `None`.  The entries past `offset` are
+    /// dropped, as the bytes they described were overwritten.
+    pub fn record(&mut self, offset: usize, location: Option<Location>) {
+        while self.entries.last().is_some_and(|entry| entry.offset >= offset) {
+            self.entries.pop();
+        }
+
+        if self.entries.last().and_then(|entry| entry.location) != location {
+            self.entries.push(SourceMapEntry { offset, location });
+        }
+    }
+
+    /// Appends the entries and files of `other`, shifted to `base`.  The bytes from `base` on have no location up to the first entry of `other`, and
+    /// the files already registered keep their names.
+    pub fn append(&mut self, base: usize, other: &SourceMap) {
+        self.record(base, None);
+
+        for entry in &other.entries {
+            self.record(base + entry.offset, entry.location);
+        }
+
+        for (file_id, name) in &other.files {
+            self.files.entry(*file_id).or_insert_with(|| name.clone());
+        }
+    }
+
+    /// Shifts the runs starting at or past `offset` by `len` bytes, as when `len` bytes are inserted at `offset`.  The inserted bytes take the
+    /// location of the run before them.
+    pub fn shift(&mut self, offset: usize, len: usize) {
+        for entry in self.entries.iter_mut().filter(|entry| entry.offset >= offset) {
+            entry.offset += len;
+        }
+    }
+
+    /// The runs of bytes sharing a location, sorted by offset.
+    pub fn entries(&self) -> &[SourceMapEntry] {
+        &self.entries
+    }
+
+    /// Returns true if no bytes have a location.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The location the byte at `offset` was generated from, found by binary search.  Offsets past the end of the product take the location of the
+    /// last run.
+    pub fn lookup(&self, offset: usize) -> Option<Location> {
+        let run = self.entries.partition_point(|entry| entry.offset <= offset);
+        run.checked_sub(1).and_then(|run| self.entries[run].location)
+    }
+
+    /// Registers `name` as the name of the file `file_id`, replacing any previous name.
+    pub fn register_file(&mut self, file_id: u32, name: &str) {
+        self.files.insert(file_id, name.to_string());
+    }
+
+    /// The registered files, by increasing number.
+    pub fn files(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.files.iter().map(|(file_id, name)| (*file_id, name.as_str()))
+    }
+
+    /// The name of the file `file_id`, if it is registered.
+    pub fn file(&self, file_id: u32) -> Option<&str> {
+        self.files.get(&file_id).map(String::as_str)
+    }
+}
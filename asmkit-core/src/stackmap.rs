@@ -0,0 +1,246 @@
+//! Stack maps, recording where the managed pointers of a garbage collected language are live at the safepoints of a product, such as the
+//! return address of each call, so a collector walking the stack can find and update them.
+//!
+//! A [`StackMap`] lists the [`StackMapLocation`]s holding live pointers at one offset: registers, numbered as the instruction stream's
+//! architecture encodes them, and stack slots, addressed from a base register.  The [`StackMaps`] of a product are sorted by offset, and
+//! serialized by [`StackMaps::to_bytes`] as a table of little-endian fields:
+//!
+//! | Field          | Size | Value                                                            |
+//! |----------------|------|------------------------------------------------------------------|
+//! | map count      | 4    | the number of stack maps                                         |
+//!
+//! followed by each stack map, in increasing order of offset:
+//!
+//! | Field          | Size | Value                                                            |
+//! |----------------|------|------------------------------------------------------------------|
+//! | offset         | 8    | the offset of the safepoint                                      |
+//! | location count | 4    | the number of live locations                                     |
+//! | locations      | 8n   | the live locations, 8 bytes each                                 |
+//!
+//! where each location is:
+//!
+//! | Field          | Size | Value                                                            |
+//! |----------------|------|------------------------------------------------------------------|
+//! | kind           | 1    | `0` for a register, `1` for a stack slot                         |
+//! | register       | 1    | the register, or the base register of the slot                   |
+//! | reserved       | 2    | `0`                                                              |
+//! | offset         | 4    | the signed offset of the slot from its base, `0` for a register  |
+//!
+//! # Example
+//! ```
+//! use asmkit_core::stackmap::{StackMapLocation, StackMaps};
+//!
+//! let mut maps = StackMaps::default();
+//! maps.record(12, vec![StackMapLocation::Register(3), StackMapLocation::Indirect { base: 5, offset: -8 }]);
+//! maps.record(5, Vec::new());
+//!
+//! let bytes = maps.to_bytes();
+//! assert_eq!(bytes, [
+//!     2, 0, 0, 0,                         // 2 maps
+//!     5, 0, 0, 0, 0, 0, 0, 0,             // at 5
+//!     0, 0, 0, 0,                         // nothing live
+//!     12, 0, 0, 0, 0, 0, 0, 0,            // at 12
+//!     2, 0, 0, 0,                         // 2 live locations
+//!     0, 3, 0, 0, 0, 0, 0, 0,             // register 3
+//!     1, 5, 0, 0, 0xf8, 0xff, 0xff, 0xff, // [register 5 - 8]
+//! ]);
+//! assert_eq!(StackMaps::from_bytes(&bytes), Ok(maps));
+//! ```
+
+use std::{error::Error, fmt};
+
+/// The size of a serialized location.
+const LOCATION_SIZE: usize = 8;
+
+/// A location holding a live pointer at a safepoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StackMapLocation {
+    /// A register, by its number in the architecture's encoding.
+    Register(u8),
+
+    /// A stack slot, at `offset` bytes from the address held in the register `base`.
+    Indirect {
+        /// The base register, by its number in the architecture's encoding.
+        base: u8,
+
+        /// The offset of the slot from the base register.
+        offset: i32,
+    },
+}
+
+/// The locations holding live pointers at a safepoint.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StackMap {
+    /// The offset of the safepoint, such as the return address of a call.
+    pub offset: usize,
+
+    /// The locations holding live pointers.
+    pub live: Vec<StackMapLocation>,
+}
+
+/// The stack maps of a product, sorted by offset, see the [module](self) documentation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StackMaps {
+    /// The stack maps, sorted by offset.
+    maps: Vec<StackMap>,
+}
+
+impl StackMaps {
+    /// Records the locations `live` at `offset`, replacing the stack map already recorded there, if any.
+    pub fn record(&mut self, offset: usize, live: Vec<StackMapLocation>) {
+        match self.maps.binary_search_by_key(&offset, |map| map.offset) {
+            Ok(index) => self.maps[index].live = live,
+            Err(index) => self.maps.insert(index, StackMap { offset, live }),
+        }
+    }
+
+    /// Appends the stack maps of `other`, shifted to `base`.
+    pub fn append(&mut self, base: usize, other: &StackMaps) {
+        for map in &other.maps {
+            self.record(base + map.offset, map.live.clone());
+        }
+    }
+
+    /// Shifts the stack maps past `offset` by `len` bytes, as when `len` bytes are inserted at `offset`.  A safepoint at `offset` itself ends the
+    /// code before the inserted bytes, and stays.
+    pub fn shift(&mut self, offset: usize, len: usize) {
+        for map in self.maps.iter_mut().filter(|map| map.offset > offset) {
+            map.offset += len;
+        }
+    }
+
+    /// The stack maps, sorted by offset.
+    pub fn maps(&self) -> &[StackMap] {
+        &self.maps
+    }
+
+    /// Returns true if no stack map is recorded.
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// The locations holding live pointers at `offset`, or `None` if no stack map is recorded there.
+    pub fn lookup(&self, offset: usize) -> Option<&[StackMapLocation]> {
+        let index = self.maps.binary_search_by_key(&offset, |map| map.offset).ok()?;
+        Some(&self.maps[index].live)
+    }
+
+    /// Serializes the stack maps, in the format described by the [module](self) documentation.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.maps.len() as u32).to_le_bytes());
+
+        for map in &self.maps {
+            bytes.extend_from_slice(&(map.offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(map.live.len() as u32).to_le_bytes());
+
+            for location in &map.live {
+                let (kind, register, offset) = match *location {
+                    StackMapLocation::Register(register) => (0, register, 0),
+                    StackMapLocation::Indirect { base, offset } => (1, base, offset),
+                };
+
+                bytes.extend_from_slice(&[kind, register, 0, 0]);
+                bytes.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Deserializes stack maps serialized by [`StackMaps::to_bytes`].
+    ///
+    /// # Errors
+    /// Returns [`StackMapError::Truncated`] if `bytes` ends within the table, [`StackMapError::UnknownKind`] if a location has an unknown
+    /// kind, and [`StackMapError::TrailingBytes`] if `bytes` continues past the table.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StackMapError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let mut maps = Self::default();
+
+        for _ in 0..reader.read_u32()? {
+            let offset = reader.read_u64()? as usize;
+            let count = reader.read_u32()? as usize;
+
+            let mut live = Vec::with_capacity(count.min(bytes.len() / LOCATION_SIZE));
+            for _ in 0..count {
+                let at = reader.offset;
+                let field = reader.read(LOCATION_SIZE)?;
+                let displacement = i32::from_le_bytes([field[4], field[5], field[6], field[7]]);
+
+                live.push(match field[0] {
+                    0 => StackMapLocation::Register(field[1]),
+                    1 => StackMapLocation::Indirect { base: field[1], offset: displacement },
+                    kind => return Err(StackMapError::UnknownKind { offset: at, kind }),
+                });
+            }
+
+            maps.record(offset, live);
+        }
+
+        if reader.offset != bytes.len() {
+            return Err(StackMapError::TrailingBytes { offset: reader.offset });
+        }
+
+        Ok(maps)
+    }
+}
+
+/// Reads the fields of a serialized table.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next `len` bytes.
+    fn read(&mut self, len: usize) -> Result<&'a [u8], StackMapError> {
+        let field = self.bytes.get(self.offset..self.offset + len).ok_or(StackMapError::Truncated { offset: self.bytes.len() })?;
+        self.offset += len;
+        Ok(field)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StackMapError> {
+        self.read(4).map(|field| u32::from_le_bytes(field.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, StackMapError> {
+        self.read(8).map(|field| u64::from_le_bytes(field.try_into().unwrap()))
+    }
+}
+
+/// An error while deserializing stack maps, see [`StackMaps::from_bytes`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum StackMapError {
+    /// The table ends early.
+    Truncated {
+        /// The length of the table.
+        offset: usize,
+    },
+
+    /// A location has an unknown kind.
+    UnknownKind {
+        /// The offset of the location in the table.
+        offset: usize,
+
+        /// The kind of the location.
+        kind: u8,
+    },
+
+    /// The table continues past its last stack map.
+    TrailingBytes {
+        /// The offset of the first byte past the last stack map.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for StackMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { offset } => write!(f, "the stack map table ends early, at {:#x}", offset),
+            Self::UnknownKind { offset, kind } => write!(f, "unknown stack map location kind {} at {:#x}", kind, offset),
+            Self::TrailingBytes { offset } => write!(f, "unexpected bytes past the stack map table, at {:#x}", offset),
+        }
+    }
+}
+
+impl Error for StackMapError {}
@@ -0,0 +1,80 @@
+//! Call stubs, reaching the external symbols too far from the code for a direct call.
+//!
+//! A direct call or jump reaches as far as its displacement does, ±2 GiB on x86-64 and ±128 MiB on AArch64.  A [`CallStub`] is a short sequence laid out with the code,
+//! which loads the full address of a symbol and jumps to it, such as the x86-64 `movabs r11, symbol; jmp r11`; the instruction streams lay out
+//! one stub per symbol, shared by all the branches to it.
+//!
+//! A stub is used either as a veneer or as an entry of a stub table:
+//!
+//! - as a veneer, the direct branches keep their relocation against the symbol, and [`Product::relocate`](crate::Product::relocate),
+//!   [`Product::resolve_all`](crate::Product::resolve_all) and [`link`](crate::link) retarget the ones whose displacement doesn't fit to the stub;
+//! - as a stub table, like the procedure linkage table of ELF, the direct branches always go through the stub, so the address of the symbol is
+//!   held in one place, which a runtime can patch to redirect every branch to the symbol.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, stub::CallStub, Product};
+//!
+//! let mut symbols = EntityList::new();
+//! let puts = symbols.push(Symbol { name: "puts".to_string() });
+//!
+//! let mut product = Product::with_relocations(vec![
+//!     0xe8, 0x00, 0x00, 0x00, 0x00,                               // call puts
+//!     0xc3,                                                       // ret
+//!     0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // stub: movabs r11, puts
+//!     0x41, 0xff, 0xe3,                                           // jmp r11
+//! ], vec![
+//!     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+//!     Relocation { offset: 8, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(puts), addend: 0 },
+//! ], symbols);
+//! product.add_stub(CallStub { symbol: puts, offset: 6 });
+//! assert_eq!(product.stub("puts"), Some(6));
+//!
+//! // `puts` is within reach of the call.
+//! let bytes = product.clone().relocate(0x1000, |_| 0x2000);
+//! assert_eq!(bytes[1..5], (0x2000 - 0x1005u32).to_le_bytes());
+//!
+//! // `puts` is past 2 GiB, so the call goes through the stub.
+//! let bytes = product.relocate(0x1000, |_| 0x7fff_0000_0000);
+//! assert_eq!(bytes[1..5], 1u32.to_le_bytes());
+//! assert_eq!(bytes[8..16], 0x7fff_0000_0000u64.to_le_bytes());
+//! ```
+//!
+//! The AArch64 branches reach ±128 MiB, and their stubs load the address from a literal next to them, `ldr x16, #8; br x16`:
+//! ```
+//! use asmkit_core::{entity::{EntityList, Symbol}, reloc::{Relocation, RelocationKind, RelocationTarget}, stub::CallStub, Product};
+//!
+//! let mut symbols = EntityList::new();
+//! let puts = symbols.push(Symbol { name: "puts".to_string() });
+//!
+//! let mut product = Product::with_relocations(vec![
+//!     0x00, 0x00, 0x00, 0x94,                         // bl puts
+//!     0xc0, 0x03, 0x5f, 0xd6,                         // ret
+//!     0x50, 0x00, 0x00, 0x58,                         // stub: ldr x16, #8
+//!     0x00, 0x02, 0x1f, 0xd6,                         // br x16
+//!     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // .quad puts
+//! ], vec![
+//!     Relocation { offset: 0, kind: RelocationKind::Branch26, target: RelocationTarget::Symbol(puts), addend: 0 },
+//!     Relocation { offset: 16, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(puts), addend: 0 },
+//! ], symbols);
+//! product.add_stub(CallStub { symbol: puts, offset: 8 });
+//!
+//! let bytes = product.clone().relocate(0x1000, |_| 0x2000);
+//! assert_eq!(bytes[0..4], [0x00, 0x04, 0x00, 0x94]); // bl 0x2000
+//!
+//! let bytes = product.relocate(0x1000, |_| 0x7fff_0000_0000);
+//! assert_eq!(bytes[0..4], [0x02, 0x00, 0x00, 0x94]); // bl stub
+//! assert_eq!(bytes[16..24], 0x7fff_0000_0000u64.to_le_bytes());
+//! ```
+
+use crate::entity::SymRef;
+
+/// A stub loading the address of a symbol and jumping to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallStub {
+    /// The symbol the stub jumps to.
+    pub symbol: SymRef,
+
+    /// The offset of the stub.
+    pub offset: usize,
+}
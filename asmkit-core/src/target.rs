@@ -0,0 +1,290 @@
+//! Targets, the architecture and operating system code is generated for, and the conventions derived from them.
+//!
+//! A [`Target`] is parsed from a target triple or taken from the host, and tells the components consuming a product which object format,
+//! calling convention and symbol mangling the operating system expects, rather than each of them taking these as separate parameters.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{raw::Endianness, target::{CallingConvention, ObjectFormat, OperatingSystem, Target}, Architecture};
+//!
+//! let target = Target::parse("x86_64-unknown-linux-gnu").unwrap();
+//! assert_eq!(target.architecture, Architecture::X86_64);
+//! assert_eq!(target.operating_system, OperatingSystem::Linux);
+//! assert_eq!(target.object_format, ObjectFormat::Elf);
+//! assert_eq!(target.calling_convention, CallingConvention::SysV);
+//! assert_eq!(target.pointer_width, 64);
+//! assert_eq!(target.endianness, Endianness::Little);
+//! assert_eq!(target.mangle("main"), "main");
+//!
+//! let target = Target::parse("x86_64-pc-windows-msvc").unwrap();
+//! assert_eq!(target.object_format, ObjectFormat::Coff);
+//! assert_eq!(target.calling_convention, CallingConvention::Win64);
+//!
+//! let target = Target::parse("aarch64-apple-darwin").unwrap();
+//! assert_eq!(target.object_format, ObjectFormat::MachO);
+//! assert_eq!(target.calling_convention, CallingConvention::Aapcs64);
+//! assert_eq!(target.mangle("main"), "_main");
+//!
+//! let target = Target::parse("thumbv7em-none-eabihf").unwrap();
+//! assert_eq!(target.architecture, Architecture::Thumb);
+//! assert_eq!(target.operating_system, OperatingSystem::Bare);
+//! assert_eq!(target.object_format, ObjectFormat::Elf);
+//! assert_eq!(target.calling_convention, CallingConvention::Aapcs);
+//! assert_eq!(target.pointer_width, 32);
+//!
+//! let target = Target::parse("riscv64gc-unknown-linux-gnu").unwrap();
+//! assert_eq!(target.architecture, Architecture::Riscv64);
+//! assert_eq!(target.calling_convention, CallingConvention::RiscvLp64);
+//! ```
+
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{mangle::mangle_c, raw::Endianness, Architecture};
+
+/// An operating system, as named by the target triples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperatingSystem {
+    /// Linux, `linux`.
+    Linux,
+
+    /// macOS and iOS, `darwin`, `macos` or `ios`.
+    Darwin,
+
+    /// Windows, `windows`.
+    Windows,
+
+    /// FreeBSD, `freebsd`.
+    FreeBsd,
+
+    /// No operating system, `none`, for bare metal and kernels.
+    Bare,
+}
+
+impl OperatingSystem {
+    /// Parses an operating system from a component of a target triple.
+    fn from_component(component: &str) -> Option<Self> {
+        match component {
+            "linux" => Some(Self::Linux),
+            "darwin" | "macos" | "ios" => Some(Self::Darwin),
+            "windows" => Some(Self::Windows),
+            "freebsd" => Some(Self::FreeBsd),
+            "none" => Some(Self::Bare),
+            _ => None,
+        }
+    }
+}
+
+/// The format of the object files of an operating system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ObjectFormat {
+    /// The Executable and Linkable Format, of Linux, the BSDs and bare metal.
+    Elf,
+
+    /// Mach-O, of macOS and iOS.
+    MachO,
+
+    /// The Common Object File Format, of Windows.
+    Coff,
+}
+
+/// The default calling convention of the C functions of a target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallingConvention {
+    /// The System V AMD64 ABI, of x86-64 outside Windows.
+    SysV,
+
+    /// The Microsoft x64 calling convention, of x86-64 on Windows.
+    Win64,
+
+    /// The procedure call standard of AArch64.
+    Aapcs64,
+
+    /// The procedure call standard of 32-bit ARM.
+    Aapcs,
+
+    /// The LP64 calling convention of 64-bit RISC-V.
+    RiscvLp64,
+}
+
+/// The error returned when parsing a target triple whose architecture or operating system is unknown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownTarget(pub String);
+
+impl fmt::Display for UnknownTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown target `{}`", self.0)
+    }
+}
+
+impl Error for UnknownTarget {}
+
+/// The architecture and operating system code is generated for, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Target {
+    /// The architecture of the target.
+    pub architecture: Architecture,
+
+    /// The operating system of the target.
+    pub operating_system: OperatingSystem,
+
+    /// The format of the object files of the target.
+    pub object_format: ObjectFormat,
+
+    /// The default calling convention of the C functions of the target.
+    pub calling_convention: CallingConvention,
+
+    /// The width of the pointers, in bits.
+    pub pointer_width: u32,
+
+    /// The byte order of the target.
+    pub endianness: Endianness,
+}
+
+impl Target {
+    /// Creates the target of `architecture` running `operating_system`, with the object format, calling convention, pointer width and byte order
+    /// they imply.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{target::{CallingConvention, ObjectFormat, OperatingSystem, Target}, Architecture};
+    ///
+    /// let target = Target::new(Architecture::Aarch64, OperatingSystem::Windows);
+    /// assert_eq!(target.object_format, ObjectFormat::Coff);
+    /// assert_eq!(target.calling_convention, CallingConvention::Aapcs64);
+    /// ```
+    pub fn new(architecture: Architecture, operating_system: OperatingSystem) -> Self {
+        let object_format = match operating_system {
+            OperatingSystem::Darwin => ObjectFormat::MachO,
+            OperatingSystem::Windows => ObjectFormat::Coff,
+            OperatingSystem::Linux | OperatingSystem::FreeBsd | OperatingSystem::Bare => ObjectFormat::Elf,
+        };
+
+        let calling_convention = match architecture {
+            Architecture::X86_64 if operating_system == OperatingSystem::Windows => CallingConvention::Win64,
+            Architecture::X86_64 => CallingConvention::SysV,
+            Architecture::Aarch64 => CallingConvention::Aapcs64,
+            Architecture::Thumb => CallingConvention::Aapcs,
+            Architecture::Riscv64 => CallingConvention::RiscvLp64,
+        };
+
+        let pointer_width = match architecture {
+            Architecture::Thumb => 32,
+            Architecture::X86_64 | Architecture::Aarch64 | Architecture::Riscv64 => 64,
+        };
+
+        Self { architecture, operating_system, object_format, calling_convention, pointer_width, endianness: Endianness::Little }
+    }
+
+    /// The target of the host, the code is generated for the machine running it.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::target::{ObjectFormat, Target};
+    ///
+    /// let host = Target::host();
+    /// assert_eq!(host.pointer_width as usize, usize::BITS as usize);
+    /// assert_eq!(host.object_format == ObjectFormat::Coff, cfg!(windows));
+    /// ```
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm", target_arch = "riscv64"))]
+    pub fn host() -> Self {
+        let architecture = if cfg!(target_arch = "x86_64") {
+            Architecture::X86_64
+        } else if cfg!(target_arch = "aarch64") {
+            Architecture::Aarch64
+        } else if cfg!(target_arch = "arm") {
+            Architecture::Thumb
+        } else {
+            Architecture::Riscv64
+        };
+
+        let operating_system = if cfg!(target_os = "linux") {
+            OperatingSystem::Linux
+        } else if cfg!(any(target_os = "macos", target_os = "ios")) {
+            OperatingSystem::Darwin
+        } else if cfg!(windows) {
+            OperatingSystem::Windows
+        } else if cfg!(target_os = "freebsd") {
+            OperatingSystem::FreeBsd
+        } else {
+            OperatingSystem::Bare
+        };
+
+        Self::new(architecture, operating_system)
+    }
+
+    /// Parses a target triple, such as `x86_64-unknown-linux-gnu` or `aarch64-apple-darwin`, made of an architecture and of a vendor, an
+    /// operating system and an environment, the vendor and the environment being optional.  The versions suffixed to the ARM and RISC-V
+    /// architectures, as in `thumbv7em` or `riscv64gc`, are ignored.
+    ///
+    /// # Errors
+    /// Returns [`UnknownTarget`] if the architecture or the operating system is unknown.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{target::{OperatingSystem, Target}, Architecture};
+    ///
+    /// assert_eq!(Target::parse("x86_64-linux"), Ok(Target::new(Architecture::X86_64, OperatingSystem::Linux)));
+    /// assert_eq!(Target::parse("arm64-apple-ios"), Ok(Target::new(Architecture::Aarch64, OperatingSystem::Darwin)));
+    /// assert!(Target::parse("sparc64-unknown-linux-gnu").is_err());
+    /// assert!(Target::parse("x86_64-unknown-haiku").is_err());
+    /// ```
+    pub fn parse(triple: &str) -> Result<Self, UnknownTarget> {
+        let unknown = || UnknownTarget(triple.to_string());
+
+        let mut components = triple.split('-');
+        let architecture = components.next().ok_or_else(unknown)?;
+        let architecture = if architecture.starts_with("thumb") || architecture.starts_with("armv7") {
+            Architecture::Thumb
+        } else if architecture.starts_with("riscv64") {
+            Architecture::Riscv64
+        } else {
+            architecture.parse().map_err(|_| unknown())?
+        };
+
+        let operating_system = components.find_map(OperatingSystem::from_component).ok_or_else(unknown)?;
+        Ok(Self::new(architecture, operating_system))
+    }
+
+    /// The symbol of the C function `name` on the target, see [`mangle_c`].
+    pub fn mangle(&self, name: &str) -> String {
+        mangle_c(name, self)
+    }
+}
+
+impl fmt::Display for Target {
+    /// Writes the canonical triple of the target, which [`Target::parse`] parses back.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{target::{OperatingSystem, Target}, Architecture};
+    ///
+    /// assert_eq!(Target::parse("aarch64-apple-darwin").unwrap().to_string(), "aarch64-apple-darwin");
+    /// assert_eq!(Target::parse("x86_64-linux").unwrap().to_string(), "x86_64-unknown-linux-gnu");
+    ///
+    /// for architecture in Architecture::ALL {
+    ///     for os in [OperatingSystem::Linux, OperatingSystem::Darwin, OperatingSystem::Windows, OperatingSystem::FreeBsd, OperatingSystem::Bare] {
+    ///         let target = Target::new(architecture, os);
+    ///         assert_eq!(Target::parse(&target.to_string()), Ok(target));
+    ///     }
+    /// }
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rest = match self.operating_system {
+            OperatingSystem::Linux => "unknown-linux-gnu",
+            OperatingSystem::Darwin => "apple-darwin",
+            OperatingSystem::Windows => "pc-windows-msvc",
+            OperatingSystem::FreeBsd => "unknown-freebsd",
+            OperatingSystem::Bare => "unknown-none",
+        };
+
+        write!(f, "{}-{}", self.architecture, rest)
+    }
+}
+
+impl FromStr for Target {
+    type Err = UnknownTarget;
+
+    fn from_str(triple: &str) -> Result<Self, Self::Err> {
+        Self::parse(triple)
+    }
+}
@@ -0,0 +1,250 @@
+//! Templates, pre-encoded instruction bytes copied into a stream as a whole, such as a blob of intrinsics or a copied sequence, whose holes are
+//! filled with the addresses of labels and symbols like the fields written by the stream itself.
+//!
+//! A template is written by [`InstructionStream::emit_template`](crate::InstructionStream::emit_template), along with a [`TemplateFixup`] for
+//! each of its holes: the offset of the hole within the template, its width, how it's filled, and the label or the symbol it refers to.  The
+//! fixups are moved to where the template lands and handed to
+//! [`InstructionStream::record_template_fixup`](crate::InstructionStream::record_template_fixup), which resolves them with the other label uses
+//! of the stream when it's finished.  The streams whose own label uses can't hold the holes keep them in [`TemplateHoles`], filled by
+//! [`TemplateHoles::resolve`].
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{entity::{EntityRef, LabelRef}, template::{check_fixups, TemplateFixup, TemplateFixupKind, TemplateTarget}};
+//!
+//! let exit = LabelRef::new(0);
+//! let fixup = TemplateFixup::rel32(6, TemplateTarget::Label(exit));
+//! assert_eq!((fixup.width, fixup.kind), (4, TemplateFixupKind::Rel32));
+//!
+//! // `mov eax, 1; jmp exit; ret`, with the displacement of the jump as a hole.
+//! check_fixups(&[0xb8, 0x01, 0x00, 0x00, 0x00, 0xe9, 0x00, 0x00, 0x00, 0x00, 0xc3], &[fixup]);
+//! ```
+//!
+//! A hole running past the end of the template is rejected:
+//! ```should_panic
+//! use asmkit_core::{entity::{EntityRef, LabelRef}, template::{check_fixups, TemplateFixup, TemplateTarget}};
+//!
+//! check_fixups(&[0xe9, 0x00, 0x00, 0x00], &[TemplateFixup::rel32(1, TemplateTarget::Label(LabelRef::new(0)))]);
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::{
+    entity::{EntityRef, LabelRef, SymRef},
+    raw::Endianness,
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+};
+
+/// How the hole of a template is filled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TemplateFixupKind {
+    /// The 32-bit signed displacement of the target from the end of the hole, as the branches and the `rip`-relative operands of x86-64 hold.
+    Rel32,
+
+    /// The 64-bit absolute address of the target, left to a relocation as it is only known once the code is loaded.
+    Abs64,
+}
+
+impl TemplateFixupKind {
+    /// The width of the holes of the kind, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Rel32 => 4,
+            Self::Abs64 => 8,
+        }
+    }
+}
+
+/// What the hole of a template refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TemplateTarget {
+    /// A label of the stream the template is written to.
+    Label(LabelRef),
+
+    /// An external symbol of the stream the template is written to.
+    Symbol(SymRef),
+}
+
+/// A hole of a template, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TemplateFixup {
+    /// The offset of the hole within the template.
+    pub offset: usize,
+
+    /// The width of the hole, in bytes, the size of its kind.
+    pub width: usize,
+
+    /// How the hole is filled.
+    pub kind: TemplateFixupKind,
+
+    /// What the hole refers to.
+    pub target: TemplateTarget,
+}
+
+impl TemplateFixup {
+    /// A hole at `offset` holding the 32-bit displacement of `target` from the end of the hole.
+    pub fn rel32(offset: usize, target: TemplateTarget) -> Self {
+        Self { offset, width: 4, kind: TemplateFixupKind::Rel32, target }
+    }
+
+    /// A hole at `offset` holding the 64-bit absolute address of `target`.
+    pub fn abs64(offset: usize, target: TemplateTarget) -> Self {
+        Self { offset, width: 8, kind: TemplateFixupKind::Abs64, target }
+    }
+}
+
+/// Checks the holes `fixups` of the template `bytes`, as the instruction streams do before writing it.
+///
+/// # Panics
+/// Panics if the width of a hole isn't the size of its kind, if a hole runs past the end of the template, or if two holes overlap.
+pub fn check_fixups(bytes: &[u8], fixups: &[TemplateFixup]) {
+    let mut holes = Vec::with_capacity(fixups.len());
+    for fixup in fixups {
+        assert!(fixup.width == fixup.kind.size(), "a {:?} hole takes {} bytes, not {}", fixup.kind, fixup.kind.size(), fixup.width);
+        assert!(
+            fixup.offset + fixup.width <= bytes.len(),
+            "the hole at {:#x} runs past the end of the {}-byte template",
+            fixup.offset,
+            bytes.len(),
+        );
+
+        holes.push(fixup.offset..fixup.offset + fixup.width);
+    }
+
+    holes.sort_by_key(|hole| hole.start);
+    for pair in holes.windows(2) {
+        assert!(pair[0].end <= pair[1].start, "the holes at {:#x} and {:#x} overlap", pair[0].start, pair[1].start);
+    }
+}
+
+/// An error filling the holes of templates, see [`TemplateHoles::resolve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A hole refers to a label which is never attached.
+    Unattached {
+        /// The offset of the hole in the stream.
+        offset: usize,
+
+        /// The label.
+        label: LabelRef,
+    },
+
+    /// The displacement of a label doesn't fit in the 32 bits of its hole.
+    OutOfRange {
+        /// The offset of the hole in the stream.
+        offset: usize,
+
+        /// The displacement of the label from the end of the hole.
+        displacement: i64,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unattached { offset, label } => write!(f, "the template hole at {:#x} refers to label {}, which is never attached", offset, label.as_u32()),
+            Self::OutOfRange { offset, displacement } => {
+                write!(f, "the displacement {:#x} of the template hole at {:#x} doesn't fit in 32 bits", displacement, offset)
+            },
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// The holes of the templates written to an instruction stream, at their offsets in the stream, for the streams filling them apart from their
+/// own label uses.
+///
+/// # Example
+/// ```
+/// use asmkit_core::{
+///     entity::{EntityRef, LabelRef, SymRef},
+///     raw::Endianness,
+///     reloc::{Relocation, RelocationKind, RelocationTarget},
+///     template::{TemplateError, TemplateFixup, TemplateHoles, TemplateTarget},
+/// };
+///
+/// let (exit, handler) = (LabelRef::new(0), SymRef::new(0));
+/// let mut holes = TemplateHoles::new();
+/// holes.push(TemplateFixup::rel32(1, TemplateTarget::Label(exit)));
+/// holes.push(TemplateFixup::abs64(5, TemplateTarget::Label(exit)));
+/// holes.push(TemplateFixup::rel32(13, TemplateTarget::Symbol(handler)));
+///
+/// let mut bytes = [0; 17];
+/// let relocations = holes.resolve(&mut bytes, Endianness::Little, |_| Some(0x20)).unwrap();
+/// assert_eq!(bytes[1..5], [0x1b, 0x00, 0x00, 0x00]);
+/// assert_eq!(relocations, [
+///     Relocation { offset: 5, kind: RelocationKind::Abs64, target: RelocationTarget::Base, addend: 0x20 },
+///     Relocation { offset: 13, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(handler), addend: -4 },
+/// ]);
+///
+/// assert_eq!(holes.resolve(&mut bytes, Endianness::Little, |_| None), Err(TemplateError::Unattached { offset: 1, label: exit }));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TemplateHoles {
+    /// The holes, by the order they were written in.
+    holes: Vec<TemplateFixup>,
+}
+
+impl TemplateHoles {
+    /// Creates an empty set of holes.
+    pub fn new() -> Self {
+        Self { holes: Vec::new() }
+    }
+
+    /// Records the hole `fixup`, at its offset in the stream.
+    pub fn push(&mut self, fixup: TemplateFixup) {
+        self.holes.push(fixup);
+    }
+
+    /// Records the hole `fixup`, at its offset in the stream, for the streams without external symbols.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol.
+    pub fn push_label(&mut self, fixup: TemplateFixup) {
+        assert!(matches!(fixup.target, TemplateTarget::Label(_)), "the template hole at {:#x} refers to a symbol, which the stream has none of", fixup.offset);
+        self.push(fixup);
+    }
+
+    /// Returns true if no hole was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.holes.is_empty()
+    }
+
+    /// Fills the holes of `bytes`, the code of the stream: the displacements of the labels at the offsets `offset` returns are written in
+    /// the byte order `endianness`, and the absolute addresses of the labels and the holes referring to symbols are returned as relocations,
+    /// sorted by offset.
+    ///
+    /// # Errors
+    /// Returns [`TemplateError::Unattached`] if a hole refers to a label `offset` returns nothing for, and [`TemplateError::OutOfRange`] if the
+    /// displacement of a label doesn't fit in 32 bits.
+    pub fn resolve(&self, bytes: &mut [u8], endianness: Endianness, offset: impl Fn(LabelRef) -> Option<usize>) -> Result<Vec<Relocation>, TemplateError> {
+        let mut relocations = Vec::new();
+
+        for hole in &self.holes {
+            let (kind, target, addend) = match (hole.target, hole.kind) {
+                (TemplateTarget::Label(label), kind) => {
+                    let target = offset(label).ok_or(TemplateError::Unattached { offset: hole.offset, label })?;
+                    if kind == TemplateFixupKind::Abs64 {
+                        // the absolute address of a label is only known once the code is loaded.
+                        (RelocationKind::Abs64, RelocationTarget::Base, target as i64)
+                    } else {
+                        let displacement = target as i64 - (hole.offset + hole.width) as i64;
+                        let value = i32::try_from(displacement).map_err(|_| TemplateError::OutOfRange { offset: hole.offset, displacement })?;
+                        endianness.write(&mut bytes[hole.offset..], value as u32 as u128, hole.width);
+                        continue;
+                    }
+                },
+
+                // the displacement is relative to the end of the hole, 4 bytes past its start.
+                (TemplateTarget::Symbol(symbol), TemplateFixupKind::Rel32) => (RelocationKind::Rel32, RelocationTarget::Symbol(symbol), -4),
+                (TemplateTarget::Symbol(symbol), TemplateFixupKind::Abs64) => (RelocationKind::Abs64, RelocationTarget::Symbol(symbol), 0),
+            };
+
+            relocations.push(Relocation { offset: hole.offset, kind, target, addend });
+        }
+
+        relocations.sort_by_key(|relocation| relocation.offset);
+        Ok(relocations)
+    }
+}
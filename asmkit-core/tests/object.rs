@@ -0,0 +1,522 @@
+//! Reads the objects written by `write_object` back, and links them with the C compiler of the host where there is one.
+
+use std::{path::PathBuf, process::Command};
+
+use asmkit_core::{
+    bss::Bss,
+    calls::{CallKind, CallSite, CallSiteTarget},
+    dwarf::debug_line,
+    entity::{Comdat, EntityList, EntityRef, LabelRef, SelectionKind, Symbol},
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    source_map::Location,
+    target::Target,
+    write_object, ObjectError, Product,
+};
+
+/// A section of an ELF object.
+struct Section {
+    name: String,
+    kind: u32,
+    flags: u64,
+    link: u32,
+    info: u32,
+    data: Vec<u8>,
+}
+
+/// A symbol of an ELF object.
+#[derive(Debug, PartialEq)]
+struct ElfSymbol {
+    name: String,
+    info: u8,
+    section: u16,
+    value: u64,
+}
+
+/// A relocation of an ELF object, with the name of its symbol.
+#[derive(Debug, PartialEq)]
+struct ElfRelocation {
+    offset: u64,
+    kind: u32,
+    symbol: String,
+    addend: i64,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn string_at(table: &[u8], offset: usize) -> String {
+    let end = table[offset..].iter().position(|byte| *byte == 0).unwrap();
+    String::from_utf8(table[offset..offset + end].to_vec()).unwrap()
+}
+
+/// A relocatable ELF object, read back.
+struct Elf {
+    machine: u16,
+    sections: Vec<Section>,
+}
+
+impl Elf {
+    fn parse(object: &[u8]) -> Self {
+        assert_eq!(object[..4], *b"\x7fELF");
+        assert_eq!(u16_at(object, 16), 1, "not a relocatable object");
+
+        let shoff = u64_at(object, 40) as usize;
+        let shnum = u16_at(object, 60) as usize;
+        let shstrndx = u16_at(object, 62) as usize;
+
+        let mut sections = (0..shnum).map(|index| {
+            let header = &object[shoff + index * 64..shoff + (index + 1) * 64];
+            let (offset, size) = (u64_at(header, 24) as usize, u64_at(header, 32) as usize);
+            Section {
+                name: u32_at(header, 0).to_string(),
+                kind: u32_at(header, 4),
+                flags: u64_at(header, 8),
+                link: u32_at(header, 40),
+                info: u32_at(header, 44),
+                data: object[offset..offset + size].to_vec(),
+            }
+        }).collect::<Vec<_>>();
+
+        let names = sections[shstrndx].data.clone();
+        for section in &mut sections {
+            section.name = string_at(&names, section.name.parse().unwrap());
+        }
+
+        Self { machine: u16_at(object, 18), sections }
+    }
+
+    fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    fn symbols(&self) -> Vec<ElfSymbol> {
+        let symtab = self.section(".symtab").unwrap();
+        let strtab = &self.sections[symtab.link as usize].data;
+        symtab.data.chunks(24).map(|entry| ElfSymbol {
+            name: string_at(strtab, u32_at(entry, 0) as usize),
+            info: entry[4],
+            section: u16_at(entry, 6),
+            value: u64_at(entry, 8),
+        }).collect()
+    }
+
+    /// The relocations of the section `name`.
+    fn relocations(&self, name: &str) -> Vec<ElfRelocation> {
+        let symbols = self.symbols();
+        let Some(rela) = self.section(&format!(".rela{}", name)) else {
+            return Vec::new();
+        };
+
+        rela.data.chunks(24).map(|entry| {
+            let info = u64_at(entry, 8);
+            let symbol = &symbols[(info >> 32) as usize];
+            let symbol = if symbol.info & 0xf == 3 { self.sections[symbol.section as usize].name.clone() } else { symbol.name.clone() };
+            ElfRelocation { offset: u64_at(entry, 0), kind: info as u32, symbol, addend: u64_at(entry, 16) as i64 }
+        }).collect()
+    }
+}
+
+/// A `main` function printing a string with `puts` and returning 42.
+fn hello() -> Product {
+    let mut bytes = vec![
+        0x53,                                                       // push rbx
+        0x48, 0xbf, 0, 0, 0, 0, 0, 0, 0, 0,                         // movabs rdi, message
+        0xe8, 0, 0, 0, 0,                                           // call puts
+        0xb8, 0x2a, 0x00, 0x00, 0x00,                               // mov eax, 42
+        0x5b,                                                       // pop rbx
+        0xc3,                                                       // ret
+    ];
+    let message = bytes.len() as i64;
+    bytes.extend_from_slice(b"hello from asmkit\0");
+
+    let mut symbols = EntityList::new();
+    let puts = symbols.push(Symbol { name: "puts".to_string() });
+    let mut product = Product::with_relocations(bytes, vec![
+        Relocation { offset: 3, kind: RelocationKind::Abs64, target: RelocationTarget::Base, addend: message },
+        Relocation { offset: 12, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    ], symbols);
+    product.add_call_site(CallSite { offset: 11, len: 5, target: CallSiteTarget::Symbol(puts), kind: CallKind::Direct });
+    product.define("main", 0);
+    product
+}
+
+/// A `main` function returning `double(21)`.
+fn call_double() -> Product {
+    let mut symbols = EntityList::new();
+    let double = symbols.push(Symbol { name: "double".to_string() });
+    let mut product = Product::with_relocations(vec![
+        0xbf, 0x15, 0x00, 0x00, 0x00,                               // mov edi, 21
+        0xe8, 0, 0, 0, 0,                                           // call double
+        0xc3,                                                       // ret
+    ], vec![
+        Relocation { offset: 6, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(double), addend: -4 },
+    ], symbols);
+    product.add_call_site(CallSite { offset: 5, len: 5, target: CallSiteTarget::Symbol(double), kind: CallKind::Direct });
+    product.define("main", 0);
+    product
+}
+
+/// A `double` function, defined weak or in the COMDAT group `comdat`.
+fn double(comdat: Option<&str>) -> Product {
+    let mut product = Product::new(vec![0x8d, 0x04, 0x3f, 0xc3]); // lea eax, [rdi + rdi]; ret
+    match comdat {
+        Some(name) => product.define_comdat("double", 0, Comdat { name: name.to_string(), selection: SelectionKind::Any }),
+        None => product.define_weak("double", 0),
+    }
+    product
+}
+
+/// [`hello`], generated from the lines 3 to 5 of `hello.src`.
+fn hello_with_lines() -> Product {
+    let mut product = hello();
+    let map = product.source_map_mut();
+    map.register_file(1, "hello.src");
+    map.record(0, Some(Location { file_id: 1, line: 3, col: 1 }));
+    map.record(11, Some(Location { file_id: 1, line: 4, col: 1 }));
+    map.record(16, Some(Location { file_id: 1, line: 5, col: 1 }));
+    map.record(23, None);
+    product
+}
+
+/// An AArch64 `main` function calling `double(21)` defined after it, which tail calls `report`.
+fn aarch64_double() -> Product {
+    let mut bytes = Vec::new();
+    for instruction in [
+        0xa9bf7bfdu32,                                              // main: stp x29, x30, [sp, #-16]!
+        0x528002a0,                                                 // mov w0, #21
+        0x94000000,                                                 // bl double
+        0xa8c17bfd,                                                 // ldp x29, x30, [sp], #16
+        0xd65f03c0,                                                 // ret
+        0x0b000000,                                                 // double: add w0, w0, w0
+        0x14000000,                                                 // b report
+    ] {
+        bytes.extend_from_slice(&instruction.to_le_bytes());
+    }
+
+    let mut symbols = EntityList::new();
+    let double = symbols.push(Symbol { name: "double".to_string() });
+    let report = symbols.push(Symbol { name: "report".to_string() });
+    let mut product = Product::with_relocations(bytes, vec![
+        Relocation { offset: 8, kind: RelocationKind::Branch26, target: RelocationTarget::Symbol(double), addend: 0 },
+        Relocation { offset: 24, kind: RelocationKind::Branch26, target: RelocationTarget::Symbol(report), addend: 0 },
+    ], symbols);
+    product.define("main", 0);
+    product.define("double", 20);
+    product
+}
+
+fn linux() -> Target {
+    Target::parse("x86_64-unknown-linux-gnu").unwrap()
+}
+
+/// Links the objects and the C `sources` into an executable with the C compiler of the host, and returns the path of the executable, or `None`
+/// if the host has no C compiler or can't run x86-64 Linux executables.
+fn link(name: &str, objects: &[Vec<u8>], sources: &[&str]) -> Option<PathBuf> {
+    if !cfg!(all(target_os = "linux", target_arch = "x86_64")) || Command::new("cc").arg("--version").output().is_err() {
+        return None;
+    }
+
+    let directory = std::env::temp_dir().join(format!("asmkit-object-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let mut command = Command::new("cc");
+    command.arg("-no-pie");
+    for (index, object) in objects.iter().enumerate() {
+        let path = directory.join(format!("{}.o", index));
+        std::fs::write(&path, object).unwrap();
+        command.arg(path);
+    }
+
+    for (index, source) in sources.iter().enumerate() {
+        let path = directory.join(format!("{}.c", index));
+        std::fs::write(&path, source).unwrap();
+        command.arg(path);
+    }
+
+    let executable = directory.join(name);
+    let output = command.arg("-o").arg(&executable).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    Some(executable)
+}
+
+#[test]
+fn writes_the_code_the_symbols_and_the_relocations() {
+    let product = hello();
+    let elf = Elf::parse(&write_object(&product, &linux()).unwrap());
+    assert_eq!(elf.machine, 62); // EM_X86_64
+
+    let text = elf.section(".text").unwrap();
+    assert_eq!((text.kind, text.flags), (1, 0x6)); // SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR
+    assert_eq!(text.data, product.bytes());
+    assert!(elf.section(".note.GNU-stack").is_some());
+
+    let symtab = elf.section(".symtab").unwrap();
+    let symbols = elf.symbols();
+    assert_eq!(symtab.info, 2, "the first global symbol follows the section symbol");
+    assert_eq!(symbols[2], ElfSymbol { name: "main".to_string(), info: 0x12, section: 1, value: 0 }); // STB_GLOBAL, STT_FUNC
+    assert_eq!(symbols[3], ElfSymbol { name: "puts".to_string(), info: 0x10, section: 0, value: 0 }); // STB_GLOBAL, undefined
+
+    let rela = elf.section(".rela.text").unwrap();
+    assert_eq!((rela.kind, rela.info), (4, 1));
+    assert_eq!(elf.relocations(".text"), [
+        ElfRelocation { offset: 3, kind: 1, symbol: ".text".to_string(), addend: 23 }, // R_X86_64_64
+        ElfRelocation { offset: 12, kind: 4, symbol: "puts".to_string(), addend: -4 }, // R_X86_64_PLT32
+    ]);
+}
+
+#[test]
+fn writes_the_relative_relocations_outside_calls_as_pc32() {
+    let mut symbols = EntityList::new();
+    let counter = symbols.push(Symbol { name: "counter".to_string() });
+    let product = Product::with_relocations(vec![0x48, 0x8d, 0x05, 0, 0, 0, 0, 0xe8, 0, 0, 0, 0], vec![ // lea rax, [rip + counter]; call 0x1000
+        Relocation { offset: 3, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(counter), addend: -4 },
+        Relocation { offset: 8, kind: RelocationKind::Rel32, target: RelocationTarget::Absolute(0x1000), addend: -4 },
+    ], symbols);
+
+    let elf = Elf::parse(&write_object(&product, &linux()).unwrap());
+    assert_eq!(elf.relocations(".text"), [
+        ElfRelocation { offset: 3, kind: 2, symbol: "counter".to_string(), addend: -4 }, // R_X86_64_PC32
+        ElfRelocation { offset: 8, kind: 2, symbol: String::new(), addend: 0xffc },
+    ]);
+}
+
+/// A `main` function returning the sum of the two loads of `answer` through the global offset table.
+fn load_answer() -> Product {
+    let mut symbols = EntityList::new();
+    let answer = symbols.push(Symbol { name: "answer".to_string() });
+    let mut product = Product::with_relocations(vec![
+        0x48, 0x8b, 0x05, 0, 0, 0, 0,                               // mov rax, qword ptr [rip + answer@GOTPCREL]
+        0x48, 0x8b, 0x0d, 0, 0, 0, 0,                               // mov rcx, qword ptr [rip + answer@GOTPCREL]
+        0x8b, 0x00,                                                 // mov eax, dword ptr [rax]
+        0x03, 0x01,                                                 // add eax, dword ptr [rcx]
+        0xc3,                                                       // ret
+    ], vec![
+        Relocation { offset: 3, kind: RelocationKind::RexGotPcRelX, target: RelocationTarget::Symbol(answer), addend: -4 },
+        Relocation { offset: 10, kind: RelocationKind::GotPcRel, target: RelocationTarget::Symbol(answer), addend: -4 },
+    ], symbols);
+    product.define("main", 0);
+    product
+}
+
+#[test]
+fn writes_the_aarch64_branches_as_calls_and_jumps() {
+    let product = aarch64_double();
+    let elf = Elf::parse(&write_object(&product, &Target::parse("aarch64-unknown-linux-gnu").unwrap()).unwrap());
+    assert_eq!(elf.machine, 183); // EM_AARCH64
+    assert_eq!(elf.section(".text").unwrap().data, product.bytes());
+
+    let symbols = elf.symbols();
+    assert_eq!(symbols[2], ElfSymbol { name: "main".to_string(), info: 0x12, section: 1, value: 0 });
+    assert_eq!(symbols[3], ElfSymbol { name: "double".to_string(), info: 0x12, section: 1, value: 20 });
+    assert_eq!(symbols[4], ElfSymbol { name: "report".to_string(), info: 0x10, section: 0, value: 0 });
+    assert_eq!(elf.relocations(".text"), [
+        ElfRelocation { offset: 8, kind: 283, symbol: "double".to_string(), addend: 0 }, // R_AARCH64_CALL26
+        ElfRelocation { offset: 24, kind: 282, symbol: "report".to_string(), addend: 0 }, // R_AARCH64_JUMP26
+    ]);
+}
+
+#[test]
+fn writes_the_global_offset_table_relocations() {
+    let elf = Elf::parse(&write_object(&load_answer(), &linux()).unwrap());
+    assert_eq!(elf.relocations(".text"), [
+        ElfRelocation { offset: 3, kind: 42, symbol: "answer".to_string(), addend: -4 }, // R_X86_64_REX_GOTPCRELX
+        ElfRelocation { offset: 10, kind: 9, symbol: "answer".to_string(), addend: -4 }, // R_X86_64_GOTPCREL
+    ]);
+}
+
+/// A `main` function returning twice the thread-local `answer`, loaded in the initial-exec model.
+fn load_thread_local() -> Product {
+    let mut symbols = EntityList::new();
+    let answer = symbols.push(Symbol { name: "answer".to_string() });
+    let mut product = Product::with_relocations(vec![
+        0x48, 0x8b, 0x05, 0, 0, 0, 0,                               // mov rax, qword ptr [rip + answer@GOTTPOFF]
+        0x64, 0x8b, 0x00,                                           // mov eax, dword ptr fs:[rax]
+        0x01, 0xc0,                                                 // add eax, eax
+        0xc3,                                                       // ret
+    ], vec![
+        Relocation { offset: 3, kind: RelocationKind::GotTpOff, target: RelocationTarget::Symbol(answer), addend: -4 },
+    ], symbols);
+    product.define("main", 0);
+    product
+}
+
+#[test]
+fn writes_the_initial_exec_relocations() {
+    let elf = Elf::parse(&write_object(&load_thread_local(), &linux()).unwrap());
+    assert_eq!(elf.relocations(".text"), [
+        ElfRelocation { offset: 3, kind: 22, symbol: "answer".to_string(), addend: -4 }, // R_X86_64_GOTTPOFF
+    ]);
+    assert_eq!(elf.symbols()[3], ElfSymbol { name: "answer".to_string(), info: 0x16, section: 0, value: 0 }); // STB_GLOBAL, STT_TLS
+}
+
+#[test]
+fn writes_the_line_table_of_the_source_map() {
+    assert!(Elf::parse(&write_object(&hello(), &linux()).unwrap()).section(".debug_line").is_none());
+
+    let product = hello_with_lines();
+    let elf = Elf::parse(&write_object(&product, &linux()).unwrap());
+    let section = elf.section(".debug_line").unwrap();
+    assert_eq!(section.data, debug_line(&product, 0));
+
+    // the address set by the line number program follows the header.
+    let header_length = u32::from_le_bytes(section.data[6..10].try_into().unwrap()) as u64;
+    assert_eq!(elf.relocations(".debug_line"), [
+        ElfRelocation { offset: 10 + header_length + 3, kind: 1, symbol: ".text".to_string(), addend: 0 }, // R_X86_64_64
+    ]);
+}
+
+#[test]
+fn writes_the_weak_definitions_as_weak_symbols() {
+    let elf = Elf::parse(&write_object(&double(None), &linux()).unwrap());
+    assert_eq!(elf.symbols()[2], ElfSymbol { name: "double".to_string(), info: 0x22, section: 1, value: 0 }); // STB_WEAK, STT_FUNC
+    assert!(elf.section(".group").is_none());
+}
+
+#[test]
+fn writes_the_comdat_definitions_in_a_group() {
+    let elf = Elf::parse(&write_object(&double(Some("double")), &linux()).unwrap());
+    let group = elf.section(".group").unwrap();
+    assert_eq!((group.kind, group.link), (17, 3)); // SHT_GROUP, linked to `.symtab`
+    assert_eq!(group.data, [1, 0, 0, 0, 2, 0, 0, 0]); // GRP_COMDAT, `.text`
+    assert_eq!(elf.sections[2].name, ".text");
+    assert_eq!(elf.sections[2].flags, 0x206); // SHF_ALLOC | SHF_EXECINSTR | SHF_GROUP
+
+    // the signature is the global symbol named after the group.
+    let symbols = elf.symbols();
+    assert_eq!(symbols[group.info as usize], ElfSymbol { name: "double".to_string(), info: 0x12, section: 2, value: 0 });
+
+    // or a local symbol, if the group is named otherwise.
+    let elf = Elf::parse(&write_object(&double(Some("helpers")), &linux()).unwrap());
+    let group = elf.section(".group").unwrap();
+    assert_eq!(elf.symbols()[group.info as usize], ElfSymbol { name: "helpers".to_string(), info: 0x00, section: 2, value: 0 });
+    assert_eq!(elf.section(".symtab").unwrap().info, 3);
+}
+
+#[test]
+fn puts_the_relocations_of_a_comdat_in_its_group() {
+    let mut product = call_double();
+    product.define_comdat("start", 0, Comdat { name: "start".to_string(), selection: SelectionKind::Any });
+
+    let elf = Elf::parse(&write_object(&product, &linux()).unwrap());
+    assert_eq!(elf.section(".group").unwrap().data, [1, 0, 0, 0, 2, 0, 0, 0, 5, 0, 0, 0]); // GRP_COMDAT, `.text`, `.rela.text`
+    assert_eq!(elf.sections[5].name, ".rela.text");
+    assert_eq!(elf.sections[5].flags, 0x240); // SHF_INFO_LINK | SHF_GROUP
+}
+
+#[test]
+fn keeps_the_names_marked_as_mangled_through_link() {
+    let mut product = hello();
+    product.define("_ZN3jit5entryE", 0);
+    product.mark_mangled("_ZN3jit5entryE");
+
+    let linked = asmkit_core::link(vec![product, double(None)], Default::default()).unwrap();
+    assert!(linked.is_mangled("_ZN3jit5entryE"));
+    assert!(!linked.is_mangled("main") && !linked.is_mangled("puts"));
+
+    // `mangle_c` leaves the names unchanged on ELF, marked or not.
+    let names = Elf::parse(&write_object(&linked, &linux()).unwrap()).symbols().into_iter().map(|symbol| symbol.name).collect::<Vec<_>>();
+    assert_eq!(names[2..], ["main", "_ZN3jit5entryE", "double", "puts"]);
+}
+
+#[test]
+fn rejects_two_comdat_groups() {
+    let mut product = double(Some("double"));
+    product.define_comdat("triple", 0, Comdat { name: "triple".to_string(), selection: SelectionKind::Any });
+    assert_eq!(write_object(&product, &linux()), Err(ObjectError::ComdatGroups { first: "double".to_string(), second: "triple".to_string() }));
+}
+
+#[test]
+fn rejects_the_uninitialized_data() {
+    let mut bss = Bss::new();
+    bss.reserve(LabelRef::new(0), 8, 8);
+
+    let mut product = hello();
+    product.set_bss(bss);
+    assert_eq!(write_object(&product, &linux()), Err(ObjectError::Bss));
+}
+
+#[test]
+fn links_with_the_c_compiler() {
+    let Some(executable) = link("hello", &[write_object(&hello(), &linux()).unwrap()], &[]) else {
+        return;
+    };
+
+    let output = Command::new(executable).output().unwrap();
+    assert_eq!(output.stdout, b"hello from asmkit\n");
+    assert_eq!(output.status.code(), Some(42));
+}
+
+#[test]
+fn links_the_weak_and_comdat_definitions_once() {
+    for (name, comdat) in [("weak", None), ("comdat", Some("double"))] {
+        let objects = [call_double(), double(comdat), double(comdat)].map(|product| write_object(&product, &linux()).unwrap());
+        let Some(executable) = link(name, &objects, &[]) else {
+            return;
+        };
+
+        assert_eq!(Command::new(executable).status().unwrap().code(), Some(42));
+    }
+}
+
+#[test]
+fn links_the_loads_through_the_global_offset_table() {
+    let mut answer = Product::new(vec![21, 0, 0, 0]);
+    answer.define("answer", 0);
+
+    let objects = [load_answer(), answer].map(|product| write_object(&product, &linux()).unwrap());
+    let Some(executable) = link("got", &objects, &[]) else {
+        return;
+    };
+
+    assert_eq!(Command::new(executable).status().unwrap().code(), Some(42));
+}
+
+#[test]
+fn links_the_initial_exec_thread_local_loads() {
+    let objects = [write_object(&load_thread_local(), &linux()).unwrap()];
+    let Some(executable) = link("tls", &objects, &["__thread int answer = 21;"]) else {
+        return;
+    };
+
+    assert_eq!(Command::new(executable).status().unwrap().code(), Some(42));
+}
+
+#[test]
+fn links_the_line_table() {
+    let Some(executable) = link("lines", &[write_object(&hello_with_lines(), &linux()).unwrap()], &[]) else {
+        return;
+    };
+
+    let Ok(output) = Command::new("objdump").arg("--dwarf=decodedline").arg(&executable).output() else {
+        return;
+    };
+
+    // the rows of `hello.src` are at the addresses of the code once linked.
+    let main = Command::new("nm").arg(&executable).output().unwrap();
+    let main = String::from_utf8(main.stdout).unwrap();
+    let main = main.lines().find(|line| line.ends_with(" T main")).unwrap();
+    let main = u64::from_str_radix(&main[..16], 16).unwrap();
+
+    let rows = String::from_utf8(output.stdout).unwrap();
+    let rows = rows.lines().filter(|line| line.starts_with("hello.src ")).map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        (fields[1].to_string(), u64::from_str_radix(fields[2].trim_start_matches("0x"), 16).unwrap())
+    }).collect::<Vec<_>>();
+
+    assert_eq!(rows, [
+        ("3".to_string(), main),
+        ("4".to_string(), main + 11),
+        ("5".to_string(), main + 16),
+        ("0".to_string(), main + 23),
+        ("-".to_string(), main + 41),
+    ]);
+}
@@ -0,0 +1,590 @@
+//! Compile-time checked assembly macros for AsmKit.
+//!
+//! The macros are driven by the same instruction registries as the text assemblers, so a mnemonic becomes available to them as soon as its forms are added to
+//! the table of its backend.  Unlike the text assemblers, mnemonics, registers and operand kinds are checked while compiling, and the expansion is a plain
+//! sequence of emitter calls.
+
+use std::str::FromStr;
+
+use asmkit_x86_64::{
+    __core::entity::{EntityRef, LabelRef},
+    register::{Reg16, Reg32, Reg64, Reg8},
+    registry::{self, check_operands, InstructionForm, Memory, MemoryTarget, Operand, OperandKind, Register, MEMORY_SIZES},
+};
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Emits x86-64 instructions into an `x86_64InstructionStream`, checking them at compile time.
+///
+/// The first item is an expression evaluating to the instruction stream (or a mutable reference to it), followed by `;`-separated instructions and label
+/// definitions, written in Intel syntax:
+///
+/// - registers are written by their lowercase names, such as `rax` or `r10b`;
+/// - `Rb(expr)`, `Rw(expr)`, `Rd(expr)` and `Rq(expr)` splice a register picked at runtime, of type `Reg8`, `Reg16`, `Reg32` or `Reg64` respectively;
+/// - integer literals, optionally negated, are immediates, and pick the first form of the instruction whose operand kinds they fit in;
+/// - `(expr)` splices an immediate computed at runtime, which picks the widest form of the instruction and is converted with `as`;
+/// - `name:` defines a label, which may be referenced by any instruction of the same invocation, before or after its definition, and `short name`
+///   references it through a short branch;
+/// - `[base + index*scale + displacement]` is a memory operand, optionally sized with a prefix such as `qword ptr`, whose displacement may be
+///   spliced as `(expr)`, and `[name]` addresses a label;
+/// - the conditional jumps are written by any of their mnemonics, such as `jnz` or `jne`.
+///
+/// Unknown mnemonics, unknown registers and operands matching no form of the instruction are compile errors.  The expansion refers to the `asmkit_x86_64`
+/// crate, which must be a dependency of the calling crate.
+///
+/// # Example
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_macros::asm_x64;
+/// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+///
+/// let scratch = Reg64::R11;
+/// let frame = 0x20;
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// asm_x64!(stream;
+///     start:;
+///     push rbp;
+///     mov rbp, rsp;
+///     mov eax, 42;
+///     mov rcx, -1;
+///     mov rdx, 0x1122334455667788;
+///     mov Rq(scratch), (frame);
+///     push -2;
+///     push fs;
+///     ret (frame);
+///     jmp start
+/// );
+///
+/// let mut expected = x86_64InstructionStream::new();
+/// let start = expected.create_label_attached();
+/// expected.push_reg64(Reg64::Rbp);
+/// expected.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+/// expected.mov_reg32_imm32(asmkit_x86_64::register::Reg32::Eax, 42);
+/// expected.mov_reg64_imm32(Reg64::Rcx, -1);
+/// expected.mov_reg64_imm64(Reg64::Rdx, 0x1122334455667788);
+/// expected.mov_reg64_imm64(scratch, frame as u64);
+/// expected.push_imm8(-2);
+/// expected.push_fs();
+/// expected.ret_near_imm16(frame as u16);
+/// expected.jmp_label(start);
+///
+/// assert_eq!(stream.finish().emit(), expected.finish().emit());
+/// ```
+///
+/// A loop over an array, with memory operands and a conditional jump back to its label:
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_macros::asm_x64;
+/// use asmkit_x86_64::stream::x86_64InstructionStream;
+///
+/// let slot = -8;
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// asm_x64!(stream;
+///     xor eax, eax;
+///     loop_start:;
+///     mov rdx, qword ptr [rdi + rcx*8];
+///     add rax, rdx;
+///     mov [rbp + (slot)], rax;
+///     dec ecx;
+///     jnz loop_start;
+///     jmp short done;
+///     lea rax, [table];
+///     done:;
+///     mov rax, qword ptr [table];
+///     ret;
+///     table:
+/// );
+///
+/// assert_eq!(stream.finish().emit(), [
+///     0x31, 0xC0,                               // xor eax, eax
+///     0x48, 0x8B, 0x14, 0xCF,                   // mov rdx, qword ptr [rdi + rcx*8]
+///     0x48, 0x01, 0xD0,                         // add rax, rdx
+///     0x48, 0x89, 0x45, 0xF8,                   // mov qword ptr [rbp - 0x8], rax
+///     0xFF, 0xC9,                               // dec ecx
+///     0x0F, 0x85, 0xED, 0xFF, 0xFF, 0xFF,       // jnz loop_start
+///     0xEB, 0x07,                               // jmp short done
+///     0x48, 0x8D, 0x05, 0x08, 0x00, 0x00, 0x00, // lea rax, [table]
+///     0x48, 0x8B, 0x05, 0x01, 0x00, 0x00, 0x00, // done: mov rax, qword ptr [table]
+///     0xC3,                                     // ret
+/// ]);
+/// ```
+///
+/// A misspelled register is rejected when compiling:
+/// ```compile_fail
+/// use asmkit_macros::asm_x64;
+/// use asmkit_x86_64::stream::x86_64InstructionStream;
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// asm_x64!(stream; mov rxa, 1);
+/// ```
+///
+/// As is a register of the wrong size for the instruction:
+/// ```compile_fail
+/// use asmkit_macros::asm_x64;
+/// use asmkit_x86_64::stream::x86_64InstructionStream;
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// asm_x64!(stream; mov rax, ebx);
+/// ```
+///
+/// As is a high byte register along with one requiring a REX prefix, which has no encoding:
+/// ```compile_fail
+/// use asmkit_macros::asm_x64;
+/// use asmkit_x86_64::stream::x86_64InstructionStream;
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// asm_x64!(stream; mov ah, sil);
+/// ```
+#[proc_macro]
+pub fn asm_x64(input: TokenStream) -> TokenStream {
+    match expand_x64(input) {
+        Ok(output) => output,
+        Err(error) => error.into_compile_error(),
+    }
+}
+
+/// An error reported at a span of the macro input.
+struct MacroError {
+    span: Span,
+    message: String,
+}
+
+impl MacroError {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into() }
+    }
+
+    /// Expands to a `compile_error!` invocation pointing at the span of the error.
+    fn into_compile_error(self) -> TokenStream {
+        let mut message = Literal::string(&self.message);
+        message.set_span(self.span);
+
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.span);
+
+        let mut arguments = Group::new(Delimiter::Parenthesis, TokenTree::Literal(message).into());
+        arguments.set_span(self.span);
+
+        [TokenTree::Ident(Ident::new("compile_error", self.span)), TokenTree::Punct(bang), TokenTree::Group(arguments)].into_iter().collect()
+    }
+}
+
+/// An operand of an instruction, as written in the macro input.
+enum Argument {
+    /// A register named in the input.
+    Register(Register),
+
+    /// A register spliced from an expression, with its kind.
+    DynamicRegister(OperandKind, TokenStream),
+
+    /// An integer literal.
+    Immediate(i64),
+
+    /// An immediate spliced from an expression.
+    DynamicImmediate(TokenStream),
+
+    /// A label defined in the same invocation.
+    Label(String),
+
+    /// A label defined in the same invocation, reached by a short branch.
+    ShortLabel(String),
+
+    /// A memory operand.
+    Memory(MemoryArgument),
+}
+
+/// A memory operand, as written in the macro input.
+#[derive(Default)]
+struct MemoryArgument {
+    /// The operand, with a displacement of 0 in place of a spliced displacement and a stand-in label.
+    memory: Memory,
+
+    /// The spliced displacement, added to the literal one, and whether it is subtracted.
+    splice: Option<(TokenStream, bool)>,
+
+    /// The label addressed by the operand.
+    label: Option<String>,
+}
+
+impl MemoryArgument {
+    /// Expands to the expression passed to the emitter for a memory operand of kind `kind`: a `Mem`, a `VsibMem` or a `LabelRef`.
+    fn expand(self, kind: &OperandKind) -> TokenStream {
+        if let Some(label) = self.label {
+            return code(&label_variable(&label));
+        }
+
+        let path = |register: Register| format!("::asmkit_x86_64::register::{}", register_path(register));
+        let base = path(Register::Reg64(self.memory.base.expect("memory operands without a label have a base")));
+        let mut displacement = TokenStream::new();
+        if let Some((expression, negative)) = self.splice {
+            let mut splice = Group::new(Delimiter::Parenthesis, expression).into_token_stream();
+            splice.extend(code("as i32"));
+            displacement.extend(code(if negative { "-" } else { "" }));
+            displacement.extend(Group::new(Delimiter::Parenthesis, splice).into_token_stream());
+            displacement.extend(code("+"));
+        }
+        displacement.extend(code(&format!("{}i32", self.memory.displacement)));
+
+        let (constructor, arguments) = match (kind, self.memory.index) {
+            (OperandKind::VsibX | OperandKind::VsibY, Some((index, scale))) => {
+                ("VsibMem::new", format!("{}, {}, ::asmkit_x86_64::memory::Scale::X{},", base, path(index), scale))
+            },
+            (_, Some((index, scale))) => ("Mem::indexed", format!("{}, {}, ::asmkit_x86_64::memory::Scale::X{},", base, path(index), scale)),
+            (_, None) => ("Mem::new", format!("{},", base)),
+        };
+
+        let mut output = code(&format!("::asmkit_x86_64::memory::{}", constructor));
+        let mut arguments = code(&arguments);
+        arguments.extend(displacement);
+        output.extend(Group::new(Delimiter::Parenthesis, arguments).into_token_stream());
+        output
+    }
+}
+
+impl Argument {
+    /// A stand-in operand, used to match the argument against the operand kinds of the instruction table.
+    fn operand(&self) -> Operand {
+        match self {
+            Self::Register(register) => Operand::Register(*register),
+            Self::DynamicRegister(OperandKind::Reg8, _) => Operand::Register(Register::Reg8(Reg8::Al)),
+            Self::DynamicRegister(OperandKind::Reg16, _) => Operand::Register(Register::Reg16(Reg16::Ax)),
+            Self::DynamicRegister(OperandKind::Reg32, _) => Operand::Register(Register::Reg32(Reg32::Eax)),
+            Self::DynamicRegister(_, _) => Operand::Register(Register::Reg64(Reg64::Rax)),
+            Self::Immediate(value) => Operand::Immediate(*value),
+            Self::DynamicImmediate(_) => Operand::Immediate(0),
+            Self::Label(_) => Operand::Label(LabelRef::new(0)),
+            Self::ShortLabel(_) => Operand::ShortLabel(LabelRef::new(0)),
+            Self::Memory(memory) => Operand::Memory(memory.memory),
+        }
+    }
+
+    /// Returns true if the argument is of the operand kind `kind`.
+    fn matches(&self, kind: &OperandKind) -> bool {
+        match self {
+            Self::DynamicImmediate(_) => is_immediate(kind),
+            _ => kind.accepts(&self.operand()),
+        }
+    }
+
+    /// Expands to the expression passed to the emitter for an operand of kind `kind`.
+    fn expand(self, kind: &OperandKind) -> TokenStream {
+        match self {
+            Self::Register(register) => code(&format!("::asmkit_x86_64::register::{}", register_path(register))),
+            Self::DynamicRegister(_, expression) => Group::new(Delimiter::Parenthesis, expression).into_token_stream(),
+            Self::Immediate(value) => code(&format!("({}i64 as {})", value, immediate_type(kind))),
+            Self::DynamicImmediate(expression) => {
+                let mut output = Group::new(Delimiter::Parenthesis, expression).into_token_stream();
+                output.extend(code(&format!("as {}", immediate_type(kind))));
+                Group::new(Delimiter::Parenthesis, output).into_token_stream()
+            },
+            Self::Label(name) | Self::ShortLabel(name) => code(&label_variable(&name)),
+            Self::Memory(memory) => memory.expand(kind),
+        }
+    }
+}
+
+/// A single token tree as a token stream.
+trait IntoTokenStream {
+    fn into_token_stream(self) -> TokenStream;
+}
+
+impl IntoTokenStream for Group {
+    fn into_token_stream(self) -> TokenStream {
+        TokenTree::Group(self).into()
+    }
+}
+
+/// Parses a fragment of the expansion.
+fn code(source: &str) -> TokenStream {
+    source.parse().expect("invalid expansion fragment")
+}
+
+/// Returns true if `kind` is one of the immediate kinds.
+fn is_immediate(kind: &OperandKind) -> bool {
+    use OperandKind::*;
+
+    matches!(kind, Imm8 | Imm16 | Imm32 | Imm64 | SImm8 | SImm32)
+}
+
+/// The type taken by the emitters for an immediate of kind `kind`.
+fn immediate_type(kind: &OperandKind) -> &'static str {
+    match kind {
+        OperandKind::Imm8 => "u8",
+        OperandKind::SImm8 => "i8",
+        OperandKind::Imm16 => "u16",
+        OperandKind::Imm32 => "u32",
+        OperandKind::SImm32 => "i32",
+        _ => "u64",
+    }
+}
+
+/// The path of a register within `asmkit_x86_64::register`, such as `Reg64::Rax`.
+fn register_path(register: Register) -> String {
+    match register {
+        Register::Reg8(register) => format!("Reg8::{:?}", register),
+        Register::Reg16(register) => format!("Reg16::{:?}", register),
+        Register::Reg32(register) => format!("Reg32::{:?}", register),
+        Register::Reg64(register) => format!("Reg64::{:?}", register),
+        Register::Xmm(register) => format!("Xmm::{:?}", register),
+        Register::Ymm(register) => format!("Ymm::{:?}", register),
+        Register::Segment(register) => format!("SegmentReg::{:?}", register),
+    }
+}
+
+/// The name of the variable holding the label `name` in the expansion.
+fn label_variable(name: &str) -> String {
+    format!("__asmkit_label_{}", name)
+}
+
+/// Splits a token stream at each top-level `separator`.
+fn split(input: TokenStream, separator: char) -> Vec<Vec<TokenTree>> {
+    let mut items = vec![Vec::new()];
+    for token in input {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == separator => items.push(Vec::new()),
+            _ => items.last_mut().unwrap().push(token),
+        }
+    }
+
+    items
+}
+
+/// Returns the label defined by an item, if it is a label definition.
+fn label_definition(item: &[TokenTree]) -> Option<&Ident> {
+    match item {
+        [TokenTree::Ident(name), TokenTree::Punct(colon)] if colon.as_char() == ':' => Some(name),
+        _ => None,
+    }
+}
+
+fn expand_x64(input: TokenStream) -> Result<TokenStream, MacroError> {
+    let mut items = split(input, ';').into_iter();
+    let stream = items.next().filter(|stream| !stream.is_empty())
+        .ok_or_else(|| MacroError::new(Span::call_site(), "expected an instruction stream expression"))?;
+    let items: Vec<_> = items.filter(|item| !item.is_empty()).collect();
+
+    let labels: Vec<String> = items.iter().filter_map(|item| label_definition(item)).map(|name| name.to_string()).collect();
+
+    let mut output = code("use ::asmkit_x86_64::__core::InstructionStream as _; let __asmkit_stream = &mut");
+    output.extend(Group::new(Delimiter::Parenthesis, stream.into_iter().collect()).into_token_stream());
+    output.extend(code(";"));
+
+    for label in &labels {
+        output.extend(code(&format!("let {} = __asmkit_stream.create_label();", label_variable(label))));
+    }
+
+    for item in items {
+        if let Some(name) = label_definition(&item) {
+            output.extend(code(&format!("__asmkit_stream.attach_label({});", label_variable(&name.to_string()))));
+        } else {
+            output.extend(expand_instruction(item, &labels)?);
+        }
+    }
+
+    Ok(Group::new(Delimiter::Brace, output).into_token_stream())
+}
+
+/// Expands a single instruction to its emitter call.
+fn expand_instruction(item: Vec<TokenTree>, labels: &[String]) -> Result<TokenStream, MacroError> {
+    let mut tokens = item.into_iter();
+    let mnemonic = match tokens.next() {
+        Some(TokenTree::Ident(mnemonic)) => mnemonic,
+        Some(token) => return Err(MacroError::new(token.span(), "expected a mnemonic")),
+        None => unreachable!("empty items are skipped"),
+    };
+
+    let name = mnemonic.to_string();
+    let forms: Vec<&InstructionForm> = registry::forms(&name).collect();
+    if forms.is_empty() {
+        return Err(MacroError::new(mnemonic.span(), format!("unknown mnemonic `{}`", name)));
+    }
+
+    let mut arguments = Vec::new();
+    let rest: TokenStream = tokens.collect();
+    if !rest.is_empty() {
+        for operand in split(rest, ',') {
+            arguments.push(parse_argument(operand, mnemonic.span(), labels)?);
+        }
+    }
+
+    let mut matching = forms.into_iter()
+        .filter(|form| form.operands.len() == arguments.len() && form.operands.iter().zip(&arguments).all(|(kind, argument)| argument.matches(kind)));
+
+    // a spliced immediate can't be checked against the ranges of the forms, so it takes the widest one, which the table lists last.
+    let form = if arguments.iter().any(|argument| matches!(argument, Argument::DynamicImmediate(_))) {
+        matching.next_back()
+    } else {
+        matching.next()
+    };
+    let form = form.ok_or_else(|| MacroError::new(mnemonic.span(), format!("invalid operands for `{}`", name)))?;
+
+    let operands = arguments.iter().map(Argument::operand).collect::<Vec<_>>();
+    check_operands(&operands).map_err(|error| MacroError::new(mnemonic.span(), error.to_string()))?;
+
+    let mut call_arguments = TokenStream::new();
+    for leading in form.leading {
+        call_arguments.extend(code(&leading.replacen("crate", "::asmkit_x86_64", 1)));
+        call_arguments.extend(code(","));
+    }
+
+    for (argument, kind) in arguments.into_iter().zip(form.operands) {
+        if kind.is_implicit() {
+            continue;
+        }
+
+        call_arguments.extend(argument.expand(kind));
+        call_arguments.extend(code(","));
+    }
+
+    let mut output = code(&format!("__asmkit_stream.{}", form.emitter));
+    output.extend(Group::new(Delimiter::Parenthesis, call_arguments).into_token_stream());
+    output.extend(code(";"));
+    Ok(output)
+}
+
+/// Parses a single operand.
+fn parse_argument(operand: Vec<TokenTree>, mnemonic: Span, labels: &[String]) -> Result<Argument, MacroError> {
+    match operand.as_slice() {
+        [] => Err(MacroError::new(mnemonic, "expected an operand")),
+        [TokenTree::Ident(name)] => {
+            let name = name.to_string();
+            if let Ok(register) = Register::from_str(&name) {
+                Ok(Argument::Register(register))
+            } else if labels.contains(&name) {
+                Ok(Argument::Label(name))
+            } else {
+                Err(MacroError::new(operand[0].span(), format!("unknown register or label `{}`", name)))
+            }
+        },
+        [TokenTree::Ident(splice), TokenTree::Group(expression)] if expression.delimiter() == Delimiter::Parenthesis => {
+            let kind = match splice.to_string().as_str() {
+                "Rb" => OperandKind::Reg8,
+                "Rw" => OperandKind::Reg16,
+                "Rd" => OperandKind::Reg32,
+                "Rq" => OperandKind::Reg64,
+                other => return Err(MacroError::new(splice.span(), format!("unknown register splice `{}`, expected `Rb`, `Rw`, `Rd` or `Rq`", other))),
+            };
+
+            Ok(Argument::DynamicRegister(kind, expression.stream()))
+        },
+        [TokenTree::Group(expression)] if expression.delimiter() == Delimiter::Parenthesis => Ok(Argument::DynamicImmediate(expression.stream())),
+        [TokenTree::Group(memory)] if memory.delimiter() == Delimiter::Bracket => parse_memory(memory, labels).map(Argument::Memory),
+        [TokenTree::Ident(size), TokenTree::Ident(ptr), TokenTree::Group(memory)] if ptr.to_string() == "ptr" && memory.delimiter() == Delimiter::Bracket => {
+            let size = match MEMORY_SIZES.iter().find(|(name, _)| size.to_string() == *name) {
+                Some((_, size)) => *size,
+                None => return Err(MacroError::new(size.span(), format!("unknown operand size `{}`", size))),
+            };
+
+            let mut memory = parse_memory(memory, labels)?;
+            memory.memory.size = Some(size);
+            Ok(Argument::Memory(memory))
+        },
+        [TokenTree::Ident(short), TokenTree::Ident(name)] if short.to_string() == "short" => {
+            let name = name.to_string();
+            if labels.contains(&name) {
+                Ok(Argument::ShortLabel(name))
+            } else {
+                Err(MacroError::new(operand[1].span(), format!("unknown label `{}`", name)))
+            }
+        },
+        [TokenTree::Literal(literal)] => parse_integer(literal, false).map(Argument::Immediate),
+        [TokenTree::Punct(minus), TokenTree::Literal(literal)] if minus.as_char() == '-' => parse_integer(literal, true).map(Argument::Immediate),
+        _ => Err(MacroError::new(operand[0].span(), "expected a register, an immediate, a label or a memory operand")),
+    }
+}
+
+/// Parses the terms of a memory operand, `[base + index*scale + displacement]` or `[label]`.
+fn parse_memory(group: &Group, labels: &[String]) -> Result<MemoryArgument, MacroError> {
+    let mut memory = MemoryArgument::default();
+    let mut displacement = 0i64;
+
+    // split into terms, each carrying its sign.
+    let mut terms = vec![(false, Vec::new())];
+    for token in group.stream() {
+        match &token {
+            TokenTree::Punct(sign) if sign.as_char() == '+' || sign.as_char() == '-' => terms.push((sign.as_char() == '-', Vec::new())),
+            _ => terms.last_mut().unwrap().1.push(token),
+        }
+    }
+
+    for (negative, term) in terms {
+        let (register, scale) = match term.as_slice() {
+            [] => return Err(MacroError::new(group.span(), "expected a register or a displacement")),
+            [TokenTree::Literal(literal)] => {
+                displacement += parse_integer(literal, negative)?;
+                continue;
+            },
+            [TokenTree::Group(expression)] if expression.delimiter() == Delimiter::Parenthesis && memory.splice.is_none() => {
+                memory.splice = Some((expression.stream(), negative));
+                continue;
+            },
+            [TokenTree::Ident(register)] => (register, None),
+            [TokenTree::Ident(register), TokenTree::Punct(star), TokenTree::Literal(scale)]
+            | [TokenTree::Literal(scale), TokenTree::Punct(star), TokenTree::Ident(register)] if star.as_char() == '*' => (register, Some(scale)),
+            _ => return Err(MacroError::new(term[0].span(), "expected a register, a label or a displacement")),
+        };
+
+        let (name, span) = (register.to_string(), register.span());
+        let register = match Register::from_str(&name) {
+            Ok(register @ (Register::Reg64(_) | Register::Xmm(_) | Register::Ymm(_))) => register,
+            Ok(_) => return Err(MacroError::new(span, format!("expected a 64-bit register, found `{}`", name))),
+            Err(_) if labels.contains(&name) && scale.is_none() && memory.label.is_none() && !negative => {
+                memory.label = Some(name);
+                memory.memory.target = Some(MemoryTarget::Label(LabelRef::new(0)));
+                continue;
+            },
+            Err(_) => return Err(MacroError::new(span, format!("unknown register or label `{}`", name))),
+        };
+
+        if negative {
+            return Err(MacroError::new(span, "registers can not be subtracted"));
+        }
+
+        match (register, scale) {
+            (Register::Reg64(base), None) if memory.memory.base.is_none() => memory.memory.base = Some(base),
+            (_, scale) if memory.memory.index.is_none() => {
+                let factor = scale.map_or(Ok(1), |scale| parse_integer(scale, false))?;
+                if ![1, 2, 4, 8].contains(&factor) {
+                    return Err(MacroError::new(scale.unwrap().span(), format!("scale {} is not one of 1, 2, 4 or 8", factor)));
+                }
+
+                if register == Register::Reg64(Reg64::Rsp) {
+                    return Err(MacroError::new(span, "`rsp` can't be used as an index register"));
+                }
+
+                memory.memory.index = Some((register, factor as u8));
+            },
+            _ => return Err(MacroError::new(span, "too many registers in memory operand")),
+        }
+    }
+
+    memory.memory.displacement = i32::try_from(displacement)
+        .map_err(|_| MacroError::new(group.span(), format!("displacement {:#x} doesn't fit in 32 bits", displacement)))?;
+
+    let has_address = memory.memory.base.is_some() || memory.memory.index.is_some() || memory.memory.displacement != 0 || memory.splice.is_some();
+    if memory.label.is_some() && has_address {
+        return Err(MacroError::new(group.span(), "a label can't be addressed with registers or a displacement"));
+    }
+
+    Ok(memory)
+}
+
+/// Parses an unsuffixed decimal, hexadecimal, octal or binary integer literal.
+fn parse_integer(literal: &Literal, negative: bool) -> Result<i64, MacroError> {
+    let text = literal.to_string().replace('_', "");
+    let (digits, radix) = match text.get(..2) {
+        Some("0x") => (&text[2..], 16),
+        Some("0o") => (&text[2..], 8),
+        Some("0b") => (&text[2..], 2),
+        _ => (text.as_str(), 10),
+    };
+
+    // immediates are range checked against the forms, so anything representable in 64 bits is accepted here.
+    let value = u64::from_str_radix(digits, radix).map_err(|_| MacroError::new(literal.span(), format!("invalid immediate `{}`", literal)))?;
+    if negative {
+        0i64.checked_sub_unsigned(value).ok_or_else(|| MacroError::new(literal.span(), format!("immediate `-{}` doesn't fit in 64 bits", literal)))
+    } else {
+        Ok(value as i64)
+    }
+}
@@ -0,0 +1,9 @@
+//! Checks the compile errors of `asm_x64!` against the expected messages in `tests/ui/*.stderr`, and that the operands of `tests/ui/pass`
+//! compile.  Run with `TRYBUILD=overwrite` to update the expected messages after changing them.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/*.rs");
+}
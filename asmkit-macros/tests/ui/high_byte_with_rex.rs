@@ -0,0 +1,7 @@
+use asmkit_macros::asm_x64;
+use asmkit_x86_64::stream::x86_64InstructionStream;
+
+fn main() {
+    let mut stream = x86_64InstructionStream::new();
+    asm_x64!(stream; mov ah, sil);
+}
@@ -0,0 +1,22 @@
+use asmkit_macros::asm_x64;
+use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+
+fn main() {
+    let base = Reg64::Rsi;
+    let offset = 0x40;
+
+    let mut stream = x86_64InstructionStream::new();
+    asm_x64!(stream;
+        top:;
+        mov rax, [rbx];
+        mov rax, qword ptr [r12 + r13*4 - 8];
+        mov dword ptr [rsp + (offset)], 7;
+        mov byte ptr [rbp - (offset) + 1], al;
+        lea Rq(base), [rax + 2*rcx];
+        lea rax, [top];
+        vgatherdps ymm0, [rax + ymm1*4 + 0x10], ymm2;
+        jz top;
+        jne short top;
+        jmp top
+    );
+}
@@ -0,0 +1,205 @@
+//! Instruction formats of the RISC-V base encoding.
+//!
+//! Each `*_type` function packs its fields into a 32-bit instruction word, and each `decode_*_imm` function extracts the (sign-extended) immediate back out of an
+//! instruction word.  The immediate scattering of the S, B and J formats is the error-prone part of the encoding, so the decoders are kept next to the encoders.
+
+/// Encodes an R-type instruction.
+#[inline(always)]
+pub fn r_type(opcode: u32, rd: u32, funct3: u32, rs1: u32, rs2: u32, funct7: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+/// Encodes an R4-type instruction, the format of the fused multiply-add instructions.
+#[inline(always)]
+pub fn r4_type(opcode: u32, rd: u32, funct3: u32, rs1: u32, rs2: u32, funct2: u32, rs3: u32) -> u32 {
+    (rs3 << 27) | (funct2 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+/// Encodes an I-type instruction.  Only the low 12 bits of `imm` are used.
+#[inline(always)]
+pub fn i_type(opcode: u32, rd: u32, funct3: u32, rs1: u32, imm: i32) -> u32 {
+    ((imm as u32 & 0xfff) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+/// Encodes an S-type instruction.  Only the low 12 bits of `imm` are used.
+#[inline(always)]
+pub fn s_type(opcode: u32, funct3: u32, rs1: u32, rs2: u32, imm: i32) -> u32 {
+    let imm = imm as u32;
+    (((imm >> 5) & 0x7f) << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | ((imm & 0x1f) << 7) | opcode
+}
+
+/// Encodes a B-type instruction.  Only bits 1 to 12 of `imm` are used, bit 0 is implicitly zero.
+#[inline(always)]
+pub fn b_type(opcode: u32, funct3: u32, rs1: u32, rs2: u32, imm: i32) -> u32 {
+    b_imm(imm) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | opcode
+}
+
+/// Encodes a U-type instruction.  `imm` is the upper 20-bit immediate, as written in assembly.
+#[inline(always)]
+pub fn u_type(opcode: u32, rd: u32, imm: u32) -> u32 {
+    ((imm & 0xfffff) << 12) | (rd << 7) | opcode
+}
+
+/// Encodes a J-type instruction.  Only bits 1 to 20 of `imm` are used, bit 0 is implicitly zero.
+#[inline(always)]
+pub fn j_type(opcode: u32, rd: u32, imm: i32) -> u32 {
+    j_imm(imm) | (rd << 7) | opcode
+}
+
+/// Scatters a branch offset into the immediate bits of a B-type instruction.
+#[inline(always)]
+pub fn b_imm(imm: i32) -> u32 {
+    let imm = imm as u32;
+    (((imm >> 12) & 0x1) << 31) | (((imm >> 5) & 0x3f) << 25) | (((imm >> 1) & 0xf) << 8) | (((imm >> 11) & 0x1) << 7)
+}
+
+/// Scatters a jump offset into the immediate bits of a J-type instruction.
+#[inline(always)]
+pub fn j_imm(imm: i32) -> u32 {
+    let imm = imm as u32;
+    (((imm >> 20) & 0x1) << 31) | (((imm >> 1) & 0x3ff) << 21) | (((imm >> 11) & 0x1) << 20) | (((imm >> 12) & 0xff) << 12)
+}
+
+/// Extracts the sign-extended immediate of an I-type instruction.
+#[inline(always)]
+pub fn decode_i_imm(instruction: u32) -> i32 {
+    (instruction as i32) >> 20
+}
+
+/// Extracts the sign-extended immediate of an S-type instruction.
+///
+/// # Example
+/// ```
+/// use asmkit_riscv::encoding::{decode_s_imm, s_type};
+///
+/// for imm in -2048..2048 {
+///     let instruction = s_type(0x23, 0b011, 2, 10, imm);
+///
+///     assert_eq!(decode_s_imm(instruction), imm);
+///     assert_eq!(instruction & 0x01fff07f, (10 << 20) | (2 << 15) | (0b011 << 12) | 0x23);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_s_imm(instruction: u32) -> i32 {
+    (((instruction & 0xfe000000) as i32) >> 20) | ((instruction >> 7) & 0x1f) as i32
+}
+
+/// Extracts the sign-extended immediate of a B-type instruction.
+///
+/// # Example
+/// ```
+/// use asmkit_riscv::encoding::{b_type, decode_b_imm};
+///
+/// // every representable branch offset survives a round trip, without disturbing the other fields.
+/// for imm in (-4096..4096).step_by(2) {
+///     let instruction = b_type(0x63, 0b101, 31, 17, imm);
+///
+///     assert_eq!(decode_b_imm(instruction), imm);
+///     assert_eq!(instruction & 0x01fff07f, (17 << 20) | (31 << 15) | (0b101 << 12) | 0x63);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_b_imm(instruction: u32) -> i32 {
+    ((((instruction & 0x80000000) as i32) >> 19) as u32
+        | ((instruction >> 20) & 0x7e0)
+        | ((instruction >> 7) & 0x1e)
+        | ((instruction << 4) & 0x800)) as i32
+}
+
+/// Extracts the sign-extended immediate of a J-type instruction.
+///
+/// # Example
+/// ```
+/// use asmkit_riscv::encoding::{decode_j_imm, j_type};
+///
+/// // every representable jump offset survives a round trip, without disturbing the other fields.
+/// for imm in (-(1 << 20)..(1 << 20)).step_by(2) {
+///     let instruction = j_type(0x6f, 5, imm);
+///
+///     assert_eq!(decode_j_imm(instruction), imm);
+///     assert_eq!(instruction & 0xfff, (5 << 7) | 0x6f);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_j_imm(instruction: u32) -> i32 {
+    ((((instruction & 0x80000000) as i32) >> 11) as u32
+        | (instruction & 0xff000)
+        | ((instruction >> 9) & 0x800)
+        | ((instruction >> 20) & 0x7fe)) as i32
+}
+
+/// Scatters a branch offset into the immediate bits of a CB-format compressed branch (`c.beqz` and `c.bnez`).
+#[inline(always)]
+pub fn cb_imm(imm: i32) -> u16 {
+    let imm = imm as u32;
+    ((((imm >> 8) & 0x1) << 12) | (((imm >> 3) & 0x3) << 10) | (((imm >> 6) & 0x3) << 5) | (((imm >> 1) & 0x3) << 3) | (((imm >> 5) & 0x1) << 2)) as u16
+}
+
+/// Scatters a jump offset into the immediate bits of a CJ-format compressed jump (`c.j`).
+#[inline(always)]
+pub fn cj_imm(imm: i32) -> u16 {
+    let imm = imm as u32;
+    ((((imm >> 11) & 0x1) << 12)
+        | (((imm >> 4) & 0x1) << 11)
+        | (((imm >> 8) & 0x3) << 9)
+        | (((imm >> 10) & 0x1) << 8)
+        | (((imm >> 6) & 0x1) << 7)
+        | (((imm >> 7) & 0x1) << 6)
+        | (((imm >> 1) & 0x7) << 3)
+        | (((imm >> 5) & 0x1) << 2)) as u16
+}
+
+/// Extracts the sign-extended immediate of a CB-format compressed branch.
+///
+/// # Example
+/// ```
+/// use asmkit_riscv::encoding::{cb_imm, decode_cb_imm};
+///
+/// for imm in (-256..256).step_by(2) {
+///     assert_eq!(decode_cb_imm(cb_imm(imm)), imm);
+///     assert_eq!(cb_imm(imm) & 0xe383, 0);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_cb_imm(instruction: u16) -> i32 {
+    let instruction = instruction as u32;
+    let imm = (((instruction >> 12) & 0x1) << 8)
+        | (((instruction >> 10) & 0x3) << 3)
+        | (((instruction >> 5) & 0x3) << 6)
+        | (((instruction >> 3) & 0x3) << 1)
+        | (((instruction >> 2) & 0x1) << 5);
+    ((imm << 23) as i32) >> 23
+}
+
+/// Extracts the sign-extended immediate of a CJ-format compressed jump.
+///
+/// # Example
+/// ```
+/// use asmkit_riscv::encoding::{cj_imm, decode_cj_imm};
+///
+/// for imm in (-2048..2048).step_by(2) {
+///     assert_eq!(decode_cj_imm(cj_imm(imm)), imm);
+///     assert_eq!(cj_imm(imm) & 0xe003, 0);
+/// }
+/// ```
+#[inline(always)]
+pub fn decode_cj_imm(instruction: u16) -> i32 {
+    let instruction = instruction as u32;
+    let imm = (((instruction >> 12) & 0x1) << 11)
+        | (((instruction >> 11) & 0x1) << 4)
+        | (((instruction >> 9) & 0x3) << 8)
+        | (((instruction >> 8) & 0x1) << 10)
+        | (((instruction >> 7) & 0x1) << 6)
+        | (((instruction >> 6) & 0x1) << 7)
+        | (((instruction >> 3) & 0x7) << 1)
+        | (((instruction >> 2) & 0x1) << 5);
+    ((imm << 20) as i32) >> 20
+}
+
+/// Returns true if `imm` fits in a signed immediate of `bits` bits.
+#[inline(always)]
+pub fn fits_signed(imm: i64, bits: u32) -> bool {
+    let min = -(1i64 << (bits - 1));
+    let max = (1i64 << (bits - 1)) - 1;
+    imm >= min && imm <= max
+}
@@ -0,0 +1,315 @@
+//! Registers in RISC-V assembly.
+
+/// A general purpose integer register.
+///
+/// The registers are named by their number; the ABI names are available through constructors such as [`XReg::a0`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XReg {
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30,
+    X31,
+}
+
+impl XReg {
+    /// The offset of this register, for instruction encoding.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_riscv::register::XReg;
+    ///
+    /// assert_eq!(XReg::a0().offset(), 10);
+    /// assert_eq!(XReg::X31.offset(), 31);
+    /// ```
+    pub fn offset(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Returns true if this register is one of `x8` to `x15`, the only registers addressable by the 3-bit register fields of the compressed instructions.
+    pub fn is_compressible(&self) -> bool {
+        (8..16).contains(&self.offset())
+    }
+
+    /// The offset of this register within the compressed register window, for instruction encoding.  Only meaningful if [`XReg::is_compressible`].
+    pub fn compressed_offset(&self) -> u32 {
+        self.offset() & 0b111
+    }
+
+    /// Hard-wired zero.
+    pub const fn zero() -> Self { Self::X0 }
+
+    /// Return address.
+    pub const fn ra() -> Self { Self::X1 }
+
+    /// Stack pointer.
+    pub const fn sp() -> Self { Self::X2 }
+
+    /// Global pointer.
+    pub const fn gp() -> Self { Self::X3 }
+
+    /// Thread pointer.
+    pub const fn tp() -> Self { Self::X4 }
+
+    /// Temporary register 0, the alternate link register.
+    pub const fn t0() -> Self { Self::X5 }
+
+    /// Temporary register 1.
+    pub const fn t1() -> Self { Self::X6 }
+
+    /// Temporary register 2.
+    pub const fn t2() -> Self { Self::X7 }
+
+    /// Saved register 0.
+    pub const fn s0() -> Self { Self::X8 }
+
+    /// Frame pointer, an alias of [`XReg::s0`].
+    pub const fn fp() -> Self { Self::X8 }
+
+    /// Saved register 1.
+    pub const fn s1() -> Self { Self::X9 }
+
+    /// Function argument 0, and return value 0.
+    pub const fn a0() -> Self { Self::X10 }
+
+    /// Function argument 1, and return value 1.
+    pub const fn a1() -> Self { Self::X11 }
+
+    /// Function argument 2.
+    pub const fn a2() -> Self { Self::X12 }
+
+    /// Function argument 3.
+    pub const fn a3() -> Self { Self::X13 }
+
+    /// Function argument 4.
+    pub const fn a4() -> Self { Self::X14 }
+
+    /// Function argument 5.
+    pub const fn a5() -> Self { Self::X15 }
+
+    /// Function argument 6.
+    pub const fn a6() -> Self { Self::X16 }
+
+    /// Function argument 7.
+    pub const fn a7() -> Self { Self::X17 }
+
+    /// Saved register 2.
+    pub const fn s2() -> Self { Self::X18 }
+
+    /// Saved register 3.
+    pub const fn s3() -> Self { Self::X19 }
+
+    /// Saved register 4.
+    pub const fn s4() -> Self { Self::X20 }
+
+    /// Saved register 5.
+    pub const fn s5() -> Self { Self::X21 }
+
+    /// Saved register 6.
+    pub const fn s6() -> Self { Self::X22 }
+
+    /// Saved register 7.
+    pub const fn s7() -> Self { Self::X23 }
+
+    /// Saved register 8.
+    pub const fn s8() -> Self { Self::X24 }
+
+    /// Saved register 9.
+    pub const fn s9() -> Self { Self::X25 }
+
+    /// Saved register 10.
+    pub const fn s10() -> Self { Self::X26 }
+
+    /// Saved register 11.
+    pub const fn s11() -> Self { Self::X27 }
+
+    /// Temporary register 3.
+    pub const fn t3() -> Self { Self::X28 }
+
+    /// Temporary register 4.
+    pub const fn t4() -> Self { Self::X29 }
+
+    /// Temporary register 5.
+    pub const fn t5() -> Self { Self::X30 }
+
+    /// Temporary register 6.
+    pub const fn t6() -> Self { Self::X31 }
+}
+
+/// A floating-point register, from the F and D extensions.
+///
+/// The registers are named by their number; the ABI names are available through constructors such as [`FReg::fa0`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FReg {
+    F0,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    F25,
+    F26,
+    F27,
+    F28,
+    F29,
+    F30,
+    F31,
+}
+
+impl FReg {
+    /// The offset of this register, for instruction encoding.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_riscv::register::FReg;
+    ///
+    /// assert_eq!(FReg::fa0().offset(), 10);
+    /// assert_eq!(FReg::fs2().offset(), 18);
+    /// assert_eq!(FReg::ft11().offset(), 31);
+    /// ```
+    pub fn offset(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Floating-point temporary register 0.
+    pub const fn ft0() -> Self { Self::F0 }
+
+    /// Floating-point temporary register 1.
+    pub const fn ft1() -> Self { Self::F1 }
+
+    /// Floating-point temporary register 2.
+    pub const fn ft2() -> Self { Self::F2 }
+
+    /// Floating-point temporary register 3.
+    pub const fn ft3() -> Self { Self::F3 }
+
+    /// Floating-point temporary register 4.
+    pub const fn ft4() -> Self { Self::F4 }
+
+    /// Floating-point temporary register 5.
+    pub const fn ft5() -> Self { Self::F5 }
+
+    /// Floating-point temporary register 6.
+    pub const fn ft6() -> Self { Self::F6 }
+
+    /// Floating-point temporary register 7.
+    pub const fn ft7() -> Self { Self::F7 }
+
+    /// Floating-point saved register 0.
+    pub const fn fs0() -> Self { Self::F8 }
+
+    /// Floating-point saved register 1.
+    pub const fn fs1() -> Self { Self::F9 }
+
+    /// Floating-point function argument 0, and return value 0.
+    pub const fn fa0() -> Self { Self::F10 }
+
+    /// Floating-point function argument 1, and return value 1.
+    pub const fn fa1() -> Self { Self::F11 }
+
+    /// Floating-point function argument 2.
+    pub const fn fa2() -> Self { Self::F12 }
+
+    /// Floating-point function argument 3.
+    pub const fn fa3() -> Self { Self::F13 }
+
+    /// Floating-point function argument 4.
+    pub const fn fa4() -> Self { Self::F14 }
+
+    /// Floating-point function argument 5.
+    pub const fn fa5() -> Self { Self::F15 }
+
+    /// Floating-point function argument 6.
+    pub const fn fa6() -> Self { Self::F16 }
+
+    /// Floating-point function argument 7.
+    pub const fn fa7() -> Self { Self::F17 }
+
+    /// Floating-point saved register 2.
+    pub const fn fs2() -> Self { Self::F18 }
+
+    /// Floating-point saved register 3.
+    pub const fn fs3() -> Self { Self::F19 }
+
+    /// Floating-point saved register 4.
+    pub const fn fs4() -> Self { Self::F20 }
+
+    /// Floating-point saved register 5.
+    pub const fn fs5() -> Self { Self::F21 }
+
+    /// Floating-point saved register 6.
+    pub const fn fs6() -> Self { Self::F22 }
+
+    /// Floating-point saved register 7.
+    pub const fn fs7() -> Self { Self::F23 }
+
+    /// Floating-point saved register 8.
+    pub const fn fs8() -> Self { Self::F24 }
+
+    /// Floating-point saved register 9.
+    pub const fn fs9() -> Self { Self::F25 }
+
+    /// Floating-point saved register 10.
+    pub const fn fs10() -> Self { Self::F26 }
+
+    /// Floating-point saved register 11.
+    pub const fn fs11() -> Self { Self::F27 }
+
+    /// Floating-point temporary register 8.
+    pub const fn ft8() -> Self { Self::F28 }
+
+    /// Floating-point temporary register 9.
+    pub const fn ft9() -> Self { Self::F29 }
+
+    /// Floating-point temporary register 10.
+    pub const fn ft10() -> Self { Self::F30 }
+
+    /// Floating-point temporary register 11.
+    pub const fn ft11() -> Self { Self::F31 }
+}
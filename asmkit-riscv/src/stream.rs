@@ -0,0 +1,1872 @@
+//! Instruction streaming implementation.
+
+use asmkit_core::{
+    displacement::{DisplacementBias, DisplacementForm, DisplacementUnit},
+    entity::{EntityList, Label, LabelRef},
+    labels::LabelTable,
+    raw::Endianness,
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+use crate::{encoding::{b_imm, b_type, cb_imm, cj_imm, fits_signed, i_type, j_imm, j_type, r4_type, r_type, s_type, u_type}, register::{FReg, XReg}};
+
+pub const OPCODE_LUI: u32 = 0b0110111;
+pub const OPCODE_AUIPC: u32 = 0b0010111;
+pub const OPCODE_JAL: u32 = 0b1101111;
+pub const OPCODE_JALR: u32 = 0b1100111;
+pub const OPCODE_BRANCH: u32 = 0b1100011;
+pub const OPCODE_LOAD: u32 = 0b0000011;
+pub const OPCODE_STORE: u32 = 0b0100011;
+pub const OPCODE_OP_IMM: u32 = 0b0010011;
+pub const OPCODE_OP: u32 = 0b0110011;
+pub const OPCODE_OP_IMM_32: u32 = 0b0011011;
+pub const OPCODE_OP_32: u32 = 0b0111011;
+pub const OPCODE_SYSTEM: u32 = 0b1110011;
+pub const OPCODE_AMO: u32 = 0b0101111;
+pub const OPCODE_LOAD_FP: u32 = 0b0000111;
+pub const OPCODE_STORE_FP: u32 = 0b0100111;
+pub const OPCODE_OP_FP: u32 = 0b1010011;
+pub const OPCODE_MADD: u32 = 0b1000011;
+pub const OPCODE_MSUB: u32 = 0b1000111;
+pub const OPCODE_NMSUB: u32 = 0b1001011;
+pub const OPCODE_NMADD: u32 = 0b1001111;
+
+/// The kind of a label fixup, deciding how the displacement is scattered into the instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixupKind {
+    /// A 13-bit B-type branch displacement.
+    Branch,
+
+    /// A 21-bit J-type jump displacement.
+    Jump,
+
+    /// A 9-bit CB-format compressed branch displacement.
+    CompressedBranch,
+
+    /// A 12-bit CJ-format compressed jump displacement.
+    CompressedJump,
+
+    /// A 32-bit displacement split into the high 20 bits of an `auipc` and the low 12 bits of the I-type instruction which follows it.
+    PcRel,
+}
+
+impl FixupKind {
+    /// How the displacement of this kind is measured: from the start of the instruction, in halfwords for the branches and the jumps, and in
+    /// bytes for the `auipc` pairs.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_riscv::stream::FixupKind;
+    ///
+    /// assert_eq!(FixupKind::Branch.form().resolve(8, 0), Ok(-4));
+    /// assert!(FixupKind::Branch.form().resolve(0, 4096).is_err());
+    /// assert!(FixupKind::Jump.form().resolve(0, 3).is_err());
+    /// assert_eq!(FixupKind::PcRel.form().resolve(0, 3), Ok(3));
+    /// ```
+    pub fn form(&self) -> DisplacementForm {
+        match self {
+            Self::Branch => DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::Start, 12),
+            Self::Jump => DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::Start, 20),
+            Self::CompressedBranch => DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::Start, 8),
+            Self::CompressedJump => DisplacementForm::new(DisplacementUnit::Halfwords, DisplacementBias::Start, 11),
+            Self::PcRel => DisplacementForm::new(DisplacementUnit::Bytes, DisplacementBias::Start, 32),
+        }
+    }
+}
+
+/// The width of an atomic memory operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Width {
+    /// A 32-bit access, the `.w` suffixed instructions.
+    Word,
+
+    /// A 64-bit access, the `.d` suffixed instructions.
+    Doubleword,
+}
+
+impl Width {
+    /// The `funct3` field of this width, for instruction encoding.
+    pub fn funct3(&self) -> u32 {
+        match self {
+            Self::Word => 0b010,
+            Self::Doubleword => 0b011,
+        }
+    }
+}
+
+/// The memory ordering of an atomic memory operation, encoded in its `aq` and `rl` bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ordering {
+    /// Neither bit set.
+    Relaxed,
+
+    /// The `aq` bit set, the `.aq` suffixed form of the instruction.
+    Acquire,
+
+    /// The `rl` bit set, the `.rl` suffixed form of the instruction.
+    Release,
+
+    /// Both bits set, the `.aqrl` suffixed form of the instruction.
+    AcqRel,
+}
+
+impl Ordering {
+    /// The `aq` and `rl` bits of this ordering, in place.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::Relaxed => 0,
+            Self::Acquire => 1 << 26,
+            Self::Release => 1 << 25,
+            Self::AcqRel => (1 << 26) | (1 << 25),
+        }
+    }
+}
+
+/// The precision of a floating-point operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    /// Single precision, the `.s` suffixed instructions of the F extension.
+    Single,
+
+    /// Double precision, the `.d` suffixed instructions of the D extension.
+    Double,
+}
+
+impl Precision {
+    /// The `fmt` field of this precision, for instruction encoding.
+    pub fn fmt(&self) -> u32 {
+        match self {
+            Self::Single => 0b00,
+            Self::Double => 0b01,
+        }
+    }
+}
+
+/// The rounding mode of a floating-point operation, encoded in its `rm` field.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even.
+    Rne,
+
+    /// Round towards zero.
+    Rtz,
+
+    /// Round down, towards negative infinity.
+    Rdn,
+
+    /// Round up, towards positive infinity.
+    Rup,
+
+    /// Round to nearest, ties to max magnitude.
+    Rmm,
+
+    /// Use the rounding mode held in the `frm` field of `fcsr`.
+    #[default]
+    Dyn,
+}
+
+impl RoundingMode {
+    /// The `rm` field of this rounding mode, for instruction encoding.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::Rne => 0b000,
+            Self::Rtz => 0b001,
+            Self::Rdn => 0b010,
+            Self::Rup => 0b011,
+            Self::Rmm => 0b100,
+            Self::Dyn => 0b111,
+        }
+    }
+}
+
+/// The integer side of a conversion between an integer and a floating-point register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntFormat {
+    /// A signed 32-bit integer, the `.w` conversions.
+    W,
+
+    /// An unsigned 32-bit integer, the `.wu` conversions.
+    Wu,
+
+    /// A signed 64-bit integer, the `.l` conversions.
+    L,
+
+    /// An unsigned 64-bit integer, the `.lu` conversions.
+    Lu,
+}
+
+impl IntFormat {
+    /// The `rs2` field selecting this format in the `fcvt` instructions.
+    pub fn selector(&self) -> u32 {
+        match self {
+            Self::W => 0b00000,
+            Self::Wu => 0b00001,
+            Self::L => 0b00010,
+            Self::Lu => 0b00011,
+        }
+    }
+}
+
+/// An instruction of a constant materialization sequence, see [`Riscv64InstructionStream::li`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LoadImmediate {
+    Lui(u32),
+    Addi(i32),
+    Addiw(i32),
+    Slli(u32),
+    Srli(u32),
+}
+
+/// Computes the instruction sequence materializing `value`, following the same strategy as LLVM: a `lui`/`addi(w)` pair for 32-bit values, and a recursive
+/// sequence of shifts and additions for the upper bits of larger values.
+fn load_immediate_sequence(value: i64, sequence: &mut Vec<LoadImmediate>) {
+    if fits_signed(value, 32) {
+        let hi20 = ((value + 0x800) >> 12) & 0xfffff;
+        let lo12 = (value << 52) >> 52;
+
+        if hi20 != 0 {
+            sequence.push(LoadImmediate::Lui(hi20 as u32));
+        }
+
+        if lo12 != 0 || hi20 == 0 {
+            sequence.push(if hi20 != 0 { LoadImmediate::Addiw(lo12 as i32) } else { LoadImmediate::Addi(lo12 as i32) });
+        }
+
+        return;
+    }
+
+    let lo12 = (value << 52) >> 52;
+    let hi52 = (value as u64).wrapping_add(0x800) >> 12;
+    let mut shift = 12 + hi52.trailing_zeros();
+    let mut hi52 = (((hi52 >> (shift - 12)) << shift) as i64) >> shift;
+
+    // use a `lui` for the upper bits when they don't fit an `addi` but would fit a `lui`.
+    if shift > 12 && !fits_signed(hi52, 12) && fits_signed(hi52 << 12, 32) {
+        shift -= 12;
+        hi52 <<= 12;
+    }
+
+    load_immediate_sequence(hi52, sequence);
+    sequence.push(LoadImmediate::Slli(shift));
+
+    if lo12 != 0 {
+        sequence.push(LoadImmediate::Addi(lo12 as i32));
+    }
+}
+
+/// A use of a label, patched once the label is attached, or once the instruction stream is finished if the label was attached first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fixup {
+    /// The offset of the instruction which uses the label.
+    pub offset: usize,
+
+    /// The label used by the instruction.
+    pub label: LabelRef,
+
+    /// How the displacement is encoded into the instruction.
+    pub kind: FixupKind,
+}
+
+/// Encodes a CI-format compressed instruction.
+fn ci_type(funct3: u16, rd: XReg, imm: i32) -> u16 {
+    (funct3 << 13) | ((((imm >> 5) & 0x1) as u16) << 12) | ((rd.offset() as u16) << 7) | (((imm & 0x1f) as u16) << 2) | 0b01
+}
+
+/// Encodes `c.addi`, if the operands qualify.
+fn encode_c_addi(rd: XReg, imm: i32) -> Option<u16> {
+    (rd != XReg::zero() && imm != 0 && fits_signed(imm as i64, 6)).then(|| ci_type(0b000, rd, imm))
+}
+
+/// Encodes `c.li`, if the operands qualify.
+fn encode_c_li(rd: XReg, imm: i32) -> Option<u16> {
+    (rd != XReg::zero() && fits_signed(imm as i64, 6)).then(|| ci_type(0b010, rd, imm))
+}
+
+/// Encodes `c.lui`, if the operands qualify.
+fn encode_c_lui(rd: XReg, imm20: u32) -> Option<u16> {
+    let qualifies = rd != XReg::zero() && rd != XReg::sp() && imm20 != 0 && (imm20 < 0x20 || (0xfffe0..0x100000).contains(&imm20));
+    qualifies.then(|| ci_type(0b011, rd, imm20 as i32))
+}
+
+/// Encodes `c.addi16sp`, if the operands qualify.
+fn encode_c_addi16sp(imm: i32) -> Option<u16> {
+    let qualifies = imm != 0 && imm % 16 == 0 && fits_signed(imm as i64, 10);
+    let imm = imm as u16;
+    qualifies.then_some(0x6101 | (((imm >> 9) & 0x1) << 12) | (((imm >> 4) & 0x1) << 6) | (((imm >> 6) & 0x1) << 5) | (((imm >> 7) & 0x3) << 3) | (((imm >> 5) & 0x1) << 2))
+}
+
+/// Encodes `c.addi4spn`, if the operands qualify.
+fn encode_c_addi4spn(rd: XReg, imm: i32) -> Option<u16> {
+    let qualifies = rd.is_compressible() && imm > 0 && imm % 4 == 0 && imm < 1024;
+    let imm = imm as u16;
+    qualifies.then(|| {
+        (((imm >> 4) & 0x3) << 11) | (((imm >> 6) & 0xf) << 7) | (((imm >> 2) & 0x1) << 6) | (((imm >> 3) & 0x1) << 5) | ((rd.compressed_offset() as u16) << 2)
+    })
+}
+
+/// Encodes one of the CR-format register instructions (`c.mv`, `c.add`, `c.jr` and `c.jalr`), if the operands qualify.
+fn encode_cr(funct4: u16, rd: XReg, rs2: XReg) -> Option<u16> {
+    (rd != XReg::zero()).then(|| (funct4 << 12) | ((rd.offset() as u16) << 7) | ((rs2.offset() as u16) << 2) | 0b10)
+}
+
+/// Encodes one of the CL/CS-format memory accesses, `scale` being the access size in bytes, if the operands qualify.
+fn encode_c_mem(funct3: u16, scale: i32, reg: XReg, base: XReg, offset: i32) -> Option<u16> {
+    let qualifies = reg.is_compressible() && base.is_compressible() && offset >= 0 && offset % scale == 0 && offset < scale * 32;
+    let imm = offset as u16;
+    let low = if scale == 4 { (((imm >> 2) & 0x1) << 6) | (((imm >> 6) & 0x1) << 5) } else { ((imm >> 6) & 0x3) << 5 };
+    qualifies.then(|| (funct3 << 13) | (((imm >> 3) & 0x7) << 10) | ((base.compressed_offset() as u16) << 7) | low | ((reg.compressed_offset() as u16) << 2))
+}
+
+/// An instruction stream for the 64-bit RISC-V target.
+pub struct Riscv64InstructionStream {
+    /// The bytes written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream, and their pending uses.
+    labels: LabelTable<Fixup>,
+
+    /// Whether the instruction emitters may pick compressed encodings, see [`Riscv64InstructionStream::enable_compression`].
+    compress: bool,
+
+    /// The holes of the templates written to the instruction stream.
+    holes: TemplateHoles,
+}
+
+impl InstructionStream for Riscv64InstructionStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.create()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.create_attached(self.bytes.len())
+    }
+
+    /// Attaches `label` to the current offset, patching the instructions which used it before.
+    ///
+    /// # Panics
+    /// Panics if the displacement from one of these instructions doesn't fit it.
+    fn attach_label(&mut self, label: LabelRef) {
+        let mut pending = self.labels.attach(label, self.bytes.len());
+        while let Some(fixup) = pending.next(&self.labels) {
+            self.resolve(fixup);
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, filled when the stream is finished.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol, which the stream has none of.
+    ///
+    /// # Example
+    /// A jump over a table whose entry holds the offset of a label from its end:
+    /// ```
+    /// use asmkit_core::{template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// let over = stream.create_label();
+    /// stream.jal_label(XReg::X0, over);
+    /// stream.emit_template(&[0; 4], &[TemplateFixup::rel32(0, TemplateTarget::Label(exit))]);
+    /// stream.attach_label(over);
+    /// stream.addi(XReg::X10, XReg::X0, 1);
+    /// stream.attach_label(exit);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x6f, 0x00, 0x80, 0x00, // j over
+    ///     0x04, 0x00, 0x00, 0x00, // exit - 0x8
+    ///     0x13, 0x05, 0x10, 0x00, // over: li a0, 1
+    /// ]);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use asmkit_core::{template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    /// use asmkit_riscv::stream::Riscv64InstructionStream;
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// stream.emit_template(&[0; 4], &[TemplateFixup::rel32(0, TemplateTarget::Label(exit))]);
+    /// let _ = stream.finish();
+    /// ```
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push_label(fixup);
+    }
+
+    /// Finishes writing to the instruction stream, resolving the label uses recorded after their label was attached, and returns the produced
+    /// bytes.
+    ///
+    /// # Panics
+    /// Panics if a used label was never attached, or if its displacement doesn't fit the instruction which uses it.
+    fn finish(mut self) -> Product {
+        for label in self.labels.refs() {
+            let mut pending = self.labels.take_pending(label);
+            while let Some(fixup) = pending.next(&self.labels) {
+                self.resolve(fixup);
+            }
+        }
+
+        let relocations = self.holes.resolve(&mut self.bytes, Endianness::Little, |label| self.labels.offset(label)).unwrap_or_else(|error| panic!("{}", error));
+        Product::with_relocations(self.bytes, relocations, EntityList::new())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
+
+impl Default for Riscv64InstructionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Riscv64InstructionStream {
+    /// Creates a new, empty instruction stream.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), labels: LabelTable::new(), compress: false, holes: TemplateHoles::new() }
+    }
+
+    /// Writes a single 32-bit instruction into the instruction stream.
+    #[inline(always)]
+    pub fn write_instruction(&mut self, instruction: u32) {
+        self.write_double_word(instruction);
+    }
+
+    /// Writes a single 16-bit compressed instruction into the instruction stream.
+    #[inline(always)]
+    pub fn write_compressed_instruction(&mut self, instruction: u16) {
+        self.write_word(instruction);
+    }
+
+    /// Allows the instruction emitters to transparently pick the 16-bit encodings of the C extension whenever their operands qualify.  Disabled by default.
+    ///
+    /// Label uses are only compressed when the label is already attached and within range of the compressed form, so forward branches keep their 32-bit
+    /// encoding.  The multi-instruction sequences of [`Riscv64InstructionStream::la_label`] and [`Riscv64InstructionStream::call_label`] are never compressed.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let sum = |compress: bool| {
+    ///     let mut stream = Riscv64InstructionStream::new();
+    ///     stream.enable_compression(compress);
+    ///
+    ///     stream.li(XReg::a0(), 0);
+    ///     let top = stream.create_label_attached();
+    ///     stream.add(XReg::a0(), XReg::a0(), XReg::a1());
+    ///     stream.addi(XReg::a1(), XReg::a1(), -1);
+    ///     stream.bne_label(XReg::a1(), XReg::zero(), top);
+    ///     stream.ret();
+    ///
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// // li a0, 0; add a0, a0, a1; addi a1, a1, -1; bnez a1, -4; ret
+    /// assert_eq!(sum(true), [0x01, 0x45, 0x2e, 0x95, 0xfd, 0x15, 0xf5, 0xfd, 0x82, 0x80]);
+    /// assert_eq!(sum(false).len(), 20);
+    /// ```
+    pub fn enable_compression(&mut self, enabled: bool) {
+        self.compress = enabled;
+    }
+
+    /// Returns the offset of `label`, if it is attached.
+    fn label_offset(&self, label: LabelRef) -> Option<usize> {
+        self.labels.offset(label)
+    }
+
+    /// Patches the displacement of `fixup` to its attached label into the instruction using it.
+    ///
+    /// # Panics
+    /// Panics if the label isn't attached, or if its displacement doesn't fit the instruction.
+    fn resolve(&mut self, fixup: Fixup) {
+        let target = match self.labels.get(fixup.label).state {
+            Label::Attached(target) => target,
+            Label::Unattached => panic!("label {:?} is used but never attached", fixup.label),
+            Label::Absolute(_) => unreachable!("labels are only bound to absolute addresses by the x86-64 stream"),
+        };
+
+        // the encoders take the displacement in bytes.
+        let form = fixup.kind.form();
+        let value = form.resolve(fixup.offset, target).unwrap_or_else(|error| panic!("{} to label {:?}", error, fixup.label));
+        let displacement = value * form.unit.size() as i64;
+
+        // the low 12 bits of a `PcRel` are sign extended, rounding its upper 20 bits up.
+        let in_range = fixup.kind != FixupKind::PcRel || fits_signed(displacement + 0x800, 32);
+        assert!(in_range, "displacement {} to label {:?} is out of range", displacement, fixup.label);
+
+        match fixup.kind {
+            FixupKind::Branch => self.patch_instruction(fixup.offset, b_imm(displacement as i32)),
+            FixupKind::Jump => self.patch_instruction(fixup.offset, j_imm(displacement as i32)),
+            FixupKind::CompressedBranch => self.patch_compressed_instruction(fixup.offset, cb_imm(displacement as i32)),
+            FixupKind::CompressedJump => self.patch_compressed_instruction(fixup.offset, cj_imm(displacement as i32)),
+            FixupKind::PcRel => {
+                let hi20 = (displacement + 0x800) >> 12;
+                let lo12 = displacement - (hi20 << 12);
+
+                self.patch_instruction(fixup.offset, (hi20 as u32 & 0xfffff) << 12);
+                self.patch_instruction(fixup.offset + 4, (lo12 as u32 & 0xfff) << 20);
+            }
+        }
+    }
+
+    /// Returns true if `label` is already attached and its displacement from the instruction about to be written fits in a field of `kind`.
+    fn label_in_range(&self, label: LabelRef, kind: FixupKind) -> bool {
+        self.label_offset(label).is_some_and(|target| kind.form().resolve(self.bytes.len(), target).is_ok())
+    }
+
+    /// Writes the compressed encoding produced by `encode` if compression is enabled and the operands qualify, returning whether it did.
+    fn try_compressed(&mut self, encode: impl FnOnce() -> Option<u16>) -> bool {
+        match self.compress.then(encode).flatten() {
+            Some(instruction) => {
+                self.write_compressed_instruction(instruction);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Merges `bits` into the compressed instruction at the provided offset.
+    fn patch_compressed_instruction(&mut self, offset: usize, bits: u16) {
+        let slot = &mut self.bytes[offset..offset + 2];
+        let instruction = u16::from_le_bytes([slot[0], slot[1]]) | bits;
+        slot.copy_from_slice(&instruction.to_le_bytes());
+    }
+
+    /// Merges `bits` into the instruction at the provided offset.
+    fn patch_instruction(&mut self, offset: usize, bits: u32) {
+        let slot = &mut self.bytes[offset..offset + 4];
+        let instruction = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]) | bits;
+        slot.copy_from_slice(&instruction.to_le_bytes());
+    }
+
+    /// Records a use of `label` by the instruction which is about to be written.
+    fn use_label(&mut self, label: LabelRef, kind: FixupKind) {
+        self.labels.use_label(label, Fixup { offset: self.bytes.len(), label, kind });
+    }
+
+    /// Writes an instruction with a signed 12-bit immediate.
+    ///
+    /// # Panics
+    /// Panics if `imm` does not fit in 12 bits.
+    fn write_i_type(&mut self, opcode: u32, funct3: u32, rd: XReg, rs1: XReg, imm: i32) {
+        assert!(fits_signed(imm as i64, 12), "immediate {} does not fit in 12 bits", imm);
+        self.write_instruction(i_type(opcode, rd.offset(), funct3, rs1.offset(), imm));
+    }
+
+    /// Writes a shift by an immediate amount, `funct6` selecting the kind of shift.
+    ///
+    /// # Panics
+    /// Panics if `shamt` is out of range for the width of the shift.
+    fn write_shift(&mut self, opcode: u32, funct3: u32, funct6: u32, rd: XReg, rs1: XReg, shamt: u32) {
+        let bits = if opcode == OPCODE_OP_IMM_32 { 5 } else { 6 };
+        assert!(shamt < (1 << bits), "shift amount {} does not fit in {} bits", shamt, bits);
+        self.write_instruction(i_type(opcode, rd.offset(), funct3, rs1.offset(), ((funct6 << 6) | shamt) as i32));
+    }
+
+    /// Writes a register-register instruction.
+    fn write_r_type(&mut self, opcode: u32, funct3: u32, funct7: u32, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_instruction(r_type(opcode, rd.offset(), funct3, rs1.offset(), rs2.offset(), funct7));
+    }
+
+    /// Writes a store instruction.
+    ///
+    /// # Panics
+    /// Panics if `offset` does not fit in 12 bits.
+    fn write_store(&mut self, funct3: u32, src: XReg, base: XReg, offset: i32) {
+        assert!(fits_signed(offset as i64, 12), "offset {} does not fit in 12 bits", offset);
+        self.write_instruction(s_type(OPCODE_STORE, funct3, base.offset(), src.offset(), offset));
+    }
+
+    /// Writes a conditional branch to a displacement.
+    ///
+    /// # Panics
+    /// Panics if `offset` is odd or does not fit in 13 bits.
+    fn write_branch(&mut self, funct3: u32, rs1: XReg, rs2: XReg, offset: i32) {
+        assert!(offset & 1 == 0 && fits_signed(offset as i64, 13), "branch offset {} is misaligned or out of range", offset);
+
+        if fits_signed(offset as i64, 9) {
+            if let Some(instruction) = self.compressible_branch(funct3, rs1, rs2) {
+                self.write_compressed_instruction(instruction | cb_imm(offset));
+                return;
+            }
+        }
+
+        self.write_instruction(b_type(OPCODE_BRANCH, funct3, rs1.offset(), rs2.offset(), offset));
+    }
+
+    /// Returns the compressed `c.beqz` or `c.bnez` equivalent of a branch comparing against `zero`, without its displacement, if compression is enabled and the
+    /// operands qualify.
+    fn compressible_branch(&self, funct3: u32, rs1: XReg, rs2: XReg) -> Option<u16> {
+        let rs = match (rs1, rs2) {
+            (rs, XReg::X0) | (XReg::X0, rs) => rs,
+            _ => return None,
+        };
+
+        if !self.compress || !rs.is_compressible() {
+            return None;
+        }
+
+        match funct3 {
+            0b000 => Some(0xc001 | (rs.compressed_offset() << 7) as u16),
+            0b001 => Some(0xe001 | (rs.compressed_offset() << 7) as u16),
+            _ => None,
+        }
+    }
+
+    /// Writes a conditional branch to a label.
+    fn write_branch_label(&mut self, funct3: u32, rs1: XReg, rs2: XReg, label: LabelRef) {
+        let in_range = self.label_in_range(label, FixupKind::CompressedBranch);
+        if in_range {
+            if let Some(instruction) = self.compressible_branch(funct3, rs1, rs2) {
+                self.use_label(label, FixupKind::CompressedBranch);
+                self.write_compressed_instruction(instruction);
+                return;
+            }
+        }
+
+        self.use_label(label, FixupKind::Branch);
+        self.write_instruction(b_type(OPCODE_BRANCH, funct3, rs1.offset(), rs2.offset(), 0));
+    }
+
+    /// Load upper immediate: *rd* = *imm20* << 12.
+    ///
+    /// # Panics
+    /// Panics if `imm20` does not fit in 20 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.lui(XReg::a0(), 0x12345); // lui a0, 0x12345
+    /// stream.auipc(XReg::t0(), 0xfffff); // auipc t0, 0xfffff
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x37, 0x55, 0x34, 0x12, 0x97, 0xf2, 0xff, 0xff]);
+    /// ```
+    pub fn lui(&mut self, rd: XReg, imm20: u32) {
+        assert!(imm20 < (1 << 20), "immediate {:#x} does not fit in 20 bits", imm20);
+
+        if self.try_compressed(|| encode_c_lui(rd, imm20)) {
+            return;
+        }
+
+        self.write_instruction(u_type(OPCODE_LUI, rd.offset(), imm20));
+    }
+
+    /// Add upper immediate to pc: *rd* = pc + (*imm20* << 12).
+    ///
+    /// # Panics
+    /// Panics if `imm20` does not fit in 20 bits.
+    pub fn auipc(&mut self, rd: XReg, imm20: u32) {
+        assert!(imm20 < (1 << 20), "immediate {:#x} does not fit in 20 bits", imm20);
+        self.write_instruction(u_type(OPCODE_AUIPC, rd.offset(), imm20));
+    }
+
+    /// Add immediate: *rd* = *rs1* + *imm12*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.addi(XReg::a0(), XReg::a1(), -1); // addi a0, a1, -1
+    /// stream.srai(XReg::a0(), XReg::a0(), 63); // srai a0, a0, 63
+    /// stream.addiw(XReg::a0(), XReg::a0(), 2047); // addiw a0, a0, 2047
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x13, 0x85, 0xf5, 0xff, 0x13, 0x55, 0xf5, 0x43, 0x1b, 0x05, 0xf5, 0x7f]);
+    /// ```
+    pub fn addi(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        let compressed = || match (rd, rs1, imm12) {
+            (_, XReg::X0, _) => encode_c_li(rd, imm12),
+            (_, _, 0) => encode_cr(0b1000, rd, rs1).filter(|_| rs1 != XReg::zero()),
+            _ if rd == rs1 => encode_c_addi(rd, imm12).or_else(|| encode_c_addi16sp(imm12).filter(|_| rd == XReg::sp())),
+            (_, XReg::X2, _) => encode_c_addi4spn(rd, imm12),
+            _ => None,
+        };
+
+        if self.try_compressed(compressed) {
+            return;
+        }
+
+        self.write_i_type(OPCODE_OP_IMM, 0b000, rd, rs1, imm12);
+    }
+
+    /// Set if less than immediate: *rd* = *rs1* < *imm12*, signed.
+    pub fn slti(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM, 0b010, rd, rs1, imm12);
+    }
+
+    /// Set if less than immediate: *rd* = *rs1* < *imm12*, unsigned.
+    pub fn sltiu(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM, 0b011, rd, rs1, imm12);
+    }
+
+    /// Exclusive or immediate: *rd* = *rs1* ^ *imm12*.
+    pub fn xori(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM, 0b100, rd, rs1, imm12);
+    }
+
+    /// Or immediate: *rd* = *rs1* | *imm12*.
+    pub fn ori(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM, 0b110, rd, rs1, imm12);
+    }
+
+    /// And immediate: *rd* = *rs1* & *imm12*.
+    pub fn andi(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM, 0b111, rd, rs1, imm12);
+    }
+
+    /// Shift left logical immediate: *rd* = *rs1* << *shamt*.
+    pub fn slli(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM, 0b001, 0b000000, rd, rs1, shamt);
+    }
+
+    /// Shift right logical immediate: *rd* = *rs1* >> *shamt*, unsigned.
+    pub fn srli(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM, 0b101, 0b000000, rd, rs1, shamt);
+    }
+
+    /// Shift right arithmetic immediate: *rd* = *rs1* >> *shamt*, signed.
+    pub fn srai(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM, 0b101, 0b010000, rd, rs1, shamt);
+    }
+
+    /// Add word immediate: *rd* = sext((*rs1* + *imm12*)\[31:0\]).
+    pub fn addiw(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        self.write_i_type(OPCODE_OP_IMM_32, 0b000, rd, rs1, imm12);
+    }
+
+    /// Shift left logical word immediate.
+    pub fn slliw(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM_32, 0b001, 0b000000, rd, rs1, shamt);
+    }
+
+    /// Shift right logical word immediate.
+    pub fn srliw(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM_32, 0b101, 0b000000, rd, rs1, shamt);
+    }
+
+    /// Shift right arithmetic word immediate.
+    pub fn sraiw(&mut self, rd: XReg, rs1: XReg, shamt: u32) {
+        self.write_shift(OPCODE_OP_IMM_32, 0b101, 0b010000, rd, rs1, shamt);
+    }
+
+    /// Add: *rd* = *rs1* + *rs2*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.add(XReg::a0(), XReg::a1(), XReg::a2()); // add a0, a1, a2
+    /// stream.sub(XReg::t6(), XReg::zero(), XReg::s11()); // sub t6, zero, s11
+    /// stream.sraw(XReg::a0(), XReg::a0(), XReg::a1()); // sraw a0, a0, a1
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x33, 0x85, 0xc5, 0x00, 0xb3, 0x0f, 0xb0, 0x41, 0x3b, 0x55, 0xb5, 0x40]);
+    /// ```
+    pub fn add(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        let compressed = || match (rs1, rs2) {
+            (XReg::X0, XReg::X0) => None,
+            (XReg::X0, rs) | (rs, XReg::X0) => encode_cr(0b1000, rd, rs),
+            _ if rd == rs1 => encode_cr(0b1001, rd, rs2),
+            _ if rd == rs2 => encode_cr(0b1001, rd, rs1),
+            _ => None,
+        };
+
+        if self.try_compressed(compressed) {
+            return;
+        }
+
+        self.write_r_type(OPCODE_OP, 0b000, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Subtract: *rd* = *rs1* - *rs2*.
+    pub fn sub(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b000, 0b0100000, rd, rs1, rs2);
+    }
+
+    /// Shift left logical: *rd* = *rs1* << *rs2*.
+    pub fn sll(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b001, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Set if less than: *rd* = *rs1* < *rs2*, signed.
+    pub fn slt(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b010, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Set if less than: *rd* = *rs1* < *rs2*, unsigned.
+    pub fn sltu(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b011, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Exclusive or: *rd* = *rs1* ^ *rs2*.
+    pub fn xor(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b100, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Shift right logical: *rd* = *rs1* >> *rs2*, unsigned.
+    pub fn srl(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b101, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Shift right arithmetic: *rd* = *rs1* >> *rs2*, signed.
+    pub fn sra(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b101, 0b0100000, rd, rs1, rs2);
+    }
+
+    /// Or: *rd* = *rs1* | *rs2*.
+    pub fn or(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b110, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// And: *rd* = *rs1* & *rs2*.
+    pub fn and(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b111, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Add word.
+    pub fn addw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b000, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Subtract word.
+    pub fn subw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b000, 0b0100000, rd, rs1, rs2);
+    }
+
+    /// Shift left logical word.
+    pub fn sllw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b001, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Shift right logical word.
+    pub fn srlw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b101, 0b0000000, rd, rs1, rs2);
+    }
+
+    /// Shift right arithmetic word.
+    pub fn sraw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b101, 0b0100000, rd, rs1, rs2);
+    }
+
+    /// Load byte, sign-extended: *rd* = *\[rs1 + offset\]*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.ld(XReg::ra(), XReg::sp(), 8); // ld ra, 8(sp)
+    /// stream.sd(XReg::ra(), XReg::sp(), -8); // sd ra, -8(sp)
+    /// stream.lbu(XReg::a0(), XReg::a1(), -2048); // lbu a0, -2048(a1)
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x83, 0x30, 0x81, 0x00, 0x23, 0x3c, 0x11, 0xfe, 0x03, 0xc5, 0x05, 0x80]);
+    /// ```
+    pub fn lb(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_i_type(OPCODE_LOAD, 0b000, rd, base, offset);
+    }
+
+    /// Load halfword, sign-extended.
+    pub fn lh(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_i_type(OPCODE_LOAD, 0b001, rd, base, offset);
+    }
+
+    /// Load word, sign-extended.
+    pub fn lw(&mut self, rd: XReg, base: XReg, offset: i32) {
+        if self.try_compressed(|| encode_c_mem(0b010, 4, rd, base, offset)) {
+            return;
+        }
+
+        self.write_i_type(OPCODE_LOAD, 0b010, rd, base, offset);
+    }
+
+    /// Load doubleword.
+    pub fn ld(&mut self, rd: XReg, base: XReg, offset: i32) {
+        if self.try_compressed(|| encode_c_mem(0b011, 8, rd, base, offset)) {
+            return;
+        }
+
+        self.write_i_type(OPCODE_LOAD, 0b011, rd, base, offset);
+    }
+
+    /// Load byte, zero-extended.
+    pub fn lbu(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_i_type(OPCODE_LOAD, 0b100, rd, base, offset);
+    }
+
+    /// Load halfword, zero-extended.
+    pub fn lhu(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_i_type(OPCODE_LOAD, 0b101, rd, base, offset);
+    }
+
+    /// Load word, zero-extended.
+    pub fn lwu(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_i_type(OPCODE_LOAD, 0b110, rd, base, offset);
+    }
+
+    /// Store byte: *\[base + offset\]* = *src*.
+    pub fn sb(&mut self, src: XReg, base: XReg, offset: i32) {
+        self.write_store(0b000, src, base, offset);
+    }
+
+    /// Store halfword.
+    pub fn sh(&mut self, src: XReg, base: XReg, offset: i32) {
+        self.write_store(0b001, src, base, offset);
+    }
+
+    /// Store word.
+    pub fn sw(&mut self, src: XReg, base: XReg, offset: i32) {
+        if self.try_compressed(|| encode_c_mem(0b110, 4, src, base, offset)) {
+            return;
+        }
+
+        self.write_store(0b010, src, base, offset);
+    }
+
+    /// Store doubleword.
+    pub fn sd(&mut self, src: XReg, base: XReg, offset: i32) {
+        if self.try_compressed(|| encode_c_mem(0b111, 8, src, base, offset)) {
+            return;
+        }
+
+        self.write_store(0b011, src, base, offset);
+    }
+
+    /// Jump and link to a pc-relative displacement.
+    ///
+    /// # Panics
+    /// Panics if `offset` is odd or does not fit in 21 bits.
+    pub fn jal(&mut self, rd: XReg, offset: i32) {
+        assert!(offset & 1 == 0 && fits_signed(offset as i64, 21), "jump offset {} is misaligned or out of range", offset);
+
+        if self.try_compressed(|| (rd == XReg::zero() && fits_signed(offset as i64, 12)).then(|| 0xa001 | cj_imm(offset))) {
+            return;
+        }
+
+        self.write_instruction(j_type(OPCODE_JAL, rd.offset(), offset));
+    }
+
+    /// Jump and link to a label.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let start = stream.create_label_attached();
+    /// let end = stream.create_label();
+    ///
+    /// stream.jal_label(XReg::zero(), end); // j end
+    /// stream.addi(XReg::zero(), XReg::zero(), 0); // nop
+    /// stream.jal_label(XReg::ra(), start); // jal start
+    /// stream.attach_label(end);
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x6f, 0x00, 0xc0, 0x00, 0x13, 0x00, 0x00, 0x00, 0xef, 0xf0, 0x9f, 0xff]);
+    /// ```
+    pub fn jal_label(&mut self, rd: XReg, label: LabelRef) {
+        let in_range = self.label_in_range(label, FixupKind::CompressedJump);
+        if self.compress && in_range && rd == XReg::zero() {
+            self.use_label(label, FixupKind::CompressedJump);
+            self.write_compressed_instruction(0xa001);
+            return;
+        }
+
+        self.use_label(label, FixupKind::Jump);
+        self.write_instruction(j_type(OPCODE_JAL, rd.offset(), 0));
+    }
+
+    /// Jump and link to *rs1* + *imm12*.
+    pub fn jalr(&mut self, rd: XReg, rs1: XReg, imm12: i32) {
+        let compressed = || match (rd, imm12) {
+            (XReg::X0, 0) => encode_cr(0b1000, rs1, XReg::zero()),
+            (XReg::X1, 0) => encode_cr(0b1001, rs1, XReg::zero()),
+            _ => None,
+        };
+
+        if self.try_compressed(compressed) {
+            return;
+        }
+
+        self.write_i_type(OPCODE_JALR, 0b000, rd, rs1, imm12);
+    }
+
+    /// Branch to a displacement if *rs1* == *rs2*.
+    pub fn beq(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b000, rs1, rs2, offset);
+    }
+
+    /// Branch to a displacement if *rs1* != *rs2*.
+    pub fn bne(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b001, rs1, rs2, offset);
+    }
+
+    /// Branch to a displacement if *rs1* < *rs2*, signed.
+    pub fn blt(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b100, rs1, rs2, offset);
+    }
+
+    /// Branch to a displacement if *rs1* >= *rs2*, signed.
+    pub fn bge(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b101, rs1, rs2, offset);
+    }
+
+    /// Branch to a displacement if *rs1* < *rs2*, unsigned.
+    pub fn bltu(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b110, rs1, rs2, offset);
+    }
+
+    /// Branch to a displacement if *rs1* >= *rs2*, unsigned.
+    pub fn bgeu(&mut self, rs1: XReg, rs2: XReg, offset: i32) {
+        self.write_branch(0b111, rs1, rs2, offset);
+    }
+
+    /// Branch to a label if *rs1* == *rs2*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let top = stream.create_label_attached();
+    /// let out = stream.create_label();
+    ///
+    /// stream.beq_label(XReg::a0(), XReg::zero(), out); // beq a0, zero, out
+    /// stream.addi(XReg::a0(), XReg::a0(), -1); // addi a0, a0, -1
+    /// stream.bltu_label(XReg::zero(), XReg::a0(), top); // bltu zero, a0, top
+    /// stream.attach_label(out);
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x63, 0x06, 0x05, 0x00, 0x13, 0x05, 0xf5, 0xff, 0xe3, 0x6c, 0xa0, 0xfe]);
+    /// ```
+    pub fn beq_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b000, rs1, rs2, label);
+    }
+
+    /// Branch to a label if *rs1* != *rs2*.
+    pub fn bne_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b001, rs1, rs2, label);
+    }
+
+    /// Branch to a label if *rs1* < *rs2*, signed.
+    pub fn blt_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b100, rs1, rs2, label);
+    }
+
+    /// Branch to a label if *rs1* >= *rs2*, signed.
+    pub fn bge_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b101, rs1, rs2, label);
+    }
+
+    /// Branch to a label if *rs1* < *rs2*, unsigned.
+    pub fn bltu_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b110, rs1, rs2, label);
+    }
+
+    /// Branch to a label if *rs1* >= *rs2*, unsigned.
+    pub fn bgeu_label(&mut self, rs1: XReg, rs2: XReg, label: LabelRef) {
+        self.write_branch_label(0b111, rs1, rs2, label);
+    }
+
+    /// Load immediate: materializes an arbitrary 64-bit constant into *rd*, using the shortest sequence of `lui`, `addi(w)`, `slli` and `srli` found.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let li = |value: i64| {
+    ///     let mut stream = Riscv64InstructionStream::new();
+    ///     stream.li(XReg::a0(), value);
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// // addi a0, zero, -2048
+    /// assert_eq!(li(-2048), [0x13, 0x05, 0x00, 0x80]);
+    ///
+    /// // lui a0, 0x12345; addiw a0, a0, 0x678
+    /// assert_eq!(li(0x12345678), [0x37, 0x55, 0x34, 0x12, 0x1b, 0x05, 0x85, 0x67]);
+    ///
+    /// // addi a0, zero, -1; srli a0, a0, 32
+    /// assert_eq!(li(0xffffffff), [0x13, 0x05, 0xf0, 0xff, 0x13, 0x55, 0x05, 0x02]);
+    ///
+    /// // lui a0, 0xffff; slli a0, a0, 20
+    /// assert_eq!(li(0x0000ffff00000000), [0x37, 0xf5, 0xff, 0x0f, 0x13, 0x15, 0x45, 0x01]);
+    ///
+    /// // lui a0, 583; addiw a0, a0, -1875; slli a0, a0, 14; addi a0, a0, -947; slli a0, a0, 12; addi a0, a0, 1511; slli a0, a0, 13; addi a0, a0, -272
+    /// assert_eq!(li(0x123456789abcdef0), [
+    ///     0x37, 0x75, 0x24, 0x00, 0x1b, 0x05, 0xd5, 0x8a,
+    ///     0x13, 0x15, 0xe5, 0x00, 0x13, 0x05, 0xd5, 0xc4,
+    ///     0x13, 0x15, 0xc5, 0x00, 0x13, 0x05, 0x75, 0x5e,
+    ///     0x13, 0x15, 0xd5, 0x00, 0x13, 0x05, 0x05, 0xef,
+    /// ]);
+    /// ```
+    pub fn li(&mut self, rd: XReg, value: i64) {
+        let mut sequence = Vec::new();
+        load_immediate_sequence(value, &mut sequence);
+
+        // a positive constant with leading zeros may be cheaper to build shifted all the way left, then shifted back with a final `srli`.
+        if value > 0 && sequence.len() > 2 {
+            let leading_zeros = value.leading_zeros();
+            let shifted = (value << leading_zeros) as u64;
+
+            for fill in [shifted | ((1u64 << leading_zeros) - 1), shifted] {
+                let mut candidate = Vec::new();
+                load_immediate_sequence(fill as i64, &mut candidate);
+                candidate.push(LoadImmediate::Srli(leading_zeros));
+
+                if candidate.len() < sequence.len() {
+                    sequence = candidate;
+                }
+            }
+        }
+
+        let mut src = XReg::zero();
+        for instruction in sequence {
+            match instruction {
+                LoadImmediate::Lui(imm20) => self.lui(rd, imm20),
+                LoadImmediate::Addi(imm12) => self.addi(rd, src, imm12),
+                LoadImmediate::Addiw(imm12) => self.addiw(rd, src, imm12),
+                LoadImmediate::Slli(shamt) => self.slli(rd, src, shamt),
+                LoadImmediate::Srli(shamt) => self.srli(rd, src, shamt),
+            }
+
+            src = rd;
+        }
+    }
+
+    /// Load address: *rd* = the address of `label`, through `auipc` and `addi`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let data = stream.create_label();
+    ///
+    /// stream.la_label(XReg::a0(), data); // auipc a0, 0; addi a0, a0, 12
+    /// stream.ret(); // jalr zero, 0(ra)
+    /// stream.attach_label(data);
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x17, 0x05, 0x00, 0x00, 0x13, 0x05, 0xc5, 0x00, 0x67, 0x80, 0x00, 0x00]);
+    /// ```
+    pub fn la_label(&mut self, rd: XReg, label: LabelRef) {
+        self.use_label(label, FixupKind::PcRel);
+        self.auipc(rd, 0);
+        self.write_i_type(OPCODE_OP_IMM, 0b000, rd, rd, 0);
+    }
+
+    /// Call a label, through `auipc` and `jalr`, using `ra` both as the link register and as the scratch register for the upper displacement.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// let function = stream.create_label_attached();
+    ///
+    /// stream.ret(); // jalr zero, 0(ra)
+    /// stream.call_label(function); // auipc ra, 0; jalr ra, -4(ra)
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x67, 0x80, 0x00, 0x00, 0x97, 0x00, 0x00, 0x00, 0xe7, 0x80, 0xc0, 0xff]);
+    /// ```
+    pub fn call_label(&mut self, label: LabelRef) {
+        self.use_label(label, FixupKind::PcRel);
+        self.auipc(XReg::ra(), 0);
+        self.write_i_type(OPCODE_JALR, 0b000, XReg::ra(), XReg::ra(), 0);
+    }
+
+    /// Copy register: *rd* = *rs*.
+    pub fn mv(&mut self, rd: XReg, rs: XReg) {
+        self.addi(rd, rs, 0);
+    }
+
+    /// One's complement: *rd* = !*rs*.
+    pub fn not(&mut self, rd: XReg, rs: XReg) {
+        self.xori(rd, rs, -1);
+    }
+
+    /// Two's complement: *rd* = -*rs*.
+    pub fn neg(&mut self, rd: XReg, rs: XReg) {
+        self.sub(rd, XReg::zero(), rs);
+    }
+
+    /// Return from subroutine, jumping to `ra`.
+    pub fn ret(&mut self) {
+        self.jalr(XReg::zero(), XReg::ra(), 0);
+    }
+
+    /// Multiply: *rd* = (*rs1* * *rs2*)\[63:0\].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.mul(XReg::a0(), XReg::a1(), XReg::a2()); // mul a0, a1, a2
+    /// stream.mulhsu(XReg::a0(), XReg::a1(), XReg::a2()); // mulhsu a0, a1, a2
+    /// stream.remuw(XReg::t0(), XReg::t1(), XReg::t2()); // remuw t0, t1, t2
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x33, 0x85, 0xc5, 0x02, 0x33, 0xa5, 0xc5, 0x02, 0xbb, 0x72, 0x73, 0x02]);
+    /// ```
+    pub fn mul(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b000, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Multiply high, signed by signed: *rd* = (*rs1* * *rs2*)\[127:64\].
+    pub fn mulh(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b001, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Multiply high, signed by unsigned.
+    pub fn mulhsu(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b010, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Multiply high, unsigned by unsigned.
+    pub fn mulhu(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b011, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Divide, signed: *rd* = *rs1* / *rs2*.
+    pub fn div(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b100, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Divide, unsigned.
+    pub fn divu(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b101, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Remainder, signed: *rd* = *rs1* % *rs2*.
+    pub fn rem(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b110, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Remainder, unsigned.
+    pub fn remu(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP, 0b111, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Multiply word.
+    pub fn mulw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b000, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Divide word, signed.
+    pub fn divw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b100, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Divide word, unsigned.
+    pub fn divuw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b101, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Remainder word, signed.
+    pub fn remw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b110, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Remainder word, unsigned.
+    pub fn remuw(&mut self, rd: XReg, rs1: XReg, rs2: XReg) {
+        self.write_r_type(OPCODE_OP_32, 0b111, 0b0000001, rd, rs1, rs2);
+    }
+
+    /// Writes an atomic memory operation, `funct5` selecting the operation.
+    fn write_amo(&mut self, funct5: u32, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_instruction(r_type(OPCODE_AMO, rd.offset(), width.funct3(), address.offset(), rs2.offset(), funct5 << 2) | ordering.bits());
+    }
+
+    /// Load reserved: *rd* = *\[address\]*, registering a reservation on the address.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::{Ordering, Riscv64InstructionStream, Width}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.lr(Width::Doubleword, Ordering::Acquire, XReg::a0(), XReg::a1()); // lr.d.aq a0, (a1)
+    /// stream.sc(Width::Doubleword, Ordering::Release, XReg::a2(), XReg::a3(), XReg::a1()); // sc.d.rl a2, a3, (a1)
+    /// stream.amoadd(Width::Word, Ordering::AcqRel, XReg::a0(), XReg::a2(), XReg::a1()); // amoadd.w.aqrl a0, a2, (a1)
+    /// stream.amomaxu(Width::Doubleword, Ordering::Relaxed, XReg::t0(), XReg::t1(), XReg::sp()); // amomaxu.d t0, t1, (sp)
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x2f, 0xb5, 0x05, 0x14,
+    ///     0x2f, 0xb6, 0xd5, 0x1a,
+    ///     0x2f, 0xa5, 0xc5, 0x06,
+    ///     0xaf, 0x32, 0x61, 0xe0,
+    /// ]);
+    /// ```
+    pub fn lr(&mut self, width: Width, ordering: Ordering, rd: XReg, address: XReg) {
+        self.write_amo(0b00010, width, ordering, rd, XReg::zero(), address);
+    }
+
+    /// Store conditional: *\[address\]* = *rs2* if the reservation is still held.  *rd* is set to zero on success and non-zero on failure.
+    pub fn sc(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b00011, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic swap: *rd* = *\[address\]*, *\[address\]* = *rs2*.
+    pub fn amoswap(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b00001, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic add: *rd* = *\[address\]*, *\[address\]* += *rs2*.
+    pub fn amoadd(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b00000, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic and: *rd* = *\[address\]*, *\[address\]* &= *rs2*.
+    pub fn amoand(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b01100, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic or: *rd* = *\[address\]*, *\[address\]* |= *rs2*.
+    pub fn amoor(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b01000, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic exclusive or: *rd* = *\[address\]*, *\[address\]* ^= *rs2*.
+    pub fn amoxor(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b00100, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic maximum, signed.
+    pub fn amomax(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b10100, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic minimum, signed.
+    pub fn amomin(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b10000, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic maximum, unsigned.
+    pub fn amomaxu(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b11100, width, ordering, rd, rs2, address);
+    }
+
+    /// Atomic minimum, unsigned.
+    pub fn amominu(&mut self, width: Width, ordering: Ordering, rd: XReg, rs2: XReg, address: XReg) {
+        self.write_amo(0b11000, width, ordering, rd, rs2, address);
+    }
+
+    /// Writes an `OP-FP` instruction from raw register offsets, as the instructions of the F and D extensions mix integer and floating-point operands.
+    fn write_fp_op(&mut self, funct5: u32, precision: Precision, funct3: u32, rd: u32, rs1: u32, rs2: u32) {
+        self.write_instruction(r_type(OPCODE_OP_FP, rd, funct3, rs1, rs2, (funct5 << 2) | precision.fmt()));
+    }
+
+    /// Floating-point load word: *rd* = *\[base + offset\]*.
+    ///
+    /// # Panics
+    /// Panics if `offset` does not fit in 12 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::{FReg, XReg}, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.flw(FReg::fa0(), XReg::sp(), 4); // flw fa0, 4(sp)
+    /// stream.fld(FReg::fs0(), XReg::a0(), -8); // fld fs0, -8(a0)
+    /// stream.fsw(FReg::ft0(), XReg::a1(), 12); // fsw ft0, 12(a1)
+    /// stream.fsd(FReg::fa5(), XReg::sp(), -2048); // fsd fa5, -2048(sp)
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x07, 0x25, 0x41, 0x00,
+    ///     0x07, 0x34, 0x85, 0xff,
+    ///     0x27, 0xa6, 0x05, 0x00,
+    ///     0x27, 0x30, 0xf1, 0x80,
+    /// ]);
+    /// ```
+    pub fn flw(&mut self, rd: FReg, base: XReg, offset: i32) {
+        assert!(fits_signed(offset as i64, 12), "offset {} does not fit in 12 bits", offset);
+        self.write_instruction(i_type(OPCODE_LOAD_FP, rd.offset(), 0b010, base.offset(), offset));
+    }
+
+    /// Floating-point load doubleword.
+    pub fn fld(&mut self, rd: FReg, base: XReg, offset: i32) {
+        assert!(fits_signed(offset as i64, 12), "offset {} does not fit in 12 bits", offset);
+        self.write_instruction(i_type(OPCODE_LOAD_FP, rd.offset(), 0b011, base.offset(), offset));
+    }
+
+    /// Floating-point store word: *\[base + offset\]* = *src*.
+    pub fn fsw(&mut self, src: FReg, base: XReg, offset: i32) {
+        assert!(fits_signed(offset as i64, 12), "offset {} does not fit in 12 bits", offset);
+        self.write_instruction(s_type(OPCODE_STORE_FP, 0b010, base.offset(), src.offset(), offset));
+    }
+
+    /// Floating-point store doubleword.
+    pub fn fsd(&mut self, src: FReg, base: XReg, offset: i32) {
+        assert!(fits_signed(offset as i64, 12), "offset {} does not fit in 12 bits", offset);
+        self.write_instruction(s_type(OPCODE_STORE_FP, 0b011, base.offset(), src.offset(), offset));
+    }
+
+    /// Floating-point add: *rd* = *rs1* + *rs2*, rounded according to `rm`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::FReg, stream::{Precision, Riscv64InstructionStream, RoundingMode}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.fadd(Precision::Single, FReg::fa0(), FReg::fa1(), FReg::fa2(), RoundingMode::default()); // fadd.s fa0, fa1, fa2, dyn
+    /// stream.fadd(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa2(), RoundingMode::Rtz); // fadd.d fa0, fa1, fa2, rtz
+    /// stream.fsub(Precision::Double, FReg::ft0(), FReg::ft1(), FReg::ft2(), RoundingMode::Rne); // fsub.d ft0, ft1, ft2, rne
+    /// stream.fmul(Precision::Single, FReg::fs1(), FReg::fs2(), FReg::fs3(), RoundingMode::Rup); // fmul.s fs1, fs2, fs3, rup
+    /// stream.fdiv(Precision::Double, FReg::fa0(), FReg::fa0(), FReg::fa1(), RoundingMode::Dyn); // fdiv.d fa0, fa0, fa1, dyn
+    /// stream.fsqrt(Precision::Double, FReg::fa0(), FReg::fa1(), RoundingMode::Rmm); // fsqrt.d fa0, fa1, rmm
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x53, 0xf5, 0xc5, 0x00,
+    ///     0x53, 0x95, 0xc5, 0x02,
+    ///     0x53, 0x80, 0x20, 0x0a,
+    ///     0xd3, 0x34, 0x39, 0x11,
+    ///     0x53, 0x75, 0xb5, 0x1a,
+    ///     0x53, 0xc5, 0x05, 0x5a,
+    /// ]);
+    /// ```
+    pub fn fadd(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b00000, precision, rm.bits(), rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point subtract: *rd* = *rs1* - *rs2*.
+    pub fn fsub(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b00001, precision, rm.bits(), rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point multiply: *rd* = *rs1* * *rs2*.
+    pub fn fmul(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b00010, precision, rm.bits(), rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point divide: *rd* = *rs1* / *rs2*.
+    pub fn fdiv(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b00011, precision, rm.bits(), rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point square root: *rd* = sqrt(*rs1*).
+    pub fn fsqrt(&mut self, precision: Precision, rd: FReg, rs1: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b01011, precision, rm.bits(), rd.offset(), rs1.offset(), 0);
+    }
+
+    /// Fused multiply-add: *rd* = (*rs1* * *rs2*) + *rs3*, rounded once.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::FReg, stream::{Precision, Riscv64InstructionStream, RoundingMode}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.fmadd(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa2(), FReg::fa3(), RoundingMode::Dyn); // fmadd.d fa0, fa1, fa2, fa3
+    /// stream.fmsub(Precision::Single, FReg::ft11(), FReg::ft10(), FReg::ft9(), FReg::ft8(), RoundingMode::Rdn); // fmsub.s ft11, ft10, ft9, ft8, rdn
+    /// stream.fnmadd(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa2(), FReg::fa3(), RoundingMode::Dyn); // fnmadd.d fa0, fa1, fa2, fa3
+    /// stream.fnmsub(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa2(), FReg::fa3(), RoundingMode::Dyn); // fnmsub.d fa0, fa1, fa2, fa3
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x43, 0xf5, 0xc5, 0x6a,
+    ///     0xc7, 0x2f, 0xdf, 0xe1,
+    ///     0x4f, 0xf5, 0xc5, 0x6a,
+    ///     0x4b, 0xf5, 0xc5, 0x6a,
+    /// ]);
+    /// ```
+    pub fn fmadd(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg, rm: RoundingMode) {
+        self.write_instruction(r4_type(OPCODE_MADD, rd.offset(), rm.bits(), rs1.offset(), rs2.offset(), precision.fmt(), rs3.offset()));
+    }
+
+    /// Fused multiply-subtract: *rd* = (*rs1* * *rs2*) - *rs3*.
+    pub fn fmsub(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg, rm: RoundingMode) {
+        self.write_instruction(r4_type(OPCODE_MSUB, rd.offset(), rm.bits(), rs1.offset(), rs2.offset(), precision.fmt(), rs3.offset()));
+    }
+
+    /// Fused negated multiply-add: *rd* = -(*rs1* * *rs2*) - *rs3*.
+    pub fn fnmadd(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg, rm: RoundingMode) {
+        self.write_instruction(r4_type(OPCODE_NMADD, rd.offset(), rm.bits(), rs1.offset(), rs2.offset(), precision.fmt(), rs3.offset()));
+    }
+
+    /// Fused negated multiply-subtract: *rd* = -(*rs1* * *rs2*) + *rs3*.
+    pub fn fnmsub(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg, rm: RoundingMode) {
+        self.write_instruction(r4_type(OPCODE_NMSUB, rd.offset(), rm.bits(), rs1.offset(), rs2.offset(), precision.fmt(), rs3.offset()));
+    }
+
+    /// Floating-point compare equal: *rd* = *rs1* == *rs2*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::{FReg, XReg}, stream::{Precision, Riscv64InstructionStream}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.feq(Precision::Double, XReg::a0(), FReg::fa0(), FReg::fa1()); // feq.d a0, fa0, fa1
+    /// stream.flt(Precision::Single, XReg::a0(), FReg::fa0(), FReg::fa1()); // flt.s a0, fa0, fa1
+    /// stream.fle(Precision::Double, XReg::t0(), FReg::ft0(), FReg::ft1()); // fle.d t0, ft0, ft1
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x53, 0x25, 0xb5, 0xa2, 0x53, 0x15, 0xb5, 0xa0, 0xd3, 0x02, 0x10, 0xa2]);
+    /// ```
+    pub fn feq(&mut self, precision: Precision, rd: XReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b10100, precision, 0b010, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point compare less than: *rd* = *rs1* < *rs2*.
+    pub fn flt(&mut self, precision: Precision, rd: XReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b10100, precision, 0b001, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Floating-point compare less than or equal: *rd* = *rs1* <= *rs2*.
+    pub fn fle(&mut self, precision: Precision, rd: XReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b10100, precision, 0b000, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Converts the floating-point value in *rs1* to an integer of the given format, the `fcvt.{w,wu,l,lu}.{s,d}` instructions.
+    ///
+    /// The conversions are selected by the `funct7` field, holding the direction and the floating-point precision, and the `rs2` field, holding the
+    /// [`IntFormat`] or the source [`Precision`]:
+    ///
+    /// | Instruction            | `funct7`  | `rs2`          |
+    /// |------------------------|-----------|----------------|
+    /// | `fcvt.int.s`           | `1100000` | integer format |
+    /// | `fcvt.int.d`           | `1100001` | integer format |
+    /// | `fcvt.s.int`           | `1101000` | integer format |
+    /// | `fcvt.d.int`           | `1101001` | integer format |
+    /// | `fcvt.s.d`             | `0100000` | `00001`        |
+    /// | `fcvt.d.s`             | `0100001` | `00000`        |
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::{FReg, XReg}, stream::{IntFormat, Precision, Riscv64InstructionStream, RoundingMode}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.fcvt_x_f(IntFormat::W, Precision::Double, XReg::a0(), FReg::fa0(), RoundingMode::Rtz); // fcvt.w.d a0, fa0, rtz
+    /// stream.fcvt_x_f(IntFormat::Wu, Precision::Single, XReg::a0(), FReg::fa0(), RoundingMode::Dyn); // fcvt.wu.s a0, fa0, dyn
+    /// stream.fcvt_x_f(IntFormat::L, Precision::Double, XReg::a0(), FReg::fa0(), RoundingMode::Dyn); // fcvt.l.d a0, fa0, dyn
+    /// stream.fcvt_x_f(IntFormat::Lu, Precision::Double, XReg::a0(), FReg::fa0(), RoundingMode::Rtz); // fcvt.lu.d a0, fa0, rtz
+    /// stream.fcvt_f_x(Precision::Double, IntFormat::W, FReg::fa0(), XReg::a0(), RoundingMode::Rne); // fcvt.d.w fa0, a0
+    /// stream.fcvt_f_x(Precision::Double, IntFormat::L, FReg::fa0(), XReg::a0(), RoundingMode::Dyn); // fcvt.d.l fa0, a0, dyn
+    /// stream.fcvt_f_x(Precision::Single, IntFormat::Lu, FReg::fa0(), XReg::a0(), RoundingMode::Rne); // fcvt.s.lu fa0, a0, rne
+    /// stream.fcvt_f_f(Precision::Single, Precision::Double, FReg::fa0(), FReg::fa1(), RoundingMode::Dyn); // fcvt.s.d fa0, fa1, dyn
+    /// stream.fcvt_f_f(Precision::Double, Precision::Single, FReg::fa0(), FReg::fa1(), RoundingMode::Rne); // fcvt.d.s fa0, fa1
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x53, 0x15, 0x05, 0xc2,
+    ///     0x53, 0x75, 0x15, 0xc0,
+    ///     0x53, 0x75, 0x25, 0xc2,
+    ///     0x53, 0x15, 0x35, 0xc2,
+    ///     0x53, 0x05, 0x05, 0xd2,
+    ///     0x53, 0x75, 0x25, 0xd2,
+    ///     0x53, 0x05, 0x35, 0xd0,
+    ///     0x53, 0xf5, 0x15, 0x40,
+    ///     0x53, 0x85, 0x05, 0x42,
+    /// ]);
+    /// ```
+    pub fn fcvt_x_f(&mut self, format: IntFormat, precision: Precision, rd: XReg, rs1: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b11000, precision, rm.bits(), rd.offset(), rs1.offset(), format.selector());
+    }
+
+    /// Converts the integer of the given format in *rs1* to a floating-point value, the `fcvt.{s,d}.{w,wu,l,lu}` instructions.
+    pub fn fcvt_f_x(&mut self, precision: Precision, format: IntFormat, rd: FReg, rs1: XReg, rm: RoundingMode) {
+        self.write_fp_op(0b11010, precision, rm.bits(), rd.offset(), rs1.offset(), format.selector());
+    }
+
+    /// Converts the floating-point value in *rs1* between precisions, the `fcvt.s.d` and `fcvt.d.s` instructions.
+    pub fn fcvt_f_f(&mut self, to: Precision, from: Precision, rd: FReg, rs1: FReg, rm: RoundingMode) {
+        self.write_fp_op(0b01000, to, rm.bits(), rd.offset(), rs1.offset(), from.fmt());
+    }
+
+    /// Sign injection: *rd* = *rs1* with the sign of *rs2*.  `fsgnj rd, rs, rs` is the `fmv` pseudo-instruction.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::FReg, stream::{Precision, Riscv64InstructionStream}};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.fsgnj(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa2()); // fsgnj.d fa0, fa1, fa2
+    /// stream.fsgnjn(Precision::Single, FReg::fa0(), FReg::fa1(), FReg::fa2()); // fsgnjn.s fa0, fa1, fa2
+    /// stream.fsgnjx(Precision::Double, FReg::fa0(), FReg::fa1(), FReg::fa1()); // fsgnjx.d fa0, fa1, fa1
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x53, 0x85, 0xc5, 0x22, 0x53, 0x95, 0xc5, 0x20, 0x53, 0xa5, 0xb5, 0x22]);
+    /// ```
+    pub fn fsgnj(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b00100, precision, 0b000, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Negated sign injection: *rd* = *rs1* with the opposite sign of *rs2*.
+    pub fn fsgnjn(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b00100, precision, 0b001, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Xor sign injection: *rd* = *rs1* with the sign of *rs1* xor the sign of *rs2*.
+    pub fn fsgnjx(&mut self, precision: Precision, rd: FReg, rs1: FReg, rs2: FReg) {
+        self.write_fp_op(0b00100, precision, 0b010, rd.offset(), rs1.offset(), rs2.offset());
+    }
+
+    /// Moves the raw bits of a double-precision register into an integer register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::{FReg, XReg}, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.fmv_x_d(XReg::a0(), FReg::fa0()); // fmv.x.d a0, fa0
+    /// stream.fmv_d_x(FReg::fa0(), XReg::a0()); // fmv.d.x fa0, a0
+    /// stream.fmv_x_w(XReg::a0(), FReg::fa0()); // fmv.x.w a0, fa0
+    /// stream.fmv_w_x(FReg::fa0(), XReg::a0()); // fmv.w.x fa0, a0
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x53, 0x05, 0x05, 0xe2,
+    ///     0x53, 0x05, 0x05, 0xf2,
+    ///     0x53, 0x05, 0x05, 0xe0,
+    ///     0x53, 0x05, 0x05, 0xf0,
+    /// ]);
+    /// ```
+    pub fn fmv_x_d(&mut self, rd: XReg, rs1: FReg) {
+        self.write_fp_op(0b11100, Precision::Double, 0b000, rd.offset(), rs1.offset(), 0);
+    }
+
+    /// Moves the raw bits of an integer register into a double-precision register.
+    pub fn fmv_d_x(&mut self, rd: FReg, rs1: XReg) {
+        self.write_fp_op(0b11110, Precision::Double, 0b000, rd.offset(), rs1.offset(), 0);
+    }
+
+    /// Moves the raw bits of a single-precision register into an integer register, sign-extending them.
+    pub fn fmv_x_w(&mut self, rd: XReg, rs1: FReg) {
+        self.write_fp_op(0b11100, Precision::Single, 0b000, rd.offset(), rs1.offset(), 0);
+    }
+
+    /// Moves the low 32 bits of an integer register into a single-precision register.
+    pub fn fmv_w_x(&mut self, rd: FReg, rs1: XReg) {
+        self.write_fp_op(0b11110, Precision::Single, 0b000, rd.offset(), rs1.offset(), 0);
+    }
+
+    /// Compressed add immediate: *rd* += *imm6*.
+    ///
+    /// The explicit compressed emitters are always available, regardless of [`Riscv64InstructionStream::enable_compression`].
+    ///
+    /// # Panics
+    /// Panics if the operands are not encodable in the compressed form.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.c_addi(XReg::a0(), -32); // c.addi a0, -32
+    /// stream.c_li(XReg::a5(), 31); // c.li a5, 31
+    /// stream.c_mv(XReg::s0(), XReg::a0()); // c.mv s0, a0
+    /// stream.c_add(XReg::a0(), XReg::t6()); // c.add a0, t6
+    /// stream.c_lui(XReg::a0(), 1); // c.lui a0, 1
+    /// stream.c_lui(XReg::t1(), 0xfffe0); // c.lui t1, 0xfffe0
+    /// stream.c_addi16sp(-512); // c.addi16sp sp, -512
+    /// stream.c_addi16sp(496); // c.addi16sp sp, 496
+    /// stream.c_addi4spn(XReg::a0(), 1020); // c.addi4spn a0, sp, 1020
+    /// stream.c_addi4spn(XReg::s1(), 4); // c.addi4spn s1, sp, 4
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x01, 0x15, 0xfd, 0x47, 0x2a, 0x84, 0x7e, 0x95, 0x05, 0x65,
+    ///     0x01, 0x73, 0x01, 0x71, 0x7d, 0x61, 0xe8, 0x1f, 0x44, 0x00,
+    /// ]);
+    /// ```
+    pub fn c_addi(&mut self, rd: XReg, imm6: i32) {
+        self.write_compressed_instruction(encode_c_addi(rd, imm6).expect("operands are not encodable as c.addi"));
+    }
+
+    /// Compressed load immediate: *rd* = *imm6*.
+    pub fn c_li(&mut self, rd: XReg, imm6: i32) {
+        self.write_compressed_instruction(encode_c_li(rd, imm6).expect("operands are not encodable as c.li"));
+    }
+
+    /// Compressed load upper immediate: *rd* = sext(*imm20*) << 12, for *imm20* within the 6-bit signed range.
+    pub fn c_lui(&mut self, rd: XReg, imm20: u32) {
+        self.write_compressed_instruction(encode_c_lui(rd, imm20).expect("operands are not encodable as c.lui"));
+    }
+
+    /// Compressed stack pointer adjustment: `sp` += *imm*, a non-zero multiple of 16.
+    pub fn c_addi16sp(&mut self, imm: i32) {
+        self.write_compressed_instruction(encode_c_addi16sp(imm).expect("operands are not encodable as c.addi16sp"));
+    }
+
+    /// Compressed stack address: *rd* = `sp` + *imm*, a non-zero multiple of 4.
+    pub fn c_addi4spn(&mut self, rd: XReg, imm: i32) {
+        self.write_compressed_instruction(encode_c_addi4spn(rd, imm).expect("operands are not encodable as c.addi4spn"));
+    }
+
+    /// Compressed copy register: *rd* = *rs*.
+    pub fn c_mv(&mut self, rd: XReg, rs: XReg) {
+        let instruction = encode_cr(0b1000, rd, rs).filter(|_| rs != XReg::zero());
+        self.write_compressed_instruction(instruction.expect("operands are not encodable as c.mv"));
+    }
+
+    /// Compressed add: *rd* += *rs*.
+    pub fn c_add(&mut self, rd: XReg, rs: XReg) {
+        let instruction = encode_cr(0b1001, rd, rs).filter(|_| rs != XReg::zero());
+        self.write_compressed_instruction(instruction.expect("operands are not encodable as c.add"));
+    }
+
+    /// Compressed jump to *rs*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.c_jr(XReg::ra()); // c.jr ra
+    /// stream.c_jalr(XReg::t0()); // c.jalr t0
+    /// stream.c_j(-2048); // c.j -2048
+    /// stream.c_j(2046); // c.j 2046
+    /// stream.c_beqz(XReg::a0(), -256); // c.beqz a0, -256
+    /// stream.c_bnez(XReg::s1(), 254); // c.bnez s1, 254
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x82, 0x80, 0x82, 0x92, 0x01, 0xb0, 0xfd, 0xaf, 0x01, 0xd1, 0xfd, 0xec]);
+    /// ```
+    pub fn c_jr(&mut self, rs: XReg) {
+        self.write_compressed_instruction(encode_cr(0b1000, rs, XReg::zero()).expect("operands are not encodable as c.jr"));
+    }
+
+    /// Compressed jump to *rs*, linking into `ra`.
+    pub fn c_jalr(&mut self, rs: XReg) {
+        self.write_compressed_instruction(encode_cr(0b1001, rs, XReg::zero()).expect("operands are not encodable as c.jalr"));
+    }
+
+    /// Compressed jump to a pc-relative displacement.
+    pub fn c_j(&mut self, offset: i32) {
+        assert!(offset & 1 == 0 && fits_signed(offset as i64, 12), "jump offset {} is misaligned or out of range", offset);
+        self.write_compressed_instruction(0xa001 | cj_imm(offset));
+    }
+
+    /// Compressed jump to a label.
+    ///
+    /// # Panics
+    /// Panics when finished if the label is out of range of the compressed form.
+    pub fn c_j_label(&mut self, label: LabelRef) {
+        self.use_label(label, FixupKind::CompressedJump);
+        self.write_compressed_instruction(0xa001);
+    }
+
+    /// Compressed branch to a displacement if *rs* == 0.
+    pub fn c_beqz(&mut self, rs: XReg, offset: i32) {
+        assert!(rs.is_compressible(), "operands are not encodable as c.beqz");
+        assert!(offset & 1 == 0 && fits_signed(offset as i64, 9), "branch offset {} is misaligned or out of range", offset);
+        self.write_compressed_instruction(0xc001 | ((rs.compressed_offset() as u16) << 7) | cb_imm(offset));
+    }
+
+    /// Compressed branch to a displacement if *rs* != 0.
+    pub fn c_bnez(&mut self, rs: XReg, offset: i32) {
+        assert!(rs.is_compressible(), "operands are not encodable as c.bnez");
+        assert!(offset & 1 == 0 && fits_signed(offset as i64, 9), "branch offset {} is misaligned or out of range", offset);
+        self.write_compressed_instruction(0xe001 | ((rs.compressed_offset() as u16) << 7) | cb_imm(offset));
+    }
+
+    /// Compressed branch to a label if *rs* == 0.
+    ///
+    /// # Panics
+    /// Panics when finished if the label is out of range of the compressed form.
+    pub fn c_beqz_label(&mut self, rs: XReg, label: LabelRef) {
+        assert!(rs.is_compressible(), "operands are not encodable as c.beqz");
+        self.use_label(label, FixupKind::CompressedBranch);
+        self.write_compressed_instruction(0xc001 | ((rs.compressed_offset() as u16) << 7));
+    }
+
+    /// Compressed branch to a label if *rs* != 0.
+    ///
+    /// # Panics
+    /// Panics when finished if the label is out of range of the compressed form.
+    pub fn c_bnez_label(&mut self, rs: XReg, label: LabelRef) {
+        assert!(rs.is_compressible(), "operands are not encodable as c.bnez");
+        self.use_label(label, FixupKind::CompressedBranch);
+        self.write_compressed_instruction(0xe001 | ((rs.compressed_offset() as u16) << 7));
+    }
+
+    /// Compressed load word: *rd* = *\[base + offset\]*, both registers within `x8` to `x15`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_riscv::{register::XReg, stream::Riscv64InstructionStream};
+    ///
+    /// let mut stream = Riscv64InstructionStream::new();
+    /// stream.c_lw(XReg::a0(), XReg::a5(), 124); // c.lw a0, 124(a5)
+    /// stream.c_ld(XReg::s1(), XReg::s0(), 248); // c.ld s1, 248(s0)
+    /// stream.c_sw(XReg::a0(), XReg::a5(), 64); // c.sw a0, 64(a5)
+    /// stream.c_sd(XReg::a5(), XReg::s1(), 8); // c.sd a5, 8(s1)
+    ///
+    /// assert_eq!(stream.finish().emit(), [0xe8, 0x5f, 0x64, 0x7c, 0xa8, 0xc3, 0x9c, 0xe4]);
+    /// ```
+    pub fn c_lw(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_compressed_instruction(encode_c_mem(0b010, 4, rd, base, offset).expect("operands are not encodable as c.lw"));
+    }
+
+    /// Compressed load doubleword.
+    pub fn c_ld(&mut self, rd: XReg, base: XReg, offset: i32) {
+        self.write_compressed_instruction(encode_c_mem(0b011, 8, rd, base, offset).expect("operands are not encodable as c.ld"));
+    }
+
+    /// Compressed store word.
+    pub fn c_sw(&mut self, src: XReg, base: XReg, offset: i32) {
+        self.write_compressed_instruction(encode_c_mem(0b110, 4, src, base, offset).expect("operands are not encodable as c.sw"));
+    }
+
+    /// Compressed store doubleword.
+    pub fn c_sd(&mut self, src: XReg, base: XReg, offset: i32) {
+        self.write_compressed_instruction(encode_c_mem(0b111, 8, src, base, offset).expect("operands are not encodable as c.sd"));
+    }
+
+    /// Environment call.
+    pub fn ecall(&mut self) {
+        self.write_instruction(OPCODE_SYSTEM);
+    }
+
+    /// Environment breakpoint.
+    pub fn ebreak(&mut self) {
+        self.write_instruction((1 << 20) | OPCODE_SYSTEM);
+    }
+}
@@ -0,0 +1,51 @@
+//! LEB128, the variable-length encoding of the integers of the WebAssembly binary format.
+//!
+//! The integer is split into groups of 7 bits, least significant first, each written to a byte whose high bit is set if another byte follows.
+//! The unsigned encoding stops once the remaining bits are zero, and the signed one once they are all copies of the sign bit of the last group.
+//!
+//! # Example
+//! ```
+//! use asmkit_wasm::leb128::{write_signed, write_unsigned};
+//!
+//! let mut bytes = Vec::new();
+//! write_unsigned(&mut bytes, 624485);
+//! assert_eq!(bytes, [0xe5, 0x8e, 0x26]);
+//!
+//! bytes.clear();
+//! write_signed(&mut bytes, -123456);
+//! assert_eq!(bytes, [0xc0, 0xbb, 0x78]);
+//!
+//! bytes.clear();
+//! write_signed(&mut bytes, 64);
+//! assert_eq!(bytes, [0xc0, 0x00]); // the sign bit of 0x40 would make it negative
+//! ```
+
+/// Writes `value` to `bytes` as an unsigned LEB128 integer.
+pub fn write_unsigned(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Writes `value` to `bytes` as a signed LEB128 integer.
+pub fn write_signed(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        // the remaining bits are copies of the sign bit of the byte.
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
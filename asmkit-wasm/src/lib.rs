@@ -0,0 +1,3 @@
+pub mod leb128;
+pub mod module;
+pub mod stream;
\ No newline at end of file
@@ -0,0 +1,149 @@
+//! Modules, wrapping the function bodies written by [`WasmFunctionStream`](crate::stream::WasmFunctionStream) into a WebAssembly binary.
+//!
+//! A [`ModuleBuilder`] holds the functions of the module, their types and the functions it exports, and writes the type, function, export and
+//! code sections of the binary.  The functions are numbered in the order they are added, which is the index [`WasmFunctionStream::call`]
+//! takes.
+//!
+//! [`WasmFunctionStream::call`]: crate::stream::WasmFunctionStream::call
+//!
+//! # Example
+//! A module exporting `add`, adding its two parameters:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_wasm::{module::ModuleBuilder, stream::{ValType, WasmFunctionStream}};
+//!
+//! let mut stream = WasmFunctionStream::with_params(2);
+//! stream.local_get(0);
+//! stream.local_get(1);
+//! stream.i32_add();
+//!
+//! let mut module = ModuleBuilder::new();
+//! let add = module.add_function(&[ValType::I32, ValType::I32], &[ValType::I32], stream.finish());
+//! module.export_function("add", add);
+//!
+//! assert_eq!(module.finish(), [
+//!     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,       // magic, version 1
+//!     0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // type section: (i32, i32) -> i32
+//!     0x03, 0x02, 0x01, 0x00,                               // function section: type 0
+//!     0x07, 0x07, 0x01, 0x03, b'a', b'd', b'd', 0x00, 0x00, // export section: "add", function 0
+//!     0x0a, 0x09, 0x01,                                     // code section
+//!     0x07, 0x00,                                           // body size, no locals
+//!     0x20, 0x00,                                           // local.get 0
+//!     0x20, 0x01,                                           // local.get 1
+//!     0x6a,                                                 // i32.add
+//!     0x0b,                                                 // end
+//! ]);
+//! ```
+
+use asmkit_core::Product;
+
+use crate::{leb128::write_unsigned, stream::ValType};
+
+/// The magic number and the version starting a WebAssembly binary.
+const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// A type of function, its parameters and its results.
+type FuncType = (Vec<ValType>, Vec<ValType>);
+
+/// A builder of WebAssembly modules, see the [module](self) documentation.
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    /// The types of the functions, each listed once.
+    types: Vec<FuncType>,
+
+    /// The index of the type of each function.
+    functions: Vec<u32>,
+
+    /// The body of each function.
+    bodies: Vec<Vec<u8>>,
+
+    /// The exported functions, by name.
+    exports: Vec<(String, u32)>,
+}
+
+impl ModuleBuilder {
+    /// Creates a new, empty module.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function taking `params` and returning `results`, whose body is the product of a
+    /// [`WasmFunctionStream`](crate::stream::WasmFunctionStream), and returns its index.
+    pub fn add_function(&mut self, params: &[ValType], results: &[ValType], body: Product) -> u32 {
+        let ty = (params.to_vec(), results.to_vec());
+        let index = match self.types.iter().position(|other| *other == ty) {
+            Some(index) => index,
+            None => {
+                self.types.push(ty);
+                self.types.len() - 1
+            },
+        };
+
+        self.functions.push(index as u32);
+        self.bodies.push(body.emit());
+        self.functions.len() as u32 - 1
+    }
+
+    /// Exports the function `function` as `name`.
+    ///
+    /// # Panics
+    /// Panics if the module has no function `function`, or if it already exports a function as `name`.
+    pub fn export_function(&mut self, name: &str, function: u32) {
+        assert!((function as usize) < self.functions.len(), "the module has no function {}", function);
+        assert!(self.exports.iter().all(|(other, _)| other != name), "the module already exports `{}`", name);
+        self.exports.push((name.to_string(), function));
+    }
+
+    /// Writes the module as a WebAssembly binary.
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = HEADER.to_vec();
+
+        write_section(&mut bytes, 1, self.types.len(), |section| {
+            for (params, results) in &self.types {
+                section.push(0x60);
+                for list in [params, results] {
+                    write_unsigned(section, list.len() as u64);
+                    section.extend(list.iter().map(ValType::code));
+                }
+            }
+        });
+
+        write_section(&mut bytes, 3, self.functions.len(), |section| {
+            for ty in &self.functions {
+                write_unsigned(section, *ty as u64);
+            }
+        });
+
+        write_section(&mut bytes, 7, self.exports.len(), |section| {
+            for (name, function) in &self.exports {
+                write_unsigned(section, name.len() as u64);
+                section.extend_from_slice(name.as_bytes());
+                section.push(0x00); // a function
+                write_unsigned(section, *function as u64);
+            }
+        });
+
+        write_section(&mut bytes, 10, self.bodies.len(), |section| {
+            for body in &self.bodies {
+                section.extend_from_slice(body);
+            }
+        });
+
+        bytes
+    }
+}
+
+/// Writes the section `id` holding a vector of `len` entries, written by `entries`, unless it has none.
+fn write_section(bytes: &mut Vec<u8>, id: u8, len: usize, entries: impl FnOnce(&mut Vec<u8>)) {
+    if len == 0 {
+        return;
+    }
+
+    let mut section = Vec::new();
+    write_unsigned(&mut section, len as u64);
+    entries(&mut section);
+
+    bytes.push(id);
+    write_unsigned(bytes, section.len() as u64);
+    bytes.extend_from_slice(&section);
+}
@@ -0,0 +1,448 @@
+//! Instruction streaming implementation.
+//!
+//! A [`WasmFunctionStream`] writes the body of a single WebAssembly function: its local variables and its code, an expression of stack machine
+//! instructions.  The branches of WebAssembly are structured, they name an enclosing `block` or `loop` by its depth rather than an offset, so the
+//! labels of the stream are opened by [`WasmFunctionStream::block`] and [`WasmFunctionStream::loop_`], closed by [`WasmFunctionStream::end`],
+//! and [`WasmFunctionStream::br`] and [`WasmFunctionStream::br_if`] turn them into depths.
+//!
+//! # Example
+//! The sum of the integers up to the parameter:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_wasm::stream::{ValType, WasmFunctionStream};
+//!
+//! let mut stream = WasmFunctionStream::with_params(1);
+//! let sum = stream.add_local(ValType::I32);
+//! let exit = stream.create_label();
+//! let top = stream.create_label();
+//!
+//! stream.block(exit, None);
+//! stream.loop_(top, None);
+//! stream.local_get(0);
+//! stream.i32_eqz();
+//! stream.br_if(exit);
+//! stream.local_get(sum);
+//! stream.local_get(0);
+//! stream.i32_add();
+//! stream.local_set(sum);
+//! stream.local_get(0);
+//! stream.i32_const(1);
+//! stream.i32_sub();
+//! stream.local_set(0);
+//! stream.br(top);
+//! stream.end();
+//! stream.end();
+//! stream.local_get(sum);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x21,             // body size
+//!     0x01, 0x01, 0x7f, // 1 local of type i32
+//!     0x02, 0x40,       // block
+//!     0x03, 0x40,       // loop
+//!     0x20, 0x00,       // local.get 0
+//!     0x45,             // i32.eqz
+//!     0x0d, 0x01,       // br_if 1
+//!     0x20, 0x01,       // local.get 1
+//!     0x20, 0x00,       // local.get 0
+//!     0x6a,             // i32.add
+//!     0x21, 0x01,       // local.set 1
+//!     0x20, 0x00,       // local.get 0
+//!     0x41, 0x01,       // i32.const 1
+//!     0x6b,             // i32.sub
+//!     0x21, 0x00,       // local.set 0
+//!     0x0c, 0x00,       // br 0
+//!     0x0b,             // end
+//!     0x0b,             // end
+//!     0x20, 0x01,       // local.get 1
+//!     0x0b,             // end
+//! ]);
+//! ```
+
+use asmkit_core::{
+    entity::{EntityList, Label, LabelRef},
+    raw::Endianness,
+    template::{TemplateFixup, TemplateHoles},
+    InstructionStream, Product,
+};
+
+use crate::leb128::{write_signed, write_unsigned};
+
+/// The type of a value, a local variable, a parameter or a result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValType {
+    /// A 32-bit integer.
+    I32,
+
+    /// A 64-bit integer.
+    I64,
+
+    /// A 32-bit float.
+    F32,
+
+    /// A 64-bit float.
+    F64,
+}
+
+impl ValType {
+    /// The code of this type, for instruction encoding.
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::I32 => 0x7f,
+            Self::I64 => 0x7e,
+            Self::F32 => 0x7d,
+            Self::F64 => 0x7c,
+        }
+    }
+}
+
+/// A structured control instruction being written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Frame {
+    /// A `block`, whose branches go to its end.
+    Block,
+
+    /// A `loop`, whose branches go back to its start.
+    Loop,
+}
+
+/// An instruction stream writing the body of a WebAssembly function.
+pub struct WasmFunctionStream {
+    /// The code written to the instruction stream.
+    bytes: Vec<u8>,
+
+    /// The labels written to the instruction stream, attached at the offset their branches go to within the code.
+    labels: EntityList<Label, LabelRef>,
+
+    /// The number of parameters of the function, numbered before the local variables.
+    params: u32,
+
+    /// The types of the local variables, by index past the parameters.
+    locals: Vec<ValType>,
+
+    /// The blocks and loops being written, outermost first, and their labels.
+    control: Vec<(LabelRef, Frame)>,
+
+    /// The holes of the templates written to the instruction stream.
+    holes: TemplateHoles,
+}
+
+impl InstructionStream for WasmFunctionStream {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.push(Label::Unattached)
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        self.labels.push(Label::Attached(self.bytes.len()))
+    }
+
+    #[inline(always)]
+    fn attach_label(&mut self, label: LabelRef) {
+        *self.labels.get_mut(label) = Label::Attached(self.bytes.len());
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Records the hole of a template, filled when the stream is finished.
+    ///
+    /// # Panics
+    /// Panics if the hole refers to a symbol, which the stream has none of.
+    #[inline(always)]
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        self.holes.push_label(fixup);
+    }
+
+    /// Finishes the function body: its size, its local variables, and its code followed by the `end` of the function.  The holes of the
+    /// templates are filled, and their relocations moved past the size and the local variables.
+    ///
+    /// # Panics
+    /// Panics if a block or a loop is still open, or if the label of a template hole is never attached.
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_wasm::stream::WasmFunctionStream;
+    ///
+    /// let mut stream = WasmFunctionStream::new();
+    /// let label = stream.create_label();
+    /// stream.block(label, None);
+    /// let _ = stream.finish();
+    /// ```
+    fn finish(mut self) -> Product {
+        assert!(self.control.is_empty(), "{} blocks or loops are still open", self.control.len());
+        self.bytes.push(0x0b);
+        let mut relocations = self.holes.resolve(&mut self.bytes, Endianness::Little, |label| self.labels.get(label).offset()).unwrap_or_else(|error| panic!("{}", error));
+
+        // the local variables are declared as runs of the same type.
+        let mut runs: Vec<(u32, ValType)> = Vec::new();
+        for local in &self.locals {
+            match runs.last_mut() {
+                Some((count, ty)) if ty == local => *count += 1,
+                _ => runs.push((1, *local)),
+            }
+        }
+
+        let mut body = Vec::with_capacity(self.bytes.len() + 2 * runs.len() + 1);
+        write_unsigned(&mut body, runs.len() as u64);
+        for (count, ty) in runs {
+            write_unsigned(&mut body, count as u64);
+            body.push(ty.code());
+        }
+        body.extend_from_slice(&self.bytes);
+
+        let mut bytes = Vec::with_capacity(body.len() + 5);
+        write_unsigned(&mut bytes, body.len() as u64);
+        bytes.extend_from_slice(&body);
+
+        // the holes are at their offsets in the code, past the size and the local variables.
+        let header = bytes.len() - self.bytes.len();
+        for relocation in &mut relocations {
+            relocation.offset += header;
+        }
+
+        Product::with_relocations(bytes, relocations, EntityList::new())
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+}
+
+impl Default for WasmFunctionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WasmFunctionStream {
+    /// Creates a new, empty instruction stream, for a function without parameters.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_params(0)
+    }
+
+    /// Creates a new, empty instruction stream, for a function with `params` parameters.
+    #[inline(always)]
+    pub fn with_params(params: u32) -> Self {
+        Self { bytes: Vec::new(), labels: EntityList::new(), params, locals: Vec::new(), control: Vec::new(), holes: TemplateHoles::new() }
+    }
+
+    /// Declares a local variable of type `ty`, and returns its index, past the parameters and the local variables declared before it.
+    pub fn add_local(&mut self, ty: ValType) -> u32 {
+        self.locals.push(ty);
+        self.params + self.locals.len() as u32 - 1
+    }
+
+    /// Writes an opcode followed by its unsigned immediate.
+    fn write_unsigned_op(&mut self, opcode: u8, immediate: u32) {
+        self.bytes.push(opcode);
+        write_unsigned(&mut self.bytes, immediate as u64);
+    }
+
+    /// The depth of the block or the loop of `label` from the innermost one.
+    ///
+    /// # Panics
+    /// Panics if `label` isn't the label of a block or a loop being written.
+    fn depth(&self, label: LabelRef) -> u32 {
+        let depth = self.control.iter().rev().position(|(other, _)| *other == label);
+        depth.unwrap_or_else(|| panic!("label {:?} is not the label of an enclosing block or loop", label)) as u32
+    }
+
+    /// Opens a structured control instruction, with the type of its result.
+    fn open(&mut self, opcode: u8, label: LabelRef, frame: Frame, result: Option<ValType>) {
+        self.bytes.push(opcode);
+        self.bytes.push(result.map_or(0x40, |ty| ty.code()));
+        self.control.push((label, frame));
+    }
+
+    /// Trap immediately, `unreachable`.
+    pub fn unreachable(&mut self) {
+        self.bytes.push(0x00);
+    }
+
+    /// No operation, `nop`.
+    pub fn nop(&mut self) {
+        self.bytes.push(0x01);
+    }
+
+    /// Open a block whose branches go to its end, leaving `result` on the stack if any, `block`.  `label` names the block for
+    /// [`WasmFunctionStream::br`] and [`WasmFunctionStream::br_if`], and is attached at its end.
+    pub fn block(&mut self, label: LabelRef, result: Option<ValType>) {
+        self.open(0x02, label, Frame::Block, result);
+    }
+
+    /// Open a loop whose branches go back to its start, leaving `result` on the stack if any, `loop`.  `label` names the loop for
+    /// [`WasmFunctionStream::br`] and [`WasmFunctionStream::br_if`], and is attached at its start.
+    pub fn loop_(&mut self, label: LabelRef, result: Option<ValType>) {
+        self.attach_label(label);
+        self.open(0x03, label, Frame::Loop, result);
+    }
+
+    /// Close the innermost block or loop, `end`.
+    ///
+    /// # Panics
+    /// Panics if no block or loop is open: the `end` of the function is written by [`InstructionStream::finish`].
+    pub fn end(&mut self) {
+        let (label, frame) = self.control.pop().expect("no block or loop is open");
+        self.bytes.push(0x0b);
+
+        if frame == Frame::Block {
+            self.attach_label(label);
+        }
+    }
+
+    /// Branch to the block or the loop of `label`, `br depth`.
+    ///
+    /// # Panics
+    /// Panics if `label` isn't the label of a block or a loop being written.
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_wasm::stream::WasmFunctionStream;
+    ///
+    /// let mut stream = WasmFunctionStream::new();
+    /// let label = stream.create_label();
+    /// stream.block(label, None);
+    /// stream.end();
+    /// stream.br(label);
+    /// ```
+    pub fn br(&mut self, label: LabelRef) {
+        let depth = self.depth(label);
+        self.write_unsigned_op(0x0c, depth);
+    }
+
+    /// Branch to the block or the loop of `label` if the `i32` popped from the stack isn't zero, `br_if depth`.
+    ///
+    /// # Panics
+    /// Panics if `label` isn't the label of a block or a loop being written.
+    pub fn br_if(&mut self, label: LabelRef) {
+        let depth = self.depth(label);
+        self.write_unsigned_op(0x0d, depth);
+    }
+
+    /// Return from the function, with its results on the stack, `return`.
+    pub fn return_(&mut self) {
+        self.bytes.push(0x0f);
+    }
+
+    /// Call the function `function`, by its index in the module, `call function`.
+    pub fn call(&mut self, function: u32) {
+        self.write_unsigned_op(0x10, function);
+    }
+
+    /// Pop a value from the stack, `drop`.
+    pub fn drop_(&mut self) {
+        self.bytes.push(0x1a);
+    }
+
+    /// Push the local variable or the parameter `local`, `local.get local`.
+    pub fn local_get(&mut self, local: u32) {
+        self.write_unsigned_op(0x20, local);
+    }
+
+    /// Pop a value into the local variable or the parameter `local`, `local.set local`.
+    pub fn local_set(&mut self, local: u32) {
+        self.write_unsigned_op(0x21, local);
+    }
+
+    /// Copy the value on top of the stack into the local variable or the parameter `local`, `local.tee local`.
+    pub fn local_tee(&mut self, local: u32) {
+        self.write_unsigned_op(0x22, local);
+    }
+
+    /// Push the `i32` `value`, `i32.const value`.
+    pub fn i32_const(&mut self, value: i32) {
+        self.bytes.push(0x41);
+        write_signed(&mut self.bytes, value as i64);
+    }
+
+    /// Push the `i64` `value`, `i64.const value`.
+    pub fn i64_const(&mut self, value: i64) {
+        self.bytes.push(0x42);
+        write_signed(&mut self.bytes, value);
+    }
+
+    /// Push 1 if the `i32` popped is zero and 0 otherwise, `i32.eqz`.
+    pub fn i32_eqz(&mut self) {
+        self.bytes.push(0x45);
+    }
+
+    /// Compare two `i32`s for equality, `i32.eq`.
+    pub fn i32_eq(&mut self) {
+        self.bytes.push(0x46);
+    }
+
+    /// Compare two `i32`s for inequality, `i32.ne`.
+    pub fn i32_ne(&mut self) {
+        self.bytes.push(0x47);
+    }
+
+    /// Compare two signed `i32`s, pushing 1 if the first is less than the second, `i32.lt_s`.
+    pub fn i32_lt_s(&mut self) {
+        self.bytes.push(0x48);
+    }
+
+    /// Compare two unsigned `i32`s, pushing 1 if the first is less than the second, `i32.lt_u`.
+    pub fn i32_lt_u(&mut self) {
+        self.bytes.push(0x49);
+    }
+
+    /// Add two `i32`s, wrapping around, `i32.add`.
+    pub fn i32_add(&mut self) {
+        self.bytes.push(0x6a);
+    }
+
+    /// Subtract the `i32` on top of the stack from the one below it, wrapping around, `i32.sub`.
+    pub fn i32_sub(&mut self) {
+        self.bytes.push(0x6b);
+    }
+
+    /// Multiply two `i32`s, wrapping around, `i32.mul`.
+    pub fn i32_mul(&mut self) {
+        self.bytes.push(0x6c);
+    }
+
+    /// Add two `i64`s, wrapping around, `i64.add`.
+    pub fn i64_add(&mut self) {
+        self.bytes.push(0x7c);
+    }
+
+    /// Subtract the `i64` on top of the stack from the one below it, wrapping around, `i64.sub`.
+    pub fn i64_sub(&mut self) {
+        self.bytes.push(0x7d);
+    }
+
+    /// Multiply two `i64`s, wrapping around, `i64.mul`.
+    pub fn i64_mul(&mut self) {
+        self.bytes.push(0x7e);
+    }
+}
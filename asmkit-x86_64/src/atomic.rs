@@ -0,0 +1,162 @@
+//! Atomic operations, following the memory orderings of Rust's atomics.
+//!
+//! x86-64 is strongly ordered: plain loads already have acquire semantics and plain stores release semantics, and every `lock`ed read-modify-write
+//! instruction is a full barrier.  Only sequentially consistent stores and fences need more than the plain instruction, as a store may otherwise be
+//! reordered after a later load.  The sequences chosen for each ordering are the ones `rustc` emits, so code generated through these emitters
+//! interoperates with Rust atomics on the same memory.
+//!
+//! The orderings which Rust rejects for an operation (such as a `Release` load) are rejected here as well, by panicking.
+
+use core::sync::atomic::Ordering;
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Atomically loads *m64* into *r64*.
+    ///
+    /// | Ordering                        | Sequence          |
+    /// |---------------------------------|-------------------|
+    /// | `Relaxed`, `Acquire`, `SeqCst`  | `mov dest, [src]` |
+    ///
+    /// # Panics
+    /// Panics if `ordering` is `Release` or `AcqRel`.
+    pub fn atomic_load_reg64_mem(&mut self, dest: Reg64, src: Mem, ordering: Ordering) {
+        match ordering {
+            Ordering::Release | Ordering::AcqRel => panic!("there is no such thing as a release load"),
+            _ => self.mov_reg64_mem64(dest, src),
+        }
+    }
+
+    /// Atomically stores *r64* into *m64*.
+    ///
+    /// | Ordering               | Sequence           |
+    /// |------------------------|--------------------|
+    /// | `Relaxed`, `Release`   | `mov [dest], src`  |
+    /// | `SeqCst`               | `xchg [dest], src` |
+    ///
+    /// A sequentially consistent store uses `xchg`, which is implicitly locked and so a full barrier, leaving the previous value of *m64* in `src`.  If
+    /// `src` must be preserved, the equivalent but slower `mov` followed by [`x86_64InstructionStream::atomic_fence`] with `SeqCst` may be emitted
+    /// instead.
+    ///
+    /// # Panics
+    /// Panics if `ordering` is `Acquire` or `AcqRel`.
+    ///
+    /// # Example
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    ///
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let store = |ordering| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.atomic_store_mem_reg64(Mem::new(Reg64::Rdi, 0), Reg64::Rax, ordering);
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// assert_eq!(store(Ordering::Relaxed), [0x48, 0x89, 0x07]); // mov qword ptr [rdi], rax
+    /// assert_eq!(store(Ordering::Release), [0x48, 0x89, 0x07]); // mov qword ptr [rdi], rax
+    /// assert_eq!(store(Ordering::SeqCst), [0x48, 0x87, 0x07]);  // xchg qword ptr [rdi], rax
+    /// ```
+    pub fn atomic_store_mem_reg64(&mut self, dest: Mem, src: Reg64, ordering: Ordering) {
+        match ordering {
+            Ordering::Acquire | Ordering::AcqRel => panic!("there is no such thing as an acquire store"),
+            Ordering::SeqCst => self.xchg_mem64_reg64(dest, src),
+            _ => self.mov_mem64_reg64(dest, src),
+        }
+    }
+
+    /// Atomically adds *r64* to *m64*, leaving the previous value of *m64* in *r64*.
+    ///
+    /// | Ordering   | Sequence                |
+    /// |------------|-------------------------|
+    /// | any        | `lock xadd [dest], src` |
+    ///
+    /// # Example
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    ///
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.atomic_fetch_add(Mem::new(Reg64::Rdi, 8), Reg64::Rax, Ordering::Relaxed);
+    /// stream.atomic_fetch_add(Mem::new(Reg64::Rdi, 8), Reg64::Rax, Ordering::SeqCst);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf0, 0x48, 0x0f, 0xc1, 0x47, 0x08, // lock xadd qword ptr [rdi + 8], rax
+    ///     0xf0, 0x48, 0x0f, 0xc1, 0x47, 0x08, // lock xadd qword ptr [rdi + 8], rax
+    /// ]);
+    /// ```
+    pub fn atomic_fetch_add(&mut self, dest: Mem, src: Reg64, _ordering: Ordering) {
+        self.lock();
+        self.xadd_mem64_reg64(dest, src);
+    }
+
+    /// Atomically stores *r64* into *m64* if *m64* equals `rax`, the expected value.
+    ///
+    /// | Ordering   | Sequence                      |
+    /// |------------|-------------------------------|
+    /// | any        | `lock cmpxchg [dest], new`    |
+    ///
+    /// Afterwards `rax` holds the previous value of *m64*, and the zero flag is set if the exchange succeeded.
+    ///
+    /// # Panics
+    /// Panics if `failure` is `Release` or `AcqRel`, as [`AtomicU64::compare_exchange`](core::sync::atomic::AtomicU64::compare_exchange) does.
+    ///
+    /// # Example
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    ///
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.atomic_compare_exchange(Mem::new(Reg64::Rdi, 0), Reg64::Rsi, Ordering::AcqRel, Ordering::Acquire);
+    ///
+    /// assert_eq!(stream.finish().emit(), [0xf0, 0x48, 0x0f, 0xb1, 0x37]); // lock cmpxchg qword ptr [rdi], rsi
+    /// ```
+    pub fn atomic_compare_exchange(&mut self, dest: Mem, new: Reg64, _success: Ordering, failure: Ordering) {
+        if matches!(failure, Ordering::Release | Ordering::AcqRel) {
+            panic!("there is no such thing as a release failure ordering");
+        }
+
+        self.lock();
+        self.cmpxchg_mem64_reg64(dest, new);
+    }
+
+    /// Emits a memory fence.
+    ///
+    /// | Ordering                          | Sequence  |
+    /// |-----------------------------------|-----------|
+    /// | `Acquire`, `Release`, `AcqRel`    | nothing   |
+    /// | `SeqCst`                          | `mfence`  |
+    ///
+    /// The acquire and release fences only restrict the order in which the instructions are generated, which is up to the caller.
+    ///
+    /// # Panics
+    /// Panics if `ordering` is `Relaxed`.
+    ///
+    /// # Example
+    /// ```
+    /// use core::sync::atomic::Ordering;
+    ///
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.atomic_fence(Ordering::AcqRel);
+    /// stream.atomic_fence(Ordering::SeqCst);
+    ///
+    /// assert_eq!(stream.finish().emit(), [0x0f, 0xae, 0xf0]); // mfence
+    /// ```
+    pub fn atomic_fence(&mut self, ordering: Ordering) {
+        match ordering {
+            Ordering::Relaxed => panic!("there is no such thing as a relaxed fence"),
+            Ordering::SeqCst => self.mfence(),
+            _ => {},
+        }
+    }
+}
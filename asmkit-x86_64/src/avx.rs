@@ -0,0 +1,261 @@
+//! The AVX forms of the floating point arithmetic, on `xmm` and `ymm` registers, and the unaligned `ymm` moves.
+//!
+//! The AVX instructions are VEX encoded and take a separate first source, so they don't overwrite their operands as their SSE counterparts do.
+//! The `xmm` forms zero the upper half of the destination `ymm` register.  Mixing them with legacy SSE instructions while the upper halves are
+//! dirty is slow on many processors, so code using `ymm` registers should end with a `vzeroupper`.
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::{RegId, RegSet},
+    encoding::EncodingOptions,
+    features::CpuFeatures,
+    memory::Mem,
+    register::{VectorReg, Xmm, Ymm},
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes a VEX encoded instruction of the `0F` map from the registers `src1` and `src2` to the register `dest`, given by number, with the
+    /// implied prefix `pp` and the vector length `l`.
+    fn write_avx_reg_reg_reg(&mut self, mnemonic: &'static str, pp: u8, opcode: u8, l: bool, (dest, src1, src2): (u8, u8, u8)) {
+        self.require_feature(CpuFeatures::AVX, mnemonic);
+
+        let instruction = self.begin_instruction();
+
+        self.write_vex((dest >= 8, false, src2 >= 8), 0b00001, false, src1, l, pp);
+        self.write_byte(opcode);
+        self.write_byte(0b11000000 | ((dest & 0b111) << 3) | (src2 & 0b111));
+
+        self.record_registers(RegSet::of(RegId::vector(dest)), RegSet::of(RegId::vector(src1)).with(RegId::vector(src2)));
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Add the packed single precision floats of *xmm2* and *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Xmm, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.vaddps_xmm_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3);
+    /// stream.vaddps_ymm_ymm_ymm(Ymm::Ymm9, Ymm::Ymm10, Ymm::Ymm11);
+    /// stream.vaddpd_ymm_ymm_ymm(Ymm::Ymm1, Ymm::Ymm2, Ymm::Ymm12);
+    /// stream.vsubps_xmm_xmm_xmm(Xmm::Xmm8, Xmm::Xmm2, Xmm::Xmm3);
+    /// stream.vsubpd_xmm_xmm_xmm(Xmm::Xmm1, Xmm::Xmm14, Xmm::Xmm3);
+    /// stream.vmulps_ymm_ymm_ymm(Ymm::Ymm0, Ymm::Ymm1, Ymm::Ymm2);
+    /// stream.vmulpd_xmm_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3);
+    /// stream.vdivps_ymm_ymm_ymm(Ymm::Ymm15, Ymm::Ymm0, Ymm::Ymm1);
+    /// stream.vdivpd_ymm_ymm_ymm(Ymm::Ymm1, Ymm::Ymm2, Ymm::Ymm3);
+    /// stream.vzeroupper();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc5, 0xe8, 0x58, 0xcb,       // vaddps xmm1, xmm2, xmm3
+    ///     0xc4, 0x41, 0x2c, 0x58, 0xcb, // vaddps ymm9, ymm10, ymm11
+    ///     0xc4, 0xc1, 0x6d, 0x58, 0xcc, // vaddpd ymm1, ymm2, ymm12
+    ///     0xc5, 0x68, 0x5c, 0xc3,       // vsubps xmm8, xmm2, xmm3
+    ///     0xc5, 0x89, 0x5c, 0xcb,       // vsubpd xmm1, xmm14, xmm3
+    ///     0xc5, 0xf4, 0x59, 0xc2,       // vmulps ymm0, ymm1, ymm2
+    ///     0xc5, 0xe9, 0x59, 0xcb,       // vmulpd xmm1, xmm2, xmm3
+    ///     0xc5, 0x7c, 0x5e, 0xf9,       // vdivps ymm15, ymm0, ymm1
+    ///     0xc5, 0xed, 0x5e, 0xcb,       // vdivpd ymm1, ymm2, ymm3
+    ///     0xc5, 0xf8, 0x77,             // vzeroupper
+    /// ]);
+    /// ```
+    pub fn vaddps_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vaddps", 0b00, 0x58, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Add the packed single precision floats of *ymm2* and *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vaddps_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vaddps", 0b00, 0x58, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Add the packed double precision floats of *xmm2* and *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vaddpd_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vaddpd", 0b01, 0x58, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Add the packed double precision floats of *ymm2* and *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vaddpd_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vaddpd", 0b01, 0x58, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Subtract the packed single precision floats of *xmm3* from *xmm2* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vsubps_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vsubps", 0b00, 0x5c, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Subtract the packed single precision floats of *ymm3* from *ymm2* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vsubps_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vsubps", 0b00, 0x5c, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Subtract the packed double precision floats of *xmm3* from *xmm2* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vsubpd_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vsubpd", 0b01, 0x5c, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Subtract the packed double precision floats of *ymm3* from *ymm2* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vsubpd_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vsubpd", 0b01, 0x5c, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Multiply the packed single precision floats of *xmm2* and *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmulps_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vmulps", 0b00, 0x59, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Multiply the packed single precision floats of *ymm2* and *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmulps_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vmulps", 0b00, 0x59, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Multiply the packed double precision floats of *xmm2* and *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmulpd_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vmulpd", 0b01, 0x59, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Multiply the packed double precision floats of *ymm2* and *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmulpd_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vmulpd", 0b01, 0x59, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Divide the packed single precision floats of *xmm2* by those of *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vdivps_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vdivps", 0b00, 0x5e, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Divide the packed single precision floats of *ymm2* by those of *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vdivps_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vdivps", 0b00, 0x5e, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Divide the packed double precision floats of *xmm2* by those of *xmm3* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vdivpd_xmm_xmm_xmm(&mut self, dest: Xmm, src1: Xmm, src2: Xmm) {
+        self.write_avx_reg_reg_reg("vdivpd", 0b01, 0x5e, false, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Divide the packed double precision floats of *ymm2* by those of *ymm3* into *ymm1*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vdivpd_ymm_ymm_ymm(&mut self, dest: Ymm, src1: Ymm, src2: Ymm) {
+        self.write_avx_reg_reg_reg("vdivpd", 0b01, 0x5e, true, (dest.number(), src1.number(), src2.number()));
+    }
+
+    /// Move the unaligned *m256* to *ymm*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.vmovdqu_ymm_mem(Ymm::Ymm5, Mem::new(Reg64::Rsi, 32));
+    /// stream.vmovdqu_mem_ymm(Mem::new(Reg64::Rdi, 0x100), Ymm::Ymm5);
+    /// stream.vmovdqu_ymm_mem(Ymm::Ymm9, Mem::new(Reg64::R12, 0));
+    /// stream.vmovdqu_mem_ymm(Mem::new(Reg64::R13, 0), Ymm::Ymm1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc5, 0xfe, 0x6f, 0x6e, 0x20,                   // vmovdqu ymm5, ymmword ptr [rsi + 32]
+    ///     0xc5, 0xfe, 0x7f, 0xaf, 0x00, 0x01, 0x00, 0x00, // vmovdqu ymmword ptr [rdi + 256], ymm5
+    ///     0xc4, 0x41, 0x7e, 0x6f, 0x0c, 0x24,             // vmovdqu ymm9, ymmword ptr [r12]
+    ///     0xc4, 0xc1, 0x7e, 0x7f, 0x4d, 0x00,             // vmovdqu ymmword ptr [r13], ymm1
+    /// ]);
+    /// ```
+    pub fn vmovdqu_ymm_mem(&mut self, dest: Ymm, src: Mem) {
+        self.write_avx_ymm_mem("vmovdqu", 0x6f, dest, src);
+    }
+
+    /// Move *ymm* to the unaligned *m256*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmovdqu_mem_ymm(&mut self, dest: Mem, src: Ymm) {
+        self.write_avx_ymm_mem("vmovdqu", 0x7f, src, dest);
+    }
+
+    /// Writes a VEX encoded `F3 0F` move between the register `reg` and the memory operand `mem`.
+    fn write_avx_ymm_mem(&mut self, mnemonic: &'static str, opcode: u8, reg: Ymm, mem: Mem) {
+        self.require_feature(CpuFeatures::AVX, mnemonic);
+
+        let instruction = self.begin_instruction();
+
+        self.write_vex((reg.is_extension(), mem.index.is_some_and(|(index, _)| index.is_extension()), mem.base.is_extension()), 0b00001, false, 0, true, 0b10);
+        self.write_byte(opcode);
+        self.write_modrm_mem(reg.offset(), mem, EncodingOptions::NONE);
+
+        match opcode {
+            0x6f => self.record_registers(RegSet::of(reg), RegSet::from(mem)),
+            _ => self.record_registers(RegSet::EMPTY, RegSet::from(mem).with(reg)),
+        }
+
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Zero the upper halves of the `ymm` registers, avoiding the penalty of legacy SSE instructions following AVX code.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vzeroupper(&mut self) {
+        self.require_feature(CpuFeatures::AVX, "vzeroupper");
+
+        let instruction = self.begin_instruction();
+        self.write_vex((false, false, false), 0b00001, false, 0, false, 0b00);
+        self.write_byte(0x77);
+        // only the upper halves are cleared, which the register sets don't tell apart from the whole registers.
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+}
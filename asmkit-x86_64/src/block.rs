@@ -0,0 +1,578 @@
+//! Basic blocks, structuring the code of a function above labels.
+//!
+//! A block is created with [`create_block`](x86_64InstructionStream::create_block), filled after
+//! [`switch_to_block`](x86_64InstructionStream::switch_to_block), and ended by one of the terminators [`br`](x86_64InstructionStream::br),
+//! [`br_cond`](x86_64InstructionStream::br_cond) or [`ret`](x86_64InstructionStream::ret).  Blocks don't need labels: once every block is
+//! terminated, [`seal_and_layout`](x86_64InstructionStream::seal_and_layout) orders them, places their code after the code written so far, and
+//! emits the terminators, leaving out the jumps to the block which follows.  [`seal_and_layout_by_weight`](x86_64InstructionStream::seal_and_layout_by_weight)
+//! orders them from a profile instead, so the hot paths fall through.
+//!
+//! # Example
+//! A diamond, returning 1 if the argument is zero and 2 otherwise:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let entry = stream.create_block();
+//! let zero = stream.create_block();
+//! let other = stream.create_block();
+//! let join = stream.create_block();
+//!
+//! stream.switch_to_block(entry);
+//! stream.cmp_reg64_imm32(Reg64::Rdi, 0);
+//! stream.br_cond(Condition::E, zero, other);
+//!
+//! stream.switch_to_block(zero);
+//! stream.mov_reg64_imm32(Reg64::Rax, 1);
+//! stream.br(join);
+//!
+//! stream.switch_to_block(other);
+//! stream.mov_reg64_imm32(Reg64::Rax, 2);
+//! stream.br(join);
+//!
+//! stream.switch_to_block(join);
+//! stream.ret();
+//!
+//! // laid out as entry, other, zero, join: the conditional jump falls through to `other`, and `zero` falls through to `join`.
+//! stream.seal_and_layout(None);
+//! let code = stream.finish().emit();
+//!
+//! assert_eq!(code, [
+//!     0x48, 0x83, 0xff, 0x00,                   // cmp rdi, 0
+//!     0x0f, 0x84, 0x0c, 0x00, 0x00, 0x00,       // je zero
+//!     0x48, 0xc7, 0xc0, 0x02, 0x00, 0x00, 0x00, // other: mov rax, 2
+//!     0xe9, 0x07, 0x00, 0x00, 0x00,             // jmp join
+//!     0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00, // zero: mov rax, 1
+//!     0xc3,                                     // join: ret
+//! ]);
+//!
+//! // the jump from `zero` to `join` is left out.
+//! assert_eq!(code.len(), 4 + 6 + 7 + 5 + 7 + 1);
+//!
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! unsafe {
+//!     extern "C" {
+//!         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+//!         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+//!         fn munmap(address: *mut u8, length: usize) -> i32;
+//!     }
+//!
+//!     let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+//!     assert_ne!(memory as isize, -1);
+//!
+//!     std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+//!     assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+//!
+//!     let diamond: extern "C" fn(u64) -> u64 = std::mem::transmute(memory);
+//!     assert_eq!(diamond(0), 1);
+//!     assert_eq!(diamond(7), 2);
+//!
+//!     munmap(memory, 4096);
+//! }
+//! ```
+
+use std::ops::Range;
+
+use asmkit_core::{
+    calls::CallSite,
+    entity::{BlockRef, EntityList, EntityRef, LabelRef, SecondaryMap},
+    fixed::CodeStorage,
+    listing::Comment,
+    patch::{PatchField, PatchPoint},
+    reloc::Relocation,
+    size_report::SizeRegions,
+    source_map::SourceMap,
+    stackmap::StackMaps,
+    InstructionStream,
+};
+
+use crate::{listing::InstructionRecord, stream::{x86_64InstructionStream, Condition, Fixup}};
+
+/// The code written to a basic block, kept apart until the blocks are laid out.
+#[derive(Debug, Default)]
+pub(crate) struct BlockBody {
+    /// The bytes of the block.
+    pub(crate) bytes: Vec<u8>,
+
+    /// The label uses of the block, at offsets within the block.
+    pub(crate) fixups: Vec<Fixup>,
+
+    /// The relocations of the block, at offsets within the block.
+    pub(crate) relocations: Vec<Relocation>,
+
+    /// The labels attached within the block.
+    pub(crate) labels: Vec<LabelRef>,
+
+    /// The comments of the block, at offsets within the block.
+    pub(crate) comments: Vec<Comment>,
+
+    /// The source map of the block, at offsets within the block.
+    pub(crate) source_map: SourceMap,
+
+    /// The size regions of the block, at offsets within the block.
+    pub(crate) size_regions: SizeRegions,
+
+    /// The instructions recorded in the block, at offsets within the block.
+    pub(crate) instructions: Vec<InstructionRecord>,
+
+    /// The atomic regions recorded in the block, at offsets within the block.
+    pub(crate) regions: Vec<Range<usize>>,
+
+    /// The patchable fields of the block, at offsets within the block.
+    pub(crate) patch_fields: Vec<PatchField>,
+
+    /// The call sites of the block, at offsets within the block.
+    pub(crate) call_sites: Vec<CallSite>,
+
+    /// The safepoints of the block, at offsets within the block.
+    pub(crate) stack_maps: StackMaps,
+
+    /// The patch points of the block, at offsets within the block.
+    pub(crate) patch_points: Vec<PatchPoint>,
+}
+
+/// How a basic block ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Terminator {
+    /// Jump to a block.
+    Br(BlockRef),
+
+    /// Jump to the first block if the condition holds, and to the second block otherwise.
+    BrCond(Condition, BlockRef, BlockRef),
+
+    /// Return.
+    Ret,
+}
+
+impl Terminator {
+    /// The blocks reached by the terminator, in order.
+    fn successors(&self) -> Vec<BlockRef> {
+        match *self {
+            Self::Br(target) => vec![target],
+            Self::BrCond(_, then, otherwise) => vec![then, otherwise],
+            Self::Ret => vec![],
+        }
+    }
+}
+
+/// A basic block.
+#[derive(Debug)]
+pub(crate) struct Block {
+    /// The code of the block, while the block isn't the one being written.
+    body: BlockBody,
+
+    /// How the block ends, once terminated.
+    terminator: Option<Terminator>,
+
+    /// The label of the start of the block, created when first needed.
+    label: Option<LabelRef>,
+
+    /// The offset of the start of the block, once laid out.
+    start: Option<usize>,
+}
+
+/// The basic blocks of an instruction stream which are not laid out yet.
+#[derive(Debug, Default)]
+pub(crate) struct Blocks {
+    /// The blocks.
+    pub(crate) list: EntityList<Block, BlockRef>,
+
+    /// The block being written, if any.
+    pub(crate) current: Option<BlockRef>,
+
+    /// The code written outside of the blocks, while a block is being written.
+    outer: BlockBody,
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Creates an empty basic block.  The first block created is the entry of the function, laid out first.
+    pub fn create_block(&mut self) -> BlockRef {
+        self.blocks().list.push(Block { body: BlockBody::default(), terminator: None, label: None, start: None })
+    }
+
+    /// The label of the start of `block`, for the instructions which refer to a block outside of the terminators, such as jump tables.
+    pub fn block_label(&mut self, block: BlockRef) -> LabelRef {
+        if let Some(label) = self.blocks().list.get(block).label {
+            return label;
+        }
+
+        let label = self.create_label();
+        let block = self.blocks().list.get_mut(block);
+        block.label = Some(label);
+
+        if let Some(start) = block.start {
+            self.attach_label_at(label, start);
+        }
+
+        label
+    }
+
+    /// Writes the instructions emitted from now on to `block`.
+    ///
+    /// # Panics
+    /// Panics if `block` is already terminated.
+    pub fn switch_to_block(&mut self, block: BlockRef) {
+        assert!(self.blocks().list.get(block).terminator.is_none(), "block {:?} is already terminated", block);
+
+        self.swap_out_block();
+
+        let mut body = std::mem::take(&mut self.blocks().list.get_mut(block).body);
+        self.swap_body(&mut body);
+        self.blocks().outer = body;
+        self.blocks().current = Some(block);
+    }
+
+    /// Puts the code of the block being written back into its block, and writes to the code outside of the blocks again.
+    fn swap_out_block(&mut self) {
+        let Some(current) = self.blocks().current.take() else {
+            return;
+        };
+
+        let mut body = std::mem::take(&mut self.blocks().outer);
+        self.swap_body(&mut body);
+        self.blocks().list.get_mut(current).body = body;
+    }
+
+    /// Ends the block being written with `terminator`.
+    fn terminate(&mut self, terminator: Terminator) {
+        let current = self.blocks().current.expect("no block is being written, see `switch_to_block`");
+        let block = self.blocks().list.get_mut(current);
+
+        assert!(block.terminator.is_none(), "block {:?} is already terminated", current);
+        block.terminator = Some(terminator);
+    }
+
+    /// Ends the block being written with a jump to `target`.
+    ///
+    /// # Panics
+    /// Panics if no block is being written, or if it's already terminated.
+    pub fn br(&mut self, target: BlockRef) {
+        self.terminate(Terminator::Br(target));
+    }
+
+    /// Ends the block being written with a jump to `then` if `condition` holds, and to `otherwise` if it doesn't.
+    ///
+    /// # Panics
+    /// Panics if no block is being written, or if it's already terminated.
+    ///
+    /// # Example
+    /// A signed `min`, laid out with either block after the comparison: the jump to `less` is inverted to a `jge` when `less` follows.
+    /// ```
+    /// use std::cmp::Ordering;
+    ///
+    /// use asmkit_core::{entity::BlockRef, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// let min = |layout: fn([BlockRef; 3]) -> [BlockRef; 3]| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     let blocks = [(); 3].map(|_| stream.create_block());
+    ///     let [entry, less, other] = blocks;
+    ///
+    ///     stream.switch_to_block(entry);
+    ///     stream.cmp_reg64_reg64(Reg64::Rdi, Reg64::Rsi);
+    ///     stream.br_cond(Condition::from_signed_cmp(Ordering::Less), less, other);
+    ///
+    ///     stream.switch_to_block(less);
+    ///     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+    ///     stream.ret();
+    ///
+    ///     stream.switch_to_block(other);
+    ///     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rsi);
+    ///     stream.ret();
+    ///
+    ///     stream.seal_and_layout(Some(&layout(blocks)));
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// let inverted = min(|[entry, less, other]| [entry, less, other]);
+    /// let kept = min(|[entry, less, other]| [entry, other, less]);
+    /// assert_eq!(inverted[3..5], [0x0f, 0x8d]); // jge other
+    /// assert_eq!(kept[3..5], [0x0f, 0x8c]);     // jl less
+    ///
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// unsafe {
+    ///     extern "C" {
+    ///         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+    ///         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+    ///         fn munmap(address: *mut u8, length: usize) -> i32;
+    ///     }
+    ///
+    ///     for code in [inverted, kept] {
+    ///         let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+    ///         assert_ne!(memory as isize, -1);
+    ///
+    ///         std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+    ///         assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+    ///
+    ///         let min: extern "C" fn(i64, i64) -> i64 = std::mem::transmute(memory);
+    ///         for (a, b) in [(1, 2), (2, 1), (-1, 1), (1, -1), (3, 3), (i64::MIN, i64::MAX)] {
+    ///             assert_eq!(min(a, b), a.min(b));
+    ///         }
+    ///
+    ///         munmap(memory, 4096);
+    ///     }
+    /// }
+    /// ```
+    pub fn br_cond(&mut self, condition: Condition, then: BlockRef, otherwise: BlockRef) {
+        self.terminate(Terminator::BrCond(condition, then, otherwise));
+    }
+
+    /// Ends the block being written with a near return.
+    ///
+    /// # Panics
+    /// Panics if no block is being written, or if it's already terminated.
+    pub fn ret(&mut self) {
+        self.terminate(Terminator::Ret);
+    }
+
+    /// Lays the blocks out after the code written so far, in `order` or in reverse post-order from the entry block, and emits their terminators.
+    /// A jump to the block which follows is left out, and a conditional jump whose taken block follows is inverted.
+    ///
+    /// The blocks are consumed, and writing resumes after the last block.
+    ///
+    /// # Panics
+    /// Panics if a block is not terminated, or if `order` doesn't hold every block exactly once.
+    ///
+    /// # Example
+    /// A loop, with its exit placed before its body:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let [entry, head, body, exit] = [(); 4].map(|_| stream.create_block());
+    ///
+    /// stream.switch_to_block(entry);
+    /// stream.mov_reg64_imm32(Reg64::Rax, 0);
+    /// stream.br(head);
+    ///
+    /// stream.switch_to_block(head);
+    /// stream.cmp_reg64_imm32(Reg64::Rdi, 0);
+    /// stream.br_cond(Condition::E, exit, body);
+    ///
+    /// stream.switch_to_block(body);
+    /// stream.add_reg64_imm32(Reg64::Rax, 1);
+    /// stream.sub_reg64_imm32(Reg64::Rdi, 1);
+    /// stream.br(head);
+    ///
+    /// stream.switch_to_block(exit);
+    /// stream.ret();
+    ///
+    /// stream.seal_and_layout(Some(&[entry, head, exit, body]));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0xc7, 0xc0, 0x00, 0x00, 0x00, 0x00, // mov rax, 0
+    ///     0x48, 0x83, 0xff, 0x00,                   // head: cmp rdi, 0
+    ///     0x0f, 0x85, 0x01, 0x00, 0x00, 0x00,       // jne body
+    ///     0xc3,                                     // exit: ret
+    ///     0x48, 0x83, 0xc0, 0x01,                   // body: add rax, 1
+    ///     0x48, 0x83, 0xef, 0x01,                   // sub rdi, 1
+    ///     0xe9, 0xe8, 0xff, 0xff, 0xff,             // jmp head
+    /// ]);
+    /// ```
+    pub fn seal_and_layout(&mut self, order: Option<&[BlockRef]>) {
+        self.swap_out_block();
+
+        let mut blocks = std::mem::take(&mut self.blocks().list);
+        let order = match order {
+            Some(order) => {
+                let mut placed = vec![false; blocks.len()];
+                for block in order {
+                    assert!(!std::mem::replace(&mut placed[block.as_usize()], true), "block {:?} is laid out twice", block);
+                }
+
+                assert!(placed.iter().all(|placed| *placed), "the layout leaves out blocks");
+                order.to_vec()
+            },
+            None => reverse_post_order(&blocks),
+        };
+
+        for (index, block_ref) in order.iter().enumerate() {
+            let next = order.get(index + 1).copied();
+            let block = blocks.get_mut(*block_ref);
+            let terminator = block.terminator.unwrap_or_else(|| panic!("block {:?} is not terminated", block_ref));
+            let body = std::mem::take(&mut block.body);
+            let label = block.label;
+
+            self.blocks().list = blocks;
+            let start = self.append_body(body);
+            self.blocks().list.get_mut(*block_ref).start = Some(start);
+            if let Some(label) = label {
+                self.attach_label_at(label, start);
+            }
+
+            match terminator {
+                Terminator::Br(target) if Some(target) == next => {},
+                Terminator::Br(target) => {
+                    let label = self.block_label(target);
+                    self.jmp_label(label);
+                },
+                Terminator::BrCond(condition, then, otherwise) if Some(otherwise) == next => {
+                    let label = self.block_label(then);
+                    self.jcc_label(condition, label);
+                },
+                Terminator::BrCond(condition, then, otherwise) if Some(then) == next => {
+                    let label = self.block_label(otherwise);
+                    self.jcc_label(condition.negate(), label);
+                },
+                Terminator::BrCond(condition, then, otherwise) => {
+                    let then = self.block_label(then);
+                    let otherwise = self.block_label(otherwise);
+                    self.jcc_label(condition, then);
+                    self.jmp_label(otherwise);
+                },
+                Terminator::Ret => self.ret_near(),
+            }
+
+            blocks = std::mem::take(&mut self.blocks().list);
+        }
+    }
+
+    /// Lays the blocks out as [`x86_64InstructionStream::seal_and_layout`] does, in an order picked from `weights`, the execution counts of the
+    /// blocks, such as from a profile.  The blocks given no weight count as never executed.
+    ///
+    /// Starting from the entry block, each block is followed by its hottest successor not laid out yet if it is hotter than the block the
+    /// reverse post-order would place next, so the hot paths fall through rather than jump, and by that block otherwise.  The blocks of weight
+    /// 0 are moved to the end, in reverse post-order; under uniform weights, the blocks are laid out in reverse post-order, as without weights.
+    ///
+    /// # Panics
+    /// Panics if a block is not terminated.
+    ///
+    /// # Example
+    /// A diamond whose `else` side is the hot one:
+    /// ```
+    /// use asmkit_core::{entity::SecondaryMap, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// fn diamond(weights: [u32; 4]) -> Vec<u8> {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     let blocks = [(); 4].map(|_| stream.create_block());
+    ///     let [entry, cold, hot, join] = blocks;
+    ///
+    ///     stream.switch_to_block(entry);
+    ///     stream.cmp_reg64_imm32(Reg64::Rdi, 0);
+    ///     stream.br_cond(Condition::E, cold, hot);
+    ///
+    ///     stream.switch_to_block(cold);
+    ///     stream.mov_reg64_imm32(Reg64::Rax, 2);
+    ///     stream.br(join);
+    ///
+    ///     stream.switch_to_block(hot);
+    ///     stream.mov_reg64_imm32(Reg64::Rax, 1);
+    ///     stream.br(join);
+    ///
+    ///     stream.switch_to_block(join);
+    ///     stream.ret();
+    ///
+    ///     let mut map = SecondaryMap::new();
+    ///     for (block, weight) in blocks.into_iter().zip(weights) {
+    ///         map.set(block, weight);
+    ///     }
+    ///
+    ///     stream.seal_and_layout_by_weight(&map);
+    ///     stream.finish().emit()
+    /// }
+    ///
+    /// // under uniform weights, the reverse post-order places `cold` before `join`, so `hot` jumps to it.
+    /// assert_eq!(diamond([1; 4]), [
+    ///     0x48, 0x83, 0xff, 0x00,                   // cmp rdi, 0
+    ///     0x0f, 0x84, 0x0c, 0x00, 0x00, 0x00,       // je cold
+    ///     0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00, // hot: mov rax, 1
+    ///     0xe9, 0x07, 0x00, 0x00, 0x00,             // jmp join
+    ///     0x48, 0xc7, 0xc0, 0x02, 0x00, 0x00, 0x00, // cold: mov rax, 2
+    ///     0xc3,                                     // join: ret
+    /// ]);
+    ///
+    /// // once `hot` is hotter than `cold`, it falls through to `join`, and the jump moves to `cold`.
+    /// assert_eq!(diamond([100, 10, 90, 100]), [
+    ///     0x48, 0x83, 0xff, 0x00,                   // cmp rdi, 0
+    ///     0x0f, 0x84, 0x08, 0x00, 0x00, 0x00,       // je cold
+    ///     0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00, // hot: mov rax, 1
+    ///     0xc3,                                     // join: ret
+    ///     0x48, 0xc7, 0xc0, 0x02, 0x00, 0x00, 0x00, // cold: mov rax, 2
+    ///     0xe9, 0xf3, 0xff, 0xff, 0xff,             // jmp join
+    /// ]);
+    ///
+    /// // the blocks never executed are moved to the end.
+    /// assert_eq!(diamond([100, 0, 100, 100]), diamond([100, 10, 90, 100]));
+    /// ```
+    pub fn seal_and_layout_by_weight(&mut self, weights: &SecondaryMap<BlockRef, u32>) {
+        self.swap_out_block();
+
+        let order = weighted_order(&self.blocks().list, weights);
+        self.seal_and_layout(Some(&order));
+    }
+}
+
+/// The blocks reached by the terminator of `block`, in reverse so the first is visited first.
+fn successors(blocks: &EntityList<Block, BlockRef>, block: BlockRef) -> Vec<BlockRef> {
+    let mut successors = blocks.get(block).terminator.map(|terminator| terminator.successors()).unwrap_or_default();
+    successors.reverse();
+    successors
+}
+
+/// The blocks in reverse post-order from the entry block, followed by the blocks it doesn't reach, in the order they were created.
+fn reverse_post_order(blocks: &EntityList<Block, BlockRef>) -> Vec<BlockRef> {
+    let mut visited = vec![false; blocks.len()];
+    let mut post_order = Vec::with_capacity(blocks.len());
+
+    if !blocks.is_empty() {
+        // each entry holds a block and the successors left to visit.
+        let entry = BlockRef::new(0);
+        let mut stack = vec![(entry, successors(blocks, entry))];
+        visited[0] = true;
+
+        while let Some((block, pending)) = stack.last_mut() {
+            match pending.pop() {
+                Some(successor) if !visited[successor.as_usize()] => {
+                    visited[successor.as_usize()] = true;
+                    stack.push((successor, successors(blocks, successor)));
+                },
+                Some(_) => {},
+                None => {
+                    post_order.push(*block);
+                    stack.pop();
+                },
+            }
+        }
+    }
+
+    post_order.reverse();
+    post_order.extend((0..blocks.len()).filter(|index| !visited[*index]).map(BlockRef::new));
+    post_order
+}
+
+/// The blocks from the entry block, each followed by its hottest successor if it is hotter than the next block of the reverse post-order, with
+/// the blocks of weight 0 moved to the end, see [`x86_64InstructionStream::seal_and_layout_by_weight`].
+fn weighted_order(blocks: &EntityList<Block, BlockRef>, weights: &SecondaryMap<BlockRef, u32>) -> Vec<BlockRef> {
+    let weight = |block: BlockRef| *weights.get(block);
+
+    // the entry block stays first, even if it's cold.
+    let (mut fallback, cold): (Vec<_>, Vec<_>) = reverse_post_order(blocks).into_iter().partition(|&block| block.as_usize() == 0 || weight(block) > 0);
+    fallback.extend(cold);
+
+    let mut placed = vec![false; blocks.len()];
+    let mut order = Vec::with_capacity(blocks.len());
+    let mut next = fallback.first().copied();
+
+    while let Some(block) = next {
+        placed[block.as_usize()] = true;
+        order.push(block);
+
+        let default = fallback.iter().copied().find(|block| !placed[block.as_usize()]);
+        let hottest = successors(blocks, block).into_iter().rev()
+            .filter(|successor| !placed[successor.as_usize()])
+            .fold(None, |hottest: Option<BlockRef>, successor| match hottest {
+                Some(hottest) if weight(hottest) >= weight(successor) => Some(hottest),
+                _ => Some(successor),
+            });
+
+        next = match (hottest, default) {
+            (Some(hottest), Some(default)) if weight(hottest) > weight(default) => Some(hottest),
+            _ => default,
+        };
+    }
+
+    order
+}
@@ -0,0 +1,270 @@
+//! Calling conventions, and saving the registers a call may clobber.
+//!
+//! [`CallConv`] lists which registers a called function may clobber (the caller-saved, or volatile, registers) and which it must preserve (the
+//! callee-saved registers).  [`x86_64InstructionStream::save_caller_saved`] and [`x86_64InstructionStream::restore_caller_saved`] use it to wrap a
+//! call made from code which can't tell which registers are live, such as an FFI shim.
+
+use asmkit_core::target::{CallingConvention, Target};
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{
+    memory::Mem,
+    register::{Reg64, Xmm},
+    stream::x86_64InstructionStream,
+};
+
+/// A calling convention of x86-64.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallConv {
+    /// The System V AMD64 ABI, used by Linux, macOS and the BSDs.
+    SysV,
+
+    /// The Microsoft x64 calling convention, used by Windows.
+    Win64,
+}
+
+impl CallConv {
+    /// The default calling convention of the C functions of `target`: [`CallConv::Win64`] on Windows, [`CallConv::SysV`] elsewhere.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::target::Target;
+    /// use asmkit_x86_64::callconv::CallConv;
+    ///
+    /// assert_eq!(CallConv::from_target(&Target::parse("x86_64-pc-windows-gnu").unwrap()), CallConv::Win64);
+    /// assert_eq!(CallConv::from_target(&Target::parse("x86_64-apple-darwin").unwrap()), CallConv::SysV);
+    /// ```
+    pub fn from_target(target: &Target) -> Self {
+        match target.calling_convention {
+            CallingConvention::Win64 => Self::Win64,
+            _ => Self::SysV,
+        }
+    }
+
+    /// The general purpose registers a called function may clobber, in the order they are pushed by
+    /// [`x86_64InstructionStream::save_caller_saved`].
+    pub fn caller_saved(&self) -> &'static [Reg64] {
+        match self {
+            Self::SysV => &[Reg64::Rax, Reg64::Rcx, Reg64::Rdx, Reg64::Rsi, Reg64::Rdi, Reg64::R8, Reg64::R9, Reg64::R10, Reg64::R11],
+            Self::Win64 => &[Reg64::Rax, Reg64::Rcx, Reg64::Rdx, Reg64::R8, Reg64::R9, Reg64::R10, Reg64::R11],
+        }
+    }
+
+    /// The `xmm` registers a called function may clobber.  Only the low 128 bits of `xmm6` to `xmm15` are preserved under [`CallConv::Win64`].
+    pub fn caller_saved_xmm(&self) -> &'static [Xmm] {
+        match self {
+            Self::SysV => &[
+                Xmm::Xmm0, Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3, Xmm::Xmm4, Xmm::Xmm5, Xmm::Xmm6, Xmm::Xmm7,
+                Xmm::Xmm8, Xmm::Xmm9, Xmm::Xmm10, Xmm::Xmm11, Xmm::Xmm12, Xmm::Xmm13, Xmm::Xmm14, Xmm::Xmm15,
+            ],
+            Self::Win64 => &[Xmm::Xmm0, Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3, Xmm::Xmm4, Xmm::Xmm5],
+        }
+    }
+
+    /// The general purpose registers a called function must preserve, besides `rsp`.
+    pub fn callee_saved(&self) -> &'static [Reg64] {
+        match self {
+            Self::SysV => &[Reg64::Rbx, Reg64::Rbp, Reg64::R12, Reg64::R13, Reg64::R14, Reg64::R15],
+            Self::Win64 => &[Reg64::Rbx, Reg64::Rbp, Reg64::Rdi, Reg64::Rsi, Reg64::R12, Reg64::R13, Reg64::R14, Reg64::R15],
+        }
+    }
+}
+
+/// The registers saved by [`x86_64InstructionStream::save_caller_saved`], to be passed to [`x86_64InstructionStream::restore_caller_saved`].
+#[must_use = "the saved registers must be restored with `restore_caller_saved`"]
+#[derive(Debug, PartialEq, Eq)]
+pub struct SavedRegs {
+    /// The calling convention whose registers are saved.
+    convention: CallConv,
+
+    /// Whether the `xmm` registers are saved.
+    include_xmm: bool,
+
+    /// The number of saves not restored yet, including this one.
+    depth: usize,
+}
+
+impl SavedRegs {
+    /// The calling convention whose registers are saved.
+    pub fn convention(&self) -> CallConv {
+        self.convention
+    }
+
+    /// The number of bytes the save moves `rsp` down by, a multiple of 16.
+    pub fn stack_size(&self) -> u32 {
+        let pushed = 8 * self.convention.caller_saved().len() as u32;
+        let xmm = if self.include_xmm { 16 * self.convention.caller_saved_xmm().len() as u32 } else { 0 };
+        (pushed + xmm + 15) & !15
+    }
+
+    /// The bytes below the pushed registers: the padding keeping `rsp` aligned, and the `xmm` registers.
+    fn area_size(&self) -> u32 {
+        self.stack_size() - 8 * self.convention.caller_saved().len() as u32
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Saves the registers `convention` lets a called function clobber, and the `xmm` ones if `include_xmm` is set.
+    ///
+    /// The general purpose registers are pushed in the order of [`CallConv::caller_saved`], then `rsp` is moved down past the `xmm` registers, which
+    /// can't be pushed, and the padding keeping `rsp` as aligned as it was, and the `xmm` registers are stored in the order of
+    /// [`CallConv::caller_saved_xmm`] from `[rsp]` up.  `rsp` thus stays 16-byte aligned if it was, as a call requires.
+    ///
+    /// The registers are restored by [`x86_64InstructionStream::restore_caller_saved`], `rax` included, so a return value must be moved out of
+    /// them before.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{callconv::CallConv, memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let saved = stream.save_caller_saved(CallConv::SysV, false);
+    /// stream.call_reg64(Reg64::Rbx);
+    /// stream.restore_caller_saved(saved);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x50, 0x51, 0x52, 0x56, 0x57,       // push rax, rcx, rdx, rsi, rdi
+    ///     0x41, 0x50, 0x41, 0x51,             // push r8, r9
+    ///     0x41, 0x52, 0x41, 0x53,             // push r10, r11
+    ///     0x48, 0x83, 0xec, 0x08,             // sub rsp, 8
+    ///     0xff, 0xd3,                         // call rbx
+    ///     0x48, 0x83, 0xc4, 0x08,             // add rsp, 8
+    ///     0x41, 0x5b, 0x41, 0x5a,             // pop r11, r10
+    ///     0x41, 0x59, 0x41, 0x58,             // pop r9, r8
+    ///     0x5f, 0x5e, 0x5a, 0x59, 0x58,       // pop rdi, rsi, rdx, rcx, rax
+    /// ]);
+    ///
+    /// // with the `xmm` registers, stored below the pushed ones.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let saved = stream.save_caller_saved(CallConv::SysV, true);
+    /// assert_eq!(saved.stack_size(), 9 * 8 + 8 + 16 * 16);
+    /// stream.restore_caller_saved(saved);
+    ///
+    /// let mut expected = x86_64InstructionStream::new();
+    /// let gprs = [Reg64::Rax, Reg64::Rcx, Reg64::Rdx, Reg64::Rsi, Reg64::Rdi, Reg64::R8, Reg64::R9, Reg64::R10, Reg64::R11];
+    /// for reg in gprs {
+    ///     expected.push_reg64(reg);
+    /// }
+    /// expected.sub_reg64_imm32(Reg64::Rsp, 8 + 16 * 16);
+    /// for (index, xmm) in CallConv::SysV.caller_saved_xmm().iter().enumerate() {
+    ///     expected.movdqu_mem_xmm(Mem::new(Reg64::Rsp, 16 * index as i32), *xmm);
+    /// }
+    /// for (index, xmm) in CallConv::SysV.caller_saved_xmm().iter().enumerate() {
+    ///     expected.movdqu_xmm_mem(*xmm, Mem::new(Reg64::Rsp, 16 * index as i32));
+    /// }
+    /// expected.add_reg64_imm32(Reg64::Rsp, 8 + 16 * 16);
+    /// for reg in gprs.into_iter().rev() {
+    ///     expected.pop_reg64(reg);
+    /// }
+    ///
+    /// assert_eq!(stream.finish(), expected.finish());
+    /// ```
+    ///
+    /// Executed, the registers and `rsp` are back to their values before the save:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{callconv::CallConv, register::{Reg64, Xmm}, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// // fn(u64) -> u64, returning its argument if the registers are restored.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let mismatch = stream.create_label();
+    /// stream.mov_reg64_reg64(Reg64::Rcx, Reg64::Rsp);
+    /// stream.movq_xmm_reg64(Xmm::Xmm15, Reg64::Rdi);
+    ///
+    /// let saved = stream.save_caller_saved(CallConv::SysV, true);
+    /// stream.mov_reg64_imm32(Reg64::Rcx, 0);
+    /// stream.mov_reg64_imm32(Reg64::Rdi, 0);
+    /// stream.movq_xmm_reg64(Xmm::Xmm15, Reg64::Rdi);
+    /// stream.restore_caller_saved(saved);
+    ///
+    /// stream.cmp_jcc(Reg64::Rcx, Reg64::Rsp, Condition::Ne, mismatch);
+    /// stream.movq_reg64_xmm(Reg64::Rax, Xmm::Xmm15);
+    /// stream.cmp_jcc(Reg64::Rax, Reg64::Rdi, Condition::Ne, mismatch);
+    /// stream.ret_near();
+    ///
+    /// stream.attach_label(mismatch);
+    /// stream.mov_reg64_imm32(Reg64::Rax, 0);
+    /// stream.ret_near();
+    ///
+    /// let code = stream.finish().emit();
+    ///
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// unsafe {
+    ///     extern "C" {
+    ///         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+    ///         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+    ///         fn munmap(address: *mut u8, length: usize) -> i32;
+    ///     }
+    ///
+    ///     let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+    ///     assert_ne!(memory as isize, -1);
+    ///
+    ///     std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+    ///     assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+    ///
+    ///     let round_trip: extern "C" fn(u64) -> u64 = std::mem::transmute(memory);
+    ///     assert_eq!(round_trip(42), 42);
+    ///     assert_eq!(round_trip(u64::MAX), u64::MAX);
+    ///
+    ///     munmap(memory, 4096);
+    /// }
+    /// ```
+    pub fn save_caller_saved(&mut self, convention: CallConv, include_xmm: bool) -> SavedRegs {
+        let depth = self.save_depth();
+        *depth += 1;
+        let saved = SavedRegs { convention, include_xmm, depth: *depth };
+
+        for reg in convention.caller_saved() {
+            self.push_reg64(*reg);
+        }
+
+        if saved.area_size() != 0 {
+            self.sub_reg64_imm32_unsigned(Reg64::Rsp, saved.area_size());
+        }
+
+        if include_xmm {
+            for (index, xmm) in convention.caller_saved_xmm().iter().enumerate() {
+                self.movdqu_mem_xmm(Mem::new(Reg64::Rsp, 16 * index as i32), *xmm);
+            }
+        }
+
+        saved
+    }
+
+    /// Restores the registers saved by [`x86_64InstructionStream::save_caller_saved`], in the reverse order, moving `rsp` back to its value before
+    /// the save.
+    ///
+    /// # Panics
+    /// Panics if `saved` is not the latest save which isn't restored yet, as the saves are on the stack.
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{callconv::CallConv, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let outer = stream.save_caller_saved(CallConv::SysV, false);
+    /// let inner = stream.save_caller_saved(CallConv::SysV, true);
+    /// stream.restore_caller_saved(outer);
+    /// ```
+    pub fn restore_caller_saved(&mut self, saved: SavedRegs) {
+        let depth = self.save_depth();
+        assert_eq!(saved.depth, *depth, "registers are restored out of order, the latest save must be restored first");
+        *depth -= 1;
+
+        if saved.include_xmm {
+            for (index, xmm) in saved.convention.caller_saved_xmm().iter().enumerate() {
+                self.movdqu_xmm_mem(*xmm, Mem::new(Reg64::Rsp, 16 * index as i32));
+            }
+        }
+
+        if saved.area_size() != 0 {
+            self.add_reg64_imm32_unsigned(Reg64::Rsp, saved.area_size());
+        }
+
+        for reg in saved.convention.caller_saved().iter().rev() {
+            self.pop_reg64(*reg);
+        }
+    }
+}
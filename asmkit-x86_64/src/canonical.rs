@@ -0,0 +1,153 @@
+//! Canonical encodings, so the same program gives the same bytes whichever of its equivalent encodings the emitters were asked for.
+//!
+//! Under [`Canonicalization::Minimal`], set by [`set_canonicalization`](crate::stream::x86_64InstructionStream::set_canonicalization), each
+//! instruction is re-encoded as it is recorded, before any label is attached after it, so the label uses and relocations of the following code
+//! are laid out from the canonical bytes:
+//!
+//! - the register to register forms of `mov` and of the arithmetic instructions take their `r/m, r` opcode, such as `89 /r` rather than
+//!   `8b /r` for `mov`, and drop a REX prefix which sets no field and selects no byte register;
+//! - the `nop`s other than the single recommended ones, such as those lengthened by
+//!   [`LONG_NOP_STYLE`](crate::encoding::EncodingOptions::LONG_NOP_STYLE), are rewritten as the recommended sequence of the same length.
+//!
+//! The displacements and immediates widened for patching are recorded as patch fields of the product, and are kept as emitted.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{
+//!     canonical::Canonicalization,
+//!     encoding::EncodingOptions,
+//!     register::{Reg32, Reg64},
+//!     stream::x86_64InstructionStream,
+//! };
+//!
+//! // the same program, with the default encodings or with equivalent ones.
+//! let emit = |canonicalization, reversed: bool| {
+//!     let mut stream = x86_64InstructionStream::new();
+//!     stream.set_canonicalization(canonicalization);
+//!     let done = stream.create_label();
+//!
+//!     if reversed {
+//!         stream.mov_reg64_reg64_with_options(Reg64::Rax, Reg64::Rdi, EncodingOptions::REVERSED_OPERANDS);
+//!         stream.mov_reg32_reg32_with_options(Reg32::Ecx, Reg32::Eax, EncodingOptions::REVERSED_OPERANDS | EncodingOptions::FORCE_REX);
+//!         stream.nop_with_options(11, EncodingOptions::LONG_NOP_STYLE);
+//!     } else {
+//!         stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+//!         stream.mov_reg32_reg32(Reg32::Ecx, Reg32::Eax);
+//!         stream.nop(11);
+//!     }
+//!
+//!     stream.jmp_label(done);
+//!     stream.attach_label(done);
+//!     stream.ret_near();
+//!     stream.finish()
+//! };
+//!
+//! let emitted = emit(Canonicalization::AsEmitted, true);
+//! assert_eq!(emitted.bytes(), [
+//!     0x48, 0x8b, 0xc7,                                                 // mov rax, rdi
+//!     0x40, 0x8b, 0xc8,                                                 // mov ecx, eax
+//!     0x66, 0x66, 0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+//!     0xe9, 0x00, 0x00, 0x00, 0x00,                                     // jmp done
+//!     0xc3,                                                             // done: ret
+//! ]);
+//!
+//! let canonical = emit(Canonicalization::Minimal, true);
+//! assert_eq!(canonical.bytes(), [
+//!     0x48, 0x89, 0xf8,                                     // mov rax, rdi
+//!     0x89, 0xc1,                                           // mov ecx, eax
+//!     0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+//!     0x66, 0x90,                                           // xchg ax, ax
+//!     0xe9, 0x00, 0x00, 0x00, 0x00,                         // jmp done
+//!     0xc3,                                                 // done: ret
+//! ]);
+//!
+//! // the default encodings are already canonical.
+//! assert_eq!(emit(Canonicalization::Minimal, false).bytes(), canonical.bytes());
+//! assert_eq!(emit(Canonicalization::AsEmitted, false).bytes(), canonical.bytes());
+//! assert_eq!(emit(Canonicalization::AsEmitted, false).content_hash(), canonical.content_hash());
+//! assert_ne!(emitted.content_hash(), canonical.content_hash());
+//! ```
+
+use crate::{
+    stats::InstructionFamily,
+    stream::{NOPS, REX, REX_B, REX_R, REX_X},
+};
+
+/// Whether the instructions are re-encoded in a canonical form, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Canonicalization {
+    /// The instructions are left as emitted.
+    #[default]
+    AsEmitted,
+
+    /// The instructions are re-encoded in their canonical form, with the fewest prefixes and the preferred opcode.
+    Minimal,
+}
+
+/// The canonical form of the instruction `bytes` of the family `family`, if it isn't canonical already.  `longest_nop` is the length of the
+/// longest `nop` available in the mode of the stream.
+pub(crate) fn canonical_form(bytes: &[u8], family: InstructionFamily, longest_nop: usize) -> Option<Vec<u8>> {
+    match family {
+        InstructionFamily::Nop => canonical_nops(bytes, longest_nop),
+        _ => canonical_reg_reg(bytes),
+    }
+}
+
+/// The recommended `nop`s of the length of `bytes`, unless `bytes` is already a single recommended `nop`, as the patch points are.
+fn canonical_nops(bytes: &[u8], longest_nop: usize) -> Option<Vec<u8>> {
+    if bytes.len() <= NOPS.len() && bytes == NOPS[bytes.len() - 1] {
+        return None;
+    }
+
+    let mut canonical = Vec::with_capacity(bytes.len());
+    while canonical.len() < bytes.len() {
+        canonical.extend_from_slice(NOPS[(bytes.len() - canonical.len()).min(longest_nop) - 1]);
+    }
+
+    (canonical != bytes).then_some(canonical)
+}
+
+/// The `r/m, r` form of a register to register `mov` or arithmetic instruction, with the fewest prefixes.
+fn canonical_reg_reg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (operand_size, rest) = match bytes {
+        [0x66, rest @ ..] => (true, rest),
+        _ => (false, bytes),
+    };
+
+    let (rex, rest) = match rest {
+        [rex @ 0x40..=0x4f, rest @ ..] => (*rex, rest),
+        _ => (REX, rest),
+    };
+
+    // the arithmetic opcodes `00` to `3b` in their four register forms, and the `mov`s `88` to `8b`.
+    let &[mut opcode, mut modrm] = rest else { return None };
+    if !((opcode < 0x40 && opcode & 0b111 < 4) || (0x88..=0x8b).contains(&opcode)) || modrm >> 6 != 0b11 {
+        return None;
+    }
+
+    // no index register, the X bit of the prefix is meaningless.
+    let mut rex = rex & !REX_X;
+
+    if opcode & 0b10 != 0 {
+        opcode &= !0b10;
+        modrm = (0b11 << 6) | ((modrm & 0b111) << 3) | ((modrm >> 3) & 0b111);
+        rex = (rex & !(REX_R | REX_B)) | ((rex & REX_R) >> 2) | ((rex & REX_B) << 2);
+    }
+
+    // without a REX prefix, the byte registers 4 to 7 are `ah` to `bh` rather than `spl` to `dil`.
+    let is_byte = opcode & 1 == 0;
+    let needs_rex = rex != REX || (is_byte && ((modrm >> 3) & 0b111 >= 4 || modrm & 0b111 >= 4));
+
+    let mut canonical = Vec::with_capacity(4);
+    if operand_size {
+        canonical.push(0x66);
+    }
+
+    if needs_rex {
+        canonical.push(rex);
+    }
+
+    canonical.extend_from_slice(&[opcode, modrm]);
+    (canonical != bytes).then_some(canonical)
+}
@@ -0,0 +1,109 @@
+//! The AES-NI and carry-less multiplication instructions.
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{features::CpuFeatures, register::Xmm, stream::x86_64InstructionStream};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Perform one round of AES encryption of the state in *xmm1* with the round key in *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.aesenc_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.aesenclast_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2);
+    /// stream.aesdec_xmm_xmm(Xmm::Xmm1, Xmm::Xmm10);
+    /// stream.aesdeclast_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.aesimc_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x38, 0xdc, 0xca,       // aesenc xmm1, xmm2
+    ///     0x66, 0x44, 0x0f, 0x38, 0xdd, 0xca, // aesenclast xmm9, xmm2
+    ///     0x66, 0x41, 0x0f, 0x38, 0xde, 0xca, // aesdec xmm1, xmm10
+    ///     0x66, 0x0f, 0x38, 0xdf, 0xca,       // aesdeclast xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0xdb, 0xca,       // aesimc xmm1, xmm2
+    /// ]);
+    /// ```
+    pub fn aesenc_xmm_xmm(&mut self, state: Xmm, key: Xmm) {
+        self.require_feature(CpuFeatures::AES, "aesenc");
+        self.write_vector_xmm_xmm(&[0x38, 0xdc], state, key, None);
+    }
+
+    /// Perform the last round of AES encryption of the state in *xmm1* with the round key in *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn aesenclast_xmm_xmm(&mut self, state: Xmm, key: Xmm) {
+        self.require_feature(CpuFeatures::AES, "aesenclast");
+        self.write_vector_xmm_xmm(&[0x38, 0xdd], state, key, None);
+    }
+
+    /// Perform one round of AES decryption of the state in *xmm1* with the round key in *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn aesdec_xmm_xmm(&mut self, state: Xmm, key: Xmm) {
+        self.require_feature(CpuFeatures::AES, "aesdec");
+        self.write_vector_xmm_xmm(&[0x38, 0xde], state, key, None);
+    }
+
+    /// Perform the last round of AES decryption of the state in *xmm1* with the round key in *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn aesdeclast_xmm_xmm(&mut self, state: Xmm, key: Xmm) {
+        self.require_feature(CpuFeatures::AES, "aesdeclast");
+        self.write_vector_xmm_xmm(&[0x38, 0xdf], state, key, None);
+    }
+
+    /// Perform the AES inverse mix columns transformation of the round key in *xmm2* into *xmm1*, for the decryption key schedule.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn aesimc_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::AES, "aesimc");
+        self.write_vector_xmm_xmm(&[0x38, 0xdb], dest, src, None);
+    }
+
+    /// Assist in expanding the AES round key in *xmm2* into *xmm1*, with the round constant *imm8*.
+    ///
+    /// # Panics
+    /// Panics if AES-NI isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.aeskeygenassist_xmm_xmm_imm8(Xmm::Xmm1, Xmm::Xmm2, 0x1b);
+    /// stream.pclmulqdq_xmm_xmm_imm8(Xmm::Xmm1, Xmm::Xmm2, 0x11);
+    /// stream.pclmulqdq_xmm_xmm_imm8(Xmm::Xmm11, Xmm::Xmm12, 0x00);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x3a, 0xdf, 0xca, 0x1b,       // aeskeygenassist xmm1, xmm2, 27
+    ///     0x66, 0x0f, 0x3a, 0x44, 0xca, 0x11,       // pclmulqdq xmm1, xmm2, 17
+    ///     0x66, 0x45, 0x0f, 0x3a, 0x44, 0xdc, 0x00, // pclmulqdq xmm11, xmm12, 0
+    /// ]);
+    /// ```
+    pub fn aeskeygenassist_xmm_xmm_imm8(&mut self, dest: Xmm, src: Xmm, imm8: u8) {
+        self.require_feature(CpuFeatures::AES, "aeskeygenassist");
+        self.write_vector_xmm_xmm(&[0x3a, 0xdf], dest, src, Some(imm8));
+    }
+
+    /// Carry-less multiply a quadword of *xmm1* by a quadword of *xmm2* into *xmm1*, bit 0 of *imm8* selecting the quadword of *xmm1* and bit 4 the
+    /// quadword of *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if PCLMULQDQ isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pclmulqdq_xmm_xmm_imm8(&mut self, dest: Xmm, src: Xmm, imm8: u8) {
+        self.require_feature(CpuFeatures::PCLMULQDQ, "pclmulqdq");
+        self.write_vector_xmm_xmm(&[0x3a, 0x44], dest, src, Some(imm8));
+    }
+}
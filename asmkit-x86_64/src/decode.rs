@@ -0,0 +1,807 @@
+//! A decoder for the instruction forms of the [`registry`](crate::registry), reading back the 64-bit code asmkit emits without an external
+//! disassembler.
+//!
+//! [`decode`] reads a single instruction into a [`DecodedInsn`], made of the [`InstructionForm`] of the registry which encodes it and of its
+//! [`Operand`]s, so decoding and re-emitting an instruction through its form gives back its bytes.  [`decode_all`] reads the whole code of a
+//! product.  The bytes outside of the registry, such as the constant pool, aren't decoded.
+//!
+//! The decoder is driven by the registry: the first [`decode`] emits each form with the [example operands](InstructionForm::example_operands)
+//! of a few seeds, and learns from the bytes which field of the instruction encodes each operand, and which fields are fixed by the opcode.  A
+//! form added to the registry is decoded without further work.  Instructions are then read in two steps, the prefixes, opcode, ModRM byte and
+//! displacement first, which have the same layout for every instruction, then the immediates of the first form whose fixed fields match.
+//!
+//! The operands are those the emitters take: the immediates are read as the integer type of their emitter, zero extended for the unsigned ones,
+//! a label is read as the displacement of the branch from the end of the instruction, as an [`Operand::Relative`], and a label or symbol in
+//! memory as a `rip`-relative [`Memory`] operand.  The register to register forms are also decoded from their `r, r/m` opcode, such as
+//! `8b /r` for `mov`.  The prefixes the registry writes on their own, such as `lock`, are read as instructions of their own.  The forms
+//! unavailable in long mode aren't decoded.
+//!
+//! # Example
+//! Every form of the registry decodes back from the bytes of random operands:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{
+//!     decode::decode,
+//!     mode::Mode,
+//!     registry::{Memory, MemoryTarget, Operand, OperandKind, INSTRUCTIONS},
+//!     stream::x86_64InstructionStream,
+//! };
+//!
+//! let mut state = 0x2545_f491_4f6c_dd1du64;
+//! let mut random = move || {
+//!     state ^= state << 13;
+//!     state ^= state >> 7;
+//!     state ^= state << 17;
+//!     state
+//! };
+//!
+//! for form in INSTRUCTIONS.iter().filter(|form| form.modes.contains(&Mode::Long)) {
+//!     for _ in 0..64 {
+//!         let mut stream = x86_64InstructionStream::new();
+//!         let operands = form.example_operands(&mut stream, random());
+//!         (form.emit)(&mut stream, &operands);
+//!         let bytes = stream.finish().emit();
+//!
+//!         // `o32` writes nothing in long mode.
+//!         if bytes.is_empty() {
+//!             continue;
+//!         }
+//!
+//!         let (decoded, len) = decode(&bytes).unwrap_or_else(|error| panic!("`{}` {:02x?}: {}", form.emitter, bytes, error));
+//!         assert_eq!(len, bytes.len(), "`{}` {:02x?}", form.emitter, bytes);
+//!
+//!         // the labels are attached at the start of the instruction, and re-emitted there, and the symbols, left to relocations, are
+//!         // re-emitted as labels attached at its end.
+//!         let mut again = x86_64InstructionStream::new();
+//!         let end = again.create_label();
+//!         let mut label = |displacement: i64| match displacement {
+//!             0 => end,
+//!             _ => {
+//!                 assert_eq!(displacement, -(len as i64), "`{}`: {}", form.emitter, decoded);
+//!                 again.create_label_attached()
+//!             },
+//!         };
+//!
+//!         let operands = decoded.form.operands.iter().zip(&decoded.operands).map(|(kind, operand)| match (kind, operand) {
+//!             (OperandKind::Label, Operand::Relative(displacement)) => Operand::Label(label(*displacement)),
+//!             (OperandKind::ShortLabel, Operand::Relative(displacement)) => Operand::ShortLabel(label(*displacement)),
+//!             (_, Operand::Memory(memory @ Memory { target: Some(MemoryTarget::Rip), displacement, .. })) => {
+//!                 Operand::Memory(Memory { target: Some(MemoryTarget::Label(label(*displacement as i64))), displacement: 0, ..*memory })
+//!             },
+//!             _ => *operand,
+//!         }).collect::<Vec<_>>();
+//!
+//!         assert!(decoded.form.accepts(&operands), "`{}` {:02x?}: {}", form.emitter, bytes, decoded);
+//!         (decoded.form.emit)(&mut again, &operands);
+//!         again.attach_label(end);
+//!         assert_eq!((decoded.mnemonic(), again.finish().emit()), (form.mnemonic, bytes), "`{}`: {}", form.emitter, decoded);
+//!     }
+//! }
+//! ```
+
+use std::{collections::HashMap, error::Error, fmt, sync::OnceLock};
+
+use asmkit_core::{InstructionStream, Product};
+
+use crate::{
+    mode::Mode,
+    register::{GpReg, Reg16, Reg32, Reg64, Reg8, VectorReg, Xmm, Ymm},
+    registry::{InstructionForm, Memory, MemoryTarget, Operand, OperandKind, Register, INSTRUCTIONS, SEGMENTS},
+    stream::{x86_64InstructionStream, REX_B, REX_R, REX_W, REX_X},
+};
+
+/// An instruction read by [`decode`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInsn {
+    /// The form of the registry encoding the instruction.
+    pub form: &'static InstructionForm,
+
+    /// The operands of the instruction, as taken by its form, see the [module](self) documentation.
+    pub operands: Vec<Operand>,
+}
+
+impl DecodedInsn {
+    /// The lowercase mnemonic of the instruction.
+    #[inline(always)]
+    pub fn mnemonic(&self) -> &'static str {
+        self.form.mnemonic
+    }
+}
+
+impl fmt::Display for DecodedInsn {
+    /// Formats the instruction in Intel syntax, with the displacement of a branch written as `$+n`, from the end of the instruction.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::decode::decode;
+    ///
+    /// assert_eq!(decode(&[0x4c, 0x89, 0xc8]).unwrap().0.to_string(), "mov rax, r9");
+    /// assert_eq!(decode(&[0x83, 0xf9, 0xff]).unwrap().0.to_string(), "cmp ecx, 0xffffffff");
+    /// assert_eq!(decode(&[0xe2, 0xfe]).unwrap().0.to_string(), "loop $-2");
+    /// assert_eq!(decode(&[0x0f, 0xa8]).unwrap().0.to_string(), "push gs");
+    /// assert_eq!(decode(&[0x48, 0x8b, 0x05, 0xf9, 0xff, 0xff, 0xff]).unwrap().0.to_string(), "mov rax, qword ptr [rip - 0x7]");
+    /// assert_eq!(decode(&[0xc4, 0xe2, 0x75, 0x92, 0x04, 0x97]).unwrap().0.to_string(), "vgatherdps ymm0, [rdi + ymm2*4], ymm1");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.form.mnemonic)?;
+
+        for (index, operand) in self.operands.iter().enumerate() {
+            f.write_str(if index == 0 { " " } else { ", " })?;
+            write!(f, "{}", operand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The error returned when the bytes can't be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes end within the instruction starting at `offset`.
+    Truncated { offset: usize },
+
+    /// The instruction starting at `offset` isn't one of the forms of the registry.
+    Unsupported { offset: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { offset } => write!(f, "the instruction at {:#x} is truncated", offset),
+            Self::Unsupported { offset } => write!(f, "the instruction at {:#x} isn't a form of the registry", offset),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+const TRUNCATED: DecodeError = DecodeError::Truncated { offset: 0 };
+const UNSUPPORTED: DecodeError = DecodeError::Unsupported { offset: 0 };
+
+/// The fields of an instruction holding a register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    /// The `reg` field of the ModRM byte, extended by `REX.R`.
+    Reg,
+
+    /// The `r/m` field of a ModRM byte with a register operand, extended by `REX.B`.
+    Rm,
+
+    /// The low 3 bits of an opcode without a ModRM byte, extended by `REX.B`.
+    Opcode,
+
+    /// The `vvvv` field of the VEX prefix.
+    Vvvv,
+}
+
+const FIELDS: [Field; 4] = [Field::Reg, Field::Rm, Field::Opcode, Field::Vvvv];
+
+/// Where an operand of an instruction is encoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Source {
+    /// A register field.
+    Field(Field),
+
+    /// The memory operand of the ModRM byte.
+    Memory,
+
+    /// The bytes following the ModRM byte and its displacement: an immediate, or the displacement of a branch.
+    Tail,
+
+    /// Nowhere, as the segment register of `push fs`.
+    Implicit(Operand),
+}
+
+/// The parts of an instruction selecting its opcode: those its forms are looked up by.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    /// The legacy prefixes, in order.
+    prefixes: Vec<u8>,
+
+    /// The `W` bit of the REX prefix.
+    rex_w: bool,
+
+    /// The `W`, `L` and `pp` fields of the VEX prefix.
+    vex: Option<(bool, bool, u8)>,
+
+    /// The opcode map: 0 for the one-byte opcodes, 1 for `0F`, 2 for `0F 38` and 3 for `0F 3A`.
+    map: u8,
+
+    /// The opcode, without its low 3 bits if it has no ModRM byte, since they may encode a register.
+    opcode: u8,
+}
+
+/// The memory operand of a ModRM byte.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Addressing {
+    /// `[base + index*scale + displacement]`, the index being optional.
+    Based,
+
+    /// `[rip + displacement]`.
+    Rip,
+
+    /// `[index*scale + displacement]` or `[displacement]`, with no base register, which the emitters never take.
+    Absolute,
+}
+
+/// The layout of an instruction, read by [`parse`] without knowing its form: its prefixes, opcode, ModRM byte and displacement.
+#[derive(Debug)]
+struct Parsed {
+    key: Key,
+
+    /// Whether the instruction has a REX prefix, making the 8-bit registers numbered 4 to 7 `spl` to `dil` rather than `ah` to `bh`.
+    rex: bool,
+
+    /// The `R`, `X` and `B` register extensions of the REX or VEX prefix, as the bit 3 of a register number.
+    extensions: (u8, u8, u8),
+
+    /// The `vvvv` field of the VEX prefix.
+    vvvv: Option<u8>,
+
+    /// The low 3 bits of the opcode.
+    opcode: u8,
+
+    modrm: Option<u8>,
+    sib: Option<u8>,
+    displacement: i32,
+
+    /// The length of the instruction up to its tail, the bytes following the ModRM byte and its displacement.
+    len: usize,
+}
+
+impl Parsed {
+    /// The register number held by `field`, if the instruction has it.
+    fn field(&self, field: Field) -> Option<u8> {
+        let (r, _, b) = self.extensions;
+
+        match field {
+            Field::Reg => self.modrm.map(|modrm| (modrm >> 3) & 0b111 | r),
+            Field::Rm => self.modrm.filter(|modrm| modrm >> 6 == 0b11).map(|modrm| modrm & 0b111 | b),
+            Field::Opcode => self.modrm.is_none().then_some(self.opcode | b),
+            Field::Vvvv => self.vvvv,
+        }
+    }
+
+    /// The addressing of the memory operand, if the instruction has one.
+    fn addressing(&self) -> Option<Addressing> {
+        let modrm = self.modrm.filter(|modrm| modrm >> 6 != 0b11)?;
+
+        Some(match (modrm >> 6, modrm & 0b111, self.sib) {
+            (0b00, 0b101, _) => Addressing::Rip,
+            (0b00, _, Some(sib)) if sib & 0b111 == 0b101 => Addressing::Absolute,
+            _ => Addressing::Based,
+        })
+    }
+
+    /// The memory operand of the instruction, of the kind `kind`.
+    fn memory(&self, kind: OperandKind) -> Option<Memory> {
+        let (_, x, b) = self.extensions;
+        let modrm = self.modrm?;
+        let size = kind.memory_size();
+
+        if self.addressing()? == Addressing::Rip {
+            return Some(Memory { size, displacement: self.displacement, target: Some(MemoryTarget::Rip), ..Memory::default() });
+        }
+
+        let (base, index) = match self.sib {
+            Some(sib) => {
+                let number = (sib >> 3) & 0b111 | x;
+
+                // a vector index has no "no index" encoding.
+                let index = match kind {
+                    OperandKind::VsibX => Some(Register::Xmm(Xmm::from_number(number))),
+                    OperandKind::VsibY => Some(Register::Ymm(Ymm::from_number(number))),
+                    _ => (number != 0b100).then(|| Register::Reg64(Reg64::from_number(number))),
+                };
+
+                (sib & 0b111 | b, index.map(|index| (index, 1 << (sib >> 6))))
+            },
+            None => (modrm & 0b111 | b, None),
+        };
+
+        Some(Memory { size, base: Some(Reg64::from_number(base)), index, displacement: self.displacement, target: None })
+    }
+
+    /// The register operand of kind `kind` held by `field`.
+    fn register(&self, kind: OperandKind, field: Field) -> Option<Operand> {
+        let number = self.field(field)?;
+
+        Some(Operand::Register(match kind {
+            OperandKind::Reg8 => match number {
+                4..=7 if !self.rex => Register::Reg8([Reg8::Ah, Reg8::Ch, Reg8::Dh, Reg8::Bh][number as usize - 4]),
+                _ => Register::Reg8(Reg8::from_number(number)),
+            },
+            OperandKind::Reg16 => Register::Reg16(Reg16::from_number(number)),
+            OperandKind::Reg32 => Register::Reg32(Reg32::from_number(number)),
+            OperandKind::Reg64 => Register::Reg64(Reg64::from_number(number)),
+            OperandKind::Xmm => Register::Xmm(Xmm::from_number(number)),
+            OperandKind::Ymm => Register::Ymm(Ymm::from_number(number)),
+            OperandKind::SegmentReg => Register::Segment(*SEGMENTS.get(number as usize)?),
+            OperandKind::WritableSegmentReg => Register::Segment(*SEGMENTS.get(number as usize).filter(|segment| segment.check_writable().is_ok())?),
+            _ => return None,
+        }))
+    }
+
+    /// The same register to register instruction with the other direction bit, its `reg` and `r/m` operands swapped, such as `89 /r` for
+    /// `8b /r`.
+    fn reversed(&self) -> Option<Self> {
+        let modrm = self.modrm.filter(|modrm| modrm >> 6 == 0b11)?;
+        let reversible = self.key.map == 0 && (self.key.opcode < 0x40 && self.key.opcode & 0b110 == 0b010 || matches!(self.key.opcode, 0x8a | 0x8b));
+        if !reversible || self.key.vex.is_some() {
+            return None;
+        }
+
+        let (r, x, b) = self.extensions;
+        let key = Key { opcode: self.key.opcode ^ 0b10, ..self.key.clone() };
+        let modrm = (modrm & 0b1100_0000) | ((modrm & 0b111) << 3) | ((modrm >> 3) & 0b111);
+        Some(Self { key, extensions: (b, x, r), modrm: Some(modrm), sib: None, ..*self })
+    }
+}
+
+/// Returns true if the opcode `opcode` of the map `map` is followed by a ModRM byte.
+fn has_modrm(map: u8, opcode: u8) -> bool {
+    match map {
+        0 => match opcode {
+            0x00..=0x3f => opcode & 0b111 < 4,
+            0x62 | 0x63 | 0x69 | 0x6b | 0x80..=0x8f | 0xc0 | 0xc1 | 0xc4..=0xc7 | 0xd0..=0xd3 | 0xd8..=0xdf | 0xf6 | 0xf7 | 0xfe | 0xff => true,
+            _ => false,
+        },
+        1 => !matches!(opcode, 0x05..=0x09 | 0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0x80..=0x8f | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf),
+        _ => true,
+    }
+}
+
+/// Reads the layout of the instruction at the start of `bytes`, up to its tail.
+fn parse(bytes: &[u8]) -> Result<Parsed, DecodeError> {
+    let mut position = 0;
+    let mut next = || {
+        let byte = bytes.get(position).copied().ok_or(TRUNCATED);
+        position += 1;
+        byte
+    };
+
+    let mut prefixes = Vec::new();
+    let mut byte = next()?;
+    while matches!(byte, 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 | 0x66 | 0x67 | 0xf0 | 0xf2 | 0xf3) {
+        if prefixes.len() == 4 {
+            return Err(UNSUPPORTED);
+        }
+
+        prefixes.push(byte);
+        byte = next()?;
+    }
+
+    let rex = (0x40..=0x4f).contains(&byte).then_some(byte);
+    if rex.is_some() {
+        byte = next()?;
+    }
+
+    let rex_bits = rex.unwrap_or(0);
+    let bit = |mask: u8| ((rex_bits & mask != 0) as u8) << 3;
+    let mut extensions = (bit(REX_R), bit(REX_X), bit(REX_B));
+    let mut vex = None;
+    let mut vvvv = None;
+
+    // in 64-bit mode, `c4` and `c5` are always VEX prefixes.
+    let (map, opcode) = match byte {
+        0xc4 | 0xc5 if rex.is_none() => {
+            let first = next()?;
+            let (map, second) = match byte {
+                0xc4 => (first & 0b1_1111, next()?),
+                _ => (1, first),
+            };
+
+            let inverted = |set: bool| ((!set) as u8) << 3;
+            extensions = match byte {
+                0xc4 => (inverted(first & 0x80 != 0), inverted(first & 0x40 != 0), inverted(first & 0x20 != 0)),
+                _ => (inverted(first & 0x80 != 0), 0, 0),
+            };
+
+            let w = byte == 0xc4 && second & 0x80 != 0;
+            vex = Some((w, second & 0b100 != 0, second & 0b11));
+            vvvv = Some(!(second >> 3) & 0b1111);
+
+            if !(1..=3).contains(&map) {
+                return Err(UNSUPPORTED);
+            }
+
+            (map, next()?)
+        },
+        0x0f => match next()? {
+            0x38 => (2, next()?),
+            0x3a => (3, next()?),
+            opcode => (1, opcode),
+        },
+        opcode => (0, opcode),
+    };
+
+    let mut parsed = Parsed {
+        key: Key { prefixes, rex_w: rex_bits & REX_W != 0, vex, map, opcode },
+        rex: rex.is_some(),
+        extensions,
+        vvvv,
+        opcode: opcode & 0b111,
+        modrm: None,
+        sib: None,
+        displacement: 0,
+        len: 0,
+    };
+
+    let modrm_follows = match vex {
+        Some(_) => !(map == 1 && opcode == 0x77),
+        None => has_modrm(map, opcode),
+    };
+
+    if !modrm_follows {
+        parsed.key.opcode &= !0b111;
+        parsed.len = position;
+        return Ok(parsed);
+    }
+
+    let modrm = next()?;
+    parsed.modrm = Some(modrm);
+
+    let (mode, rm) = (modrm >> 6, modrm & 0b111);
+    if mode != 0b11 && rm == 0b100 {
+        parsed.sib = Some(next()?);
+    }
+
+    let displacement_size = match (mode, rm, parsed.sib) {
+        (0b01, _, _) => 1,
+        (0b10, _, _) => 4,
+        (0b00, 0b101, _) => 4,
+        (0b00, _, Some(sib)) if sib & 0b111 == 0b101 => 4,
+        _ => 0,
+    };
+
+    let mut displacement = [0; 4];
+    for byte in &mut displacement[..displacement_size] {
+        *byte = next()?;
+    }
+
+    parsed.displacement = match displacement_size {
+        1 => displacement[0] as i8 as i32,
+        _ => i32::from_le_bytes(displacement),
+    };
+
+    parsed.len = position;
+    Ok(parsed)
+}
+
+/// The register number of a register operand, as encoded in a register field.
+fn register_number(operand: &Operand) -> Option<u8> {
+    match operand {
+        Operand::Register(Register::Reg8(reg)) => Some(reg.offset() | ((reg.is_extension() as u8) << 3)),
+        Operand::Register(Register::Reg16(reg)) => Some(reg.number()),
+        Operand::Register(Register::Reg32(reg)) => Some(reg.number()),
+        Operand::Register(Register::Reg64(reg)) => Some(reg.number()),
+        Operand::Register(Register::Xmm(reg)) => Some(reg.number()),
+        Operand::Register(Register::Ymm(reg)) => Some(reg.number()),
+        Operand::Register(Register::Segment(reg)) => Some(reg.offset()),
+        _ => None,
+    }
+}
+
+/// An instruction emitted by a form to learn its encoding: its layout, its tail, and the operands it is emitted with.
+type Probe<'a> = (Parsed, &'a [u8], Vec<Operand>);
+
+/// An encoding of a form, learned from the bytes it emits.
+#[derive(Debug)]
+struct Encoding {
+    form: &'static InstructionForm,
+
+    /// Where each operand is encoded.
+    sources: Vec<Source>,
+
+    /// The value of each register field which encodes no operand, such as the opcode extension of a `/digit` opcode in the `reg` field, in
+    /// the order of [`FIELDS`].
+    fixed: [Option<u8>; 4],
+
+    /// The addressing of the memory operand.
+    addressing: Option<Addressing>,
+
+    /// The ModRM byte, SIB byte and displacement of a memory operand which encodes no operand, such as the one of the multi-byte `nop`s.
+    fixed_memory: Option<(Option<u8>, Option<u8>, i32)>,
+
+    /// The length of the tail.
+    tail: usize,
+
+    /// The bytes of the tail, if no operand is encoded in it.
+    fixed_tail: Option<Vec<u8>>,
+}
+
+impl Encoding {
+    /// Learns the encoding of `form` from the instructions `probes`, emitted by the form with the operands they are paired with and sharing
+    /// the same key and tail length.  Returns nothing if the operands aren't always encoded in the same fields.
+    fn learn(form: &'static InstructionForm, probes: &[Probe]) -> Option<Self> {
+        let (first, first_tail, first_operands) = probes.first()?;
+
+        let sources = form.operands.iter().enumerate().map(|(index, kind)| match kind {
+            _ if kind.is_implicit() => Some(Source::Implicit(first_operands[index])),
+            OperandKind::Reg8 | OperandKind::Reg16 | OperandKind::Reg32 | OperandKind::Reg64 | OperandKind::Xmm | OperandKind::Ymm
+            | OperandKind::SegmentReg | OperandKind::WritableSegmentReg => {
+                let encoded_in = |field: &&Field| probes.iter().all(|(parsed, _, operands)| parsed.field(**field).is_some() && parsed.field(**field) == register_number(&operands[index]));
+                FIELDS.iter().find(encoded_in).map(|field| Source::Field(*field))
+            },
+            OperandKind::Imm8 | OperandKind::Imm16 | OperandKind::Imm32 | OperandKind::Imm64 | OperandKind::SImm8 | OperandKind::SImm32
+            | OperandKind::Rounding | OperandKind::Label | OperandKind::ShortLabel | OperandKind::Rel32 | OperandKind::Symbol => Some(Source::Tail),
+            // the segment and offset of a far pointer are two operands of the tail.
+            OperandKind::FarPtr16 | OperandKind::FarPtr32 => None,
+            _ => Some(Source::Memory),
+        }).collect::<Option<Vec<_>>>()?;
+
+        let mut fixed = [None; 4];
+        for (index, field) in FIELDS.iter().enumerate() {
+            if sources.contains(&Source::Field(*field)) {
+                continue;
+            }
+
+            fixed[index] = first.field(*field);
+            if probes.iter().any(|(parsed, _, _)| parsed.field(*field) != fixed[index]) {
+                return None;
+            }
+        }
+
+        let tail_operands = sources.iter().filter(|source| **source == Source::Tail).count();
+        let fixed_tail = (tail_operands == 0).then(|| first_tail.to_vec());
+        if tail_operands > 1 || probes.iter().any(|(_, tail, _)| fixed_tail.as_deref().is_some_and(|fixed| fixed != *tail)) {
+            return None;
+        }
+
+        let memory = |parsed: &Parsed| (parsed.modrm, parsed.sib, parsed.displacement);
+        let fixed_memory = (first.addressing().is_some() && !sources.contains(&Source::Memory)).then(|| memory(first));
+        if probes.iter().any(|(parsed, _, _)| fixed_memory.is_some_and(|fixed| fixed != memory(parsed))) {
+            return None;
+        }
+
+        Some(Self { form, sources, fixed, addressing: first.addressing(), fixed_memory, tail: first_tail.len(), fixed_tail })
+    }
+
+    /// Reads the operands of the instruction `parsed`, followed by `tail`, if it has this encoding.
+    fn operands(&self, parsed: &Parsed, tail: &[u8]) -> Option<Vec<Operand>> {
+        let fixed = FIELDS.iter().zip(&self.fixed).all(|(field, fixed)| self.sources.contains(&Source::Field(*field)) || parsed.field(*field) == *fixed);
+        let fixed_memory = self.fixed_memory.is_none_or(|fixed| fixed == (parsed.modrm, parsed.sib, parsed.displacement));
+        if !fixed || !fixed_memory || parsed.addressing() != self.addressing || self.fixed_tail.as_deref().is_some_and(|fixed| fixed != tail) {
+            return None;
+        }
+
+        self.form.operands.iter().zip(&self.sources).map(|(kind, source)| match source {
+            Source::Field(field) => parsed.register(*kind, *field),
+            Source::Memory => parsed.memory(*kind).map(Operand::Memory),
+            Source::Implicit(operand) => Some(*operand),
+            Source::Tail => {
+                let value = match *tail {
+                    [byte] => byte as i8 as i64,
+                    [low, high] => i16::from_le_bytes([low, high]) as i64,
+                    [a, b, c, d] => i32::from_le_bytes([a, b, c, d]) as i64,
+                    [a, b, c, d, e, f, g, h] => i64::from_le_bytes([a, b, c, d, e, f, g, h]),
+                    _ => return None,
+                };
+
+                match kind {
+                    OperandKind::Label | OperandKind::ShortLabel | OperandKind::Rel32 | OperandKind::Symbol => Some(Operand::Relative(value)),
+                    OperandKind::Imm8 => Some(Operand::Immediate(value as u8 as i64)),
+                    OperandKind::Imm16 => Some(Operand::Immediate(value as u16 as i64)),
+                    OperandKind::Imm32 => Some(Operand::Immediate(value as u32 as i64)),
+                    _ => Some(Operand::Immediate(value)).filter(|operand| kind.accepts(operand)),
+                }
+            },
+        }).collect()
+    }
+}
+
+/// The number of seeds of the example operands each form is emitted with to learn its encodings.
+const PROBES: u64 = 32;
+
+/// The encodings of the forms of the registry, by key, in the order of the registry.
+struct Index {
+    encodings: HashMap<Key, Vec<Encoding>>,
+
+    /// The forms writing a lone prefix, such as `lock`, by the prefix.
+    prefixes: HashMap<u8, &'static InstructionForm>,
+}
+
+impl Index {
+    /// Learns the encodings of every form of the registry.
+    fn learn() -> Self {
+        let mut encodings: HashMap<Key, Vec<Encoding>> = HashMap::new();
+        let mut prefixes = HashMap::new();
+
+        for form in INSTRUCTIONS.iter().filter(|form| form.modes.contains(&Mode::Long)) {
+            let mut groups: Vec<(Key, usize, Vec<Probe>)> = Vec::new();
+            let mut emitted = Vec::new();
+
+            for seed in 0..PROBES {
+                let mut stream = x86_64InstructionStream::new();
+                let operands = form.example_operands(&mut stream, seed);
+                (form.emit)(&mut stream, &operands);
+                emitted.push((stream.finish().emit(), operands));
+            }
+
+            for (bytes, operands) in &emitted {
+                if let ([prefix], []) = (bytes.as_slice(), form.operands) {
+                    if parse(bytes).is_err() {
+                        prefixes.insert(*prefix, form);
+                        continue;
+                    }
+                }
+
+                let Ok(parsed) = parse(bytes) else { continue };
+                let Some(tail) = bytes.get(parsed.len..) else { continue };
+
+                match groups.iter_mut().find(|(key, len, _)| *key == parsed.key && *len == tail.len()) {
+                    Some((_, _, probes)) => probes.push((parsed, tail, operands.clone())),
+                    None => groups.push((parsed.key.clone(), tail.len(), vec![(parsed, tail, operands.clone())])),
+                }
+            }
+
+            for (key, _, probes) in groups {
+                if let Some(encoding) = Encoding::learn(form, &probes) {
+                    encodings.entry(key).or_default().push(encoding);
+                }
+            }
+        }
+
+        Self { encodings, prefixes }
+    }
+
+    /// Decodes the instruction `parsed`, the start of `bytes`.
+    fn decode(&self, parsed: &Parsed, bytes: &[u8]) -> Result<(DecodedInsn, usize), DecodeError> {
+        let mut error = UNSUPPORTED;
+
+        for encoding in self.encodings.get(&parsed.key).into_iter().flatten() {
+            let Some(tail) = bytes.get(parsed.len..parsed.len + encoding.tail) else {
+                error = TRUNCATED;
+                continue;
+            };
+
+            if let Some(operands) = encoding.operands(parsed, tail) {
+                return Ok((DecodedInsn { form: encoding.form, operands }, parsed.len + encoding.tail));
+            }
+        }
+
+        Err(error)
+    }
+}
+
+/// The encodings of the forms of the registry, learned on first use.
+fn index() -> &'static Index {
+    static INDEX: OnceLock<Index> = OnceLock::new();
+    INDEX.get_or_init(Index::learn)
+}
+
+/// Decodes the instruction at the start of `bytes`, returning it and its length.
+///
+/// # Errors
+/// Returns [`DecodeError::Truncated`] if `bytes` ends within the instruction, and [`DecodeError::Unsupported`] if the instruction isn't one of
+/// the forms of the [`registry`](crate::registry), both at offset 0.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::{decode::{decode, DecodeError}, register::{Reg32, Reg64}, registry::{Operand, Register}};
+///
+/// let (insn, len) = decode(&[0x49, 0x81, 0xc5, 0x00, 0xf0, 0xff, 0xff, 0xc3]).unwrap();
+/// assert_eq!((insn.mnemonic(), insn.form.emitter, len), ("add", "add_reg64_imm32", 7));
+/// assert_eq!(insn.operands, [Operand::Register(Register::Reg64(Reg64::R13)), Operand::Immediate(-0x1000)]);
+///
+/// // the `r, r/m` opcode of `mov`, which asmkit doesn't emit.
+/// let (insn, len) = decode(&[0x8b, 0xc8]).unwrap();
+/// assert_eq!((insn.form.emitter, len), ("mov_reg32_reg32", 2));
+/// assert_eq!(insn.operands, [Operand::Register(Register::Reg32(Reg32::Ecx)), Operand::Register(Register::Reg32(Reg32::Eax))]);
+///
+/// assert_eq!(decode(&[0x48, 0xb8, 0x00]), Err(DecodeError::Truncated { offset: 0 }));
+/// assert_eq!(decode(&[0x0f, 0x0b]), Err(DecodeError::Unsupported { offset: 0 })); // ud2
+/// ```
+pub fn decode(bytes: &[u8]) -> Result<(DecodedInsn, usize), DecodeError> {
+    let index = index();
+    let decoded = parse(bytes).and_then(|parsed| {
+        index.decode(&parsed, bytes).or_else(|error| match parsed.reversed() {
+            Some(reversed) => index.decode(&reversed, bytes).map_err(|_| error),
+            None => Err(error),
+        })
+    });
+
+    // a prefix of an instruction outside of the registry, or of no instruction, is read on its own.
+    decoded.or_else(|error| match bytes.first().and_then(|prefix| index.prefixes.get(prefix)) {
+        Some(form) => Ok((DecodedInsn { form, operands: Vec::new() }, 1)),
+        None => Err(error),
+    })
+}
+
+/// Decodes the code of `product`, returning each instruction and its offset.
+///
+/// # Errors
+/// Returns the [`DecodeError`] of the first instruction which can't be decoded, at its offset in the product.
+///
+/// # Example
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_x86_64::{decode::decode_all, register::Reg64, stream::x86_64InstructionStream};
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// let top = stream.create_label_attached();
+/// stream.push_reg64(Reg64::Rbx);
+/// stream.sub_reg64_imm32(Reg64::Rbx, 1);
+/// stream.pop_reg64(Reg64::Rbx);
+/// stream.loop_label(top);
+/// stream.ret_near();
+/// let product = stream.finish();
+///
+/// let listing = decode_all(&product).unwrap().iter().map(|(offset, insn)| format!("{:02x}  {}", offset, insn)).collect::<Vec<_>>();
+/// assert_eq!(listing, [
+///     "00  push rbx",
+///     "01  sub rbx, 0x1",
+///     "05  pop rbx",
+///     "06  loop $-8",
+///     "08  ret",
+/// ]);
+///
+/// // the multi-byte `nop`s are read back as one instruction each.
+/// let mut stream = x86_64InstructionStream::new();
+/// stream.ret_near();
+/// stream.nop(12);
+/// let offsets = decode_all(&stream.finish()).unwrap().iter().map(|(offset, insn)| (*offset, insn.to_string())).collect::<Vec<_>>();
+/// assert_eq!(offsets, [(0, "ret".to_string()), (1, "nop".to_string()), (10, "nop".to_string())]);
+/// ```
+///
+/// A function with a prologue, memory operands, SSE instructions and a conditional jump:
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_x86_64::{
+///     decode::decode_all,
+///     memory::{Mem, Scale},
+///     register::{Reg64, Xmm},
+///     stream::{x86_64InstructionStream, Condition},
+/// };
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// stream.push_reg64(Reg64::Rbp);
+/// stream.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+/// stream.sub_reg64_imm32(Reg64::Rsp, 0x20);
+/// let top = stream.create_label_attached();
+/// stream.mov_reg64_mem64(Reg64::Rax, Mem::indexed(Reg64::Rdi, Reg64::Rcx, Scale::X8, 8));
+/// stream.movsd_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rbp, -8));
+/// stream.addsd_xmm_xmm(Xmm::Xmm0, Xmm::Xmm1);
+/// stream.movsd_mem_xmm(Mem::new(Reg64::Rbp, -8), Xmm::Xmm0);
+/// stream.cmp_reg64_imm32(Reg64::Rax, 0);
+/// stream.jcc_label(Condition::Ne, top);
+/// stream.nop(3);
+/// stream.add_reg64_imm32(Reg64::Rsp, 0x20);
+/// stream.pop_reg64(Reg64::Rbp);
+/// stream.ret_near();
+/// let product = stream.finish();
+///
+/// let listing = decode_all(&product).unwrap().iter().map(|(offset, insn)| format!("{:02x}  {}", offset, insn)).collect::<Vec<_>>();
+/// assert_eq!(listing, [
+///     "00  push rbp",
+///     "01  mov rbp, rsp",
+///     "04  sub rsp, 0x20",
+///     "08  mov rax, qword ptr [rdi + rcx*8 + 0x8]",
+///     "0d  movsd xmm0, qword ptr [rbp - 0x8]",
+///     "12  addsd xmm0, xmm1",
+///     "16  movsd qword ptr [rbp - 0x8], xmm0",
+///     "1b  cmp rax, 0x0",
+///     "1f  jne $-29",
+///     "25  nop",
+///     "28  add rsp, 0x20",
+///     "2c  pop rbp",
+///     "2d  ret",
+/// ]);
+/// ```
+pub fn decode_all(product: &Product) -> Result<Vec<(usize, DecodedInsn)>, DecodeError> {
+    let bytes = product.bytes();
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let (insn, len) = decode(&bytes[offset..]).map_err(|error| match error {
+            DecodeError::Truncated { .. } => DecodeError::Truncated { offset },
+            DecodeError::Unsupported { .. } => DecodeError::Unsupported { offset },
+        })?;
+
+        instructions.push((offset, insn));
+        offset += len;
+    }
+
+    Ok(instructions)
+}
@@ -0,0 +1,287 @@
+//! The registers defined and used by the instructions, for the passes computing liveness over the emitted code without decoding it.
+//!
+//! Each instruction emitted while the [listing](crate::listing) is enabled records the registers it writes, its *defs*, and those it reads,
+//! its *uses*, as [`RegSet`]s of [`RegId`]s, read back through [`EmittedInsn::defs`](crate::listing::EmittedInsn::defs) and
+//! [`EmittedInsn::uses`](crate::listing::EmittedInsn::uses).  The sets include the implicit operands: `mul` defines `rdx:rax`, `div` uses it,
+//! the string instructions use `rsi`, `rdi` and `rcx`, and `push`, `pop`, `call` and `ret` use and define `rsp`.
+//!
+//! A register is identified by its number, whatever the width of the operand: `al`, `ah`, `ax`, `eax` and `rax` are the same register, and so
+//! are `xmm3` and `ymm3`.  The widths only matter to what a write leaves of the previous value:
+//!
+//! - a write to a 32-bit register zero extends to 64 bits, and only defines the register;
+//! - a write to an 8 or 16-bit register leaves the upper bits unchanged, and both defines and uses the register;
+//! - a write to a vector register defines it, even by the legacy SSE instructions which leave the upper half of the `ymm` register unchanged.
+//!
+//! The idioms zeroing a register, such as `xor eax, eax` or `sub rax, rax`, only define it, as their result doesn't depend on its value.
+//!
+//! The registers addressing a memory operand are used, the flags are described by the [`flags`](crate::flags) module, and the registers a
+//! call clobbers or a system call reads are those of their convention, see [`x86_64InstructionStream::syscall`].
+//!
+//! [`x86_64InstructionStream::syscall`]: crate::stream::x86_64InstructionStream::syscall
+//!
+//! # Example
+//! ```
+//! use asmkit_x86_64::{defuse::{RegId, RegSet}, register::{Reg32, Reg64, Reg8, Xmm, Ymm}};
+//!
+//! assert_eq!(RegId::from(Reg64::Rax), RegId::RAX);
+//! assert_eq!(RegId::from(Reg32::R9d), RegId::R9);
+//! assert_eq!(RegId::from(Reg8::Ah), RegId::RAX);
+//! assert_eq!(RegId::from(Reg8::Spl), RegId::RSP);
+//! assert_eq!(RegId::from(Xmm::Xmm3), RegId::from(Ymm::Ymm3));
+//! assert!(RegId::from(Xmm::Xmm3).is_vector());
+//!
+//! let set = RegSet::of(Reg64::Rdx).with(Reg32::Eax).with(Ymm::Ymm15);
+//! assert!(set.contains(Reg8::Al) && set.contains(Xmm::Xmm15) && !set.contains(Reg64::Rcx));
+//! assert_eq!(set.len(), 3);
+//! assert_eq!(set.to_string(), "rax | rdx | ymm15");
+//! assert_eq!(set.iter().collect::<Vec<_>>(), [RegId::RAX, RegId::RDX, RegId::vector(15)]);
+//! assert_eq!(set.without(RegSet::of(Reg64::Rax)), RegSet::of(Reg64::Rdx) | RegSet::of(Xmm::Xmm15));
+//! ```
+
+use std::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+};
+
+use crate::{
+    memory::{Mem, VsibMem},
+    register::{GpReg, Reg16, Reg32, Reg64, Reg8, VectorReg, Xmm, Ymm},
+};
+
+/// A register of any width, numbered 0 to 15 for the general purpose registers, and 16 to 31 for the vector registers.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegId(u8);
+
+impl RegId {
+    pub const RAX: Self = Self(0);
+    pub const RCX: Self = Self(1);
+    pub const RDX: Self = Self(2);
+    pub const RBX: Self = Self(3);
+    pub const RSP: Self = Self(4);
+    pub const RBP: Self = Self(5);
+    pub const RSI: Self = Self(6);
+    pub const RDI: Self = Self(7);
+    pub const R8: Self = Self(8);
+    pub const R9: Self = Self(9);
+    pub const R10: Self = Self(10);
+    pub const R11: Self = Self(11);
+    pub const R12: Self = Self(12);
+    pub const R13: Self = Self(13);
+    pub const R14: Self = Self(14);
+    pub const R15: Self = Self(15);
+
+    /// The general purpose register numbered `number`, see [`GpReg::number`].
+    ///
+    /// # Panics
+    /// Panics if `number` isn't below 16.
+    pub const fn gp(number: u8) -> Self {
+        assert!(number < 16, "there are 16 general purpose registers");
+        Self(number)
+    }
+
+    /// The vector register numbered `number`, see [`VectorReg::number`].
+    ///
+    /// # Panics
+    /// Panics if `number` isn't below 16.
+    pub const fn vector(number: u8) -> Self {
+        assert!(number < 16, "there are 16 vector registers");
+        Self(16 + number)
+    }
+
+    /// The number of the register among the registers of its kind, from 0 to 15.
+    pub const fn number(self) -> u8 {
+        self.0 & 15
+    }
+
+    /// Returns true if this is a general purpose register.
+    pub const fn is_gp(self) -> bool {
+        self.0 < 16
+    }
+
+    /// Returns true if this is a vector register.
+    pub const fn is_vector(self) -> bool {
+        self.0 >= 16
+    }
+
+    /// The name of the whole register, such as `rax` or `ymm3`.
+    pub fn name(self) -> &'static str {
+        match self.is_gp() {
+            true => Reg64::from_number(self.number()).name(),
+            false => Ymm::from_number(self.number()).name(),
+        }
+    }
+}
+
+impl fmt::Display for RegId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Debug for RegId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RegId({})", self.name())
+    }
+}
+
+impl From<Reg64> for RegId {
+    fn from(reg: Reg64) -> Self {
+        Self::gp(reg.number())
+    }
+}
+
+impl From<Reg32> for RegId {
+    fn from(reg: Reg32) -> Self {
+        Self::gp(reg.number())
+    }
+}
+
+impl From<Reg16> for RegId {
+    fn from(reg: Reg16) -> Self {
+        Self::gp(reg.number())
+    }
+}
+
+impl From<Reg8> for RegId {
+    /// The register `reg` is part of, `rax` for both `al` and `ah`.
+    fn from(reg: Reg8) -> Self {
+        Self::gp(reg.number())
+    }
+}
+
+impl From<Xmm> for RegId {
+    fn from(reg: Xmm) -> Self {
+        Self::vector(reg.number())
+    }
+}
+
+impl From<Ymm> for RegId {
+    fn from(reg: Ymm) -> Self {
+        Self::vector(reg.number())
+    }
+}
+
+/// A set of registers, combined with `|`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RegSet(u32);
+
+impl RegSet {
+    /// No register.
+    pub const EMPTY: Self = Self(0);
+
+    /// The set of the register `reg`.
+    pub fn of(reg: impl Into<RegId>) -> Self {
+        Self(1 << reg.into().0)
+    }
+
+    /// Returns the registers of `self` and `reg`.
+    pub fn with(self, reg: impl Into<RegId>) -> Self {
+        self | Self::of(reg)
+    }
+
+    /// Returns true if the register `reg` is in the set.
+    pub fn contains(self, reg: impl Into<RegId>) -> bool {
+        self.0 & Self::of(reg).0 != 0
+    }
+
+    /// Returns true if any register of `other` is in the set.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns true if no register is in the set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of registers in the set.
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns the registers of `self` without those of `other`.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The registers in the set, by number, the general purpose registers first.
+    pub fn iter(self) -> impl Iterator<Item = RegId> {
+        (0..32).filter(move |bit| self.0 & (1 << bit) != 0).map(RegId)
+    }
+}
+
+/// The registers used by an instruction combining `dest` with `src`, which zeroes `dest` when both are the same register: the zeroing idiom
+/// uses neither.
+pub(crate) fn zeroing_uses<Reg: Into<RegId> + PartialEq>(dest: Reg, src: Reg) -> RegSet {
+    match dest == src {
+        true => RegSet::EMPTY,
+        false => RegSet::of(dest).with(src),
+    }
+}
+
+impl From<RegId> for RegSet {
+    fn from(reg: RegId) -> Self {
+        Self::of(reg)
+    }
+}
+
+impl From<Mem> for RegSet {
+    /// The registers addressing the memory operand `mem`, its base and its index.
+    fn from(mem: Mem) -> Self {
+        match mem.index {
+            Some((index, _)) => Self::of(mem.base).with(index),
+            None => Self::of(mem.base),
+        }
+    }
+}
+
+impl<Index: Into<RegId>> From<VsibMem<Index>> for RegSet {
+    /// The registers addressing the vector memory operand `mem`, its base and its vector of indices.
+    fn from(mem: VsibMem<Index>) -> Self {
+        Self::of(mem.base).with(mem.index)
+    }
+}
+
+impl<Reg: Into<RegId>> FromIterator<Reg> for RegSet {
+    fn from_iter<T: IntoIterator<Item = Reg>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::EMPTY, Self::with)
+    }
+}
+
+impl BitOr for RegSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for RegSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Display for RegSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, reg) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" | ")?;
+            }
+
+            f.write_str(reg.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RegSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RegSet({})", self)
+    }
+}
@@ -0,0 +1,180 @@
+//! Diagnostics about the labels of an instruction stream, see
+//! [`x86_64InstructionStream::validate`](crate::stream::x86_64InstructionStream::validate), and about the encoding of its instructions, see
+//! `x86_64InstructionStream::verify` under the `verify` feature.
+//!
+//! The encoding of the instructions is checked as they are emitted in debug builds, panicking on the first [`EncodingDiagnostic`].  Under the
+//! `verify` feature, it is rather checked over the listing by `verify`, which reports every diagnostic, in release builds as well.
+
+use std::fmt;
+
+use asmkit_core::entity::{EntityRef, LabelRef};
+
+use crate::stream::DisplacementOutOfRange;
+
+/// A label which is likely misused, reported by [`x86_64InstructionStream::validate`](crate::stream::x86_64InstructionStream::validate).
+#[derive(Clone, Debug, PartialEq)]
+pub enum LabelDiagnostic {
+    /// A label used by instructions but never attached, which can't be resolved when the instruction stream is finished.
+    Undefined {
+        /// The label.
+        label: LabelRef,
+
+        /// The name of the label, if any.
+        name: Option<String>,
+
+        /// The offsets of the fields using the label.
+        uses: Vec<usize>,
+    },
+
+    /// A label attached out of reach of an 8-bit displacement using it, such as the displacement of a `loop`, which can't be resolved when the
+    /// instruction stream is finished.
+    OutOfRange {
+        /// The label.
+        label: LabelRef,
+
+        /// The name of the label, if any.
+        name: Option<String>,
+
+        /// The displacement which doesn't reach the label.
+        error: DisplacementOutOfRange,
+    },
+
+    /// A label attached but never used nor exported, harmless but often the sign of a missing branch.
+    Unused {
+        /// The label.
+        label: LabelRef,
+
+        /// The name of the label, if any.
+        name: Option<String>,
+
+        /// The offset the label is attached to.
+        offset: usize,
+    },
+}
+
+impl LabelDiagnostic {
+    /// Returns true if the diagnostic keeps the instruction stream from being finished.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Undefined { .. } | Self::OutOfRange { .. })
+    }
+}
+
+/// Writes a label by name, or by index if it has no name.
+fn write_label(f: &mut fmt::Formatter<'_>, label: LabelRef, name: &Option<String>) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "label `{}`", name),
+        None => write!(f, "label #{}", label.as_u32()),
+    }
+}
+
+impl fmt::Display for LabelDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined { label, name, uses } => {
+                write_label(f, *label, name)?;
+                write!(f, " is never attached, but used at")?;
+
+                for (i, offset) in uses.iter().enumerate() {
+                    write!(f, "{} {:#x}", if i == 0 { "" } else { "," }, offset)?;
+                }
+
+                Ok(())
+            },
+            Self::OutOfRange { label, name, error } => {
+                write_label(f, *label, name)?;
+                write!(f, " is out of reach of the {}-bit displacement at {:#x}, {} bytes away", error.bits, error.offset, error.displacement)
+            },
+            Self::Unused { label, name, offset } => {
+                write_label(f, *label, name)?;
+                write!(f, " is attached at {:#x}, but never used", offset)
+            },
+        }
+    }
+}
+
+/// The longest instruction the processor decodes, in bytes.
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// An instruction the processor won't decode as intended, reported by `x86_64InstructionStream::verify` under the `verify` feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EncodingDiagnostic {
+    /// An instruction longer than [`MAX_INSTRUCTION_LEN`], which raises a general protection fault.
+    TooLong {
+        /// The offset of the instruction.
+        offset: usize,
+
+        /// The length of the instruction, in bytes.
+        len: usize,
+    },
+
+    /// An instruction with two different legacy prefixes of the same group, such as `lock` and `rep`, whose behavior is undefined.  Repeating
+    /// the same prefix, as the long `nop`s do, is harmless.
+    ConflictingPrefixes {
+        /// The offset of the instruction.
+        offset: usize,
+
+        /// The first prefix of the group.
+        first: u8,
+
+        /// The prefix of the same group following it.
+        second: u8,
+    },
+
+    /// A field holding a label which starts within an instruction but ends past it, so patching it would overwrite the next instruction.
+    FixupOutsideInstruction {
+        /// The offset of the field.
+        offset: usize,
+
+        /// The offset of the instruction the field starts in.
+        instruction: usize,
+    },
+}
+
+impl fmt::Display for EncodingDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLong { offset, len } => {
+                write!(f, "the instruction at {:#x} takes {} bytes, past the {}-byte limit", offset, len, MAX_INSTRUCTION_LEN)
+            },
+            Self::ConflictingPrefixes { offset, first, second } => {
+                write!(f, "the instruction at {:#x} has the prefixes {:#04x} and {:#04x} of the same group", offset, first, second)
+            },
+            Self::FixupOutsideInstruction { offset, instruction } => {
+                write!(f, "the label field at {:#x} ends past the instruction at {:#x}", offset, instruction)
+            },
+        }
+    }
+}
+
+/// The group of the legacy prefix `byte`, from 0 to 3: `lock` and the repeat prefixes, the segment overrides, the operand size override and
+/// the address size override.
+#[cfg(any(debug_assertions, feature = "verify"))]
+fn prefix_group(byte: u8) -> Option<usize> {
+    match byte {
+        0xf0 | 0xf2 | 0xf3 => Some(0),
+        0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => Some(1),
+        0x66 => Some(2),
+        0x67 => Some(3),
+        _ => None,
+    }
+}
+
+/// Checks the length and the prefixes of the instruction `bytes` emitted at `offset`.
+#[cfg(any(debug_assertions, feature = "verify"))]
+pub(crate) fn check_instruction(offset: usize, bytes: &[u8]) -> Vec<EncodingDiagnostic> {
+    let mut diagnostics = Vec::new();
+    if bytes.len() > MAX_INSTRUCTION_LEN {
+        diagnostics.push(EncodingDiagnostic::TooLong { offset, len: bytes.len() });
+    }
+
+    let mut groups = [None; 4];
+    for (&byte, group) in bytes.iter().map_while(|byte| Some(byte).zip(prefix_group(*byte))) {
+        match groups[group] {
+            Some(first) if first != byte => diagnostics.push(EncodingDiagnostic::ConflictingPrefixes { offset, first, second: byte }),
+            Some(_) => {},
+            None => groups[group] = Some(byte),
+        }
+    }
+
+    diagnostics
+}
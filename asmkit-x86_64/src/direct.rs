@@ -0,0 +1,141 @@
+//! The direct stores and the enqueue stores, writing to device memory such as the work queues of accelerators, and `serialize`.
+//!
+//! [`x86_64InstructionStream::movdiri_mem_reg32`] and [`x86_64InstructionStream::movdiri_mem_reg64`] store a doubleword or a quadword as a
+//! single write, bypassing the caches.  [`x86_64InstructionStream::movdir64b_reg64_mem`] copies 64 bytes from memory as a single write, and
+//! [`x86_64InstructionStream::enqcmd_reg64_mem`] and [`x86_64InstructionStream::enqcmds_reg64_mem`] submit a 64-byte command to the work queue of
+//! a device, setting `ZF` if the device refused it.  [`x86_64InstructionStream::serialize`] waits for every instruction before it to complete, as
+//! `cpuid` does, without clobbering any register.  These instructions require the MOVDIRI, MOVDIR64B, ENQCMD and SERIALIZE extensions, which
+//! aren't tracked by [`CpuFeatures`](crate::features::CpuFeatures).
+//!
+//! Unlike the other moves, the 64-byte writes take their destination in the register of the ModRM byte, as the address of the destination,
+//! and their source as the memory operand.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{memory::Mem, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.movdiri_mem_reg32(Mem::new(Reg64::Rdi, 0), Reg32::Eax);
+//! stream.movdiri_mem_reg64(Mem::new(Reg64::Rdi, 8), Reg64::R10);
+//! stream.enqcmd_reg64_mem(Reg64::Rdi, Mem::new(Reg64::Rsi, 0));
+//! stream.enqcmds_reg64_mem(Reg64::R8, Mem::new(Reg64::Rsp, 0));
+//! stream.serialize();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x0f, 0x38, 0xf9, 0x07,                   // movdiri dword ptr [rdi], eax
+//!     0x4c, 0x0f, 0x38, 0xf9, 0x57, 0x08,       // movdiri qword ptr [rdi + 8], r10
+//!     0xf2, 0x0f, 0x38, 0xf8, 0x3e,             // enqcmd rdi, zmmword ptr [rsi]
+//!     0xf3, 0x44, 0x0f, 0x38, 0xf8, 0x04, 0x24, // enqcmds r8, zmmword ptr [rsp]
+//!     0x0f, 0x01, 0xe8,                         // serialize
+//! ]);
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::{Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Move 64 bytes from *m512* to the address in *r64* as a single direct store, `movdir64b dest, src`.  The address in *r64* must be 64-byte
+    /// aligned, while *m512* needn't be.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and the destination is known to be misaligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`]: its address is in a register, so it is only known for `rsp` and `rbp` in the current
+    /// frame, as 16-byte aligned.
+    ///
+    /// # Example
+    /// The destination register goes in the register field of the ModRM byte, and the source in its memory operand:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movdir64b_reg64_mem(Reg64::Rax, Mem::new(Reg64::Rsi, 0));
+    /// stream.movdir64b_reg64_mem(Reg64::R9, Mem::new(Reg64::R12, 64));
+    /// stream.movdir64b_reg64_mem(Reg64::Rsp, Mem::new(Reg64::Rbp, -64));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x38, 0xf8, 0x06,                   // movdir64b rax, zmmword ptr [rsi]
+    ///     0x66, 0x45, 0x0f, 0x38, 0xf8, 0x4c, 0x24, 0x40, // movdir64b r9, zmmword ptr [r12 + 64]
+    ///     0x66, 0x0f, 0x38, 0xf8, 0x65, 0xc0,             // movdir64b rsp, zmmword ptr [rbp - 64]
+    /// ]);
+    /// ```
+    ///
+    /// Within a frame, the destination is only known to be 16-byte aligned, which the checks accept; an unaligned source is fine:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{frame::FrameBuilder, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_slot(64, 16);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.movdir64b_reg64_mem(Reg64::Rsp, Mem::new(Reg64::Rsi, 3));
+    /// stream.movdir64b_reg64_mem(Reg64::Rdi, Mem::new(Reg64::Rsp, 8));
+    /// ```
+    pub fn movdir64b_reg64_mem(&mut self, dest: Reg64, src: Mem) {
+        self.check_alignment("movdir64b", Mem::new(dest, 0), 64);
+
+        let instruction = self.begin_instruction();
+        self.write_byte(0x66);
+        self.write_rex_modrm_mem(false, &[0x0f, 0x38, 0xf8], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::EMPTY, RegSet::of(dest) | RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r32* to *m32* as a single direct store, `movdiri dest, src`.
+    pub fn movdiri_mem_reg32(&mut self, dest: Mem, src: Reg32) {
+        self.write_movdiri(false, dest, src.offset(), src.is_extension(), RegSet::of(src));
+    }
+
+    /// Move *r64* to *m64* as a single direct store, `movdiri dest, src`.
+    pub fn movdiri_mem_reg64(&mut self, dest: Mem, src: Reg64) {
+        self.write_movdiri(true, dest, src.offset(), src.is_extension(), RegSet::of(src));
+    }
+
+    /// Submit the 64-byte command at *m512* to the work queue at the address in *r64*, as a user-mode enqueue store, `enqcmd dest, src`.  `ZF`
+    /// is set if the device didn't accept the command, and the other status flags are cleared.
+    pub fn enqcmd_reg64_mem(&mut self, dest: Reg64, src: Mem) {
+        self.write_enqueue(0xf2, dest, src);
+    }
+
+    /// Submit the 64-byte command at *m512* to the work queue at the address in *r64*, as a supervisor-mode enqueue store, `enqcmds dest, src`.
+    /// `ZF` is set if the device didn't accept the command, and the other status flags are cleared.  Privileged.
+    pub fn enqcmds_reg64_mem(&mut self, dest: Reg64, src: Mem) {
+        self.write_enqueue(0xf3, dest, src);
+    }
+
+    /// Serialize the instruction execution: wait for every instruction before it to complete and for its stores to drain, `serialize`.
+    pub fn serialize(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xe8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Fence);
+    }
+
+    /// Writes `movdiri`, `0F 38 F9 /r`, storing the register `src` (its offset and whether it is an extension register) to `dest`.
+    fn write_movdiri(&mut self, rex_w: bool, dest: Mem, src: u8, is_extension: bool, uses: RegSet) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(rex_w, &[0x0f, 0x38, 0xf9], (src, is_extension), dest);
+        self.record_registers(RegSet::EMPTY, uses | RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Writes an enqueue store, `0F 38 F8 /r` selected by its mandatory `prefix`.
+    fn write_enqueue(&mut self, prefix: u8, dest: Reg64, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_byte(prefix);
+        self.write_rex_modrm_mem(false, &[0x0f, 0x38, 0xf8], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::EMPTY, RegSet::of(dest) | RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+}
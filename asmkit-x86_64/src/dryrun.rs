@@ -0,0 +1,158 @@
+//! Dry runs, measuring the code a sequence of emitters writes without adding it to the instruction stream, for layout planning.
+//!
+//! [`dry_run`] and [`x86_64InstructionStream::dry_run`] run the emitters on a scratch stream encoding as the planned one does, then discard it, so
+//! the measured lengths always match those of the emitters.  A dry run also reports the labels its code attaches, at their offsets from the start
+//! of the code, so a planner can tell whether a branch over it reaches with an 8-bit displacement.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{dryrun::dry_run, generic::Mov, register::{Reg32, Reg64}, stream::{Condition, x86_64InstructionStream}};
+//!
+//! // the length of each emitter, measured then emitted.
+//! let sequence: [&dyn Fn(&mut x86_64InstructionStream); 7] = [
+//!     &|s| s.mov_reg64_imm64(Reg64::Rax, 0x1122_3344_5566_7788),
+//!     &|s| s.mov_reg32_imm32(Reg32::Eax, 1),
+//!     &|s| s.xor_reg32_reg32(Reg32::R9d, Reg32::R9d),
+//!     &|s| s.add_reg64_imm32(Reg64::Rsp, 8),
+//!     &|s| s.push_reg64(Reg64::R12),
+//!     &|s| { let skip = s.create_label(); s.jcc_label(Condition::E, skip); s.attach_label(skip); },
+//!     &|s| s.ret_near(),
+//! ];
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! for emit in sequence {
+//!     let planned = stream.dry_run(|s| emit(s)).len();
+//!     assert_eq!(dry_run(|s| emit(s)), planned);
+//!
+//!     let start = stream.estimated_final_size();
+//!     emit(&mut stream);
+//!     assert_eq!(stream.estimated_final_size() - start, planned);
+//! }
+//!
+//! assert_eq!(stream.finish().emit().len(), 10 + 5 + 3 + 4 + 2 + 6 + 1);
+//!
+//! // a dry run measures with the encoding preferences of its stream.
+//! let mut stream = x86_64InstructionStream::new();
+//! assert_eq!(stream.dry_run(|s| s.mov(Reg64::Rax, 1u64)).len(), 10);
+//! stream.prefer_small_encodings(true);
+//! assert_eq!(stream.dry_run(|s| s.mov(Reg64::Rax, 1u64)).len(), 5);
+//! stream.abandon();
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, entity::{EntityRef, LabelRef}, InstructionStream};
+
+use crate::stream::x86_64InstructionStream;
+
+/// The code measured by a dry run, see the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DryRun {
+    /// The length of the code, in bytes.
+    len: usize,
+
+    /// The labels attached by the code, and their offsets from its start.
+    attachments: Vec<(LabelRef, usize)>,
+}
+
+impl DryRun {
+    /// The length of the measured code, in bytes.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the measured code is empty.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The labels the measured code attaches, and their offsets from its start, in the order the labels were created.
+    #[inline(always)]
+    pub fn attachments(&self) -> &[(LabelRef, usize)] {
+        &self.attachments
+    }
+
+    /// The offset from the start of the measured code `label` would be attached to, if the code attaches it.
+    pub fn label_offset(&self, label: LabelRef) -> Option<usize> {
+        self.attachments.iter().find(|(other, _)| *other == label).map(|(_, offset)| *offset)
+    }
+}
+
+/// Measures the code `emit` writes to a new instruction stream, in bytes.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::{dryrun::dry_run, register::Reg64};
+///
+/// assert_eq!(dry_run(|s| s.mov_reg64_imm64(Reg64::Rax, 0)), 10);
+/// assert_eq!(dry_run(|s| { s.push_reg64(Reg64::Rbp); s.pop_reg64(Reg64::Rbp); }), 2);
+/// ```
+pub fn dry_run(emit: impl FnOnce(&mut x86_64InstructionStream)) -> usize {
+    x86_64InstructionStream::new().dry_run(emit).len()
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Measures the code `emit` would write to the stream at its current offset, without writing it.  The code is written to a scratch stream
+    /// encoding as this one does, with the same mode, features, encoding preferences, alignment fill and frame, holding as many bytes so its
+    /// labels are aligned as they would be here.
+    ///
+    /// The code can use the labels and symbols of the stream, and the labels it creates are those the same emitters create when run on the stream
+    /// right after.  Attaching a label of the stream within a [basic block](crate::block) measures the label from the start of the block.  A pop
+    /// removing a push written before the dry run, with the [peephole optimizations](x86_64InstructionStream::enable_peephole) enabled, isn't
+    /// measured as removed.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let exit = stream.create_label();
+    ///
+    /// let body = |s: &mut x86_64InstructionStream| {
+    ///     s.push_reg64(Reg64::Rbx);
+    ///     let top = s.create_label();
+    ///     s.attach_label_aligned(top, 16);
+    ///     s.add_reg64_imm32(Reg64::Rbx, 1);
+    ///     s.jmp_label(top);
+    ///     s.attach_label(exit);
+    ///     s.pop_reg64(Reg64::Rbx);
+    /// };
+    ///
+    /// stream.write_bytes(&[0x90; 3]);
+    /// let run = stream.dry_run(body);
+    ///
+    /// // the padding aligns `top` from the offset of the stream.
+    /// let top = run.attachments()[1].0;
+    /// assert_eq!(run.attachments(), [(exit, 22), (top, 13)]);
+    /// assert_eq!(run.len(), 23);
+    ///
+    /// // with the body measured, a short jump over it is known to reach.
+    /// assert!(run.len() < 0x80);
+    /// body(&mut stream);
+    /// assert_eq!(stream.label_offset(top), Some(3 + 13));
+    /// assert_eq!(stream.label_offset(exit), Some(3 + 22));
+    /// stream.ret_near();
+    ///
+    /// assert_eq!(stream.finish().emit().len(), 3 + 23 + 1);
+    /// ```
+    pub fn dry_run(&self, emit: impl FnOnce(&mut x86_64InstructionStream)) -> DryRun {
+        let mut scratch = self.scratch();
+        let start = scratch.code().len();
+        emit(&mut scratch);
+
+        let attachments = (0..scratch.label_count())
+            .map(LabelRef::new)
+            .filter_map(|label| {
+                let offset = scratch.label_offset(label)?;
+                let before = (label.as_usize() < self.label_count()).then(|| self.label_offset(label)).flatten();
+                (before != Some(offset) && offset >= start).then_some((label, offset - start))
+            })
+            .collect();
+
+        let len = scratch.code().len() - start;
+        scratch.abandon();
+        DryRun { len, attachments }
+    }
+}
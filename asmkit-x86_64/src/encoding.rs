@@ -0,0 +1,473 @@
+//! Explicit encoding selection, for code which is patched once it is generated.
+//!
+//! The emitters pick the smallest encoding of an instruction.  Code which is live-patched sometimes needs a wider one: a 32-bit displacement, so
+//! a patch can grow the offset past the reach of 8 bits, or the *imm32* form of `add`, so the constant can be rewritten.  The `_with_options`
+//! emitters take [`EncodingOptions`] to force them, and record the forced displacements and immediates as the
+//! [`PatchField`](asmkit_core::patch::PatchField)s of the product, so a patch knows the space it has.
+//!
+//! [`emit_patch_point`](x86_64InstructionStream::emit_patch_point) reserves a whole gap instead, recorded as a
+//! [`PatchPoint`](asmkit_core::patch::PatchPoint) of the product for a runtime to turn into a call or a jump.
+//!
+//! # Example
+//! A load whose offset is patched in place, past the reach of an 8-bit displacement:
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # fn main() {
+//! use asmkit_core::{exec::ExecutableBuffer, patch::{PatchField, PatchFieldKind}, InstructionStream};
+//! use asmkit_x86_64::{encoding::EncodingOptions, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.mov_reg64_mem64_with_options(Reg64::Rax, Mem::new(Reg64::Rdi, 8), EncodingOptions::FORCE_DISP32);
+//! stream.ret_near();
+//!
+//! let product = stream.finish();
+//! assert_eq!(product.patch_fields(), [PatchField { offset: 3, size: 4, kind: PatchFieldKind::Displacement }]);
+//!
+//! let mut buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+//! let load: extern "C" fn(*const u64) -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+//!
+//! let table = (0..0x10000).collect::<Vec<u64>>();
+//! assert_eq!(load(table.as_ptr()), 1);
+//!
+//! buffer.patch_field(3, 0xabcd * 8).unwrap();
+//! assert_eq!(load(table.as_ptr()), 0xabcd);
+//! # }
+//! # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+//! # fn main() {}
+//! ```
+
+use std::ops::{BitOr, BitOrAssign};
+
+use asmkit_core::{fixed::CodeStorage, patch::{PatchFieldKind, PatchPointRef, PATCH_POINT_SIZES}, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::{Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, NOPS, REX, REX_B, REX_R, REX_W},
+};
+
+/// The encoding choices forced on an instruction, combined with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EncodingOptions(u8);
+
+impl EncodingOptions {
+    /// The smallest encoding, as the emitters without options pick.
+    pub const NONE: Self = Self(0);
+
+    /// A 32-bit displacement for memory operands, even when it fits in 8 bits or is zero.
+    pub const FORCE_DISP32: Self = Self(1 << 0);
+
+    /// The *imm32* form of the arithmetic instructions, even when the immediate fits in 8 bits, and an immediate kept even when it would be
+    /// elided by the peephole patterns.
+    pub const FORCE_IMM32: Self = Self(1 << 1);
+
+    /// A REX prefix, even when no field needs one.
+    pub const FORCE_REX: Self = Self(1 << 2);
+
+    /// Padding made of a single long `nop` rather than as few as possible, so it can be replaced by a single instruction, see
+    /// [`x86_64InstructionStream::nop_with_options`].
+    pub const LONG_NOP_STYLE: Self = Self(1 << 3);
+
+    /// The `r, r/m` opcode of a register to register instruction, such as `8b /r` rather than `89 /r` for `mov`, with the registers swapped in
+    /// the MODRM byte.
+    pub const REVERSED_OPERANDS: Self = Self(1 << 4);
+
+    /// Returns true if all the options of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if no option is set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for EncodingOptions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for EncodingOptions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes the 32-bit immediate of a move, recorded as a patchable field under [`EncodingOptions::FORCE_IMM32`].
+    fn write_mov_imm32(&mut self, imm32: u32, options: EncodingOptions) {
+        if options.contains(EncodingOptions::FORCE_IMM32) {
+            self.write_patchable_double_word(imm32, PatchFieldKind::Immediate);
+        } else {
+            self.write_double_word(imm32);
+        }
+    }
+
+    /// Move *m64* to *r64*, with the encoding choices of `options`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{patch::{PatchField, PatchFieldKind}, InstructionStream};
+    /// use asmkit_x86_64::{encoding::EncodingOptions, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg64_mem64(Reg64::Rax, Mem::new(Reg64::Rdi, 8));
+    /// stream.mov_reg64_mem64_with_options(Reg64::Rax, Mem::new(Reg64::Rdi, 8), EncodingOptions::FORCE_DISP32);
+    /// stream.mov_mem64_reg64_with_options(Mem::new(Reg64::Rsp, 0), Reg64::R9, EncodingOptions::FORCE_DISP32);
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x48, 0x8b, 0x47, 0x08,                         // mov rax, qword ptr [rdi + 8]
+    ///     0x48, 0x8b, 0x87, 0x08, 0x00, 0x00, 0x00,       // mov rax, qword ptr [rdi + 8]
+    ///     0x4c, 0x89, 0x8c, 0x24, 0x00, 0x00, 0x00, 0x00, // mov qword ptr [rsp], r9
+    /// ]);
+    ///
+    /// assert_eq!(product.patch_fields(), [
+    ///     PatchField { offset: 7, size: 4, kind: PatchFieldKind::Displacement },
+    ///     PatchField { offset: 15, size: 4, kind: PatchFieldKind::Displacement },
+    /// ]);
+    /// ```
+    pub fn mov_reg64_mem64_with_options(&mut self, dest: Reg64, src: Mem, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem_with_options(true, &[0x8b], (dest.offset(), dest.is_extension()), src, options);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r64* to *m64*, with the encoding choices of `options`.
+    pub fn mov_mem64_reg64_with_options(&mut self, dest: Mem, src: Reg64, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem_with_options(true, &[0x89], (src.offset(), src.is_extension()), dest, options);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32* to *m32*, with the encoding choices of `options`.  The immediate is always 32 bits wide, and is recorded as a patchable
+    /// field under [`EncodingOptions::FORCE_IMM32`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{patch::{PatchField, PatchFieldKind}, InstructionStream};
+    /// use asmkit_x86_64::{encoding::EncodingOptions, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let options = EncodingOptions::FORCE_DISP32 | EncodingOptions::FORCE_IMM32 | EncodingOptions::FORCE_REX;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_mem32_imm32(Mem::new(Reg64::Rsp, 0), 1);
+    /// stream.mov_mem32_imm32_with_options(Mem::new(Reg64::Rsp, 0), 1, options);
+    /// stream.mov_mem64_imm32_with_options(Mem::new(Reg64::Rbp, -8), 0, EncodingOptions::FORCE_IMM32);
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0xc7, 0x04, 0x24, 0x01, 0x00, 0x00, 0x00,                               // mov dword ptr [rsp], 1
+    ///     0x40, 0xc7, 0x84, 0x24, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // mov dword ptr [rsp], 1
+    ///     0x48, 0xc7, 0x45, 0xf8, 0x00, 0x00, 0x00, 0x00,                         // mov qword ptr [rbp - 8], 0
+    /// ]);
+    ///
+    /// assert_eq!(product.patch_fields(), [
+    ///     PatchField { offset: 11, size: 4, kind: PatchFieldKind::Displacement },
+    ///     PatchField { offset: 15, size: 4, kind: PatchFieldKind::Immediate },
+    ///     PatchField { offset: 23, size: 4, kind: PatchFieldKind::Immediate },
+    /// ]);
+    /// ```
+    pub fn mov_mem32_imm32_with_options(&mut self, dest: Mem, src: u32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(None, false, 0xc7, dest, options);
+        self.write_mov_imm32(src, options);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32*, sign extended to 64 bits, to *m64*, with the encoding choices of `options`, see
+    /// [`x86_64InstructionStream::mov_mem32_imm32_with_options`].
+    pub fn mov_mem64_imm32_with_options(&mut self, dest: Mem, src: i32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(None, true, 0xc7, dest, options);
+        self.write_mov_imm32(src as u32, options);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Add *imm32*, sign extended to 64 bits, to *r64*, with the encoding choices of `options`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{patch::{PatchField, PatchFieldKind}, InstructionStream};
+    /// use asmkit_x86_64::{encoding::EncodingOptions, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_peephole(true);
+    /// stream.add_reg64_imm32(Reg64::Rax, 0);
+    /// stream.add_reg64_imm32(Reg64::Rax, 1);
+    /// stream.add_reg64_imm32_with_options(Reg64::Rax, 0, EncodingOptions::FORCE_IMM32);
+    /// stream.cmp_reg32_imm32(Reg32::Ecx, 5);
+    /// stream.cmp_reg32_imm32_with_options(Reg32::Ecx, 5, EncodingOptions::FORCE_IMM32 | EncodingOptions::FORCE_REX);
+    /// stream.cmp_reg64_imm32_with_options(Reg64::R8, -1, EncodingOptions::NONE);
+    ///
+    /// let mut product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x48, 0x83, 0xc0, 0x01,                   // add rax, 1
+    ///     0x48, 0x81, 0xc0, 0x00, 0x00, 0x00, 0x00, // add rax, 0
+    ///     0x83, 0xf9, 0x05,                         // cmp ecx, 5
+    ///     0x40, 0x81, 0xf9, 0x05, 0x00, 0x00, 0x00, // cmp ecx, 5
+    ///     0x49, 0x83, 0xf8, 0xff,                   // cmp r8, -1
+    /// ]);
+    ///
+    /// assert_eq!(product.patch_fields(), [
+    ///     PatchField { offset: 7, size: 4, kind: PatchFieldKind::Immediate },
+    ///     PatchField { offset: 17, size: 4, kind: PatchFieldKind::Immediate },
+    /// ]);
+    ///
+    /// // the constant is rewritten in place, past the reach of an 8-bit immediate.
+    /// product.patch_field(7, -0x1000).unwrap();
+    /// assert_eq!(product.bytes()[4..11], [0x48, 0x81, 0xc0, 0x00, 0xf0, 0xff, 0xff]); // add rax, -0x1000
+    /// ```
+    pub fn add_reg64_imm32_with_options(&mut self, dest: Reg64, imm32: i32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg64_imm32(0, dest, imm32 as u32, options);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Compare *imm32*, sign extended to 64 bits, with *r64*, with the encoding choices of `options`.
+    pub fn cmp_reg64_imm32_with_options(&mut self, reg64: Reg64, imm32: i32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg64_imm32(7, reg64, imm32 as u32, options);
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg64));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Compare *imm32* with *r32*, with the encoding choices of `options`.
+    pub fn cmp_reg32_imm32_with_options(&mut self, reg32: Reg32, imm32: u32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg32_imm32(7, reg32, imm32, options);
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg32));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Move *r64* to *r64*, with the encoding choices of `options`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{encoding::EncodingOptions, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg64_reg64_with_options(Reg64::Rax, Reg64::R9, EncodingOptions::NONE);
+    /// stream.mov_reg64_reg64_with_options(Reg64::Rax, Reg64::R9, EncodingOptions::REVERSED_OPERANDS);
+    /// stream.mov_reg32_reg32_with_options(Reg32::Ecx, Reg32::Edx, EncodingOptions::FORCE_REX);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x4c, 0x89, 0xc8, // mov rax, r9
+    ///     0x49, 0x8b, 0xc1, // mov rax, r9
+    ///     0x40, 0x89, 0xd1, // mov ecx, edx
+    /// ]);
+    /// ```
+    pub fn mov_reg64_reg64_with_options(&mut self, dest: Reg64, src: Reg64, options: EncodingOptions) {
+        if options.is_empty() {
+            self.mov_reg64_reg64(dest, src);
+            return;
+        }
+
+        let instruction = self.begin_instruction();
+        self.write_reg_reg_with_options(true, 0x89, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()), options);
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r32* to *r32*, with the encoding choices of `options`.
+    pub fn mov_reg32_reg32_with_options(&mut self, dest: Reg32, src: Reg32, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg_with_options(false, 0x89, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()), options);
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Writes a two register instruction of the `op r/m, r` form like [`x86_64InstructionStream::write_reg_reg`], honoring the
+    /// [`EncodingOptions::FORCE_REX`] and [`EncodingOptions::REVERSED_OPERANDS`] options.
+    fn write_reg_reg_with_options(&mut self, rex_w: bool, opcode: u8, rm: (u8, bool), reg: (u8, bool), options: EncodingOptions) {
+        let (opcode, rm, reg) = if options.contains(EncodingOptions::REVERSED_OPERANDS) { (opcode | 0b10, reg, rm) } else { (opcode, rm, reg) };
+
+        let mut prefix = REX;
+
+        if rex_w {
+            prefix |= REX_W;
+        }
+
+        if rm.1 {
+            prefix |= REX_B;
+        }
+
+        if reg.1 {
+            prefix |= REX_R;
+        }
+
+        if prefix != REX || options.contains(EncodingOptions::FORCE_REX) {
+            self.write_byte(prefix);
+        }
+
+        self.write_byte(opcode);
+        self.write_byte((0b11 << 6) | (reg.0 << 3) | rm.0);
+    }
+
+    /// Store the effective address of *m* in *r64*, with the encoding choices of `options`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{encoding::EncodingOptions, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.lea_reg64_mem(Reg64::Rcx, Mem::new(Reg64::Rbp, -8));
+    /// stream.lea_reg64_mem_with_options(Reg64::Rcx, Mem::new(Reg64::Rbp, -8), EncodingOptions::FORCE_DISP32);
+    /// stream.lea_reg64_mem_with_options(Reg64::R10, Mem::new(Reg64::R13, 0), EncodingOptions::FORCE_DISP32);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x8d, 0x4d, 0xf8,                   // lea rcx, [rbp - 8]
+    ///     0x48, 0x8d, 0x8d, 0xf8, 0xff, 0xff, 0xff, // lea rcx, [rbp - 8]
+    ///     0x4d, 0x8d, 0x95, 0x00, 0x00, 0x00, 0x00, // lea r10, [r13]
+    /// ]);
+    /// ```
+    pub fn lea_reg64_mem_with_options(&mut self, dest: Reg64, src: Mem, options: EncodingOptions) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem_with_options(true, &[0x8d], (dest.offset(), dest.is_extension()), src, options);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Lea);
+    }
+
+    /// No operation, taking `length` bytes.  Under [`EncodingOptions::LONG_NOP_STYLE`], the padding is a single `nop` lengthened with `66`
+    /// prefixes, which a patch can replace with a single instruction of up to 15 bytes without a thread ever running half of it; otherwise,
+    /// it is made of as few `nop`s as possible, as [`x86_64InstructionStream::nop`] does.
+    ///
+    /// # Panics
+    /// Panics if `length` is more than 15 bytes, the longest instruction, under [`EncodingOptions::LONG_NOP_STYLE`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{encoding::EncodingOptions, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.nop_with_options(11, EncodingOptions::NONE);
+    /// stream.nop_with_options(11, EncodingOptions::LONG_NOP_STYLE);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,             // nop word ptr [rax + rax + 0]
+    ///     0x66, 0x90,                                                       // xchg ax, ax
+    ///     0x66, 0x66, 0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+    /// ]);
+    /// ```
+    pub fn nop_with_options(&mut self, length: usize, options: EncodingOptions) {
+        if !options.contains(EncodingOptions::LONG_NOP_STYLE) || length <= NOPS.len() {
+            self.nop(length);
+            return;
+        }
+
+        assert!(length <= 15, "a single nop can't take {} bytes, the longest instruction is 15 bytes", length);
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x66; 15][..length - NOPS.len()]);
+        self.write_bytes(NOPS[NOPS.len() - 1]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Nop);
+    }
+
+    /// Reserves a patch point: a gap of `size` bytes holding a single `nop`, recorded on the product under `id` so a runtime can turn it into a
+    /// call or a jump once the code is loaded, see [`ExecutableBuffer::patch_point_to_call`](asmkit_core::exec::ExecutableBuffer::patch_point_to_call).
+    ///
+    /// Under [`x86_64InstructionStream::enable_patch_point_padding`], a gap which would span a 64-byte boundary is moved past it, padded with the
+    /// [`Fill`](crate::stream::Fill) set by [`x86_64InstructionStream::set_align_fill`], so it can be rewritten atomically.  Within an
+    /// [atomic region](crate::region), the padding is inserted before the region.
+    ///
+    /// # Panics
+    /// Panics if `size` isn't within [`PATCH_POINT_SIZES`], or if a patch point was already emitted with the id `id`.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, patch::PatchPoint, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg32, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let slow = stream.create_label();
+    /// stream.mov_reg32_imm32(Reg32::Eax, 1);
+    /// let point = stream.emit_patch_point(6, 42);
+    /// stream.ret_near();
+    /// stream.attach_label(slow);
+    /// stream.mov_reg32_imm32(Reg32::Eax, 2);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes()[5..11], [0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00]); // nop word ptr [rax + rax + 0]
+    /// assert_eq!(product.patch_point(point), Some(&PatchPoint { id: 42, offset: 5, size: 6 }));
+    ///
+    /// let mut buffer = ExecutableBuffer::new(product, |_| unreachable!()).unwrap();
+    /// let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    /// assert_eq!(function(), 1);
+    ///
+    /// buffer.patch_point_to_call(point, buffer.as_ptr().wrapping_add(12)).unwrap();
+    /// assert_eq!(buffer.bytes()[5..11], [
+    ///     0xe8, 0x02, 0x00, 0x00, 0x00, // call slow
+    ///     0x90,                         // nop
+    /// ]);
+    /// assert_eq!(function(), 2);
+    ///
+    /// buffer.patch_point_restore_nops(point).unwrap();
+    /// assert_eq!(buffer.bytes()[5..11], [0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00]);
+    /// assert_eq!(function(), 1);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn emit_patch_point(&mut self, size: u8, id: u64) -> PatchPointRef {
+        let size = size as usize;
+        assert!(PATCH_POINT_SIZES.contains(&size), "a patch point can't take {} bytes, it takes 5 to 8", size);
+
+        let offset = self.code().len();
+        if *self.patch_point_padding() && offset / 64 != (offset + size - 1) / 64 {
+            self.write_padding(offset.wrapping_neg() & 63);
+        }
+
+        self.record_patch_point(id, size);
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(NOPS[size - 1]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Nop);
+
+        PatchPointRef(id)
+    }
+
+    /// Enables or disables the padding of the patch points emitted from now on, keeping each of them within a 64-byte cache line, see
+    /// [`x86_64InstructionStream::emit_patch_point`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{patch::PatchPoint, InstructionStream};
+    /// use asmkit_x86_64::stream::{Fill, x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_patch_point_padding(true);
+    /// stream.set_align_fill(Fill::Int3);
+    /// stream.nop(60);
+    /// stream.emit_patch_point(5, 1); // would span offsets 60 to 64
+    /// stream.emit_patch_point(8, 2);
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes()[60..64], [0xcc; 4]);
+    /// assert_eq!(product.patch_points(), [
+    ///     PatchPoint { id: 1, offset: 64, size: 5 },
+    ///     PatchPoint { id: 2, offset: 69, size: 8 },
+    /// ]);
+    /// ```
+    pub fn enable_patch_point_padding(&mut self, enable: bool) {
+        *self.patch_point_padding() = enable;
+    }
+}
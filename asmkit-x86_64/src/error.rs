@@ -0,0 +1,49 @@
+//! The errors of the x86_64 emitters.
+
+use std::{error::Error, fmt};
+
+use crate::{features::CpuFeatures, gather::OverlappingGatherRegisters, register::{GpReg, Reg8, SegmentReg}};
+
+/// An error while emitting an instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AsmError {
+    /// The instruction with the given mnemonic requires a CPU feature which isn't enabled for the target, see
+    /// [`x86_64InstructionStream::new_with_features`](crate::stream::x86_64InstructionStream::new_with_features).
+    FeatureNotEnabled(CpuFeatures, &'static str),
+
+    /// The segment register can't be the destination of a `mov`, see [`SegmentReg::check_writable`].
+    ReadOnlySegment(SegmentReg),
+
+    /// The high byte register can't be encoded in the same instruction as the register of the given name, which requires a REX prefix, see
+    /// [`Reg8::check_encodable_with`].
+    UnencodableOperands(Reg8, &'static str),
+
+    /// Two operands of a gather instruction are the same vector register, see
+    /// [`check_gather_registers`](crate::gather::check_gather_registers).
+    OverlappingGatherRegisters(OverlappingGatherRegisters),
+
+    /// The code grew to the given number of bytes, past the 2 GiB reach of the 32-bit displacements, see
+    /// [`check_code_size`](crate::stream::check_code_size).
+    CodeTooLarge(usize),
+
+    /// The code doesn't fit in the buffer of the given size, see
+    /// [`x86_64InstructionStream::finish_in_place`](crate::stream::x86_64InstructionStream::finish_in_place).
+    BufferFull(usize),
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FeatureNotEnabled(feature, mnemonic) => write!(f, "`{}` requires {}, which isn't enabled for the target", mnemonic, feature),
+            Self::ReadOnlySegment(segment) => write!(f, "`{}` can't be written by `mov`", segment.name()),
+            Self::BufferFull(capacity) => write!(f, "buffer of {} bytes is full", capacity),
+            Self::CodeTooLarge(len) => write!(f, "code too large, {} bytes exceed the 2 GiB limit", len),
+            Self::OverlappingGatherRegisters(error) => write!(f, "{}", error),
+            Self::UnencodableOperands(high, rex) => {
+                write!(f, "`{}` can't be encoded along with `{}`, which requires a REX prefix", high.name(), rex)
+            },
+        }
+    }
+}
+
+impl Error for AsmError {}
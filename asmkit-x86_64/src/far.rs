@@ -0,0 +1,231 @@
+//! Far branches, transferring control to another code segment through a far pointer, as the code switching between real, protected and long
+//! mode does.
+//!
+//! A far pointer is an offset followed by a 16-bit segment selector.  The direct far branches take it as an immediate `ptr16:16` or `ptr16:32`,
+//! and are unavailable in long mode.  The indirect ones load it from memory, as an `m16:16`, `m16:32` or `m16:64` selected by the operand size:
+//! the `0x66` prefix, the default size, or `REX.W`.  Only Intel processors load an `m16:64`: AMD processors ignore `REX.W` and load an `m16:32`.
+//!
+//! The far pointers are popped back by the far returns, [`x86_64InstructionStream::ret_far`] and its operand size variants such as
+//! [`x86_64InstructionStream::ret_far64`], and by the interrupt returns, [`x86_64InstructionStream::iret`], [`x86_64InstructionStream::iretd`]
+//! and [`x86_64InstructionStream::iretq`].
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{features::CpuFeatures, memory::Mem, mode::Mode, register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.jmp_far_mem16_64(Mem::new(Reg64::Rsp, 8));
+//! stream.jmp_far_mem16_64(Mem::new(Reg64::R12, 0));
+//! stream.jmp_far_mem16_32(Mem::new(Reg64::Rdi, 0));
+//! stream.jmp_far_mem16_16(Mem::new(Reg64::Rdi, 0));
+//! stream.call_far_mem16_64(Mem::new(Reg64::Rsp, 8));
+//! stream.call_far_mem16_32(Mem::new(Reg64::Rax, 0));
+//! stream.call_far_mem16_16(Mem::new(Reg64::R9, 16));
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x48, 0xff, 0x6c, 0x24, 0x08,       // jmp far tbyte ptr [rsp + 8]
+//!     0x49, 0xff, 0x2c, 0x24,             // jmp far tbyte ptr [r12]
+//!     0xff, 0x2f,                         // jmp far fword ptr [rdi]
+//!     0x66, 0xff, 0x2f,                   // jmp far dword ptr [rdi]
+//!     0x48, 0xff, 0x5c, 0x24, 0x08,       // call far tbyte ptr [rsp + 8]
+//!     0xff, 0x18,                         // call far fword ptr [rax]
+//!     0x66, 0x41, 0xff, 0x59, 0x10,       // call far dword ptr [r9 + 16]
+//! ]);
+//!
+//! // entering protected mode, then reloading `cs` with its code segment.
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+//! stream.jmp_far_ptr16_32(0x08, 0x0001_0000);
+//! stream.a32();
+//! stream.jmp_far_mem16_16(Mem::new(Reg64::Rdi, 0));
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x66, 0xea, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, // jmp far 0x8:0x10000
+//!     0x67, 0xff, 0x2f,                               // jmp far dword ptr [edi]
+//! ]);
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Protected, CpuFeatures::ALL);
+//! stream.jmp_far_ptr16_32(0x10, 0x1234_5678);
+//! stream.call_far_ptr16_32(0x08, 0x1000);
+//! stream.jmp_far_mem16_32(Mem::new(Reg64::Rdi, 0));
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0xea, 0x78, 0x56, 0x34, 0x12, 0x10, 0x00, // jmp far 0x10:0x12345678
+//!     0x9a, 0x00, 0x10, 0x00, 0x00, 0x08, 0x00, // call far 0x8:0x1000
+//!     0xff, 0x2f,                               // jmp far fword ptr [edi]
+//! ]);
+//! ```
+//!
+//! The direct far branches are unavailable in long mode:
+//! ```should_panic
+//! use asmkit_x86_64::stream::x86_64InstructionStream;
+//!
+//! x86_64InstructionStream::new().jmp_far_ptr16_32(0x08, 0x1000);
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    mode::Mode,
+    register::Reg64,
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Jump far, absolute, to `offset` in the segment `segment`, `jmp segment:offset`.
+    ///
+    /// # Panics
+    /// Panics outside real mode.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, mode::Mode, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+    /// let start = stream.create_label();
+    /// stream.jmp_far_ptr16_16(0x0000, 0x7c05); // reload `cs`
+    /// stream.attach_label(start);
+    /// stream.call_far_ptr16_16(0xf000, 0xfff0);
+    /// stream.call_label(start);
+    /// stream.jmp_label(start);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xea, 0x05, 0x7c, 0x00, 0x00, // ljmp 0:0x7c05
+    ///     0x9a, 0xf0, 0xff, 0x00, 0xf0, // lcall 0xf000:0xfff0
+    ///     0xe8, 0xf8, 0xff,             // call start
+    ///     0xe9, 0xf5, 0xff,             // jmp start
+    /// ]);
+    /// ```
+    pub fn jmp_far_ptr16_16(&mut self, segment: u16, offset: u16) {
+        assert!(self.mode() == Mode::Real, "`jmp` to a `ptr16:16` is unavailable in {}", self.mode());
+        self.write_far_direct(0xea, segment, &offset.to_le_bytes());
+    }
+
+    /// Call far, absolute, to `offset` in the segment `segment`, `call segment:offset`.
+    ///
+    /// # Panics
+    /// Panics outside real mode.
+    pub fn call_far_ptr16_16(&mut self, segment: u16, offset: u16) {
+        assert!(self.mode() == Mode::Real, "`call` to a `ptr16:16` is unavailable in {}", self.mode());
+        self.write_far_direct(0x9a, segment, &offset.to_le_bytes());
+    }
+
+    /// Jump far, absolute, to the 32-bit `offset` in the segment `segment`, `jmp segment:offset`.
+    ///
+    /// # Panics
+    /// Panics in long mode.
+    pub fn jmp_far_ptr16_32(&mut self, segment: u16, offset: u32) {
+        assert!(self.mode() != Mode::Long, "`jmp` to a `ptr16:32` is unavailable in long mode");
+        self.write_far_direct(0xea, segment, &offset.to_le_bytes());
+    }
+
+    /// Call far, absolute, to the 32-bit `offset` in the segment `segment`, `call segment:offset`.
+    ///
+    /// # Panics
+    /// Panics in long mode.
+    pub fn call_far_ptr16_32(&mut self, segment: u16, offset: u32) {
+        assert!(self.mode() != Mode::Long, "`call` to a `ptr16:32` is unavailable in long mode");
+        self.write_far_direct(0x9a, segment, &offset.to_le_bytes());
+    }
+
+    /// Jump far, absolute indirect, to the far pointer *m16:16*.
+    pub fn jmp_far_mem16_16(&mut self, mem: Mem) {
+        self.write_far_indirect(5, 16, mem);
+    }
+
+    /// Jump far, absolute indirect, to the far pointer *m16:32*.
+    pub fn jmp_far_mem16_32(&mut self, mem: Mem) {
+        self.write_far_indirect(5, 32, mem);
+    }
+
+    /// Jump far, absolute indirect, to the far pointer *m16:64*.  Intel processors only.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::{Mem, Scale}, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.jmp_far_mem16_64(Mem::indexed(Reg64::Rax, Reg64::Rcx, Scale::X8, 0));
+    /// stream.jmp_far_mem16_64(Mem::indexed(Reg64::R12, Reg64::R13, Scale::X2, 0x10));
+    /// stream.jmp_far_mem16_64(Mem::indexed(Reg64::Rbp, Reg64::R9, Scale::X4, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0xff, 0x2c, 0xc8,             // jmp far tbyte ptr [rax + rcx*8]
+    ///     0x4b, 0xff, 0x6c, 0x6c, 0x10,       // jmp far tbyte ptr [r12 + r13*2 + 0x10]
+    ///     0x4a, 0xff, 0x6c, 0x8d, 0x00,       // jmp far tbyte ptr [rbp + r9*4]
+    /// ]);
+    /// ```
+    pub fn jmp_far_mem16_64(&mut self, mem: Mem) {
+        self.require_long_mode("jmp far");
+        self.write_far_indirect(5, 64, mem);
+    }
+
+    /// Call far, absolute indirect, to the far pointer *m16:16*.
+    pub fn call_far_mem16_16(&mut self, mem: Mem) {
+        self.write_far_indirect(3, 16, mem);
+    }
+
+    /// Call far, absolute indirect, to the far pointer *m16:32*.
+    pub fn call_far_mem16_32(&mut self, mem: Mem) {
+        self.write_far_indirect(3, 32, mem);
+    }
+
+    /// Call far, absolute indirect, to the far pointer *m16:64*.  Intel processors only.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    pub fn call_far_mem16_64(&mut self, mem: Mem) {
+        self.require_long_mode("call far");
+        self.write_far_indirect(3, 64, mem);
+    }
+
+    /// Writes a direct far branch to `offset`, 2 or 4 bytes, in the segment `segment`.
+    fn write_far_direct(&mut self, opcode: u8, segment: u16, offset: &[u8]) {
+        let instruction = self.begin_instruction();
+
+        if offset.len() == 4 {
+            self.write_dword_operand_prefix();
+        }
+
+        self.write_byte(opcode);
+        self.write_bytes(offset);
+        self.write_word(segment);
+
+        let (family, stack) = match opcode {
+            0x9a => (InstructionFamily::Call, RegSet::of(Reg64::Rsp)),
+            _ => (InstructionFamily::Branch, RegSet::EMPTY),
+        };
+
+        self.record_registers(stack, stack);
+        self.end_instruction(instruction, family);
+    }
+
+    /// Writes an indirect far branch, `FF /digit`, through a far pointer in `mem` whose offset is `bits` wide.
+    fn write_far_indirect(&mut self, digit: u8, bits: u8, mem: Mem) {
+        let instruction = self.begin_instruction();
+
+        match bits {
+            16 => self.write_word_operand_prefix(),
+            32 => self.write_dword_operand_prefix(),
+            _ => {},
+        }
+
+        self.write_rex_modrm_mem(bits == 64, &[0xff], (digit, false), mem);
+
+        let (family, stack) = match digit {
+            3 => (InstructionFamily::Call, RegSet::of(Reg64::Rsp)),
+            _ => (InstructionFamily::Branch, RegSet::EMPTY),
+        };
+
+        self.record_registers(stack, stack | RegSet::from(mem));
+        self.end_instruction(instruction, family);
+    }
+}
@@ -0,0 +1,217 @@
+//! The CPU features of the target, gating the emitters of the instructions outside of the x86_64 baseline.
+//!
+//! A stream created with [`x86_64InstructionStream::new_with_features`](crate::stream::x86_64InstructionStream::new_with_features) rejects the
+//! instructions its target doesn't support, so code generated for an older processor can't contain an AVX instruction by accident.
+//! [`x86_64InstructionStream::new`](crate::stream::x86_64InstructionStream::new) enables every feature.
+//!
+//! `sse` and `sse2` are part of the x86_64 baseline, so their instructions are never rejected.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{error::AsmError, features::CpuFeatures, register::Xmm, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::SSE2 | CpuFeatures::SSE4_1);
+//! assert_eq!(stream.check_feature(CpuFeatures::AVX, "vaddps"), Err(AsmError::FeatureNotEnabled(CpuFeatures::AVX, "vaddps")));
+//!
+//! stream.addps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x0f, 0x58, 0xca, // addps xmm1, xmm2
+//! ]);
+//! ```
+//!
+//! The AVX instructions are rejected:
+//! ```should_panic
+//! use asmkit_x86_64::{features::CpuFeatures, register::Xmm, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::SSE2 | CpuFeatures::SSE4_1);
+//! stream.vaddps_xmm_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, Xmm::Xmm3);
+//! ```
+
+use std::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+};
+
+/// A set of CPU features, combined with `|`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CpuFeatures(u32);
+
+impl CpuFeatures {
+    /// No feature.
+    pub const NONE: Self = Self(0);
+
+    /// SSE2, part of the x86_64 baseline.
+    pub const SSE2: Self = Self(1 << 0);
+
+    /// SSE3.
+    pub const SSE3: Self = Self(1 << 1);
+
+    /// Supplemental SSE3.
+    pub const SSSE3: Self = Self(1 << 2);
+
+    /// SSE4.1, see [`crate::sse41`].
+    pub const SSE4_1: Self = Self(1 << 3);
+
+    /// SSE4.2.
+    pub const SSE4_2: Self = Self(1 << 4);
+
+    /// `popcnt`.
+    pub const POPCNT: Self = Self(1 << 5);
+
+    /// `lzcnt`.
+    pub const LZCNT: Self = Self(1 << 6);
+
+    /// The first bit manipulation instruction set, such as `andn` and `tzcnt`.
+    pub const BMI1: Self = Self(1 << 7);
+
+    /// The second bit manipulation instruction set, such as `pdep` and `shlx`.
+    pub const BMI2: Self = Self(1 << 8);
+
+    /// AVX, the VEX encoded instructions on `ymm` registers.
+    pub const AVX: Self = Self(1 << 9);
+
+    /// AVX2, such as the gathers of [`crate::gather`].
+    pub const AVX2: Self = Self(1 << 10);
+
+    /// The fused multiply-add instructions.
+    pub const FMA: Self = Self(1 << 11);
+
+    /// The AVX-512 foundation.
+    pub const AVX512F: Self = Self(1 << 12);
+
+    /// The AES-NI instructions, see [`crate::crypto`].
+    pub const AES: Self = Self(1 << 13);
+
+    /// `pclmulqdq`, see [`crate::crypto`].
+    pub const PCLMULQDQ: Self = Self(1 << 14);
+
+    /// The SHA extensions.
+    pub const SHA: Self = Self(1 << 15);
+
+    /// The restricted transactional memory instructions, see [`crate::rtm`].
+    pub const RTM: Self = Self(1 << 16);
+
+    /// `xsave` and `xrstor`, see [`crate::fpstate`].
+    pub const XSAVE: Self = Self(1 << 17);
+
+    /// `xsaveopt`, see [`crate::fpstate`].
+    pub const XSAVEOPT: Self = Self(1 << 18);
+
+    /// Every feature, as [`x86_64InstructionStream::new`](crate::stream::x86_64InstructionStream::new) enables.
+    pub const ALL: Self = Self((1 << 19) - 1);
+
+    /// The names of the features, in the order of their bits.
+    const NAMES: [&'static str; 19] = [
+        "sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "popcnt", "lzcnt", "bmi1", "bmi2", "avx", "avx2", "fma", "avx512f", "aes", "pclmulqdq", "sha",
+        "rtm", "xsave", "xsaveopt",
+    ];
+
+    /// Returns true if all the features of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if no feature is set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the features of `self` without those of `other`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::features::CpuFeatures;
+    ///
+    /// let features = CpuFeatures::ALL.without(CpuFeatures::AVX | CpuFeatures::AVX2);
+    /// assert!(features.contains(CpuFeatures::SSE4_1));
+    /// assert!(!features.contains(CpuFeatures::AVX));
+    /// ```
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The names of the features set, as accepted by [`std::arch::is_x86_feature_detected`].
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES.into_iter().enumerate().filter(move |(bit, _)| self.0 & (1 << bit) != 0).map(|(_, name)| name)
+    }
+
+    /// Detects the features of the host processor.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(target_arch = "x86_64")]
+    /// # fn main() {
+    /// use asmkit_x86_64::features::CpuFeatures;
+    ///
+    /// assert!(CpuFeatures::detect_host().contains(CpuFeatures::SSE2));
+    /// # }
+    /// # #[cfg(not(target_arch = "x86_64"))]
+    /// # fn main() {}
+    /// ```
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    pub fn detect_host() -> Self {
+        let detected = [
+            std::arch::is_x86_feature_detected!("sse2"),
+            std::arch::is_x86_feature_detected!("sse3"),
+            std::arch::is_x86_feature_detected!("ssse3"),
+            std::arch::is_x86_feature_detected!("sse4.1"),
+            std::arch::is_x86_feature_detected!("sse4.2"),
+            std::arch::is_x86_feature_detected!("popcnt"),
+            std::arch::is_x86_feature_detected!("lzcnt"),
+            std::arch::is_x86_feature_detected!("bmi1"),
+            std::arch::is_x86_feature_detected!("bmi2"),
+            std::arch::is_x86_feature_detected!("avx"),
+            std::arch::is_x86_feature_detected!("avx2"),
+            std::arch::is_x86_feature_detected!("fma"),
+            std::arch::is_x86_feature_detected!("avx512f"),
+            std::arch::is_x86_feature_detected!("aes"),
+            std::arch::is_x86_feature_detected!("pclmulqdq"),
+            std::arch::is_x86_feature_detected!("sha"),
+            std::arch::is_x86_feature_detected!("rtm"),
+            std::arch::is_x86_feature_detected!("xsave"),
+            std::arch::is_x86_feature_detected!("xsaveopt"),
+        ];
+
+        Self(detected.iter().enumerate().fold(0, |features, (bit, &detected)| features | ((detected as u32) << bit)))
+    }
+}
+
+impl BitOr for CpuFeatures {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for CpuFeatures {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, name) in self.names().enumerate() {
+            if i != 0 {
+                f.write_str(" | ")?;
+            }
+
+            f.write_str(name)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CpuFeatures({})", self)
+    }
+}
@@ -0,0 +1,124 @@
+//! Padding policies laying the code out for the instruction fetch, which reads the code by aligned 16-byte windows and caches it by 64-byte lines.
+//!
+//! Three policies are set on the stream, each off by default:
+//!
+//! - [`align_branch_targets`](crate::stream::x86_64InstructionStream::align_branch_targets) pads each label attached by
+//!   [`attach_label`](asmkit_core::InstructionStream::attach_label) to an [`Align`] boundary, with the [`Fill`](crate::stream::Fill) of the
+//!   stream, so the first fetch of a branch target reads a full window;
+//! - [`avoid_jcc_erratum_mitigation`](crate::stream::x86_64InstructionStream::avoid_jcc_erratum_mitigation) pads with `nop`s before each jump,
+//!   call and return crossing or ending on a 32-byte boundary, which the microcode of the processors affected by the JCC erratum leaves out of
+//!   the decoded instruction cache.  A conditional jump right after an arithmetic instruction, such as a `cmp` or a `test`, is fused with it by
+//!   the processor, and both are padded as one;
+//! - [`function_alignment`](crate::stream::x86_64InstructionStream::function_alignment) pads the functions started by
+//!   [`begin_function`](crate::stream::x86_64InstructionStream::begin_function).
+//!
+//! The padding is inserted where the code is written: code moved afterwards, such as the basic blocks laid out when sealed or the cold text, is
+//! padded at its offset when written.  Within an [atomic region](crate::region), the padding is inserted before the region, moving its code
+//! along.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+//!
+//! // a `cmp` and a `je` after `prefix` bytes.
+//! let emit = |prefix: usize, mitigate: bool| {
+//!     let mut stream = x86_64InstructionStream::new();
+//!     stream.avoid_jcc_erratum_mitigation(mitigate);
+//!     stream.write_bytes(&vec![0xc3; prefix]);
+//!
+//!     let done = stream.create_label();
+//!     stream.cmp_reg64_reg64(Reg64::Rax, Reg64::Rbx);
+//!     stream.jcc_label(Condition::E, done);
+//!     stream.attach_label(done);
+//!     stream.finish().emit()
+//! };
+//!
+//! // the fused pair spans 26 to 35, across the boundary at 32: it's moved to the boundary.
+//! assert_eq!(emit(26, false)[26..], [
+//!     0x48, 0x39, 0xd8,                   // cmp rax, rbx
+//!     0x0f, 0x84, 0x00, 0x00, 0x00, 0x00, // je done
+//! ]);
+//! assert_eq!(emit(26, true)[26..], [
+//!     0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+//!     0x48, 0x39, 0xd8,                   // cmp rax, rbx
+//!     0x0f, 0x84, 0x00, 0x00, 0x00, 0x00, // je done
+//! ]);
+//!
+//! // the pair ends on the boundary at 32.
+//! assert_eq!(emit(23, true).len(), 23 + 9 + 9);
+//!
+//! // the pair spans 14 to 23, within a 32-byte block, though across a 16-byte window.
+//! assert_eq!(emit(14, true), emit(14, false));
+//! assert_eq!(emit(14, true).len(), 14 + 9);
+//!
+//! // a label between the `cmp` and the `je` keeps them apart: only the `je`, spanning 29 to 35, is moved.
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.avoid_jcc_erratum_mitigation(true);
+//! stream.write_bytes(&[0xc3; 26]);
+//!
+//! let (retry, done) = (stream.create_label(), stream.create_label());
+//! stream.cmp_reg64_reg64(Reg64::Rax, Reg64::Rbx);
+//! stream.attach_label(retry);
+//! stream.jcc_label(Condition::E, done);
+//! stream.jmp_label(retry);
+//! stream.attach_label(done);
+//! assert_eq!(stream.label_offset(retry), Some(29));
+//!
+//! assert_eq!(stream.finish().emit()[26..], [
+//!     0x48, 0x39, 0xd8,                   // cmp rax, rbx
+//!     0x0f, 0x1f, 0x00,                   // retry: nop dword ptr [rax]
+//!     0x0f, 0x84, 0x05, 0x00, 0x00, 0x00, // je done
+//!     0xe9, 0xf2, 0xff, 0xff, 0xff,       // jmp retry
+//! ]);
+//! ```
+
+/// The boundary the branch targets are aligned to, see [`x86_64InstructionStream::align_branch_targets`](crate::stream::x86_64InstructionStream::align_branch_targets).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Align {
+    /// The branch targets aren't aligned.
+    #[default]
+    None,
+
+    /// The branch targets start a 16-byte fetch window.
+    To16,
+
+    /// The branch targets start a 32-byte block.
+    To32,
+
+    /// The branch targets start a 64-byte cache line.
+    To64,
+}
+
+impl Align {
+    /// The boundary, in bytes, 1 if the branch targets aren't aligned.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::None => 1,
+            Self::To16 => 16,
+            Self::To32 => 32,
+            Self::To64 => 64,
+        }
+    }
+}
+
+/// The boundary the jumps shouldn't cross or end on, for the processors affected by the JCC erratum.
+const JCC_ERRATUM_BOUNDARY: usize = 32;
+
+/// Returns true if the instruction `bytes` is a conditional jump, which the processor may fuse with the arithmetic instruction before it.
+pub(crate) fn is_jcc(bytes: &[u8]) -> bool {
+    // the branch hints `cs` and `ds`.
+    let start = bytes.iter().take_while(|&&byte| byte == 0x2e || byte == 0x3e).count();
+    matches!(bytes[start..], [0x70..=0x7f, ..] | [0x0f, 0x80..=0x8f, ..])
+}
+
+/// The padding moving the jump spanning `start..end` to the next 32-byte boundary if it crosses or ends on one, or 0 if it doesn't or is too long
+/// to fit within one block.
+pub(crate) fn jcc_erratum_padding(start: usize, end: usize) -> usize {
+    let crosses = start / JCC_ERRATUM_BOUNDARY != (end - 1) / JCC_ERRATUM_BOUNDARY;
+    if end - start >= JCC_ERRATUM_BOUNDARY || !(crosses || end.is_multiple_of(JCC_ERRATUM_BOUNDARY)) {
+        return 0;
+    }
+
+    JCC_ERRATUM_BOUNDARY - start % JCC_ERRATUM_BOUNDARY
+}
@@ -0,0 +1,316 @@
+//! The effects of the instructions on the flags of `rflags`, for the passes moving instructions around, such as checking that a `mov` can be
+//! hoisted across a `cmp`, or that nothing between a `cmp` and its `jcc` clobbers the flags the jump tests.
+//!
+//! [`flag_effects`] describes an instruction by its mnemonic as a [`FlagEffects`]: the flags it reads, those it writes and those it leaves
+//! undefined.  The description is conservative where the effects depend on the operands:
+//!
+//! - a flag which the instruction may leave unchanged, as the shifts do with a count of 0, is both read and written, since its value after the
+//!   instruction may be the one before it;
+//! - a flag which is only defined in some cases, as the overflow flag of a shift by more than 1, is both written and undefined.
+//!
+//! The instructions emitted by the text assembler expose their effects in the [listing](crate::listing), see
+//! [`EmittedInsn::flag_effects`](crate::listing::EmittedInsn::flag_effects).
+//!
+//! # Example
+//! ```
+//! use asmkit_x86_64::{flags::{flag_effects, FlagEffects, FlagSet}, stream::Condition};
+//!
+//! let status = FlagSet::STATUS;
+//! let logic = FlagEffects { reads: FlagSet::NONE, writes: status.without(FlagSet::AF), undefined: FlagSet::AF };
+//!
+//! assert_eq!(flag_effects("mov"), Some(FlagEffects::NONE));
+//! assert_eq!(flag_effects("lea"), Some(FlagEffects::NONE));
+//! assert_eq!(flag_effects("add"), Some(FlagEffects { reads: FlagSet::NONE, writes: status, undefined: FlagSet::NONE }));
+//! assert_eq!(flag_effects("adc"), Some(FlagEffects { reads: FlagSet::CF, writes: status, undefined: FlagSet::NONE }));
+//! assert_eq!(flag_effects("cmp"), flag_effects("sub"));
+//! assert_eq!(flag_effects("xor"), Some(logic));
+//! assert_eq!(flag_effects("test"), Some(logic));
+//!
+//! // `inc` and `dec` leave the carry flag alone.
+//! let inc = flag_effects("inc").unwrap();
+//! assert_eq!(inc.writes, FlagSet::OF | FlagSet::SF | FlagSet::ZF | FlagSet::AF | FlagSet::PF);
+//! assert!(!inc.touches(FlagSet::CF));
+//!
+//! // a shift by 0 leaves the flags unchanged, and the overflow flag is undefined past a count of 1.
+//! let shl = flag_effects("shl").unwrap();
+//! assert_eq!(shl.reads, status);
+//! assert_eq!(shl.writes, status.without(FlagSet::AF));
+//! assert_eq!(shl.undefined, FlagSet::OF | FlagSet::AF);
+//!
+//! let mul = flag_effects("mul").unwrap();
+//! assert_eq!((mul.writes, mul.undefined), (FlagSet::CF | FlagSet::OF, FlagSet::SF | FlagSet::ZF | FlagSet::AF | FlagSet::PF));
+//! assert_eq!(flag_effects("div").unwrap().undefined, status);
+//!
+//! assert_eq!(flag_effects("ucomisd").unwrap().writes, status);
+//! assert_eq!(flag_effects("cld").unwrap().writes, FlagSet::DF);
+//! assert_eq!(flag_effects("stosb").unwrap().reads, FlagSet::DF);
+//! assert_eq!(flag_effects("jbe").unwrap().reads, FlagSet::CF | FlagSet::ZF);
+//! assert_eq!(flag_effects("jnle"), flag_effects("jg"));
+//! assert_eq!(Condition::L.flags(), FlagSet::SF | FlagSet::OF);
+//! assert_eq!(flag_effects("loope").unwrap().reads, FlagSet::ZF);
+//! assert_eq!(flag_effects("frobnicate"), None);
+//!
+//! // a `mov` can move across a `cmp`, a `jl` can't move across a `sub`.
+//! assert!(!flag_effects("mov").unwrap().conflicts_with(&flag_effects("cmp").unwrap()));
+//! assert!(flag_effects("jl").unwrap().conflicts_with(&flag_effects("sub").unwrap()));
+//! ```
+
+use std::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+};
+
+use crate::stream::Condition;
+
+/// A set of flags of `rflags`, combined with `|`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FlagSet(u8);
+
+impl FlagSet {
+    /// No flag.
+    pub const NONE: Self = Self(0);
+
+    /// The carry flag.
+    pub const CF: Self = Self(1 << 0);
+
+    /// The parity flag, set if the low byte of the result has an even number of bits set.
+    pub const PF: Self = Self(1 << 1);
+
+    /// The auxiliary carry flag, the carry out of the low nibble.
+    pub const AF: Self = Self(1 << 2);
+
+    /// The zero flag.
+    pub const ZF: Self = Self(1 << 3);
+
+    /// The sign flag.
+    pub const SF: Self = Self(1 << 4);
+
+    /// The overflow flag.
+    pub const OF: Self = Self(1 << 5);
+
+    /// The direction flag, stepping the string instructions down when set.
+    pub const DF: Self = Self(1 << 6);
+
+    /// The interrupt enable flag.
+    pub const IF: Self = Self(1 << 7);
+
+    /// The status flags, written by the arithmetic instructions.
+    pub const STATUS: Self = Self(0b11_1111);
+
+    /// Every flag tracked.
+    pub const ALL: Self = Self(u8::MAX);
+
+    /// The names of the flags, in the order of their bits.
+    const NAMES: [&'static str; 8] = ["cf", "pf", "af", "zf", "sf", "of", "df", "if"];
+
+    /// Returns true if all the flags of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if any flag of `other` is set.
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns true if no flag is set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the flags of `self` without those of `other`.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// The names of the flags set.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES.into_iter().enumerate().filter(move |(bit, _)| self.0 & (1 << bit) != 0).map(|(_, name)| name)
+    }
+}
+
+impl BitOr for FlagSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for FlagSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Display for FlagSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, name) in self.names().enumerate() {
+            if i != 0 {
+                f.write_str(" | ")?;
+            }
+
+            f.write_str(name)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FlagSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FlagSet({})", self)
+    }
+}
+
+/// The effects of an instruction on the flags, returned by [`flag_effects`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FlagEffects {
+    /// The flags the result of the instruction depends on, including those it may leave unchanged.
+    pub reads: FlagSet,
+
+    /// The flags the instruction sets to a defined value, in all or some cases.
+    pub writes: FlagSet,
+
+    /// The flags the instruction leaves undefined, in all or some cases.
+    pub undefined: FlagSet,
+}
+
+impl FlagEffects {
+    /// No effect on the flags.
+    pub const NONE: Self = Self::new(FlagSet::NONE, FlagSet::NONE, FlagSet::NONE);
+
+    const fn new(reads: FlagSet, writes: FlagSet, undefined: FlagSet) -> Self {
+        Self { reads, writes, undefined }
+    }
+
+    /// The flags the instruction modifies, whether to a defined value or not.
+    pub fn clobbers(&self) -> FlagSet {
+        self.writes | self.undefined
+    }
+
+    /// Returns true if the instruction reads or modifies any flag of `flags`.
+    pub fn touches(&self, flags: FlagSet) -> bool {
+        (self.reads | self.clobbers()).intersects(flags)
+    }
+
+    /// Returns true if the order of this instruction and `other` matters to the flags: one of them modifies a flag the other reads or modifies.
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.clobbers().intersects(other.reads | other.clobbers()) || other.clobbers().intersects(self.reads)
+    }
+}
+
+impl Condition {
+    /// The flags tested by the condition, those its conditional instructions read, see [`FlagEffects::reads`].  A condition and its
+    /// [negation](Condition::negate) test the same flags.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{flags::{flag_effects, FlagSet}, stream::Condition};
+    ///
+    /// assert_eq!(Condition::Le.flags(), FlagSet::ZF | FlagSet::SF | FlagSet::OF);
+    /// assert_eq!(flag_effects("jle").unwrap().reads, Condition::Le.flags());
+    /// assert_eq!(flag_effects("jnae").unwrap().reads, Condition::B.flags());
+    /// ```
+    #[doc(alias = "flags_read")]
+    pub fn flags(&self) -> FlagSet {
+        match self {
+            Self::O | Self::No => FlagSet::OF,
+            Self::B | Self::Ae => FlagSet::CF,
+            Self::E | Self::Ne => FlagSet::ZF,
+            Self::Be | Self::A => FlagSet::CF | FlagSet::ZF,
+            Self::S | Self::Ns => FlagSet::SF,
+            Self::P | Self::Np => FlagSet::PF,
+            Self::L | Self::Ge => FlagSet::SF | FlagSet::OF,
+            Self::Le | Self::G => FlagSet::ZF | FlagSet::SF | FlagSet::OF,
+        }
+    }
+}
+
+/// Parses the condition code suffix of a conditional instruction, such as the `ne` of `jne`, including the aliases such as `nz`.
+fn condition(suffix: &str) -> Option<Condition> {
+    Some(match suffix {
+        "o" => Condition::O,
+        "no" => Condition::No,
+        "b" | "c" | "nae" => Condition::B,
+        "ae" | "nc" | "nb" => Condition::Ae,
+        "e" | "z" => Condition::E,
+        "ne" | "nz" => Condition::Ne,
+        "be" | "na" => Condition::Be,
+        "a" | "nbe" => Condition::A,
+        "s" => Condition::S,
+        "ns" => Condition::Ns,
+        "p" | "pe" => Condition::P,
+        "np" | "po" => Condition::Np,
+        "l" | "nge" => Condition::L,
+        "ge" | "nl" => Condition::Ge,
+        "le" | "ng" => Condition::Le,
+        "g" | "nle" => Condition::G,
+        _ => return None,
+    })
+}
+
+/// The effects on the flags of the instruction `mnemonic`, lowercase, or `None` if the mnemonic isn't one of the instructions emitted by the
+/// crate.
+///
+/// The string instructions are named with their prefix, such as `rep movsb`.  `movsd` is the SSE move, which doesn't read the direction flag.
+pub fn flag_effects(mnemonic: &str) -> Option<FlagEffects> {
+    use FlagSet as F;
+
+    let status = F::STATUS;
+    let effects = match mnemonic {
+        "add" | "sub" | "cmp" | "xadd" | "cmpxchg" => FlagEffects::new(F::NONE, status, F::NONE),
+        "adc" => FlagEffects::new(F::CF, status, F::NONE),
+        "or" | "xor" | "test" => FlagEffects::new(F::NONE, status.without(F::AF), F::AF),
+        "inc" | "dec" => FlagEffects::new(F::NONE, status.without(F::CF), F::NONE),
+        "mul" | "imul" => FlagEffects::new(F::NONE, F::CF | F::OF, F::SF | F::ZF | F::AF | F::PF),
+        "div" => FlagEffects::new(F::NONE, F::NONE, status),
+
+        // a count of 0 leaves the flags unchanged, and a count past 1 leaves the overflow flag undefined.
+        "shl" | "shr" | "sar" | "shld" => FlagEffects::new(status, status.without(F::AF), F::OF | F::AF),
+
+        // `ptest`, the comparisons of scalars, the waits and the enqueue stores clear the flags they don't set.
+        "ptest" | "ucomiss" | "ucomisd" | "xtest" | "umwait" | "tpause" | "enqcmd" | "enqcmds" => FlagEffects::new(F::NONE, status, F::NONE),
+
+        // the kernel returns with the flags saved in `r11`.
+        "syscall" => FlagEffects::NONE,
+
+        "cld" => FlagEffects::new(F::NONE, F::DF, F::NONE),
+        "cli" | "sti" | "int" | "int3" => FlagEffects::new(F::NONE, F::IF, F::NONE),
+        "iret" | "iretd" | "iretq" => FlagEffects::new(F::NONE, F::ALL, F::NONE),
+
+        "lodsb" | "lodsw" | "stosb" | "stosw" | "movsb" | "movsw" | "rep movsb" | "rep stosb" => FlagEffects::new(F::DF, F::NONE, F::NONE),
+
+        // a zero count leaves the flags unchanged.
+        "repe cmpsb" => FlagEffects::new(status | F::DF, status, F::NONE),
+
+        "loope" | "loopne" => FlagEffects::new(F::ZF, F::NONE, F::NONE),
+
+        "mov" | "movzx" | "movsxd" | "lea" | "xchg" | "push" | "pop" | "cqo" | "nop" | "call" | "jmp" | "ret" | "retf" | "loop" | "jrcxz"
+        | "lfs" | "lgs" | "lss" | "cpuid" | "mfence" | "lfence" | "sfence" | "hlt" | "swapgs" | "monitor" | "mwait" | "umonitor" | "xbegin"
+        | "xend" | "xabort" | "ptwrite" | "ldmxcsr" | "stmxcsr" | "fxsave" | "fxsave64" | "fxrstor" | "fxrstor64" | "xsave" | "xsave64"
+        | "xrstor" | "xrstor64" | "xsaveopt" | "xsaveopt64" | "movdiri" | "movdir64b" | "serialize" | "lock" | "o32" | "a32" | "retfw"
+        | "retfq" | "jmp far" | "call far" => FlagEffects::NONE,
+
+        "addps" | "addpd" | "addss" | "addsd" | "subps" | "subpd" | "subss" | "subsd" | "mulps" | "mulpd" | "mulss" | "mulsd" | "divps"
+        | "divpd" | "divss" | "divsd" | "minps" | "minpd" | "minss" | "minsd" | "maxps" | "maxpd" | "maxss" | "maxsd" | "sqrtps" | "sqrtpd"
+        | "sqrtss" | "sqrtsd" | "andps" | "andpd" | "andnps" | "andnpd" | "orps" | "orpd" | "xorps" | "xorpd" | "roundps" | "roundpd"
+        | "roundss" | "roundsd" | "blendps" | "blendpd" | "pblendw" | "blendvps" | "blendvpd" | "pblendvb" | "packusdw" | "pmaxsd" | "pmaxud"
+        | "pminsd" | "pminud" | "pmulld" | "movaps" | "movapd" | "movups" | "movupd" | "movss" | "movsd" | "movd" | "movq" | "movdqa"
+        | "movdqu" | "movntdq" | "movntdqa" | "movnti" | "movntps" | "movntpd" | "maskmovdqu" | "aesenc" | "aesenclast" | "aesdec"
+        | "aesdeclast" | "aesimc" | "aeskeygenassist" | "pclmulqdq" | "vaddps" | "vaddpd" | "vsubps" | "vsubpd" | "vmulps" | "vmulpd"
+        | "vdivps" | "vdivpd" | "vmovaps" | "vmovdqa" | "vmovdqu" | "vzeroupper" | "vgatherdps" | "vgatherdpd" | "vgatherqps" | "vgatherqpd"
+        | "vpgatherdd" | "vpgatherdq" | "vpgatherqd" | "vpgatherqq" => FlagEffects::NONE,
+
+        _ => {
+            let condition = mnemonic.strip_prefix('j').and_then(condition)?;
+            FlagEffects::new(condition.flags(), F::NONE, F::NONE)
+        },
+    };
+
+    Some(effects)
+}
@@ -0,0 +1,775 @@
+//! SSE floating point moves, arithmetic and comparisons, on scalars and packed vectors.
+//!
+//! The mandatory prefix selects the shape of the operands: none for packed single precision floats (`ps`), `66` for packed double precision floats
+//! (`pd`), `F3` for a scalar single precision float (`ss`) and `F2` for a scalar double precision float (`sd`).  The scalar forms only touch the low
+//! element of the destination, leaving the others unchanged.
+//!
+//! Every instruction has a memory operand form, reading the full vector for the packed forms and the scalar for the scalar ones.  The packed forms
+//! fault on a memory operand which isn't 16-byte aligned, except for `movups` and `movupd`: see
+//! [`x86_64InstructionStream::enable_alignment_checks`] to catch misaligned operands when the code is assembled.
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::Xmm,
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes an SSE floating point instruction from *xmm2* to *xmm1*, with the optional mandatory `prefix` and the opcode `0F opcode`.
+    fn write_float_xmm_xmm(&mut self, prefix: Option<u8>, opcode: u8, dest: Xmm, src: Xmm, family: InstructionFamily) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(prefix, false, &[opcode], reg, rm, None);
+
+        // `xorps` and `xorpd` of a register with itself are zeroing idioms.
+        let (defs, uses) = match opcode {
+            0x57 if dest == src => (RegSet::of(dest), RegSet::EMPTY),
+            _ => float_registers(prefix, opcode, dest, RegSet::of(src)),
+        };
+
+        self.record_registers(defs, uses);
+        self.end_instruction(instruction, family);
+    }
+
+    /// Writes an SSE floating point instruction between *xmm* and the memory operand `mem`, with the optional mandatory `prefix` and the opcode
+    /// `0F opcode`.
+    fn write_float_xmm_mem(&mut self, prefix: Option<u8>, opcode: u8, xmm: Xmm, mem: Mem, family: InstructionFamily) {
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(prefix, false, &[opcode], (xmm.offset(), xmm.is_extension()), mem, None);
+
+        // the scalar loads zero the rest of *xmm*, unlike the moves between registers.
+        let (defs, uses) = match opcode {
+            0x10 => (RegSet::of(xmm), RegSet::from(mem)),
+            _ => float_registers(prefix, opcode, xmm, RegSet::from(mem)),
+        };
+
+        self.record_registers(defs, uses);
+        self.end_instruction(instruction, family);
+    }
+
+    /// Move the low single precision float of *xmm2* to *xmm1*, leaving the rest of *xmm1* unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movss_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.movss_xmm_mem(Xmm::Xmm9, Mem::new(Reg64::Rsp, 4));
+    /// stream.movss_mem_xmm(Mem::new(Reg64::Rdi, 0), Xmm::Xmm0);
+    /// stream.movsd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm10);
+    /// stream.movsd_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rbp, -8));
+    /// stream.movsd_mem_xmm(Mem::new(Reg64::R12, 8), Xmm::Xmm15);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x0f, 0x10, 0xca,                   // movss xmm1, xmm2
+    ///     0xf3, 0x44, 0x0f, 0x10, 0x4c, 0x24, 0x04, // movss xmm9, dword ptr [rsp + 4]
+    ///     0xf3, 0x0f, 0x11, 0x07,                   // movss dword ptr [rdi], xmm0
+    ///     0xf2, 0x41, 0x0f, 0x10, 0xca,             // movsd xmm1, xmm10
+    ///     0xf2, 0x0f, 0x10, 0x45, 0xf8,             // movsd xmm0, qword ptr [rbp - 8]
+    ///     0xf2, 0x45, 0x0f, 0x11, 0x7c, 0x24, 0x08, // movsd qword ptr [r12 + 8], xmm15
+    /// ]);
+    /// ```
+    pub fn movss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the single precision float *m32* to the low doubleword of *xmm*, zeroing the rest of *xmm*.
+    pub fn movss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the low single precision float of *xmm* to *m32*.
+    pub fn movss_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.write_float_xmm_mem(Some(0xf3), 0x11, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Move the low double precision float of *xmm2* to *xmm1*, leaving the rest of *xmm1* unchanged.
+    pub fn movsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the double precision float *m64* to the low quadword of *xmm*, zeroing the rest of *xmm*.
+    pub fn movsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the low double precision float of *xmm* to *m64*.
+    pub fn movsd_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.write_float_xmm_mem(Some(0xf2), 0x11, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of *xmm2* to *xmm1*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movaps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.movaps_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rsp, 16));
+    /// stream.movaps_mem_xmm(Mem::new(Reg64::R13, 0), Xmm::Xmm9);
+    /// stream.movapd_xmm_mem(Xmm::Xmm2, Mem::new(Reg64::Rdi, 0x100));
+    /// stream.movups_xmm_mem(Xmm::Xmm3, Mem::new(Reg64::Rsi, 3));
+    /// stream.movupd_mem_xmm(Mem::new(Reg64::Rax, -1), Xmm::Xmm4);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0x28, 0xca,                               // movaps xmm1, xmm2
+    ///     0x0f, 0x28, 0x4c, 0x24, 0x10,                   // movaps xmm1, xmmword ptr [rsp + 16]
+    ///     0x45, 0x0f, 0x29, 0x4d, 0x00,                   // movaps xmmword ptr [r13], xmm9
+    ///     0x66, 0x0f, 0x28, 0x97, 0x00, 0x01, 0x00, 0x00, // movapd xmm2, xmmword ptr [rdi + 256]
+    ///     0x0f, 0x10, 0x5e, 0x03,                         // movups xmm3, xmmword ptr [rsi + 3]
+    ///     0x66, 0x0f, 0x11, 0x60, 0xff,                   // movupd xmmword ptr [rax - 1], xmm4
+    /// ]);
+    /// ```
+    pub fn movaps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x28, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of the 16-byte aligned *m128* to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{frame::FrameBuilder, register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_spill_64();
+    /// let vector = builder.alloc_slot(16, 8); // only 8-byte aligned, at `[rbp - 24]`
+    /// let frame = builder.freeze();
+    /// let slot = frame.slot(vector);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// stream.intrinsic_prologue(frame);
+    /// stream.movaps_xmm_mem(Xmm::Xmm0, slot);
+    /// ```
+    pub fn movaps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("movaps", src, 16);
+        self.write_float_xmm_mem(None, 0x28, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of *xmm* to the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn movaps_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movaps", dest, 16);
+        self.write_float_xmm_mem(None, 0x29, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of *xmm2* to *xmm1*.
+    pub fn movapd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x28, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of the 16-byte aligned *m128* to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn movapd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("movapd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x28, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of *xmm* to the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn movapd_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movapd", dest, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x29, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of *xmm2* to *xmm1*.
+    pub fn movups_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of the unaligned *m128* to *xmm*.
+    pub fn movups_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(None, 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed single precision floats of *xmm* to the unaligned *m128*.
+    pub fn movups_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.write_float_xmm_mem(None, 0x11, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of *xmm2* to *xmm1*.
+    pub fn movupd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of the unaligned *m128* to *xmm*.
+    pub fn movupd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0x66), 0x10, dest, src, InstructionFamily::Mov);
+    }
+
+    /// Move the packed double precision floats of *xmm* to the unaligned *m128*.
+    pub fn movupd_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.write_float_xmm_mem(Some(0x66), 0x11, src, dest, InstructionFamily::Mov);
+    }
+
+    /// Add the low single precision float of *xmm2* to *xmm1*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.addss_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.addsd_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rdi, 8));
+    /// stream.addps_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2);
+    /// stream.addpd_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::R12, 32));
+    /// stream.subsd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm10);
+    /// stream.mulss_xmm_mem(Xmm::Xmm3, Mem::new(Reg64::Rsp, 4));
+    /// stream.divpd_xmm_xmm(Xmm::Xmm4, Xmm::Xmm5);
+    /// stream.minps_xmm_mem(Xmm::Xmm6, Mem::new(Reg64::Rax, 0));
+    /// stream.maxsd_xmm_xmm(Xmm::Xmm7, Xmm::Xmm8);
+    /// stream.sqrtsd_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rbp, -8));
+    /// stream.sqrtps_xmm_xmm(Xmm::Xmm15, Xmm::Xmm0);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x0f, 0x58, 0xca,                   // addss xmm1, xmm2
+    ///     0xf2, 0x0f, 0x58, 0x47, 0x08,             // addsd xmm0, qword ptr [rdi + 8]
+    ///     0x44, 0x0f, 0x58, 0xca,                   // addps xmm9, xmm2
+    ///     0x66, 0x41, 0x0f, 0x58, 0x4c, 0x24, 0x20, // addpd xmm1, xmmword ptr [r12 + 32]
+    ///     0xf2, 0x41, 0x0f, 0x5c, 0xca,             // subsd xmm1, xmm10
+    ///     0xf3, 0x0f, 0x59, 0x5c, 0x24, 0x04,       // mulss xmm3, dword ptr [rsp + 4]
+    ///     0x66, 0x0f, 0x5e, 0xe5,                   // divpd xmm4, xmm5
+    ///     0x0f, 0x5d, 0x30,                         // minps xmm6, xmmword ptr [rax]
+    ///     0xf2, 0x41, 0x0f, 0x5f, 0xf8,             // maxsd xmm7, xmm8
+    ///     0xf2, 0x0f, 0x51, 0x45, 0xf8,             // sqrtsd xmm0, qword ptr [rbp - 8]
+    ///     0x44, 0x0f, 0x51, 0xf8,                   // sqrtps xmm15, xmm0
+    /// ]);
+    /// ```
+    pub fn addss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the low single precision float of *m32* to *xmm*.
+    pub fn addss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the low double precision float of *xmm2* to *xmm1*.
+    pub fn addsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the low double precision float of *m64* to *xmm*.
+    pub fn addsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the packed single precision floats of *xmm2* to *xmm1*.
+    pub fn addps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the packed single precision floats of the 16-byte aligned *m128* to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn addps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("addps", src, 16);
+        self.write_float_xmm_mem(None, 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the packed double precision floats of *xmm2* to *xmm1*.
+    pub fn addpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Add the packed double precision floats of the 16-byte aligned *m128* to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn addpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("addpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x58, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the low single precision float of *xmm2* from *xmm1*.
+    pub fn subss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the low single precision float of *m32* from *xmm*.
+    pub fn subss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the low double precision float of *xmm2* from *xmm1*.
+    pub fn subsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the low double precision float of *m64* from *xmm*.
+    pub fn subsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the packed single precision floats of *xmm2* from *xmm1*.
+    pub fn subps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the packed single precision floats of the 16-byte aligned *m128* from *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn subps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("subps", src, 16);
+        self.write_float_xmm_mem(None, 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the packed double precision floats of *xmm2* from *xmm1*.
+    pub fn subpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Subtract the packed double precision floats of the 16-byte aligned *m128* from *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn subpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("subpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x5c, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the low single precision float of *xmm1* by *xmm2*.
+    pub fn mulss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the low single precision float of *xmm* by *m32*.
+    pub fn mulss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the low double precision float of *xmm1* by *xmm2*.
+    pub fn mulsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the low double precision float of *xmm* by *m64*.
+    pub fn mulsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the packed single precision floats of *xmm1* by *xmm2*.
+    pub fn mulps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the packed single precision floats of *xmm* by the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn mulps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("mulps", src, 16);
+        self.write_float_xmm_mem(None, 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the packed double precision floats of *xmm1* by *xmm2*.
+    pub fn mulpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Multiply the packed double precision floats of *xmm* by the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn mulpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("mulpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x59, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the low single precision float of *xmm1* by *xmm2*.
+    pub fn divss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the low single precision float of *xmm* by *m32*.
+    pub fn divss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the low double precision float of *xmm1* by *xmm2*.
+    pub fn divsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the low double precision float of *xmm* by *m64*.
+    pub fn divsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the packed single precision floats of *xmm1* by *xmm2*.
+    pub fn divps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the packed single precision floats of *xmm* by the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn divps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("divps", src, 16);
+        self.write_float_xmm_mem(None, 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the packed double precision floats of *xmm1* by *xmm2*.
+    pub fn divpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Divide the packed double precision floats of *xmm* by the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn divpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("divpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x5e, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the low single precision float of *xmm1* and *xmm2* in *xmm1*.
+    pub fn minss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the low single precision float of *xmm* and *m32* in *xmm*.
+    pub fn minss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the low double precision float of *xmm1* and *xmm2* in *xmm1*.
+    pub fn minsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the low double precision float of *xmm* and *m64* in *xmm*.
+    pub fn minsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the packed single precision floats of *xmm1* and *xmm2* in *xmm1*.
+    pub fn minps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the packed single precision floats of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn minps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("minps", src, 16);
+        self.write_float_xmm_mem(None, 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the packed double precision floats of *xmm1* and *xmm2* in *xmm1*.
+    pub fn minpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the minimum of the packed double precision floats of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn minpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("minpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x5d, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the low single precision float of *xmm1* and *xmm2* in *xmm1*.
+    pub fn maxss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the low single precision float of *xmm* and *m32* in *xmm*.
+    pub fn maxss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the low double precision float of *xmm1* and *xmm2* in *xmm1*.
+    pub fn maxsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the low double precision float of *xmm* and *m64* in *xmm*.
+    pub fn maxsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the packed single precision floats of *xmm1* and *xmm2* in *xmm1*.
+    pub fn maxps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the packed single precision floats of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn maxps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("maxps", src, 16);
+        self.write_float_xmm_mem(None, 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the packed double precision floats of *xmm1* and *xmm2* in *xmm1*.
+    pub fn maxpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Keep the maximum of the packed double precision floats of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn maxpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("maxpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x5f, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square root of the low single precision float of *xmm2* into *xmm1*.
+    pub fn sqrtss_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf3), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square root of the low single precision float of *m32* into *xmm*.
+    pub fn sqrtss_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf3), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square root of the low double precision float of *xmm2* into *xmm1*.
+    pub fn sqrtsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0xf2), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square root of the low double precision float of *m64* into *xmm*.
+    pub fn sqrtsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.write_float_xmm_mem(Some(0xf2), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square roots of the packed single precision floats of *xmm2* into *xmm1*.
+    pub fn sqrtps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square roots of the packed single precision floats of the 16-byte aligned *m128* into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn sqrtps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("sqrtps", src, 16);
+        self.write_float_xmm_mem(None, 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square roots of the packed double precision floats of *xmm2* into *xmm1*.
+    pub fn sqrtpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compute the square roots of the packed double precision floats of the 16-byte aligned *m128* into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn sqrtpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("sqrtpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x51, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND the packed single precision floats of *xmm1* with *xmm2*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.andps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.andnpd_xmm_mem(Xmm::Xmm9, Mem::new(Reg64::Rdi, 0));
+    /// stream.orps_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rsp, 16));
+    /// stream.xorpd_xmm_xmm(Xmm::Xmm0, Xmm::Xmm0);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0x54, 0xca,             // andps xmm1, xmm2
+    ///     0x66, 0x44, 0x0f, 0x55, 0x0f, // andnpd xmm9, xmmword ptr [rdi]
+    ///     0x0f, 0x56, 0x44, 0x24, 0x10, // orps xmm0, xmmword ptr [rsp + 16]
+    ///     0x66, 0x0f, 0x57, 0xc0,       // xorpd xmm0, xmm0
+    /// ]);
+    /// ```
+    pub fn andps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x54, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND the packed single precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn andps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("andps", src, 16);
+        self.write_float_xmm_mem(None, 0x54, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND the packed double precision floats of *xmm1* with *xmm2*.
+    pub fn andpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x54, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND the packed double precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn andpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("andpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x54, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND NOT the packed single precision floats of *xmm1* with *xmm2*, as NOT *xmm1* AND *xmm2*.
+    pub fn andnps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x55, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND NOT the packed single precision floats of *xmm* with the 16-byte aligned *m128*, as NOT *xmm* AND *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn andnps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("andnps", src, 16);
+        self.write_float_xmm_mem(None, 0x55, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND NOT the packed double precision floats of *xmm1* with *xmm2*, as NOT *xmm1* AND *xmm2*.
+    pub fn andnpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x55, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise AND NOT the packed double precision floats of *xmm* with the 16-byte aligned *m128*, as NOT *xmm* AND *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn andnpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("andnpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x55, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise OR the packed single precision floats of *xmm1* with *xmm2*.
+    pub fn orps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x56, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise OR the packed single precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn orps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("orps", src, 16);
+        self.write_float_xmm_mem(None, 0x56, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise OR the packed double precision floats of *xmm1* with *xmm2*.
+    pub fn orpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x56, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise OR the packed double precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn orpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("orpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x56, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise XOR the packed single precision floats of *xmm1* with *xmm2*.
+    pub fn xorps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(None, 0x57, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise XOR the packed single precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xorps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("xorps", src, 16);
+        self.write_float_xmm_mem(None, 0x57, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise XOR the packed double precision floats of *xmm1* with *xmm2*.
+    pub fn xorpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x57, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Bitwise XOR the packed double precision floats of *xmm* with the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xorpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("xorpd", src, 16);
+        self.write_float_xmm_mem(Some(0x66), 0x57, dest, src, InstructionFamily::Vector);
+    }
+
+    /// Compare the low single precision floats of *xmm1* and *xmm2*, setting the zero, parity and carry flags as an unsigned comparison would, with
+    /// all three set if either is NaN.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.ucomiss_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.ucomiss_xmm_mem(Xmm::Xmm9, Mem::new(Reg64::Rsp, 4));
+    /// stream.ucomisd_xmm_xmm(Xmm::Xmm0, Xmm::Xmm10);
+    /// stream.ucomisd_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rdi, 8));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0x2e, 0xca,                   // ucomiss xmm1, xmm2
+    ///     0x44, 0x0f, 0x2e, 0x4c, 0x24, 0x04, // ucomiss xmm9, dword ptr [rsp + 4]
+    ///     0x66, 0x41, 0x0f, 0x2e, 0xc2,       // ucomisd xmm0, xmm10
+    ///     0x66, 0x0f, 0x2e, 0x47, 0x08,       // ucomisd xmm0, qword ptr [rdi + 8]
+    /// ]);
+    /// ```
+    pub fn ucomiss_xmm_xmm(&mut self, src1: Xmm, src2: Xmm) {
+        self.write_float_xmm_xmm(None, 0x2e, src1, src2, InstructionFamily::Vector);
+    }
+
+    /// Compare the low single precision float of *xmm* and *m32*, see [`x86_64InstructionStream::ucomiss_xmm_xmm`].
+    pub fn ucomiss_xmm_mem(&mut self, src1: Xmm, src2: Mem) {
+        self.write_float_xmm_mem(None, 0x2e, src1, src2, InstructionFamily::Vector);
+    }
+
+    /// Compare the low double precision floats of *xmm1* and *xmm2*, setting the zero, parity and carry flags as an unsigned comparison would, with
+    /// all three set if either is NaN.
+    pub fn ucomisd_xmm_xmm(&mut self, src1: Xmm, src2: Xmm) {
+        self.write_float_xmm_xmm(Some(0x66), 0x2e, src1, src2, InstructionFamily::Vector);
+    }
+
+    /// Compare the low double precision float of *xmm* and *m64*, see [`x86_64InstructionStream::ucomisd_xmm_xmm`].
+    pub fn ucomisd_xmm_mem(&mut self, src1: Xmm, src2: Mem) {
+        self.write_float_xmm_mem(Some(0x66), 0x2e, src1, src2, InstructionFamily::Vector);
+    }
+}
+
+/// The registers defined and used by the SSE floating point instruction `0F opcode` with the optional mandatory `prefix`, between *xmm* and the
+/// operand addressed by the registers `other`.  The scalar forms merge into *xmm*, which they both define and use.
+fn float_registers(prefix: Option<u8>, opcode: u8, xmm: Xmm, other: RegSet) -> (RegSet, RegSet) {
+    let scalar = matches!(prefix, Some(0xf3 | 0xf2));
+
+    match opcode {
+        0x11 | 0x29 | 0x2e => (RegSet::EMPTY, other.with(xmm)), // stores and comparisons
+        0x10 | 0x28 | 0x51 if !scalar => (RegSet::of(xmm), other), // moves and square roots of the whole vector
+        _ => (RegSet::of(xmm), other.with(xmm)),
+    }
+}
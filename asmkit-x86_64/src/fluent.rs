@@ -0,0 +1,754 @@
+//! A fluent interface to the instruction stream, chaining emitters.
+//!
+//! The emitters of [`x86_64InstructionStream`] return nothing, which keeps them usable as the emitters of the instruction table.  [`Fluent`] wraps a
+//! stream, and forwards every emitter as a method returning the wrapper, so long sequences read as one expression:
+//!
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut fluent = x86_64InstructionStream::new();
+//! fluent.fluent()
+//!     .push_reg64(Reg64::Rbp)
+//!     .mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp)
+//!     .sub_reg64_imm32(Reg64::Rsp, 32);
+//!
+//! let mut statements = x86_64InstructionStream::new();
+//! statements.push_reg64(Reg64::Rbp);
+//! statements.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+//! statements.sub_reg64_imm32(Reg64::Rsp, 32);
+//!
+//! assert_eq!(fluent.finish(), statements.finish());
+//! ```
+//!
+//! The settings of the stream, such as [`x86_64InstructionStream::set_code_model`], are left to the stream itself.
+
+use core::sync::atomic::Ordering;
+
+use asmkit_core::{
+    entity::{LabelRef, SymRef},
+    fixed::CodeStorage,
+    InstructionStream,
+};
+
+use crate::{
+    callconv::CallConv,
+    encoding::EncodingOptions,
+    frame::{Frame, SlotRef},
+    generic::{Add, Cmp, CmpOperand, Mov, Sub},
+    memops::MemcpyLen,
+    memory::{Mem, VsibMem},
+    register::{Reg16, Reg32, Reg64, Reg8, SegmentReg, Xmm, Ymm},
+    safepoint::StackMapEntry,
+    scratch::ScratchPool,
+    section::Section,
+    sse41::RoundingControl,
+    stream::{x86_64InstructionStream, CallTarget, Condition},
+    system::InterruptEntry,
+    wide::Dividend,
+};
+
+/// Forwards emitters of the instruction stream, returning the wrapper.
+macro_rules! forward {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[doc = concat!("Emits [`x86_64InstructionStream::", stringify!($name), "`].")]
+            #[inline(always)]
+            pub fn $name(&mut self, $($arg: $ty),*) -> &mut Self {
+                self.stream.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+/// An instruction stream whose emitters chain, returned by [`x86_64InstructionStream::fluent`].
+pub struct Fluent<'a, S: CodeStorage = Vec<u8>> {
+    /// The wrapped instruction stream.
+    stream: &'a mut x86_64InstructionStream<S>,
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Wraps the instruction stream in a [`Fluent`], whose emitters chain.
+    #[inline(always)]
+    pub fn fluent(&mut self) -> Fluent<'_, S> {
+        Fluent { stream: self }
+    }
+}
+
+impl<S: CodeStorage> Fluent<'_, S> {
+    /// The wrapped instruction stream, for the methods which aren't forwarded.
+    #[inline(always)]
+    pub fn stream(&mut self) -> &mut x86_64InstructionStream<S> {
+        self.stream
+    }
+
+    /// Creates a label and passes it to `f`, for the chains which use a label before attaching it.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// // counts rdi down to zero.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.fluent().with_label(|fluent, top| {
+    ///     fluent
+    ///         .attach_label(top)
+    ///         .sub_reg64_imm32(Reg64::Rdi, 1)
+    ///         .jcc_label(Condition::Ne, top)
+    ///         .ret_near();
+    /// });
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x83, 0xef, 0x01,             // sub rdi, 1
+    ///     0x0f, 0x85, 0xf6, 0xff, 0xff, 0xff, // jne top
+    ///     0xc3,                               // ret
+    /// ]);
+    /// ```
+    pub fn with_label(&mut self, f: impl FnOnce(&mut Self, LabelRef)) -> &mut Self {
+        let label = self.stream.create_label();
+        f(self, label);
+        self
+    }
+
+    /// Creates a label attached to the current offset and passes it to `f`, such as the top of a loop.
+    pub fn with_label_attached(&mut self, f: impl FnOnce(&mut Self, LabelRef)) -> &mut Self {
+        let label = self.stream.create_label_attached();
+        f(self, label);
+        self
+    }
+
+    /// Attaches `label` to the current offset, see [`InstructionStream::attach_label`].
+    #[inline(always)]
+    pub fn attach_label(&mut self, label: LabelRef) -> &mut Self {
+        self.stream.attach_label(label);
+        self
+    }
+
+    /// Attaches a comment to the current offset, see [`x86_64InstructionStream::comment`].
+    #[inline(always)]
+    pub fn comment(&mut self, text: impl Into<String>) -> &mut Self {
+        self.stream.comment(text);
+        self
+    }
+
+    /// Sets the location of the source code of the next instructions, see [`x86_64InstructionStream::set_source_location`].
+    #[inline(always)]
+    pub fn set_source_location(&mut self, file_id: u32, line: u32, col: u32) -> &mut Self {
+        self.stream.set_source_location(file_id, line, col);
+        self
+    }
+
+    /// Clears the location of the source code of the next instructions, see [`x86_64InstructionStream::clear_source_location`].
+    #[inline(always)]
+    pub fn clear_source_location(&mut self) -> &mut Self {
+        self.stream.clear_source_location();
+        self
+    }
+
+    /// Writes a byte, see [`InstructionStream::write_byte`].
+    #[inline(always)]
+    pub fn write_byte(&mut self, byte: u8) -> &mut Self {
+        self.stream.write_byte(byte);
+        self
+    }
+
+    /// Writes bytes, see [`InstructionStream::write_bytes`].
+    #[inline(always)]
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.stream.write_bytes(bytes);
+        self
+    }
+
+    /// Emits a polymorphic `mov`, see [`Mov`].
+    #[inline(always)]
+    pub fn mov<D, T>(&mut self, dest: D, src: T) -> &mut Self
+    where
+        x86_64InstructionStream<S>: Mov<D, T>,
+    {
+        self.stream.mov(dest, src);
+        self
+    }
+
+    /// Emits a polymorphic `add`, see [`Add`].
+    #[inline(always)]
+    pub fn add<D, T>(&mut self, dest: D, src: T) -> &mut Self
+    where
+        x86_64InstructionStream<S>: Add<D, T>,
+    {
+        self.stream.add(dest, src);
+        self
+    }
+
+    /// Emits a polymorphic `sub`, see [`Sub`].
+    #[inline(always)]
+    pub fn sub<D, T>(&mut self, dest: D, src: T) -> &mut Self
+    where
+        x86_64InstructionStream<S>: Sub<D, T>,
+    {
+        self.stream.sub(dest, src);
+        self
+    }
+
+    /// Emits a comparison fused with a conditional jump, see [`x86_64InstructionStream::cmp_jcc`].
+    #[inline(always)]
+    pub fn cmp_jcc(&mut self, lhs: Reg64, rhs: impl Into<CmpOperand<Reg64>>, condition: Condition, target: LabelRef) -> &mut Self {
+        self.stream.cmp_jcc(lhs, rhs, condition, target);
+        self
+    }
+
+    /// Emits a 32-bit comparison fused with a conditional jump, see [`x86_64InstructionStream::cmp_jcc32`].
+    #[inline(always)]
+    pub fn cmp_jcc32(&mut self, lhs: Reg32, rhs: impl Into<CmpOperand<Reg32>>, condition: Condition, target: LabelRef) -> &mut Self {
+        self.stream.cmp_jcc32(lhs, rhs, condition, target);
+        self
+    }
+
+    /// Emits a polymorphic `cmp`, see [`Cmp`].
+    #[inline(always)]
+    pub fn cmp<D, T>(&mut self, dest: D, src: T) -> &mut Self
+    where
+        x86_64InstructionStream<S>: Cmp<D, T>,
+    {
+        self.stream.cmp(dest, src);
+        self
+    }
+
+    forward! {
+        // Integer, stack, control flow and label instructions, see the [`stream`](crate::stream) module.
+        define_symbol(name: &str, label: LabelRef);
+        define_raw_symbol(name: &str, label: LabelRef);
+        name_label(label: LabelRef, name: &str);
+        attach_label_aligned(label: LabelRef, align: usize);
+        begin_function(label: LabelRef);
+        push_region(name: &str);
+        pop_region();
+        load_label_address(dest: Reg64, label: LabelRef);
+        load_symbol_address(dest: Reg64, symbol: SymRef);
+        mov_reg64_got_symbol(dest: Reg64, symbol: SymRef);
+        lea_reg64_symbol(dest: Reg64, symbol: SymRef);
+        intrinsic_push_rbp();
+        intrinsic_prologue(frame: Frame);
+        intrinsic_prologue_probed(frame: Frame, convention: CallConv);
+        intrinsic_alloc_stack_probed(size: u32, convention: CallConv);
+        intrinsic_epilogue();
+        intrinsic_tail_call(target: CallTarget);
+        mov_slot_reg64(slot: SlotRef, src: Reg64);
+        mov_reg64_slot(dest: Reg64, slot: SlotRef);
+        mov_mem64_reg64(dest: Mem, src: Reg64);
+        mov_reg64_mem64(dest: Reg64, src: Mem);
+        mov_mem32_reg32(dest: Mem, src: Reg32);
+        mov_reg32_mem32(dest: Reg32, src: Mem);
+        mov_mem16_reg16(dest: Mem, src: Reg16);
+        mov_reg16_mem16(dest: Reg16, src: Mem);
+        mov_mem8_reg8(dest: Mem, src: Reg8);
+        mov_reg8_mem8(dest: Reg8, src: Mem);
+        mov_mem8_imm8(dest: Mem, src: u8);
+        mov_mem16_imm16(dest: Mem, src: u16);
+        mov_mem32_imm32(dest: Mem, src: u32);
+        mov_mem64_imm32(dest: Mem, src: i32);
+        mov_mem64_imm32_unsigned(dest: Mem, src: u32);
+        mov_reg64_mem64_with_options(dest: Reg64, src: Mem, options: EncodingOptions);
+        mov_mem64_reg64_with_options(dest: Mem, src: Reg64, options: EncodingOptions);
+        mov_mem32_imm32_with_options(dest: Mem, src: u32, options: EncodingOptions);
+        mov_mem64_imm32_with_options(dest: Mem, src: i32, options: EncodingOptions);
+        mov_reg64_label(dest: Reg64, label: LabelRef);
+        mov_label8_imm8(label: LabelRef, src: u8);
+        mov_label16_imm16(label: LabelRef, src: u16);
+        mov_label32_imm32(label: LabelRef, src: u32);
+        mov_label64_imm32(label: LabelRef, src: i32);
+        mov_label64_imm32_unsigned(label: LabelRef, src: u32);
+        xchg_reg64_reg64(dest: Reg64, src: Reg64);
+        xchg_mem64_reg64(dest: Mem, src: Reg64);
+        xadd_mem64_reg64(dest: Mem, src: Reg64);
+        cmpxchg_mem64_reg64(dest: Mem, src: Reg64);
+        lock();
+        o32();
+        a32();
+        mfence();
+        lfence();
+        sfence();
+        add_reg64_imm32(dest: Reg64, imm32: i32);
+        add_reg64_imm32_unsigned(dest: Reg64, imm32: u32);
+        sub_reg64_imm32(dest: Reg64, imm32: i32);
+        sub_reg64_imm32_unsigned(dest: Reg64, imm32: u32);
+        add_reg64_imm32_with_options(dest: Reg64, imm32: i32, options: EncodingOptions);
+        mov_reg8_reg8(dest: Reg8, src: Reg8);
+        mov_reg16_reg16(dest: Reg16, src: Reg16);
+        mov_reg32_reg32(dest: Reg32, src: Reg32);
+        mov_reg64_reg64(dest: Reg64, src: Reg64);
+        mov_reg64_reg64_with_options(dest: Reg64, src: Reg64, options: EncodingOptions);
+        mov_reg32_reg32_with_options(dest: Reg32, src: Reg32, options: EncodingOptions);
+        mov_reg8_imm8(dest: Reg8, src: u8);
+        mov_reg16_imm16(dest: Reg16, src: u16);
+        mov_reg32_imm32(dest: Reg32, src: u32);
+        mov_reg64_imm32(dest: Reg64, src: i32);
+        mov_reg64_imm32_unsigned(dest: Reg64, src: u32);
+        mov_reg64_imm64(dest: Reg64, src: u64);
+        push_reg16(reg16: Reg16);
+        push_reg64(reg64: Reg64);
+        pop_reg64(reg64: Reg64);
+        push_imm8(imm8: i8);
+        push_imm8_unsigned(imm8: u8);
+        push_imm16(imm16: u16);
+        push_imm32(imm32: i32);
+        push_imm32_unsigned(imm32: u32);
+        push_fs();
+        push_gs();
+        nop(length: usize);
+        nop_with_options(length: usize, options: EncodingOptions);
+        ret_near();
+        ret_far();
+        ret_near_imm16(imm16: u16);
+        ret_far_imm16(imm16: u16);
+        ret_far16();
+        ret_far16_imm16(imm16: u16);
+        ret_far64();
+        ret_far64_imm16(imm16: u16);
+        jmp_label(label: LabelRef);
+        jcc_label(condition: Condition, label: LabelRef);
+        jmp_short_label(label: LabelRef);
+        jcc_short_label(condition: Condition, label: LabelRef);
+        call_label(label: LabelRef);
+        call_symbol(symbol: SymRef);
+        jmp_symbol(symbol: SymRef);
+        jmp_rel32(rel32: i32);
+        call_reg64(reg64: Reg64);
+        jmp_reg64(reg64: Reg64);
+        loop_label(label: LabelRef);
+        loope_label(label: LabelRef);
+        loopne_label(label: LabelRef);
+        jrcxz_label(label: LabelRef);
+        call_mem(mem: Mem);
+        jmp_mem(mem: Mem);
+        call_mem_scaled(base: Reg64, index: Reg64);
+        jmp_mem_scaled(base: Reg64, index: Reg64);
+        call_mem_label(label: LabelRef);
+        jmp_mem_label(label: LabelRef);
+        call_mem_symbol(symbol: SymRef);
+        jmp_mem_symbol(symbol: SymRef);
+        cmp_reg64_imm32(reg64: Reg64, imm32: i32);
+        cmp_reg64_imm32_unsigned(reg64: Reg64, imm32: u32);
+        cmp_reg32_imm32(reg32: Reg32, imm32: u32);
+        add_reg32_imm32(dest: Reg32, imm32: u32);
+        sub_reg32_imm32(dest: Reg32, imm32: u32);
+        cmp_reg64_imm32_with_options(reg64: Reg64, imm32: i32, options: EncodingOptions);
+        cmp_reg32_imm32_with_options(reg32: Reg32, imm32: u32, options: EncodingOptions);
+        cmp_reg64_reg64(lhs: Reg64, rhs: Reg64);
+        cmp_reg32_reg32(lhs: Reg32, rhs: Reg32);
+        test_mem8_imm8(mem: Mem, imm8: u8);
+        test_reg64_reg64(lhs: Reg64, rhs: Reg64);
+        test_reg32_reg32(lhs: Reg32, rhs: Reg32);
+        test_reg8_reg8(lhs: Reg8, rhs: Reg8);
+        add_reg64_reg64(dest: Reg64, src: Reg64);
+        adc_reg64_reg64(dest: Reg64, src: Reg64);
+        sub_reg64_reg64(dest: Reg64, src: Reg64);
+        sub_reg32_reg32(dest: Reg32, src: Reg32);
+        or_mem64_imm8(dest: Mem, imm8: i8);
+        or_mem64_imm8_unsigned(dest: Mem, imm8: u8);
+        xor_reg32_reg32(dest: Reg32, src: Reg32);
+        shl_reg64_imm8(dest: Reg64, imm8: u8);
+        shl_reg64_cl(dest: Reg64);
+        shr_reg64_cl(dest: Reg64);
+        sar_reg64_cl(dest: Reg64);
+        shld_reg64_reg64_imm8(dest: Reg64, src: Reg64, imm8: u8);
+        mul_wide(src: Reg64);
+        imul_wide(src: Reg64);
+        cqo();
+        div_wide(divisor: Reg64, dividend: Dividend);
+        intrinsic_add_u128(lo_dst: Reg64, hi_dst: Reg64, lo_src: Reg64, hi_src: Reg64);
+        intrinsic_mul_u64_u64_to_u128(lhs: Reg64, rhs: Reg64);
+        intrinsic_shl_u128(lo: Reg64, hi: Reg64, amount: u8);
+        lea_reg64_label(dest: Reg64, label: LabelRef);
+        lea_reg64_mem(dest: Reg64, src: Mem);
+        lea_reg64_mem_with_options(dest: Reg64, src: Mem, options: EncodingOptions);
+        movsxd_reg64_mem32_scaled(dest: Reg64, base: Reg64, index: Reg64);
+        movsxd_reg64_reg32(dest: Reg64, src: Reg32);
+        movsxd_reg64_mem32(dest: Reg64, src: Mem);
+        movzx_reg32_mem8(dest: Reg32, src: Mem);
+        attach_jump_table(table: LabelRef, entries: &[LabelRef]);
+        data_label_diff(a: LabelRef, b: LabelRef, size: usize, shift: u32);
+        data_label_diff_u8(a: LabelRef, b: LabelRef);
+        data_label_diff_u16(a: LabelRef, b: LabelRef);
+        data_label_diff_u32(a: LabelRef, b: LabelRef);
+        data_label_diff_u64(a: LabelRef, b: LabelRef);
+        switch_via_table(index: Reg64, table: LabelRef, scratch: Reg64, bounds: Option<(u32, LabelRef)>);
+        switch_via_table_with_pool(index: Reg64, table: LabelRef, pool: &ScratchPool, bounds: Option<(u32, LabelRef)>);
+    }
+
+    forward! {
+        // Atomics, see the [`atomic`](crate::atomic) module.
+        atomic_load_reg64_mem(dest: Reg64, src: Mem, ordering: Ordering);
+        atomic_store_mem_reg64(dest: Mem, src: Reg64, ordering: Ordering);
+        atomic_fetch_add(dest: Mem, src: Reg64, ordering: Ordering);
+        atomic_compare_exchange(dest: Mem, new: Reg64, success: Ordering, failure: Ordering);
+        atomic_fence(ordering: Ordering);
+    }
+
+    forward! {
+        // Transfers between general purpose and `xmm` registers, see the [`sse`](crate::sse) module.
+        movd_xmm_reg32(dest: Xmm, src: Reg32);
+        movd_reg32_xmm(dest: Reg32, src: Xmm);
+        movq_xmm_reg64(dest: Xmm, src: Reg64);
+        movq_reg64_xmm(dest: Reg64, src: Xmm);
+        movq_xmm_xmm(dest: Xmm, src: Xmm);
+        movq_xmm_xmm_mr(dest: Xmm, src: Xmm);
+        movdqu_xmm_mem(dest: Xmm, src: Mem);
+        movdqu_mem_xmm(dest: Mem, src: Xmm);
+        movdqa_xmm_xmm(dest: Xmm, src: Xmm);
+        movdqa_xmm_mem(dest: Xmm, src: Mem);
+        movdqa_mem_xmm(dest: Mem, src: Xmm);
+        movq_xmm_mem(dest: Xmm, src: Mem);
+        movq_mem_xmm(dest: Mem, src: Xmm);
+        bitcast_f64_to_u64(dest: Reg64, src: Xmm);
+        bitcast_u64_to_f64(dest: Xmm, src: Reg64);
+        bitcast_f32_to_u32(dest: Reg32, src: Xmm);
+        bitcast_u32_to_f32(dest: Xmm, src: Reg32);
+    }
+
+    forward! {
+        // SSE4.1, see the [`sse41`](crate::sse41) module.
+        roundps_xmm_xmm(dest: Xmm, src: Xmm, rounding: RoundingControl);
+        roundpd_xmm_xmm(dest: Xmm, src: Xmm, rounding: RoundingControl);
+        roundss_xmm_xmm(dest: Xmm, src: Xmm, rounding: RoundingControl);
+        roundsd_xmm_xmm(dest: Xmm, src: Xmm, rounding: RoundingControl);
+        blendps_xmm_xmm_imm8(dest: Xmm, src: Xmm, imm8: u8);
+        blendpd_xmm_xmm_imm8(dest: Xmm, src: Xmm, imm8: u8);
+        pblendw_xmm_xmm_imm8(dest: Xmm, src: Xmm, imm8: u8);
+        blendvps_xmm_xmm(dest: Xmm, src: Xmm);
+        blendvpd_xmm_xmm(dest: Xmm, src: Xmm);
+        pblendvb_xmm_xmm(dest: Xmm, src: Xmm);
+        ptest_xmm_xmm(src1: Xmm, src2: Xmm);
+        pmulld_xmm_xmm(dest: Xmm, src: Xmm);
+        pminsd_xmm_xmm(dest: Xmm, src: Xmm);
+        pmaxsd_xmm_xmm(dest: Xmm, src: Xmm);
+        pminud_xmm_xmm(dest: Xmm, src: Xmm);
+        pmaxud_xmm_xmm(dest: Xmm, src: Xmm);
+        packusdw_xmm_xmm(dest: Xmm, src: Xmm);
+        roundps_xmm_mem(dest: Xmm, src: Mem, rounding: RoundingControl);
+        roundpd_xmm_mem(dest: Xmm, src: Mem, rounding: RoundingControl);
+        roundss_xmm_mem(dest: Xmm, src: Mem, rounding: RoundingControl);
+        roundsd_xmm_mem(dest: Xmm, src: Mem, rounding: RoundingControl);
+        blendps_xmm_mem_imm8(dest: Xmm, src: Mem, imm8: u8);
+        blendpd_xmm_mem_imm8(dest: Xmm, src: Mem, imm8: u8);
+        pblendw_xmm_mem_imm8(dest: Xmm, src: Mem, imm8: u8);
+        blendvps_xmm_mem(dest: Xmm, src: Mem);
+        blendvpd_xmm_mem(dest: Xmm, src: Mem);
+        pblendvb_xmm_mem(dest: Xmm, src: Mem);
+        ptest_xmm_mem(src1: Xmm, src: Mem);
+        pmulld_xmm_mem(dest: Xmm, src: Mem);
+        pminsd_xmm_mem(dest: Xmm, src: Mem);
+        pmaxsd_xmm_mem(dest: Xmm, src: Mem);
+        pminud_xmm_mem(dest: Xmm, src: Mem);
+        pmaxud_xmm_mem(dest: Xmm, src: Mem);
+        packusdw_xmm_mem(dest: Xmm, src: Mem);
+    }
+
+    forward! {
+        // Floating point moves and arithmetic, see the [`float`](crate::float) module.
+        movss_xmm_xmm(dest: Xmm, src: Xmm);
+        movss_xmm_mem(dest: Xmm, src: Mem);
+        movss_mem_xmm(dest: Mem, src: Xmm);
+        movsd_xmm_xmm(dest: Xmm, src: Xmm);
+        movsd_xmm_mem(dest: Xmm, src: Mem);
+        movsd_mem_xmm(dest: Mem, src: Xmm);
+        movaps_xmm_xmm(dest: Xmm, src: Xmm);
+        movaps_xmm_mem(dest: Xmm, src: Mem);
+        movaps_mem_xmm(dest: Mem, src: Xmm);
+        movapd_xmm_xmm(dest: Xmm, src: Xmm);
+        movapd_xmm_mem(dest: Xmm, src: Mem);
+        movapd_mem_xmm(dest: Mem, src: Xmm);
+        movups_xmm_xmm(dest: Xmm, src: Xmm);
+        movups_xmm_mem(dest: Xmm, src: Mem);
+        movups_mem_xmm(dest: Mem, src: Xmm);
+        movupd_xmm_xmm(dest: Xmm, src: Xmm);
+        movupd_xmm_mem(dest: Xmm, src: Mem);
+        movupd_mem_xmm(dest: Mem, src: Xmm);
+        addss_xmm_xmm(dest: Xmm, src: Xmm);
+        addss_xmm_mem(dest: Xmm, src: Mem);
+        addsd_xmm_xmm(dest: Xmm, src: Xmm);
+        addsd_xmm_mem(dest: Xmm, src: Mem);
+        addps_xmm_xmm(dest: Xmm, src: Xmm);
+        addps_xmm_mem(dest: Xmm, src: Mem);
+        addpd_xmm_xmm(dest: Xmm, src: Xmm);
+        addpd_xmm_mem(dest: Xmm, src: Mem);
+        subss_xmm_xmm(dest: Xmm, src: Xmm);
+        subss_xmm_mem(dest: Xmm, src: Mem);
+        subsd_xmm_xmm(dest: Xmm, src: Xmm);
+        subsd_xmm_mem(dest: Xmm, src: Mem);
+        subps_xmm_xmm(dest: Xmm, src: Xmm);
+        subps_xmm_mem(dest: Xmm, src: Mem);
+        subpd_xmm_xmm(dest: Xmm, src: Xmm);
+        subpd_xmm_mem(dest: Xmm, src: Mem);
+        mulss_xmm_xmm(dest: Xmm, src: Xmm);
+        mulss_xmm_mem(dest: Xmm, src: Mem);
+        mulsd_xmm_xmm(dest: Xmm, src: Xmm);
+        mulsd_xmm_mem(dest: Xmm, src: Mem);
+        mulps_xmm_xmm(dest: Xmm, src: Xmm);
+        mulps_xmm_mem(dest: Xmm, src: Mem);
+        mulpd_xmm_xmm(dest: Xmm, src: Xmm);
+        mulpd_xmm_mem(dest: Xmm, src: Mem);
+        divss_xmm_xmm(dest: Xmm, src: Xmm);
+        divss_xmm_mem(dest: Xmm, src: Mem);
+        divsd_xmm_xmm(dest: Xmm, src: Xmm);
+        divsd_xmm_mem(dest: Xmm, src: Mem);
+        divps_xmm_xmm(dest: Xmm, src: Xmm);
+        divps_xmm_mem(dest: Xmm, src: Mem);
+        divpd_xmm_xmm(dest: Xmm, src: Xmm);
+        divpd_xmm_mem(dest: Xmm, src: Mem);
+        minss_xmm_xmm(dest: Xmm, src: Xmm);
+        minss_xmm_mem(dest: Xmm, src: Mem);
+        minsd_xmm_xmm(dest: Xmm, src: Xmm);
+        minsd_xmm_mem(dest: Xmm, src: Mem);
+        minps_xmm_xmm(dest: Xmm, src: Xmm);
+        minps_xmm_mem(dest: Xmm, src: Mem);
+        minpd_xmm_xmm(dest: Xmm, src: Xmm);
+        minpd_xmm_mem(dest: Xmm, src: Mem);
+        maxss_xmm_xmm(dest: Xmm, src: Xmm);
+        maxss_xmm_mem(dest: Xmm, src: Mem);
+        maxsd_xmm_xmm(dest: Xmm, src: Xmm);
+        maxsd_xmm_mem(dest: Xmm, src: Mem);
+        maxps_xmm_xmm(dest: Xmm, src: Xmm);
+        maxps_xmm_mem(dest: Xmm, src: Mem);
+        maxpd_xmm_xmm(dest: Xmm, src: Xmm);
+        maxpd_xmm_mem(dest: Xmm, src: Mem);
+        sqrtss_xmm_xmm(dest: Xmm, src: Xmm);
+        sqrtss_xmm_mem(dest: Xmm, src: Mem);
+        sqrtsd_xmm_xmm(dest: Xmm, src: Xmm);
+        sqrtsd_xmm_mem(dest: Xmm, src: Mem);
+        sqrtps_xmm_xmm(dest: Xmm, src: Xmm);
+        sqrtps_xmm_mem(dest: Xmm, src: Mem);
+        sqrtpd_xmm_xmm(dest: Xmm, src: Xmm);
+        sqrtpd_xmm_mem(dest: Xmm, src: Mem);
+        andps_xmm_xmm(dest: Xmm, src: Xmm);
+        andps_xmm_mem(dest: Xmm, src: Mem);
+        andpd_xmm_xmm(dest: Xmm, src: Xmm);
+        andpd_xmm_mem(dest: Xmm, src: Mem);
+        andnps_xmm_xmm(dest: Xmm, src: Xmm);
+        andnps_xmm_mem(dest: Xmm, src: Mem);
+        andnpd_xmm_xmm(dest: Xmm, src: Xmm);
+        andnpd_xmm_mem(dest: Xmm, src: Mem);
+        orps_xmm_xmm(dest: Xmm, src: Xmm);
+        orps_xmm_mem(dest: Xmm, src: Mem);
+        orpd_xmm_xmm(dest: Xmm, src: Xmm);
+        orpd_xmm_mem(dest: Xmm, src: Mem);
+        xorps_xmm_xmm(dest: Xmm, src: Xmm);
+        xorps_xmm_mem(dest: Xmm, src: Mem);
+        xorpd_xmm_xmm(dest: Xmm, src: Xmm);
+        xorpd_xmm_mem(dest: Xmm, src: Mem);
+        ucomiss_xmm_xmm(src1: Xmm, src2: Xmm);
+        ucomiss_xmm_mem(src1: Xmm, src2: Mem);
+        ucomisd_xmm_xmm(src1: Xmm, src2: Xmm);
+        ucomisd_xmm_mem(src1: Xmm, src2: Mem);
+    }
+
+    forward! {
+        // AES-NI and `pclmulqdq`, see the [`crypto`](crate::crypto) module.
+        aesenc_xmm_xmm(state: Xmm, key: Xmm);
+        aesenclast_xmm_xmm(state: Xmm, key: Xmm);
+        aesdec_xmm_xmm(state: Xmm, key: Xmm);
+        aesdeclast_xmm_xmm(state: Xmm, key: Xmm);
+        aesimc_xmm_xmm(dest: Xmm, src: Xmm);
+        aeskeygenassist_xmm_xmm_imm8(dest: Xmm, src: Xmm, imm8: u8);
+        pclmulqdq_xmm_xmm_imm8(dest: Xmm, src: Xmm, imm8: u8);
+    }
+
+    forward! {
+        // Non-temporal moves, see the [`nontemporal`](crate::nontemporal) module.
+        movnti_mem32_reg32(dest: Mem, src: Reg32);
+        movnti_mem64_reg64(dest: Mem, src: Reg64);
+        movntdq_mem_xmm(dest: Mem, src: Xmm);
+        movntps_mem_xmm(dest: Mem, src: Xmm);
+        movntpd_mem_xmm(dest: Mem, src: Xmm);
+        movntdqa_xmm_mem(dest: Xmm, src: Mem);
+        maskmovdqu_xmm_xmm(src: Xmm, mask: Xmm);
+    }
+
+    forward! {
+        // AVX2 gathers, see the [`gather`](crate::gather) module.
+        vgatherdps_xmm_vm32x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vgatherdps_ymm_vm32y_ymm(dest: Ymm, src: VsibMem<Ymm>, mask: Ymm);
+        vgatherqps_xmm_vm64x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vgatherqps_xmm_vm64y_xmm(dest: Xmm, src: VsibMem<Ymm>, mask: Xmm);
+        vgatherdpd_xmm_vm32x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vgatherdpd_ymm_vm32x_ymm(dest: Ymm, src: VsibMem<Xmm>, mask: Ymm);
+        vgatherqpd_xmm_vm64x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vgatherqpd_ymm_vm64y_ymm(dest: Ymm, src: VsibMem<Ymm>, mask: Ymm);
+        vpgatherdd_xmm_vm32x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vpgatherdd_ymm_vm32y_ymm(dest: Ymm, src: VsibMem<Ymm>, mask: Ymm);
+        vpgatherqd_xmm_vm64x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vpgatherqd_xmm_vm64y_xmm(dest: Xmm, src: VsibMem<Ymm>, mask: Xmm);
+        vpgatherdq_xmm_vm32x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vpgatherdq_ymm_vm32x_ymm(dest: Ymm, src: VsibMem<Xmm>, mask: Ymm);
+        vpgatherqq_xmm_vm64x_xmm(dest: Xmm, src: VsibMem<Xmm>, mask: Xmm);
+        vpgatherqq_ymm_vm64y_ymm(dest: Ymm, src: VsibMem<Ymm>, mask: Ymm);
+    }
+
+    forward! {
+        // AVX floating point arithmetic, see the [`avx`](crate::avx) module.
+        vaddps_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vaddps_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vaddpd_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vaddpd_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vsubps_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vsubps_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vsubpd_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vsubpd_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vmulps_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vmulps_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vmulpd_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vmulpd_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vdivps_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vdivps_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vdivpd_xmm_xmm_xmm(dest: Xmm, src1: Xmm, src2: Xmm);
+        vdivpd_ymm_ymm_ymm(dest: Ymm, src1: Ymm, src2: Ymm);
+        vzeroupper();
+        vmovdqu_ymm_mem(dest: Ymm, src: Mem);
+        vmovdqu_mem_ymm(dest: Mem, src: Ymm);
+    }
+
+    forward! {
+        // String instructions and memory intrinsics, see the [`memops`](crate::memops) module.  The clobbered registers are discarded.
+        rep_movsb();
+        rep_stosb();
+        repe_cmpsb();
+        lodsb();
+        lodsw();
+        stosb();
+        stosw();
+        movsb();
+        movsw();
+        intrinsic_memcpy(dst: Reg64, src: Reg64, len: MemcpyLen);
+        intrinsic_memcpy_with_pool(dst: Reg64, src: Reg64, len: MemcpyLen, pool: &ScratchPool);
+        intrinsic_memset(dst: Reg64, value: u8, len: MemcpyLen);
+        intrinsic_memset_with_pool(dst: Reg64, value: u8, len: MemcpyLen, pool: &ScratchPool);
+        intrinsic_memcmp(lhs: Reg64, rhs: Reg64, len: MemcpyLen, result: Reg64);
+    }
+
+    forward! {
+        // Loads from the constant pool, see the [`pool`](crate::pool) module.
+        movaps_xmm_label(dest: Xmm, label: LabelRef);
+        movdqa_xmm_label(dest: Xmm, label: LabelRef);
+        vmovaps_ymm_label(dest: Ymm, label: LabelRef);
+        vmovdqa_ymm_label(dest: Ymm, label: LabelRef);
+    }
+
+    forward! {
+        // System instructions and interrupt service routines, see the [`system`](crate::system) module.
+        swapgs();
+        cld();
+        int3();
+        int_imm8(imm8: u8);
+        hlt();
+        cli();
+        sti();
+        cpuid();
+        syscall();
+        ptwrite_reg64(src: Reg64);
+        ptwrite_mem64(src: Mem);
+        mov_reg16_sreg(dest: Reg16, src: SegmentReg);
+        mov_reg64_sreg(dest: Reg64, src: SegmentReg);
+        mov_sreg_reg16(dest: SegmentReg, src: Reg16);
+        lfs_reg32_mem(dest: Reg32, src: Mem);
+        lgs_reg32_mem(dest: Reg32, src: Mem);
+        lss_reg32_mem(dest: Reg32, src: Mem);
+        iret();
+        iretd();
+        iretq();
+        intrinsic_isr_prologue(entry: InterruptEntry, swapgs: bool);
+        intrinsic_isr_epilogue(entry: InterruptEntry, swapgs: bool);
+        isr_stub(vector: u8, handler: SymRef);
+    }
+
+    forward! {
+        // Waits, see the [`wait`](crate::wait) module.
+        umonitor_reg64(src: Reg64);
+        umwait_reg32(control: Reg32);
+        tpause_reg32(control: Reg32);
+        monitor();
+        mwait();
+    }
+
+    forward! {
+        // Direct stores and enqueue stores, see the [`direct`](crate::direct) module.
+        movdir64b_reg64_mem(dest: Reg64, src: Mem);
+        movdiri_mem_reg32(dest: Mem, src: Reg32);
+        movdiri_mem_reg64(dest: Mem, src: Reg64);
+        enqcmd_reg64_mem(dest: Reg64, src: Mem);
+        enqcmds_reg64_mem(dest: Reg64, src: Mem);
+        serialize();
+    }
+
+    forward! {
+        // Thread-local storage, see the [`tls`](crate::tls) module.
+        intrinsic_load_tls_ie(dest: Reg64, symbol: SymRef);
+        intrinsic_read_fs_base_offset(dest: Reg64, offset: i32);
+    }
+
+    forward! {
+        // Protected and real mode instructions, see the [`mode`](crate::mode) module.
+        push_reg32(reg32: Reg32);
+        pop_reg32(reg32: Reg32);
+        call_reg32(reg32: Reg32);
+        jmp_reg32(reg32: Reg32);
+        inc_reg32(reg32: Reg32);
+        dec_reg32(reg32: Reg32);
+        load_label_address16(dest: Reg16, label: LabelRef);
+    }
+
+    forward! {
+        // Far branches, see the [`far`](crate::far) module.
+        jmp_far_ptr16_16(segment: u16, offset: u16);
+        call_far_ptr16_16(segment: u16, offset: u16);
+        jmp_far_ptr16_32(segment: u16, offset: u32);
+        call_far_ptr16_32(segment: u16, offset: u32);
+        jmp_far_mem16_16(mem: Mem);
+        jmp_far_mem16_32(mem: Mem);
+        jmp_far_mem16_64(mem: Mem);
+        call_far_mem16_16(mem: Mem);
+        call_far_mem16_32(mem: Mem);
+        call_far_mem16_64(mem: Mem);
+    }
+
+    forward! {
+        // Text sections, see the [`section`](crate::section) module.
+        switch_section(section: Section);
+    }
+
+    forward! {
+        // Restricted transactional memory, see the [`rtm`](crate::rtm) module.
+        xbegin_label(label: LabelRef);
+        xend();
+        xabort_imm8(imm8: u8);
+        xtest();
+    }
+
+    forward! {
+        // Floating point and vector state, see the [`fpstate`](crate::fpstate) module.
+        fxsave_mem(dest: Mem);
+        fxrstor_mem(src: Mem);
+        fxsave64_mem(dest: Mem);
+        fxrstor64_mem(src: Mem);
+        ldmxcsr_mem(src: Mem);
+        stmxcsr_mem(dest: Mem);
+        xsave_mem(dest: Mem);
+        xrstor_mem(src: Mem);
+        xsaveopt_mem(dest: Mem);
+        xsave64_mem(dest: Mem);
+        xrstor64_mem(src: Mem);
+        xsaveopt64_mem(dest: Mem);
+    }
+
+    forward! {
+        // Safepoints, see the [`safepoint`](crate::safepoint) module.
+        record_safepoint(live: &[StackMapEntry]);
+        intrinsic_call(target: CallTarget, live: Option<&[StackMapEntry]>);
+    }
+
+    forward! {
+        // Atomic regions, see the [`region`](crate::region) module.
+        begin_atomic_region();
+        end_atomic_region();
+    }
+}
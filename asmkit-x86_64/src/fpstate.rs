@@ -0,0 +1,250 @@
+//! The instructions saving and restoring the floating point and vector state, as a context switch or a signal handler does.
+//!
+//! `fxsave` and `fxrstor` move the x87 and SSE state to and from a 512-byte area, which must be 16-byte aligned.  `xsave`, `xsaveopt` and
+//! `xrstor` move the components selected by the mask in `edx:eax`, such as the AVX state, to and from an area sized by `cpuid` leaf `0xd`,
+//! which must be 64-byte aligned.  The `64` forms save the 64-bit x87 instruction and operand pointers, rather than their segments and 32-bit
+//! offsets.
+//!
+//! All of them, and `stmxcsr` and `ldmxcsr`, share the `0F AE` opcode and are told apart by the register field of the ModRM byte:
+//!
+//! | Field | Instruction          |
+//! |-------|----------------------|
+//! | 0     | `fxsave`             |
+//! | 1     | `fxrstor`            |
+//! | 2     | `ldmxcsr`            |
+//! | 3     | `stmxcsr`            |
+//! | 4     | `xsave`              |
+//! | 5     | `xrstor`             |
+//! | 6     | `xsaveopt`           |
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let area = Mem::new(Reg64::R13, 0);
+//!
+//! stream.fxsave_mem(area);
+//! stream.fxrstor_mem(area);
+//! stream.ldmxcsr_mem(area);
+//! stream.stmxcsr_mem(area);
+//! stream.xsave_mem(area);
+//! stream.xrstor_mem(area);
+//! stream.xsaveopt_mem(area);
+//! stream.fxsave64_mem(area);
+//! stream.fxrstor64_mem(area);
+//! stream.xsave64_mem(area);
+//! stream.xrstor64_mem(area);
+//! stream.xsaveopt64_mem(area);
+//!
+//! // `r13` can't be a base without a displacement, so an 8-bit displacement of 0 is written.
+//! assert_eq!(stream.finish().emit(), [
+//!     0x41, 0x0f, 0xae, 0x45, 0x00, // fxsave [r13]
+//!     0x41, 0x0f, 0xae, 0x4d, 0x00, // fxrstor [r13]
+//!     0x41, 0x0f, 0xae, 0x55, 0x00, // ldmxcsr dword ptr [r13]
+//!     0x41, 0x0f, 0xae, 0x5d, 0x00, // stmxcsr dword ptr [r13]
+//!     0x41, 0x0f, 0xae, 0x65, 0x00, // xsave [r13]
+//!     0x41, 0x0f, 0xae, 0x6d, 0x00, // xrstor [r13]
+//!     0x41, 0x0f, 0xae, 0x75, 0x00, // xsaveopt [r13]
+//!     0x49, 0x0f, 0xae, 0x45, 0x00, // fxsave64 [r13]
+//!     0x49, 0x0f, 0xae, 0x4d, 0x00, // fxrstor64 [r13]
+//!     0x49, 0x0f, 0xae, 0x65, 0x00, // xsave64 [r13]
+//!     0x49, 0x0f, 0xae, 0x6d, 0x00, // xrstor64 [r13]
+//!     0x49, 0x0f, 0xae, 0x75, 0x00, // xsaveopt64 [r13]
+//! ]);
+//! ```
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{
+    defuse::RegSet,
+    features::CpuFeatures,
+    memory::Mem,
+    register::{Reg64, VectorReg, Xmm},
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes an instruction of the `0F AE` group with the register field `digit` and the memory operand `mem`.
+    fn write_state_mem(&mut self, rex_w: bool, digit: u8, mem: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(rex_w, &[0x0f, 0xae], (digit, false), mem);
+
+        // the `xsave` group takes the mask of the state components in `edx:eax`, the saves read the vector registers and the restores reload them.
+        let vectors: RegSet = (0..16).map(Xmm::from_number).collect();
+        let mask = if digit >= 4 { RegSet::of(Reg64::Rax).with(Reg64::Rdx) } else { RegSet::EMPTY };
+
+        match digit {
+            1 | 5 => self.record_registers(vectors, mask | RegSet::from(mem)),
+            0 | 4 | 6 => self.record_registers(RegSet::EMPTY, vectors | mask | RegSet::from(mem)),
+            _ => self.record_registers(RegSet::EMPTY, RegSet::from(mem)),
+        }
+
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Save the x87 and SSE state to the 512-byte area at *m512byte*, which must be 16-byte aligned.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m512byte* is known to be misaligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{frame::FrameBuilder, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_spill_64();
+    /// let area = builder.alloc_slot(512, 8); // only 8-byte aligned
+    /// let frame = builder.freeze();
+    /// let slot = frame.slot(area);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// stream.intrinsic_prologue(frame);
+    /// stream.fxsave_mem(slot);
+    /// ```
+    pub fn fxsave_mem(&mut self, dest: Mem) {
+        self.check_alignment("fxsave", dest, 16);
+        self.write_state_mem(false, 0, dest);
+    }
+
+    /// Restore the x87 and SSE state from the 512-byte area at *m512byte*, which must be 16-byte aligned.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m512byte* is known to be misaligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn fxrstor_mem(&mut self, src: Mem) {
+        self.check_alignment("fxrstor", src, 16);
+        self.write_state_mem(false, 1, src);
+    }
+
+    /// Save the x87 and SSE state to *m512byte*, with the 64-bit x87 instruction and operand pointers.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m512byte* is known to be misaligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn fxsave64_mem(&mut self, dest: Mem) {
+        self.check_alignment("fxsave64", dest, 16);
+        self.write_state_mem(true, 0, dest);
+    }
+
+    /// Restore the x87 and SSE state from *m512byte*, with the 64-bit x87 instruction and operand pointers.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m512byte* is known to be misaligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn fxrstor64_mem(&mut self, src: Mem) {
+        self.check_alignment("fxrstor64", src, 16);
+        self.write_state_mem(true, 1, src);
+    }
+
+    /// Load `mxcsr` from *m32*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.stmxcsr_mem(Mem::new(Reg64::Rsp, 4));
+    /// stream.ldmxcsr_mem(Mem::new(Reg64::Rsp, 4));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0xae, 0x5c, 0x24, 0x04, // stmxcsr dword ptr [rsp + 4]
+    ///     0x0f, 0xae, 0x54, 0x24, 0x04, // ldmxcsr dword ptr [rsp + 4]
+    /// ]);
+    /// ```
+    pub fn ldmxcsr_mem(&mut self, src: Mem) {
+        self.write_state_mem(false, 2, src);
+    }
+
+    /// Store `mxcsr` to *m32*.
+    pub fn stmxcsr_mem(&mut self, dest: Mem) {
+        self.write_state_mem(false, 3, dest);
+    }
+
+    /// Save the state components selected by `edx:eax` to the area at *mem*, which must be 64-byte aligned.
+    ///
+    /// # Panics
+    /// Panics if XSAVE isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are enabled
+    /// and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{frame::FrameBuilder, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_spill_64();
+    /// let area = builder.alloc_slot(1024, 8); // only 8-byte aligned
+    /// let frame = builder.freeze();
+    /// let slot = frame.slot(area);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// stream.intrinsic_prologue(frame);
+    /// stream.xsave_mem(slot);
+    /// ```
+    pub fn xsave_mem(&mut self, dest: Mem) {
+        self.require_feature(CpuFeatures::XSAVE, "xsave");
+        self.check_alignment("xsave", dest, 64);
+        self.write_state_mem(false, 4, dest);
+    }
+
+    /// Restore the state components selected by `edx:eax` from the area at *mem*, which must be 64-byte aligned.
+    ///
+    /// # Panics
+    /// Panics if XSAVE isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are enabled
+    /// and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xrstor_mem(&mut self, src: Mem) {
+        self.require_feature(CpuFeatures::XSAVE, "xrstor");
+        self.check_alignment("xrstor", src, 64);
+        self.write_state_mem(false, 5, src);
+    }
+
+    /// Save the state components selected by `edx:eax` to the area at *mem*, skipping those unmodified since they were last restored from it.
+    ///
+    /// # Panics
+    /// Panics if XSAVEOPT isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are
+    /// enabled and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xsaveopt_mem(&mut self, dest: Mem) {
+        self.require_feature(CpuFeatures::XSAVEOPT, "xsaveopt");
+        self.check_alignment("xsaveopt", dest, 64);
+        self.write_state_mem(false, 6, dest);
+    }
+
+    /// Save the state components selected by `edx:eax` to *mem*, with the 64-bit x87 instruction and operand pointers.
+    ///
+    /// # Panics
+    /// Panics if XSAVE isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are enabled
+    /// and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xsave64_mem(&mut self, dest: Mem) {
+        self.require_feature(CpuFeatures::XSAVE, "xsave64");
+        self.check_alignment("xsave64", dest, 64);
+        self.write_state_mem(true, 4, dest);
+    }
+
+    /// Restore the state components selected by `edx:eax` from *mem*, with the 64-bit x87 instruction and operand pointers.
+    ///
+    /// # Panics
+    /// Panics if XSAVE isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are enabled
+    /// and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xrstor64_mem(&mut self, src: Mem) {
+        self.require_feature(CpuFeatures::XSAVE, "xrstor64");
+        self.check_alignment("xrstor64", src, 64);
+        self.write_state_mem(true, 5, src);
+    }
+
+    /// Save the modified state components selected by `edx:eax` to *mem*, with the 64-bit x87 instruction and operand pointers.
+    ///
+    /// # Panics
+    /// Panics if XSAVEOPT isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`], or if the alignment checks are
+    /// enabled and *mem* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn xsaveopt64_mem(&mut self, dest: Mem) {
+        self.require_feature(CpuFeatures::XSAVEOPT, "xsaveopt64");
+        self.check_alignment("xsaveopt64", dest, 64);
+        self.write_state_mem(true, 6, dest);
+    }
+}
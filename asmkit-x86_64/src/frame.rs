@@ -0,0 +1,176 @@
+//! Stack frame layout.
+//!
+//! A [`FrameBuilder`] hands out stack slots while a function is being generated, and is frozen into a [`Frame`] once every slot is known.  The frame is then
+//! passed to [`x86_64InstructionStream::intrinsic_prologue`](crate::stream::x86_64InstructionStream::intrinsic_prologue), which reserves its size on the stack
+//! and lets the slots be accessed by [`SlotRef`].
+//!
+//! Slots are laid out downwards from the canonical frame address, the value of `rsp` before the call which entered the function, which is 16-byte aligned by
+//! the System V and Windows calling conventions.  The return address, and the saved `rbp` if a frame pointer is used, sit right below it, and the slots
+//! below those.  The frame size is rounded so that `rsp` is 16-byte aligned again after the prologue, ready for calls.
+
+use asmkit_core::entity::{EntityList, EntityRef};
+
+use crate::{memory::Mem, register::Reg64};
+
+/// An opaque reference to a stack slot of a [`FrameBuilder`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, PartialOrd)]
+pub struct SlotRef(u32);
+
+impl EntityRef for SlotRef {
+    #[inline(always)]
+    fn new(value: usize) -> Self {
+        Self(value as u32)
+    }
+
+    #[inline(always)]
+    fn as_u32(&self) -> u32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn as_usize(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A stack slot, placed at `offset` bytes below the canonical frame address.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    offset: u32,
+}
+
+/// Rounds `value` up to a multiple of `align`, which is a power of two.
+#[inline(always)]
+fn align_up(value: u32, align: u32) -> u32 {
+    (value + align - 1) & !(align - 1)
+}
+
+/// Allocates the stack slots of a function.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::{frame::FrameBuilder, memory::Mem, register::Reg64};
+///
+/// let mut builder = FrameBuilder::new();
+/// let flag = builder.alloc_slot(1, 1);
+/// let spill = builder.alloc_spill_64();
+/// let vector = builder.alloc_slot(16, 16);
+/// let frame = builder.freeze();
+///
+/// // `rbp` is 16 bytes below the canonical frame address, the slots take 1 + 7 (padding) + 8 + 16 bytes below it.
+/// assert_eq!(frame.size(), 32);
+/// assert_eq!(frame.slot(flag), Mem::new(Reg64::Rbp, -1));
+/// assert_eq!(frame.slot(spill), Mem::new(Reg64::Rbp, -16));
+/// assert_eq!(frame.slot(vector), Mem::new(Reg64::Rbp, -32));
+///
+/// // without a frame pointer, the same slots are addressed from `rsp`, and the frame also covers the 8 bytes `push rbp` would have taken.
+/// let mut builder = FrameBuilder::without_frame_pointer();
+/// let flag = builder.alloc_slot(1, 1);
+/// let spill = builder.alloc_spill_64();
+/// let vector = builder.alloc_slot(16, 16);
+/// let frame = builder.freeze();
+///
+/// assert_eq!(frame.size(), 40);
+/// assert_eq!(frame.slot(flag), Mem::new(Reg64::Rsp, 39));
+/// assert_eq!(frame.slot(spill), Mem::new(Reg64::Rsp, 24));
+/// assert_eq!(frame.slot(vector), Mem::new(Reg64::Rsp, 0));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameBuilder {
+    /// The slots allocated so far.
+    slots: EntityList<Slot, SlotRef>,
+
+    /// The number of bytes used below the canonical frame address, including the return address and the saved frame pointer.
+    used: u32,
+
+    /// Whether `rbp` is used as a frame pointer.
+    frame_pointer: bool,
+}
+
+impl Default for FrameBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBuilder {
+    /// Creates a frame builder for a function saving `rbp` and using it as a frame pointer, addressing the slots relative to `rbp`.
+    pub fn new() -> Self {
+        Self { slots: EntityList::new(), used: 16, frame_pointer: true }
+    }
+
+    /// Creates a frame builder for a function without a frame pointer, addressing the slots relative to `rsp`.
+    pub fn without_frame_pointer() -> Self {
+        Self { slots: EntityList::new(), used: 8, frame_pointer: false }
+    }
+
+    /// Allocates a slot of `size` bytes, aligned to `align` bytes.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or is greater than 16, the alignment of the canonical frame address.
+    pub fn alloc_slot(&mut self, size: u32, align: u32) -> SlotRef {
+        assert!(align.is_power_of_two() && align <= 16, "slot alignment must be a power of two no greater than 16");
+
+        self.used = align_up(self.used + size, align);
+        self.slots.push(Slot { offset: self.used })
+    }
+
+    /// Allocates an 8-byte slot for spilling a 64-bit register.
+    #[inline(always)]
+    pub fn alloc_spill_64(&mut self) -> SlotRef {
+        self.alloc_slot(8, 8)
+    }
+
+    /// Computes the layout of the frame, after which no more slots may be allocated.
+    pub fn freeze(self) -> Frame {
+        let header = if self.frame_pointer { 16 } else { 8 };
+
+        Frame {
+            slots: self.slots,
+            size: align_up(self.used, 16) - header,
+            header,
+            frame_pointer: self.frame_pointer,
+        }
+    }
+}
+
+/// The layout of a stack frame, produced by [`FrameBuilder::freeze`].
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// The slots of the frame.
+    slots: EntityList<Slot, SlotRef>,
+
+    /// The number of bytes reserved by the prologue.
+    size: u32,
+
+    /// The number of bytes between the canonical frame address and the start of the reserved area.
+    header: u32,
+
+    /// Whether `rbp` is used as a frame pointer.
+    frame_pointer: bool,
+}
+
+impl Frame {
+    /// The number of bytes reserved below the return address, or below the saved `rbp` if a frame pointer is used.  Always keeps `rsp` 16-byte aligned.
+    #[inline(always)]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns true if `rbp` is used as a frame pointer.
+    #[inline(always)]
+    pub fn uses_frame_pointer(&self) -> bool {
+        self.frame_pointer
+    }
+
+    /// The memory operand addressing `slot` once the prologue has run, relative to `rbp` if a frame pointer is used and to `rsp` otherwise.
+    pub fn slot(&self, slot: SlotRef) -> Mem {
+        let offset = self.slots.get(slot).offset as i32;
+
+        if self.frame_pointer {
+            Mem::new(Reg64::Rbp, self.header as i32 - offset)
+        } else {
+            Mem::new(Reg64::Rsp, (self.size + self.header) as i32 - offset)
+        }
+    }
+}
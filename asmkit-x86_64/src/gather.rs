@@ -0,0 +1,360 @@
+//! The AVX2 gather instructions, loading the elements of a vector from the addresses of a [`VsibMem`] operand.
+//!
+//! A gather only loads the elements whose mask element has its sign bit set, then clears the mask, so the mask must be reset before every gather.
+//! The destination, the index and the mask must be distinct registers, or the processor raises `#UD`; the emitters check it, see
+//! [`check_gather_registers`].
+//!
+//! The `d` forms take 32-bit indices and the `q` forms 64-bit ones, so gathering 32-bit elements with 64-bit indices fills half the destination:
+//! `vgatherqps` and `vpgatherqd` take a `ymm` index along with an `xmm` destination and mask.
+
+use std::{error::Error, fmt};
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::{RegId, RegSet},
+    features::CpuFeatures,
+    memory::VsibMem,
+    register::{VectorReg, Xmm, Ymm},
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+/// An operand of a gather instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GatherOperand {
+    /// The destination register.
+    Dest,
+
+    /// The index register of the vector memory operand.
+    Index,
+
+    /// The mask register.
+    Mask,
+}
+
+/// The error returned when two operands of a gather instruction are the same register, see [`check_gather_registers`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlappingGatherRegisters {
+    /// The first of the overlapping operands.
+    pub first: GatherOperand,
+
+    /// The second of the overlapping operands.
+    pub second: GatherOperand,
+
+    /// The number of the register both operands use.
+    pub register: u8,
+}
+
+impl fmt::Display for OverlappingGatherRegisters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |operand| match operand {
+            GatherOperand::Dest => "destination",
+            GatherOperand::Index => "index",
+            GatherOperand::Mask => "mask",
+        };
+
+        write!(
+            f,
+            "the {} and the {} of a gather are both vector register {}, but must be distinct",
+            name(self.first), name(self.second), self.register,
+        )
+    }
+}
+
+impl Error for OverlappingGatherRegisters {}
+
+/// Checks that the destination, index and mask registers of a gather instruction are distinct, comparing `xmm` and `ymm` registers by number.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::{gather::{check_gather_registers, GatherOperand, OverlappingGatherRegisters}, register::{Xmm, Ymm}};
+///
+/// assert_eq!(check_gather_registers(Ymm::Ymm1, Ymm::Ymm2, Ymm::Ymm3), Ok(()));
+/// assert_eq!(check_gather_registers(Xmm::Xmm1, Ymm::Ymm2, Xmm::Xmm2), Err(OverlappingGatherRegisters {
+///     first: GatherOperand::Index,
+///     second: GatherOperand::Mask,
+///     register: 2,
+/// }));
+/// ```
+pub fn check_gather_registers(dest: impl VectorReg, index: impl VectorReg, mask: impl VectorReg) -> Result<(), OverlappingGatherRegisters> {
+    let operands = [(GatherOperand::Dest, dest.number()), (GatherOperand::Index, index.number()), (GatherOperand::Mask, mask.number())];
+
+    for (i, &(first, register)) in operands.iter().enumerate() {
+        if let Some(&(second, _)) = operands[i + 1..].iter().find(|(_, other)| *other == register) {
+            return Err(OverlappingGatherRegisters { first, second, register });
+        }
+    }
+
+    Ok(())
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes a gather instruction in the `0F 38` map with the `66` implied prefix, `l` selecting 256-bit vectors.
+    fn write_gather<Dest: VectorReg, Index: VectorReg, Mask: VectorReg>(
+        &mut self,
+        w: bool,
+        opcode: u8,
+        l: bool,
+        dest: Dest,
+        src: VsibMem<Index>,
+        mask: Mask,
+    ) {
+        if let Err(error) = check_gather_registers(dest, src.index, mask) {
+            panic!("{}", error);
+        }
+
+        let instruction = self.begin_instruction();
+
+        let (dest, index, base) = (dest.number(), src.index.number(), src.base);
+        self.write_vex((dest >= 8, index >= 8, base.is_extension()), 0b00010, w, mask.number(), l, 0b01);
+        self.write_byte(opcode);
+
+        // the index is always encoded through a SIB byte, and `rbp` and `r13` have no displacement-free form as its base.
+        let mode = if src.displacement == 0 && base.offset() != 0b101 {
+            0b00
+        } else if i8::try_from(src.displacement).is_ok() {
+            0b01
+        } else {
+            0b10
+        };
+
+        self.write_byte((mode << 6) | ((dest & 0b111) << 3) | 0b100);
+        self.write_byte((src.scale.bits() << 6) | ((index & 0b111) << 3) | base.offset());
+
+        match mode {
+            0b01 => self.write_byte(src.displacement as u8),
+            0b10 => self.write_double_word(src.displacement as u32),
+            _ => {},
+        }
+
+        // the mask is cleared as the elements are loaded, and the elements left unloaded keep the value of `dest`.
+        let defs = RegSet::of(RegId::vector(dest)).with(RegId::vector(mask.number()));
+        self.record_registers(defs, defs.with(base).with(RegId::vector(index)));
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Gather 4 single precision floats with 32-bit indices from *vm32x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::{Scale, VsibMem}, register::{Reg64, Xmm, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.vgatherdps_ymm_vm32y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Ymm::Ymm2, Scale::X4, 0), Ymm::Ymm3);
+    /// stream.vgatherdps_ymm_vm32y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rbp, Ymm::Ymm2, Scale::X4, 0), Ymm::Ymm3);
+    /// stream.vgatherdps_xmm_vm32x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rsp, Xmm::Xmm2, Scale::X1, 0), Xmm::Xmm3);
+    /// stream.vgatherqps_xmm_vm64y_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Ymm::Ymm2, Scale::X4, 0), Xmm::Xmm3);
+    /// stream.vgatherqps_xmm_vm64x_xmm(Xmm::Xmm9, VsibMem::new(Reg64::Rbx, Xmm::Xmm2, Scale::X4, 0x1000), Xmm::Xmm3);
+    /// stream.vgatherdpd_ymm_vm32x_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Ymm::Ymm3);
+    /// stream.vgatherdpd_xmm_vm32x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Xmm::Xmm3);
+    /// stream.vgatherqpd_ymm_vm64y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::R13, Ymm::Ymm10, Scale::X8, 0x40), Ymm::Ymm0);
+    /// stream.vgatherqpd_xmm_vm64x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Xmm::Xmm3);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc4, 0xe2, 0x65, 0x92, 0x0c, 0x90,                         // vgatherdps ymm1, [rax + ymm2*4], ymm3
+    ///     0xc4, 0xe2, 0x65, 0x92, 0x4c, 0x95, 0x00,                   // vgatherdps ymm1, [rbp + ymm2*4], ymm3
+    ///     0xc4, 0xe2, 0x61, 0x92, 0x0c, 0x14,                         // vgatherdps xmm1, [rsp + xmm2], xmm3
+    ///     0xc4, 0xe2, 0x65, 0x93, 0x0c, 0x90,                         // vgatherqps xmm1, [rax + ymm2*4], xmm3
+    ///     0xc4, 0x62, 0x61, 0x93, 0x8c, 0x93, 0x00, 0x10, 0x00, 0x00, // vgatherqps xmm9, [rbx + xmm2*4 + 0x1000], xmm3
+    ///     0xc4, 0xe2, 0xe5, 0x92, 0x0c, 0xd0,                         // vgatherdpd ymm1, [rax + xmm2*8], ymm3
+    ///     0xc4, 0xe2, 0xe1, 0x92, 0x0c, 0xd0,                         // vgatherdpd xmm1, [rax + xmm2*8], xmm3
+    ///     0xc4, 0x82, 0xfd, 0x93, 0x4c, 0xd5, 0x40,                   // vgatherqpd ymm1, [r13 + ymm10*8 + 0x40], ymm0
+    ///     0xc4, 0xe2, 0xe1, 0x93, 0x0c, 0xd0,                         // vgatherqpd xmm1, [rax + xmm2*8], xmm3
+    /// ]);
+    /// ```
+    ///
+    /// Overlapping registers are rejected:
+    /// ```should_panic
+    /// use asmkit_x86_64::{memory::{Scale, VsibMem}, register::{Reg64, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.vgatherdps_ymm_vm32y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Ymm::Ymm1, Scale::X4, 0), Ymm::Ymm3);
+    /// ```
+    pub fn vgatherdps_xmm_vm32x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherdps");
+        self.write_gather(false, 0x92, false, dest, src, mask);
+    }
+
+    /// Gather 8 single precision floats with 32-bit indices from *vm32y* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherdps_ymm_vm32y_ymm(&mut self, dest: Ymm, src: VsibMem<Ymm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherdps");
+        self.write_gather(false, 0x92, true, dest, src, mask);
+    }
+
+    /// Gather 2 single precision floats with 64-bit indices from *vm64x* into the low half of *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherqps_xmm_vm64x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherqps");
+        self.write_gather(false, 0x93, false, dest, src, mask);
+    }
+
+    /// Gather 4 single precision floats with 64-bit indices from *vm64y* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherqps_xmm_vm64y_xmm(&mut self, dest: Xmm, src: VsibMem<Ymm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherqps");
+        self.write_gather(false, 0x93, true, dest, src, mask);
+    }
+
+    /// Gather 2 double precision floats with 32-bit indices from *vm32x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherdpd_xmm_vm32x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherdpd");
+        self.write_gather(true, 0x92, false, dest, src, mask);
+    }
+
+    /// Gather 4 double precision floats with 32-bit indices from *vm32x* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherdpd_ymm_vm32x_ymm(&mut self, dest: Ymm, src: VsibMem<Xmm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherdpd");
+        self.write_gather(true, 0x92, true, dest, src, mask);
+    }
+
+    /// Gather 2 double precision floats with 64-bit indices from *vm64x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherqpd_xmm_vm64x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherqpd");
+        self.write_gather(true, 0x93, false, dest, src, mask);
+    }
+
+    /// Gather 4 double precision floats with 64-bit indices from *vm64y* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vgatherqpd_ymm_vm64y_ymm(&mut self, dest: Ymm, src: VsibMem<Ymm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vgatherqpd");
+        self.write_gather(true, 0x93, true, dest, src, mask);
+    }
+
+    /// Gather 4 doublewords with 32-bit indices from *vm32x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::{Scale, VsibMem}, register::{Reg64, Xmm, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.vpgatherdd_ymm_vm32y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Ymm::Ymm2, Scale::X4, 0), Ymm::Ymm3);
+    /// stream.vpgatherdd_xmm_vm32x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X4, 0), Xmm::Xmm3);
+    /// stream.vpgatherqd_xmm_vm64y_xmm(Xmm::Xmm1, VsibMem::new(Reg64::R12, Ymm::Ymm2, Scale::X4, 0), Xmm::Xmm3);
+    /// stream.vpgatherqd_xmm_vm64x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X4, 0), Xmm::Xmm3);
+    /// stream.vpgatherdq_ymm_vm32x_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Ymm::Ymm3);
+    /// stream.vpgatherdq_xmm_vm32x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Xmm::Xmm3);
+    /// stream.vpgatherqq_ymm_vm64y_ymm(Ymm::Ymm1, VsibMem::new(Reg64::Rax, Ymm::Ymm2, Scale::X8, 0), Ymm::Ymm3);
+    /// stream.vpgatherqq_xmm_vm64x_xmm(Xmm::Xmm1, VsibMem::new(Reg64::Rax, Xmm::Xmm2, Scale::X8, 0), Xmm::Xmm3);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc4, 0xe2, 0x65, 0x90, 0x0c, 0x90, // vpgatherdd ymm1, [rax + ymm2*4], ymm3
+    ///     0xc4, 0xe2, 0x61, 0x90, 0x0c, 0x90, // vpgatherdd xmm1, [rax + xmm2*4], xmm3
+    ///     0xc4, 0xc2, 0x65, 0x91, 0x0c, 0x94, // vpgatherqd xmm1, [r12 + ymm2*4], xmm3
+    ///     0xc4, 0xe2, 0x61, 0x91, 0x0c, 0x90, // vpgatherqd xmm1, [rax + xmm2*4], xmm3
+    ///     0xc4, 0xe2, 0xe5, 0x90, 0x0c, 0xd0, // vpgatherdq ymm1, [rax + xmm2*8], ymm3
+    ///     0xc4, 0xe2, 0xe1, 0x90, 0x0c, 0xd0, // vpgatherdq xmm1, [rax + xmm2*8], xmm3
+    ///     0xc4, 0xe2, 0xe5, 0x91, 0x0c, 0xd0, // vpgatherqq ymm1, [rax + ymm2*8], ymm3
+    ///     0xc4, 0xe2, 0xe1, 0x91, 0x0c, 0xd0, // vpgatherqq xmm1, [rax + xmm2*8], xmm3
+    /// ]);
+    /// ```
+    pub fn vpgatherdd_xmm_vm32x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherdd");
+        self.write_gather(false, 0x90, false, dest, src, mask);
+    }
+
+    /// Gather 8 doublewords with 32-bit indices from *vm32y* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherdd_ymm_vm32y_ymm(&mut self, dest: Ymm, src: VsibMem<Ymm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherdd");
+        self.write_gather(false, 0x90, true, dest, src, mask);
+    }
+
+    /// Gather 2 doublewords with 64-bit indices from *vm64x* into the low half of *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherqd_xmm_vm64x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherqd");
+        self.write_gather(false, 0x91, false, dest, src, mask);
+    }
+
+    /// Gather 4 doublewords with 64-bit indices from *vm64y* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherqd_xmm_vm64y_xmm(&mut self, dest: Xmm, src: VsibMem<Ymm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherqd");
+        self.write_gather(false, 0x91, true, dest, src, mask);
+    }
+
+    /// Gather 2 quadwords with 32-bit indices from *vm32x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherdq_xmm_vm32x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherdq");
+        self.write_gather(true, 0x90, false, dest, src, mask);
+    }
+
+    /// Gather 4 quadwords with 32-bit indices from *vm32x* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherdq_ymm_vm32x_ymm(&mut self, dest: Ymm, src: VsibMem<Xmm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherdq");
+        self.write_gather(true, 0x90, true, dest, src, mask);
+    }
+
+    /// Gather 2 quadwords with 64-bit indices from *vm64x* into *xmm1*, under the mask *xmm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherqq_xmm_vm64x_xmm(&mut self, dest: Xmm, src: VsibMem<Xmm>, mask: Xmm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherqq");
+        self.write_gather(true, 0x91, false, dest, src, mask);
+    }
+
+    /// Gather 4 quadwords with 64-bit indices from *vm64y* into *ymm1*, under the mask *ymm2*.
+    ///
+    /// # Panics
+    /// Panics if AVX2 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the destination, index and mask registers aren't distinct, see [`check_gather_registers`].
+    pub fn vpgatherqq_ymm_vm64y_ymm(&mut self, dest: Ymm, src: VsibMem<Ymm>, mask: Ymm) {
+        self.require_feature(CpuFeatures::AVX2, "vpgatherqq");
+        self.write_gather(true, 0x91, true, dest, src, mask);
+    }
+}
@@ -0,0 +1,274 @@
+//! Polymorphic emitters, picking the instruction form from the types of their operands.
+//!
+//! The traits of this module are implemented by [`x86_64InstructionStream`] for each supported combination of operands, so `stream.mov(Reg64::Rax, 1u64)`
+//! and `stream.mov(Reg64::Rax, Reg64::Rbx)` resolve to different forms.  By default, each combination always emits the same form; once
+//! [`x86_64InstructionStream::prefer_small_encodings`] is enabled, they pick the smallest encoding with the same effect instead.  The emitters named after
+//! their operand widths, such as [`x86_64InstructionStream::mov_reg64_imm64`], are never affected.
+//!
+//! The smaller forms rely on how x86-64 extends immediates and results, which differs between them:
+//!
+//! - writing a 32-bit register **zero** extends the result into the full 64-bit register, so `mov eax, imm32` loads any value from `0` to `u32::MAX`
+//!   into `rax`;
+//! - the *imm32* of a 64-bit instruction, such as `mov rax, imm32` or `add rax, imm32`, is **sign** extended, so it loads any value from `i32::MIN` to
+//!   `i32::MAX`, and `mov rax, -1` is all ones, while `mov eax, 0xffffffff` (the same 32 bits) loads `0x00000000ffffffff`;
+//! - the *imm8* of the `83` arithmetic forms is sign extended as well, covering `-128` to `127`.
+//!
+//! A 64-bit immediate is thus loaded with `mov r32, imm32` if it is at most `u32::MAX`, with the sign extended `mov r64, imm32` if it is a negative
+//! value of at least `i32::MIN`, and with `movabs` otherwise.
+
+use asmkit_core::{fixed::CodeStorage, entity::LabelRef, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    register::{Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, Condition, REX, REX_W},
+};
+
+/// Moves `src` into `dest`.
+pub trait Mov<Dest, Src> {
+    /// Moves `src` into `dest`, with the form selected by the types of the operands.
+    fn mov(&mut self, dest: Dest, src: Src);
+}
+
+/// Adds `src` to `dest`.
+pub trait Add<Dest, Src> {
+    /// Adds `src` to `dest`, with the form selected by the types of the operands.
+    fn add(&mut self, dest: Dest, src: Src);
+}
+
+/// Subtracts `src` from `dest`.
+pub trait Sub<Dest, Src> {
+    /// Subtracts `src` from `dest`, with the form selected by the types of the operands.
+    fn sub(&mut self, dest: Dest, src: Src);
+}
+
+/// Compares `dest` with `src`, setting the flags as a subtraction would.
+pub trait Cmp<Dest, Src> {
+    /// Compares `dest` with `src`, with the form selected by the types of the operands.
+    fn cmp(&mut self, dest: Dest, src: Src);
+}
+
+/// The right-hand side of a comparison fused with a branch by [`x86_64InstructionStream::cmp_jcc`], a register of the width `Reg` or an immediate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CmpOperand<Reg> {
+    /// A register.
+    Reg(Reg),
+
+    /// An immediate, sign extended to the width of the register it's compared with.
+    Imm(i32),
+}
+
+impl From<Reg64> for CmpOperand<Reg64> {
+    fn from(reg: Reg64) -> Self {
+        Self::Reg(reg)
+    }
+}
+
+impl From<Reg32> for CmpOperand<Reg32> {
+    fn from(reg: Reg32) -> Self {
+        Self::Reg(reg)
+    }
+}
+
+impl<Reg> From<i32> for CmpOperand<Reg> {
+    fn from(imm: i32) -> Self {
+        Self::Imm(imm)
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes the arithmetic instruction `extension` of *imm32* to the accumulator, `rax` if `rex_w` is set and `eax` otherwise, with the
+    /// accumulator form.
+    fn write_arith_accumulator_imm32(&mut self, rex_w: bool, extension: u8, imm32: i32) {
+        let instruction = self.begin_instruction();
+
+        if rex_w {
+            self.write_byte(REX | REX_W);
+        }
+
+        self.write_byte((extension << 3) | 0b101); // accumulator opcode
+        self.write_double_word(imm32 as u32);
+
+        let defs = if extension == 7 { RegSet::EMPTY } else { RegSet::of(Reg64::Rax) };
+        self.record_registers(defs, RegSet::of(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Compares `lhs` with `rhs` and jumps to `target` if `condition` holds, picking the smallest comparison:
+    ///
+    /// - `test lhs, lhs` when `rhs` is zero and the condition is [`Condition::E`] or [`Condition::Ne`];
+    /// - the sign extended *imm8* form for immediates from `-128` to `127`;
+    /// - the accumulator form for other immediates compared with `rax`;
+    /// - the *imm32* or register form otherwise.
+    ///
+    /// The comparison is emitted in its smallest form whether or not [`x86_64InstructionStream::prefer_small_encodings`] is enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let target = stream.create_label_attached();
+    /// stream.cmp_jcc(Reg64::Rdi, 0, Condition::E, target);
+    /// stream.cmp_jcc(Reg64::Rdi, 0, Condition::L, target);
+    /// stream.cmp_jcc(Reg64::Rdi, 127, Condition::Ne, target);
+    /// stream.cmp_jcc(Reg64::Rdi, 128, Condition::A, target);
+    /// stream.cmp_jcc(Reg64::Rax, 128, Condition::A, target);
+    /// stream.cmp_jcc(Reg64::Rdi, Reg64::Rsi, Condition::B, target);
+    /// stream.cmp_jcc32(Reg32::R9d, 0, Condition::Ne, target);
+    /// stream.cmp_jcc32(Reg32::Eax, -200, Condition::G, target);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x85, 0xff,                         // test rdi, rdi
+    ///     0x0f, 0x84, 0xf7, 0xff, 0xff, 0xff,       // je target
+    ///     0x48, 0x83, 0xff, 0x00,                   // cmp rdi, 0
+    ///     0x0f, 0x8c, 0xed, 0xff, 0xff, 0xff,       // jl target
+    ///     0x48, 0x83, 0xff, 0x7f,                   // cmp rdi, 127
+    ///     0x0f, 0x85, 0xe3, 0xff, 0xff, 0xff,       // jne target
+    ///     0x48, 0x81, 0xff, 0x80, 0x00, 0x00, 0x00, // cmp rdi, 128
+    ///     0x0f, 0x87, 0xd6, 0xff, 0xff, 0xff,       // ja target
+    ///     0x48, 0x3d, 0x80, 0x00, 0x00, 0x00,       // cmp rax, 128
+    ///     0x0f, 0x87, 0xca, 0xff, 0xff, 0xff,       // ja target
+    ///     0x48, 0x39, 0xf7,                         // cmp rdi, rsi
+    ///     0x0f, 0x82, 0xc1, 0xff, 0xff, 0xff,       // jb target
+    ///     0x45, 0x85, 0xc9,                         // test r9d, r9d
+    ///     0x0f, 0x85, 0xb8, 0xff, 0xff, 0xff,       // jne target
+    ///     0x3d, 0x38, 0xff, 0xff, 0xff,             // cmp eax, -200
+    ///     0x0f, 0x8f, 0xad, 0xff, 0xff, 0xff,       // jg target
+    /// ]);
+    /// ```
+    pub fn cmp_jcc(&mut self, lhs: Reg64, rhs: impl Into<CmpOperand<Reg64>>, condition: Condition, target: LabelRef) {
+        match rhs.into() {
+            CmpOperand::Imm(0) if matches!(condition, Condition::E | Condition::Ne) => self.test_reg64_reg64(lhs, lhs),
+            CmpOperand::Imm(imm) if lhs == Reg64::Rax && i8::try_from(imm).is_err() => self.write_arith_accumulator_imm32(true, 7, imm),
+            CmpOperand::Imm(imm) => self.cmp_reg64_imm32(lhs, imm),
+            CmpOperand::Reg(rhs) => self.cmp_reg64_reg64(lhs, rhs),
+        }
+
+        self.jcc_label(condition, target);
+    }
+
+    /// Compares `lhs` with `rhs` and jumps to `target` if `condition` holds, picking the smallest comparison like
+    /// [`x86_64InstructionStream::cmp_jcc`].
+    pub fn cmp_jcc32(&mut self, lhs: Reg32, rhs: impl Into<CmpOperand<Reg32>>, condition: Condition, target: LabelRef) {
+        match rhs.into() {
+            CmpOperand::Imm(0) if matches!(condition, Condition::E | Condition::Ne) => self.test_reg32_reg32(lhs, lhs),
+            CmpOperand::Imm(imm) if lhs == Reg32::Eax && i8::try_from(imm).is_err() => self.write_arith_accumulator_imm32(false, 7, imm),
+            CmpOperand::Imm(imm) => self.cmp_reg32_imm32(lhs, imm as u32),
+            CmpOperand::Reg(rhs) => self.cmp_reg32_reg32(lhs, rhs),
+        }
+
+        self.jcc_label(condition, target);
+    }
+
+    /// Returns true if the arithmetic instruction on `dest` and *imm32* is shorter with the accumulator form.
+    #[inline(always)]
+    fn prefers_accumulator(&self, dest: Reg64, imm32: i32) -> bool {
+        self.prefers_small_encodings() && dest == Reg64::Rax && i8::try_from(imm32).is_err()
+    }
+}
+
+impl<S: CodeStorage> Mov<Reg64, Reg64> for x86_64InstructionStream<S> {
+    #[inline(always)]
+    fn mov(&mut self, dest: Reg64, src: Reg64) {
+        self.mov_reg64_reg64(dest, src);
+    }
+}
+
+impl<S: CodeStorage> Mov<Reg32, Reg32> for x86_64InstructionStream<S> {
+    #[inline(always)]
+    fn mov(&mut self, dest: Reg32, src: Reg32) {
+        self.mov_reg32_reg32(dest, src);
+    }
+}
+
+impl<S: CodeStorage> Mov<Reg32, u32> for x86_64InstructionStream<S> {
+    #[inline(always)]
+    fn mov(&mut self, dest: Reg32, src: u32) {
+        self.mov_reg32_imm32(dest, src);
+    }
+}
+
+impl<S: CodeStorage> Mov<Reg64, u64> for x86_64InstructionStream<S> {
+    /// Moves *imm64* into *r64*, with `movabs` unless small encodings are preferred.
+    fn mov(&mut self, dest: Reg64, src: u64) {
+        if !self.prefers_small_encodings() {
+            self.mov_reg64_imm64(dest, src);
+        } else if let Ok(imm32) = u32::try_from(src) {
+            // writing the 32-bit register zero extends the immediate.
+            self.mov_reg32_imm32(Reg32::from(dest), imm32);
+        } else if let Ok(imm32) = i32::try_from(src as i64) {
+            self.mov_reg64_imm32(dest, imm32);
+        } else {
+            self.mov_reg64_imm64(dest, src);
+        }
+    }
+}
+
+impl<S: CodeStorage> Mov<Reg64, i64> for x86_64InstructionStream<S> {
+    /// Moves *imm64* into *r64*, with `movabs` unless small encodings are preferred.
+    #[inline(always)]
+    fn mov(&mut self, dest: Reg64, src: i64) {
+        Mov::mov(self, dest, src as u64);
+    }
+}
+
+impl<S: CodeStorage> Add<Reg64, Reg64> for x86_64InstructionStream<S> {
+    #[inline(always)]
+    fn add(&mut self, dest: Reg64, src: Reg64) {
+        self.add_reg64_reg64(dest, src);
+    }
+}
+
+impl<S: CodeStorage> Add<Reg64, i32> for x86_64InstructionStream<S> {
+    fn add(&mut self, dest: Reg64, src: i32) {
+        if self.prefers_accumulator(dest, src) {
+            self.write_arith_accumulator_imm32(true, 0, src);
+        } else {
+            self.add_reg64_imm32(dest, src);
+        }
+    }
+}
+
+impl<S: CodeStorage> Sub<Reg64, i32> for x86_64InstructionStream<S> {
+    fn sub(&mut self, dest: Reg64, src: i32) {
+        if self.prefers_accumulator(dest, src) {
+            self.write_arith_accumulator_imm32(true, 5, src);
+        } else {
+            self.sub_reg64_imm32(dest, src);
+        }
+    }
+}
+
+impl<S: CodeStorage> Cmp<Reg64, i32> for x86_64InstructionStream<S> {
+    /// Compares *r64* with *imm32*, sign extended to 64 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{generic::{Cmp, Sub}, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.prefer_small_encodings(true);
+    /// stream.cmp(Reg64::Rax, 0x1000);
+    /// stream.cmp(Reg64::Rax, -1);
+    /// stream.sub(Reg64::Rax, 0x1000);
+    /// stream.cmp(Reg64::Rcx, 0x1000);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x3d, 0x00, 0x10, 0x00, 0x00,       // cmp rax, 0x1000
+    ///     0x48, 0x83, 0xf8, 0xff,                   // cmp rax, -1
+    ///     0x48, 0x2d, 0x00, 0x10, 0x00, 0x00,       // sub rax, 0x1000
+    ///     0x48, 0x81, 0xf9, 0x00, 0x10, 0x00, 0x00, // cmp rcx, 0x1000
+    /// ]);
+    /// ```
+    fn cmp(&mut self, dest: Reg64, src: i32) {
+        if self.prefers_accumulator(dest, src) {
+            self.write_arith_accumulator_imm32(true, 7, src);
+        } else {
+            self.cmp_reg64_imm32(dest, src);
+        }
+    }
+}
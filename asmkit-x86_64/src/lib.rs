@@ -1,2 +1,52 @@
+#[doc(hidden)]
+pub use asmkit_core as __core;
+
 pub mod stream;
-pub mod register;
\ No newline at end of file
+pub mod register;
+pub mod frame;
+pub mod memory;
+pub mod encoding;
+pub mod features;
+pub mod error;
+pub mod atomic;
+pub mod stats;
+pub mod diagnostics;
+pub mod sse;
+pub mod sse41;
+pub mod float;
+pub mod crypto;
+pub mod nontemporal;
+pub mod gather;
+pub mod avx;
+pub mod pool;
+pub mod listing;
+pub mod generic;
+pub mod fluent;
+pub mod block;
+pub mod callconv;
+pub mod system;
+pub mod thunk;
+pub mod wide;
+pub mod memops;
+pub mod scratch;
+pub mod rtm;
+pub mod fpstate;
+pub mod section;
+pub mod safepoint;
+pub mod stub;
+pub mod wait;
+pub mod direct;
+pub mod tls;
+pub mod mode;
+pub mod far;
+pub mod registry;
+pub mod flags;
+pub mod defuse;
+pub mod region;
+pub mod dryrun;
+pub mod canonical;
+pub mod decode;
+pub mod fetch;
+
+#[cfg(feature = "text-asm")]
+pub mod text;
@@ -0,0 +1,301 @@
+//! Read-back of the emitted instructions, for verification passes over the code, such as checking that no instruction crosses a cache line or
+//! finding the call sites.
+//!
+//! The instruction boundaries are only recorded while [`x86_64InstructionStream::enable_listing`] is set.  Each instruction is known by its
+//! [`InstructionFamily`]; its [`InstructionForm`] in the [`registry`](crate::registry), and so its mnemonic and operands, are only known for
+//! the instructions emitted by the text assembler.
+//!
+//! The instructions also record the registers they define and use, see the [`defuse`](crate::defuse) module.
+//!
+//! The bytes are read back as they are emitted: the fields holding labels are only patched once the stream is finished.
+
+use std::{fmt, ops::Range};
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{defuse::RegSet, flags::{flag_effects, FlagEffects}, registry::InstructionForm, stats::InstructionFamily, stream::x86_64InstructionStream};
+
+/// The record of an emitted instruction.
+#[derive(Clone, Debug)]
+pub(crate) struct InstructionRecord {
+    pub(crate) offset: usize,
+    pub(crate) len: usize,
+    pub(crate) family: InstructionFamily,
+    pub(crate) text: Option<(&'static InstructionForm, String)>,
+    pub(crate) registers: Option<(RegSet, RegSet)>,
+}
+
+/// The instructions recorded while the listing is enabled.
+#[derive(Default)]
+pub(crate) struct InstructionListing {
+    /// Whether instructions are recorded.
+    pub(crate) enabled: bool,
+
+    /// The instructions of the code being written, by offset.
+    pub(crate) records: Vec<InstructionRecord>,
+
+    /// The atomic regions of the code being written, by offset, the enclosing regions first.
+    pub(crate) regions: Vec<Range<usize>>,
+
+    /// The form and operands of the next instruction, set by the text assembler.
+    pub(crate) next_text: Option<(&'static InstructionForm, String)>,
+
+    /// The registers defined and used by the next instruction, set by its emitter.
+    pub(crate) next_registers: Option<(RegSet, RegSet)>,
+}
+
+impl InstructionListing {
+    /// Records the instruction spanning `range`, if the listing is enabled.
+    pub(crate) fn record(&mut self, range: Range<usize>, family: InstructionFamily) {
+        if self.enabled {
+            let registers = self.next_registers.take();
+            self.records.push(InstructionRecord { offset: range.start, len: range.len(), family, text: self.next_text.take(), registers });
+        }
+    }
+
+    /// Records the atomic region spanning `range`, if the listing is enabled.
+    pub(crate) fn record_region(&mut self, range: Range<usize>) {
+        if self.enabled {
+            // the enclosing regions end last, yet come first.
+            let index = self.regions.partition_point(|region| region.start < range.start);
+            self.regions.insert(index, range);
+        }
+    }
+
+    /// Drops the registers set for an instruction which emitted no bytes.
+    pub(crate) fn skip(&mut self) {
+        self.next_registers = None;
+    }
+
+    /// Drops the last instruction, elided after being emitted.
+    pub(crate) fn forget(&mut self) {
+        if self.enabled {
+            self.records.pop();
+        }
+    }
+}
+
+/// An instruction emitted to the stream, returned by [`x86_64InstructionStream::instructions`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmittedInsn<'a> {
+    /// The offset of the instruction, including its prefixes.
+    pub offset: usize,
+
+    /// The bytes of the instruction, with the fields holding labels not patched yet.
+    pub bytes: &'a [u8],
+
+    /// The family of the instruction.
+    pub family: InstructionFamily,
+
+    /// The form of the instruction in the registry, if it was emitted by the text assembler.
+    pub form: Option<&'static InstructionForm>,
+
+    /// The mnemonic of the instruction, if it was emitted by the text assembler.
+    pub mnemonic: Option<&'static str>,
+
+    /// The operands of the instruction, as written in the source of the text assembler.
+    pub operands: Option<&'a str>,
+
+    /// The registers defined and used by the instruction, if its emitter records them.
+    registers: Option<(RegSet, RegSet)>,
+}
+
+impl EmittedInsn<'_> {
+    /// The effects of the instruction on the flags, if its mnemonic is known, see [`flag_effects`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{flags::FlagSet, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.assemble("cmp rdi, 0\nmov rax, rdi\nloope done\ndone:\nret").unwrap();
+    ///
+    /// let effects = stream.instructions().map(|instruction| instruction.flag_effects().unwrap()).collect::<Vec<_>>();
+    /// assert_eq!(effects[0].writes, FlagSet::STATUS);
+    /// assert!(!effects[1].touches(FlagSet::ALL));
+    /// assert_eq!(effects[2].reads, FlagSet::ZF);
+    /// assert!(effects[1..].iter().all(|effects| effects.clobbers().is_empty()));
+    /// ```
+    pub fn flag_effects(&self) -> Option<FlagEffects> {
+        self.mnemonic.and_then(flag_effects)
+    }
+
+    /// The registers the instruction writes, including the implicit ones, or `None` if its emitter doesn't record them.  See the
+    /// [`defuse`](crate::defuse) module.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{defuse::RegSet, memory::Mem, register::{Reg32, Reg64, Reg8}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.mov_reg64_mem64(Reg64::Rax, Mem::new(Reg64::Rdi, 8));
+    /// stream.mov_reg32_imm32(Reg32::Ecx, 1);
+    /// stream.mov_reg8_reg8(Reg8::Ah, Reg8::Bl);
+    /// stream.add_reg64_reg64(Reg64::Rax, Reg64::Rsi);
+    /// stream.push_reg64(Reg64::R12);
+    /// stream.ret_near();
+    ///
+    /// let defs = stream.instructions().map(|instruction| instruction.defs().unwrap()).collect::<Vec<_>>();
+    /// let uses = stream.instructions().map(|instruction| instruction.uses().unwrap()).collect::<Vec<_>>();
+    /// assert_eq!(defs, [
+    ///     RegSet::of(Reg64::Rax),
+    ///     RegSet::of(Reg64::Rcx),
+    ///     RegSet::of(Reg64::Rax),
+    ///     RegSet::of(Reg64::Rax),
+    ///     RegSet::of(Reg64::Rsp),
+    ///     RegSet::of(Reg64::Rsp),
+    /// ]);
+    ///
+    /// // the 32-bit write defines all of `rcx`, the 8-bit write keeps the rest of `rax`.
+    /// assert_eq!(uses, [
+    ///     RegSet::of(Reg64::Rdi),
+    ///     RegSet::EMPTY,
+    ///     RegSet::of(Reg64::Rax).with(Reg64::Rbx),
+    ///     RegSet::of(Reg64::Rax).with(Reg64::Rsi),
+    ///     RegSet::of(Reg64::Rsp).with(Reg64::R12),
+    ///     RegSet::of(Reg64::Rsp),
+    /// ]);
+    /// ```
+    pub fn defs(&self) -> Option<RegSet> {
+        self.registers.map(|(defs, _)| defs)
+    }
+
+    /// The registers the instruction reads, including the implicit ones and those addressing its memory operands, or `None` if its emitter
+    /// doesn't record them.  See the [`defuse`](crate::defuse) module.
+    pub fn uses(&self) -> Option<RegSet> {
+        self.registers.map(|(_, uses)| uses)
+    }
+
+    /// The offsets the instruction spans.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.bytes.len()
+    }
+
+    /// Returns true if the instruction crosses a multiple of `boundary`, so it is split across two aligned blocks of `boundary` bytes.
+    ///
+    /// # Panics
+    /// Panics if `boundary` is zero.
+    pub fn crosses(&self, boundary: usize) -> bool {
+        let range = self.range();
+        !range.is_empty() && range.start / boundary != (range.end - 1) / boundary
+    }
+}
+
+/// Formats the instruction as a line of a listing: its offset, its bytes, and its mnemonic and operands, or its family if it wasn't emitted by
+/// the text assembler.
+///
+/// # Example
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+///
+/// let mut stream = x86_64InstructionStream::new();
+/// stream.enable_listing(true);
+/// stream.push_reg64(Reg64::R12);
+/// stream.assemble("mov rax, rdi\npush -3\nret").unwrap();
+///
+/// let listing = stream.instructions().map(|instruction| instruction.to_string()).collect::<Vec<_>>();
+/// assert_eq!(listing, [
+///     "00000000  41 54                    ; push",
+///     "00000002  48 89 f8                 mov rax, rdi",
+///     "00000005  6a fd                    push -3",
+///     "00000007  c3                       ret",
+/// ]);
+/// ```
+impl fmt::Display for EmittedInsn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ");
+        write!(f, "{:08x}  {:<24} ", self.offset, bytes)?;
+
+        match (self.mnemonic, self.operands) {
+            (Some(mnemonic), Some("")) | (Some(mnemonic), None) => write!(f, "{}", mnemonic),
+            (Some(mnemonic), Some(operands)) => write!(f, "{} {}", mnemonic, operands),
+            (None, _) => write!(f, "; {}", self.family.name()),
+        }
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// The instructions emitted while [`x86_64InstructionStream::enable_listing`] was set, by offset.  The instructions of the basic blocks are
+    /// listed once the blocks are laid out.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stats::InstructionFamily, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.push_reg64(Reg64::R12);
+    /// stream.assemble("mov rax, rdi\nret").unwrap();
+    ///
+    /// let instructions = stream.instructions().collect::<Vec<_>>();
+    /// assert_eq!(instructions.len(), 3);
+    ///
+    /// assert_eq!((instructions[0].offset, instructions[0].bytes), (0, &[0x41, 0x54][..]));
+    /// assert_eq!((instructions[0].family, instructions[0].mnemonic), (InstructionFamily::Push, None));
+    ///
+    /// assert_eq!(instructions[1].range(), 2..5);
+    /// assert_eq!((instructions[1].mnemonic, instructions[1].operands), (Some("mov"), Some("rax, rdi")));
+    /// assert_eq!((instructions[2].mnemonic, instructions[2].operands), (Some("ret"), Some("")));
+    /// ```
+    pub fn instructions(&self) -> impl Iterator<Item = EmittedInsn<'_>> {
+        let bytes = self.code();
+
+        self.instruction_listing().records.iter().map(move |record| EmittedInsn {
+            offset: record.offset,
+            bytes: &bytes[record.offset..record.offset + record.len],
+            family: record.family,
+            form: record.text.as_ref().map(|(form, _)| *form),
+            mnemonic: record.text.as_ref().map(|(form, _)| form.mnemonic),
+            operands: record.text.as_ref().map(|(_, operands)| operands.as_str()),
+            registers: record.registers,
+        })
+    }
+
+    /// The instructions which overlap `range`, see [`x86_64InstructionStream::instructions`].
+    pub fn instructions_overlapping(&self, range: Range<usize>) -> impl Iterator<Item = EmittedInsn<'_>> {
+        self.instructions().filter(move |instruction| instruction.offset < range.end && range.start < instruction.range().end)
+    }
+
+    /// The instructions which cross a multiple of `boundary`, such as the calls which can't be patched atomically because they straddle a 64-byte
+    /// cache line.
+    ///
+    /// # Panics
+    /// Panics if `boundary` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// let target = stream.create_label();
+    /// stream.write_bytes(&[0x90; 60]); // padding, left out of the listing
+    /// stream.call_label(target);       // spans 60..65, crossing 64
+    /// stream.attach_label(target);
+    /// stream.call_label(target);
+    /// stream.ret_near();
+    ///
+    /// let crossing = stream.instructions_crossing(64).collect::<Vec<_>>();
+    /// assert_eq!(crossing.len(), 1);
+    /// assert_eq!(crossing[0].range(), 60..65);
+    ///
+    /// assert_eq!(stream.call_sites().map(|call| call.offset).collect::<Vec<_>>(), [60, 65]);
+    /// assert_eq!(stream.instructions_overlapping(64..66).count(), 2);
+    /// assert_eq!(stream.instructions_overlapping(70..71).count(), 1);
+    /// ```
+    pub fn instructions_crossing(&self, boundary: usize) -> impl Iterator<Item = EmittedInsn<'_>> {
+        assert!(boundary != 0, "the boundary can't be zero");
+        self.instructions().filter(move |instruction| instruction.crosses(boundary))
+    }
+
+    /// The calls, see [`x86_64InstructionStream::instructions`].
+    pub fn call_sites(&self) -> impl Iterator<Item = EmittedInsn<'_>> {
+        self.instructions().filter(|instruction| instruction.family == InstructionFamily::Call)
+    }
+}
@@ -0,0 +1,712 @@
+//! Inline memory operations: the `memcpy`, `memset` and `memcmp` intrinsics, and the string instructions they build on.
+//!
+//! A constant length up to [`UNROLL_LIMIT`] bytes is unrolled into the widest moves the target has: 32 bytes through `ymm5` when AVX is enabled,
+//! see [`x86_64InstructionStream::new_with_features`], 16 bytes through `xmm5` otherwise, then 8, 4, 2 and 1 bytes through `r11`, or `r10` or `r9`
+//! when `r11` is an operand, or through a register of the [`ScratchPool`] given to the `_with_pool` variants.  Longer lengths, and lengths held in a register, go through the `rep` string instructions, which take their operands in
+//! `rdi`, `rsi` and `rcx`; the operands are moved there in parallel, so they can be held in any register.
+//!
+//! Each intrinsic returns the registers it clobbers as [`Clobbers`].  The operand registers are left untouched unless they are clobbered.
+//!
+//! # Example
+//! The intrinsics checked against [`std::ptr::copy`], [`std::ptr::write_bytes`] and the comparison of slices:
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # fn main() {
+//! use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+//! use asmkit_x86_64::{features::CpuFeatures, memops::MemcpyLen, register::Reg64, stream::x86_64InstructionStream};
+//!
+//! fn load(features: CpuFeatures, build: impl FnOnce(&mut x86_64InstructionStream)) -> ExecutableBuffer {
+//!     let mut stream = x86_64InstructionStream::new_with_features(features);
+//!     build(&mut stream);
+//!     stream.ret_near();
+//!     ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap()
+//! }
+//!
+//! let src = (0..1100).map(|i| (i * 7 + 3) as u8).collect::<Vec<_>>();
+//!
+//! // the 16-byte moves of the baseline, and the 32-byte ones of AVX when the host has it.
+//! for features in [CpuFeatures::SSE2, CpuFeatures::detect_host()] {
+//!     for len in [0, 1, 2, 7, 8, 15, 16, 31, 33, 64, 100, 255, 256, 257, 1000] {
+//!         let mut expected = vec![0xaa; 1100];
+//!         unsafe { std::ptr::copy(src.as_ptr(), expected.as_mut_ptr(), len) };
+//!
+//!         // (dst, src) in (rdi, rsi).
+//!         let memcpy = load(features, |stream| {
+//!             stream.intrinsic_memcpy(Reg64::Rdi, Reg64::Rsi, MemcpyLen::Const(len));
+//!         });
+//!         let memcpy: extern "C" fn(*mut u8, *const u8) = unsafe { std::mem::transmute(memcpy.as_ptr()) };
+//!
+//!         let mut dst = vec![0xaa; 1100];
+//!         memcpy(dst.as_mut_ptr(), src.as_ptr());
+//!         assert_eq!(dst, expected);
+//!
+//!         // (src, dst, len) in (rdi, rsi, rdx), the pointers swapped from where `rep movsb` takes them.
+//!         let memcpy = load(features, |stream| {
+//!             stream.intrinsic_memcpy(Reg64::Rsi, Reg64::Rdi, MemcpyLen::Reg(Reg64::Rdx));
+//!         });
+//!         let memcpy: extern "C" fn(*const u8, *mut u8, usize) = unsafe { std::mem::transmute(memcpy.as_ptr()) };
+//!
+//!         let mut dst = vec![0xaa; 1100];
+//!         memcpy(src.as_ptr(), dst.as_mut_ptr(), len);
+//!         assert_eq!(dst, expected);
+//!
+//!         // (dst) in (rdi).
+//!         let memset = load(features, |stream| {
+//!             stream.intrinsic_memset(Reg64::Rdi, 0x5c, MemcpyLen::Const(len));
+//!         });
+//!         let memset: extern "C" fn(*mut u8) = unsafe { std::mem::transmute(memset.as_ptr()) };
+//!
+//!         let mut expected = vec![0xaa; 1100];
+//!         unsafe { std::ptr::write_bytes(expected.as_mut_ptr(), 0x5c, len) };
+//!
+//!         let mut dst = vec![0xaa; 1100];
+//!         memset(dst.as_mut_ptr());
+//!         assert_eq!(dst, expected);
+//!
+//!         // (lhs, rhs, len) in (rdi, rsi, rdx), the result returned in rax.
+//!         let memcmp = load(features, |stream| {
+//!             stream.intrinsic_memcmp(Reg64::Rdi, Reg64::Rsi, MemcpyLen::Reg(Reg64::Rdx), Reg64::Rax);
+//!         });
+//!         let memcmp: extern "C" fn(*const u8, *const u8, usize) -> i64 = unsafe { std::mem::transmute(memcmp.as_ptr()) };
+//!
+//!         let mut other = src.clone();
+//!         assert_eq!(memcmp(src.as_ptr(), other.as_ptr(), len), 0);
+//!
+//!         if len > 0 {
+//!             other[len / 2] = other[len / 2].wrapping_add(1);
+//!             let sign = memcmp(src.as_ptr(), other.as_ptr(), len).signum();
+//!             assert_eq!(sign, src[..len].cmp(&other[..len]) as i64);
+//!             assert_eq!(memcmp(other.as_ptr(), src.as_ptr(), len).signum(), -sign);
+//!         }
+//!     }
+//! }
+//! # }
+//! # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+//! # fn main() {}
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::{RegId, RegSet},
+    features::CpuFeatures,
+    memory::Mem,
+    register::{GpReg, Reg16, Reg32, Reg64, Reg8, VectorReg, Xmm, Ymm},
+    scratch::ScratchPool,
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, Condition},
+};
+
+/// The longest constant length unrolled by the intrinsics, in bytes.  Longer lengths go through the `rep` string instructions.
+pub const UNROLL_LIMIT: usize = 256;
+
+/// The length of a memory intrinsic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemcpyLen {
+    /// A length known when the code is generated, unrolled up to [`UNROLL_LIMIT`] bytes.
+    Const(usize),
+
+    /// A length held in a register.
+    Reg(Reg64),
+}
+
+/// The registers clobbered by a memory intrinsic.  A vector register stands for both its `xmm` and `ymm` forms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clobbers {
+    /// The clobbered 64-bit registers, a bit per register number.
+    regs: u16,
+
+    /// The clobbered vector registers, a bit per register number.
+    vectors: u16,
+}
+
+impl Clobbers {
+    /// Returns true if no register is clobbered.
+    pub fn is_empty(&self) -> bool {
+        self.regs == 0 && self.vectors == 0
+    }
+
+    /// Returns true if *r64* is clobbered.
+    pub fn contains_reg64(&self, reg: Reg64) -> bool {
+        self.regs & (1 << reg.number()) != 0
+    }
+
+    /// Returns true if the vector register is clobbered.
+    pub fn contains_vector(&self, reg: impl VectorReg) -> bool {
+        self.vectors & (1 << reg.number()) != 0
+    }
+
+    /// The clobbered 64-bit registers, by number.
+    pub fn reg64s(&self) -> impl Iterator<Item = Reg64> + '_ {
+        Reg64::REGS.into_iter().filter(|&reg| self.contains_reg64(reg))
+    }
+
+    /// The clobbered vector registers, by number.
+    pub fn xmms(&self) -> impl Iterator<Item = Xmm> + '_ {
+        Xmm::REGS.into_iter().filter(|&reg| self.contains_vector(reg))
+    }
+
+    /// Adds *r64* to the clobbered registers.
+    fn insert_reg64(&mut self, reg: Reg64) {
+        self.regs |= 1 << reg.number();
+    }
+
+    /// Adds the vector register to the clobbered registers.
+    fn insert_vector(&mut self, reg: impl VectorReg) {
+        self.vectors |= 1 << reg.number();
+    }
+}
+
+/// The vector register used by the unrolled intrinsics.
+impl From<Clobbers> for RegSet {
+    /// The clobbered registers, as defined by the intrinsic.
+    fn from(clobbers: Clobbers) -> Self {
+        clobbers.reg64s().map(RegId::from).chain(clobbers.xmms().map(RegId::from)).collect()
+    }
+}
+
+/// The registers of the `rep` string instructions copying or comparing `[rsi]` and `[rdi]`, which step `rsi` and `rdi` and count `rcx`
+/// down, using and defining all three.
+fn string_registers() -> RegSet {
+    RegSet::of(Reg64::Rsi).with(Reg64::Rdi).with(Reg64::Rcx)
+}
+
+const VECTOR_SCRATCH: Xmm = Xmm::Xmm5;
+
+/// The scratch registers of the unrolled intrinsics which aren't given a [`ScratchPool`], in order.
+const DEFAULT_SCRATCH: [Reg64; 3] = [Reg64::R11, Reg64::R10, Reg64::R9];
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Move `rcx` bytes from `[rsi]` to `[rdi]`, advancing `rsi` and `rdi` past them and clearing `rcx`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{defuse::RegSet, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.rep_movsb();
+    /// stream.rep_stosb();
+    /// stream.repe_cmpsb();
+    ///
+    /// // the operands are implicit: the pointers and the count are used and defined, and `rep stosb` stores `al`.
+    /// let operands = RegSet::of(Reg64::Rsi).with(Reg64::Rdi).with(Reg64::Rcx);
+    /// let registers = stream.instructions().map(|instruction| (instruction.defs().unwrap(), instruction.uses().unwrap())).collect::<Vec<_>>();
+    /// assert_eq!(registers, [
+    ///     (operands, operands),
+    ///     (RegSet::of(Reg64::Rdi).with(Reg64::Rcx), RegSet::of(Reg64::Rdi).with(Reg64::Rcx).with(Reg64::Rax)),
+    ///     (operands, operands),
+    /// ]);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0xa4, // rep movsb byte ptr es:[rdi], byte ptr [rsi]
+    ///     0xf3, 0xaa, // rep stosb byte ptr es:[rdi], al
+    ///     0xf3, 0xa6, // rep cmpsb byte ptr [rsi], byte ptr es:[rdi]
+    /// ]);
+    /// ```
+    pub fn rep_movsb(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xf3, 0xa4]);
+        self.record_registers(string_registers(), string_registers());
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Store `al` to the `rcx` bytes at `[rdi]`, advancing `rdi` past them and clearing `rcx`.
+    pub fn rep_stosb(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xf3, 0xaa]);
+        self.record_registers(RegSet::of(Reg64::Rdi).with(Reg64::Rcx), RegSet::of(Reg64::Rdi).with(Reg64::Rcx).with(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Compare the bytes at `[rsi]` with those at `[rdi]` while they are equal, up to `rcx` bytes, advancing `rsi` and `rdi` past the bytes
+    /// compared and decrementing `rcx`.  The flags are those of the last comparison, and left untouched when `rcx` is zero.
+    pub fn repe_cmpsb(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xf3, 0xa6]);
+        self.record_registers(string_registers(), string_registers());
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Load the byte at `[rsi]` into `al`, advancing `rsi` past it.
+    ///
+    /// The string instructions address through `rsi` and `rdi`, and through `si` and `di` in [real mode](crate::mode), where they take 16-bit
+    /// operands rather than 32-bit ones.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, mode::Mode, stream::x86_64InstructionStream};
+    ///
+    /// for (mode, word) in [(Mode::Long, &[0x66][..]), (Mode::Real, &[][..])] {
+    ///     let mut stream = x86_64InstructionStream::new_with_mode(mode, CpuFeatures::ALL);
+    ///     stream.lodsb();
+    ///     stream.lodsw();
+    ///     stream.stosb();
+    ///     stream.stosw();
+    ///     stream.movsb();
+    ///     stream.movsw();
+    ///
+    ///     assert_eq!(stream.finish().emit(), [
+    ///         &[0xac][..],        // lodsb
+    ///         word, &[0xad][..],  // lodsw
+    ///         &[0xaa][..],        // stosb
+    ///         word, &[0xab][..],  // stosw
+    ///         &[0xa4][..],        // movsb
+    ///         word, &[0xa5][..],  // movsw
+    ///     ].concat());
+    /// }
+    /// ```
+    pub fn lodsb(&mut self) {
+        self.write_string(false, 0xac);
+    }
+
+    /// Load the word at `[rsi]` into `ax`, advancing `rsi` past it.
+    pub fn lodsw(&mut self) {
+        self.write_string(true, 0xad);
+    }
+
+    /// Store `al` to the byte at `[rdi]`, advancing `rdi` past it.
+    pub fn stosb(&mut self) {
+        self.write_string(false, 0xaa);
+    }
+
+    /// Store `ax` to the word at `[rdi]`, advancing `rdi` past it.
+    pub fn stosw(&mut self) {
+        self.write_string(true, 0xab);
+    }
+
+    /// Move the byte at `[rsi]` to `[rdi]`, advancing `rsi` and `rdi` past it.
+    pub fn movsb(&mut self) {
+        self.write_string(false, 0xa4);
+    }
+
+    /// Move the word at `[rsi]` to `[rdi]`, advancing `rsi` and `rdi` past it.
+    pub fn movsw(&mut self) {
+        self.write_string(true, 0xa5);
+    }
+
+    /// Writes a string instruction on bytes, or on words if `word` is true.
+    fn write_string(&mut self, word: bool, opcode: u8) {
+        let instruction = self.begin_instruction();
+
+        if word {
+            self.write_word_operand_prefix();
+        }
+
+        self.write_byte(opcode);
+
+        // `lods` loads `al` or `ax` from `[rsi]`, `stos` stores them to `[rdi]`, and `movs` copies `[rsi]` to `[rdi]`, stepping the pointers.
+        let (defs, uses) = match opcode {
+            0xac | 0xad => (RegSet::of(Reg64::Rax).with(Reg64::Rsi), RegSet::of(Reg64::Rax).with(Reg64::Rsi)),
+            0xaa | 0xab => (RegSet::of(Reg64::Rdi), RegSet::of(Reg64::Rax).with(Reg64::Rdi)),
+            _ => (RegSet::of(Reg64::Rsi).with(Reg64::Rdi), RegSet::of(Reg64::Rsi).with(Reg64::Rdi)),
+        };
+
+        self.record_registers(defs, uses);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Copy `len` bytes from `[src]` to `[dst]`, which must not overlap, and return the registers clobbered.
+    ///
+    /// A constant length up to [`UNROLL_LIMIT`] is unrolled, clobbering `r11` (or `r10` or `r9` when `r11` is an operand) and `xmm5`, or `ymm5`
+    /// when AVX is enabled.  Otherwise the copy is a `rep movsb`, clobbering `rdi`, `rsi` and `rcx`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, memops::MemcpyLen, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::SSE2);
+    /// let clobbers = stream.intrinsic_memcpy(Reg64::Rax, Reg64::R8, MemcpyLen::Const(27));
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::R11]);
+    /// assert_eq!(clobbers.xmms().collect::<Vec<_>>(), [Xmm::Xmm5]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x41, 0x0f, 0x6f, 0x28,       // movdqu xmm5, xmmword ptr [r8]
+    ///     0xf3, 0x0f, 0x7f, 0x28,             // movdqu xmmword ptr [rax], xmm5
+    ///     0x4d, 0x8b, 0x58, 0x10,             // mov r11, qword ptr [r8 + 16]
+    ///     0x4c, 0x89, 0x58, 0x10,             // mov qword ptr [rax + 16], r11
+    ///     0x66, 0x45, 0x8b, 0x58, 0x18,       // mov r11w, word ptr [r8 + 24]
+    ///     0x66, 0x44, 0x89, 0x58, 0x18,       // mov word ptr [rax + 24], r11w
+    ///     0x45, 0x8a, 0x58, 0x1a,             // mov r11b, byte ptr [r8 + 26]
+    ///     0x44, 0x88, 0x58, 0x1a,             // mov byte ptr [rax + 26], r11b
+    /// ]);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let clobbers = stream.intrinsic_memcpy(Reg64::Rsi, Reg64::Rdi, MemcpyLen::Reg(Reg64::Rdx));
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::Rcx, Reg64::Rsi, Reg64::Rdi]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x89, 0xd1, // mov rcx, rdx
+    ///     0x48, 0x87, 0xf7, // xchg rdi, rsi
+    ///     0xf3, 0xa4,       // rep movsb byte ptr es:[rdi], byte ptr [rsi]
+    /// ]);
+    /// ```
+    pub fn intrinsic_memcpy(&mut self, dst: Reg64, src: Reg64, len: MemcpyLen) -> Clobbers {
+        self.intrinsic_memcpy_with_pool(dst, src, len, &ScratchPool::new(&DEFAULT_SCRATCH))
+    }
+
+    /// Copy `len` bytes from `[src]` to `[dst]` as [`x86_64InstructionStream::intrinsic_memcpy`] does, unrolling through a register acquired from
+    /// `pool` instead of `r11`, and return the registers clobbered.
+    ///
+    /// # Panics
+    /// Panics if the length is unrolled and every candidate of `pool` is either acquired, `dst` or `src`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, memops::MemcpyLen, register::Reg64, scratch::ScratchPool, stream::x86_64InstructionStream};
+    ///
+    /// let pool = ScratchPool::new(&[Reg64::Rax, Reg64::Rcx, Reg64::Rdx]);
+    /// let _held = pool.acquire(&[]).unwrap();
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::SSE2);
+    /// let clobbers = stream.intrinsic_memcpy_with_pool(Reg64::Rdi, Reg64::Rcx, MemcpyLen::Const(12), &pool);
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::Rdx]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x8b, 0x11,       // mov rdx, qword ptr [rcx]
+    ///     0x48, 0x89, 0x17,       // mov qword ptr [rdi], rdx
+    ///     0x8b, 0x51, 0x08,       // mov edx, dword ptr [rcx + 8]
+    ///     0x89, 0x57, 0x08,       // mov dword ptr [rdi + 8], edx
+    /// ]);
+    /// ```
+    ///
+    /// An exhausted pool is rejected:
+    /// ```should_panic
+    /// use asmkit_x86_64::{memops::MemcpyLen, register::Reg64, scratch::ScratchPool, stream::x86_64InstructionStream};
+    ///
+    /// let pool = ScratchPool::new(&[Reg64::Rdi]);
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_memcpy_with_pool(Reg64::Rdi, Reg64::Rsi, MemcpyLen::Const(8), &pool);
+    /// ```
+    pub fn intrinsic_memcpy_with_pool(&mut self, dst: Reg64, src: Reg64, len: MemcpyLen, pool: &ScratchPool) -> Clobbers {
+        let mut clobbers = Clobbers::default();
+
+        match len {
+            MemcpyLen::Const(len) if len <= UNROLL_LIMIT => {
+                let guard = pool.acquire(&[dst, src]).expect("no scratch register is free for the unrolled `memcpy`");
+                let scratch = *guard;
+                let mut offset = 0;
+
+                self.write_unrolled(len, &mut clobbers, |stream, size| {
+                    let (from, to) = (Mem::new(src, offset as i32), Mem::new(dst, offset as i32));
+
+                    match size {
+                        32 => {
+                            let ymm = Ymm::Ymm5;
+                            stream.vmovdqu_ymm_mem(ymm, from);
+                            stream.vmovdqu_mem_ymm(to, ymm);
+                        },
+                        16 => {
+                            stream.movdqu_xmm_mem(VECTOR_SCRATCH, from);
+                            stream.movdqu_mem_xmm(to, VECTOR_SCRATCH);
+                        },
+                        8 => {
+                            stream.mov_reg64_mem64(scratch, from);
+                            stream.mov_mem64_reg64(to, scratch);
+                        },
+                        4 => {
+                            stream.mov_reg32_mem32(Reg32::from(scratch), from);
+                            stream.mov_mem32_reg32(to, Reg32::from(scratch));
+                        },
+                        2 => {
+                            stream.mov_reg16_mem16(Reg16::from(scratch), from);
+                            stream.mov_mem16_reg16(to, Reg16::from(scratch));
+                        },
+                        _ => {
+                            stream.mov_reg8_mem8(Reg8::from(scratch), from);
+                            stream.mov_mem8_reg8(to, Reg8::from(scratch));
+                        },
+                    }
+
+                    offset += size;
+                    Some(scratch).filter(|_| size < 16)
+                });
+            },
+            len => {
+                self.write_string_operands(Some(dst), Some(src), len, &mut clobbers);
+                self.rep_movsb();
+            },
+        }
+
+        clobbers
+    }
+
+    /// Fill the `len` bytes at `[dst]` with `value`, and return the registers clobbered.
+    ///
+    /// A constant length up to [`UNROLL_LIMIT`] is unrolled, clobbering `r11` (or `r10` or `r9` when `r11` is an operand) and `xmm5`, or `ymm5`
+    /// when AVX is enabled; the vector stores load the splatted `value` from the constant pool, see
+    /// [`x86_64InstructionStream::add_const_splat_u64`].  Otherwise the fill is a `rep stosb`, clobbering `rdi`, `rcx` and `rax`.  A zero `value`
+    /// is loaded by a `xor`, clobbering the flags.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memops::MemcpyLen, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let clobbers = stream.intrinsic_memset(Reg64::Rdi, 0, MemcpyLen::Const(14));
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::R11]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x45, 0x31, 0xdb,                         // xor r11d, r11d
+    ///     0x4c, 0x89, 0x1f,                         // mov qword ptr [rdi], r11
+    ///     0xc7, 0x47, 0x08, 0x00, 0x00, 0x00, 0x00, // mov dword ptr [rdi + 8], 0
+    ///     0x66, 0xc7, 0x47, 0x0c, 0x00, 0x00,       // mov word ptr [rdi + 12], 0
+    /// ]);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_memset(Reg64::Rdi, 0xab, MemcpyLen::Const(9));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x49, 0xbb, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, 0xab, // movabs r11, 0xabababababababab
+    ///     0x4c, 0x89, 0x1f,                                           // mov qword ptr [rdi], r11
+    ///     0xc6, 0x47, 0x08, 0xab,                                     // mov byte ptr [rdi + 8], 0xab
+    /// ]);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_memset(Reg64::Rdi, 0, MemcpyLen::Reg(Reg64::Rsi));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x89, 0xf1, // mov rcx, rsi
+    ///     0x31, 0xc0,       // xor eax, eax
+    ///     0xf3, 0xaa,       // rep stosb byte ptr es:[rdi], al
+    /// ]);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let clobbers = stream.intrinsic_memset(Reg64::R8, 0xff, MemcpyLen::Reg(Reg64::Rdi));
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::Rax, Reg64::Rcx, Reg64::Rdi]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x89, 0xf9,             // mov rcx, rdi
+    ///     0x4c, 0x89, 0xc7,             // mov rdi, r8
+    ///     0xb8, 0xff, 0x00, 0x00, 0x00, // mov eax, 0xff
+    ///     0xf3, 0xaa,                   // rep stosb byte ptr es:[rdi], al
+    /// ]);
+    /// ```
+    pub fn intrinsic_memset(&mut self, dst: Reg64, value: u8, len: MemcpyLen) -> Clobbers {
+        self.intrinsic_memset_with_pool(dst, value, len, &ScratchPool::new(&DEFAULT_SCRATCH))
+    }
+
+    /// Fill the `len` bytes at `[dst]` with `value` as [`x86_64InstructionStream::intrinsic_memset`] does, unrolling through a register acquired
+    /// from `pool` instead of `r11`, and return the registers clobbered.
+    ///
+    /// # Panics
+    /// Panics if the length is unrolled and every candidate of `pool` is either acquired or `dst`.
+    pub fn intrinsic_memset_with_pool(&mut self, dst: Reg64, value: u8, len: MemcpyLen, pool: &ScratchPool) -> Clobbers {
+        let mut clobbers = Clobbers::default();
+
+        match len {
+            MemcpyLen::Const(len) if len <= UNROLL_LIMIT => {
+                let splat = u64::from_le_bytes([value; 8]);
+                let guard = pool.acquire(&[dst]).expect("no scratch register is free for the unrolled `memset`");
+                let scratch = *guard;
+                let (mut offset, mut splat_loaded) = (0, false);
+
+                self.write_unrolled(len, &mut clobbers, |stream, size| {
+                    let to = Mem::new(dst, offset as i32);
+
+                    match size {
+                        32 => {
+                            let ymm = Ymm::Ymm5;
+                            if !splat_loaded {
+                                let label = stream.add_const_splat_u64(splat, 4);
+                                stream.vmovdqa_ymm_label(ymm, label);
+                                splat_loaded = true;
+                            }
+
+                            stream.vmovdqu_mem_ymm(to, ymm);
+                        },
+                        16 => {
+                            // the low half of a `ymm5` splat is an `xmm5` splat.
+                            if !splat_loaded {
+                                let label = stream.add_const_splat_u64(splat, 2);
+                                stream.movdqa_xmm_label(VECTOR_SCRATCH, label);
+                                splat_loaded = true;
+                            }
+
+                            stream.movdqu_mem_xmm(to, VECTOR_SCRATCH);
+                        },
+                        8 => {
+                            match splat {
+                                0 => stream.xor_reg32_reg32(Reg32::from(scratch), Reg32::from(scratch)),
+                                _ => stream.mov_reg64_imm64(scratch, splat),
+                            }
+
+                            stream.mov_mem64_reg64(to, scratch);
+                        },
+                        4 => stream.mov_mem32_imm32(to, splat as u32),
+                        2 => stream.mov_mem16_imm16(to, splat as u16),
+                        _ => stream.mov_mem8_imm8(to, value),
+                    }
+
+                    offset += size;
+
+                    // the narrower tails are stored as immediates.
+                    Some(scratch).filter(|_| size == 8)
+                });
+            },
+            len => {
+                self.write_string_operands(Some(dst), None, len, &mut clobbers);
+                match value {
+                    0 => self.xor_reg32_reg32(Reg32::Eax, Reg32::Eax),
+                    _ => self.mov_reg32_imm32(Reg32::Eax, value as u32),
+                }
+
+                clobbers.insert_reg64(Reg64::Rax);
+                self.rep_stosb();
+            },
+        }
+
+        clobbers
+    }
+
+    /// Compare the `len` bytes at `[lhs]` with those at `[rhs]` into *r64*, and return the registers clobbered.  As with the C `memcmp`, *r64* is
+    /// zero if the bytes are equal, and otherwise the difference of the first differing bytes, as unsigned integers: negative if `lhs` is the
+    /// lesser.
+    ///
+    /// The comparison is a `repe cmpsb` for any length, clobbering `rsi`, `rdi`, `rcx` and `rax`, along with *r64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memops::MemcpyLen, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let clobbers = stream.intrinsic_memcmp(Reg64::Rdi, Reg64::Rsi, MemcpyLen::Const(16), Reg64::Rdx);
+    ///
+    /// assert_eq!(clobbers.reg64s().collect::<Vec<_>>(), [Reg64::Rax, Reg64::Rcx, Reg64::Rdx, Reg64::Rsi, Reg64::Rdi]);
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x87, 0xf7,                   // xchg rdi, rsi
+    ///     0xb9, 0x10, 0x00, 0x00, 0x00,       // mov ecx, 16
+    ///     0x31, 0xc0,                         // xor eax, eax
+    ///     0xf3, 0xa6,                         // repe cmpsb byte ptr [rsi], byte ptr es:[rdi]
+    ///     0x0f, 0x84, 0x0a, 0x00, 0x00, 0x00, // je done
+    ///     0x0f, 0xb6, 0x46, 0xff,             // movzx eax, byte ptr [rsi - 1]
+    ///     0x0f, 0xb6, 0x4f, 0xff,             // movzx ecx, byte ptr [rdi - 1]
+    ///     0x29, 0xc8,                         // sub eax, ecx
+    ///     0x48, 0x63, 0xd0,                   // done: movsxd rdx, eax
+    /// ]);
+    /// ```
+    pub fn intrinsic_memcmp(&mut self, lhs: Reg64, rhs: Reg64, len: MemcpyLen, result: Reg64) -> Clobbers {
+        let mut clobbers = Clobbers::default();
+
+        // `cmpsb` compares `[rsi]` with `[rdi]`.
+        self.write_string_operands(Some(rhs), Some(lhs), len, &mut clobbers);
+
+        // the flags of the `xor` stand for equal bytes when no byte is compared.
+        self.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+        self.repe_cmpsb();
+
+        let done = self.create_label();
+        self.jcc_label(Condition::E, done);
+        self.movzx_reg32_mem8(Reg32::Eax, Mem::new(Reg64::Rsi, -1));
+        self.movzx_reg32_mem8(Reg32::Ecx, Mem::new(Reg64::Rdi, -1));
+        self.sub_reg32_reg32(Reg32::Eax, Reg32::Ecx);
+        self.attach_label(done);
+        self.movsxd_reg64_reg32(result, Reg32::Eax);
+
+        clobbers.insert_reg64(Reg64::Rax);
+        clobbers.insert_reg64(result);
+        clobbers
+    }
+
+    /// Writes the chunks of an unrolled intrinsic of `len` bytes, from the widest to the narrowest, through `chunk`, which writes a chunk of the
+    /// given size and returns the general purpose scratch register it uses, if any.  The `ymm` chunks are followed by a `vzeroupper`, before any
+    /// legacy SSE instruction.
+    fn write_unrolled(&mut self, mut len: usize, clobbers: &mut Clobbers, mut chunk: impl FnMut(&mut Self, usize) -> Option<Reg64>) {
+        let widest = if self.features().contains(CpuFeatures::AVX) { 32 } else { 16 };
+
+        for size in [32, 16, 8, 4, 2, 1] {
+            if size > widest {
+                continue;
+            }
+
+            while len >= size {
+                let scratch = chunk(self, size);
+                len -= size;
+
+                if size >= 16 {
+                    clobbers.insert_vector(VECTOR_SCRATCH);
+                }
+
+                if let Some(scratch) = scratch {
+                    clobbers.insert_reg64(scratch);
+                }
+            }
+
+            if size == 32 && clobbers.contains_vector(VECTOR_SCRATCH) {
+                self.vzeroupper();
+            }
+        }
+    }
+
+    /// Moves the destination `dst` to `rdi`, the source `src` to `rsi` and the length `len` to `rcx`, for a string instruction, in parallel so the
+    /// operands can be held in any register.
+    fn write_string_operands(&mut self, dst: Option<Reg64>, src: Option<Reg64>, len: MemcpyLen, clobbers: &mut Clobbers) {
+        let mut moves = Vec::new();
+
+        if let Some(dst) = dst {
+            moves.push((Reg64::Rdi, dst));
+        }
+
+        if let Some(src) = src {
+            moves.push((Reg64::Rsi, src));
+        }
+
+        if let MemcpyLen::Reg(len) = len {
+            moves.push((Reg64::Rcx, len));
+        }
+
+        for &(dest, _) in &moves {
+            clobbers.insert_reg64(dest);
+        }
+
+        self.write_parallel_moves(moves);
+
+        match len {
+            MemcpyLen::Const(len) => {
+                clobbers.insert_reg64(Reg64::Rcx);
+
+                match u32::try_from(len) {
+                    Ok(len) => self.mov_reg32_imm32(Reg32::Ecx, len),
+                    Err(_) => self.mov_reg64_imm64(Reg64::Rcx, len as u64),
+                }
+            },
+            MemcpyLen::Reg(_) => {},
+        }
+    }
+
+    /// Writes the `(dest, src)` moves of `moves` as if they were done at once, ordering them so no source is overwritten before it is read, and
+    /// breaking the cycles with exchanges.  The destinations must be distinct.
+    fn write_parallel_moves(&mut self, mut moves: Vec<(Reg64, Reg64)>) {
+        moves.retain(|(dest, src)| dest != src);
+
+        while !moves.is_empty() {
+            let ready = moves.iter().position(|&(dest, _)| moves.iter().all(|&(_, src)| src != dest));
+
+            match ready {
+                Some(index) => {
+                    let (dest, src) = moves.remove(index);
+                    self.mov_reg64_reg64(dest, src);
+                },
+                None => {
+                    // every destination is the source of another move: the exchange leaves the old value of `dest` in `src`.
+                    let (dest, src) = moves.remove(0);
+                    self.xchg_reg64_reg64(dest, src);
+
+                    for (_, other) in &mut moves {
+                        if *other == dest {
+                            *other = src;
+                        }
+                    }
+
+                    moves.retain(|(dest, src)| dest != src);
+                },
+            }
+        }
+    }
+}
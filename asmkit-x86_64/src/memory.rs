@@ -0,0 +1,116 @@
+//! Memory operands.
+
+use crate::register::Reg64;
+
+/// A memory operand addressed by a base register, an optional scaled index register and a displacement, `[base + index*scale + displacement]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mem {
+    /// The base register.
+    pub base: Reg64,
+
+    /// The index register and its scale, if any.
+    pub index: Option<(Reg64, Scale)>,
+
+    /// The displacement from the base register.
+    pub displacement: i32,
+}
+
+impl Mem {
+    /// Creates a memory operand addressing `base + displacement`.
+    #[inline(always)]
+    pub fn new(base: Reg64, displacement: i32) -> Self {
+        Self { base, index: None, displacement }
+    }
+
+    /// Creates a memory operand addressing `base + index*scale + displacement`.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`, which can't be encoded as an index register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::{Mem, Scale}, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg64_mem64(Reg64::Rax, Mem::indexed(Reg64::Rbx, Reg64::Rcx, Scale::X8, 0));
+    /// stream.mov_mem32_reg32(Mem::indexed(Reg64::R13, Reg64::R12, Scale::X4, -8), asmkit_x86_64::register::Reg32::Edx);
+    /// stream.lea_reg64_mem(Reg64::Rsi, Mem::indexed(Reg64::Rsp, Reg64::Rdi, Scale::X1, 0x1000));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x8b, 0x04, 0xcb,                         // mov rax, qword ptr [rbx + rcx*8]
+    ///     0x43, 0x89, 0x54, 0xa5, 0xf8,                   // mov dword ptr [r13 + r12*4 - 8], edx
+    ///     0x48, 0x8d, 0xb4, 0x3c, 0x00, 0x10, 0x00, 0x00, // lea rsi, [rsp + rdi + 0x1000]
+    /// ]);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::{memory::{Mem, Scale}, register::Reg64};
+    ///
+    /// Mem::indexed(Reg64::Rax, Reg64::Rsp, Scale::X1, 0);
+    /// ```
+    #[inline(always)]
+    pub fn indexed(base: Reg64, index: Reg64, scale: Scale, displacement: i32) -> Self {
+        assert!(index != Reg64::Rsp, "`rsp` can't be used as an index register");
+        Self { base, index: Some((index, scale)), displacement }
+    }
+}
+
+/// The scale of an index register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    X1,
+    X2,
+    X4,
+    X8,
+}
+
+impl Scale {
+    /// The scale field of the SIB byte, for instruction encoding.
+    #[inline(always)]
+    pub fn bits(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The factor of the scale, 1, 2, 4 or 8.
+    #[inline(always)]
+    pub fn factor(&self) -> u8 {
+        1 << self.bits()
+    }
+
+    /// The scale multiplying by `factor`, if it is 1, 2, 4 or 8.
+    pub fn from_factor(factor: u8) -> Option<Self> {
+        match factor {
+            1 => Some(Self::X1),
+            2 => Some(Self::X2),
+            4 => Some(Self::X4),
+            8 => Some(Self::X8),
+            _ => None,
+        }
+    }
+}
+
+/// A vector memory operand, addressing one element at `[base + index[i]*scale + displacement]` for each element `i` of the vector register
+/// `index`, as the gather instructions do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VsibMem<Index> {
+    /// The base register.
+    pub base: Reg64,
+
+    /// The vector register holding the indices, an [`Xmm`](crate::register::Xmm) or a [`Ymm`](crate::register::Ymm).
+    pub index: Index,
+
+    /// The scale of the indices.
+    pub scale: Scale,
+
+    /// The displacement from the base register.
+    pub displacement: i32,
+}
+
+impl<Index> VsibMem<Index> {
+    /// Creates a vector memory operand addressing `base + index*scale + displacement`.
+    #[inline(always)]
+    pub fn new(base: Reg64, index: Index, scale: Scale, displacement: i32) -> Self {
+        Self { base, index, scale, displacement }
+    }
+}
@@ -0,0 +1,363 @@
+//! Operating modes, generating 32-bit protected mode code or 16-bit real mode code, such as the stages of a bootloader, with the same stream as
+//! 64-bit code.
+//!
+//! A stream created by [`new_with_mode`](x86_64InstructionStream::new_with_mode) with [`Mode::Protected`] emits code for 32-bit protected
+//! mode, where the default operand and address sizes are 32 bits:
+//!
+//! - the 32-bit and 16-bit emitters encode the same in both modes, the `0x66` prefix selecting the 16-bit operand size;
+//! - the memory operands address through the lower 32 bits of their base register, `[rdi + 8]` meaning `[edi + 8]`;
+//! - the instructions requiring a REX prefix, such as the 64-bit operations and the `r8` to `r15` and `spl` to `dil` registers, panic once
+//!   emitted, and so do the `rip`-relative operands, which would address absolutely;
+//! - the emitters whose operand size defaults to 64 bits in long mode, such as [`x86_64InstructionStream::push_reg64`], panic, in favor of
+//!   their 32-bit counterparts, such as [`x86_64InstructionStream::push_reg32`];
+//! - [`x86_64InstructionStream::inc_reg32`] and [`x86_64InstructionStream::dec_reg32`] pick the 1-byte `40+r` and `48+r` forms, which are REX
+//!   prefixes in long mode.
+//!
+//! With [`Mode::Real`], the stream emits code for 16-bit real mode, where the BIOS runs a boot sector, see
+//! [`Product::to_boot_sector`](asmkit_core::Product::to_boot_sector).  The default operand and address sizes are 16 bits, and the `0x66` and
+//! `0x67` prefixes select the 32-bit ones.  On top of the rules of protected mode:
+//!
+//! - the 16-bit emitters, such as [`x86_64InstructionStream::mov_reg16_imm16`] and [`x86_64InstructionStream::mov_sreg_reg16`], leave out the
+//!   `0x66` prefix;
+//! - the 32-bit emitters keep their encoding, which takes 16-bit operands unless prefixed by [`x86_64InstructionStream::o32`]:
+//!   `xor_reg32_reg32(Eax, Eax)` is `xor ax, ax`;
+//! - the memory operands address through 32-bit registers, and panic unless prefixed by [`x86_64InstructionStream::a32`], while the string
+//!   instructions, such as [`x86_64InstructionStream::lodsb`], address through `si` and `di`;
+//! - the near branches to labels take 16-bit displacements, and the far branches to a `ptr16:16`, see the [`far`](crate::far) module, are
+//!   available.
+//!
+//! # Example
+//! The same function in long and protected mode:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{features::CpuFeatures, mode::Mode, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Long, CpuFeatures::ALL);
+//! stream.push_reg64(Reg64::Rbp);
+//! stream.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+//! stream.inc_reg32(Reg32::Eax);
+//! stream.dec_reg32(Reg32::Ecx);
+//! stream.pop_reg64(Reg64::Rbp);
+//! stream.call_reg64(Reg64::Rax);
+//! stream.ret_near();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x55,             // push rbp
+//!     0x48, 0x89, 0xe5, // mov rbp, rsp
+//!     0xff, 0xc0,       // inc eax
+//!     0xff, 0xc9,       // dec ecx
+//!     0x5d,             // pop rbp
+//!     0xff, 0xd0,       // call rax
+//!     0xc3,             // ret
+//! ]);
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Protected, CpuFeatures::ALL);
+//! stream.push_reg32(Reg32::Ebp);
+//! stream.mov_reg32_reg32(Reg32::Ebp, Reg32::Esp);
+//! stream.inc_reg32(Reg32::Eax);
+//! stream.dec_reg32(Reg32::Ecx);
+//! stream.pop_reg32(Reg32::Ebp);
+//! stream.call_reg32(Reg32::Eax);
+//! stream.ret_near();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x55,       // push ebp
+//!     0x89, 0xe5, // mov ebp, esp
+//!     0x40,       // inc eax
+//!     0x49,       // dec ecx
+//!     0x5d,       // pop ebp
+//!     0xff, 0xd0, // call eax
+//!     0xc3,       // ret
+//! ]);
+//! ```
+//!
+//! A boot sector printing a message through the BIOS and halting, matching NASM byte for byte:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{
+//!     features::CpuFeatures,
+//!     mode::Mode,
+//!     register::{Reg16, Reg32, Reg8, SegmentReg},
+//!     stream::{Condition, x86_64InstructionStream},
+//! };
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+//! let (print, hang, message) = (stream.create_label(), stream.create_label(), stream.create_label());
+//!
+//! stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+//! stream.mov_sreg_reg16(SegmentReg::Ds, Reg16::Ax);
+//! stream.load_label_address16(Reg16::Si, message);
+//! stream.mov_reg8_imm8(Reg8::Ah, 0x0e);
+//! stream.cld();
+//! stream.attach_label(print);
+//! stream.lodsb();
+//! stream.test_reg8_reg8(Reg8::Al, Reg8::Al);
+//! stream.jcc_short_label(Condition::E, hang);
+//! stream.int_imm8(0x10);
+//! stream.jmp_short_label(print);
+//! stream.attach_label(hang);
+//! stream.cli();
+//! stream.hlt();
+//! stream.jmp_short_label(hang);
+//! stream.attach_label(message);
+//! stream.write_bytes(b"Hello\0");
+//!
+//! // bits 16
+//! // org 0x7c00
+//! //         xor ax, ax
+//! //         mov ds, ax
+//! //         mov si, message
+//! //         mov ah, 0x0e
+//! //         cld
+//! // print:  lodsb
+//! //         test al, al
+//! //         jz hang
+//! //         int 0x10
+//! //         jmp print
+//! // hang:   cli
+//! //         hlt
+//! //         jmp hang
+//! // message: db "Hello", 0
+//! // times 510 - ($ - $$) db 0
+//! // dw 0xaa55
+//! let sector = stream.finish().to_boot_sector().unwrap();
+//! assert_eq!(sector[..30], [
+//!     0x31, 0xc0,       // xor ax, ax
+//!     0x8e, 0xd8,       // mov ds, ax
+//!     0xbe, 0x17, 0x7c, // mov si, message
+//!     0xb4, 0x0e,       // mov ah, 0x0e
+//!     0xfc,             // cld
+//!     0xac,             // print: lodsb
+//!     0x84, 0xc0,       // test al, al
+//!     0x74, 0x04,       // jz hang
+//!     0xcd, 0x10,       // int 0x10
+//!     0xeb, 0xf7,       // jmp print
+//!     0xfa,             // hang: cli
+//!     0xf4,             // hlt
+//!     0xeb, 0xfc,       // jmp hang
+//!     b'H', b'e', b'l', b'l', b'o', 0x00,
+//!     0x00,
+//! ]);
+//! assert!(sector[30..510].iter().all(|&byte| byte == 0));
+//! assert_eq!(sector[510..], [0x55, 0xaa]);
+//! ```
+//!
+//! The instructions requiring a REX prefix are rejected in protected mode:
+//! ```should_panic
+//! use asmkit_x86_64::{features::CpuFeatures, mode::Mode, register::Reg32, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Protected, CpuFeatures::ALL);
+//! stream.mov_reg32_reg32(Reg32::Eax, Reg32::R8d);
+//! ```
+//!
+//! The memory operands require `a32` in real mode:
+//! ```should_panic
+//! use asmkit_x86_64::{features::CpuFeatures, memory::Mem, mode::Mode, register::{Reg16, Reg64}, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+//! stream.mov_reg16_mem16(Reg16::Ax, Mem::new(Reg64::Rbx, 0));
+//! ```
+
+use std::fmt;
+
+use asmkit_core::{fixed::CodeStorage, entity::LabelRef, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    register::{GpReg, Reg16, Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, FixupKind, REX, REX_B},
+};
+
+/// The legacy prefixes, which may precede the REX prefix of an instruction.
+const LEGACY_PREFIXES: [u8; 11] = [0x66, 0x67, 0xf0, 0xf2, 0xf3, 0x26, 0x2e, 0x36, 0x3e, 0x64, 0x65];
+
+/// The operating mode a stream generates code for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// 64-bit long mode.
+    #[default]
+    Long,
+
+    /// 32-bit protected mode.
+    Protected,
+
+    /// 16-bit real mode.
+    Real,
+}
+
+impl Mode {
+    /// Every mode.
+    pub const ALL: [Self; 3] = [Self::Long, Self::Protected, Self::Real];
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Long => "long mode",
+            Self::Protected => "protected mode",
+            Self::Real => "real mode",
+        })
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Checks that the stream generates code for long mode, for the emitters whose operand size defaults to 64 bits.
+    ///
+    /// # Panics
+    /// Panics in protected and real mode.
+    pub(crate) fn require_long_mode(&self, mnemonic: &str) {
+        assert!(self.mode() == Mode::Long, "`{}` with a 64-bit operand is unavailable in {}", mnemonic, self.mode());
+    }
+
+    /// Checks that the instruction `bytes`, emitted at `start` in protected or real mode, has no REX prefix.
+    ///
+    /// # Panics
+    /// Panics if the instruction has a REX prefix.
+    pub(crate) fn check_legacy_encoding(&self, start: usize, bytes: &[u8]) {
+        let prefixes = bytes.iter().take_while(|byte| LEGACY_PREFIXES.contains(byte)).count();
+
+        // `40+r` and `48+r` on their own are the short forms of `inc` and `dec`.
+        if bytes.len() > prefixes + 1 && (0x40..=0x4f).contains(&bytes[prefixes]) {
+            panic!("the instruction at {:#x} requires a REX prefix, unavailable in {}", start, self.mode());
+        }
+    }
+
+    /// Checks that a `rip`-relative field isn't written in protected or real mode, where the `[rip + disp32]` ModRM encoding addresses absolutely.
+    ///
+    /// # Panics
+    /// Panics outside long mode if the last byte written is a ModRM byte selecting `[rip + disp32]`.
+    pub(crate) fn check_legacy_rip_relative(&self) {
+        if self.mode() != Mode::Long && self.code().last().is_some_and(|modrm| modrm & 0b11_000_111 == 0b101) {
+            panic!("`rip`-relative addressing is unavailable in {}", self.mode());
+        }
+    }
+
+    /// The operand size prefix selecting 16-bit operands, or `None` in real mode where they are the default.
+    pub(crate) fn word_operand_prefix(&self) -> Option<u8> {
+        (self.mode() != Mode::Real).then_some(0x66)
+    }
+
+    /// Writes the operand size prefix selecting 16-bit operands, unless in real mode.
+    pub(crate) fn write_word_operand_prefix(&mut self) {
+        if let Some(prefix) = self.word_operand_prefix() {
+            self.write_byte(prefix);
+        }
+    }
+
+    /// Writes the operand size prefix selecting 32-bit operands, in real mode only.
+    pub(crate) fn write_dword_operand_prefix(&mut self) {
+        if self.mode() == Mode::Real {
+            self.write_byte(0x66);
+        }
+    }
+
+    /// The fixup of the displacement of a near branch to a label, 16 bits wide in real mode.
+    pub(crate) fn near_branch_fixup(&self) -> FixupKind {
+        match self.mode() {
+            Mode::Real => FixupKind::Rel16,
+            _ => FixupKind::Rel32,
+        }
+    }
+
+    /// Push *r32*.
+    ///
+    /// # Panics
+    /// Panics outside protected mode, where `push` takes 64-bit or 16-bit operands, or if `reg32` requires a REX prefix.
+    pub fn push_reg32(&mut self, reg32: Reg32) {
+        self.require_protected_mode("push", reg32);
+
+        let instruction = self.begin_instruction();
+        self.write_byte(0x50 + reg32.offset());
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(reg32));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Pop top of stack into *r32*.
+    ///
+    /// # Panics
+    /// Panics outside protected mode, where `pop` takes 64-bit or 16-bit operands, or if `reg32` requires a REX prefix.
+    pub fn pop_reg32(&mut self, reg32: Reg32) {
+        self.require_protected_mode("pop", reg32);
+
+        let instruction = self.begin_instruction();
+        self.write_byte(0x58 + reg32.offset());
+        self.record_registers(RegSet::of(Reg64::Rsp).with(reg32), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Pop);
+    }
+
+    /// Call near, absolute indirect, to the address in *r32*.
+    ///
+    /// # Panics
+    /// Panics outside protected mode, where `call` takes 64-bit or 16-bit operands, or if `reg32` requires a REX prefix.
+    pub fn call_reg32(&mut self, reg32: Reg32) {
+        self.require_protected_mode("call", reg32);
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xff, (0b11 << 6) | (2 << 3) | reg32.offset()]);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(reg32));
+        self.end_instruction(instruction, InstructionFamily::Call);
+    }
+
+    /// Jump near, absolute indirect, to the address in *r32*.
+    ///
+    /// # Panics
+    /// Panics outside protected mode, where `jmp` takes 64-bit or 16-bit operands, or if `reg32` requires a REX prefix.
+    pub fn jmp_reg32(&mut self, reg32: Reg32) {
+        self.require_protected_mode("jmp", reg32);
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xff, (0b11 << 6) | (4 << 3) | reg32.offset()]);
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg32));
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Increment *r32* by 1, with the 1-byte `40+r` form in protected and real mode.
+    pub fn inc_reg32(&mut self, reg32: Reg32) {
+        self.write_inc_dec(0, reg32);
+    }
+
+    /// Decrement *r32* by 1, with the 1-byte `48+r` form in protected and real mode.
+    pub fn dec_reg32(&mut self, reg32: Reg32) {
+        self.write_inc_dec(1, reg32);
+    }
+
+    /// Writes `inc` (`digit` 0) or `dec` (`digit` 1) of *r32*.
+    fn write_inc_dec(&mut self, digit: u8, reg32: Reg32) {
+        let instruction = self.begin_instruction();
+
+        match self.mode() {
+            Mode::Protected | Mode::Real if !reg32.is_extension() => self.write_byte(0x40 + (digit << 3) + reg32.offset()),
+            _ => self.write_reg_reg(false, 0xff, (reg32.offset(), reg32.is_extension()), (digit, false)),
+        }
+
+        self.record_registers(RegSet::of(reg32), RegSet::of(reg32));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Checks that the stream generates code for protected mode, for the emitters of 32-bit operands which default to 64 bits in long mode and to
+    /// 16 bits in real mode, and that *r32* can be encoded there.
+    ///
+    /// # Panics
+    /// Panics outside protected mode, or if `reg32` requires a REX prefix.
+    fn require_protected_mode(&self, mnemonic: &str, reg32: Reg32) {
+        assert!(self.mode() == Mode::Protected, "`{}` with a 32-bit operand is unavailable in {}", mnemonic, self.mode());
+        assert!(!reg32.is_extension(), "`{}` requires a REX prefix, unavailable in protected mode", reg32.name());
+    }
+
+    /// Loads the 16-bit address of `label` into *r16*, `mov dest, label`, left to a [`RelocationKind::Abs16`](asmkit_core::reloc::RelocationKind::Abs16)
+    /// relocation against the load address.
+    pub fn load_label_address16(&mut self, dest: Reg16, label: LabelRef) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B);
+        }
+
+        self.write_byte(0xb8 + dest.offset());
+        self.write_label(label, FixupKind::Abs16);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+}
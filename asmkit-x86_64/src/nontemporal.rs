@@ -0,0 +1,163 @@
+//! Non-temporal moves, which bypass the caches for data that won't be accessed again soon, such as the destination of a large copy.
+//!
+//! Non-temporal stores are weakly ordered, even on x86-64: they may become visible to other processors after later stores.  A sequence of them must
+//! be followed by an [`x86_64InstructionStream::sfence`] before the data is published, such as by storing a flag or releasing a lock.
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    features::CpuFeatures,
+    memory::Mem,
+    register::{Reg32, Reg64, Xmm},
+    stats::InstructionFamily,
+    stream::x86_64InstructionStream,
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Move *r32* to *m32* with a non-temporal hint.  Must be followed by an `sfence` before the data is published.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movnti_mem32_reg32(Mem::new(Reg64::R12, 0), Reg32::Eax);
+    /// stream.movnti_mem64_reg64(Mem::new(Reg64::R12, 8), Reg64::R9);
+    /// stream.sfence();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x41, 0x0f, 0xc3, 0x04, 0x24,       // movnti dword ptr [r12], eax
+    ///     0x4d, 0x0f, 0xc3, 0x4c, 0x24, 0x08, // movnti qword ptr [r12 + 8], r9
+    ///     0x0f, 0xae, 0xf8,                   // sfence
+    /// ]);
+    /// ```
+    pub fn movnti_mem32_reg32(&mut self, dest: Mem, src: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0x0f, 0xc3], (src.offset(), src.is_extension()), dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r64* to *m64* with a non-temporal hint.  Must be followed by an `sfence` before the data is published.
+    pub fn movnti_mem64_reg64(&mut self, dest: Mem, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(true, &[0x0f, 0xc3], (src.offset(), src.is_extension()), dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Writes a non-temporal move between *m128* and *xmm*, with the optional mandatory `prefix`.
+    fn write_nontemporal_xmm_mem(&mut self, prefix: Option<u8>, opcode: &[u8], xmm: Xmm, mem: Mem) {
+        let instruction = self.begin_instruction();
+
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        self.write_rex_modrm_mem(false, opcode, (xmm.offset(), xmm.is_extension()), mem);
+
+        // `movntdqa` loads *xmm*, the others store it.
+        match opcode {
+            [0x0f, 0x38, 0x2a] => self.record_registers(RegSet::of(xmm), RegSet::from(mem)),
+            _ => self.record_registers(RegSet::EMPTY, RegSet::from(mem).with(xmm)),
+        }
+
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the packed integers of *xmm* to *m128* with a non-temporal hint.  *m128* must be 16-byte aligned, and the store followed by an `sfence`
+    /// before the data is published.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movntdq_mem_xmm(Mem::new(Reg64::R12, 0), Xmm::Xmm1);
+    /// stream.movntps_mem_xmm(Mem::new(Reg64::R12, 16), Xmm::Xmm9);
+    /// stream.movntpd_mem_xmm(Mem::new(Reg64::Rdi, 0), Xmm::Xmm2);
+    /// stream.sfence();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x41, 0x0f, 0xe7, 0x0c, 0x24, // movntdq xmmword ptr [r12], xmm1
+    ///     0x45, 0x0f, 0x2b, 0x4c, 0x24, 0x10, // movntps xmmword ptr [r12 + 16], xmm9
+    ///     0x66, 0x0f, 0x2b, 0x17,             // movntpd xmmword ptr [rdi], xmm2
+    ///     0x0f, 0xae, 0xf8,                   // sfence
+    /// ]);
+    /// ```
+    pub fn movntdq_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movntdq", dest, 16);
+        self.write_nontemporal_xmm_mem(Some(0x66), &[0x0f, 0xe7], src, dest);
+    }
+
+    /// Move the packed single precision floats of *xmm* to *m128* with a non-temporal hint.  *m128* must be 16-byte aligned, and the store followed
+    /// by an `sfence` before the data is published.
+    pub fn movntps_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movntps", dest, 16);
+        self.write_nontemporal_xmm_mem(None, &[0x0f, 0x2b], src, dest);
+    }
+
+    /// Move the packed double precision floats of *xmm* to *m128* with a non-temporal hint.  *m128* must be 16-byte aligned, and the store followed
+    /// by an `sfence` before the data is published.
+    pub fn movntpd_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movntpd", dest, 16);
+        self.write_nontemporal_xmm_mem(Some(0x66), &[0x0f, 0x2b], src, dest);
+    }
+
+    /// Move *m128* to *xmm* with a non-temporal hint, streaming from write-combining memory.  *m128* must be 16-byte aligned.  Loads from
+    /// write-combining memory are weakly ordered, and must be preceded by an `mfence` to observe earlier stores.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movntdqa_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::R12, 0));
+    /// stream.movntdqa_xmm_mem(Xmm::Xmm10, Mem::new(Reg64::Rax, 0x100));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x41, 0x0f, 0x38, 0x2a, 0x0c, 0x24,                   // movntdqa xmm1, xmmword ptr [r12]
+    ///     0x66, 0x44, 0x0f, 0x38, 0x2a, 0x90, 0x00, 0x01, 0x00, 0x00, // movntdqa xmm10, xmmword ptr [rax + 256]
+    /// ]);
+    /// ```
+    pub fn movntdqa_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "movntdqa");
+        self.check_alignment("movntdqa", src, 16);
+        self.write_nontemporal_xmm_mem(Some(0x66), &[0x0f, 0x38, 0x2a], dest, src);
+    }
+
+    /// Move the bytes of *xmm1* whose mask sign bit in *xmm2* is set to `[rdi]`, with a non-temporal hint.  Must be followed by an `sfence` before the
+    /// data is published.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.maskmovdqu_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.maskmovdqu_xmm_xmm(Xmm::Xmm9, Xmm::Xmm10);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0xf7, 0xca,       // maskmovdqu xmm1, xmm2
+    ///     0x66, 0x45, 0x0f, 0xf7, 0xca, // maskmovdqu xmm9, xmm10
+    /// ]);
+    /// ```
+    pub fn maskmovdqu_xmm_xmm(&mut self, src: Xmm, mask: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((src.offset(), src.is_extension()), (mask.offset(), mask.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, &[0xf7], reg, rm, None);
+
+        // the destination is implicitly addressed by `rdi`.
+        self.record_registers(RegSet::EMPTY, RegSet::of(src).with(mask).with(Reg64::Rdi));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+}
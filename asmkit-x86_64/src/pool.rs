@@ -0,0 +1,290 @@
+//! The constant pool, holding the data loaded by the code, such as vector constants, shuffle masks and splatted scalars.
+//!
+//! Constants are laid out after the code when the instruction stream is finished, each aligned to its natural size within the product, and are
+//! addressed by a label, usually through a `rip`-relative load.  The alignment is relative to the start of the product, which must be loaded at an
+//! address aligned to the largest constant, 32 bytes at most for the vector constants.
+//!
+//! Constants with the same bytes are only laid out once, at the strictest of their alignments.
+
+use std::collections::HashMap;
+
+use asmkit_core::{fixed::CodeStorage, entity::LabelRef, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    features::CpuFeatures,
+    register::{Xmm, Ymm},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, FixupKind, REX, REX_R},
+};
+
+/// A constant of the pool.
+#[derive(Clone)]
+struct Constant {
+    label: LabelRef,
+    bytes: Vec<u8>,
+    align: usize,
+}
+
+/// The constants of an instruction stream, laid out when it is finished.
+#[derive(Clone, Default)]
+pub(crate) struct ConstantPool {
+    constants: Vec<Constant>,
+
+    /// The index of the constant holding some bytes.
+    by_content: HashMap<Vec<u8>, usize>,
+}
+
+impl ConstantPool {
+    /// Returns true if `label` is the label of a constant.
+    pub(crate) fn contains(&self, label: LabelRef) -> bool {
+        self.constants.iter().any(|constant| constant.label == label)
+    }
+
+    /// The alignment of the constant at `label`, if it is the label of a constant.
+    pub(crate) fn align(&self, label: LabelRef) -> Option<usize> {
+        self.constants.iter().find(|constant| constant.label == label).map(|constant| constant.align)
+    }
+
+    /// Returns true if the pool holds no constants.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.constants.is_empty()
+    }
+
+    /// An upper bound of the size of the laid out pool, including the padding aligning the constants.
+    pub(crate) fn size_bound(&self) -> usize {
+        self.constants.iter().map(|constant| constant.bytes.len() + constant.align - 1).sum()
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Adds `bytes` to the constant pool, aligned to `align` bytes, returning the label of the constant.  A constant with the same bytes is reused,
+    /// and aligned to the strictest of both alignments.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Ymm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.add_const(&[1, 2, 3, 4], 4);
+    /// let mask = stream.add_const_ymm([0xff; 32]);
+    /// stream.vmovdqa_ymm_label(Ymm::Ymm1, mask);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// let disp = i32::from_le_bytes(product.bytes()[4..8].try_into().unwrap());
+    /// let offset = (8 + disp) as usize;
+    ///
+    /// // the 32-byte constant is 32-aligned, though added after a 4-byte one.
+    /// assert_eq!(offset % 32, 0);
+    /// assert_eq!(product.bytes()[offset..offset + 32], [0xff; 32]);
+    /// assert_eq!(product.bytes()[offset + 32..], [1, 2, 3, 4]);
+    /// ```
+    pub fn add_const(&mut self, bytes: &[u8], align: usize) -> LabelRef {
+        assert!(align.is_power_of_two(), "constant alignment must be a power of two");
+
+        if let Some(&index) = self.constants().by_content.get(bytes) {
+            let constant = &mut self.constants().constants[index];
+            constant.align = constant.align.max(align);
+            return constant.label;
+        }
+
+        let label = self.create_label();
+        let pool = self.constants();
+        pool.by_content.insert(bytes.to_vec(), pool.constants.len());
+        pool.constants.push(Constant { label, bytes: bytes.to_vec(), align });
+        label
+    }
+
+    /// Adds a 16-byte constant to the pool, aligned to 16 bytes, see [`x86_64InstructionStream::add_const`].
+    pub fn add_const_xmm(&mut self, bytes: [u8; 16]) -> LabelRef {
+        self.add_const(&bytes, 16)
+    }
+
+    /// Adds a 32-byte constant to the pool, aligned to 32 bytes, see [`x86_64InstructionStream::add_const`].
+    pub fn add_const_ymm(&mut self, bytes: [u8; 32]) -> LabelRef {
+        self.add_const(&bytes, 32)
+    }
+
+    /// Adds `lanes` copies of `element` to the pool, aligned to their total size.
+    ///
+    /// # Panics
+    /// Panics if `lanes` is not a power of two, or if the constant is larger than 32 bytes.
+    fn add_const_splat(&mut self, element: &[u8], lanes: usize) -> LabelRef {
+        assert!(lanes.is_power_of_two(), "the lane count of a splatted constant must be a power of two");
+
+        let size = element.len() * lanes;
+        assert!(size <= 32, "splatted constant of {} bytes is larger than a ymm register", size);
+
+        self.add_const(&element.repeat(lanes), size)
+    }
+
+    /// Adds `lanes` copies of the single precision `value` to the pool, aligned to their total size.
+    ///
+    /// # Panics
+    /// Panics if `lanes` is not a power of two, or if the constant is larger than 32 bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let ones = stream.add_const_splat_f32(1.0, 4);
+    ///
+    /// // the same bytes, deduplicated.
+    /// assert_eq!(stream.add_const_splat_u32(0x3f80_0000, 4), ones);
+    /// assert_eq!(stream.add_const_xmm([0x00, 0x00, 0x80, 0x3f].repeat(4).try_into().unwrap()), ones);
+    /// assert_ne!(stream.add_const_splat_f32(1.0, 8), ones);
+    ///
+    /// stream.movaps_xmm_label(Xmm::Xmm9, ones);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes()[..9], [
+    ///     0x44, 0x0f, 0x28, 0x0d, 0x38, 0x00, 0x00, 0x00, // movaps xmm9, xmmword ptr [rip + ones]
+    ///     0xc3,                                           // ret
+    /// ]);
+    ///
+    /// // the 8-lane splat is laid out first, at 32, and `ones` follows it.
+    /// assert_eq!(product.bytes()[32..64], [0x00, 0x00, 0x80, 0x3f].repeat(8));
+    /// assert_eq!(product.bytes()[8 + 0x38..], [0x00, 0x00, 0x80, 0x3f].repeat(4));
+    /// ```
+    pub fn add_const_splat_f32(&mut self, value: f32, lanes: usize) -> LabelRef {
+        self.add_const_splat(&value.to_le_bytes(), lanes)
+    }
+
+    /// Adds `lanes` copies of the double precision `value` to the pool, aligned to their total size.
+    ///
+    /// # Panics
+    /// Panics if `lanes` is not a power of two, or if the constant is larger than 32 bytes.
+    pub fn add_const_splat_f64(&mut self, value: f64, lanes: usize) -> LabelRef {
+        self.add_const_splat(&value.to_le_bytes(), lanes)
+    }
+
+    /// Adds `lanes` copies of the 32-bit `value` to the pool, aligned to their total size.
+    ///
+    /// # Panics
+    /// Panics if `lanes` is not a power of two, or if the constant is larger than 32 bytes.
+    pub fn add_const_splat_u32(&mut self, value: u32, lanes: usize) -> LabelRef {
+        self.add_const_splat(&value.to_le_bytes(), lanes)
+    }
+
+    /// Adds `lanes` copies of the 64-bit `value` to the pool, aligned to their total size.
+    ///
+    /// # Panics
+    /// Panics if `lanes` is not a power of two, or if the constant is larger than 32 bytes.
+    pub fn add_const_splat_u64(&mut self, value: u64, lanes: usize) -> LabelRef {
+        self.add_const_splat(&value.to_le_bytes(), lanes)
+    }
+
+    /// Lays out the constant pool after the code, from the most aligned constant to the least, so the padding between them is minimal.
+    pub(crate) fn lay_out_constants(&mut self) {
+        let mut constants = std::mem::take(&mut self.constants().constants);
+        constants.sort_by_key(|constant| std::cmp::Reverse(constant.align));
+
+        for constant in constants {
+            self.attach_label_aligned(constant.label, constant.align);
+            self.write_bytes(&constant.bytes);
+        }
+    }
+
+    /// Writes a load of *xmm* from the constant at `label`, with the optional mandatory `prefix`.
+    fn write_xmm_label(&mut self, prefix: Option<u8>, opcode: u8, dest: Xmm, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        if dest.is_extension() {
+            self.write_byte(REX | REX_R);
+        }
+
+        self.write_bytes(&[0x0f, opcode]);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Writes a VEX.256 load of *ymm* from the constant at `label`, with the implied prefix `pp`.
+    fn write_ymm_label(&mut self, pp: u8, opcode: u8, dest: Ymm, label: LabelRef) {
+        let instruction = self.begin_instruction();
+        self.write_vex((dest.is_extension(), false, false), 0b00001, false, 0, true, pp);
+        self.write_byte(opcode);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Move the packed single precision values of the 16-byte aligned constant at `label` to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and the constant is less than 16-byte aligned, see
+    /// [`x86_64InstructionStream::enable_alignment_checks`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// let constant = stream.add_const(&[0; 16], 4);
+    /// stream.movaps_xmm_label(Xmm::Xmm0, constant);
+    /// ```
+    pub fn movaps_xmm_label(&mut self, dest: Xmm, label: LabelRef) {
+        self.check_label_alignment("movaps", label, 16);
+        self.write_xmm_label(None, 0x28, dest, label);
+    }
+
+    /// Move the packed integers of the 16-byte aligned constant at `label` to *xmm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Xmm, Ymm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let shuffle = stream.add_const_xmm([3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8, 15, 14, 13, 12]);
+    /// let ones = stream.add_const_splat_u64(1, 4);
+    /// stream.movdqa_xmm_label(Xmm::Xmm1, shuffle);
+    /// stream.vmovaps_ymm_label(Ymm::Ymm9, ones);
+    /// stream.vmovdqa_ymm_label(Ymm::Ymm1, ones);
+    ///
+    /// assert_eq!(stream.finish().bytes()[..24], [
+    ///     0x66, 0x0f, 0x6f, 0x0d, 0x38, 0x00, 0x00, 0x00, // movdqa xmm1, xmmword ptr [rip + shuffle]
+    ///     0xc5, 0x7c, 0x28, 0x0d, 0x10, 0x00, 0x00, 0x00, // vmovaps ymm9, ymmword ptr [rip + ones]
+    ///     0xc5, 0xfd, 0x6f, 0x0d, 0x08, 0x00, 0x00, 0x00, // vmovdqa ymm1, ymmword ptr [rip + ones]
+    /// ]);
+    /// ```
+    pub fn movdqa_xmm_label(&mut self, dest: Xmm, label: LabelRef) {
+        self.check_label_alignment("movdqa", label, 16);
+        self.write_xmm_label(Some(0x66), 0x6f, dest, label);
+    }
+
+    /// Move the packed single precision values of the 32-byte aligned constant at `label` to *ymm*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmovaps_ymm_label(&mut self, dest: Ymm, label: LabelRef) {
+        self.require_feature(CpuFeatures::AVX, "vmovaps");
+        self.check_label_alignment("vmovaps", label, 32);
+        self.write_ymm_label(0b00, 0x28, dest, label);
+    }
+
+    /// Move the packed integers of the 32-byte aligned constant at `label` to *ymm*.
+    ///
+    /// # Panics
+    /// Panics if AVX isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn vmovdqa_ymm_label(&mut self, dest: Ymm, label: LabelRef) {
+        self.require_feature(CpuFeatures::AVX, "vmovdqa");
+        self.check_label_alignment("vmovdqa", label, 32);
+        self.write_ymm_label(0b01, 0x6f, dest, label);
+    }
+}
@@ -0,0 +1,154 @@
+//! Atomic regions, keeping a sequence of instructions exactly as emitted, such as the initial-exec sequence the linker rewrites as a whole or a
+//! carry chain.
+//!
+//! The instructions between [`x86_64InstructionStream::begin_atomic_region`] and [`x86_64InstructionStream::end_atomic_region`] are barriers
+//! to the passes over the code:
+//! - the peephole patterns are off within a region, and a `push` before a region is never folded with a `pop` within or after it, see
+//!   [`x86_64InstructionStream::enable_peephole`].
+//! - the alignment padding requested within a region, by [`x86_64InstructionStream::attach_label_aligned`] or a patch point, is inserted
+//!   before the region rather than inside it, moving the code of the region along.
+//!
+//! Regions nest: the padding goes before the outermost region.  The regions are recorded in the listing while
+//! [`x86_64InstructionStream::enable_listing`] is set, read back by [`x86_64InstructionStream::atomic_regions`].
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.enable_peephole(true);
+//! stream.enable_listing(true);
+//! let top = stream.create_label();
+//!
+//! stream.push_reg64(Reg64::Rbx);
+//! stream.begin_atomic_region();
+//! stream.pop_reg64(Reg64::Rbx);               // kept, the `push` is before the region
+//! stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rax);
+//! stream.attach_label_aligned(top, 8);        // padded before the region
+//! stream.add_reg64_imm32(Reg64::Rax, 1);
+//! stream.end_atomic_region();
+//! stream.jmp_label(top);
+//!
+//! assert_eq!(stream.label_offset(top), Some(8));
+//! assert_eq!(stream.atomic_regions().collect::<Vec<_>>(), [4..12]);
+//! assert_eq!(stream.instructions().map(|instruction| instruction.offset).collect::<Vec<_>>(), [0, 4, 5, 8, 12]);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x53,                         // push rbx
+//!     0x0f, 0x1f, 0x00,             // nop dword ptr [rax]
+//!     0x5b,                         // pop rbx
+//!     0x48, 0x89, 0xc0,             // mov rax, rax
+//!     0x48, 0x83, 0xc0, 0x01,       // top: add rax, 1
+//!     0xe9, 0xf7, 0xff, 0xff, 0xff, // jmp top
+//! ]);
+//! ```
+
+use std::ops::Range;
+
+use asmkit_core::{fixed::CodeStorage, entity::LabelRef};
+
+use crate::stream::x86_64InstructionStream;
+
+/// The atomic regions being written.
+#[derive(Debug, Default)]
+pub(crate) struct AtomicRegions {
+    /// The offsets the regions start at, outermost first.
+    pub(crate) starts: Vec<usize>,
+
+    /// The labels attached within the outermost region, moved along with its code by the padding inserted before it.
+    pub(crate) labels: Vec<LabelRef>,
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Starts an atomic region, within the region being written if any, see the [module](self) documentation.
+    ///
+    /// # Example
+    /// The padding before a region moves its relocations and labels along, and the jumps emitted before it still reach them:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::{Fill, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_peephole(true);
+    /// stream.set_align_fill(Fill::Int3);
+    /// let tp = stream.create_symbol("tp");
+    /// let body = stream.create_label();
+    ///
+    /// stream.jmp_label(body);
+    /// stream.begin_atomic_region();
+    /// stream.intrinsic_load_tls_ie(Reg64::Rax, tp);
+    /// stream.attach_label_aligned(body, 32);
+    /// stream.add_reg64_imm32(Reg64::Rax, 0); // kept within the region
+    /// stream.end_atomic_region();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.relocations()[0].offset, 24);
+    /// assert_eq!(product.bytes()[..5], [0xe9, 0x1b, 0x00, 0x00, 0x00]); // jmp body
+    /// assert_eq!(product.bytes()[5..21], [0xcc; 16]);
+    /// assert_eq!(product.bytes()[21..], [
+    ///     0x48, 0x8b, 0x05, 0x00, 0x00, 0x00, 0x00, // mov rax, qword ptr [rip + tp@gottpoff]
+    ///     0x64, 0x48, 0x8b, 0x00,                   // mov rax, qword ptr fs:[rax]
+    ///     0x48, 0x83, 0xc0, 0x00,                   // body: add rax, 0
+    /// ]);
+    /// ```
+    pub fn begin_atomic_region(&mut self) {
+        let start = self.code().len();
+        self.clear_last_instruction();
+        self.regions().starts.push(start);
+    }
+
+    /// Ends the innermost atomic region being written, recording it in the listing if it is enabled.
+    ///
+    /// # Panics
+    /// Panics if no atomic region is being written.
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// x86_64InstructionStream::new().end_atomic_region();
+    /// ```
+    pub fn end_atomic_region(&mut self) {
+        let start = self.regions().starts.pop().expect("no atomic region is being written, see `begin_atomic_region`");
+        if self.regions().starts.is_empty() {
+            self.regions().labels.clear();
+        }
+
+        let end = self.code().len();
+        self.clear_last_instruction();
+        self.record_atomic_region(start..end);
+    }
+
+    /// Returns true if an atomic region is being written.
+    #[inline(always)]
+    pub fn in_atomic_region(&self) -> bool {
+        !self.regions_ref().starts.is_empty()
+    }
+
+    /// The atomic regions ended while [`x86_64InstructionStream::enable_listing`] was set, by offset, the enclosing regions first.  The regions of
+    /// the basic blocks are listed once the blocks are laid out.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.begin_atomic_region();
+    /// stream.add_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+    /// stream.begin_atomic_region();
+    /// stream.adc_reg64_reg64(Reg64::Rdx, Reg64::Rsi);
+    /// stream.end_atomic_region();
+    /// stream.end_atomic_region();
+    ///
+    /// // the fixed sequences of the intrinsics are regions too.
+    /// stream.intrinsic_add_u128(Reg64::Rax, Reg64::Rdx, Reg64::Rdi, Reg64::Rsi);
+    ///
+    /// assert!(!stream.in_atomic_region());
+    /// assert_eq!(stream.atomic_regions().collect::<Vec<_>>(), [0..6, 3..6, 6..12]);
+    /// ```
+    pub fn atomic_regions(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.instruction_listing().regions.iter().cloned()
+    }
+}
@@ -1,5 +1,9 @@
 //! Registers in x86_64 assembly.
 
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{error::AsmError, memory::Mem};
+
 /// An 8-bit register.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Reg8 {
@@ -52,30 +56,70 @@ impl Reg8 {
         }
     }
 
-    /// Returns true if this register is one of the extension registers, such as `r8`
+    /// Returns true if this register is one of the extension registers, such as `r8b`
     /// 
     /// # Example
     /// ```
-    /// use asmkit_x86_64::register::Reg32;
+    /// use asmkit_x86_64::register::Reg8;
     /// 
-    /// let r8d = Reg32::R8d;
-    /// let eax = Reg32::Eax;
+    /// let r8b = Reg8::R8b;
+    /// let al = Reg8::Al;
     /// 
-    /// assert!(r8d.is_extension());
-    /// assert!(!eax.is_extension());
+    /// assert!(r8b.is_extension());
+    /// assert!(!al.is_extension());
     /// ```
     pub fn is_extension(&self) -> bool {
-        match self {
-            Self::R8b | Self::R9b | Self::R10b | Self::R11b | Self::R12b | Self::R13b | Self::R14b | Self::R15b => true,
-            _ => false,
+        matches!(self, Self::R8b | Self::R9b | Self::R10b | Self::R11b | Self::R12b | Self::R13b | Self::R14b | Self::R15b)
+    }
+
+    /// Returns true if this register can only be encoded with a REX prefix: the extension registers, and `spl`, `bpl`, `sil` and `dil`, whose offsets
+    /// mean `ah`, `ch`, `dh` and `bh` without one.
+    pub fn requires_rex(&self) -> bool {
+        self.is_extension() || matches!(self, Self::Spl | Self::Bpl | Self::Sil | Self::Dil)
+    }
+
+    /// Returns true if this register is one of the legacy high byte registers, `ah`, `ch`, `dh` and `bh`, which can't be encoded with a REX prefix.
+    pub fn is_high_byte(&self) -> bool {
+        matches!(self, Self::Ah | Self::Ch | Self::Dh | Self::Bh)
+    }
+
+    /// Checks that this register can be encoded in the same instruction as `other`, which isn't the case of a high byte register along with a register
+    /// requiring a REX prefix.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{error::AsmError, register::Reg8};
+    ///
+    /// assert_eq!(Reg8::Ah.check_encodable_with(Reg8::Bl), Ok(()));
+    /// assert_eq!(Reg8::Sil.check_encodable_with(Reg8::R9b), Ok(()));
+    /// assert_eq!(Reg8::Ah.check_encodable_with(Reg8::R9b), Err(AsmError::UnencodableOperands(Reg8::Ah, "r9b")));
+    /// assert_eq!(Reg8::Dil.check_encodable_with(Reg8::Bh), Err(AsmError::UnencodableOperands(Reg8::Bh, "dil")));
+    /// ```
+    pub fn check_encodable_with(&self, other: Reg8) -> Result<(), AsmError> {
+        match (*self, other) {
+            (high, rex) | (rex, high) if high.is_high_byte() && rex.requires_rex() => Err(AsmError::UnencodableOperands(high, rex.name())),
+            _ => Ok(()),
         }
     }
 
-    /// Returns true if this register is reserved for a specific purpose, such as `spl`.
-    pub fn is_reserved(&self) -> bool {
-        match self {
-            Self::Spl | Self::Bpl | Self::Sil | Self::Dil => true,
-            _ => false,
+    /// Checks that this register can be encoded in the same instruction as the memory operand `mem`, which isn't the case of a high byte register
+    /// along with an extension base or index register, requiring a REX prefix.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{error::AsmError, memory::{Mem, Scale}, register::{Reg64, Reg8}};
+    ///
+    /// assert_eq!(Reg8::Ah.check_encodable_with_mem(Mem::new(Reg64::Rbx, 8)), Ok(()));
+    /// assert_eq!(Reg8::R9b.check_encodable_with_mem(Mem::new(Reg64::R12, 8)), Ok(()));
+    /// assert_eq!(Reg8::Ah.check_encodable_with_mem(Mem::new(Reg64::R12, 8)), Err(AsmError::UnencodableOperands(Reg8::Ah, "r12")));
+    /// assert_eq!(Reg8::Bh.check_encodable_with_mem(Mem::indexed(Reg64::Rax, Reg64::R9, Scale::X2, 0)), Err(AsmError::UnencodableOperands(Reg8::Bh, "r9")));
+    /// ```
+    pub fn check_encodable_with_mem(&self, mem: Mem) -> Result<(), AsmError> {
+        let extension = [Some(mem.base), mem.index.map(|(index, _)| index)].into_iter().flatten().find(|reg| reg.is_extension());
+
+        match extension {
+            Some(reg) if self.is_high_byte() => Err(AsmError::UnencodableOperands(*self, reg.name())),
+            _ => Ok(()),
         }
     }
 }
@@ -137,10 +181,7 @@ impl Reg16 {
     /// assert!(!eax.is_extension());
     /// ```
     pub fn is_extension(&self) -> bool {
-        match self {
-            Self::R8w | Self::R9w | Self::R10w | Self::R11w | Self::R12w | Self::R13w | Self::R14w | Self::R15w => true,
-            _ => false,
-        }
+        matches!(self, Self::R8w | Self::R9w | Self::R10w | Self::R11w | Self::R12w | Self::R13w | Self::R14w | Self::R15w)
     }
 }
 
@@ -201,10 +242,7 @@ impl Reg32 {
     /// assert!(!eax.is_extension());
     /// ```
     pub fn is_extension(&self) -> bool {
-        match self {
-            Self::R8d | Self::R9d | Self::R10d | Self::R11d | Self::R12d | Self::R13d | Self::R14d | Self::R15d => true,
-            _ => false,
-        }
+        matches!(self, Self::R8d | Self::R9d | Self::R10d | Self::R11d | Self::R12d | Self::R13d | Self::R14d | Self::R15d)
     }
 }
 
@@ -265,9 +303,750 @@ impl Reg64 {
     /// assert!(!rax.is_extension());
     /// ```
     pub fn is_extension(&self) -> bool {
+        matches!(self, Self::R8 | Self::R9 | Self::R10 | Self::R11 | Self::R12 | Self::R13 | Self::R14 | Self::R15)
+    }
+}
+
+/// A general purpose register of any width, identified by its number among the 16 general purpose registers: `eax`, `ax` and `al` are the low bits
+/// of `rax`.  The legacy high byte registers `ah`, `ch`, `dh` and `bh` are numbered as the register they are part of.
+///
+/// The conversions between the widths go through the number, so narrowing to 8 bits never picks a high byte register: `rsp`, `rbp`, `rsi` and
+/// `rdi` narrow to `spl`, `bpl`, `sil` and `dil`, which require a REX prefix.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::register::{GpReg, Reg16, Reg32, Reg64, Reg8};
+///
+/// for number in 0..16 {
+///     let reg64 = Reg64::from_number(number);
+///     assert_eq!(reg64.number(), number);
+///
+///     assert_eq!(reg64.low32().widen64(), reg64);
+///     assert_eq!(reg64.low16().widen64(), reg64);
+///     assert_eq!(reg64.low8().widen64(), reg64);
+///     assert_eq!(reg64.low32().low16(), reg64.low16());
+///     assert_eq!(reg64.low32().low8(), reg64.low8());
+///     assert_eq!(reg64.low16().low8(), reg64.low8());
+///     assert_eq!(reg64.low8().widen16(), reg64.low16());
+///     assert_eq!(reg64.low8().widen32(), reg64.low32());
+///     assert_eq!(reg64.low16().widen32(), reg64.low32());
+///
+///     assert!(!reg64.low8().is_high_byte());
+///     assert_eq!(reg64.name().parse(), Ok(reg64));
+///     assert_eq!(reg64.low32().name().parse(), Ok(reg64.low32()));
+///     assert_eq!(reg64.low16().name().parse(), Ok(reg64.low16()));
+///     assert_eq!(reg64.low8().name().parse(), Ok(reg64.low8()));
+///     assert_eq!(Reg8::from_number(number).number(), number);
+///     assert_eq!(Reg16::from_number(number).number(), number);
+///     assert_eq!(Reg32::from_number(number).number(), number);
+/// }
+///
+/// assert_eq!(Reg64::Rsp.low8(), Reg8::Spl);
+/// assert_eq!(Reg32::Edi.low8(), Reg8::Dil);
+/// assert_eq!(Reg8::Ah.widen64(), Reg64::Rax);
+/// assert_eq!(Reg8::Bh.widen32(), Reg32::Ebx);
+///
+/// for high in [Reg8::Ah, Reg8::Ch, Reg8::Dh, Reg8::Bh] {
+///     assert_eq!(high.name().parse(), Ok(high));
+///     assert_eq!(high.widen16().low8().name(), high.name().replace('h', "l"));
+/// }
+/// ```
+pub trait GpReg: Copy + fmt::Debug + PartialEq {
+    /// The registers of this width, by number.
+    const REGS: [Self; 16];
+
+    /// The number of this register, from 0 to 15.
+    fn number(&self) -> u8;
+
+    /// The lowercase name of this register, as accepted by its [`FromStr`] implementation.
+    fn name(&self) -> &'static str;
+
+    /// The register of this width numbered `number`.
+    ///
+    /// # Panics
+    /// Panics if `number` isn't below 16.
+    #[inline(always)]
+    fn from_number(number: u8) -> Self {
+        Self::REGS[number as usize]
+    }
+}
+
+impl GpReg for Reg8 {
+    const REGS: [Self; 16] = [
+        Self::Al, Self::Cl, Self::Dl, Self::Bl, Self::Spl, Self::Bpl, Self::Sil, Self::Dil,
+        Self::R8b, Self::R9b, Self::R10b, Self::R11b, Self::R12b, Self::R13b, Self::R14b, Self::R15b,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        match self.is_high_byte() {
+            true => self.offset() - 4,
+            false => self.offset() | ((self.is_extension() as u8) << 3),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = ["al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil", "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+        const HIGH_BYTE_NAMES: [&str; 4] = ["ah", "ch", "dh", "bh"];
+
+        match self.is_high_byte() {
+            true => HIGH_BYTE_NAMES[self.number() as usize],
+            false => NAMES[self.number() as usize],
+        }
+    }
+}
+
+impl GpReg for Reg16 {
+    const REGS: [Self; 16] = [
+        Self::Ax, Self::Cx, Self::Dx, Self::Bx, Self::Sp, Self::Bp, Self::Si, Self::Di,
+        Self::R8w, Self::R9w, Self::R10w, Self::R11w, Self::R12w, Self::R13w, Self::R14w, Self::R15w,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        self.offset() | ((self.is_extension() as u8) << 3)
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w"];
+        NAMES[self.number() as usize]
+    }
+}
+
+impl GpReg for Reg32 {
+    const REGS: [Self; 16] = [
+        Self::Eax, Self::Ecx, Self::Edx, Self::Ebx, Self::Esp, Self::Ebp, Self::Esi, Self::Edi,
+        Self::R8d, Self::R9d, Self::R10d, Self::R11d, Self::R12d, Self::R13d, Self::R14d, Self::R15d,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        self.offset() | ((self.is_extension() as u8) << 3)
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+        NAMES[self.number() as usize]
+    }
+}
+
+impl GpReg for Reg64 {
+    const REGS: [Self; 16] = [
+        Self::Rax, Self::Rcx, Self::Rdx, Self::Rbx, Self::Rsp, Self::Rbp, Self::Rsi, Self::Rdi,
+        Self::R8, Self::R9, Self::R10, Self::R11, Self::R12, Self::R13, Self::R14, Self::R15,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        self.offset() | ((self.is_extension() as u8) << 3)
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+        NAMES[self.number() as usize]
+    }
+}
+
+impl Reg64 {
+    /// The lower 32 bits of this register.
+    pub fn low32(&self) -> Reg32 {
+        Reg32::from_number(self.number())
+    }
+
+    /// The lower 16 bits of this register.
+    pub fn low16(&self) -> Reg16 {
+        Reg16::from_number(self.number())
+    }
+
+    /// The lowest 8 bits of this register, never a high byte register, see [`GpReg`].
+    pub fn low8(&self) -> Reg8 {
+        Reg8::from_number(self.number())
+    }
+}
+
+impl Reg32 {
+    /// The 64-bit register this register is the lower half of.
+    pub fn widen64(&self) -> Reg64 {
+        Reg64::from_number(self.number())
+    }
+
+    /// The lower 16 bits of this register.
+    pub fn low16(&self) -> Reg16 {
+        Reg16::from_number(self.number())
+    }
+
+    /// The lowest 8 bits of this register, never a high byte register, see [`GpReg`].
+    pub fn low8(&self) -> Reg8 {
+        Reg8::from_number(self.number())
+    }
+}
+
+impl Reg16 {
+    /// The 64-bit register this register is the lower 16 bits of.
+    pub fn widen64(&self) -> Reg64 {
+        Reg64::from_number(self.number())
+    }
+
+    /// The 32-bit register this register is the lower half of.
+    pub fn widen32(&self) -> Reg32 {
+        Reg32::from_number(self.number())
+    }
+
+    /// The lowest 8 bits of this register, never a high byte register, see [`GpReg`].
+    pub fn low8(&self) -> Reg8 {
+        Reg8::from_number(self.number())
+    }
+}
+
+impl Reg8 {
+    /// The 64-bit register this register is part of; `rax` for both `al` and `ah`.
+    pub fn widen64(&self) -> Reg64 {
+        Reg64::from_number(self.number())
+    }
+
+    /// The 32-bit register this register is part of; `eax` for both `al` and `ah`.
+    pub fn widen32(&self) -> Reg32 {
+        Reg32::from_number(self.number())
+    }
+
+    /// The 16-bit register this register is part of; `ax` for both `al` and `ah`.
+    pub fn widen16(&self) -> Reg16 {
+        Reg16::from_number(self.number())
+    }
+}
+
+impl From<Reg64> for Reg32 {
+    /// The lower 32 bits of a 64-bit register, see [`Reg64::low32`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::{Reg32, Reg64};
+    ///
+    /// assert_eq!(Reg32::from(Reg64::Rax), Reg32::Eax);
+    /// assert_eq!(Reg32::from(Reg64::R12), Reg32::R12d);
+    /// ```
+    fn from(reg64: Reg64) -> Self {
+        reg64.low32()
+    }
+}
+
+impl From<Reg64> for Reg16 {
+    /// The lower 16 bits of a 64-bit register, see [`Reg64::low16`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::{Reg16, Reg64};
+    ///
+    /// assert_eq!(Reg16::from(Reg64::Rax), Reg16::Ax);
+    /// assert_eq!(Reg16::from(Reg64::R12), Reg16::R12w);
+    /// ```
+    fn from(reg64: Reg64) -> Self {
+        reg64.low16()
+    }
+}
+
+impl From<Reg64> for Reg8 {
+    /// The lowest 8 bits of a 64-bit register, never a high byte register, see [`Reg64::low8`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::{Reg8, Reg64};
+    ///
+    /// assert_eq!(Reg8::from(Reg64::Rax), Reg8::Al);
+    /// assert_eq!(Reg8::from(Reg64::Rsi), Reg8::Sil);
+    /// assert_eq!(Reg8::from(Reg64::R12), Reg8::R12b);
+    /// ```
+    fn from(reg64: Reg64) -> Self {
+        reg64.low8()
+    }
+}
+
+/// A 128-bit SSE register.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Xmm {
+    Xmm0,
+    Xmm1,
+    Xmm2,
+    Xmm3,
+    Xmm4,
+    Xmm5,
+    Xmm6,
+    Xmm7,
+    Xmm8,
+    Xmm9,
+    Xmm10,
+    Xmm11,
+    Xmm12,
+    Xmm13,
+    Xmm14,
+    Xmm15,
+}
+
+impl Xmm {
+    /// The offset of this register, for instruction encoding.
+    pub fn offset(&self) -> u8 {
+        match self {
+            Self::Xmm0 => 0,
+            Self::Xmm1 => 1,
+            Self::Xmm2 => 2,
+            Self::Xmm3 => 3,
+            Self::Xmm4 => 4,
+            Self::Xmm5 => 5,
+            Self::Xmm6 => 6,
+            Self::Xmm7 => 7,
+            Self::Xmm8 => 0,
+            Self::Xmm9 => 1,
+            Self::Xmm10 => 2,
+            Self::Xmm11 => 3,
+            Self::Xmm12 => 4,
+            Self::Xmm13 => 5,
+            Self::Xmm14 => 6,
+            Self::Xmm15 => 7,
+        }
+    }
+
+    /// Returns true if this register is one of the extension registers, such as `xmm8`
+    /// 
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Xmm;
+    /// 
+    /// assert!(Xmm::Xmm8.is_extension());
+    /// assert!(!Xmm::Xmm7.is_extension());
+    /// ```
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Self::Xmm8 | Self::Xmm9 | Self::Xmm10 | Self::Xmm11 | Self::Xmm12 | Self::Xmm13 | Self::Xmm14 | Self::Xmm15)
+    }
+}
+
+/// A 256-bit AVX register, whose low 128 bits are the [`Xmm`] register of the same number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ymm {
+    Ymm0,
+    Ymm1,
+    Ymm2,
+    Ymm3,
+    Ymm4,
+    Ymm5,
+    Ymm6,
+    Ymm7,
+    Ymm8,
+    Ymm9,
+    Ymm10,
+    Ymm11,
+    Ymm12,
+    Ymm13,
+    Ymm14,
+    Ymm15,
+}
+
+impl Ymm {
+    /// The offset of this register, for instruction encoding.
+    pub fn offset(&self) -> u8 {
+        match self {
+            Self::Ymm0 => 0,
+            Self::Ymm1 => 1,
+            Self::Ymm2 => 2,
+            Self::Ymm3 => 3,
+            Self::Ymm4 => 4,
+            Self::Ymm5 => 5,
+            Self::Ymm6 => 6,
+            Self::Ymm7 => 7,
+            Self::Ymm8 => 0,
+            Self::Ymm9 => 1,
+            Self::Ymm10 => 2,
+            Self::Ymm11 => 3,
+            Self::Ymm12 => 4,
+            Self::Ymm13 => 5,
+            Self::Ymm14 => 6,
+            Self::Ymm15 => 7,
+        }
+    }
+
+    /// Returns true if this register is one of the extension registers, such as `ymm8`
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Ymm;
+    ///
+    /// assert!(Ymm::Ymm8.is_extension());
+    /// assert!(!Ymm::Ymm7.is_extension());
+    /// ```
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Self::Ymm8 | Self::Ymm9 | Self::Ymm10 | Self::Ymm11 | Self::Ymm12 | Self::Ymm13 | Self::Ymm14 | Self::Ymm15)
+    }
+}
+
+/// A vector register, `xmm` or `ymm`, identified by its number among the 16 vector registers: `xmm3` and `ymm3` are the same register.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::register::{VectorReg, Xmm, Ymm};
+///
+/// for number in 0..16 {
+///     let xmm = Xmm::from_number(number);
+///     assert_eq!(xmm.number(), number);
+///     assert_eq!(xmm.widen256().low128(), xmm);
+///     assert_eq!(xmm.widen256(), Ymm::from_number(number));
+///     assert_eq!(&xmm.name()[1..], &xmm.widen256().name()[1..]);
+///     assert_eq!(xmm.name().parse(), Ok(xmm));
+///     assert_eq!(xmm.widen256().name().parse(), Ok(xmm.widen256()));
+/// }
+/// ```
+pub trait VectorReg: Copy + fmt::Debug {
+    /// The registers of this width, by number.
+    const REGS: [Self; 16];
+
+    /// The number of this register, from 0 to 15.
+    fn number(&self) -> u8;
+
+    /// The lowercase name of this register, as accepted by its [`FromStr`] implementation.
+    fn name(&self) -> &'static str;
+
+    /// The register of this width numbered `number`.
+    ///
+    /// # Panics
+    /// Panics if `number` isn't below 16.
+    #[inline(always)]
+    fn from_number(number: u8) -> Self {
+        Self::REGS[number as usize]
+    }
+}
+
+impl VectorReg for Xmm {
+    const REGS: [Self; 16] = [
+        Self::Xmm0, Self::Xmm1, Self::Xmm2, Self::Xmm3, Self::Xmm4, Self::Xmm5, Self::Xmm6, Self::Xmm7,
+        Self::Xmm8, Self::Xmm9, Self::Xmm10, Self::Xmm11, Self::Xmm12, Self::Xmm13, Self::Xmm14, Self::Xmm15,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        self.offset() | ((self.is_extension() as u8) << 3)
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = [
+            "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8", "xmm9", "xmm10", "xmm11", "xmm12", "xmm13", "xmm14", "xmm15",
+        ];
+        NAMES[self.number() as usize]
+    }
+}
+
+impl VectorReg for Ymm {
+    const REGS: [Self; 16] = [
+        Self::Ymm0, Self::Ymm1, Self::Ymm2, Self::Ymm3, Self::Ymm4, Self::Ymm5, Self::Ymm6, Self::Ymm7,
+        Self::Ymm8, Self::Ymm9, Self::Ymm10, Self::Ymm11, Self::Ymm12, Self::Ymm13, Self::Ymm14, Self::Ymm15,
+    ];
+
+    #[inline(always)]
+    fn number(&self) -> u8 {
+        self.offset() | ((self.is_extension() as u8) << 3)
+    }
+
+    fn name(&self) -> &'static str {
+        const NAMES: [&str; 16] = [
+            "ymm0", "ymm1", "ymm2", "ymm3", "ymm4", "ymm5", "ymm6", "ymm7", "ymm8", "ymm9", "ymm10", "ymm11", "ymm12", "ymm13", "ymm14", "ymm15",
+        ];
+        NAMES[self.number() as usize]
+    }
+}
+
+impl Xmm {
+    /// The `ymm` register whose low 128 bits are this register.
+    pub fn widen256(&self) -> Ymm {
+        Ymm::from_number(self.number())
+    }
+}
+
+impl Ymm {
+    /// The low 128 bits of this register.
+    pub fn low128(&self) -> Xmm {
+        Xmm::from_number(self.number())
+    }
+}
+
+/// A segment register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SegmentReg {
+    Es,
+    Cs,
+    Ss,
+    Ds,
+    Fs,
+    Gs,
+}
+
+impl SegmentReg {
+    /// The offset of this register, for instruction encoding.
+    pub fn offset(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The name of this register, in lowercase.
+    pub fn name(&self) -> &'static str {
+        const NAMES: [&str; 6] = ["es", "cs", "ss", "ds", "fs", "gs"];
+        NAMES[*self as usize]
+    }
+
+    /// Checks that this register can be written by a `mov`, which isn't the case of `cs`, only loaded by far jumps, calls and returns.
+    ///
+    /// # Errors
+    /// Returns [`AsmError::ReadOnlySegment`] for [`SegmentReg::Cs`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{error::AsmError, register::SegmentReg};
+    ///
+    /// assert_eq!(SegmentReg::Fs.check_writable(), Ok(()));
+    /// assert_eq!(SegmentReg::Cs.check_writable(), Err(AsmError::ReadOnlySegment(SegmentReg::Cs)));
+    /// ```
+    pub fn check_writable(&self) -> Result<(), AsmError> {
         match self {
-            Self::R8 | Self::R9 | Self::R10 | Self::R11 | Self::R12 | Self::R13 | Self::R14 | Self::R15 => true,
-            _ => false,
+            Self::Cs => Err(AsmError::ReadOnlySegment(*self)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for SegmentReg {
+    type Err = UnknownRegister;
+
+    /// Parses a segment register from its lowercase name.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "es" => Ok(Self::Es),
+            "cs" => Ok(Self::Cs),
+            "ss" => Ok(Self::Ss),
+            "ds" => Ok(Self::Ds),
+            "fs" => Ok(Self::Fs),
+            "gs" => Ok(Self::Gs),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+/// The error returned when parsing an unknown register name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownRegister(pub String);
+
+impl fmt::Display for UnknownRegister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown register `{}`", self.0)
+    }
+}
+
+impl Error for UnknownRegister {}
+
+impl FromStr for Reg8 {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Reg8;
+    ///
+    /// assert_eq!("sil".parse(), Ok(Reg8::Sil));
+    /// assert!("xmm0".parse::<Reg8>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "al" => Ok(Self::Al),
+            "bl" => Ok(Self::Bl),
+            "cl" => Ok(Self::Cl),
+            "dl" => Ok(Self::Dl),
+            "sil" => Ok(Self::Sil),
+            "dil" => Ok(Self::Dil),
+            "bpl" => Ok(Self::Bpl),
+            "spl" => Ok(Self::Spl),
+            "ah" => Ok(Self::Ah),
+            "bh" => Ok(Self::Bh),
+            "ch" => Ok(Self::Ch),
+            "dh" => Ok(Self::Dh),
+            "r8b" => Ok(Self::R8b),
+            "r9b" => Ok(Self::R9b),
+            "r10b" => Ok(Self::R10b),
+            "r11b" => Ok(Self::R11b),
+            "r12b" => Ok(Self::R12b),
+            "r13b" => Ok(Self::R13b),
+            "r14b" => Ok(Self::R14b),
+            "r15b" => Ok(Self::R15b),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+impl FromStr for Reg16 {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Reg16;
+    ///
+    /// assert_eq!("r9w".parse(), Ok(Reg16::R9w));
+    /// assert!("xmm0".parse::<Reg16>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "ax" => Ok(Self::Ax),
+            "bx" => Ok(Self::Bx),
+            "cx" => Ok(Self::Cx),
+            "dx" => Ok(Self::Dx),
+            "si" => Ok(Self::Si),
+            "di" => Ok(Self::Di),
+            "bp" => Ok(Self::Bp),
+            "sp" => Ok(Self::Sp),
+            "r8w" => Ok(Self::R8w),
+            "r9w" => Ok(Self::R9w),
+            "r10w" => Ok(Self::R10w),
+            "r11w" => Ok(Self::R11w),
+            "r12w" => Ok(Self::R12w),
+            "r13w" => Ok(Self::R13w),
+            "r14w" => Ok(Self::R14w),
+            "r15w" => Ok(Self::R15w),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+impl FromStr for Reg32 {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Reg32;
+    ///
+    /// assert_eq!("eax".parse(), Ok(Reg32::Eax));
+    /// assert!("xmm0".parse::<Reg32>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "eax" => Ok(Self::Eax),
+            "ebx" => Ok(Self::Ebx),
+            "ecx" => Ok(Self::Ecx),
+            "edx" => Ok(Self::Edx),
+            "esi" => Ok(Self::Esi),
+            "edi" => Ok(Self::Edi),
+            "ebp" => Ok(Self::Ebp),
+            "esp" => Ok(Self::Esp),
+            "r8d" => Ok(Self::R8d),
+            "r9d" => Ok(Self::R9d),
+            "r10d" => Ok(Self::R10d),
+            "r11d" => Ok(Self::R11d),
+            "r12d" => Ok(Self::R12d),
+            "r13d" => Ok(Self::R13d),
+            "r14d" => Ok(Self::R14d),
+            "r15d" => Ok(Self::R15d),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+impl FromStr for Reg64 {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Reg64;
+    ///
+    /// assert_eq!("r12".parse(), Ok(Reg64::R12));
+    /// assert!("xmm0".parse::<Reg64>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "rax" => Ok(Self::Rax),
+            "rbx" => Ok(Self::Rbx),
+            "rcx" => Ok(Self::Rcx),
+            "rdx" => Ok(Self::Rdx),
+            "rsi" => Ok(Self::Rsi),
+            "rdi" => Ok(Self::Rdi),
+            "rbp" => Ok(Self::Rbp),
+            "rsp" => Ok(Self::Rsp),
+            "r8" => Ok(Self::R8),
+            "r9" => Ok(Self::R9),
+            "r10" => Ok(Self::R10),
+            "r11" => Ok(Self::R11),
+            "r12" => Ok(Self::R12),
+            "r13" => Ok(Self::R13),
+            "r14" => Ok(Self::R14),
+            "r15" => Ok(Self::R15),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+impl FromStr for Xmm {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Xmm;
+    ///
+    /// assert_eq!("xmm12".parse(), Ok(Xmm::Xmm12));
+    /// assert!("rax".parse::<Xmm>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "xmm0" => Ok(Self::Xmm0),
+            "xmm1" => Ok(Self::Xmm1),
+            "xmm2" => Ok(Self::Xmm2),
+            "xmm3" => Ok(Self::Xmm3),
+            "xmm4" => Ok(Self::Xmm4),
+            "xmm5" => Ok(Self::Xmm5),
+            "xmm6" => Ok(Self::Xmm6),
+            "xmm7" => Ok(Self::Xmm7),
+            "xmm8" => Ok(Self::Xmm8),
+            "xmm9" => Ok(Self::Xmm9),
+            "xmm10" => Ok(Self::Xmm10),
+            "xmm11" => Ok(Self::Xmm11),
+            "xmm12" => Ok(Self::Xmm12),
+            "xmm13" => Ok(Self::Xmm13),
+            "xmm14" => Ok(Self::Xmm14),
+            "xmm15" => Ok(Self::Xmm15),
+            _ => Err(UnknownRegister(name.to_string())),
+        }
+    }
+}
+
+impl FromStr for Ymm {
+    type Err = UnknownRegister;
+
+    /// Parses a register from its lowercase name.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::register::Ymm;
+    ///
+    /// assert_eq!("ymm12".parse(), Ok(Ymm::Ymm12));
+    /// assert!("xmm12".parse::<Ymm>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "ymm0" => Ok(Self::Ymm0),
+            "ymm1" => Ok(Self::Ymm1),
+            "ymm2" => Ok(Self::Ymm2),
+            "ymm3" => Ok(Self::Ymm3),
+            "ymm4" => Ok(Self::Ymm4),
+            "ymm5" => Ok(Self::Ymm5),
+            "ymm6" => Ok(Self::Ymm6),
+            "ymm7" => Ok(Self::Ymm7),
+            "ymm8" => Ok(Self::Ymm8),
+            "ymm9" => Ok(Self::Ymm9),
+            "ymm10" => Ok(Self::Ymm10),
+            "ymm11" => Ok(Self::Ymm11),
+            "ymm12" => Ok(Self::Ymm12),
+            "ymm13" => Ok(Self::Ymm13),
+            "ymm14" => Ok(Self::Ymm14),
+            "ymm15" => Ok(Self::Ymm15),
+            _ => Err(UnknownRegister(name.to_string())),
         }
     }
 }
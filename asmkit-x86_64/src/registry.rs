@@ -0,0 +1,1295 @@
+//! The registry of the instruction forms, mapping a mnemonic and the kinds of its operands to the emitter writing it.
+//!
+//! [`INSTRUCTIONS`] describes the public emitters by their Intel-syntax mnemonic, the [`OperandKind`] of each of their operands, the name of
+//! the emitter method and a thunk calling it with parsed [`Operand`]s.  It is the single table shared by the text assembler of the `text`
+//! module, the `asm_x64!` macro of `asmkit-macros`, and the [listing](crate::listing) of the instructions they emit.  An emitter becomes
+//! available to all of them by adding its forms to the table.
+
+use std::{fmt, str::FromStr};
+
+use asmkit_core::{entity::{EntityRef, LabelRef, SymRef}, InstructionStream};
+
+use crate::{
+    error::AsmError,
+    flags::{flag_effects, FlagEffects},
+    gather::check_gather_registers,
+    memory::{Mem, Scale, VsibMem},
+    mode::Mode,
+    register::{GpReg, Reg16, Reg32, Reg64, Reg8, SegmentReg, UnknownRegister, VectorReg, Xmm, Ymm},
+    sse41::RoundingControl,
+    stream::x86_64InstructionStream,
+};
+
+/// A register operand of any kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Reg32(Reg32),
+    Reg64(Reg64),
+    Xmm(Xmm),
+    Ymm(Ymm),
+
+    /// A segment register, such as the `fs` of `push fs`.
+    Segment(SegmentReg),
+}
+
+impl Register {
+    /// The lowercase name of the register.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reg8(register) => register.name(),
+            Self::Reg16(register) => register.name(),
+            Self::Reg32(register) => register.name(),
+            Self::Reg64(register) => register.name(),
+            Self::Xmm(register) => register.name(),
+            Self::Ymm(register) => register.name(),
+            Self::Segment(register) => register.name(),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = UnknownRegister;
+
+    /// Parses a register of any kind from its lowercase name.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        name.parse().map(Self::Reg64)
+            .or_else(|_| name.parse().map(Self::Reg32))
+            .or_else(|_| name.parse().map(Self::Reg16))
+            .or_else(|_| name.parse().map(Self::Reg8))
+            .or_else(|_| name.parse().map(Self::Xmm))
+            .or_else(|_| name.parse().map(Self::Ymm))
+            .or_else(|_| name.parse().map(Self::Segment))
+    }
+}
+
+/// The sizes of the memory operands in bytes, by the name of their `ptr` prefix, such as `qword` in `qword ptr [rax]`.
+pub const MEMORY_SIZES: [(&str, u8); 8] = [
+    ("byte", 1), ("word", 2), ("dword", 4), ("fword", 6), ("qword", 8), ("tbyte", 10), ("xmmword", 16), ("ymmword", 32),
+];
+
+/// What a `rip`-relative memory operand addresses, written in place of its registers, such as `[label]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryTarget {
+    /// A label of the stream.
+    Label(LabelRef),
+
+    /// An external symbol.
+    Symbol(SymRef),
+
+    /// The global offset table entry of an external symbol, `[symbol@gotpcrel]`.
+    Got(SymRef),
+
+    /// The displacement of the operand from the end of the instruction, `[rip + displacement]`, as the [decoder](crate::decode) reads the
+    /// other targets.
+    Rip,
+}
+
+/// A memory operand, `[base + index*scale + displacement]`, or a `rip`-relative [target](MemoryTarget).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Memory {
+    /// The size of the access in bytes, if given by a `ptr` prefix such as `qword ptr`.
+    pub size: Option<u8>,
+
+    /// The base register.
+    pub base: Option<Reg64>,
+
+    /// The index register and its scale, a vector register for the gather instructions.
+    pub index: Option<(Register, u8)>,
+
+    /// The displacement.
+    pub displacement: i32,
+
+    /// The target of a `rip`-relative operand.
+    pub target: Option<MemoryTarget>,
+}
+
+impl Memory {
+    /// The operand as taken by the emitters, if it has a base register, and a 64-bit index other than `rsp` with a scale of 1, 2, 4 or 8.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{memory::{Mem, Scale}, register::Reg64, registry::{Memory, Register}};
+    ///
+    /// let memory = Memory { size: Some(8), base: Some(Reg64::Rbx), index: Some((Register::Reg64(Reg64::Rcx), 8)), displacement: -0x10, target: None };
+    /// assert_eq!(memory.mem(), Some(Mem::indexed(Reg64::Rbx, Reg64::Rcx, Scale::X8, -0x10)));
+    /// assert_eq!(Memory { base: None, ..memory }.mem(), None);
+    /// ```
+    pub fn mem(&self) -> Option<Mem> {
+        let base = self.base.filter(|_| self.target.is_none())?;
+
+        match self.index {
+            Some((Register::Reg64(index), scale)) if index != Reg64::Rsp => Some(Mem::indexed(base, index, Scale::from_factor(scale)?, self.displacement)),
+            Some(_) => None,
+            None => Some(Mem::new(base, self.displacement)),
+        }
+    }
+
+    /// The operand as taken by the gathers with an `xmm` index, if it has a base register and an `xmm` index.
+    pub fn vsib_xmm(&self) -> Option<VsibMem<Xmm>> {
+        match (self.target, self.base, self.index) {
+            (None, Some(base), Some((Register::Xmm(index), scale))) => Some(VsibMem::new(base, index, Scale::from_factor(scale)?, self.displacement)),
+            _ => None,
+        }
+    }
+
+    /// The operand as taken by the gathers with a `ymm` index, if it has a base register and a `ymm` index.
+    pub fn vsib_ymm(&self) -> Option<VsibMem<Ymm>> {
+        match (self.target, self.base, self.index) {
+            (None, Some(base), Some((Register::Ymm(index), scale))) => Some(VsibMem::new(base, index, Scale::from_factor(scale)?, self.displacement)),
+            _ => None,
+        }
+    }
+
+    /// The target of the operand, if it is nothing but its target, as taken by the emitters of the labels and symbols.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{entity::{EntityRef, LabelRef}};
+    /// use asmkit_x86_64::registry::{Memory, MemoryTarget};
+    ///
+    /// let memory = Memory { target: Some(MemoryTarget::Label(LabelRef::new(0))), ..Memory::default() };
+    /// assert_eq!(memory.target(), Some(MemoryTarget::Label(LabelRef::new(0))));
+    /// assert_eq!(Memory { displacement: 8, ..memory }.target(), None);
+    /// ```
+    pub fn target(&self) -> Option<MemoryTarget> {
+        self.target.filter(|_| self.base.is_none() && self.index.is_none() && self.displacement == 0)
+    }
+}
+
+impl fmt::Display for Memory {
+    /// Formats the operand in Intel syntax, such as `qword ptr [rbx + rcx*8 - 0x10]`.  The labels and symbols have no names here, and are
+    /// written by number, such as `[label3]`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{register::Reg64, registry::{Memory, MemoryTarget, Register}};
+    ///
+    /// let memory = Memory { size: Some(8), base: Some(Reg64::Rbx), index: Some((Register::Reg64(Reg64::Rcx), 8)), displacement: -0x10, target: None };
+    /// assert_eq!(memory.to_string(), "qword ptr [rbx + rcx*8 - 0x10]");
+    /// assert_eq!(Memory { size: None, index: Some((Register::Reg64(Reg64::Rcx), 1)), displacement: 0, ..memory }.to_string(), "[rbx + rcx]");
+    /// assert_eq!(Memory { size: Some(16), displacement: 7, target: Some(MemoryTarget::Rip), ..Memory::default() }.to_string(), "xmmword ptr [rip + 0x7]");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(size) = self.size {
+            match MEMORY_SIZES.iter().find(|(_, bytes)| *bytes == size) {
+                Some((name, _)) => write!(f, "{} ptr ", name)?,
+                None => write!(f, "{}-byte ptr ", size)?,
+            }
+        }
+
+        let mut terms = 0;
+        f.write_str("[")?;
+
+        match self.target {
+            Some(MemoryTarget::Label(label)) => write!(f, "label{}", label.as_u32())?,
+            Some(MemoryTarget::Symbol(symbol)) => write!(f, "symbol{}", symbol.as_u32())?,
+            Some(MemoryTarget::Got(symbol)) => write!(f, "symbol{}@gotpcrel", symbol.as_u32())?,
+            Some(MemoryTarget::Rip) => f.write_str("rip")?,
+            None => (),
+        }
+
+        if self.target.is_some() {
+            terms += 1;
+        }
+
+        if let Some(base) = self.base {
+            f.write_str(if terms == 0 { "" } else { " + " })?;
+            f.write_str(base.name())?;
+            terms += 1;
+        }
+
+        if let Some((index, scale)) = self.index {
+            f.write_str(if terms == 0 { "" } else { " + " })?;
+            f.write_str(index.name())?;
+            terms += 1;
+
+            if scale != 1 {
+                write!(f, "*{}", scale)?;
+            }
+        }
+
+        match self.displacement {
+            displacement if terms == 0 => write!(f, "{:#x}", displacement)?,
+            0 => (),
+            displacement if displacement < 0 => write!(f, " - {:#x}", displacement.unsigned_abs())?,
+            displacement => write!(f, " + {:#x}", displacement)?,
+        }
+
+        f.write_str("]")
+    }
+}
+
+/// A parsed operand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Immediate(i64),
+    Memory(Memory),
+    Label(LabelRef),
+
+    /// A label reached by a short branch, `short label`.
+    ShortLabel(LabelRef),
+
+    /// An external symbol.
+    Symbol(SymRef),
+
+    /// The displacement of a branch from the end of the instruction, `$+n`.
+    Relative(i64),
+
+    /// A far pointer, `segment:offset`.
+    FarPointer { segment: u16, offset: u32 },
+}
+
+impl fmt::Display for Operand {
+    /// Formats the operand in Intel syntax, the labels and symbols by number as the [memory operands](Memory) do.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{register::Reg8, registry::{Operand, Register}};
+    ///
+    /// assert_eq!(Operand::Register(Register::Reg8(Reg8::Ah)).to_string(), "ah");
+    /// assert_eq!(Operand::Immediate(-0x10).to_string(), "-0x10");
+    /// assert_eq!(Operand::Relative(-2).to_string(), "$-2");
+    /// assert_eq!(Operand::FarPointer { segment: 0x8, offset: 0x1000 }.to_string(), "0x8:0x1000");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Register(register) => f.write_str(register.name()),
+            Self::Immediate(value) if *value < 0 => write!(f, "-{:#x}", value.unsigned_abs()),
+            Self::Immediate(value) => write!(f, "{:#x}", value),
+            Self::Memory(memory) => write!(f, "{}", memory),
+            Self::Label(label) => write!(f, "label{}", label.as_u32()),
+            Self::ShortLabel(label) => write!(f, "short label{}", label.as_u32()),
+            Self::Symbol(symbol) => write!(f, "symbol{}", symbol.as_u32()),
+            Self::Relative(displacement) => write!(f, "${:+}", displacement),
+            Self::FarPointer { segment, offset } => write!(f, "{:#x}:{:#x}", segment, offset),
+        }
+    }
+}
+
+/// Checks that the 8-bit register operands of an instruction can be encoded together and with its memory operands, see
+/// [`Reg8::check_encodable_with`] and [`Reg8::check_encodable_with_mem`], and that the vector registers of a gather are distinct, see
+/// [`check_gather_registers`].
+pub fn check_operands(operands: &[Operand]) -> Result<(), AsmError> {
+    let registers = operands.iter().filter_map(|operand| match operand {
+        Operand::Register(Register::Reg8(register)) => Some(*register),
+        _ => None,
+    }).collect::<Vec<_>>();
+
+    for (index, register) in registers.iter().enumerate() {
+        for other in &registers[index + 1..] {
+            register.check_encodable_with(*other)?;
+        }
+
+        for operand in operands {
+            if let Some(mem) = match operand { Operand::Memory(memory) => memory.mem(), _ => None } {
+                register.check_encodable_with_mem(mem)?;
+            }
+        }
+    }
+
+    // the destination and mask of a gather surround its memory operand.
+    let vector = |operand: &Operand| match operand {
+        Operand::Register(Register::Xmm(register)) => Some(register.number()),
+        Operand::Register(Register::Ymm(register)) => Some(register.number()),
+        _ => None,
+    };
+
+    if let [dest, Operand::Memory(Memory { index: Some((index, _)), .. }), mask] = operands {
+        if let (Some(dest), Some(index), Some(mask)) = (vector(dest), vector(&Operand::Register(*index)), vector(mask)) {
+            check_gather_registers(Ymm::from_number(dest), Ymm::from_number(index), Ymm::from_number(mask)).map_err(AsmError::OverlappingGatherRegisters)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The kind of operand accepted by an [`InstructionForm`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperandKind {
+    Reg8,
+    Reg16,
+    Reg32,
+    Reg64,
+    Xmm,
+    Ymm,
+
+    /// Any segment register.
+    SegmentReg,
+
+    /// A segment register other than `cs`, which `mov` can't write, see [`SegmentReg::check_writable`].
+    WritableSegmentReg,
+
+    /// The `fs` segment register, implicit in the encoding.
+    Fs,
+
+    /// The `gs` segment register, implicit in the encoding.
+    Gs,
+
+    /// The `cl` register holding the count of a shift, implicit in the encoding.
+    Cl,
+
+    /// An immediate which fits in 8 bits, either signed or unsigned.
+    Imm8,
+
+    /// An immediate which fits in 16 bits, either signed or unsigned.
+    Imm16,
+
+    /// An immediate which fits in 32 bits, either signed or unsigned.
+    Imm32,
+
+    /// Any immediate.
+    Imm64,
+
+    /// An immediate which survives being sign extended from 8 bits, passed to the emitters as an `i8`.
+    SImm8,
+
+    /// An immediate which survives being sign extended from 32 bits, passed to the emitters as an `i32`.
+    SImm32,
+
+    /// The rounding control immediate of the `round*` instructions, from 8 to 11, passed to the emitters as a [`RoundingControl`].
+    Rounding,
+
+    /// A memory operand accessing a byte, written without a size or with `byte ptr`.
+    Mem8,
+
+    /// A memory operand accessing a word, written without a size or with `word ptr`.
+    Mem16,
+
+    /// A memory operand accessing a double word, written without a size or with `dword ptr`.
+    Mem32,
+
+    /// A memory operand accessing the 6-byte far pointer *m16:32*, written without a size or with `fword ptr`.
+    Mem48,
+
+    /// A memory operand accessing a quad word, written without a size or with `qword ptr`.
+    Mem64,
+
+    /// A memory operand accessing the 10-byte far pointer *m16:64*, written without a size or with `tbyte ptr`.
+    Mem80,
+
+    /// A memory operand accessing 16 bytes, written without a size or with `xmmword ptr`.
+    Mem128,
+
+    /// A memory operand accessing 32 bytes, written without a size or with `ymmword ptr`.
+    Mem256,
+
+    /// A memory operand of any size, whose address is taken rather than accessed, as the operand of `lea`, or whose size is set by the
+    /// instruction, as the save area of `fxsave`.
+    Memory,
+
+    /// A memory operand with an `xmm` index, as the gathers take.
+    VsibX,
+
+    /// A memory operand with a `ymm` index, as the gathers take.
+    VsibY,
+
+    /// A byte at a label, `byte ptr [label]`.
+    LabelMem8,
+
+    /// A word at a label, `word ptr [label]`.
+    LabelMem16,
+
+    /// A double word at a label, `dword ptr [label]`.
+    LabelMem32,
+
+    /// A quad word at a label, `qword ptr [label]`.
+    LabelMem64,
+
+    /// 16 bytes at a label, `xmmword ptr [label]`.
+    LabelMem128,
+
+    /// 32 bytes at a label, `ymmword ptr [label]`.
+    LabelMem256,
+
+    /// A label whose address is taken, `[label]`.
+    LabelMemory,
+
+    /// A quad word at an external symbol, `qword ptr [symbol]`.
+    SymbolMem64,
+
+    /// An external symbol whose address is taken, `[symbol]`.
+    SymbolMemory,
+
+    /// The global offset table entry of an external symbol, `qword ptr [symbol@gotpcrel]`.
+    GotMem64,
+
+    Label,
+
+    /// A label reached by a short branch, written `short label`.
+    ShortLabel,
+
+    /// The 32-bit displacement of a branch from the end of the instruction, written `$+n`.
+    Rel32,
+
+    /// An external symbol.
+    Symbol,
+
+    /// A far pointer with a 16-bit offset, `segment:offset`.
+    FarPtr16,
+
+    /// A far pointer with a 32-bit offset, `segment:offset`.
+    FarPtr32,
+}
+
+impl OperandKind {
+    /// The size in bytes of the memory operands of this kind, if it is a sized memory kind.
+    pub fn memory_size(&self) -> Option<u8> {
+        match self {
+            Self::Mem8 | Self::LabelMem8 => Some(1),
+            Self::Mem16 | Self::LabelMem16 => Some(2),
+            Self::Mem32 | Self::LabelMem32 => Some(4),
+            Self::Mem48 => Some(6),
+            Self::Mem64 | Self::LabelMem64 | Self::SymbolMem64 | Self::GotMem64 => Some(8),
+            Self::Mem80 => Some(10),
+            Self::Mem128 | Self::LabelMem128 => Some(16),
+            Self::Mem256 | Self::LabelMem256 => Some(32),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the operands of this kind are implicit in the encoding, and so aren't passed to the emitter, as the `cl` of
+    /// `shl rax, cl`.
+    pub fn is_implicit(&self) -> bool {
+        matches!(self, Self::Fs | Self::Gs | Self::Cl)
+    }
+
+    /// Returns true if `operand` is of this kind.  A memory operand is accepted by the memory kinds if the emitters can encode it, see
+    /// [`Memory::mem`] and [`Memory::target`], and by the sized memory kinds if it has their size or no size.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::entity::{EntityRef, LabelRef};
+    /// use asmkit_x86_64::{register::Reg64, registry::{Memory, MemoryTarget, Operand, OperandKind}};
+    ///
+    /// let memory = Memory { size: None, base: Some(Reg64::Rbx), index: None, displacement: 8, target: None };
+    /// assert!(OperandKind::Mem32.accepts(&Operand::Memory(memory)));
+    /// assert!(OperandKind::Mem32.accepts(&Operand::Memory(Memory { size: Some(4), ..memory })));
+    /// assert!(!OperandKind::Mem32.accepts(&Operand::Memory(Memory { size: Some(8), ..memory })));
+    /// assert!(OperandKind::Memory.accepts(&Operand::Memory(Memory { size: Some(8), ..memory })));
+    /// assert!(!OperandKind::Memory.accepts(&Operand::Memory(Memory { base: None, ..memory })));
+    ///
+    /// let label = Memory { target: Some(MemoryTarget::Label(LabelRef::new(0))), ..Memory::default() };
+    /// assert!(OperandKind::LabelMem64.accepts(&Operand::Memory(label)));
+    /// assert!(!OperandKind::Mem64.accepts(&Operand::Memory(label)));
+    /// ```
+    pub fn accepts(&self, operand: &Operand) -> bool {
+        let fits = |min: i64, max: i64| matches!(operand, Operand::Immediate(value) if (min..=max).contains(value));
+        let sized = |memory: &Memory| self.memory_size().is_none() || memory.size.is_none() || memory.size == self.memory_size();
+        let memory = |valid: fn(&Memory) -> bool| matches!(operand, Operand::Memory(memory) if valid(memory) && sized(memory));
+
+        match self {
+            Self::Reg8 => matches!(operand, Operand::Register(Register::Reg8(_))),
+            Self::Reg16 => matches!(operand, Operand::Register(Register::Reg16(_))),
+            Self::Reg32 => matches!(operand, Operand::Register(Register::Reg32(_))),
+            Self::Reg64 => matches!(operand, Operand::Register(Register::Reg64(_))),
+            Self::Xmm => matches!(operand, Operand::Register(Register::Xmm(_))),
+            Self::Ymm => matches!(operand, Operand::Register(Register::Ymm(_))),
+            Self::SegmentReg => matches!(operand, Operand::Register(Register::Segment(_))),
+            Self::WritableSegmentReg => matches!(operand, Operand::Register(Register::Segment(segment)) if segment.check_writable().is_ok()),
+            Self::Fs => matches!(operand, Operand::Register(Register::Segment(SegmentReg::Fs))),
+            Self::Gs => matches!(operand, Operand::Register(Register::Segment(SegmentReg::Gs))),
+            Self::Cl => matches!(operand, Operand::Register(Register::Reg8(Reg8::Cl))),
+            Self::Imm8 => fits(i8::MIN as i64, u8::MAX as i64),
+            Self::Imm16 => fits(i16::MIN as i64, u16::MAX as i64),
+            Self::Imm32 => fits(i32::MIN as i64, u32::MAX as i64),
+            Self::Imm64 => matches!(operand, Operand::Immediate(_)),
+            Self::SImm8 => fits(i8::MIN as i64, i8::MAX as i64),
+            Self::SImm32 => fits(i32::MIN as i64, i32::MAX as i64),
+            Self::Rounding => fits(0b1000, 0b1011),
+            Self::Mem8 | Self::Mem16 | Self::Mem32 | Self::Mem48 | Self::Mem64 | Self::Mem80 | Self::Mem128 | Self::Mem256 | Self::Memory => {
+                memory(|memory| memory.mem().is_some())
+            },
+            Self::VsibX => memory(|memory| memory.vsib_xmm().is_some()),
+            Self::VsibY => memory(|memory| memory.vsib_ymm().is_some()),
+            Self::LabelMem8 | Self::LabelMem16 | Self::LabelMem32 | Self::LabelMem64 | Self::LabelMem128 | Self::LabelMem256 | Self::LabelMemory => {
+                memory(|memory| matches!(memory.target(), Some(MemoryTarget::Label(_))))
+            },
+            Self::SymbolMem64 | Self::SymbolMemory => memory(|memory| matches!(memory.target(), Some(MemoryTarget::Symbol(_)))),
+            Self::GotMem64 => memory(|memory| matches!(memory.target(), Some(MemoryTarget::Got(_)))),
+            Self::Label => matches!(operand, Operand::Label(_)),
+            Self::ShortLabel => matches!(operand, Operand::ShortLabel(_)),
+            Self::Rel32 => matches!(operand, Operand::Relative(displacement) if i32::try_from(*displacement).is_ok()),
+            Self::Symbol => matches!(operand, Operand::Symbol(_)),
+            Self::FarPtr16 => matches!(operand, Operand::FarPointer { offset, .. } if *offset <= u16::MAX as u32),
+            Self::FarPtr32 => matches!(operand, Operand::FarPointer { .. }),
+        }
+    }
+}
+
+/// One form of an instruction, mapping a mnemonic and its operand kinds to an emitter.
+#[derive(Debug)]
+pub struct InstructionForm {
+    /// The lowercase mnemonic.
+    pub mnemonic: &'static str,
+
+    /// The kinds of the operands, in order.
+    pub operands: &'static [OperandKind],
+
+    /// The name of the emitter method, which takes the leading arguments then the operands in order, leaving out the [implicit
+    /// operands](OperandKind::is_implicit).  Used by the `asm_x64!` macro of `asmkit-macros`.
+    pub emitter: &'static str,
+
+    /// The arguments passed to the emitter before the operands, as expressions with paths starting with `crate`, such as the condition of the
+    /// `jcc_label` forms.
+    pub leading: &'static [&'static str],
+
+    /// The modes the form is available in, see [`x86_64InstructionStream::new_with_mode`].
+    pub modes: &'static [Mode],
+
+    /// Emits the instruction.  Only called with operands accepted by [`InstructionForm::operands`], by a stream of one of its modes.
+    pub emit: fn(&mut x86_64InstructionStream, &[Operand]),
+}
+
+/// Forms are equal if they have the same mnemonic, operand kinds, emitter, leading arguments and modes, whose thunks make the same call.
+impl PartialEq for InstructionForm {
+    fn eq(&self, other: &Self) -> bool {
+        self.mnemonic == other.mnemonic && self.operands == other.operands && self.emitter == other.emitter && self.leading == other.leading
+            && self.modes == other.modes
+    }
+}
+
+impl InstructionForm {
+    /// The effects of the instruction on the flags, see [`flag_effects`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{flags::FlagSet, registry::INSTRUCTIONS};
+    ///
+    /// // every mnemonic of the registry has its effects.
+    /// let conditional = [
+    ///     "adc", "loope", "loopne", "shl", "shr", "sar", "shld", "lodsb", "lodsw", "stosb", "stosw", "movsb", "movsw", "rep movsb",
+    ///     "rep stosb", "repe cmpsb",
+    /// ];
+    /// let status = [
+    ///     "add", "adc", "sub", "cmp", "xadd", "cmpxchg", "ptest", "ucomiss", "ucomisd", "xtest", "umwait", "tpause", "enqcmd", "enqcmds",
+    ///     "repe cmpsb",
+    /// ];
+    ///
+    /// for form in INSTRUCTIONS {
+    ///     let effects = form.flag_effects();
+    ///     let conditional = form.emitter.starts_with("jcc") || conditional.contains(&form.mnemonic);
+    ///     assert_eq!(effects.reads.is_empty(), !conditional, "`{}`", form.mnemonic);
+    ///     assert_eq!(effects.writes == FlagSet::STATUS, status.contains(&form.mnemonic), "`{}`", form.mnemonic);
+    /// }
+    /// ```
+    pub fn flag_effects(&self) -> FlagEffects {
+        flag_effects(self.mnemonic).unwrap_or_else(|| panic!("no flag effects for `{}`", self.mnemonic))
+    }
+
+    /// Returns true if the form accepts `operands`, which are as many as its operand kinds and each accepted by its kind.
+    pub fn accepts(&self, operands: &[Operand]) -> bool {
+        self.operands.len() == operands.len() && self.operands.iter().zip(operands).all(|(kind, operand)| kind.accepts(operand))
+    }
+
+    /// Operands accepted by the form and by its emitter in the mode of `stream`, picked from `seed`, for exercising the form.  The labels and
+    /// symbols are created in `stream`, the labels attached at its current offset, so a branch targets the instruction itself.
+    ///
+    /// The registers of an instruction are distinct, numbered `seed`, `seed + 5` and `seed + 10` modulo 16, so the seeds 0 to 15 give each
+    /// operand every register.  One seed in 8, and every seed outside long mode, only picks registers which don't require a REX prefix, and the
+    /// 8-bit registers numbered 4 to 7 are then the high byte registers `ah` to `bh`.  The even seeds pick immediates which fit in 8 bits, and
+    /// one seed in 3 a memory operand with a scaled index.  The [decoder](crate::decode) learns the encodings of the forms from the bytes of
+    /// their example operands.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::{register::Reg8, registry::{forms, Operand, Register}, stream::x86_64InstructionStream};
+    ///
+    /// let mov = forms("mov").next().unwrap();
+    /// let mut stream = x86_64InstructionStream::new();
+    /// assert_eq!(mov.example_operands(&mut stream, 3), [Operand::Register(Register::Reg8(Reg8::Bl)), Operand::Register(Register::Reg8(Reg8::R8b))]);
+    /// assert_eq!(mov.example_operands(&mut stream, 5), [Operand::Register(Register::Reg8(Reg8::Ch)), Operand::Register(Register::Reg8(Reg8::Dl))]);
+    /// ```
+    pub fn example_operands(&self, stream: &mut x86_64InstructionStream, seed: u64) -> Vec<Operand> {
+        // a splitmix64 generator, for the immediates and displacements.
+        let mut state = seed;
+        let mut random = move || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut mixed = state;
+            mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            mixed ^ (mixed >> 31)
+        };
+
+        let legacy = seed % 8 == 5 || stream.mode() != Mode::Long;
+        let registers = if legacy { 8 } else { 16 };
+        let small = seed.is_multiple_of(2);
+
+        self.operands.iter().enumerate().map(|(index, kind)| {
+            let number = (seed.wrapping_add(5 * index as u64) % registers) as u8;
+
+            let mut memory = |index: Option<Register>| {
+                let base = Reg64::from_number((seed.wrapping_mul(3).wrapping_add(1) % registers) as u8);
+                let displacement = match seed / 3 % 3 {
+                    0 => 0,
+                    1 => random() as i8 as i32,
+                    _ => random() as i32,
+                };
+
+                Operand::Memory(Memory { size: kind.memory_size(), base: Some(base), index: index.map(|index| (index, 1 << (seed / 4 % 4))), displacement, target: None })
+            };
+
+            match kind {
+                OperandKind::Reg8 if legacy && number >= 4 => Operand::Register(Register::Reg8([Reg8::Ah, Reg8::Ch, Reg8::Dh, Reg8::Bh][number as usize - 4])),
+                OperandKind::Reg8 => Operand::Register(Register::Reg8(Reg8::from_number(number))),
+                OperandKind::Reg16 => Operand::Register(Register::Reg16(Reg16::from_number(number))),
+                OperandKind::Reg32 => Operand::Register(Register::Reg32(Reg32::from_number(number))),
+                OperandKind::Reg64 => Operand::Register(Register::Reg64(Reg64::from_number(number))),
+                OperandKind::Xmm => Operand::Register(Register::Xmm(Xmm::from_number(number))),
+                OperandKind::Ymm => Operand::Register(Register::Ymm(Ymm::from_number(number))),
+                OperandKind::SegmentReg => Operand::Register(Register::Segment(SEGMENTS[number as usize % 6])),
+                OperandKind::WritableSegmentReg => Operand::Register(Register::Segment([SegmentReg::Es, SegmentReg::Ss, SegmentReg::Ds, SegmentReg::Fs, SegmentReg::Gs][number as usize % 5])),
+                OperandKind::Fs => Operand::Register(Register::Segment(SegmentReg::Fs)),
+                OperandKind::Gs => Operand::Register(Register::Segment(SegmentReg::Gs)),
+                OperandKind::Cl => Operand::Register(Register::Reg8(Reg8::Cl)),
+                OperandKind::Imm8 => Operand::Immediate(random() as u8 as i64),
+                OperandKind::Imm16 => Operand::Immediate(random() as u16 as i64),
+                OperandKind::Imm32 if small => Operand::Immediate(random() as i8 as u32 as i64),
+                OperandKind::Imm32 => Operand::Immediate(random() as u32 as i64),
+                OperandKind::Imm64 => Operand::Immediate(random() as i64),
+                OperandKind::SImm8 => Operand::Immediate(random() as i8 as i64),
+                OperandKind::SImm32 if small => Operand::Immediate(random() as i8 as i64),
+                OperandKind::SImm32 => Operand::Immediate(random() as i32 as i64),
+                OperandKind::Rounding => Operand::Immediate(0b1000 + (seed % 4) as i64),
+                OperandKind::Mem8 | OperandKind::Mem16 | OperandKind::Mem32 | OperandKind::Mem48 | OperandKind::Mem64 | OperandKind::Mem80
+                | OperandKind::Mem128 | OperandKind::Mem256 | OperandKind::Memory => {
+                    // `rsp` can't be an index.
+                    let index = (seed % 3 == 2).then(|| Reg64::from_number((seed.wrapping_mul(7).wrapping_add(2) % registers) as u8))
+                        .filter(|index| *index != Reg64::Rsp);
+                    memory(index.map(Register::Reg64))
+                },
+                OperandKind::VsibX => memory(Some(Register::Xmm(Xmm::from_number(number)))),
+                OperandKind::VsibY => memory(Some(Register::Ymm(Ymm::from_number(number)))),
+                OperandKind::LabelMem8 | OperandKind::LabelMem16 | OperandKind::LabelMem32 | OperandKind::LabelMem64 | OperandKind::LabelMem128
+                | OperandKind::LabelMem256 | OperandKind::LabelMemory => {
+                    let target = MemoryTarget::Label(stream.create_label_attached());
+                    Operand::Memory(Memory { size: kind.memory_size(), target: Some(target), ..Memory::default() })
+                },
+                OperandKind::SymbolMem64 => {
+                    let target = MemoryTarget::Symbol(stream.create_symbol(&format!("symbol{}", index)));
+                    Operand::Memory(Memory { size: kind.memory_size(), target: Some(target), ..Memory::default() })
+                },
+                // a local symbol, which `lea` reaches without going through the global offset table.
+                OperandKind::SymbolMemory => {
+                    let target = MemoryTarget::Symbol(stream.create_local_symbol(&format!("symbol{}", index)));
+                    Operand::Memory(Memory { target: Some(target), ..Memory::default() })
+                },
+                OperandKind::GotMem64 => {
+                    let target = MemoryTarget::Got(stream.create_symbol(&format!("symbol{}", index)));
+                    Operand::Memory(Memory { size: kind.memory_size(), target: Some(target), ..Memory::default() })
+                },
+                OperandKind::Label => Operand::Label(stream.create_label_attached()),
+                OperandKind::ShortLabel => Operand::ShortLabel(stream.create_label_attached()),
+                OperandKind::Rel32 if small => Operand::Relative(random() as i8 as i64),
+                OperandKind::Rel32 => Operand::Relative(random() as i32 as i64),
+                OperandKind::Symbol => Operand::Symbol(stream.create_symbol(&format!("symbol{}", index))),
+                OperandKind::FarPtr16 => Operand::FarPointer { segment: random() as u16, offset: random() as u16 as u32 },
+                OperandKind::FarPtr32 => Operand::FarPointer { segment: random() as u16, offset: random() as u32 },
+            }
+        }).collect()
+    }
+}
+
+/// The segment registers, in the order of their numbers.
+pub(crate) const SEGMENTS: [SegmentReg; 6] = [SegmentReg::Es, SegmentReg::Cs, SegmentReg::Ss, SegmentReg::Ds, SegmentReg::Fs, SegmentReg::Gs];
+
+/// An argument of the emitters, converted from an operand accepted by the kind of its parameter.
+trait FromOperand {
+    fn from_operand(operand: Operand) -> Self;
+}
+
+macro_rules! from_operand {
+    ($($type:ty: $operand:ident => $pattern:pat => $value:expr),* $(,)?) => {
+        $(
+            impl FromOperand for $type {
+                fn from_operand($operand: Operand) -> Self {
+                    match $operand {
+                        $pattern => $value,
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_operand! {
+    Reg8: operand => Operand::Register(Register::Reg8(reg)) => reg,
+    Reg16: operand => Operand::Register(Register::Reg16(reg)) => reg,
+    Reg32: operand => Operand::Register(Register::Reg32(reg)) => reg,
+    Reg64: operand => Operand::Register(Register::Reg64(reg)) => reg,
+    Xmm: operand => Operand::Register(Register::Xmm(reg)) => reg,
+    Ymm: operand => Operand::Register(Register::Ymm(reg)) => reg,
+    SegmentReg: operand => Operand::Register(Register::Segment(reg)) => reg,
+    LabelRef: operand => Operand::Label(label) | Operand::ShortLabel(label) | Operand::Memory(Memory { target: Some(MemoryTarget::Label(label)), .. }) => label,
+    SymRef: operand => Operand::Symbol(symbol) | Operand::Memory(Memory { target: Some(MemoryTarget::Symbol(symbol) | MemoryTarget::Got(symbol)), .. }) => symbol,
+    Mem: operand => Operand::Memory(memory) => memory.mem().unwrap(),
+    VsibMem<Xmm>: operand => Operand::Memory(memory) => memory.vsib_xmm().unwrap(),
+    VsibMem<Ymm>: operand => Operand::Memory(memory) => memory.vsib_ymm().unwrap(),
+    RoundingControl: operand => Operand::Immediate(value) => RoundingControl::from_bits(value as u8).unwrap(),
+    u8: operand => Operand::Immediate(value) => value as u8,
+    i8: operand => Operand::Immediate(value) => value as i8,
+    u16: operand => Operand::Immediate(value) => value as u16,
+    u32: operand => Operand::Immediate(value) => value as u32,
+    i32: operand => Operand::Immediate(value) | Operand::Relative(value) => value as i32,
+    u64: operand => Operand::Immediate(value) => value as u64,
+}
+
+/// A form of the mnemonic `$mnemonic` calling `$emitter` with the `$leading` arguments, then with its operands of the kinds `$kind`, each
+/// converted to the type of the parameter of the emitter.  The operands of the kinds `$implicit` follow, and aren't passed to the emitter.  The
+/// form is available in the modes `$mode`, by default in every mode.
+macro_rules! form {
+    (@modes $modes:expr, $mnemonic:literal, $emitter:ident[$($leading:expr),*]($($kind:ident),*; $($implicit:ident),*)) => {
+        InstructionForm {
+            mnemonic: $mnemonic,
+            operands: &[$(OperandKind::$kind,)* $(OperandKind::$implicit),*],
+            emitter: stringify!($emitter),
+            leading: &[$(stringify!($leading)),*],
+            modes: $modes,
+            emit: |stream, operands| {
+                #[allow(unused_mut, unused_variables)]
+                let mut operands = operands.iter().copied();
+                stream.$emitter($($leading,)* $({ let _ = OperandKind::$kind; FromOperand::from_operand(operands.next().unwrap()) }),*)
+            },
+        }
+    };
+    ($mnemonic:literal, $emitter:ident $([$($leading:expr),*])? ($($kind:ident),* $(; $($implicit:ident),*)?) $(in $($mode:ident),+)?) => {
+        form!(@modes form!(@list $($($mode),+)?), $mnemonic, $emitter[$($($leading),*)?]($($kind),*; $($($implicit),*)?))
+    };
+    (@list) => { &Mode::ALL };
+    (@list $($mode:ident),+) => { &[$(Mode::$mode),+] };
+}
+
+/// A form of the far `jmp` or `call` to a `segment:offset` pointer, `$emitter` taking the segment then the offset, of the type `$offset`.
+macro_rules! far_form {
+    ($mnemonic:literal, $emitter:ident($kind:ident as $offset:ty) in $($mode:ident),+) => {
+        InstructionForm {
+            mnemonic: $mnemonic,
+            operands: &[OperandKind::$kind],
+            emitter: stringify!($emitter),
+            leading: &[],
+            modes: &[$(Mode::$mode),+],
+            emit: |stream, operands| match operands {
+                [Operand::FarPointer { segment, offset }] => stream.$emitter(*segment, *offset as $offset),
+                _ => unreachable!(),
+            },
+        }
+    };
+}
+
+/// Every instruction form of the registry.  Forms are tried in order, so the shorter encodings come first.  The conditional jumps are listed
+/// by their canonical mnemonic, in the order of [`Condition::ALL`](crate::stream::Condition::ALL), and their other mnemonics are [`ALIASES`].
+/// The prefixes `lock`, `o32` and `a32` are forms of their own, with no operands, written before the instruction they prefix, see [`PREFIXES`].
+///
+/// # Example
+/// Each form emits its example operands in its first mode:
+/// ```
+/// use asmkit_x86_64::{features::CpuFeatures, registry::INSTRUCTIONS, stream::x86_64InstructionStream};
+///
+/// for form in INSTRUCTIONS {
+///     for seed in 0..16 {
+///         let mut stream = x86_64InstructionStream::new_with_mode(form.modes[0], CpuFeatures::ALL);
+///         let operands = form.example_operands(&mut stream, seed);
+///         assert!(form.accepts(&operands), "`{}` {:?}", form.emitter, operands);
+///         (form.emit)(&mut stream, &operands);
+///     }
+/// }
+/// ```
+///
+/// and the same bytes as a direct call of its emitter:
+/// ```
+/// use asmkit_core::InstructionStream;
+/// use asmkit_x86_64::{
+///     memory::{Mem, Scale, VsibMem},
+///     register::{Reg64, Reg8, Xmm, Ymm},
+///     registry::{forms, Memory, MemoryTarget, Operand, Register},
+///     sse41::RoundingControl,
+///     stream::{x86_64InstructionStream, Condition},
+/// };
+///
+/// let emit = |mnemonic: &str, operands: &dyn Fn(&mut x86_64InstructionStream) -> Vec<Operand>| {
+///     let mut stream = x86_64InstructionStream::new();
+///     let operands = operands(&mut stream);
+///     let form = forms(mnemonic).find(|form| form.accepts(&operands)).unwrap();
+///     (form.emit)(&mut stream, &operands);
+///     (form.emitter, stream.finish().emit())
+/// };
+///
+/// let direct = |call: &dyn Fn(&mut x86_64InstructionStream)| {
+///     let mut stream = x86_64InstructionStream::new();
+///     call(&mut stream);
+///     stream.finish().emit()
+/// };
+///
+/// let rax = Operand::Register(Register::Reg64(Reg64::Rax));
+/// assert_eq!(
+///     emit("shl", &|_| vec![rax, Operand::Register(Register::Reg8(Reg8::Cl))]),
+///     ("shl_reg64_cl", direct(&|stream| stream.shl_reg64_cl(Reg64::Rax))),
+/// );
+/// assert_eq!(
+///     emit("jz", &|stream| vec![Operand::ShortLabel(stream.create_label_attached())]),
+///     ("jcc_short_label", direct(&|stream| { let top = stream.create_label_attached(); stream.jcc_short_label(Condition::E, top) })),
+/// );
+///
+/// let far = Memory { size: Some(6), base: Some(Reg64::Rbx), ..Memory::default() };
+/// assert_eq!(
+///     emit("jmp far", &|_| vec![Operand::Memory(far)]),
+///     ("jmp_far_mem16_32", direct(&|stream| stream.jmp_far_mem16_32(Mem::new(Reg64::Rbx, 0)))),
+/// );
+/// assert_eq!(
+///     emit("movaps", &|stream| vec![
+///         Operand::Register(Register::Xmm(Xmm::Xmm1)),
+///         Operand::Memory(Memory { target: Some(MemoryTarget::Label(stream.create_label_attached())), ..Memory::default() }),
+///     ]),
+///     ("movaps_xmm_label", direct(&|stream| { let top = stream.create_label_attached(); stream.movaps_xmm_label(Xmm::Xmm1, top) })),
+/// );
+///
+/// let vsib = Memory { base: Some(Reg64::Rdi), index: Some((Register::Ymm(Ymm::Ymm2), 4)), displacement: 0x40, ..Memory::default() };
+/// assert_eq!(
+///     emit("vgatherdps", &|_| vec![Operand::Register(Register::Ymm(Ymm::Ymm0)), Operand::Memory(vsib), Operand::Register(Register::Ymm(Ymm::Ymm1))]),
+///     ("vgatherdps_ymm_vm32y_ymm", direct(&|stream| stream.vgatherdps_ymm_vm32y_ymm(Ymm::Ymm0, VsibMem::new(Reg64::Rdi, Ymm::Ymm2, Scale::X4, 0x40), Ymm::Ymm1))),
+/// );
+/// assert_eq!(
+///     emit("roundsd", &|_| vec![Operand::Register(Register::Xmm(Xmm::Xmm0)), Operand::Register(Register::Xmm(Xmm::Xmm3)), Operand::Immediate(9)]),
+///     ("roundsd_xmm_xmm", direct(&|stream| stream.roundsd_xmm_xmm(Xmm::Xmm0, Xmm::Xmm3, RoundingControl::Floor))),
+/// );
+/// ```
+pub static INSTRUCTIONS: &[InstructionForm] = &[
+    form!("mov", mov_reg8_reg8(Reg8, Reg8)),
+    form!("mov", mov_reg16_reg16(Reg16, Reg16)),
+    form!("mov", mov_reg32_reg32(Reg32, Reg32)),
+    form!("mov", mov_reg64_reg64(Reg64, Reg64)),
+    form!("mov", mov_reg8_imm8(Reg8, Imm8)),
+    form!("mov", mov_reg16_imm16(Reg16, Imm16)),
+    form!("mov", mov_reg32_imm32(Reg32, Imm32)),
+    form!("mov", mov_reg64_imm32(Reg64, SImm32)),
+    form!("mov", mov_reg64_imm64(Reg64, Imm64)),
+    form!("mov", mov_reg8_mem8(Reg8, Mem8)),
+    form!("mov", mov_reg16_mem16(Reg16, Mem16)),
+    form!("mov", mov_reg32_mem32(Reg32, Mem32)),
+    form!("mov", mov_reg64_mem64(Reg64, Mem64)),
+    form!("mov", mov_mem8_reg8(Mem8, Reg8)),
+    form!("mov", mov_mem16_reg16(Mem16, Reg16)),
+    form!("mov", mov_mem32_reg32(Mem32, Reg32)),
+    form!("mov", mov_mem64_reg64(Mem64, Reg64)),
+    form!("mov", mov_mem8_imm8(Mem8, Imm8)),
+    form!("mov", mov_mem16_imm16(Mem16, Imm16)),
+    form!("mov", mov_mem32_imm32(Mem32, Imm32)),
+    form!("mov", mov_mem64_imm32(Mem64, SImm32)),
+    form!("mov", mov_reg64_label(Reg64, LabelMem64)),
+    form!("mov", mov_reg64_got_symbol(Reg64, GotMem64)),
+    form!("mov", mov_label8_imm8(LabelMem8, Imm8)),
+    form!("mov", mov_label16_imm16(LabelMem16, Imm16)),
+    form!("mov", mov_label32_imm32(LabelMem32, Imm32)),
+    form!("mov", mov_label64_imm32(LabelMem64, SImm32)),
+    form!("mov", load_label_address16(Reg16, Label)),
+    form!("mov", mov_reg16_sreg(Reg16, SegmentReg)),
+    form!("mov", mov_reg64_sreg(Reg64, SegmentReg)),
+    form!("mov", mov_sreg_reg16(WritableSegmentReg, Reg16)),
+    form!("movzx", movzx_reg32_mem8(Reg32, Mem8)),
+    form!("movsxd", movsxd_reg64_reg32(Reg64, Reg32)),
+    form!("movsxd", movsxd_reg64_mem32(Reg64, Mem32)),
+    form!("lea", lea_reg64_mem(Reg64, Memory)),
+    form!("lea", lea_reg64_label(Reg64, LabelMemory)),
+    form!("lea", lea_reg64_symbol(Reg64, SymbolMemory)),
+    form!("xchg", xchg_reg64_reg64(Reg64, Reg64)),
+    form!("xchg", xchg_mem64_reg64(Mem64, Reg64)),
+    form!("xadd", xadd_mem64_reg64(Mem64, Reg64)),
+    form!("cmpxchg", cmpxchg_mem64_reg64(Mem64, Reg64)),
+    form!("lock", lock()),
+    form!("o32", o32()),
+    form!("a32", a32()),
+    form!("mfence", mfence()),
+    form!("lfence", lfence()),
+    form!("sfence", sfence()),
+    form!("push", push_reg16(Reg16)),
+    form!("push", push_reg32(Reg32) in Protected),
+    form!("push", push_reg64(Reg64) in Long),
+    form!("push", push_imm8(SImm8)),
+    form!("push", push_imm32(SImm32)),
+    form!("push", push_fs(; Fs)),
+    form!("push", push_gs(; Gs)),
+    form!("pop", pop_reg32(Reg32) in Protected),
+    form!("pop", pop_reg64(Reg64) in Long),
+    form!("inc", inc_reg32(Reg32)),
+    form!("dec", dec_reg32(Reg32)),
+    form!("add", add_reg32_imm32(Reg32, Imm32)),
+    form!("add", add_reg64_imm32(Reg64, SImm32)),
+    form!("add", add_reg64_reg64(Reg64, Reg64)),
+    form!("adc", adc_reg64_reg64(Reg64, Reg64)),
+    form!("sub", sub_reg32_imm32(Reg32, Imm32)),
+    form!("sub", sub_reg64_imm32(Reg64, SImm32)),
+    form!("sub", sub_reg32_reg32(Reg32, Reg32)),
+    form!("sub", sub_reg64_reg64(Reg64, Reg64)),
+    form!("or", or_mem64_imm8(Mem64, SImm8)),
+    form!("xor", xor_reg32_reg32(Reg32, Reg32)),
+    form!("shl", shl_reg64_imm8(Reg64, Imm8)),
+    form!("shl", shl_reg64_cl(Reg64; Cl)),
+    form!("shr", shr_reg64_cl(Reg64; Cl)),
+    form!("sar", sar_reg64_cl(Reg64; Cl)),
+    form!("shld", shld_reg64_reg64_imm8(Reg64, Reg64, Imm8)),
+    form!("mul", mul_wide(Reg64)),
+    form!("imul", imul_wide(Reg64)),
+    form!("cqo", cqo()),
+    form!("nop", nop[1]()),
+    form!("nop", nop[2]()),
+    form!("nop", nop[3]()),
+    form!("nop", nop[4]()),
+    form!("nop", nop[5]() in Protected, Long),
+    form!("nop", nop[6]() in Protected, Long),
+    form!("nop", nop[7]() in Protected, Long),
+    form!("nop", nop[8]() in Protected, Long),
+    form!("nop", nop[9]() in Protected, Long),
+    form!("call", call_label(Label)),
+    form!("call", call_symbol(Symbol)),
+    form!("call", call_reg32(Reg32) in Protected),
+    form!("call", call_reg64(Reg64) in Long),
+    form!("call", call_mem(Mem64) in Long),
+    form!("call", call_mem_label(LabelMem64)),
+    form!("call", call_mem_symbol(SymbolMem64)),
+    form!("cmp", cmp_reg64_imm32(Reg64, SImm32)),
+    form!("cmp", cmp_reg32_imm32(Reg32, Imm32)),
+    form!("cmp", cmp_reg64_reg64(Reg64, Reg64)),
+    form!("cmp", cmp_reg32_reg32(Reg32, Reg32)),
+    form!("test", test_reg64_reg64(Reg64, Reg64)),
+    form!("test", test_reg32_reg32(Reg32, Reg32)),
+    form!("test", test_reg8_reg8(Reg8, Reg8)),
+    form!("test", test_mem8_imm8(Mem8, Imm8)),
+    form!("jmp", jmp_rel32(Rel32)),
+    form!("jmp", jmp_label(Label)),
+    form!("jmp", jmp_short_label(ShortLabel)),
+    form!("jmp", jmp_symbol(Symbol)),
+    form!("jmp", jmp_reg32(Reg32) in Protected),
+    form!("jmp", jmp_reg64(Reg64) in Long),
+    form!("jmp", jmp_mem(Mem64) in Long),
+    form!("jmp", jmp_mem_label(LabelMem64)),
+    form!("jmp", jmp_mem_symbol(SymbolMem64)),
+    form!("jo", jcc_label[crate::stream::Condition::O](Label)),
+    form!("jno", jcc_label[crate::stream::Condition::No](Label)),
+    form!("jb", jcc_label[crate::stream::Condition::B](Label)),
+    form!("jae", jcc_label[crate::stream::Condition::Ae](Label)),
+    form!("je", jcc_label[crate::stream::Condition::E](Label)),
+    form!("jne", jcc_label[crate::stream::Condition::Ne](Label)),
+    form!("jbe", jcc_label[crate::stream::Condition::Be](Label)),
+    form!("ja", jcc_label[crate::stream::Condition::A](Label)),
+    form!("js", jcc_label[crate::stream::Condition::S](Label)),
+    form!("jns", jcc_label[crate::stream::Condition::Ns](Label)),
+    form!("jp", jcc_label[crate::stream::Condition::P](Label)),
+    form!("jnp", jcc_label[crate::stream::Condition::Np](Label)),
+    form!("jl", jcc_label[crate::stream::Condition::L](Label)),
+    form!("jge", jcc_label[crate::stream::Condition::Ge](Label)),
+    form!("jle", jcc_label[crate::stream::Condition::Le](Label)),
+    form!("jg", jcc_label[crate::stream::Condition::G](Label)),
+    form!("jo", jcc_short_label[crate::stream::Condition::O](ShortLabel)),
+    form!("jno", jcc_short_label[crate::stream::Condition::No](ShortLabel)),
+    form!("jb", jcc_short_label[crate::stream::Condition::B](ShortLabel)),
+    form!("jae", jcc_short_label[crate::stream::Condition::Ae](ShortLabel)),
+    form!("je", jcc_short_label[crate::stream::Condition::E](ShortLabel)),
+    form!("jne", jcc_short_label[crate::stream::Condition::Ne](ShortLabel)),
+    form!("jbe", jcc_short_label[crate::stream::Condition::Be](ShortLabel)),
+    form!("ja", jcc_short_label[crate::stream::Condition::A](ShortLabel)),
+    form!("js", jcc_short_label[crate::stream::Condition::S](ShortLabel)),
+    form!("jns", jcc_short_label[crate::stream::Condition::Ns](ShortLabel)),
+    form!("jp", jcc_short_label[crate::stream::Condition::P](ShortLabel)),
+    form!("jnp", jcc_short_label[crate::stream::Condition::Np](ShortLabel)),
+    form!("jl", jcc_short_label[crate::stream::Condition::L](ShortLabel)),
+    form!("jge", jcc_short_label[crate::stream::Condition::Ge](ShortLabel)),
+    form!("jle", jcc_short_label[crate::stream::Condition::Le](ShortLabel)),
+    form!("jg", jcc_short_label[crate::stream::Condition::G](ShortLabel)),
+    form!("loop", loop_label(Label)),
+    form!("loope", loope_label(Label)),
+    form!("loopne", loopne_label(Label)),
+    form!("jrcxz", jrcxz_label(Label)),
+    form!("ret", ret_near()),
+    form!("ret", ret_near_imm16(Imm16)),
+    form!("retf", ret_far()),
+    form!("retf", ret_far_imm16(Imm16)),
+    form!("retfw", ret_far16()),
+    form!("retfw", ret_far16_imm16(Imm16)),
+    form!("retfq", ret_far64() in Long),
+    form!("retfq", ret_far64_imm16(Imm16) in Long),
+    far_form!("jmp far", jmp_far_ptr16_16(FarPtr16 as u16) in Real),
+    far_form!("jmp far", jmp_far_ptr16_32(FarPtr32 as u32) in Protected, Real),
+    form!("jmp far", jmp_far_mem16_16(Mem32)),
+    form!("jmp far", jmp_far_mem16_32(Mem48)),
+    form!("jmp far", jmp_far_mem16_64(Mem80) in Long),
+    far_form!("call far", call_far_ptr16_16(FarPtr16 as u16) in Real),
+    far_form!("call far", call_far_ptr16_32(FarPtr32 as u32) in Protected, Real),
+    form!("call far", call_far_mem16_16(Mem32)),
+    form!("call far", call_far_mem16_32(Mem48)),
+    form!("call far", call_far_mem16_64(Mem80) in Long),
+    form!("swapgs", swapgs()),
+    form!("cld", cld()),
+    form!("int3", int3()),
+    form!("int", int_imm8(Imm8)),
+    form!("hlt", hlt()),
+    form!("cli", cli()),
+    form!("sti", sti()),
+    form!("cpuid", cpuid()),
+    form!("syscall", syscall() in Long),
+    form!("ptwrite", ptwrite_reg64(Reg64)),
+    form!("ptwrite", ptwrite_mem64(Mem64)),
+    form!("lfs", lfs_reg32_mem(Reg32, Mem48)),
+    form!("lgs", lgs_reg32_mem(Reg32, Mem48)),
+    form!("lss", lss_reg32_mem(Reg32, Mem48)),
+    form!("iret", iret()),
+    form!("iretd", iretd()),
+    form!("iretq", iretq() in Long),
+    form!("rep movsb", rep_movsb()),
+    form!("rep stosb", rep_stosb()),
+    form!("repe cmpsb", repe_cmpsb()),
+    form!("lodsb", lodsb()),
+    form!("lodsw", lodsw()),
+    form!("stosb", stosb()),
+    form!("stosw", stosw()),
+    form!("movsb", movsb()),
+    form!("movsw", movsw()),
+    form!("umonitor", umonitor_reg64(Reg64)),
+    form!("umwait", umwait_reg32(Reg32)),
+    form!("tpause", tpause_reg32(Reg32)),
+    form!("monitor", monitor()),
+    form!("mwait", mwait()),
+    form!("xbegin", xbegin_label(Label)),
+    form!("xend", xend()),
+    form!("xabort", xabort_imm8(Imm8)),
+    form!("xtest", xtest()),
+    form!("movdir64b", movdir64b_reg64_mem(Reg64, Memory)),
+    form!("movdiri", movdiri_mem_reg32(Mem32, Reg32)),
+    form!("movdiri", movdiri_mem_reg64(Mem64, Reg64)),
+    form!("enqcmd", enqcmd_reg64_mem(Reg64, Memory)),
+    form!("enqcmds", enqcmds_reg64_mem(Reg64, Memory)),
+    form!("serialize", serialize()),
+    form!("fxsave", fxsave_mem(Memory)),
+    form!("fxrstor", fxrstor_mem(Memory)),
+    form!("fxsave64", fxsave64_mem(Memory)),
+    form!("fxrstor64", fxrstor64_mem(Memory)),
+    form!("ldmxcsr", ldmxcsr_mem(Mem32)),
+    form!("stmxcsr", stmxcsr_mem(Mem32)),
+    form!("xsave", xsave_mem(Memory)),
+    form!("xrstor", xrstor_mem(Memory)),
+    form!("xsaveopt", xsaveopt_mem(Memory)),
+    form!("xsave64", xsave64_mem(Memory)),
+    form!("xrstor64", xrstor64_mem(Memory)),
+    form!("xsaveopt64", xsaveopt64_mem(Memory)),
+    form!("movd", movd_xmm_reg32(Xmm, Reg32)),
+    form!("movd", movd_reg32_xmm(Reg32, Xmm)),
+    form!("movq", movq_xmm_reg64(Xmm, Reg64)),
+    form!("movq", movq_reg64_xmm(Reg64, Xmm)),
+    form!("movq", movq_xmm_xmm(Xmm, Xmm)),
+    form!("movq", movq_xmm_xmm_mr(Xmm, Xmm)),
+    form!("movq", movq_xmm_mem(Xmm, Mem64)),
+    form!("movq", movq_mem_xmm(Mem64, Xmm)),
+    form!("movdqu", movdqu_xmm_mem(Xmm, Mem128)),
+    form!("movdqu", movdqu_mem_xmm(Mem128, Xmm)),
+    form!("movdqa", movdqa_xmm_xmm(Xmm, Xmm)),
+    form!("movdqa", movdqa_xmm_mem(Xmm, Mem128)),
+    form!("movdqa", movdqa_mem_xmm(Mem128, Xmm)),
+    form!("movdqa", movdqa_xmm_label(Xmm, LabelMem128)),
+    form!("movaps", movaps_xmm_label(Xmm, LabelMem128)),
+    form!("vmovaps", vmovaps_ymm_label(Ymm, LabelMem256)),
+    form!("vmovdqa", vmovdqa_ymm_label(Ymm, LabelMem256)),
+    form!("movss", movss_xmm_xmm(Xmm, Xmm)),
+    form!("movss", movss_xmm_mem(Xmm, Mem32)),
+    form!("movss", movss_mem_xmm(Mem32, Xmm)),
+    form!("movsd", movsd_xmm_xmm(Xmm, Xmm)),
+    form!("movsd", movsd_xmm_mem(Xmm, Mem64)),
+    form!("movsd", movsd_mem_xmm(Mem64, Xmm)),
+    form!("movaps", movaps_xmm_xmm(Xmm, Xmm)),
+    form!("movaps", movaps_xmm_mem(Xmm, Mem128)),
+    form!("movaps", movaps_mem_xmm(Mem128, Xmm)),
+    form!("movapd", movapd_xmm_xmm(Xmm, Xmm)),
+    form!("movapd", movapd_xmm_mem(Xmm, Mem128)),
+    form!("movapd", movapd_mem_xmm(Mem128, Xmm)),
+    form!("movups", movups_xmm_xmm(Xmm, Xmm)),
+    form!("movups", movups_xmm_mem(Xmm, Mem128)),
+    form!("movups", movups_mem_xmm(Mem128, Xmm)),
+    form!("movupd", movupd_xmm_xmm(Xmm, Xmm)),
+    form!("movupd", movupd_xmm_mem(Xmm, Mem128)),
+    form!("movupd", movupd_mem_xmm(Mem128, Xmm)),
+    form!("addss", addss_xmm_xmm(Xmm, Xmm)),
+    form!("addss", addss_xmm_mem(Xmm, Mem32)),
+    form!("addsd", addsd_xmm_xmm(Xmm, Xmm)),
+    form!("addsd", addsd_xmm_mem(Xmm, Mem64)),
+    form!("addps", addps_xmm_xmm(Xmm, Xmm)),
+    form!("addps", addps_xmm_mem(Xmm, Mem128)),
+    form!("addpd", addpd_xmm_xmm(Xmm, Xmm)),
+    form!("addpd", addpd_xmm_mem(Xmm, Mem128)),
+    form!("subss", subss_xmm_xmm(Xmm, Xmm)),
+    form!("subss", subss_xmm_mem(Xmm, Mem32)),
+    form!("subsd", subsd_xmm_xmm(Xmm, Xmm)),
+    form!("subsd", subsd_xmm_mem(Xmm, Mem64)),
+    form!("subps", subps_xmm_xmm(Xmm, Xmm)),
+    form!("subps", subps_xmm_mem(Xmm, Mem128)),
+    form!("subpd", subpd_xmm_xmm(Xmm, Xmm)),
+    form!("subpd", subpd_xmm_mem(Xmm, Mem128)),
+    form!("mulss", mulss_xmm_xmm(Xmm, Xmm)),
+    form!("mulss", mulss_xmm_mem(Xmm, Mem32)),
+    form!("mulsd", mulsd_xmm_xmm(Xmm, Xmm)),
+    form!("mulsd", mulsd_xmm_mem(Xmm, Mem64)),
+    form!("mulps", mulps_xmm_xmm(Xmm, Xmm)),
+    form!("mulps", mulps_xmm_mem(Xmm, Mem128)),
+    form!("mulpd", mulpd_xmm_xmm(Xmm, Xmm)),
+    form!("mulpd", mulpd_xmm_mem(Xmm, Mem128)),
+    form!("divss", divss_xmm_xmm(Xmm, Xmm)),
+    form!("divss", divss_xmm_mem(Xmm, Mem32)),
+    form!("divsd", divsd_xmm_xmm(Xmm, Xmm)),
+    form!("divsd", divsd_xmm_mem(Xmm, Mem64)),
+    form!("divps", divps_xmm_xmm(Xmm, Xmm)),
+    form!("divps", divps_xmm_mem(Xmm, Mem128)),
+    form!("divpd", divpd_xmm_xmm(Xmm, Xmm)),
+    form!("divpd", divpd_xmm_mem(Xmm, Mem128)),
+    form!("minss", minss_xmm_xmm(Xmm, Xmm)),
+    form!("minss", minss_xmm_mem(Xmm, Mem32)),
+    form!("minsd", minsd_xmm_xmm(Xmm, Xmm)),
+    form!("minsd", minsd_xmm_mem(Xmm, Mem64)),
+    form!("minps", minps_xmm_xmm(Xmm, Xmm)),
+    form!("minps", minps_xmm_mem(Xmm, Mem128)),
+    form!("minpd", minpd_xmm_xmm(Xmm, Xmm)),
+    form!("minpd", minpd_xmm_mem(Xmm, Mem128)),
+    form!("maxss", maxss_xmm_xmm(Xmm, Xmm)),
+    form!("maxss", maxss_xmm_mem(Xmm, Mem32)),
+    form!("maxsd", maxsd_xmm_xmm(Xmm, Xmm)),
+    form!("maxsd", maxsd_xmm_mem(Xmm, Mem64)),
+    form!("maxps", maxps_xmm_xmm(Xmm, Xmm)),
+    form!("maxps", maxps_xmm_mem(Xmm, Mem128)),
+    form!("maxpd", maxpd_xmm_xmm(Xmm, Xmm)),
+    form!("maxpd", maxpd_xmm_mem(Xmm, Mem128)),
+    form!("sqrtss", sqrtss_xmm_xmm(Xmm, Xmm)),
+    form!("sqrtss", sqrtss_xmm_mem(Xmm, Mem32)),
+    form!("sqrtsd", sqrtsd_xmm_xmm(Xmm, Xmm)),
+    form!("sqrtsd", sqrtsd_xmm_mem(Xmm, Mem64)),
+    form!("sqrtps", sqrtps_xmm_xmm(Xmm, Xmm)),
+    form!("sqrtps", sqrtps_xmm_mem(Xmm, Mem128)),
+    form!("sqrtpd", sqrtpd_xmm_xmm(Xmm, Xmm)),
+    form!("sqrtpd", sqrtpd_xmm_mem(Xmm, Mem128)),
+    form!("andps", andps_xmm_xmm(Xmm, Xmm)),
+    form!("andps", andps_xmm_mem(Xmm, Mem128)),
+    form!("andpd", andpd_xmm_xmm(Xmm, Xmm)),
+    form!("andpd", andpd_xmm_mem(Xmm, Mem128)),
+    form!("andnps", andnps_xmm_xmm(Xmm, Xmm)),
+    form!("andnps", andnps_xmm_mem(Xmm, Mem128)),
+    form!("andnpd", andnpd_xmm_xmm(Xmm, Xmm)),
+    form!("andnpd", andnpd_xmm_mem(Xmm, Mem128)),
+    form!("orps", orps_xmm_xmm(Xmm, Xmm)),
+    form!("orps", orps_xmm_mem(Xmm, Mem128)),
+    form!("orpd", orpd_xmm_xmm(Xmm, Xmm)),
+    form!("orpd", orpd_xmm_mem(Xmm, Mem128)),
+    form!("xorps", xorps_xmm_xmm(Xmm, Xmm)),
+    form!("xorps", xorps_xmm_mem(Xmm, Mem128)),
+    form!("xorpd", xorpd_xmm_xmm(Xmm, Xmm)),
+    form!("xorpd", xorpd_xmm_mem(Xmm, Mem128)),
+    form!("ucomiss", ucomiss_xmm_xmm(Xmm, Xmm)),
+    form!("ucomiss", ucomiss_xmm_mem(Xmm, Mem32)),
+    form!("ucomisd", ucomisd_xmm_xmm(Xmm, Xmm)),
+    form!("ucomisd", ucomisd_xmm_mem(Xmm, Mem64)),
+    form!("roundps", roundps_xmm_xmm(Xmm, Xmm, Rounding)),
+    form!("roundps", roundps_xmm_mem(Xmm, Mem128, Rounding)),
+    form!("roundpd", roundpd_xmm_xmm(Xmm, Xmm, Rounding)),
+    form!("roundpd", roundpd_xmm_mem(Xmm, Mem128, Rounding)),
+    form!("roundss", roundss_xmm_xmm(Xmm, Xmm, Rounding)),
+    form!("roundss", roundss_xmm_mem(Xmm, Mem32, Rounding)),
+    form!("roundsd", roundsd_xmm_xmm(Xmm, Xmm, Rounding)),
+    form!("roundsd", roundsd_xmm_mem(Xmm, Mem64, Rounding)),
+    form!("blendps", blendps_xmm_xmm_imm8(Xmm, Xmm, Imm8)),
+    form!("blendps", blendps_xmm_mem_imm8(Xmm, Mem128, Imm8)),
+    form!("blendpd", blendpd_xmm_xmm_imm8(Xmm, Xmm, Imm8)),
+    form!("blendpd", blendpd_xmm_mem_imm8(Xmm, Mem128, Imm8)),
+    form!("pblendw", pblendw_xmm_xmm_imm8(Xmm, Xmm, Imm8)),
+    form!("pblendw", pblendw_xmm_mem_imm8(Xmm, Mem128, Imm8)),
+    form!("blendvps", blendvps_xmm_xmm(Xmm, Xmm)),
+    form!("blendvps", blendvps_xmm_mem(Xmm, Mem128)),
+    form!("blendvpd", blendvpd_xmm_xmm(Xmm, Xmm)),
+    form!("blendvpd", blendvpd_xmm_mem(Xmm, Mem128)),
+    form!("pblendvb", pblendvb_xmm_xmm(Xmm, Xmm)),
+    form!("pblendvb", pblendvb_xmm_mem(Xmm, Mem128)),
+    form!("ptest", ptest_xmm_xmm(Xmm, Xmm)),
+    form!("ptest", ptest_xmm_mem(Xmm, Mem128)),
+    form!("pmulld", pmulld_xmm_xmm(Xmm, Xmm)),
+    form!("pmulld", pmulld_xmm_mem(Xmm, Mem128)),
+    form!("pminsd", pminsd_xmm_xmm(Xmm, Xmm)),
+    form!("pminsd", pminsd_xmm_mem(Xmm, Mem128)),
+    form!("pmaxsd", pmaxsd_xmm_xmm(Xmm, Xmm)),
+    form!("pmaxsd", pmaxsd_xmm_mem(Xmm, Mem128)),
+    form!("pminud", pminud_xmm_xmm(Xmm, Xmm)),
+    form!("pminud", pminud_xmm_mem(Xmm, Mem128)),
+    form!("pmaxud", pmaxud_xmm_xmm(Xmm, Xmm)),
+    form!("pmaxud", pmaxud_xmm_mem(Xmm, Mem128)),
+    form!("packusdw", packusdw_xmm_xmm(Xmm, Xmm)),
+    form!("packusdw", packusdw_xmm_mem(Xmm, Mem128)),
+    form!("movnti", movnti_mem32_reg32(Mem32, Reg32)),
+    form!("movnti", movnti_mem64_reg64(Mem64, Reg64)),
+    form!("movntdq", movntdq_mem_xmm(Mem128, Xmm)),
+    form!("movntps", movntps_mem_xmm(Mem128, Xmm)),
+    form!("movntpd", movntpd_mem_xmm(Mem128, Xmm)),
+    form!("movntdqa", movntdqa_xmm_mem(Xmm, Mem128)),
+    form!("maskmovdqu", maskmovdqu_xmm_xmm(Xmm, Xmm)),
+    form!("aesenc", aesenc_xmm_xmm(Xmm, Xmm)),
+    form!("aesenclast", aesenclast_xmm_xmm(Xmm, Xmm)),
+    form!("aesdec", aesdec_xmm_xmm(Xmm, Xmm)),
+    form!("aesdeclast", aesdeclast_xmm_xmm(Xmm, Xmm)),
+    form!("aesimc", aesimc_xmm_xmm(Xmm, Xmm)),
+    form!("aeskeygenassist", aeskeygenassist_xmm_xmm_imm8(Xmm, Xmm, Imm8)),
+    form!("pclmulqdq", pclmulqdq_xmm_xmm_imm8(Xmm, Xmm, Imm8)),
+    form!("vaddps", vaddps_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vaddps", vaddps_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vaddpd", vaddpd_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vaddpd", vaddpd_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vsubps", vsubps_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vsubps", vsubps_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vsubpd", vsubpd_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vsubpd", vsubpd_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vmulps", vmulps_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vmulps", vmulps_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vmulpd", vmulpd_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vmulpd", vmulpd_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vdivps", vdivps_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vdivps", vdivps_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vdivpd", vdivpd_xmm_xmm_xmm(Xmm, Xmm, Xmm)),
+    form!("vdivpd", vdivpd_ymm_ymm_ymm(Ymm, Ymm, Ymm)),
+    form!("vmovdqu", vmovdqu_ymm_mem(Ymm, Mem256)),
+    form!("vmovdqu", vmovdqu_mem_ymm(Mem256, Ymm)),
+    form!("vzeroupper", vzeroupper()),
+    form!("vgatherdps", vgatherdps_xmm_vm32x_xmm(Xmm, VsibX, Xmm)),
+    form!("vgatherdps", vgatherdps_ymm_vm32y_ymm(Ymm, VsibY, Ymm)),
+    form!("vgatherqps", vgatherqps_xmm_vm64x_xmm(Xmm, VsibX, Xmm)),
+    form!("vgatherqps", vgatherqps_xmm_vm64y_xmm(Xmm, VsibY, Xmm)),
+    form!("vgatherdpd", vgatherdpd_xmm_vm32x_xmm(Xmm, VsibX, Xmm)),
+    form!("vgatherdpd", vgatherdpd_ymm_vm32x_ymm(Ymm, VsibX, Ymm)),
+    form!("vgatherqpd", vgatherqpd_xmm_vm64x_xmm(Xmm, VsibX, Xmm)),
+    form!("vgatherqpd", vgatherqpd_ymm_vm64y_ymm(Ymm, VsibY, Ymm)),
+    form!("vpgatherdd", vpgatherdd_xmm_vm32x_xmm(Xmm, VsibX, Xmm)),
+    form!("vpgatherdd", vpgatherdd_ymm_vm32y_ymm(Ymm, VsibY, Ymm)),
+    form!("vpgatherqd", vpgatherqd_xmm_vm64x_xmm(Xmm, VsibX, Xmm)),
+    form!("vpgatherqd", vpgatherqd_xmm_vm64y_xmm(Xmm, VsibY, Xmm)),
+    form!("vpgatherdq", vpgatherdq_xmm_vm32x_xmm(Xmm, VsibX, Xmm)),
+    form!("vpgatherdq", vpgatherdq_ymm_vm32x_ymm(Ymm, VsibX, Ymm)),
+    form!("vpgatherqq", vpgatherqq_xmm_vm64x_xmm(Xmm, VsibX, Xmm)),
+    form!("vpgatherqq", vpgatherqq_ymm_vm64y_ymm(Ymm, VsibY, Ymm)),
+];
+
+/// The other mnemonics of the instructions of the registry, each with its canonical mnemonic, such as `jz` for `je`.
+pub static ALIASES: &[(&str, &str)] = &[
+    ("jc", "jb"), ("jnae", "jb"), ("jnc", "jae"), ("jnb", "jae"), ("jz", "je"), ("jnz", "jne"), ("jna", "jbe"), ("jnbe", "ja"),
+    ("jpe", "jp"), ("jpo", "jnp"), ("jnge", "jl"), ("jnl", "jge"), ("jng", "jle"), ("jnle", "jg"),
+];
+
+/// The prefixes of the registry, which the text assembler accepts before an instruction on the same line, such as `lock xadd [rdi], rax`.
+pub static PREFIXES: &[&str] = &["lock", "o32", "a32"];
+
+/// The forms of the instruction `mnemonic`, or of the instruction it is an [alias](ALIASES) of, in the order they are tried.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::registry::forms;
+///
+/// assert_eq!(forms("jnz").map(|form| form.emitter).collect::<Vec<_>>(), ["jcc_label", "jcc_short_label"]);
+/// assert_eq!(forms("jnz").map(|form| form.mnemonic).collect::<Vec<_>>(), ["jne", "jne"]);
+/// assert_eq!(forms("mov").next().unwrap().emitter, "mov_reg8_reg8");
+/// assert_eq!(forms("frobnicate").count(), 0);
+/// ```
+pub fn forms(mnemonic: &str) -> impl DoubleEndedIterator<Item = &'static InstructionForm> + '_ {
+    let mnemonic = ALIASES.iter().find(|(alias, _)| *alias == mnemonic).map_or(mnemonic, |(_, canonical)| canonical);
+    INSTRUCTIONS.iter().filter(move |form| form.mnemonic == mnemonic)
+}
@@ -0,0 +1,121 @@
+//! The restricted transactional memory instructions.
+//!
+//! [`x86_64InstructionStream::xbegin_label`] starts a transaction, whose stores only become visible once
+//! [`x86_64InstructionStream::xend`] commits it.  If the transaction aborts, on a conflict or through
+//! [`x86_64InstructionStream::xabort_imm8`], its stores are discarded and execution resumes at the abort handler with the abort status in `eax`.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let abort = stream.create_label();
+//!
+//! stream.xbegin_label(abort);
+//! stream.add_reg64_reg64(Reg64::Rdi, Reg64::Rsi);
+//! stream.xend();
+//! stream.attach_label(abort);
+//! stream.xtest();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0xc7, 0xf8, 0x06, 0x00, 0x00, 0x00, // xbegin abort
+//!     0x48, 0x01, 0xf7,                   // add rdi, rsi
+//!     0x0f, 0x01, 0xd5,                   // xend
+//!                                         // abort:
+//!     0x0f, 0x01, 0xd6,                   // xtest
+//! ]);
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, entity::LabelRef, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    features::CpuFeatures,
+    register::Reg64,
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, FixupKind},
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Start a transaction, resuming at *label* if it aborts.  The displacement of the label is measured from the end of the 6-byte instruction.
+    ///
+    /// # Panics
+    /// Panics if RTM isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let retry = stream.create_label_attached();
+    ///
+    /// stream.xabort_imm8(0xff);
+    /// stream.xbegin_label(retry);
+    ///
+    /// // the xbegin at 0x3 ends at 0x9, 9 bytes past the label.
+    /// assert_eq!(stream.finish().emit(), [
+    ///                                         // retry:
+    ///     0xc6, 0xf8, 0xff,                   // xabort 0xff
+    ///     0xc7, 0xf8, 0xf7, 0xff, 0xff, 0xff, // xbegin retry
+    /// ]);
+    /// ```
+    pub fn xbegin_label(&mut self, label: LabelRef) {
+        self.require_feature(CpuFeatures::RTM, "xbegin");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xc7, 0xf8]);
+        self.write_label(label, FixupKind::Rel32);
+        // an abort resumes at `label` with its status in `eax`, and the register is left unchanged otherwise.
+        self.record_registers(RegSet::of(Reg64::Rax), RegSet::of(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Commit the current transaction.
+    ///
+    /// # Panics
+    /// Panics if RTM isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn xend(&mut self) {
+        self.require_feature(CpuFeatures::RTM, "xend");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xd5]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Abort the current transaction, passing *imm8* to the abort handler in bits 24 to 31 of `eax`.
+    ///
+    /// # Panics
+    /// Panics if RTM isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn xabort_imm8(&mut self, imm8: u8) {
+        self.require_feature(CpuFeatures::RTM, "xabort");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xc6, 0xf8, imm8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Clear the zero flag if a transaction is in progress, set it otherwise.
+    ///
+    /// # Panics
+    /// Panics if RTM isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{features::CpuFeatures, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::ALL.without(CpuFeatures::RTM));
+    /// stream.xtest();
+    /// ```
+    pub fn xtest(&mut self) {
+        self.require_feature(CpuFeatures::RTM, "xtest");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xd6]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+}
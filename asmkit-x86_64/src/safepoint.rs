@@ -0,0 +1,102 @@
+//! Safepoints, recording which frame slots and registers hold the managed pointers of a garbage collected language, see the
+//! [`stackmap`](asmkit_core::stackmap) module of `asmkit-core`.
+//!
+//! [`record_safepoint`](x86_64InstructionStream::record_safepoint) records a stack map at the current offset, and
+//! [`intrinsic_call`](x86_64InstructionStream::intrinsic_call) records one right after a call, at the return address the collector finds on the
+//! stack.  The registers are numbered as they are encoded, `rax` being 0 and `r15` 15, and the frame slots are addressed from the base register
+//! of their memory operand, `rbp` if the frame uses a frame pointer and `rsp` otherwise.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{stackmap::{StackMapLocation, StackMaps}, InstructionStream};
+//! use asmkit_x86_64::{frame::FrameBuilder, register::Reg64, safepoint::StackMapEntry, stream::{CallTarget, x86_64InstructionStream}};
+//!
+//! let mut builder = FrameBuilder::new();
+//! let object = builder.alloc_spill_64();
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let collect = stream.create_symbol("collect");
+//! stream.intrinsic_prologue(builder.freeze());
+//! stream.mov_slot_reg64(object, Reg64::Rdi);
+//! stream.intrinsic_call(CallTarget::Symbol(collect), Some(&[StackMapEntry::FrameSlot(object), StackMapEntry::Register(Reg64::Rbx)]));
+//! stream.intrinsic_epilogue();
+//! stream.ret_near();
+//! let product = stream.finish();
+//!
+//! // the stack map is recorded at the return address, the end of the call.
+//! let call = product.call_sites()[0];
+//! assert_eq!(product.stack_maps().maps()[0].offset, call.offset + call.len);
+//! assert_eq!(product.stack_maps().lookup(call.offset + call.len), Some(&[
+//!     StackMapLocation::Indirect { base: 5, offset: -8 }, // [rbp - 8]
+//!     StackMapLocation::Register(3),                      // rbx
+//! ][..]));
+//!
+//! // the serialized table reads back the same.
+//! assert_eq!(StackMaps::from_bytes(&product.stack_maps().to_bytes()).as_ref(), Ok(product.stack_maps()));
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, stackmap::StackMapLocation};
+
+use crate::{frame::SlotRef, register::Reg64, stream::{x86_64InstructionStream, CallTarget}};
+
+/// A location holding a live managed pointer at a safepoint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackMapEntry {
+    /// A slot of the current stack frame.
+    FrameSlot(SlotRef),
+
+    /// A register.
+    Register(Reg64),
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Records that the managed pointers are held in `live` at the current offset, replacing the safepoint already recorded there, if any.
+    ///
+    /// # Panics
+    /// Panics if `live` holds a frame slot and no stack frame was set up, or the frame has no frame pointer and registers are saved by
+    /// [`x86_64InstructionStream::save_caller_saved`], moving `rsp` away from the slots.
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{callconv::CallConv, frame::FrameBuilder, safepoint::StackMapEntry, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::without_frame_pointer();
+    /// let object = builder.alloc_spill_64();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.save_caller_saved(CallConv::SysV, false);
+    /// stream.record_safepoint(&[StackMapEntry::FrameSlot(object)]);
+    /// ```
+    pub fn record_safepoint(&mut self, live: &[StackMapEntry]) {
+        let live = live.iter().map(|entry| match *entry {
+            StackMapEntry::Register(reg) => StackMapLocation::Register(reg.offset()),
+            StackMapEntry::FrameSlot(slot) => {
+                let mem = self.frame().expect("no stack frame was set up").slot(slot);
+                assert!(mem.base != Reg64::Rsp || *self.save_depth() == 0, "a frame slot addressed from `rsp` can't be recorded while registers are saved");
+
+                StackMapLocation::Indirect { base: mem.base.offset(), offset: mem.displacement }
+            },
+        }).collect();
+
+        let offset = self.code().len();
+        self.stack_maps().record(offset, live);
+    }
+
+    /// Calls `target`, recording the managed pointers held in `live`, if any, as a safepoint at the return address.
+    ///
+    /// # Panics
+    /// Panics if `live` can't be recorded, see [`x86_64InstructionStream::record_safepoint`].
+    pub fn intrinsic_call(&mut self, target: CallTarget, live: Option<&[StackMapEntry]>) {
+        match target {
+            CallTarget::Label(label) => self.call_label(label),
+            CallTarget::Symbol(symbol) => self.call_symbol(symbol),
+            CallTarget::Reg(reg) => self.call_reg64(reg),
+            CallTarget::Mem(mem) => self.call_mem(mem),
+        }
+
+        if let Some(live) = live {
+            self.record_safepoint(live);
+        }
+    }
+}
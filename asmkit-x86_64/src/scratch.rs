@@ -0,0 +1,141 @@
+//! A pool of scratch registers, handing out any free register outside of a set of operands.
+//!
+//! A [`ScratchPool`] is created with an ordered list of candidates, and [`ScratchPool::acquire`] returns the first free candidate which isn't
+//! excluded as a [`ScratchReg`] guard, giving the register back to the pool when dropped.  The emitters needing a scratch register take it from a
+//! pool through their `_with_pool` variants, such as
+//! [`intrinsic_memcpy_with_pool`](crate::stream::x86_64InstructionStream::intrinsic_memcpy_with_pool), so they only clobber the registers the
+//! caller gave up.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::Reg64, scratch::ScratchPool, stream::x86_64InstructionStream};
+//!
+//! let pool = ScratchPool::new(&[Reg64::Rax, Reg64::Rcx, Reg64::Rdx]);
+//!
+//! // nested acquisitions get distinct registers, skipping the excluded ones.
+//! let a = pool.acquire(&[]).unwrap();
+//! let b = pool.acquire(&[Reg64::Rcx]).unwrap();
+//! assert_eq!((*a, *b), (Reg64::Rax, Reg64::Rdx));
+//!
+//! // the pool is exhausted rather than handing out a register twice.
+//! assert!(pool.acquire(&[Reg64::Rcx]).is_none());
+//! let c = pool.acquire(&[]).unwrap();
+//! assert_eq!(*c, Reg64::Rcx);
+//! assert!(pool.acquire(&[]).is_none());
+//!
+//! // a dropped guard gives its register back.
+//! drop(b);
+//! assert_eq!(*pool.acquire(&[]).unwrap(), Reg64::Rdx);
+//!
+//! // the guard stands for its register in the emitters.
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.mov_reg64_reg64(*a, *c);
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0x48, 0x89, 0xc8, // mov rax, rcx
+//! ]);
+//! ```
+
+use std::{cell::Cell, fmt, ops::Deref};
+
+use crate::register::{GpReg, Reg64};
+
+/// A pool of scratch registers, handing out its candidates in order.
+///
+/// The pool tracks the registers acquired through a shared reference, so several guards can be held at once.
+pub struct ScratchPool {
+    /// The candidates, in the order they are handed out.
+    candidates: [Reg64; 16],
+
+    /// The number of candidates.
+    len: usize,
+
+    /// The acquired registers, a bit per register number.
+    acquired: Cell<u16>,
+}
+
+impl ScratchPool {
+    /// Creates a pool handing out `candidates` in order.
+    ///
+    /// # Panics
+    /// Panics if `candidates` holds a register twice.
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{register::Reg64, scratch::ScratchPool};
+    ///
+    /// ScratchPool::new(&[Reg64::Rax, Reg64::Rcx, Reg64::Rax]);
+    /// ```
+    pub fn new(candidates: &[Reg64]) -> Self {
+        let mut pool = Self { candidates: [Reg64::Rax; 16], len: 0, acquired: Cell::new(0) };
+        let mut seen = 0u16;
+
+        for &reg in candidates {
+            assert!(seen & (1 << reg.number()) == 0, "register {:?} is a candidate of the scratch pool twice", reg);
+            seen |= 1 << reg.number();
+
+            pool.candidates[pool.len] = reg;
+            pool.len += 1;
+        }
+
+        pool
+    }
+
+    /// The candidates of the pool, in the order they are handed out.
+    pub fn candidates(&self) -> &[Reg64] {
+        &self.candidates[..self.len]
+    }
+
+    /// Returns true if *r64* is a candidate of the pool and isn't acquired.
+    pub fn is_available(&self, reg: Reg64) -> bool {
+        self.candidates().contains(&reg) && self.acquired.get() & (1 << reg.number()) == 0
+    }
+
+    /// Acquires the first free candidate which isn't in `exclude`, until the returned guard is dropped, or returns `None` if every candidate is
+    /// either acquired or excluded.
+    pub fn acquire(&self, exclude: &[Reg64]) -> Option<ScratchReg<'_>> {
+        let reg = self.candidates().iter().copied().find(|&reg| self.is_available(reg) && !exclude.contains(&reg))?;
+        self.acquired.set(self.acquired.get() | (1 << reg.number()));
+
+        Some(ScratchReg { pool: self, reg })
+    }
+}
+
+impl fmt::Debug for ScratchPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let available = self.candidates().iter().filter(|&&reg| self.is_available(reg)).collect::<Vec<_>>();
+        f.debug_struct("ScratchPool").field("candidates", &self.candidates()).field("available", &available).finish()
+    }
+}
+
+/// A register acquired from a [`ScratchPool`], given back when dropped.  Dereferences to the register.
+#[derive(Debug)]
+pub struct ScratchReg<'a> {
+    /// The pool the register was acquired from.
+    pool: &'a ScratchPool,
+
+    /// The acquired register.
+    reg: Reg64,
+}
+
+impl ScratchReg<'_> {
+    /// The acquired register.
+    pub fn reg(&self) -> Reg64 {
+        self.reg
+    }
+}
+
+impl Deref for ScratchReg<'_> {
+    type Target = Reg64;
+
+    fn deref(&self) -> &Reg64 {
+        &self.reg
+    }
+}
+
+impl Drop for ScratchReg<'_> {
+    fn drop(&mut self) {
+        self.pool.acquired.set(self.pool.acquired.get() & !(1 << self.reg.number()));
+    }
+}
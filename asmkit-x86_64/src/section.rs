@@ -0,0 +1,186 @@
+//! Text sections, moving the unlikely paths of the code, such as panic shims and slow paths, out of line.
+//!
+//! The instructions are written to the [`Section::Text`] section unless [`switch_section`](x86_64InstructionStream::switch_section) or
+//! [`emit_cold`](x86_64InstructionStream::emit_cold) routes them to [`Section::ColdText`].  When the stream is finished, the cold text is laid out
+//! after the text, at an offset aligned to [`cold_text_align`](x86_64InstructionStream::cold_text_align), and before the constant pool.
+//!
+//! Labels work across both sections: the final layout is known when the stream is finished, so the branches between the sections are resolved
+//! to displacements like any other, and never left to relocations.  The alignment of a label attached in the cold text is kept up to the
+//! alignment of the cold text.
+//!
+//! # Example
+//! Returning 1 for a non-zero argument, with the zero case moved out of line:
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::{Reg32, Reg64}, stream::{Condition, x86_64InstructionStream}};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let slow = stream.create_label();
+//! let done = stream.create_label();
+//!
+//! stream.test_reg64_reg64(Reg64::Rdi, Reg64::Rdi);
+//! stream.jcc_label(Condition::E, slow);
+//! stream.mov_reg32_imm32(Reg32::Eax, 1);
+//! stream.attach_label(done);
+//! stream.ret_near();
+//!
+//! stream.emit_cold(|stream| {
+//!     stream.attach_label(slow);
+//!     stream.mov_reg32_imm32(Reg32::Eax, 2);
+//!     stream.jmp_label(done);
+//! });
+//!
+//! let code = stream.finish().emit();
+//! assert_eq!(code, [
+//!     0x48, 0x85, 0xff,                   // test rdi, rdi
+//!     0x0f, 0x84, 0x07, 0x00, 0x00, 0x00, // je slow
+//!     0xb8, 0x01, 0x00, 0x00, 0x00,       // mov eax, 1
+//!     0xc3,                               // done: ret
+//!     0x90,                               // align 16
+//!     0xb8, 0x02, 0x00, 0x00, 0x00,       // slow: mov eax, 2
+//!     0xe9, 0xf4, 0xff, 0xff, 0xff,       // jmp done
+//! ]);
+//!
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # {
+//! use asmkit_core::{exec::ExecutableBuffer, Product};
+//!
+//! let buffer = ExecutableBuffer::new(Product::new(code), |_| unreachable!()).unwrap();
+//! let function: extern "C" fn(u64) -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+//! assert_eq!(function(7), 1);
+//! assert_eq!(function(0), 2);
+//! # }
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{block::BlockBody, stream::{x86_64InstructionStream, Fill}};
+
+/// A section of the code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Section {
+    /// The code on the likely paths, laid out first.
+    #[default]
+    Text,
+
+    /// The code on the unlikely paths, laid out after the text.
+    ColdText,
+}
+
+/// The sections of an instruction stream.
+#[derive(Debug)]
+pub(crate) struct Sections {
+    /// The section being written.
+    pub(crate) current: Section,
+
+    /// The code of the section which isn't being written.
+    other: BlockBody,
+
+    /// The alignment of the start of the cold text.
+    align: usize,
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Self { current: Section::Text, other: BlockBody::default(), align: 16 }
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// The section the instructions are written to.
+    pub fn section(&self) -> Section {
+        self.sections_ref().current
+    }
+
+    /// Writes the instructions emitted from now on to `section`.
+    ///
+    /// # Panics
+    /// Panics if a basic block is being written, see [`x86_64InstructionStream::switch_to_block`].
+    pub fn switch_section(&mut self, section: Section) {
+        if self.sections().current == section {
+            return;
+        }
+
+        assert!(self.blocks().current.is_none(), "the section can't be switched while a basic block is being written");
+
+        let mut body = std::mem::take(&mut self.sections().other);
+        self.swap_body(&mut body);
+
+        let sections = self.sections();
+        sections.other = body;
+        sections.current = section;
+    }
+
+    /// Writes the instructions emitted by `emit` to the cold text, then switches back to the section being written.
+    ///
+    /// # Panics
+    /// Panics if a basic block is being written, see [`x86_64InstructionStream::switch_to_block`].
+    pub fn emit_cold(&mut self, emit: impl FnOnce(&mut Self)) {
+        let section = self.section();
+
+        self.switch_section(Section::ColdText);
+        emit(self);
+        self.switch_section(section);
+    }
+
+    /// Sets the alignment of the start of the cold text, 16 bytes by default.  The padding is filled with the [`Fill`] set by
+    /// [`x86_64InstructionStream::set_align_fill`].
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::{Condition, Fill, x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.set_cold_text_align(32);
+    /// stream.set_align_fill(Fill::Int3);
+    ///
+    /// let fail = stream.create_label();
+    /// stream.jcc_label(Condition::O, fail);
+    /// stream.ret_near();
+    /// stream.emit_cold(|stream| {
+    ///     stream.attach_label(fail);
+    ///     stream.int3();
+    /// });
+    ///
+    /// let code = stream.finish().emit();
+    /// assert_eq!(code[..7], [
+    ///     0x0f, 0x80, 0x1a, 0x00, 0x00, 0x00, // jo fail, over the alignment gap
+    ///     0xc3,                               // ret
+    /// ]);
+    /// assert_eq!(code[7..32], [0xcc; 25]);
+    /// assert_eq!(code[32..], [0xcc]);         // fail: int3
+    /// ```
+    pub fn set_cold_text_align(&mut self, align: usize) {
+        assert!(align.is_power_of_two(), "section alignment must be a power of two");
+        self.sections().align = align;
+    }
+
+    /// The alignment of the start of the cold text.
+    pub fn cold_text_align(&self) -> usize {
+        self.sections_ref().align
+    }
+
+    /// Switches back to the text, and lays the cold text out after it, aligned.
+    pub(crate) fn lay_out_cold_text(&mut self) {
+        self.switch_section(Section::Text);
+
+        let cold = std::mem::take(&mut self.sections().other);
+        if cold.bytes.is_empty() {
+            return;
+        }
+
+        let align = self.sections().align;
+        let padding = self.code().len().wrapping_neg() & (align - 1);
+        match self.align_fill() {
+            Fill::Nop => self.write_nops(padding),
+            Fill::Int3 => self.write_bytes(&vec![0xcc; padding]),
+            Fill::Zero => self.write_bytes(&vec![0; padding]),
+        }
+
+        self.append_body(cold);
+    }
+}
@@ -0,0 +1,420 @@
+//! Transfers between the general purpose and the SSE registers.
+//!
+//! `movd` and `movq` share their opcodes between both directions, `66 0F 6E` loading an `xmm` register and `66 0F 7E` storing it, with the `xmm`
+//! register always in the `reg` field of the MODRM byte and REX.W selecting the 64-bit form.  The `bitcast_*` intrinsics pick the right one for
+//! reinterpreting the bits of a float as an integer, and back.
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::{Reg32, Reg64, Xmm},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, REX, REX_B, REX_R, REX_W},
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes an SSE instruction with a register-direct operand: the mandatory `prefix` if any, a REX prefix if needed, `0F` followed by `opcode`
+    /// (which starts with `38` or `3A` for the three-byte maps), the MODRM byte and `imm8` if any.
+    pub(crate) fn write_sse_reg_reg(&mut self, prefix: Option<u8>, rex_w: bool, opcode: &[u8], reg: (u8, bool), rm: (u8, bool), imm8: Option<u8>) {
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        let mut rex = REX;
+        if rex_w {
+            rex |= REX_W;
+        }
+
+        if reg.1 {
+            rex |= REX_R;
+        }
+
+        if rm.1 {
+            rex |= REX_B;
+        }
+
+        if rex != REX {
+            self.write_byte(rex);
+        }
+
+        self.write_byte(0x0f);
+        self.write_bytes(opcode);
+        self.write_byte((0b11 << 6) | (reg.0 << 3) | rm.0); // register-direct MODRM encoded
+
+        if let Some(imm8) = imm8 {
+            self.write_byte(imm8);
+        }
+    }
+
+    /// Writes an SSE instruction with a memory operand, as [`x86_64InstructionStream::write_sse_reg_reg`] does with a register-direct one.
+    pub(crate) fn write_sse_reg_mem(&mut self, prefix: Option<u8>, rex_w: bool, opcode: &[u8], reg: (u8, bool), mem: Mem, imm8: Option<u8>) {
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        let mut escaped = [0x0f; 4];
+        escaped[1..=opcode.len()].copy_from_slice(opcode);
+        self.write_rex_modrm_mem(rex_w, &escaped[..=opcode.len()], reg, mem);
+
+        if let Some(imm8) = imm8 {
+            self.write_byte(imm8);
+        }
+    }
+
+    /// Writes a vector instruction with the `66` mandatory prefix, from *xmm2* to *xmm1*.
+    pub(crate) fn write_vector_xmm_xmm(&mut self, opcode: &[u8], dest: Xmm, src: Xmm, imm8: Option<u8>) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, opcode, reg, rm, imm8);
+
+        let (defs, uses) = vector_registers(opcode, dest, RegSet::of(src));
+        self.record_registers(defs, uses);
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Writes a vector instruction with the `66` mandatory prefix, from *m128* to *xmm*.
+    pub(crate) fn write_vector_xmm_mem(&mut self, opcode: &[u8], dest: Xmm, src: Mem, imm8: Option<u8>) {
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(Some(0x66), false, opcode, (dest.offset(), dest.is_extension()), src, imm8);
+        let (defs, uses) = vector_registers(opcode, dest, RegSet::from(src));
+        self.record_registers(defs, uses);
+        self.end_instruction(instruction, InstructionFamily::Vector);
+    }
+
+    /// Move doubleword from *r32* to *xmm*, zeroing the upper bits of *xmm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movd_xmm_reg32(Xmm::Xmm1, Reg32::Eax);
+    /// stream.movd_xmm_reg32(Xmm::Xmm9, Reg32::R10d);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x6e, 0xc8,       // movd xmm1, eax
+    ///     0x66, 0x45, 0x0f, 0x6e, 0xca, // movd xmm9, r10d
+    /// ]);
+    /// ```
+    pub fn movd_xmm_reg32(&mut self, dest: Xmm, src: Reg32) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, &[0x6e], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move doubleword from *xmm* to *r32*, zeroing the upper bits of the 64-bit register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movd_reg32_xmm(Reg32::Eax, Xmm::Xmm1);
+    /// stream.movd_reg32_xmm(Reg32::R10d, Xmm::Xmm9);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x7e, 0xc8,       // movd eax, xmm1
+    ///     0x66, 0x45, 0x0f, 0x7e, 0xca, // movd r10d, xmm9
+    /// ]);
+    /// ```
+    pub fn movd_reg32_xmm(&mut self, dest: Reg32, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((src.offset(), src.is_extension()), (dest.offset(), dest.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, &[0x7e], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move quadword from *r64* to *xmm*, zeroing the upper bits of *xmm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movq_xmm_reg64(Xmm::Xmm1, Reg64::Rax);
+    /// stream.movq_xmm_reg64(Xmm::Xmm12, Reg64::R9);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x48, 0x0f, 0x6e, 0xc8, // movq xmm1, rax
+    ///     0x66, 0x4d, 0x0f, 0x6e, 0xe1, // movq xmm12, r9
+    /// ]);
+    /// ```
+    pub fn movq_xmm_reg64(&mut self, dest: Xmm, src: Reg64) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), true, &[0x6e], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move quadword from *xmm* to *r64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movq_reg64_xmm(Reg64::Rax, Xmm::Xmm1);
+    /// stream.movq_reg64_xmm(Reg64::R9, Xmm::Xmm12);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x48, 0x0f, 0x7e, 0xc8, // movq rax, xmm1
+    ///     0x66, 0x4d, 0x0f, 0x7e, 0xe1, // movq r9, xmm12
+    /// ]);
+    /// ```
+    pub fn movq_reg64_xmm(&mut self, dest: Reg64, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((src.offset(), src.is_extension()), (dest.offset(), dest.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), true, &[0x7e], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move quadword from *xmm* to *xmm*, zeroing the upper quadword of the destination (`F3 0F 7E`).
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movq_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.movq_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2);
+    /// stream.movq_xmm_xmm(Xmm::Xmm1, Xmm::Xmm10);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x0f, 0x7e, 0xca,       // movq xmm1, xmm2
+    ///     0xf3, 0x44, 0x0f, 0x7e, 0xca, // movq xmm9, xmm2
+    ///     0xf3, 0x41, 0x0f, 0x7e, 0xca, // movq xmm1, xmm10
+    /// ]);
+    /// ```
+    pub fn movq_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(Some(0xf3), false, &[0x7e], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move quadword from *xmm* to *xmm*, zeroing the upper quadword of the destination, with the store encoding (`66 0F D6`) which has the operands
+    /// the other way around in the MODRM byte.  Behaves exactly as [`x86_64InstructionStream::movq_xmm_xmm`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movq_xmm_xmm_mr(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.movq_xmm_xmm_mr(Xmm::Xmm1, Xmm::Xmm10);
+    /// stream.movq_xmm_xmm_mr(Xmm::Xmm10, Xmm::Xmm1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0xd6, 0xd1,       // movq xmm1, xmm2
+    ///     0x66, 0x44, 0x0f, 0xd6, 0xd1, // movq xmm1, xmm10
+    ///     0x66, 0x41, 0x0f, 0xd6, 0xca, // movq xmm10, xmm1
+    /// ]);
+    /// ```
+    pub fn movq_xmm_xmm_mr(&mut self, dest: Xmm, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((src.offset(), src.is_extension()), (dest.offset(), dest.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, &[0xd6], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the unaligned packed integers of *m128* to *xmm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movdqu_mem_xmm(Mem::new(Reg64::Rsp, 240), Xmm::Xmm15);
+    /// stream.movdqu_xmm_mem(Xmm::Xmm0, Mem::new(Reg64::Rsp, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x44, 0x0f, 0x7f, 0xbc, 0x24, 0xf0, 0x00, 0x00, 0x00, // movdqu [rsp + 240], xmm15
+    ///     0xf3, 0x0f, 0x6f, 0x04, 0x24,                               // movdqu xmm0, [rsp]
+    /// ]);
+    /// ```
+    pub fn movdqu_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xf3);
+        self.write_rex_modrm_mem(false, &[0x0f, 0x6f], (dest.offset(), dest.is_extension()), src);
+
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the packed integers of *xmm* to the unaligned *m128*.
+    pub fn movdqu_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xf3);
+        self.write_rex_modrm_mem(false, &[0x0f, 0x7f], (src.offset(), src.is_extension()), dest);
+
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the packed integers of *xmm2* to *xmm1*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movdqa_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2);
+    /// stream.movdqa_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rdi, 16));
+    /// stream.movdqa_mem_xmm(Mem::new(Reg64::R13, 0), Xmm::Xmm10);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x44, 0x0f, 0x6f, 0xca,       // movdqa xmm9, xmm2
+    ///     0x66, 0x0f, 0x6f, 0x4f, 0x10,       // movdqa xmm1, xmmword ptr [rdi + 16]
+    ///     0x66, 0x45, 0x0f, 0x7f, 0x55, 0x00, // movdqa xmmword ptr [r13], xmm10
+    /// ]);
+    /// ```
+    pub fn movdqa_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        let instruction = self.begin_instruction();
+
+        let (reg, rm) = ((dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.write_sse_reg_reg(Some(0x66), false, &[0x6f], reg, rm, None);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the packed integers of the 16-byte aligned *m128* to *xmm*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn movdqa_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.check_alignment("movdqa", src, 16);
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(Some(0x66), false, &[0x6f], (dest.offset(), dest.is_extension()), src, None);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the packed integers of *xmm* to the 16-byte aligned *m128*.
+    ///
+    /// # Panics
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn movdqa_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        self.check_alignment("movdqa", dest, 16);
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(Some(0x66), false, &[0x7f], (src.offset(), src.is_extension()), dest, None);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move quadword from *m64* to *xmm*, zeroing the upper quadword of *xmm*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movq_xmm_mem(Xmm::Xmm9, Mem::new(Reg64::Rsp, 8));
+    /// stream.movq_mem_xmm(Mem::new(Reg64::Rax, -8), Xmm::Xmm1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x44, 0x0f, 0x7e, 0x4c, 0x24, 0x08, // movq xmm9, qword ptr [rsp + 8]
+    ///     0x66, 0x0f, 0xd6, 0x48, 0xf8,             // movq qword ptr [rax - 8], xmm1
+    /// ]);
+    /// ```
+    pub fn movq_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(Some(0xf3), false, &[0x7e], (dest.offset(), dest.is_extension()), src, None);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the low quadword of *xmm* to *m64*.
+    pub fn movq_mem_xmm(&mut self, dest: Mem, src: Xmm) {
+        let instruction = self.begin_instruction();
+        self.write_sse_reg_mem(Some(0x66), false, &[0xd6], (src.offset(), src.is_extension()), dest, None);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Reinterprets the bits of the `f64` in the low quadword of *xmm* as a `u64` in *r64*, as [`f64::to_bits`] does.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.bitcast_f64_to_u64(Reg64::Rax, Xmm::Xmm0);
+    /// stream.bitcast_u64_to_f64(Xmm::Xmm0, Reg64::Rdi);
+    /// stream.bitcast_f32_to_u32(Reg32::Eax, Xmm::Xmm0);
+    /// stream.bitcast_u32_to_f32(Xmm::Xmm0, Reg32::Edi);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x48, 0x0f, 0x7e, 0xc0, // movq rax, xmm0
+    ///     0x66, 0x48, 0x0f, 0x6e, 0xc7, // movq xmm0, rdi
+    ///     0x66, 0x0f, 0x7e, 0xc0,       // movd eax, xmm0
+    ///     0x66, 0x0f, 0x6e, 0xc7,       // movd xmm0, edi
+    /// ]);
+    /// ```
+    #[inline(always)]
+    pub fn bitcast_f64_to_u64(&mut self, dest: Reg64, src: Xmm) {
+        self.movq_reg64_xmm(dest, src);
+    }
+
+    /// Reinterprets the bits of the `u64` in *r64* as an `f64` in the low quadword of *xmm*, as [`f64::from_bits`] does.
+    #[inline(always)]
+    pub fn bitcast_u64_to_f64(&mut self, dest: Xmm, src: Reg64) {
+        self.movq_xmm_reg64(dest, src);
+    }
+
+    /// Reinterprets the bits of the `f32` in the low doubleword of *xmm* as a `u32` in *r32*, as [`f32::to_bits`] does.
+    #[inline(always)]
+    pub fn bitcast_f32_to_u32(&mut self, dest: Reg32, src: Xmm) {
+        self.movd_reg32_xmm(dest, src);
+    }
+
+    /// Reinterprets the bits of the `u32` in *r32* as an `f32` in the low doubleword of *xmm*, as [`f32::from_bits`] does.
+    #[inline(always)]
+    pub fn bitcast_u32_to_f32(&mut self, dest: Xmm, src: Reg32) {
+        self.movd_xmm_reg32(dest, src);
+    }
+}
+
+/// The registers defined and used by the vector instruction `66 0F opcode` from the operand addressed by the registers `other` to *xmm*.
+fn vector_registers(opcode: &[u8], xmm: Xmm, other: RegSet) -> (RegSet, RegSet) {
+    match opcode {
+        [0x38, 0x17] => (RegSet::EMPTY, other.with(xmm)), // ptest
+        [0x38, 0x10 | 0x14 | 0x15] => (RegSet::of(xmm), other.with(xmm).with(Xmm::Xmm0)), // the blends by the mask in `xmm0`
+        [0x38, 0xdb] | [0x3a, 0x08 | 0x09 | 0xdf] => (RegSet::of(xmm), other), // whole vector results: aesimc, roundps, roundpd, aeskeygenassist
+        _ => (RegSet::of(xmm), other.with(xmm)),
+    }
+}
@@ -0,0 +1,498 @@
+//! The SSE4.1 instructions on `xmm` registers.
+//!
+//! Every SSE4.1 instruction takes the `66` mandatory prefix, and lives in either the `0F 38` map or, when it takes an immediate, the `0F 3A` map.
+//! The variable blends (`blendvps`, `blendvpd` and `pblendvb`) implicitly read their mask from `xmm0`.
+
+use asmkit_core::fixed::CodeStorage;
+
+use crate::{features::CpuFeatures, memory::Mem, register::Xmm, stream::x86_64InstructionStream};
+
+/// The rounding mode of the `round*` instructions.
+///
+/// The immediate encoded for each mode also suppresses the precision exception, as compilers do when lowering [`f64::floor`] and friends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundingControl {
+    /// Round to the nearest integer, ties to even.
+    Nearest,
+
+    /// Round towards negative infinity, as [`f64::floor`].
+    Floor,
+
+    /// Round towards positive infinity, as [`f64::ceil`].
+    Ceil,
+
+    /// Round towards zero, as [`f64::trunc`].
+    Trunc,
+}
+
+impl RoundingControl {
+    /// The rounding control immediate, for instruction encoding.
+    #[inline(always)]
+    pub fn bits(&self) -> u8 {
+        0b1000 | *self as u8 // precision exception suppressed
+    }
+
+    /// The rounding mode of the rounding control immediate `bits`, with the precision exception suppressed.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::sse41::RoundingControl;
+    ///
+    /// assert_eq!(RoundingControl::from_bits(RoundingControl::Trunc.bits()), Some(RoundingControl::Trunc));
+    /// assert_eq!(RoundingControl::from_bits(0b0011), None);
+    /// ```
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0b1000 => Some(Self::Nearest),
+            0b1001 => Some(Self::Floor),
+            0b1010 => Some(Self::Ceil),
+            0b1011 => Some(Self::Trunc),
+            _ => None,
+        }
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Round packed single precision floats in *xmm2* into *xmm1*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, sse41::RoundingControl, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.roundps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, RoundingControl::Nearest);
+    /// stream.roundps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, RoundingControl::Floor);
+    /// stream.roundps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, RoundingControl::Ceil);
+    /// stream.roundps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, RoundingControl::Trunc);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x3a, 0x08, 0xca, 0x08, // roundps xmm1, xmm2, 8
+    ///     0x66, 0x0f, 0x3a, 0x08, 0xca, 0x09, // roundps xmm1, xmm2, 9
+    ///     0x66, 0x0f, 0x3a, 0x08, 0xca, 0x0a, // roundps xmm1, xmm2, 10
+    ///     0x66, 0x0f, 0x3a, 0x08, 0xca, 0x0b, // roundps xmm1, xmm2, 11
+    /// ]);
+    /// ```
+    pub fn roundps_xmm_xmm(&mut self, dest: Xmm, src: Xmm, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundps");
+        self.write_vector_xmm_xmm(&[0x3a, 0x08], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round packed double precision floats in *xmm2* into *xmm1*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn roundpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundpd");
+        self.write_vector_xmm_xmm(&[0x3a, 0x09], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round the low single precision float in *xmm2* into the low doubleword of *xmm1*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, sse41::RoundingControl, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.roundpd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2, RoundingControl::Ceil);
+    /// stream.roundss_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2, RoundingControl::Trunc);
+    /// stream.roundsd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm10, RoundingControl::Nearest);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x3a, 0x09, 0xca, 0x0a,       // roundpd xmm1, xmm2, 10
+    ///     0x66, 0x44, 0x0f, 0x3a, 0x0a, 0xca, 0x0b, // roundss xmm9, xmm2, 11
+    ///     0x66, 0x41, 0x0f, 0x3a, 0x0b, 0xca, 0x08, // roundsd xmm1, xmm10, 8
+    /// ]);
+    /// ```
+    pub fn roundss_xmm_xmm(&mut self, dest: Xmm, src: Xmm, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundss");
+        self.write_vector_xmm_xmm(&[0x3a, 0x0a], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round the low double precision float in *xmm2* into the low quadword of *xmm1*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn roundsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundsd");
+        self.write_vector_xmm_xmm(&[0x3a, 0x0b], dest, src, Some(rounding.bits()));
+    }
+
+    /// Select the packed single precision floats of *xmm2* whose bit is set in *imm8* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.blendps_xmm_xmm_imm8(Xmm::Xmm1, Xmm::Xmm2, 5);
+    /// stream.blendpd_xmm_xmm_imm8(Xmm::Xmm1, Xmm::Xmm2, 1);
+    /// stream.pblendw_xmm_xmm_imm8(Xmm::Xmm1, Xmm::Xmm2, 0xf0);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x3a, 0x0c, 0xca, 0x05, // blendps xmm1, xmm2, 5
+    ///     0x66, 0x0f, 0x3a, 0x0d, 0xca, 0x01, // blendpd xmm1, xmm2, 1
+    ///     0x66, 0x0f, 0x3a, 0x0e, 0xca, 0xf0, // pblendw xmm1, xmm2, 240
+    /// ]);
+    /// ```
+    pub fn blendps_xmm_xmm_imm8(&mut self, dest: Xmm, src: Xmm, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendps");
+        self.write_vector_xmm_xmm(&[0x3a, 0x0c], dest, src, Some(imm8));
+    }
+
+    /// Select the packed double precision floats of *xmm2* whose bit is set in *imm8* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn blendpd_xmm_xmm_imm8(&mut self, dest: Xmm, src: Xmm, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendpd");
+        self.write_vector_xmm_xmm(&[0x3a, 0x0d], dest, src, Some(imm8));
+    }
+
+    /// Select the packed words of *xmm2* whose bit is set in *imm8* into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pblendw_xmm_xmm_imm8(&mut self, dest: Xmm, src: Xmm, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "pblendw");
+        self.write_vector_xmm_xmm(&[0x3a, 0x0e], dest, src, Some(imm8));
+    }
+
+    /// Select the packed single precision floats of *xmm2* whose mask sign bit in `xmm0` is set into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.blendvps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.blendvpd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pblendvb_xmm_xmm(Xmm::Xmm9, Xmm::Xmm2);
+    /// stream.ptest_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pmulld_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pminsd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pmaxsd_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pminud_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pmaxud_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.packusdw_xmm_xmm(Xmm::Xmm1, Xmm::Xmm12);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x38, 0x14, 0xca,       // blendvps xmm1, xmm2, xmm0
+    ///     0x66, 0x0f, 0x38, 0x15, 0xca,       // blendvpd xmm1, xmm2, xmm0
+    ///     0x66, 0x44, 0x0f, 0x38, 0x10, 0xca, // pblendvb xmm9, xmm2, xmm0
+    ///     0x66, 0x0f, 0x38, 0x17, 0xca,       // ptest xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x40, 0xca,       // pmulld xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x39, 0xca,       // pminsd xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x3d, 0xca,       // pmaxsd xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x3b, 0xca,       // pminud xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x3f, 0xca,       // pmaxud xmm1, xmm2
+    ///     0x66, 0x41, 0x0f, 0x38, 0x2b, 0xcc, // packusdw xmm1, xmm12
+    /// ]);
+    /// ```
+    pub fn blendvps_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendvps");
+        self.write_vector_xmm_xmm(&[0x38, 0x14], dest, src, None);
+    }
+
+    /// Select the packed double precision floats of *xmm2* whose mask sign bit in `xmm0` is set into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn blendvpd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendvpd");
+        self.write_vector_xmm_xmm(&[0x38, 0x15], dest, src, None);
+    }
+
+    /// Select the packed bytes of *xmm2* whose mask sign bit in `xmm0` is set into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pblendvb_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pblendvb");
+        self.write_vector_xmm_xmm(&[0x38, 0x10], dest, src, None);
+    }
+
+    /// Set the zero flag if *xmm1* AND *xmm2* is zero, and the carry flag if *xmm2* AND NOT *xmm1* is zero.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn ptest_xmm_xmm(&mut self, src1: Xmm, src2: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "ptest");
+        self.write_vector_xmm_xmm(&[0x38, 0x17], src1, src2, None);
+    }
+
+    /// Multiply the packed doublewords of *xmm1* and *xmm2*, keeping the low 32 bits of each product in *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pmulld_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmulld");
+        self.write_vector_xmm_xmm(&[0x38, 0x40], dest, src, None);
+    }
+
+    /// Keep the minimum of the packed signed doublewords of *xmm1* and *xmm2* in *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pminsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pminsd");
+        self.write_vector_xmm_xmm(&[0x38, 0x39], dest, src, None);
+    }
+
+    /// Keep the maximum of the packed signed doublewords of *xmm1* and *xmm2* in *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pmaxsd_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmaxsd");
+        self.write_vector_xmm_xmm(&[0x38, 0x3d], dest, src, None);
+    }
+
+    /// Keep the minimum of the packed unsigned doublewords of *xmm1* and *xmm2* in *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pminud_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pminud");
+        self.write_vector_xmm_xmm(&[0x38, 0x3b], dest, src, None);
+    }
+
+    /// Keep the maximum of the packed unsigned doublewords of *xmm1* and *xmm2* in *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn pmaxud_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmaxud");
+        self.write_vector_xmm_xmm(&[0x38, 0x3f], dest, src, None);
+    }
+
+    /// Pack the signed doublewords of *xmm1* and *xmm2* into unsigned words with saturation, into *xmm1*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn packusdw_xmm_xmm(&mut self, dest: Xmm, src: Xmm) {
+        self.require_feature(CpuFeatures::SSE4_1, "packusdw");
+        self.write_vector_xmm_xmm(&[0x38, 0x2b], dest, src, None);
+    }
+
+    /// Round the packed single precision floats of the 16-byte aligned *m128* into *xmm*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Xmm}, sse41::RoundingControl, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.roundps_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rdi, 0), RoundingControl::Floor);
+    /// stream.roundsd_xmm_mem(Xmm::Xmm9, Mem::new(Reg64::Rsp, 8), RoundingControl::Trunc);
+    /// stream.blendps_xmm_mem_imm8(Xmm::Xmm1, Mem::new(Reg64::R12, 16), 5);
+    /// stream.pblendvb_xmm_mem(Xmm::Xmm2, Mem::new(Reg64::Rax, 0));
+    /// stream.ptest_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rbp, -16));
+    /// stream.pmulld_xmm_mem(Xmm::Xmm10, Mem::new(Reg64::R13, 0x100));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x0f, 0x3a, 0x08, 0x0f, 0x09,                         // roundps xmm1, xmmword ptr [rdi], 9
+    ///     0x66, 0x44, 0x0f, 0x3a, 0x0b, 0x4c, 0x24, 0x08, 0x0b,       // roundsd xmm9, qword ptr [rsp + 8], 11
+    ///     0x66, 0x41, 0x0f, 0x3a, 0x0c, 0x4c, 0x24, 0x10, 0x05,       // blendps xmm1, xmmword ptr [r12 + 16], 5
+    ///     0x66, 0x0f, 0x38, 0x10, 0x10,                               // pblendvb xmm2, xmmword ptr [rax], xmm0
+    ///     0x66, 0x0f, 0x38, 0x17, 0x4d, 0xf0,                         // ptest xmm1, xmmword ptr [rbp - 16]
+    ///     0x66, 0x45, 0x0f, 0x38, 0x40, 0x95, 0x00, 0x01, 0x00, 0x00, // pmulld xmm10, xmmword ptr [r13 + 256]
+    /// ]);
+    /// ```
+    pub fn roundps_xmm_mem(&mut self, dest: Xmm, src: Mem, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundps");
+        self.check_alignment("roundps", src, 16);
+        self.write_vector_xmm_mem(&[0x3a, 0x08], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round the packed double precision floats of the 16-byte aligned *m128* into *xmm*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn roundpd_xmm_mem(&mut self, dest: Xmm, src: Mem, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundpd");
+        self.check_alignment("roundpd", src, 16);
+        self.write_vector_xmm_mem(&[0x3a, 0x09], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round the single precision float *m32* into the low doubleword of *xmm*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn roundss_xmm_mem(&mut self, dest: Xmm, src: Mem, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundss");
+        self.write_vector_xmm_mem(&[0x3a, 0x0a], dest, src, Some(rounding.bits()));
+    }
+
+    /// Round the double precision float *m64* into the low quadword of *xmm*, with the rounding mode `rounding`.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    pub fn roundsd_xmm_mem(&mut self, dest: Xmm, src: Mem, rounding: RoundingControl) {
+        self.require_feature(CpuFeatures::SSE4_1, "roundsd");
+        self.write_vector_xmm_mem(&[0x3a, 0x0b], dest, src, Some(rounding.bits()));
+    }
+
+    /// Select the packed single precision floats of the 16-byte aligned *m128* whose bit is set in *imm8* into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn blendps_xmm_mem_imm8(&mut self, dest: Xmm, src: Mem, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendps");
+        self.check_alignment("blendps", src, 16);
+        self.write_vector_xmm_mem(&[0x3a, 0x0c], dest, src, Some(imm8));
+    }
+
+    /// Select the packed double precision floats of the 16-byte aligned *m128* whose bit is set in *imm8* into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn blendpd_xmm_mem_imm8(&mut self, dest: Xmm, src: Mem, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendpd");
+        self.check_alignment("blendpd", src, 16);
+        self.write_vector_xmm_mem(&[0x3a, 0x0d], dest, src, Some(imm8));
+    }
+
+    /// Select the packed words of the 16-byte aligned *m128* whose bit is set in *imm8* into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pblendw_xmm_mem_imm8(&mut self, dest: Xmm, src: Mem, imm8: u8) {
+        self.require_feature(CpuFeatures::SSE4_1, "pblendw");
+        self.check_alignment("pblendw", src, 16);
+        self.write_vector_xmm_mem(&[0x3a, 0x0e], dest, src, Some(imm8));
+    }
+
+    /// Select the packed single precision floats of the 16-byte aligned *m128* whose mask sign bit in `xmm0` is set into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn blendvps_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendvps");
+        self.check_alignment("blendvps", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x14], dest, src, None);
+    }
+
+    /// Select the packed double precision floats of the 16-byte aligned *m128* whose mask sign bit in `xmm0` is set into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn blendvpd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "blendvpd");
+        self.check_alignment("blendvpd", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x15], dest, src, None);
+    }
+
+    /// Select the packed bytes of the 16-byte aligned *m128* whose mask sign bit in `xmm0` is set into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pblendvb_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pblendvb");
+        self.check_alignment("pblendvb", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x10], dest, src, None);
+    }
+
+    /// Set the zero flag if *xmm* AND the 16-byte aligned *m128* is zero, and the carry flag if *m128* AND NOT *xmm* is zero.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn ptest_xmm_mem(&mut self, src1: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "ptest");
+        self.check_alignment("ptest", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x17], src1, src, None);
+    }
+
+    /// Multiply the packed doublewords of *xmm* and the 16-byte aligned *m128*, keeping the low 32 bits of each product in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pmulld_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmulld");
+        self.check_alignment("pmulld", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x40], dest, src, None);
+    }
+
+    /// Keep the minimum of the packed signed doublewords of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pminsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pminsd");
+        self.check_alignment("pminsd", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x39], dest, src, None);
+    }
+
+    /// Keep the maximum of the packed signed doublewords of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pmaxsd_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmaxsd");
+        self.check_alignment("pmaxsd", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x3d], dest, src, None);
+    }
+
+    /// Keep the minimum of the packed unsigned doublewords of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pminud_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pminud");
+        self.check_alignment("pminud", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x3b], dest, src, None);
+    }
+
+    /// Keep the maximum of the packed unsigned doublewords of *xmm* and the 16-byte aligned *m128* in *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn pmaxud_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "pmaxud");
+        self.check_alignment("pmaxud", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x3f], dest, src, None);
+    }
+
+    /// Pack the signed doublewords of *xmm* and the 16-byte aligned *m128* into unsigned words with saturation, into *xmm*.
+    ///
+    /// # Panics
+    /// Panics if SSE4.1 isn't enabled for the target, see [`x86_64InstructionStream::new_with_features`].
+    /// Panics if the alignment checks are enabled and *m128* is known to be misaligned, see [`x86_64InstructionStream::enable_alignment_checks`].
+    pub fn packusdw_xmm_mem(&mut self, dest: Xmm, src: Mem) {
+        self.require_feature(CpuFeatures::SSE4_1, "packusdw");
+        self.check_alignment("packusdw", src, 16);
+        self.write_vector_xmm_mem(&[0x38, 0x2b], dest, src, None);
+    }
+}
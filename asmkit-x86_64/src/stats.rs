@@ -0,0 +1,137 @@
+//! Statistics about the instructions emitted into an instruction stream.
+//!
+//! Collecting statistics is opt-in, see [`x86_64InstructionStream::enable_stats`](crate::stream::x86_64InstructionStream::enable_stats), so streams which
+//! don't need them only pay for a flag check per instruction.
+
+use std::{collections::BTreeMap, fmt};
+
+/// A family of mnemonics, grouping the instructions counted by [`Stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InstructionFamily {
+    /// Moves between registers and memory, including the sign extending moves.
+    Mov,
+
+    /// Address computations.
+    Lea,
+
+    /// Pushes onto the stack.
+    Push,
+
+    /// Pops from the stack.
+    Pop,
+
+    /// Integer arithmetic, logic, shifts and comparisons.
+    Arith,
+
+    /// Jumps, conditional or not.
+    Branch,
+
+    /// Calls.
+    Call,
+
+    /// Returns.
+    Ret,
+
+    /// Atomic read-modify-write instructions, along with their `lock` prefix.
+    Atomic,
+
+    /// Memory fences.
+    Fence,
+
+    /// No-operations, emitted explicitly rather than as alignment padding.
+    Nop,
+
+    /// SSE arithmetic, comparisons, blends and conversions on `xmm` registers.
+    Vector,
+
+    /// System instructions, such as `swapgs` and the flag instructions.
+    System,
+}
+
+impl InstructionFamily {
+    /// The name of the family, as displayed in the statistics table.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mov => "mov",
+            Self::Lea => "lea",
+            Self::Push => "push",
+            Self::Pop => "pop",
+            Self::Arith => "arith",
+            Self::Branch => "branch",
+            Self::Call => "call",
+            Self::Ret => "ret",
+            Self::Atomic => "atomic",
+            Self::Fence => "fence",
+            Self::Nop => "nop",
+            Self::Vector => "vector",
+            Self::System => "system",
+        }
+    }
+
+    /// Returns true if the instructions of this family end a basic block.
+    pub fn is_terminator(&self) -> bool {
+        matches!(self, Self::Branch | Self::Ret)
+    }
+}
+
+/// The instructions of a single [`InstructionFamily`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FamilyStats {
+    /// The number of instructions emitted.
+    pub instructions: usize,
+
+    /// The number of bytes taken by the instructions.
+    pub bytes: usize,
+}
+
+/// Statistics about the instructions emitted into an instruction stream, since statistics were enabled.
+///
+/// Raw bytes written through the [`InstructionStream`](asmkit_core::InstructionStream) methods, such as jump tables, aren't instructions and aren't
+/// counted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of instructions emitted.
+    pub instructions: usize,
+
+    /// The instructions emitted, by family.
+    pub families: BTreeMap<InstructionFamily, FamilyStats>,
+
+    /// The number of jumps, conditional or not.
+    pub branches: usize,
+
+    /// The number of calls.
+    pub calls: usize,
+
+    /// The number of labels used by an instruction but not attached yet.
+    pub unresolved_labels: usize,
+
+    /// The size in bytes of the largest basic block, a run of instructions without any label attached in between and ended by a jump or a return.
+    pub largest_basic_block: usize,
+}
+
+impl Stats {
+    /// The number of bytes taken by all the instructions.
+    pub fn bytes(&self) -> usize {
+        self.families.values().map(|family| family.bytes).sum()
+    }
+}
+
+impl fmt::Display for Stats {
+    /// Formats the statistics as a table.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<20}{:>8}", "instructions", self.instructions)?;
+        writeln!(f, "{:<20}{:>8}", "bytes", self.bytes())?;
+        writeln!(f, "{:<20}{:>8}", "branches", self.branches)?;
+        writeln!(f, "{:<20}{:>8}", "calls", self.calls)?;
+        writeln!(f, "{:<20}{:>8}", "unresolved labels", self.unresolved_labels)?;
+        writeln!(f, "{:<20}{:>8}", "largest block", self.largest_basic_block)?;
+        writeln!(f)?;
+        writeln!(f, "{:<12}{:>8}{:>8}", "family", "count", "bytes")?;
+
+        for (family, stats) in &self.families {
+            writeln!(f, "{:<12}{:>8}{:>8}", family.name(), stats.instructions, stats.bytes)?;
+        }
+
+        Ok(())
+    }
+}
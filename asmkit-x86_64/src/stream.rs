@@ -1,8 +1,32 @@
 //! Instruction streaming implementation.
 
-use asmkit_core::{entity::{EntityList, Label, LabelRef}, InstructionStream, Product};
+use asmkit_core::{
+    bss::{self, Bss},
+    calls::{CallKind, CallSite, CallSiteTarget},
+    displacement::{DisplacementBias, DisplacementForm, DisplacementUnit},
+    entity::{EntityList, EntityRef, Label, LabelRef, SymRef, Symbol},
+    fixed::{CodeStorage, FixedBuf, FixedBufError},
+    labels::LabelTable,
+    listing::Comment,
+    patch::{PatchField, PatchFieldKind, PatchPoint},
+    reloc::{Relocation, RelocationKind, RelocationTarget},
+    size_report::SizeRegions,
+    source_map::{Location, SourceMap},
+    stackmap::StackMaps,
+    stub::CallStub,
+    mangle::mangle_c,
+    target::Target,
+    template::{TemplateFixup, TemplateFixupKind, TemplateTarget},
+    Architecture,
+    InstructionStream,
+    Product,
+};
 
-use crate::register::{Reg64, Reg8, Reg16, Reg32};
+use std::{collections::{HashMap, HashSet}, error::Error, fmt, ops::Range};
+
+use crate::{block::{BlockBody, Blocks}, callconv::CallConv, canonical::{canonical_form, Canonicalization}, defuse::{zeroing_uses, RegSet}, diagnostics::LabelDiagnostic, encoding::EncodingOptions, error::AsmError, features::CpuFeatures, fetch::{is_jcc, jcc_erratum_padding, Align}, frame::{Frame, SlotRef}, mode::Mode, listing::{InstructionListing, InstructionRecord}, memory::Mem, pool::ConstantPool, region::AtomicRegions, register::{GpReg, Reg64, Reg8, Reg16, Reg32}, scratch::ScratchPool, section::{Section, Sections}, stats::{InstructionFamily, Stats}, stub::Stubs};
+#[cfg(feature = "verify")]
+use crate::diagnostics::{check_instruction, EncodingDiagnostic};
 
 pub const REX: u8 = 0b01000000;
 pub const REX_W: u8 = 0b1000;
@@ -10,150 +34,5452 @@ pub const REX_R: u8 = 0b100;
 pub const REX_X: u8 = 0b10;
 pub const REX_B: u8 = 0b1;
 
+/// The size of the pages touched one at a time by [`x86_64InstructionStream::intrinsic_alloc_stack_probed`], the guard page of the stack.
+pub const PAGE_SIZE: u32 = 4096;
+
+/// The largest code of a stream, the reach of the 32-bit displacements.
+pub const MAX_CODE_SIZE: usize = i32::MAX as usize;
+
+/// Checks that code of `len` bytes is within [`MAX_CODE_SIZE`], as the stream does wherever its buffer grows.
+///
+/// # Errors
+/// Returns [`AsmError::CodeTooLarge`] if it isn't.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::{error::AsmError, stream::{check_code_size, MAX_CODE_SIZE}};
+///
+/// assert_eq!(check_code_size(MAX_CODE_SIZE), Ok(()));
+/// assert_eq!(check_code_size(MAX_CODE_SIZE + 1), Err(AsmError::CodeTooLarge(MAX_CODE_SIZE + 1)));
+/// ```
+pub fn check_code_size(len: usize) -> Result<(), AsmError> {
+    match len <= MAX_CODE_SIZE {
+        true => Ok(()),
+        false => Err(AsmError::CodeTooLarge(len)),
+    }
+}
+
+/// The condition code of a conditional jump, tested against the flags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Condition {
+    /// Overflow.
+    O,
+
+    /// No overflow.
+    No,
+
+    /// Below, an unsigned less than.
+    B,
+
+    /// Above or equal, an unsigned greater than or equal.
+    Ae,
+
+    /// Equal.
+    E,
+
+    /// Not equal.
+    Ne,
+
+    /// Below or equal, an unsigned less than or equal.
+    Be,
+
+    /// Above, an unsigned greater than.
+    A,
+
+    /// Sign.
+    S,
+
+    /// No sign.
+    Ns,
+
+    /// Parity even.
+    P,
+
+    /// Parity odd.
+    Np,
+
+    /// Less than, signed.
+    L,
+
+    /// Greater than or equal, signed.
+    Ge,
+
+    /// Less than or equal, signed.
+    Le,
+
+    /// Greater than, signed.
+    G,
+}
+
+impl Condition {
+    /// Every condition, in the order of their condition codes.
+    pub const ALL: [Self; 16] = [
+        Self::O, Self::No, Self::B, Self::Ae, Self::E, Self::Ne, Self::Be, Self::A,
+        Self::S, Self::Ns, Self::P, Self::Np, Self::L, Self::Ge, Self::Le, Self::G,
+    ];
+
+    /// The condition code, for instruction encoding.
+    #[inline(always)]
+    pub fn bits(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The opposite condition, which holds whenever this one doesn't.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::stream::Condition;
+    ///
+    /// assert_eq!(Condition::E.negate(), Condition::Ne);
+    /// assert_eq!(Condition::Le.negate(), Condition::G);
+    ///
+    /// for condition in Condition::ALL {
+    ///     assert_ne!(condition.negate(), condition);
+    ///     assert_eq!(condition.negate().negate(), condition);
+    ///     assert_eq!(condition.negate().flags(), condition.flags());
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn negate(&self) -> Self {
+        // the condition codes come in pairs, differing in their lowest bit.
+        Self::ALL[(self.bits() ^ 1) as usize]
+    }
+
+    /// The condition holding after `cmp lhs, rhs` if `lhs` compares to `rhs` as `ordering`, the operands being signed integers.
+    ///
+    /// # Example
+    /// ```
+    /// use std::cmp::Ordering;
+    ///
+    /// use asmkit_x86_64::stream::Condition;
+    ///
+    /// assert_eq!(Condition::from_signed_cmp(Ordering::Less), Condition::L);
+    /// assert_eq!(Condition::from_signed_cmp(Ordering::Equal), Condition::E);
+    /// assert_eq!(Condition::from_signed_cmp(Ordering::Greater), Condition::G);
+    ///
+    /// // `lhs >= rhs` is "not less".
+    /// assert_eq!(Condition::from_signed_cmp(Ordering::Less).negate(), Condition::Ge);
+    /// ```
+    pub fn from_signed_cmp(ordering: std::cmp::Ordering) -> Self {
+        match ordering {
+            std::cmp::Ordering::Less => Self::L,
+            std::cmp::Ordering::Equal => Self::E,
+            std::cmp::Ordering::Greater => Self::G,
+        }
+    }
+
+    /// The condition holding after `cmp lhs, rhs` if `lhs` compares to `rhs` as `ordering`, the operands being unsigned integers.
+    ///
+    /// # Example
+    /// ```
+    /// use std::cmp::Ordering;
+    ///
+    /// use asmkit_x86_64::stream::Condition;
+    ///
+    /// assert_eq!(Condition::from_unsigned_cmp(Ordering::Less), Condition::B);
+    /// assert_eq!(Condition::from_unsigned_cmp(Ordering::Equal), Condition::E);
+    /// assert_eq!(Condition::from_unsigned_cmp(Ordering::Greater), Condition::A);
+    /// assert_eq!(Condition::from_unsigned_cmp(Ordering::Greater).negate(), Condition::Be);
+    /// ```
+    pub fn from_unsigned_cmp(ordering: std::cmp::Ordering) -> Self {
+        match ordering {
+            std::cmp::Ordering::Less => Self::B,
+            std::cmp::Ordering::Equal => Self::E,
+            std::cmp::Ordering::Greater => Self::A,
+        }
+    }
+}
+
+/// The target of a call or a jump, see [`x86_64InstructionStream::intrinsic_tail_call`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CallTarget {
+    /// A label of the stream, reached with a 32-bit displacement.
+    Label(LabelRef),
+
+    /// An external symbol, reached with a 32-bit displacement left to a relocation.
+    Symbol(SymRef),
+
+    /// The address held in a register.
+    Reg(Reg64),
+
+    /// The address held in memory, such as a slot of a vtable.
+    Mem(Mem),
+}
+
+/// The kind of a label fixup, deciding how the label's offset is encoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixupKind {
+    /// A 32-bit displacement relative to the end of the field, as used by `rip`-relative operands and the 32-bit jumps.  Only valid as the last field of an
+    /// instruction.
+    Rel32,
+
+    /// An 8-bit displacement relative to the end of the field, as used by `loop` and `jrcxz`, reaching 128 bytes back and 127 bytes forward.
+    Rel8,
+
+    /// A 16-bit displacement relative to the end of the field, as used by the near branches of [real mode](crate::mode) code, reaching 32 KiB back
+    /// and forward.  Only valid as the last field of an instruction.
+    Rel16,
+
+    /// A 32-bit displacement followed by an immediate of the given number of bytes, relative to the end of the instruction rather than the end of the
+    /// field, as used by the `rip`-relative operands of the instructions taking an immediate.
+    Rel32Imm(usize),
+
+    /// A 32-bit jump table entry, holding the offset of the label from the table base.
+    TableEntry(LabelRef),
+
+    /// A 16-bit absolute address, left to an [`RelocationKind::Abs16`] relocation against the load address.
+    Abs16,
+
+    /// A 32-bit absolute address, left to an [`RelocationKind::Abs32`] relocation against the load address.
+    Abs32,
+
+    /// A 64-bit absolute address, left to an [`RelocationKind::Abs64`] relocation against the load address.
+    Abs64,
+
+    /// An unsigned field of `size` bytes holding the offset of the label from the label `from`, shifted right by `shift` bits, see
+    /// [`x86_64InstructionStream::data_label_diff`].
+    LabelDiff {
+        /// The label the offset is taken from.
+        from: LabelRef,
+
+        /// The size of the field, in bytes.
+        size: usize,
+
+        /// The number of bits the offset is shifted right by.
+        shift: u32,
+    },
+}
+
+impl FixupKind {
+    /// The size of the field holding the label, in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            Self::Rel8 => 1,
+            Self::Rel16 | Self::Abs16 => 2,
+            Self::Abs64 => 8,
+            Self::LabelDiff { size, .. } => size,
+            _ => 4,
+        }
+    }
+
+    /// How the displacement of a relative kind is measured: in bytes, from the end of the field, or from the end of the immediate following it for
+    /// [`FixupKind::Rel32Imm`].  The table entries, absolute fixups and label differences aren't measured from their field, and have no form.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_x86_64::stream::FixupKind;
+    ///
+    /// assert_eq!(FixupKind::Rel8.form().unwrap().resolve(1, 0x81), Ok(0x7f));
+    /// assert_eq!(FixupKind::Rel32Imm(1).form().unwrap().resolve(2, 0), Ok(-7));
+    /// assert_eq!(FixupKind::Abs64.form(), None);
+    /// ```
+    pub fn form(self) -> Option<DisplacementForm> {
+        let (bias, bits) = match self {
+            Self::Rel8 => (1, 8),
+            Self::Rel16 => (2, 16),
+            Self::Rel32 => (4, 32),
+            Self::Rel32Imm(size) => (4 + size, 32),
+            _ => return None,
+        };
+
+        Some(DisplacementForm::new(DisplacementUnit::Bytes, DisplacementBias::End(bias), bits))
+    }
+}
+
+/// How addresses are materialized by [`x86_64InstructionStream::load_label_address`] and [`x86_64InstructionStream::load_symbol_address`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CodeModel {
+    /// Position-independent code, addressing labels and symbols relative to `rip`.  The code may be loaded anywhere within 2 GiB of its symbols.
+    #[default]
+    Pic,
+
+    /// Absolute 32-bit addresses, for code and symbols loaded in the low 4 GiB of the address space.
+    Small,
+
+    /// Absolute 64-bit addresses, for code and symbols loaded anywhere.
+    LargeAbs,
+}
+
+/// The bytes filling the padding inserted by [`x86_64InstructionStream::attach_label_aligned`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Fill {
+    /// The recommended multi-byte `nop`s, as few as possible, for padding which may be executed.
+    #[default]
+    Nop,
+
+    /// `int3` breakpoints, trapping if the padding is ever executed.
+    Int3,
+
+    /// Zero bytes.
+    Zero,
+}
+
+/// The recommended multi-byte `nop` sequences, by length minus one.
+pub(crate) const NOPS: [&[u8]; 9] = [
+    &[0x90],                                              // nop
+    &[0x66, 0x90],                                        // xchg ax, ax
+    &[0x0f, 0x1f, 0x00],                                  // nop dword ptr [rax]
+    &[0x0f, 0x1f, 0x40, 0x00],                            // nop dword ptr [rax + 0]
+    &[0x0f, 0x1f, 0x44, 0x00, 0x00],                      // nop dword ptr [rax + rax + 0]
+    &[0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00],                // nop word ptr [rax + rax + 0]
+    &[0x0f, 0x1f, 0x80, 0x00, 0x00, 0x00, 0x00],          // nop dword ptr [rax + 0]
+    &[0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],    // nop dword ptr [rax + rax + 0]
+    &[0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00], // nop word ptr [rax + rax + 0]
+];
+
+/// A use of a label, patched once the instruction stream is finished.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fixup {
+    /// The offset of the field holding the label.
+    pub offset: usize,
+
+    /// The label used by the instruction.
+    pub label: LabelRef,
+
+    /// How the label is encoded into the field.
+    pub kind: FixupKind,
+}
+
+impl Fixup {
+    /// The value patched into the field of a relative fixup, whose label is attached at `target` and, for a [`FixupKind::TableEntry`], whose table
+    /// starts at `table`.
+    ///
+    /// # Errors
+    /// Returns [`DisplacementOutOfRange`] if the value doesn't fit in the field: past the reach of an 8-bit or 16-bit displacement, or once the code
+    /// outgrows 2 GiB for a 32-bit one.
+    ///
+    /// # Panics
+    /// Panics if the fixup is absolute, as absolute fixups are left to relocations rather than patched, or if it is a label difference, see
+    /// [`Fixup::difference`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::entity::{EntityRef, LabelRef};
+    /// use asmkit_x86_64::stream::{DisplacementOutOfRange, Fixup, FixupKind};
+    ///
+    /// let fixup = Fixup { offset: 1, label: LabelRef::new(0), kind: FixupKind::Rel32 };
+    /// assert_eq!(fixup.value(0x100, 0), Ok(0xfb));
+    /// assert_eq!(fixup.value(0, 0), Ok(-5));
+    ///
+    /// // a label 3 GiB away.
+    /// assert_eq!(fixup.value(0xc000_0000, 0), Err(DisplacementOutOfRange { offset: 1, displacement: 0xc000_0000 - 5, bits: 32 }));
+    ///
+    /// let fixup = Fixup { offset: 1, label: LabelRef::new(0), kind: FixupKind::Rel8 };
+    /// assert_eq!(fixup.value(0x81, 0), Ok(0x7f));
+    /// assert_eq!(fixup.value(0x82, 0), Err(DisplacementOutOfRange { offset: 1, displacement: 0x80, bits: 8 }));
+    ///
+    /// let fixup = Fixup { offset: 1, label: LabelRef::new(0), kind: FixupKind::Rel16 };
+    /// assert_eq!(fixup.value(0x8002, 0), Ok(0x7fff));
+    /// assert_eq!(fixup.value(0x8003, 0), Err(DisplacementOutOfRange { offset: 1, displacement: 0x8000, bits: 16 }));
+    /// ```
+    pub fn value(&self, target: usize, table: usize) -> Result<i32, DisplacementOutOfRange> {
+        let displacement = match self.kind {
+            FixupKind::TableEntry(_) => target as i64 - table as i64,
+            FixupKind::Abs16 | FixupKind::Abs32 | FixupKind::Abs64 => panic!("absolute fixups are relocated, not patched"),
+            FixupKind::LabelDiff { .. } => panic!("label differences are computed by `Fixup::difference`"),
+            kind => {
+                // the displacements count bytes, so they are never misaligned.
+                let form = kind.form().unwrap();
+                return form.resolve(self.offset, target).map(|value| value as i32).map_err(|_| DisplacementOutOfRange {
+                    offset: self.offset,
+                    displacement: target as i64 - form.origin(self.offset) as i64,
+                    bits: form.bits,
+                });
+            },
+        };
+
+        i32::try_from(displacement).map_err(|_| DisplacementOutOfRange { offset: self.offset, displacement, bits: 32 })
+    }
+
+    /// The value patched into the field of a [`FixupKind::LabelDiff`] fixup, whose label is attached at `target` and whose `from` label is attached
+    /// at `from`.
+    ///
+    /// # Errors
+    /// Returns [`InvalidLabelDiff::NotMultiple`] if the difference isn't a multiple of the scale, and [`InvalidLabelDiff::OutOfRange`] if the
+    /// scaled difference is negative or doesn't fit in the field.
+    ///
+    /// # Panics
+    /// Panics if the fixup isn't a label difference.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::entity::{EntityRef, LabelRef};
+    /// use asmkit_x86_64::stream::{Fixup, FixupKind, InvalidLabelDiff};
+    ///
+    /// let fixup = Fixup { offset: 0, label: LabelRef::new(1), kind: FixupKind::LabelDiff { from: LabelRef::new(0), size: 1, shift: 2 } };
+    /// assert_eq!(fixup.difference(0x20, 0x10), Ok(4));
+    /// assert_eq!(fixup.difference(0x12, 0x10), Err(InvalidLabelDiff::NotMultiple { offset: 0, difference: 2, scale: 4 }));
+    /// assert_eq!(fixup.difference(0x10, 0x20), Err(InvalidLabelDiff::OutOfRange { offset: 0, value: -4, size: 1 }));
+    /// assert_eq!(fixup.difference(0x410, 0x10), Err(InvalidLabelDiff::OutOfRange { offset: 0, value: 0x100, size: 1 }));
+    /// ```
+    pub fn difference(&self, target: usize, from: usize) -> Result<u64, InvalidLabelDiff> {
+        let FixupKind::LabelDiff { size, shift, .. } = self.kind else {
+            panic!("{:?} fixups are not label differences", self.kind);
+        };
+
+        let difference = target as i64 - from as i64;
+        let scale = 1u64 << shift;
+        if difference.rem_euclid(scale as i64) != 0 {
+            return Err(InvalidLabelDiff::NotMultiple { offset: self.offset, difference, scale });
+        }
+
+        let value = difference >> shift;
+        if value < 0 || (size < 8 && value >= 1 << (8 * size)) {
+            return Err(InvalidLabelDiff::OutOfRange { offset: self.offset, value, size });
+        }
+
+        Ok(value as u64)
+    }
+}
+
+/// The error returned when a displacement can't reach its label, see [`Fixup::value`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplacementOutOfRange {
+    /// The offset of the field holding the displacement.
+    pub offset: usize,
+
+    /// The displacement which doesn't fit in the field.
+    pub displacement: i64,
+
+    /// The size of the field, in bits.
+    pub bits: u32,
+}
+
+impl fmt::Display for DisplacementOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "displacement {} of the field at {:#x} doesn't fit in {} bits", self.displacement, self.offset, self.bits)?;
+
+        match self.bits {
+            32 => f.write_str(", the code is too large"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error for DisplacementOutOfRange {}
+
+/// The error returned when the difference of two labels can't be stored in its field, see [`Fixup::difference`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidLabelDiff {
+    /// The difference isn't a multiple of the scale.
+    NotMultiple {
+        /// The offset of the field holding the difference.
+        offset: usize,
+
+        /// The difference of the labels, in bytes.
+        difference: i64,
+
+        /// The scale the difference is divided by.
+        scale: u64,
+    },
+
+    /// The scaled difference is negative, or doesn't fit in the field.
+    OutOfRange {
+        /// The offset of the field holding the difference.
+        offset: usize,
+
+        /// The scaled difference.
+        value: i64,
+
+        /// The size of the field, in bytes.
+        size: usize,
+    },
+}
+
+impl fmt::Display for InvalidLabelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotMultiple { offset, difference, scale } => {
+                write!(f, "label difference {} of the field at {:#x} isn't a multiple of {}", difference, offset, scale)
+            },
+            Self::OutOfRange { offset, value, size } => {
+                write!(f, "label difference {} of the field at {:#x} doesn't fit in an unsigned {}-byte field", value, offset, size)
+            },
+        }
+    }
+}
+
+impl Error for InvalidLabelDiff {}
+
+/// The number of instructions elided by each peephole pattern, see [`x86_64InstructionStream::enable_peephole`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PeepholeCounters {
+    /// Moves from a register to itself.
+    pub self_moves: usize,
+
+    /// `push` and `pop` pairs of the same register, counted as one.
+    pub push_pops: usize,
+
+    /// Additions and subtractions of zero.
+    pub zero_arithmetic: usize,
+}
+
 /// An instruction stream for the x86_64 target.
 #[allow(non_camel_case_types)]
-pub struct x86_64InstructionStream {
+pub struct x86_64InstructionStream<S: CodeStorage = Vec<u8>> {
     /// The bytes written to the instruction stream.
-    bytes: Vec<u8>,
+    bytes: S,
+
+    /// The labels written to the instruction stream.  Their uses aren't chained in the table but kept in `fixups`, as the blocks, the sections
+    /// and the padding move the code after a label is attached.
+    labels: LabelTable<Fixup>,
+
+    /// The label uses which are resolved when the instruction stream is finished.
+    fixups: Vec<Fixup>,
+
+    /// The external symbols used by the instruction stream.
+    symbols: EntityList<Symbol, SymRef>,
+
+    /// The symbols defined in the same image as the code, see [`x86_64InstructionStream::create_local_symbol`].
+    local_symbols: HashSet<SymRef>,
+
+    /// The relocations against external symbols, passed on to the product.
+    relocations: Vec<Relocation>,
+
+    /// The labels exported as symbols, defined in the product.
+    definitions: Vec<(String, LabelRef)>,
+
+    /// The symbol names mangled for the target or given raw, marked in the product so `write_object` doesn't mangle them again.
+    mangled_names: HashSet<String>,
+
+    /// The names given to labels, used in diagnostics.
+    label_names: HashMap<LabelRef, String>,
+
+    /// The comments attached to offsets, passed on to the product.
+    comments: Vec<Comment>,
+
+    /// The location of the source code the next instructions are generated from, see [`x86_64InstructionStream::set_source_location`].
+    source_location: Option<Location>,
+
+    /// The regions the bytes are attributed to, see [`x86_64InstructionStream::push_region`].
+    size_regions: SizeRegions,
+
+    /// The names of the size regions being written, from the outermost, and their numbers in the [`SizeRegions`] of the code being written.
+    size_region_stack: Vec<(String, usize)>,
+
+    /// The calls to [`x86_64InstructionStream::pop_region`] without a size region to end.
+    unbalanced_pops: usize,
+
+    /// The locations of the source code the code was generated from, passed on to the product.
+    source_map: SourceMap,
+
+    /// The fields emitted wider than needed to be patched, passed on to the product, see [`EncodingOptions`].
+    patch_fields: Vec<PatchField>,
+
+    /// The calls to labels and symbols, passed on to the product.
+    call_sites: Vec<CallSite>,
+
+    /// The safepoints recorded by [`x86_64InstructionStream::record_safepoint`], passed on to the product.
+    stack_maps: StackMaps,
+
+    /// The gaps emitted by [`x86_64InstructionStream::emit_patch_point`], passed on to the product.
+    patch_points: Vec<PatchPoint>,
+
+    /// The ids of the patch points emitted so far.
+    patch_point_ids: HashSet<u64>,
+
+    /// Whether the patch points are kept within a cache line, see [`x86_64InstructionStream::enable_patch_point_padding`].
+    patch_point_padding: bool,
+
+    /// The labels attached in the basic block being written, shifted along with its code when the blocks are laid out.
+    body_labels: Vec<LabelRef>,
+
+    /// The basic blocks which are not laid out yet.
+    blocks: Blocks,
+
+    /// The sections of the code, see [`x86_64InstructionStream::switch_section`].
+    sections: Sections,
+
+    /// The stubs of the external symbols, see [`x86_64InstructionStream::set_stub_mode`].
+    stubs: Stubs,
+
+    /// The number of register saves not restored yet, see [`x86_64InstructionStream::save_caller_saved`].
+    save_depth: usize,
+
+    /// The constants laid out after the code, see [`x86_64InstructionStream::add_const`].
+    constants: ConstantPool,
+
+    /// The uninitialized data reserved past the product, see [`x86_64InstructionStream::reserve_bss`].
+    bss: Bss,
+
+    /// How addresses are materialized.
+    code_model: CodeModel,
+
+    /// The operating mode the code is generated for, see [`x86_64InstructionStream::new_with_mode`].
+    mode: Mode,
+
+    /// The CPU features of the target, see [`x86_64InstructionStream::new_with_features`].
+    features: CpuFeatures,
+
+    /// The `__chkstk` routine probing large frames under [`CallConv::Win64`], see [`x86_64InstructionStream::set_chkstk_symbol`].
+    chkstk: Option<SymRef>,
+
+    /// The target the code is generated for, see [`x86_64InstructionStream::set_target`].
+    target: Option<Target>,
+
+    /// The bytes filling alignment padding.
+    align_fill: Fill,
+
+    /// Whether the instructions are re-encoded in a canonical form.
+    canonicalization: Canonicalization,
+
+    /// The boundary the labels are aligned to, see [`x86_64InstructionStream::align_branch_targets`].
+    branch_target_align: Align,
+
+    /// Whether the jumps are kept off the 32-byte boundaries, see [`x86_64InstructionStream::avoid_jcc_erratum_mitigation`].
+    jcc_erratum: bool,
+
+    /// The alignment of the functions, see [`x86_64InstructionStream::function_alignment`].
+    function_align: usize,
+
+    /// Whether the polymorphic emitters pick the smallest encoding.
+    small_encodings: bool,
+
+    /// Whether the peephole patterns are enabled.
+    peephole: bool,
+
+    /// The instructions elided by the peephole patterns.
+    peephole_counters: PeepholeCounters,
+
+    /// The start and end offsets, and the register, of a `push` which was the last instruction emitted, if no label was attached since.
+    last_push: Option<(usize, usize, Reg64)>,
+
+    /// The start and end offsets, and the family, of the last instruction emitted, if no label was attached since.
+    last_instruction: Option<(usize, usize, InstructionFamily)>,
+
+    /// The atomic regions being written, see the [`region`](crate::region) module.
+    regions: AtomicRegions,
+
+    /// Whether statistics are collected.
+    stats_enabled: bool,
+
+    /// The instructions emitted while the listing is enabled, see [`x86_64InstructionStream::enable_listing`].
+    listing: InstructionListing,
+
+    /// The statistics collected since they were enabled.
+    stats: Stats,
+
+    /// The labels used by an instruction but not attached yet, while statistics are collected.
+    unresolved_labels: HashSet<LabelRef>,
+
+    /// The offset of the current basic block, while statistics are collected.
+    block_start: usize,
+
+    /// The offset of the prefixes written for the next instruction, such as `lock`.
+    prefix_start: Option<usize>,
+
+    /// Whether the next instruction addresses memory through 32-bit registers in real mode, set by [`x86_64InstructionStream::a32`].
+    address_size_32: bool,
+
+    /// The frame of the function being generated, set by [`x86_64InstructionStream::intrinsic_prologue`].
+    frame: Option<Frame>,
+
+    /// Whether the operands of the instructions requiring aligned memory are checked.
+    alignment_checks: bool,
+}
+
+// instruction streams are moved to the threads assembling in parallel.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<x86_64InstructionStream>();
+};
+
+impl<S: CodeStorage> InstructionStream for x86_64InstructionStream<S> {
+    #[inline(always)]
+    fn create_label(&mut self) -> LabelRef {
+        self.labels.create()
+    }
+
+    #[inline(always)]
+    fn create_label_attached(&mut self) -> LabelRef {
+        let label = self.create_label();
+        self.attach_label(label);
+        label
+    }
+
+    #[inline(always)]
+    fn attach_label(&mut self, label: LabelRef) {
+        let padding = self.bytes.len().wrapping_neg() & (self.branch_target_align.bytes() - 1);
+        if padding != 0 {
+            self.write_padding(padding);
+        }
+
+        self.last_push = None;
+        self.last_instruction = None;
+        self.block_start = self.bytes.len();
+        self.labels.get_mut(label).state = Label::Attached(self.bytes.len());
+
+        // the labels of the blocks and of the cold text are moved along with their code, as are those of the atomic regions.
+        if self.blocks.current.is_some() || self.sections.current == Section::ColdText {
+            self.body_labels.push(label);
+        }
+
+        if !self.regions.starts.is_empty() {
+            self.regions.labels.push(label);
+        }
+
+        if self.stats_enabled && self.unresolved_labels.remove(&label) {
+            self.stats.unresolved_labels = self.unresolved_labels.len();
+        }
+    }
+
+    #[inline(always)]
+    fn write_byte(&mut self, byte: u8) {
+        self.bytes.extend_from_slice(&[byte]);
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn write_word(&mut self, word: u16) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn write_double_word(&mut self, word: u32) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn write_quad_word(&mut self, word: u64) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn write_double_quad_word(&mut self, word: u128) {
+        self.bytes.extend_from_slice(&word.to_le_bytes());
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+        self.check_growth();
+    }
+
+    #[inline(always)]
+    fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Registers the hole of a template as a use of its label, whose displacement or address is resolved when the stream is finished, or as a
+    /// relocation against its symbol, as for the fields written by the instructions.
+    ///
+    /// # Example
+    /// A template of three instructions, whose jump reaches a label of the stream:
+    /// ```
+    /// use asmkit_core::{template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let template = [
+    ///     0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+    ///     0xe9, 0x00, 0x00, 0x00, 0x00, // jmp exit
+    ///     0xc3,                         // ret
+    /// ];
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// stream.push_reg64(Reg64::Rbx);
+    /// stream.emit_template(&template, &[TemplateFixup::rel32(6, TemplateTarget::Label(exit))]);
+    /// stream.attach_label(exit);
+    /// stream.pop_reg64(Reg64::Rbx);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x53,                         // push rbx
+    ///     0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+    ///     0xe9, 0x01, 0x00, 0x00, 0x00, // jmp exit
+    ///     0xc3,                         // ret
+    ///     0x5b,                         // exit: pop rbx
+    /// ]);
+    /// ```
+    ///
+    /// The holes referring to symbols are relocated:
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, template::{TemplateFixup, TemplateTarget}, InstructionStream};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let handler = stream.create_symbol("handler");
+    /// stream.ret_near();
+    /// stream.emit_template(
+    ///     &[0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xe0], // movabs rax, handler; jmp rax
+    ///     &[TemplateFixup::abs64(2, TemplateTarget::Symbol(handler))],
+    /// );
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 3, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(handler), addend: 0 },
+    /// ]);
+    /// ```
+    fn record_template_fixup(&mut self, fixup: TemplateFixup) {
+        match (fixup.target, fixup.kind) {
+            (TemplateTarget::Label(label), TemplateFixupKind::Rel32) => self.record_label_use(fixup.offset, label, FixupKind::Rel32),
+            (TemplateTarget::Label(label), TemplateFixupKind::Abs64) => self.record_label_use(fixup.offset, label, FixupKind::Abs64),
+            (TemplateTarget::Symbol(symbol), kind) => {
+                let (kind, addend) = match kind {
+                    // the displacement is relative to the end of the hole, 4 bytes past its start.
+                    TemplateFixupKind::Rel32 => (RelocationKind::Rel32, -4),
+                    TemplateFixupKind::Abs64 => (RelocationKind::Abs64, 0),
+                };
+                self.relocations.push(Relocation { offset: fixup.offset, kind, target: RelocationTarget::Symbol(symbol), addend });
+            },
+        }
+    }
+
+    /// # Panics
+    /// Panics if a label is used but never attached, reporting it as a [`LabelDiagnostic::Undefined`], if basic blocks were created but never laid
+    /// out, if an atomic region is never ended, if the calls to [`x86_64InstructionStream::push_region`] and
+    /// [`x86_64InstructionStream::pop_region`] are unbalanced, with [`AsmError::CodeTooLarge`] if the code is larger than [`MAX_CODE_SIZE`], or
+    /// with [`AsmError::BufferFull`] if it doesn't fit in the [`FixedBuf`] it's written to.
+    fn finish(mut self) -> Product {
+        let stubs = self.resolve().unwrap_or_else(|error| panic!("{}", error));
+
+        let label_offsets = (0..self.labels.len()).map(|index| self.label_offset(LabelRef::new(index))).collect::<Vec<_>>();
+        let named_labels = std::mem::take(&mut self.label_names)
+            .into_iter()
+            .filter_map(|(label, name)| Some((name, label_offsets[label.as_usize()]?)))
+            .collect::<Vec<_>>();
+
+        let definitions = std::mem::take(&mut self.definitions)
+            .into_iter()
+            .map(|(name, label)| (name, self.attached_offset(label)))
+            .collect::<Vec<_>>();
+
+        // the parts are taken rather than moved out, as the stream is dropped afterwards, see `Drop`.
+        let (bytes, relocations, symbols) = (self.bytes.take(), std::mem::take(&mut self.relocations), std::mem::take(&mut self.symbols));
+        let mut product = Product::with_relocations(bytes, relocations, symbols);
+        for (name, offset) in definitions {
+            product.define(&name, offset);
+        }
+
+        for name in std::mem::take(&mut self.mangled_names) {
+            product.mark_mangled(&name);
+        }
+
+        for comment in std::mem::take(&mut self.comments) {
+            product.comment(comment.offset, comment.text);
+        }
+
+        *product.source_map_mut() = std::mem::take(&mut self.source_map);
+        *product.size_regions_mut() = std::mem::take(&mut self.size_regions);
+        *product.stack_maps_mut() = std::mem::take(&mut self.stack_maps);
+        product.set_bss(std::mem::take(&mut self.bss));
+
+        for field in std::mem::take(&mut self.patch_fields) {
+            product.add_patch_field(field);
+        }
+
+        for site in std::mem::take(&mut self.call_sites) {
+            product.add_call_site(site);
+        }
+
+        for point in std::mem::take(&mut self.patch_points) {
+            product.add_patch_point(point);
+        }
+
+        for stub in stubs {
+            product.add_stub(stub);
+        }
+
+        product.set_label_offsets(label_offsets);
+        for (name, offset) in named_labels {
+            product.add_named_label(&name, offset);
+        }
+
+        product
+    }
+
+    #[inline(always)]
+    fn finish_boxed(self: Box<Self>) -> Product {
+        (*self).finish()
+    }
+
+    /// Discards the stream without producing a product, leaving out the check of [`Drop`] that a stream isn't dropped with its code half written.
+    ///
+    /// # Example
+    /// A stream dropped while unwinding from a panic is left alone, so the panic isn't turned into an abort:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let result = std::panic::catch_unwind(|| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     let exit = stream.create_label();
+    ///     stream.jmp_label(exit);
+    ///     panic!("code generation failed");
+    /// });
+    /// assert!(result.is_err());
+    ///
+    /// // an abandoned stream isn't checked.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// stream.jmp_label(exit);
+    /// stream.abandon();
+    /// ```
+    fn abandon(mut self) {
+        // the drop check only looks at the label uses.
+        self.fixups.clear();
+    }
+
+    #[inline(always)]
+    fn abandon_boxed(self: Box<Self>) {
+        (*self).abandon()
+    }
+}
+
+impl<S: CodeStorage> Drop for x86_64InstructionStream<S> {
+    /// # Panics
+    /// In debug builds, panics if the stream has code using labels which are never attached, as it is dropped neither finished nor abandoned,
+    /// such as when code generation returns early and half a function is silently discarded.  A stream dropped while unwinding from another panic
+    /// is left alone.
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// stream.jmp_label(exit);
+    /// drop(stream);
+    /// # #[cfg(not(debug_assertions))]
+    /// # panic!();
+    /// ```
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && !std::thread::panicking() {
+            let unresolved = self.fixups.iter().filter(|fixup| matches!(self.labels.get(fixup.label).state, Label::Unattached)).count();
+            assert!(
+                unresolved == 0,
+                "instruction stream dropped without being finished or abandoned, with {} bytes of code and {} uses of unattached labels",
+                self.bytes.len(),
+                unresolved,
+            );
+        }
+    }
+}
+
+impl Default for x86_64InstructionStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl x86_64InstructionStream {
+    /// Creates a new, empty instruction stream, with every CPU feature enabled.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::new_with_features(CpuFeatures::ALL)
+    }
+
+    /// Creates a new, empty instruction stream generating code for the operating mode `mode`, on a target with the CPU features `features`, see the
+    /// [`mode`](crate::mode) module.
+    pub fn new_with_mode(mode: Mode, features: CpuFeatures) -> Self {
+        let mut stream = Self::new_with_features(features);
+        stream.mode = mode;
+        stream
+    }
+
+    /// Creates a new, empty instruction stream for a target with the CPU features `features`.  The emitters of the instructions requiring a feature
+    /// which isn't enabled panic, see [`x86_64InstructionStream::check_feature`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, register::Xmm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::ALL.without(CpuFeatures::AVX | CpuFeatures::AVX2));
+    /// stream.addps_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    /// stream.pmulld_xmm_xmm(Xmm::Xmm1, Xmm::Xmm2);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0x58, 0xca,             // addps xmm1, xmm2
+    ///     0x66, 0x0f, 0x38, 0x40, 0xca, // pmulld xmm1, xmm2
+    /// ]);
+    /// ```
+    ///
+    /// The instructions of the disabled features are rejected:
+    /// ```should_panic
+    /// use asmkit_x86_64::{features::CpuFeatures, register::Ymm, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_features(CpuFeatures::ALL.without(CpuFeatures::AVX | CpuFeatures::AVX2));
+    /// stream.vaddps_ymm_ymm_ymm(Ymm::Ymm1, Ymm::Ymm2, Ymm::Ymm3);
+    /// ```
+    pub fn new_with_features(features: CpuFeatures) -> Self {
+        Self::with_storage(Vec::new(), features)
+    }
+}
+
+impl x86_64InstructionStream<FixedBuf<'_>> {
+    /// Finishes writing to the instruction stream in the buffer of its [`FixedBuf`], resolving the label uses in place, and returns the number of
+    /// bytes written.  The parts of a product other than its bytes, such as the comments and the source map, are dropped.
+    ///
+    /// # Errors
+    /// Returns [`AsmError::BufferFull`] if the code doesn't fit in the buffer, whose bytes are then left unresolved.
+    ///
+    /// # Panics
+    /// Panics as [`InstructionStream::finish`] does, and if the code needs relocations, which can't be applied in place.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{fixed::FixedBuf, InstructionStream};
+    /// use asmkit_x86_64::{error::AsmError, features::CpuFeatures, register::Reg64, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// // 64 bytes of code, ending with the last byte of the buffer.
+    /// let emit = |buffer: &mut [u8], extra: bool| {
+    ///     let mut stream = x86_64InstructionStream::with_storage(FixedBuf::new(buffer), CpuFeatures::ALL);
+    ///     let (top, exit) = (stream.create_label(), stream.create_label());
+    ///     stream.attach_label(top);
+    ///     stream.add_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+    ///     stream.jcc_label(Condition::E, exit);
+    ///     stream.jmp_label(top);
+    ///     stream.attach_label(exit);
+    ///     for reg in [Reg64::Rax, Reg64::Rcx, Reg64::Rdx, Reg64::Rbx] {
+    ///         stream.mov_reg64_imm64(reg, u64::MAX);
+    ///     }
+    ///     stream.nop(9);
+    ///     stream.ret_near();
+    ///     if extra {
+    ///         stream.int3();
+    ///     }
+    ///
+    ///     stream.finish_in_place()
+    /// };
+    ///
+    /// let mut buffer = [0; 64];
+    /// assert_eq!(emit(&mut buffer, false), Ok(64));
+    /// assert_eq!(buffer[..14], [
+    ///     0x48, 0x01, 0xf8,                   // top: add rax, rdi
+    ///     0x0f, 0x84, 0x05, 0x00, 0x00, 0x00, // je exit
+    ///     0xe9, 0xf2, 0xff, 0xff, 0xff,       // jmp top
+    /// ]);
+    /// assert_eq!(buffer[63], 0xc3);
+    ///
+    /// // one more byte doesn't fit.
+    /// assert_eq!(emit(&mut buffer, true), Err(AsmError::BufferFull(64)));
+    /// ```
+    pub fn finish_in_place(mut self) -> Result<usize, AsmError> {
+        if let Err(error) = self.resolve() {
+            self.abandon();
+            return Err(error);
+        }
+
+        assert!(self.relocations.is_empty(), "the code needs relocations, which can't be applied in place, see `finish`");
+        Ok(self.bytes.len())
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Creates a new, empty instruction stream writing its code into `storage`, for a target with the CPU features `features`.  A [`FixedBuf`]
+    /// writes it into a buffer of the caller, without growing, and the stream is then finished in place by
+    /// [`x86_64InstructionStream::finish_in_place`].
+    ///
+    /// The code of the basic blocks and the sections other than the one being written is kept aside in a `Vec<u8>`, and swapped with the storage
+    /// by copying it when switching to them.
+    pub fn with_storage(storage: S, features: CpuFeatures) -> Self {
+        Self {
+            bytes: storage,
+            labels: LabelTable::new(),
+            fixups: Vec::new(),
+            symbols: EntityList::new(),
+            local_symbols: HashSet::new(),
+            relocations: Vec::new(),
+            definitions: Vec::new(),
+            mangled_names: HashSet::new(),
+            label_names: HashMap::new(),
+            comments: Vec::new(),
+            source_location: None,
+            size_regions: SizeRegions::default(),
+            size_region_stack: Vec::new(),
+            unbalanced_pops: 0,
+            source_map: SourceMap::default(),
+            patch_fields: Vec::new(),
+            call_sites: Vec::new(),
+            stack_maps: StackMaps::default(),
+            patch_points: Vec::new(),
+            patch_point_ids: HashSet::new(),
+            patch_point_padding: false,
+            body_labels: Vec::new(),
+            blocks: Blocks::default(),
+            sections: Sections::default(),
+            stubs: Stubs::default(),
+            save_depth: 0,
+            constants: ConstantPool::default(),
+            bss: Bss::new(),
+            code_model: CodeModel::Pic,
+            mode: Mode::Long,
+            features,
+            chkstk: None,
+            target: None,
+            align_fill: Fill::Nop,
+            canonicalization: Canonicalization::AsEmitted,
+            branch_target_align: Align::None,
+            jcc_erratum: false,
+            function_align: 1,
+            small_encodings: false,
+            peephole: false,
+            peephole_counters: PeepholeCounters::default(),
+            last_push: None,
+            last_instruction: None,
+            regions: AtomicRegions::default(),
+            stats_enabled: false,
+            listing: InstructionListing::default(),
+            stats: Stats::default(),
+            unresolved_labels: HashSet::new(),
+            block_start: 0,
+            prefix_start: None,
+            address_size_32: false,
+            frame: None,
+            alignment_checks: false,
+        }
+    }
+
+    /// The operating mode the stream generates code for, [`Mode::Long`] unless created by [`x86_64InstructionStream::new_with_mode`].
+    #[inline(always)]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Swaps the code being written with `body`, such as when switching to another basic block.
+    ///
+    /// # Panics
+    /// Panics if an atomic region is being written, as its code would be split.
+    pub(crate) fn swap_body(&mut self, body: &mut BlockBody) {
+        assert!(self.regions.starts.is_empty(), "an atomic region can't span basic blocks or sections");
+
+        self.bytes.swap(&mut body.bytes);
+        std::mem::swap(&mut self.fixups, &mut body.fixups);
+        std::mem::swap(&mut self.relocations, &mut body.relocations);
+        std::mem::swap(&mut self.body_labels, &mut body.labels);
+        std::mem::swap(&mut self.comments, &mut body.comments);
+        std::mem::swap(&mut self.source_map, &mut body.source_map);
+        std::mem::swap(&mut self.size_regions, &mut body.size_regions);
+        std::mem::swap(&mut self.listing.records, &mut body.instructions);
+        std::mem::swap(&mut self.listing.regions, &mut body.regions);
+        std::mem::swap(&mut self.patch_fields, &mut body.patch_fields);
+        std::mem::swap(&mut self.call_sites, &mut body.call_sites);
+        std::mem::swap(&mut self.stack_maps, &mut body.stack_maps);
+        std::mem::swap(&mut self.patch_points, &mut body.patch_points);
+
+        // the location carries over to the code of the other block.
+        self.source_map.record(self.bytes.len(), self.source_location);
+        self.record_size_region_stack();
+
+        self.last_push = None;
+        self.last_instruction = None;
+        self.prefix_start = None;
+        self.address_size_32 = false;
+        self.block_start = self.bytes.len();
+    }
+
+    /// Appends the code of a basic block, shifting its label uses, relocations and labels to where it lands, and returns its offset.
+    pub(crate) fn append_body(&mut self, body: BlockBody) -> usize {
+        let base = self.bytes.len();
+        self.bytes.extend_from_slice(&body.bytes);
+        self.check_growth();
+        self.fixups.extend(body.fixups.into_iter().map(|fixup| Fixup { offset: fixup.offset + base, ..fixup }));
+        self.relocations.extend(body.relocations.into_iter().map(|relocation| Relocation { offset: relocation.offset + base, ..relocation }));
+        self.comments.extend(body.comments.into_iter().map(|comment| Comment { offset: comment.offset + base, ..comment }));
+        self.source_map.append(base, &body.source_map);
+        self.size_regions.append(base, &body.size_regions);
+        self.listing.records.extend(body.instructions.into_iter().map(|record| InstructionRecord { offset: record.offset + base, ..record }));
+        self.listing.regions.extend(body.regions.into_iter().map(|region| region.start + base..region.end + base));
+        self.patch_fields.extend(body.patch_fields.into_iter().map(|field| PatchField { offset: field.offset + base, ..field }));
+        self.call_sites.extend(body.call_sites.into_iter().map(|site| CallSite { offset: site.offset + base, ..site }));
+        self.stack_maps.append(base, &body.stack_maps);
+        self.patch_points.extend(body.patch_points.into_iter().map(|point| PatchPoint { offset: point.offset + base, ..point }));
+
+        for &label in &body.labels {
+            if let Label::Attached(offset) = &mut self.labels.get_mut(label).state {
+                *offset += base;
+            }
+        }
+
+        // the code appended to the cold text moves along with it.
+        if self.sections.current == Section::ColdText {
+            self.body_labels.extend(body.labels);
+        }
+
+        // the code written next is in the size regions being written, rather than in those of the block.
+        self.record_size_region_stack();
+
+        self.last_push = None;
+        self.last_instruction = None;
+        self.block_start = base;
+        base
+    }
+
+    /// Attaches `label` to `offset`, at or before the current offset.
+    pub(crate) fn attach_label_at(&mut self, label: LabelRef, offset: usize) {
+        self.labels.get_mut(label).state = Label::Attached(offset);
+
+        if self.sections.current == Section::ColdText {
+            self.body_labels.push(label);
+        }
+
+        if !self.regions.starts.is_empty() {
+            self.regions.labels.push(label);
+        }
+
+        if self.stats_enabled && self.unresolved_labels.remove(&label) {
+            self.stats.unresolved_labels = self.unresolved_labels.len();
+        }
+    }
+
+    /// Lays out the code written aside and resolves the label uses, as the stream is finished, and returns the call stubs.
+    ///
+    /// # Errors
+    /// Returns [`AsmError::BufferFull`] if the code doesn't fit in the storage of the stream.
+    ///
+    /// # Panics
+    /// Panics as [`InstructionStream::finish`] does.
+    fn resolve(&mut self) -> Result<Vec<CallStub>, AsmError> {
+        assert!(self.blocks.list.is_empty(), "basic blocks are created but never laid out, see `seal_and_layout`");
+        assert!(self.regions.starts.is_empty(), "an atomic region is never ended, see `end_atomic_region`");
+        if let Some((name, _)) = self.size_region_stack.last() {
+            panic!("the size region `{}` is never popped, see `pop_region`", name);
+        }
+
+        assert!(self.unbalanced_pops == 0, "`pop_region` is called {} more times than `push_region`", self.unbalanced_pops);
+        self.lay_out_cold_text();
+        if !self.stubs.labels.is_empty() {
+            // the stubs are shared by the branches to a symbol, and belong to none of them.
+            self.source_map.record(self.bytes.len(), None);
+        }
+
+        let stubs = self.lay_out_stubs();
+        if !self.constants.is_empty() {
+            // the constants aren't generated from the source code of the instructions before them.
+            self.source_map.record(self.bytes.len(), None);
+            self.lay_out_constants();
+        }
+
+        self.check_growth();
+        if let Some(FixedBufError::BufferFull { capacity }) = self.bytes.error() {
+            return Err(AsmError::BufferFull(capacity));
+        }
+
+        let bss_offset = bss::bss_offset(self.bytes.len());
+        for reserved in self.bss.labels() {
+            self.labels.get_mut(reserved.label).state = Label::Attached(bss_offset + reserved.offset);
+        }
+
+        if let Some(diagnostic) = self.validate().into_iter().find(LabelDiagnostic::is_fatal) {
+            panic!("{}", diagnostic);
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            if let Label::Absolute(address) = self.labels.get(fixup.label).state {
+                self.write_absolute_fixup(&fixup, address);
+                continue;
+            }
+
+            let target = self.attached_offset(fixup.label);
+            let table = match fixup.kind {
+                FixupKind::TableEntry(base) => self.attached_offset(base),
+                FixupKind::Abs16 | FixupKind::Abs32 | FixupKind::Abs64 => {
+                    // the absolute address of a label is only known once the code is loaded.
+                    let kind = match fixup.kind {
+                        FixupKind::Abs16 => RelocationKind::Abs16,
+                        FixupKind::Abs32 => RelocationKind::Abs32,
+                        _ => RelocationKind::Abs64,
+                    };
+                    self.relocations.push(Relocation { offset: fixup.offset, kind, target: RelocationTarget::Base, addend: target as i64 });
+                    continue;
+                },
+                FixupKind::Rel8 => {
+                    // out of range displacements are reported by `validate`.
+                    let value = fixup.value(target, 0).unwrap_or_else(|error| panic!("{}", error));
+                    self.bytes[fixup.offset] = value as u8;
+                    continue;
+                },
+                FixupKind::Rel16 => {
+                    let value = fixup.value(target, 0).unwrap_or_else(|error| panic!("{}", error));
+                    self.bytes[fixup.offset..fixup.offset + 2].copy_from_slice(&(value as u16).to_le_bytes());
+                    continue;
+                },
+                FixupKind::Rel32 | FixupKind::Rel32Imm(_) => 0,
+                FixupKind::LabelDiff { from, size, .. } => {
+                    let value = fixup.difference(target, self.attached_offset(from)).unwrap_or_else(|error| panic!("{}", error));
+                    self.bytes[fixup.offset..fixup.offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+                    continue;
+                },
+            };
+
+            let value = fixup.value(target, table).unwrap_or_else(|error| panic!("{}", error));
+            self.bytes[fixup.offset..fixup.offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        self.relocations.sort_by_key(|relocation| relocation.offset);
+
+        Ok(stubs)
+    }
+
+    /// Checks the size of the code, where the buffer grows: a branch on its length, rather than a check of each byte.
+    ///
+    /// # Panics
+    /// Panics with [`AsmError::CodeTooLarge`] if the code is larger than [`MAX_CODE_SIZE`].
+    #[inline(always)]
+    pub(crate) fn check_growth(&self) {
+        if let Err(error) = check_code_size(self.bytes.len()) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Inserts `padding` bytes of the [`Fill`] set by [`x86_64InstructionStream::set_align_fill`] at the current offset, or before the outermost
+    /// atomic region being written, moving its code along, see the [`region`](crate::region) module.
+    pub(crate) fn write_padding(&mut self, padding: usize) {
+        let end = self.bytes.len();
+        match self.align_fill {
+            Fill::Nop => self.write_nops(padding),
+            Fill::Int3 => self.bytes.resize(end + padding, 0xcc),
+            Fill::Zero => self.bytes.resize(end + padding, 0),
+        }
+
+        self.check_growth();
+
+        // the padding dropped by a full buffer moves nothing.
+        if self.bytes.error().is_some() {
+            return;
+        }
+
+        if let Some(&start) = self.regions.starts.first() {
+            self.bytes[start..].rotate_right(padding);
+            self.shift_code(start, padding);
+        }
+    }
+
+    /// Shifts the code from `offset` on, its label uses, relocations and the labels attached within the atomic regions, by the `len` bytes inserted
+    /// at `offset`.
+    fn shift_code(&mut self, offset: usize, len: usize) {
+        let shift = |at: &mut usize| if *at >= offset { *at += len };
+
+        self.fixups.iter_mut().for_each(|fixup| shift(&mut fixup.offset));
+        self.relocations.iter_mut().for_each(|relocation| shift(&mut relocation.offset));
+        self.comments.iter_mut().for_each(|comment| shift(&mut comment.offset));
+        self.source_map.shift(offset, len);
+        self.size_regions.shift(offset, len);
+        self.listing.records.iter_mut().for_each(|record| shift(&mut record.offset));
+        for region in &mut self.listing.regions {
+            if region.start >= offset {
+                *region = region.start + len..region.end + len;
+            }
+        }
+        self.patch_fields.iter_mut().for_each(|field| shift(&mut field.offset));
+        self.call_sites.iter_mut().for_each(|site| shift(&mut site.offset));
+        self.stack_maps.shift(offset, len);
+        self.patch_points.iter_mut().for_each(|point| shift(&mut point.offset));
+        self.regions.starts.iter_mut().for_each(shift);
+        shift(&mut self.block_start);
+
+        for &label in &self.regions.labels {
+            if let Label::Attached(attached) = &mut self.labels.get_mut(label).state {
+                shift(attached);
+            }
+        }
+    }
+
+    /// The atomic regions being written.
+    #[inline(always)]
+    pub(crate) fn regions(&mut self) -> &mut AtomicRegions {
+        &mut self.regions
+    }
+
+    /// The atomic regions being written, for reading.
+    #[inline(always)]
+    pub(crate) fn regions_ref(&self) -> &AtomicRegions {
+        &self.regions
+    }
+
+    /// Records the atomic region spanning `range` in the listing.
+    #[inline(always)]
+    pub(crate) fn record_atomic_region(&mut self, range: Range<usize>) {
+        self.listing.record_region(range);
+    }
+
+    /// Forgets the last instruction, so the next `pop` isn't folded with a `push` nor the next jump fused with an arithmetic instruction.
+    #[inline(always)]
+    pub(crate) fn clear_last_instruction(&mut self) {
+        self.last_push = None;
+        self.last_instruction = None;
+    }
+
+    /// The basic blocks which are not laid out yet.
+    #[inline(always)]
+    pub(crate) fn blocks(&mut self) -> &mut Blocks {
+        &mut self.blocks
+    }
+
+    /// The sections of the code.
+    #[inline(always)]
+    pub(crate) fn sections(&mut self) -> &mut Sections {
+        &mut self.sections
+    }
+
+    /// The stubs of the external symbols.
+    #[inline(always)]
+    pub(crate) fn stubs(&mut self) -> &mut Stubs {
+        &mut self.stubs
+    }
+
+    /// The stubs of the external symbols, for reading.
+    #[inline(always)]
+    pub(crate) fn stubs_ref(&self) -> &Stubs {
+        &self.stubs
+    }
+
+    /// Records a patch point with the id `id`, taking the `size` bytes at the current offset.
+    ///
+    /// # Panics
+    /// Panics if a patch point was already emitted with the id `id`.
+    pub(crate) fn record_patch_point(&mut self, id: u64, size: usize) {
+        assert!(self.patch_point_ids.insert(id), "patch point {} is emitted twice", id);
+        self.patch_points.push(PatchPoint { id, offset: self.bytes.len(), size });
+    }
+
+    /// Whether the patch points are kept within a cache line, see [`x86_64InstructionStream::enable_patch_point_padding`].
+    #[inline(always)]
+    pub(crate) fn patch_point_padding(&mut self) -> &mut bool {
+        &mut self.patch_point_padding
+    }
+
+    /// The safepoints recorded so far.
+    #[inline(always)]
+    pub(crate) fn stack_maps(&mut self) -> &mut StackMaps {
+        &mut self.stack_maps
+    }
+
+    /// The sections of the code, for reading.
+    #[inline(always)]
+    pub(crate) fn sections_ref(&self) -> &Sections {
+        &self.sections
+    }
+
+    /// The number of register saves not restored yet.
+    #[inline(always)]
+    pub(crate) fn save_depth(&mut self) -> &mut usize {
+        &mut self.save_depth
+    }
+
+    /// The code written so far.
+    #[inline(always)]
+    pub(crate) fn code(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The number of labels created so far.
+    #[inline(always)]
+    pub(crate) fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Creates a stream encoding as this one does, holding as many bytes and the same labels, symbols, stubs, constants and frame, so the code
+    /// written to it is encoded as it would be here.  Its bytes are zeroed rather than copied, and the statistics and the listing are disabled.
+    pub(crate) fn scratch(&self) -> x86_64InstructionStream {
+        let mut stream = x86_64InstructionStream::new_with_mode(self.mode, self.features);
+        stream.bytes = vec![0; self.bytes.len()];
+        stream.labels = self.labels.clone();
+        stream.symbols = self.symbols.clone();
+        stream.stubs = self.stubs.clone();
+        stream.constants = self.constants.clone();
+        stream.code_model = self.code_model;
+        stream.target = self.target;
+        stream.chkstk = self.chkstk;
+        stream.align_fill = self.align_fill;
+        stream.canonicalization = self.canonicalization;
+        stream.branch_target_align = self.branch_target_align;
+        stream.jcc_erratum = self.jcc_erratum;
+        stream.function_align = self.function_align;
+        stream.last_instruction = self.last_instruction;
+        stream.small_encodings = self.small_encodings;
+        stream.peephole = self.peephole;
+        stream.address_size_32 = self.address_size_32;
+        stream.frame = self.frame.clone();
+        stream.alignment_checks = self.alignment_checks;
+        stream
+    }
+
+    /// The instructions recorded while the listing is enabled.
+    #[inline(always)]
+    pub(crate) fn instruction_listing(&self) -> &InstructionListing {
+        &self.listing
+    }
+
+    /// The instructions recorded while the listing is enabled, such as to set the text of the next instruction.
+    #[cfg(feature = "text-asm")]
+    #[inline(always)]
+    pub(crate) fn instruction_listing_mut(&mut self) -> &mut InstructionListing {
+        &mut self.listing
+    }
+
+    /// The constant pool.
+    #[inline(always)]
+    pub(crate) fn constants(&mut self) -> &mut ConstantPool {
+        &mut self.constants
+    }
+
+    /// The offset `label` is attached to.
+    ///
+    /// # Panics
+    /// Panics if the label is not attached.
+    fn attached_offset(&self, label: LabelRef) -> usize {
+        match self.labels.get(label).state {
+            Label::Attached(offset) => offset,
+            Label::Unattached => panic!("label {:?} is used but never attached", label),
+            Label::Absolute(address) => panic!("label {:?} is bound to the absolute address {:#x}, not attached to an offset", label, address),
+        }
+    }
+
+    /// Writes the field of `fixup`, whose label is bound to the absolute `address`: a displacement is left to a relocation against the address,
+    /// resolved once the load address is known, and an absolute field holds the address itself.
+    ///
+    /// # Panics
+    /// Panics if the field is an 8 or 16-bit displacement, a jump table entry or a label difference, which can't refer to an absolute address,
+    /// or if the address doesn't fit in an absolute field.
+    fn write_absolute_fixup(&mut self, fixup: &Fixup, address: u64) {
+        let field = fixup.offset..fixup.offset + fixup.kind.size();
+        match fixup.kind {
+            FixupKind::Rel32 | FixupKind::Rel32Imm(_) => {
+                // the displacement is relative to the end of the instruction, past the immediate.
+                let addend = -4 - match fixup.kind {
+                    FixupKind::Rel32Imm(size) => size as i64,
+                    _ => 0,
+                };
+                self.relocations.push(Relocation { offset: fixup.offset, kind: RelocationKind::Rel32, target: RelocationTarget::Absolute(address), addend });
+            },
+            FixupKind::Abs16 => {
+                let address = u16::try_from(address).unwrap_or_else(|_| panic!("address {:#x} of label {:?} doesn't fit in 16 bits", address, fixup.label));
+                self.bytes[field].copy_from_slice(&address.to_le_bytes());
+            },
+            FixupKind::Abs32 => {
+                let address = u32::try_from(address).unwrap_or_else(|_| panic!("address {:#x} of label {:?} doesn't fit in 32 bits", address, fixup.label));
+                self.bytes[field].copy_from_slice(&address.to_le_bytes());
+            },
+            FixupKind::Abs64 => self.bytes[field].copy_from_slice(&address.to_le_bytes()),
+            FixupKind::Rel8 | FixupKind::Rel16 | FixupKind::TableEntry(_) | FixupKind::LabelDiff { .. } => {
+                panic!("label {:?} is bound to the absolute address {:#x}, out of reach of a {:?} field", fixup.label, address, fixup.kind)
+            },
+        }
+    }
+
+    /// Writes a field holding `label`, patched when the instruction stream is finished.
+    pub(crate) fn write_label(&mut self, label: LabelRef, kind: FixupKind) {
+        if matches!(kind, FixupKind::Rel32 | FixupKind::Rel32Imm(_)) {
+            self.check_legacy_rip_relative();
+        }
+
+        self.record_label_use(self.bytes.len(), label, kind);
+        self.bytes.resize(self.bytes.len() + kind.size(), 0);
+        self.check_growth();
+    }
+
+    /// Records a use of `label` in the field at `offset`, patched when the instruction stream is finished.
+    fn record_label_use(&mut self, offset: usize, label: LabelRef, kind: FixupKind) {
+        self.fixups.push(Fixup { offset, label, kind });
+        self.labels.get_mut(label).uses += 1;
+
+        if self.stats_enabled && self.labels.get(label).state == Label::Unattached {
+            self.unresolved_labels.insert(label);
+            self.stats.unresolved_labels = self.unresolved_labels.len();
+        }
+    }
+
+    /// Writes a field holding the address of `symbol`, left to a relocation.
+    pub(crate) fn write_symbol(&mut self, symbol: SymRef, kind: RelocationKind, addend: i64) {
+        if kind != RelocationKind::Abs32 && kind != RelocationKind::Abs64 {
+            self.check_legacy_rip_relative();
+        }
+
+        self.relocations.push(Relocation { offset: self.bytes.len(), kind, target: RelocationTarget::Symbol(symbol), addend });
+
+        match kind {
+            RelocationKind::Abs64 => self.write_quad_word(0),
+            _ => self.write_double_word(0),
+        }
+    }
+
+    /// Writes the 32-bit displacement of a direct branch to `symbol`, to its stub if it goes through one, see [`x86_64InstructionStream::set_stub_mode`].
+    fn write_symbol_branch(&mut self, symbol: SymRef) {
+        match self.stub_target(symbol) {
+            Some(stub) => self.write_label(stub, FixupKind::Rel32),
+            None => self.write_symbol(symbol, RelocationKind::Rel32, -4),
+        }
+    }
+
+    /// Makes the polymorphic emitters of the [`generic`](crate::generic) module pick the smallest encoding with the same effect, rather than always the
+    /// same form for a combination of operands.  Disabled by default.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{generic::{Add, Mov}, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let emit = |small, f: &dyn Fn(&mut x86_64InstructionStream)| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.prefer_small_encodings(small);
+    ///     f(&mut stream);
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// let one = |stream: &mut x86_64InstructionStream| stream.mov(Reg64::Rax, 1u64);
+    /// assert_eq!(emit(false, &one), [0x48, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]); // movabs rax, 1
+    /// assert_eq!(emit(true, &one), [0xb8, 0x01, 0x00, 0x00, 0x00]);                                 // mov eax, 1
+    ///
+    /// // zero extended from 32 bits.
+    /// let max = |stream: &mut x86_64InstructionStream| stream.mov(Reg64::R9, u32::MAX as u64);
+    /// assert_eq!(emit(true, &max), [0x41, 0xb9, 0xff, 0xff, 0xff, 0xff]); // mov r9d, 0xffffffff
+    ///
+    /// // negative values are sign extended, never loaded through a 32-bit register.
+    /// let minus_one = |stream: &mut x86_64InstructionStream| stream.mov(Reg64::Rax, -1i64);
+    /// assert_eq!(emit(true, &minus_one), [0x48, 0xc7, 0xc0, 0xff, 0xff, 0xff, 0xff]); // mov rax, -1
+    ///
+    /// let large = |stream: &mut x86_64InstructionStream| stream.mov(Reg64::Rax, 1u64 << 32);
+    /// assert_eq!(emit(true, &large), [0x48, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]); // movabs rax, 0x100000000
+    ///
+    /// // the accumulator has shorter arithmetic forms.
+    /// let add = |stream: &mut x86_64InstructionStream| stream.add(Reg64::Rax, 0x1000);
+    /// assert_eq!(emit(false, &add), [0x48, 0x81, 0xc0, 0x00, 0x10, 0x00, 0x00]); // add rax, 0x1000
+    /// assert_eq!(emit(true, &add), [0x48, 0x05, 0x00, 0x10, 0x00, 0x00]);       // add rax, 0x1000
+    /// ```
+    #[inline(always)]
+    pub fn prefer_small_encodings(&mut self, enabled: bool) {
+        self.small_encodings = enabled;
+    }
+
+    /// Sets whether the instructions emitted from now on are re-encoded in a canonical form, [`Canonicalization::AsEmitted`] by default, see the
+    /// [`canonical`](crate::canonical) module.
+    #[inline(always)]
+    pub fn set_canonicalization(&mut self, canonicalization: Canonicalization) {
+        self.canonicalization = canonicalization;
+    }
+
+    /// Whether the instructions are re-encoded in a canonical form, see [`x86_64InstructionStream::set_canonicalization`].
+    #[inline(always)]
+    pub fn canonicalization(&self) -> Canonicalization {
+        self.canonicalization
+    }
+
+    /// Returns true if the polymorphic emitters pick the smallest encoding, see [`x86_64InstructionStream::prefer_small_encodings`].
+    #[inline(always)]
+    pub fn prefers_small_encodings(&self) -> bool {
+        self.small_encodings
+    }
+
+    /// Enables or disables the peephole patterns, which drop instructions without any effect as they are emitted:
+    ///
+    /// - moves from a 8, 16 or 64-bit register to itself (a 32-bit move to itself clears the upper half of the register, and is kept);
+    /// - a `pop` of the register pushed by the instruction right before it, which removes the `push` as well;
+    /// - additions and subtractions of zero, whose only effect is on the flags, which are left untouched.
+    ///
+    /// A `push` and a `pop` are never collapsed if a label is attached between them, as the `pop` may be reached by a branch.  The patterns are off
+    /// within the [atomic regions](crate::region), and never collapse a `push` and a `pop` across their boundaries.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::{PeepholeCounters, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_peephole(true);
+    /// stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rax);
+    /// stream.mov_reg32_reg32(Reg32::Eax, Reg32::Eax); // kept, clears the upper half of rax
+    /// stream.push_reg64(Reg64::Rbp);
+    /// stream.pop_reg64(Reg64::Rbp);
+    /// stream.push_reg64(Reg64::Rbx);
+    /// stream.pop_reg64(Reg64::Rcx); // kept, a different register
+    /// stream.add_reg64_imm32(Reg64::Rsp, 0);
+    /// stream.sub_reg64_imm32(Reg64::R9, 0);
+    ///
+    /// // a label between the `push` and the `pop` is a barrier.
+    /// stream.push_reg64(Reg64::Rbp);
+    /// let target = stream.create_label_attached();
+    /// stream.pop_reg64(Reg64::Rbp);
+    ///
+    /// assert_eq!(stream.peephole_counters(), PeepholeCounters { self_moves: 1, push_pops: 1, zero_arithmetic: 2 });
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x89, 0xc0, // mov eax, eax
+    ///     0x53,       // push rbx
+    ///     0x59,       // pop rcx
+    ///     0x55,       // push rbp
+    ///     0x5d,       // target: pop rbp
+    /// ]);
+    /// ```
+    #[inline(always)]
+    pub fn enable_peephole(&mut self, enable: bool) {
+        self.peephole = enable;
+        self.last_push = None;
+    }
+
+    /// Returns true if the peephole patterns apply to the next instruction, enabled and outside of the atomic regions.
+    #[inline(always)]
+    fn peephole_active(&self) -> bool {
+        self.peephole && self.regions.starts.is_empty()
+    }
+
+    /// The number of instructions elided by the peephole patterns so far.
+    #[inline(always)]
+    pub fn peephole_counters(&self) -> PeepholeCounters {
+        self.peephole_counters
+    }
+
+    /// Enables or disables the collection of [`Stats`] about the instructions emitted from now on.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stats::{FamilyStats, InstructionFamily}, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_stats(true);
+    ///
+    /// let done = stream.create_label();
+    /// let helper = stream.create_label();
+    /// stream.push_reg64(Reg64::Rbx);                 // 1 byte
+    /// stream.mov_reg64_reg64(Reg64::Rbx, Reg64::Rdi); // 3 bytes
+    /// stream.cmp_reg64_imm32(Reg64::Rbx, 0);          // 4 bytes
+    /// stream.jcc_label(Condition::E, done);           // 6 bytes, ends the first block of 14 bytes
+    /// stream.call_label(helper);                      // 5 bytes
+    /// stream.add_reg64_imm32(Reg64::Rax, 1);          // 4 bytes
+    /// stream.attach_label(done);
+    /// stream.pop_reg64(Reg64::Rbx);                   // 1 byte
+    /// stream.ret_near();                              // 1 byte
+    ///
+    /// let stats = stream.stats();
+    /// assert_eq!(stats.instructions, 8);
+    /// assert_eq!(stats.bytes(), 25);
+    /// assert_eq!(stats.branches, 1);
+    /// assert_eq!(stats.calls, 1);
+    /// assert_eq!(stats.unresolved_labels, 1); // `helper`
+    /// assert_eq!(stats.largest_basic_block, 14);
+    /// assert_eq!(stats.families[&InstructionFamily::Arith], FamilyStats { instructions: 2, bytes: 8 });
+    /// assert_eq!(stream.estimated_final_size(), 25);
+    ///
+    /// assert_eq!(stats.to_string(), "\
+    /// instructions               8
+    /// bytes                     25
+    /// branches                   1
+    /// calls                      1
+    /// unresolved labels          1
+    /// largest block             14
+    ///
+    /// family         count   bytes
+    /// mov                1       3
+    /// push               1       1
+    /// pop                1       1
+    /// arith              2       8
+    /// branch             1       6
+    /// call               1       5
+    /// ret                1       1
+    /// ");
+    ///
+    /// // `helper` is never attached, so the code can't be finished.
+    /// stream.abandon();
+    /// ```
+    pub fn enable_stats(&mut self, enable: bool) {
+        self.stats_enabled = enable;
+        self.block_start = self.bytes.len();
+    }
+
+    /// Enables or disables the recording of the emitted instructions, read back by [`x86_64InstructionStream::instructions`].  Only the instructions
+    /// emitted while enabled are recorded.
+    pub fn enable_listing(&mut self, enable: bool) {
+        self.listing.enabled = enable;
+    }
+
+    /// The statistics collected while enabled by [`x86_64InstructionStream::enable_stats`].
+    #[inline(always)]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// An upper bound of the size of the finished code, in bytes.
+    ///
+    /// Finishing the stream only patches label uses in place, and every branch is emitted in its widest form up front, so a pending forward branch can't
+    /// grow when its label is attached, and the estimate is the current size of the stream, plus the size of the constant pool and its padding.
+    #[inline(always)]
+    pub fn estimated_final_size(&self) -> usize {
+        self.bytes.len() + self.constants.size_bound()
+    }
+
+    /// Marks the start of an instruction, returning its offset for [`x86_64InstructionStream::end_instruction`].  The offset includes any prefix written
+    /// before the instruction through a dedicated method, such as [`x86_64InstructionStream::lock`].
+    #[inline(always)]
+    pub(crate) fn begin_instruction(&mut self) -> usize {
+        self.prefix_start.take().unwrap_or(self.bytes.len())
+    }
+
+    /// Marks the end of an instruction of the family `family`, started at `start`, counting it in the statistics and recording it in the listing if
+    /// they are enabled.  Instructions elided by the peephole patterns emit no bytes and aren't counted.
+    #[inline(always)]
+    pub(crate) fn end_instruction(&mut self, start: usize, family: InstructionFamily) {
+        self.address_size_32 = false;
+        if start == self.bytes.len() {
+            self.listing.skip();
+            return;
+        }
+
+        if self.canonicalization == Canonicalization::Minimal {
+            if let Some(canonical) = canonical_form(&self.bytes[start..], family, self.longest_nop()) {
+                self.bytes.truncate(start);
+                self.bytes.extend_from_slice(&canonical);
+            }
+        }
+
+        let start = match family {
+            InstructionFamily::Branch | InstructionFamily::Call | InstructionFamily::Ret if self.jcc_erratum => self.avoid_jcc_erratum(start),
+            _ => start,
+        };
+
+        let end = self.bytes.len();
+        self.last_instruction = Some((start, end, family));
+
+        if self.mode != Mode::Long {
+            self.check_legacy_encoding(start, &self.bytes[start..end]);
+        }
+
+        // under the `verify` feature, the encoding is reported by `verify` rather than asserted.
+        #[cfg(all(debug_assertions, not(feature = "verify")))]
+        if let Some(diagnostic) = crate::diagnostics::check_instruction(start, &self.bytes[start..end]).first() {
+            panic!("{}", diagnostic);
+        }
+
+        self.listing.record(start..end, family);
+        if !self.stats_enabled {
+            return;
+        }
+
+        let stats = &mut self.stats;
+        stats.instructions += 1;
+
+        let family_stats = stats.families.entry(family).or_default();
+        family_stats.instructions += 1;
+        family_stats.bytes += end - start;
+
+        match family {
+            InstructionFamily::Branch => stats.branches += 1,
+            InstructionFamily::Call => stats.calls += 1,
+            _ => {},
+        }
+
+        stats.largest_basic_block = stats.largest_basic_block.max(end - self.block_start);
+        if family.is_terminator() {
+            self.block_start = end;
+        }
+    }
+
+    /// Records the registers defined and used by the instruction being emitted, read back from the listing if it is enabled.  Called before
+    /// [`x86_64InstructionStream::end_instruction`], see the [`defuse`](crate::defuse) module for what is defined and used.
+    #[inline(always)]
+    pub(crate) fn record_registers(&mut self, defs: RegSet, uses: RegSet) {
+        if self.listing.enabled {
+            self.listing.next_registers = Some((defs, uses));
+        }
+    }
+
+    /// Pads with `nop`s before the jump started at `start`, and before the arithmetic instruction right before it if the processor may fuse them, so
+    /// they neither cross nor end on a 32-byte boundary, and returns the new start of the jump.
+    fn avoid_jcc_erratum(&mut self, start: usize) -> usize {
+        let end = self.bytes.len();
+        let fused_start = match self.last_instruction {
+            Some((arith_start, arith_end, InstructionFamily::Arith)) if arith_end == start && is_jcc(&self.bytes[start..end]) => arith_start,
+            _ => start,
+        };
+
+        let padding = jcc_erratum_padding(fused_start, end);
+        if padding == 0 {
+            return start;
+        }
+
+        // within an atomic region, the padding goes before the region.
+        let offset = self.regions.starts.first().map_or(fused_start, |&region| region.min(fused_start));
+        self.write_nops(padding);
+        if self.bytes.error().is_some() {
+            return start;
+        }
+
+        self.bytes[offset..].rotate_right(padding);
+        self.shift_code(offset, padding);
+        start + padding
+    }
+
+    /// Removes an instruction of `bytes` bytes, elided after being emitted, from the statistics and the listing.
+    fn forget_instruction(&mut self, family: InstructionFamily, bytes: usize) {
+        self.listing.forget();
+        if !self.stats_enabled {
+            return;
+        }
+
+        self.stats.instructions -= 1;
+
+        if let Some(family_stats) = self.stats.families.get_mut(&family) {
+            family_stats.instructions -= 1;
+            family_stats.bytes -= bytes;
+        }
+    }
+
+    /// Creates a reference to the external symbol `name`, whose address is resolved when the product is relocated.  The name is mangled for the
+    /// target if one is set, see [`x86_64InstructionStream::set_target`], and marked in the product as mangled, see [`Product::mark_mangled`];
+    /// without a target it is left for [`asmkit_core::write_object`] to mangle.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{target::Target, InstructionStream};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let symbols = |triple: &str| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.set_target(Some(Target::parse(triple).unwrap()));
+    ///     let malloc = stream.create_symbol("malloc");
+    ///     let stub = stream.create_raw_symbol("_ZN3jit4stub17h0123456789abcdefE");
+    ///
+    ///     let start = stream.create_label_attached();
+    ///     stream.define_symbol("entry", start);
+    ///     stream.ret_near();
+    ///
+    ///     let product = stream.finish();
+    ///     [&product.symbol(malloc).name, &product.symbol(stub).name, &product.definitions()[0].name].map(|name| name.clone())
+    /// };
+    ///
+    /// assert_eq!(symbols("x86_64-unknown-linux-gnu"), ["malloc", "_ZN3jit4stub17h0123456789abcdefE", "entry"]);
+    /// assert_eq!(symbols("x86_64-apple-darwin"), ["_malloc", "_ZN3jit4stub17h0123456789abcdefE", "_entry"]);
+    /// assert_eq!(symbols("x86_64-pc-windows-msvc"), ["malloc", "_ZN3jit4stub17h0123456789abcdefE", "entry"]);
+    /// ```
+    pub fn create_symbol(&mut self, name: &str) -> SymRef {
+        match self.target {
+            Some(target) => self.create_raw_symbol(&mangle_c(name, &target)),
+            None => self.symbols.push(Symbol { name: name.to_string() }),
+        }
+    }
+
+    /// Creates a reference to the external symbol `name`, already mangled, see [`x86_64InstructionStream::create_symbol`].
+    pub fn create_raw_symbol(&mut self, name: &str) -> SymRef {
+        self.mangled_names.insert(name.to_string());
+        self.symbols.push(Symbol { name: name.to_string() })
+    }
+
+    /// Creates a reference to the symbol `name`, defined in the same image as the code, such as by another product linked with it or by a hidden
+    /// symbol of its object file, so [`x86_64InstructionStream::lea_reg64_symbol`] reaches it without going through the global offset table.
+    pub fn create_local_symbol(&mut self, name: &str) -> SymRef {
+        let symbol = self.create_symbol(name);
+        self.local_symbols.insert(symbol);
+        symbol
+    }
+
+    /// Returns true if `symbol` was created by [`x86_64InstructionStream::create_local_symbol`].
+    pub fn is_local_symbol(&self, symbol: SymRef) -> bool {
+        self.local_symbols.contains(&symbol)
+    }
+
+    /// Exports `label` as the symbol `name`, defined in the product for other products to call with [`x86_64InstructionStream::call_symbol`] once
+    /// they are linked together by [`asmkit_core::link()`].  The name is mangled for the target if one is set, see
+    /// [`x86_64InstructionStream::create_symbol`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if the label is never attached.
+    pub fn define_symbol(&mut self, name: &str, label: LabelRef) {
+        match self.target {
+            Some(target) => self.define_raw_symbol(&mangle_c(name, &target), label),
+            None => self.definitions.push((name.to_string(), label)),
+        }
+    }
+
+    /// Exports `label` as the symbol `name`, already mangled, see [`x86_64InstructionStream::define_symbol`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if the label is never attached.
+    pub fn define_raw_symbol(&mut self, name: &str, label: LabelRef) {
+        self.mangled_names.insert(name.to_string());
+        self.definitions.push((name.to_string(), label));
+    }
+
+    /// Attaches the comment `text` to the current offset, such as an annotation of the compiler generating the code.  Comments are passed on to
+    /// the product, and printed before the instruction they annotate in its [`Product::listing`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let check = stream.create_label();
+    /// stream.comment("spill of v17");
+    /// stream.push_reg64(Reg64::Rbx);
+    /// stream.attach_label(check);
+    /// stream.define_symbol("check", check);
+    /// stream.comment("bounds check for array a");
+    /// stream.cmp_reg64_reg64(Reg64::Rdi, Reg64::Rsi);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.comments()[1].offset, 1);
+    /// assert_eq!(product.listing().to_string(), "\
+    /// ; spill of v17
+    /// 00000000  53
+    /// check:
+    /// ; bounds check for array a
+    /// 00000001  48 39 f7 c3
+    /// ");
+    /// ```
+    pub fn comment(&mut self, text: impl Into<String>) {
+        self.comments.push(Comment { offset: self.bytes.len(), text: text.into() });
+    }
+
+    /// Sets the location of the source code the next instructions are generated from, until it is set again or cleared by
+    /// [`x86_64InstructionStream::clear_source_location`].  The locations are recorded in the [`SourceMap`] of the product, with the bytes of
+    /// consecutive instructions from the same location merged into a single run.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{source_map::Location, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.register_source_file(0, "main.src");
+    /// stream.set_source_location(0, 1, 1);
+    /// stream.push_reg64(Reg64::Rbp);
+    /// stream.set_source_location(0, 1, 1);
+    /// stream.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+    /// stream.set_source_location(0, 2, 5);
+    /// stream.add_reg64_imm32(Reg64::Rdi, 1);
+    /// stream.clear_source_location();
+    /// stream.pop_reg64(Reg64::Rbp);
+    /// stream.set_source_location(0, 3, 1);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x55,                   // 1:1 push rbp
+    ///     0x48, 0x89, 0xe5,       // 1:1 mov rbp, rsp
+    ///     0x48, 0x83, 0xc7, 0x01, // 2:5 add rdi, 1
+    ///     0x5d,                   // pop rbp
+    ///     0xc3,                   // 3:1 ret
+    /// ]);
+    ///
+    /// let location = |line, col| Some(Location { file_id: 0, line, col });
+    /// assert_eq!(product.source_map().entries().len(), 4);
+    /// assert_eq!(product.source_location(0), location(1, 1));
+    /// assert_eq!(product.source_location(3), location(1, 1));
+    /// assert_eq!(product.source_location(4), location(2, 5));
+    /// assert_eq!(product.source_location(7), location(2, 5));
+    /// assert_eq!(product.source_location(8), None);
+    /// assert_eq!(product.source_location(9), location(3, 1));
+    /// assert_eq!(product.source_location(10), None);
+    /// assert_eq!(product.source_map().file(0), Some("main.src"));
+    /// ```
+    pub fn set_source_location(&mut self, file_id: u32, line: u32, col: u32) {
+        self.source_location = Some(Location { file_id, line, col });
+        self.source_map.record(self.bytes.len(), self.source_location);
+    }
+
+    /// Clears the location of the source code, so the next instructions have none, see [`x86_64InstructionStream::set_source_location`].
+    pub fn clear_source_location(&mut self) {
+        self.source_location = None;
+        self.source_map.record(self.bytes.len(), None);
+    }
+
+    /// Registers `name` as the name of the source file `file_id`, in the [`SourceMap`] of the product.
+    pub fn register_source_file(&mut self, file_id: u32, name: &str) {
+        self.source_map.register_file(file_id, name);
+    }
+
+    /// Starts the size region `name`, nested in the size region being written if any, which the bytes written until the matching
+    /// [`x86_64InstructionStream::pop_region`] are attributed to, in the [`SizeRegions`] of the product, see the
+    /// [`size_report`](asmkit_core::size_report) module.  The size regions of the same name in the same size region are merged, and their bytes
+    /// add up.
+    ///
+    /// The size regions follow their code when it's moved, such as by the [basic blocks](crate::block) or to the cold text, and the padding inserted
+    /// before an [atomic region](crate::region) is attributed to the size region the atomic region starts in.
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if a size region is never popped.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.push_region("main");
+    /// stream.push_reg64(Reg64::Rbx);
+    ///
+    /// // `min` inlined twice, then `abs` inlined in `min`.
+    /// for _ in 0..2 {
+    ///     stream.push_region("inline min");
+    ///     stream.cmp_reg32_reg32(Reg32::Edi, Reg32::Esi);
+    ///     stream.sub_reg32_reg32(Reg32::Edi, Reg32::Esi);
+    ///     stream.pop_region();
+    /// }
+    ///
+    /// stream.push_region("inline min");
+    /// stream.push_region("inline abs");
+    /// stream.mov_reg32_reg32(Reg32::Eax, Reg32::Edi);
+    /// stream.sub_reg32_reg32(Reg32::Eax, Reg32::Esi);
+    /// stream.pop_region();
+    /// stream.pop_region();
+    ///
+    /// stream.pop_reg64(Reg64::Rbx);
+    /// stream.pop_region();
+    /// stream.ret_near();
+    ///
+    /// let report = stream.finish().size_report();
+    /// let main = report.region(&["main"]).unwrap();
+    /// let min = report.region(&["main", "inline min"]).unwrap();
+    /// let abs = report.region(&["main", "inline min", "inline abs"]).unwrap();
+    ///
+    /// // `push rbx` and `pop rbx`; `cmp edi, esi` and `sub edi, esi`, twice; `mov eax, edi` and `sub eax, esi`; `ret` in none.
+    /// assert_eq!((main.inclusive, main.exclusive), (1 + 2 * 4 + 4 + 1, 2));
+    /// assert_eq!((min.inclusive, min.exclusive), (2 * 4 + 4, 2 * 4));
+    /// assert_eq!((abs.inclusive, abs.exclusive), (4, 4));
+    /// assert_eq!((report.total(), report.unattributed()), (15, 1));
+    ///
+    /// assert_eq!(report.folded(), "main 2\nmain;inline min 8\nmain;inline min;inline abs 4\n");
+    /// assert_eq!(report.children(main).map(|region| region.name.as_str()).collect::<Vec<_>>(), ["inline min"]);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.push_region("main");
+    /// stream.ret_near();
+    /// stream.finish();
+    /// ```
+    pub fn push_region(&mut self, name: &str) {
+        let parent = self.size_region_stack.last().map(|&(_, region)| region);
+        let region = self.size_regions.region(parent, name);
+        self.size_region_stack.push((name.to_string(), region));
+        self.size_regions.record(self.bytes.len(), Some(region));
+    }
+
+    /// Ends the innermost size region started by [`x86_64InstructionStream::push_region`], so the bytes written next are attributed to the size
+    /// region it's nested in, if any.
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if no size region was being written.
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.push_region("main");
+    /// stream.pop_region();
+    /// stream.pop_region();
+    /// stream.finish();
+    /// ```
+    pub fn pop_region(&mut self) {
+        if self.size_region_stack.pop().is_none() {
+            self.unbalanced_pops += 1;
+        }
+
+        self.size_regions.record(self.bytes.len(), self.size_region_stack.last().map(|&(_, region)| region));
+    }
+
+    /// Records the size regions being written from the current offset, numbered in the [`SizeRegions`] of the code being written.
+    fn record_size_region_stack(&mut self) {
+        let mut parent = None;
+        for (name, region) in &mut self.size_region_stack {
+            *region = self.size_regions.region(parent, name);
+            parent = Some(*region);
+        }
+
+        self.size_regions.record(self.bytes.len(), parent);
+    }
+
+    /// Binds `label` to the absolute `address`, outside the code, such as the address of a function or a variable of the running program, in place
+    /// of attaching it.
+    ///
+    /// The `rip`-relative operands and the 32-bit branches to the label are left to [`RelocationKind::Rel32`] relocations against
+    /// [`RelocationTarget::Absolute`], resolved once the address the code is loaded at is known, by [`Product::resolve_all`] or when the code is
+    /// mapped, and the absolute addresses of the label, as loaded by [`x86_64InstructionStream::load_label_address`] outside the `Pic` code model,
+    /// hold `address` itself once the stream is finished.
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if the label is used by a field which can't refer to an absolute address, an 8 or 16-bit
+    /// displacement, a jump table entry or a label difference, if `address` doesn't fit in a 16 or 32-bit absolute field, or if the label is
+    /// exported by [`x86_64InstructionStream::define_symbol`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{CodeModel, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let puts = stream.create_label();
+    /// let counter = stream.create_label();
+    /// stream.call_label(puts);
+    /// stream.mov_reg64_label(Reg64::Rax, counter);
+    /// stream.set_code_model(CodeModel::LargeAbs);
+    /// stream.load_label_address(Reg64::Rcx, counter);
+    /// stream.bind_label_absolute(puts, 0x7f00_0000_1000);
+    /// stream.bind_label_absolute(counter, 0x7f00_0000_2000);
+    ///
+    /// let mut product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00,                               // call puts
+    ///     0x48, 0x8b, 0x05, 0x00, 0x00, 0x00, 0x00,                   // mov rax, qword ptr [rip + counter]
+    ///     0x48, 0xb9, 0x00, 0x20, 0x00, 0x00, 0x00, 0x7f, 0x00, 0x00, // movabs rcx, counter
+    /// ]);
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Absolute(0x7f00_0000_1000), addend: -4 },
+    ///     Relocation { offset: 8, kind: RelocationKind::Rel32, target: RelocationTarget::Absolute(0x7f00_0000_2000), addend: -4 },
+    /// ]);
+    ///
+    /// // the displacements are computed once the load address is known.
+    /// product.resolve_all(|_| None, 0x7f00_0000_0000).unwrap();
+    /// assert_eq!(product.bytes()[1..5], (0x1000i32 - 5).to_le_bytes());
+    /// assert_eq!(product.bytes()[8..12], (0x2000i32 - 12).to_le_bytes());
+    /// ```
+    ///
+    /// Calling a function of the program and reading one of its variables, from code mapped within reach of them:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// unsafe {
+    ///     use asmkit_core::InstructionStream;
+    ///     use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    ///     extern "C" {
+    ///         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+    ///         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+    ///         fn munmap(address: *mut u8, length: usize) -> i32;
+    ///     }
+    ///
+    ///     extern "C" fn double(x: u64) -> u64 { x * 2 }
+    ///     static VALUE: u64 = 21;
+    ///
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     let function = stream.create_label();
+    ///     let value = stream.create_label();
+    ///     stream.bind_label_absolute(function, double as *const () as u64);
+    ///     stream.bind_label_absolute(value, &VALUE as *const u64 as u64);
+    ///     stream.push_reg64(Reg64::Rax); // realigns the stack
+    ///     stream.mov_reg64_label(Reg64::Rdi, value);
+    ///     stream.call_label(function);
+    ///     stream.pop_reg64(Reg64::Rcx);
+    ///     stream.ret_near();
+    ///     let mut product = stream.finish();
+    ///
+    ///     // maps a page 1 GiB past the function, if the kernel takes the hint.
+    ///     let hint = (double as *const () as usize & !0xfff).wrapping_add(1 << 30) as *mut u8;
+    ///     let memory = mmap(hint, 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+    ///     assert_ne!(memory as isize, -1);
+    ///
+    ///     if product.resolve_all(|_| None, memory as u64).is_ok() {
+    ///         std::ptr::copy_nonoverlapping(product.bytes().as_ptr(), memory, product.bytes().len());
+    ///         assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+    ///
+    ///         let code: extern "C" fn() -> u64 = std::mem::transmute(memory);
+    ///         assert_eq!(code(), 42);
+    ///     } else {
+    ///         assert_ne!(memory, hint);
+    ///     }
+    ///
+    ///     munmap(memory, 4096);
+    /// }
+    /// ```
+    ///
+    /// Only the 32-bit displacements reach an absolute address:
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let function = stream.create_label();
+    /// stream.bind_label_absolute(function, 0x40_1000);
+    /// stream.jmp_short_label(function);
+    /// stream.finish();
+    /// ```
+    pub fn bind_label_absolute(&mut self, label: LabelRef, address: u64) {
+        self.labels.get_mut(label).state = Label::Absolute(address);
+
+        if self.stats_enabled && self.unresolved_labels.remove(&label) {
+            self.stats.unresolved_labels = self.unresolved_labels.len();
+        }
+    }
+
+    /// Names `label`, so diagnostics about it are easier to trace back to the code generating it.
+    pub fn name_label(&mut self, label: LabelRef, name: &str) {
+        self.label_names.insert(label, name.to_string());
+    }
+
+    /// The offset `label` is attached to, if it is attached.  The offsets of the labels are recorded on the product when the stream is finished,
+    /// see [`Product::label_offset`], and those of the named labels by name, see [`Product::named_label_offset`].
+    ///
+    /// The labels of the constants and of the uninitialized data are only attached once the stream is finished, and the offsets of the labels
+    /// attached in [basic blocks](crate::block) change as the blocks are laid out.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let entry = stream.create_label_attached();
+    /// let exit = stream.create_label();
+    /// let unused = stream.create_label();
+    /// stream.name_label(exit, "exit");
+    /// stream.name_label(unused, "unused");
+    ///
+    /// stream.push_reg64(Reg64::Rbp);
+    /// stream.jmp_label(exit);
+    /// assert_eq!(stream.label_offset(entry), Some(0));
+    /// assert_eq!(stream.label_offset(exit), None);
+    ///
+    /// stream.attach_label(exit);
+    /// stream.pop_reg64(Reg64::Rbp);
+    /// stream.ret_near();
+    /// assert_eq!(stream.label_offset(exit), Some(6));
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.label_offset(entry), Some(0));
+    /// assert_eq!(product.label_offset(exit), Some(6));
+    /// assert_eq!(product.label_offset(unused), None);
+    /// assert_eq!(product.named_label_offset("exit"), Some(6));
+    /// assert_eq!(product.named_label_offset("unused"), None);
+    /// assert_eq!(product.named_labels(), [("exit".to_string(), 6)]);
+    /// ```
+    pub fn label_offset(&self, label: LabelRef) -> Option<usize> {
+        self.labels.offset(label)
+    }
+
+    /// The name given to `label` by [`x86_64InstructionStream::name_label`], if any.
+    pub fn label_name(&self, label: LabelRef) -> Option<&str> {
+        self.label_names.get(&label).map(String::as_str)
+    }
+
+    /// Checks the labels of the instruction stream, returning a diagnostic for each label which is used but never attached, for each use of a
+    /// label out of reach of its 8-bit displacement, and for each label which is attached but neither used nor exported with
+    /// [`x86_64InstructionStream::define_symbol`], in the order the labels were created.
+    ///
+    /// [`InstructionStream::finish`] panics on the fatal diagnostics, the unattached and out of reach labels.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{diagnostics::LabelDiagnostic, stream::{Condition, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let start = stream.create_label_attached();
+    /// let exit = stream.create_label();
+    /// let retry = stream.create_label();
+    /// stream.name_label(exit, "exit");
+    ///
+    /// stream.jcc_label(Condition::E, exit); // 0f 84 rel32
+    /// stream.jmp_label(exit);               // e9 rel32
+    /// stream.attach_label(retry);
+    /// stream.ret_near();
+    ///
+    /// let diagnostics = stream.validate();
+    /// assert_eq!(diagnostics, [
+    ///     LabelDiagnostic::Unused { label: start, name: None, offset: 0 },
+    ///     LabelDiagnostic::Undefined { label: exit, name: Some("exit".to_string()), uses: vec![2, 7] },
+    ///     LabelDiagnostic::Unused { label: retry, name: None, offset: 11 },
+    /// ]);
+    /// assert_eq!(diagnostics[1].to_string(), "label `exit` is never attached, but used at 0x2, 0x7");
+    /// assert_eq!(diagnostics[2].to_string(), "label #2 is attached at 0xb, but never used");
+    ///
+    /// // exporting a label uses it.
+    /// stream.define_symbol("start", start);
+    /// stream.attach_label(exit);
+    /// stream.jmp_label(retry);
+    /// assert!(stream.validate().is_empty());
+    /// ```
+    ///
+    /// Finishing a stream with an unattached label panics:
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let exit = stream.create_label();
+    /// stream.name_label(exit, "exit");
+    /// stream.jmp_label(exit);
+    /// stream.finish();
+    /// ```
+    pub fn validate(&self) -> Vec<LabelDiagnostic> {
+        let mut uses = vec![Vec::new(); self.labels.len()];
+        let mut out_of_range = vec![Vec::new(); self.labels.len()];
+        for fixup in &self.fixups {
+            uses[fixup.label.as_usize()].push(fixup.offset);
+
+            // only the 8-bit and 16-bit displacements can fall short of their label before the code outgrows 2 GiB.
+            if let (FixupKind::Rel8 | FixupKind::Rel16, Label::Attached(target)) = (fixup.kind, self.labels.get(fixup.label).state) {
+                if let Err(error) = fixup.value(target, 0) {
+                    out_of_range[fixup.label.as_usize()].push(error);
+                }
+            }
+
+            if let FixupKind::LabelDiff { from, .. } = fixup.kind {
+                uses[from.as_usize()].push(fixup.offset);
+            }
+        }
+
+        let mut exported = vec![false; self.labels.len()];
+        for (_, label) in &self.definitions {
+            exported[label.as_usize()] = true;
+        }
+
+        let mut diagnostics = Vec::new();
+        for (index, ((label, uses), out_of_range)) in self.labels.refs().map(|label| self.labels.get(label).state).zip(uses).zip(out_of_range).enumerate() {
+            let label_ref = LabelRef::new(index);
+            let name = self.label_names.get(&label_ref).cloned();
+
+            for error in out_of_range {
+                diagnostics.push(LabelDiagnostic::OutOfRange { label: label_ref, name: name.clone(), error });
+            }
+
+            match label {
+                // the labels of constants and of the uninitialized data are attached once the pool is laid out, when the stream is finished.
+                Label::Unattached if !uses.is_empty() && !self.constants.contains(label_ref) && !self.bss.contains(label_ref) => diagnostics.push(LabelDiagnostic::Undefined { label: label_ref, name, uses }),
+                Label::Attached(offset) if uses.is_empty() && !exported[index] => {
+                    diagnostics.push(LabelDiagnostic::Unused { label: label_ref, name, offset });
+                },
+                _ => {},
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Checks the encoding of the instructions recorded while the listing is enabled, see [`x86_64InstructionStream::enable_listing`]: each must
+    /// take at most [`MAX_INSTRUCTION_LEN`](crate::diagnostics::MAX_INSTRUCTION_LEN) bytes, have no two different legacy prefixes of the same group, and hold the whole of the label
+    /// fields starting within it.  Returns the diagnostics of the instructions by offset, followed by those of the label fields.
+    ///
+    /// The emitters only write well formed instructions, but the bytes written through [`InstructionStream::write_byte`] right before an
+    /// instruction, such as after a prefix written by [`x86_64InstructionStream::lock`], become part of it.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{diagnostics::EncodingDiagnostic, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// let exit = stream.create_label();
+    /// stream.lock();
+    /// stream.xadd_mem64_reg64(Mem::new(Reg64::Rdi, 8), Reg64::Rax);
+    /// stream.jmp_label(exit);
+    /// stream.nop(20);
+    /// stream.attach_label(exit);
+    /// assert!(stream.verify().is_empty());
+    ///
+    /// stream.lock();
+    /// stream.write_byte(0xf3); // rep
+    /// stream.xadd_mem64_reg64(Mem::new(Reg64::Rdi, 8), Reg64::Rax);
+    ///
+    /// stream.lock();
+    /// for _ in 0..10 {
+    ///     stream.write_byte(0x2e); // cs
+    /// }
+    /// stream.xadd_mem64_reg64(Mem::new(Reg64::Rdi, 8), Reg64::Rax);
+    ///
+    /// let diagnostics = stream.verify();
+    /// assert_eq!(diagnostics, [
+    ///     EncodingDiagnostic::ConflictingPrefixes { offset: 31, first: 0xf0, second: 0xf3 },
+    ///     EncodingDiagnostic::TooLong { offset: 38, len: 16 },
+    /// ]);
+    /// assert_eq!(diagnostics[0].to_string(), "the instruction at 0x1f has the prefixes 0xf0 and 0xf3 of the same group");
+    /// assert_eq!(diagnostics[1].to_string(), "the instruction at 0x26 takes 16 bytes, past the 15-byte limit");
+    /// ```
+    #[cfg(feature = "verify")]
+    pub fn verify(&self) -> Vec<EncodingDiagnostic> {
+        let records = &self.listing.records;
+        let mut diagnostics = Vec::new();
+        for record in records {
+            diagnostics.extend(check_instruction(record.offset, &self.bytes[record.offset..record.offset + record.len]));
+        }
+
+        for fixup in &self.fixups {
+            // the field starts in the last instruction starting at or before it, if in any.
+            let index = records.partition_point(|record| record.offset <= fixup.offset);
+            let Some(record) = index.checked_sub(1).map(|index| &records[index]) else {
+                continue;
+            };
+
+            let end = record.offset + record.len;
+            if fixup.offset < end && fixup.offset + fixup.kind.size() > end {
+                diagnostics.push(EncodingDiagnostic::FixupOutsideInstruction { offset: fixup.offset, instruction: record.offset });
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Sets the bytes filling the padding inserted by [`x86_64InstructionStream::attach_label_aligned`].
+    #[inline(always)]
+    pub fn set_align_fill(&mut self, fill: Fill) {
+        self.align_fill = fill;
+    }
+
+    /// The bytes filling alignment padding, [`Fill::Nop`] by default.
+    #[inline(always)]
+    pub fn align_fill(&self) -> Fill {
+        self.align_fill
+    }
+
+    /// Pads the instruction stream to a multiple of `align` bytes with the [`Fill`] set by [`x86_64InstructionStream::set_align_fill`], then
+    /// attaches `label`, such as to align a loop header.
+    ///
+    /// Labels are resolved once the instruction stream is finished, so the padding is accounted for by every jump to a label, whether emitted before or
+    /// after it.  The padding isn't counted as instructions in the statistics.  Within an [atomic region](crate::region), the padding is inserted
+    /// before the region, moving its code along.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::{Fill, x86_64InstructionStream}};
+    ///
+    /// let align = |prefix: &[u8], fill| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.set_align_fill(fill);
+    ///     stream.write_bytes(prefix);
+    ///
+    ///     let top = stream.create_label();
+    ///     stream.attach_label_aligned(top, 16);
+    ///     stream.jmp_label(top);
+    ///
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// // already aligned, nothing is inserted.
+    /// assert_eq!(align(&[], Fill::Nop), [0xe9, 0xfb, 0xff, 0xff, 0xff]); // top: jmp top
+    ///
+    /// assert_eq!(align(&[0xc3; 3], Fill::Nop), [
+    ///     0xc3, 0xc3, 0xc3,
+    ///     0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+    ///     0x0f, 0x1f, 0x40, 0x00,                               // nop dword ptr [rax + 0]
+    ///     0xe9, 0xfb, 0xff, 0xff, 0xff,                         // top: jmp top
+    /// ]);
+    ///
+    /// assert_eq!(align(&[0xc3; 13], Fill::Int3)[13..], [0xcc, 0xcc, 0xcc, 0xe9, 0xfb, 0xff, 0xff, 0xff]);
+    /// assert_eq!(align(&[0xc3; 17], Fill::Zero)[17..], [0; 15].into_iter().chain([0xe9, 0xfb, 0xff, 0xff, 0xff]).collect::<Vec<_>>());
+    ///
+    /// // a jump emitted before the padding still reaches the label.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let top = stream.create_label();
+    /// stream.jmp_label(top);
+    /// stream.attach_label_aligned(top, 8);
+    /// stream.ret_near();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xe9, 0x03, 0x00, 0x00, 0x00, // jmp top
+    ///     0x0f, 0x1f, 0x00,             // nop dword ptr [rax]
+    ///     0xc3,                         // top: ret
+    /// ]);
+    /// ```
+    pub fn attach_label_aligned(&mut self, label: LabelRef, align: usize) {
+        assert!(align.is_power_of_two(), "label alignment must be a power of two");
+
+        let padding = self.bytes.len().wrapping_neg() & (align - 1);
+        self.write_padding(padding);
+        self.attach_label(label);
+    }
+
+    /// Sets the boundary the labels attached by [`InstructionStream::attach_label`] from now on are padded to, with the [`Fill`] set by
+    /// [`x86_64InstructionStream::set_align_fill`], [`Align::None`] by default, see the [`fetch`](crate::fetch) module.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{fetch::Align, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.align_branch_targets(Align::To16);
+    /// stream.mov_reg64_reg64(Reg64::Rcx, Reg64::Rdi);
+    ///
+    /// let top = stream.create_label();
+    /// stream.attach_label(top);
+    /// stream.sub_reg64_imm32(Reg64::Rcx, 1);
+    /// stream.jmp_label(top);
+    ///
+    /// // every label is padded, the exit of the loop too, unless already aligned.
+    /// let (exit, done) = (stream.create_label_attached(), stream.create_label_attached());
+    /// assert_eq!((stream.label_offset(top), stream.label_offset(exit), stream.label_offset(done)), (Some(16), Some(32), Some(32)));
+    ///
+    /// let bytes = stream.finish().emit();
+    /// assert_eq!(bytes[..16], [
+    ///     0x48, 0x89, 0xf9,                                     // mov rcx, rdi
+    ///     0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+    ///     0x0f, 0x1f, 0x40, 0x00,                               // nop dword ptr [rax + 0]
+    /// ]);
+    /// assert_eq!(bytes.len(), 32);
+    /// ```
+    #[inline(always)]
+    pub fn align_branch_targets(&mut self, align: Align) {
+        self.branch_target_align = align;
+    }
+
+    /// Sets whether the jumps, calls and returns emitted from now on are kept from crossing or ending on a 32-byte boundary, along with the
+    /// arithmetic instruction a conditional jump may be fused with, as the mitigation of the JCC erratum requires, false by default, see the
+    /// [`fetch`](crate::fetch) module.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// // a `ret` ending on the boundary at 32, then a `jmp` across the one at 64.
+    /// let emit = |mitigate| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.avoid_jcc_erratum_mitigation(mitigate);
+    ///     stream.write_bytes(&[0xcc; 31]);
+    ///     stream.ret_near();
+    ///     stream.write_bytes(&[0xcc; 30]);
+    ///
+    ///     let top = stream.create_label_attached();
+    ///     stream.jmp_label(top);
+    ///     stream.finish().emit()
+    /// };
+    ///
+    /// let bytes = emit(false);
+    /// assert_eq!((bytes.len(), bytes[31], bytes[62]), (67, 0xc3, 0xe9));
+    ///
+    /// let bytes = emit(true);
+    /// assert_eq!((bytes.len(), bytes[32], bytes[64]), (69, 0xc3, 0xe9));
+    /// assert_eq!(bytes[31], 0x90);                                   // nop
+    /// assert_eq!(bytes[63..], [0x90, 0xe9, 0xfa, 0xff, 0xff, 0xff]); // top: nop; jmp top
+    /// ```
+    #[inline(always)]
+    pub fn avoid_jcc_erratum_mitigation(&mut self, enabled: bool) {
+        self.jcc_erratum = enabled;
+    }
+
+    /// Sets the alignment of the functions started by [`x86_64InstructionStream::begin_function`] from now on, 1 by default.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// x86_64InstructionStream::new().function_alignment(24);
+    /// ```
+    #[inline(always)]
+    pub fn function_alignment(&mut self, align: u32) {
+        assert!(align.is_power_of_two(), "function alignment must be a power of two");
+        self.function_align = align as usize;
+    }
+
+    /// Starts a function, padding the stream to the alignment set by [`x86_64InstructionStream::function_alignment`] with the [`Fill`] set by
+    /// [`x86_64InstructionStream::set_align_fill`], then attaching `label` as its entry.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg32, stream::{Fill, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.function_alignment(16);
+    /// stream.set_align_fill(Fill::Int3);
+    ///
+    /// let (zero, one) = (stream.create_label(), stream.create_label());
+    /// stream.begin_function(zero);
+    /// stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.ret_near();
+    ///
+    /// stream.begin_function(one);
+    /// stream.mov_reg32_imm32(Reg32::Eax, 1);
+    /// stream.ret_near();
+    ///
+    /// assert_eq!((stream.label_offset(zero), stream.label_offset(one)), (Some(0), Some(16)));
+    /// assert_eq!(stream.finish().emit()[..22], [
+    ///     0x31, 0xc0,                   // zero: xor eax, eax
+    ///     0xc3,                         // ret
+    ///     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, // int3 ...
+    ///     0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+    ///     0xcc, 0xcc, 0xcc,
+    ///     0xb8, 0x01, 0x00, 0x00, 0x00, // one: mov eax, 1
+    ///     0xc3,                         // ret
+    /// ]);
+    /// ```
+    pub fn begin_function(&mut self, label: LabelRef) {
+        let padding = self.bytes.len().wrapping_neg() & (self.function_align - 1);
+        self.write_padding(padding);
+        self.attach_label(label);
+    }
+
+    /// Reserves `size` bytes of uninitialized data aligned to `align`, and returns their label.
+    ///
+    /// The uninitialized data takes no bytes in the product: it's laid out past the end of the product, at [`Product::bss_offset`], where the
+    /// loader reserves and zeroes [`Product::bss_size`] bytes, see the [`bss`] module.  The label is attached there when the stream is finished,
+    /// so it's usually addressed through a `rip`-relative operand; it can't be attached by the stream.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or is larger than [`bss::BSS_ALIGN`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{bss::BSS_ALIGN, InstructionStream};
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let counter = stream.reserve_bss(8, 8);
+    /// let buffer = stream.reserve_bss(0x1000, 16);
+    ///
+    /// stream.lea_reg64_label(Reg64::Rax, buffer);
+    /// stream.mov_label64_imm32(counter, 1);
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x48, 0x8d, 0x05, 0x09, 0x00, 0x01, 0x00,                   // lea rax, [rip + buffer]
+    ///     0x48, 0xc7, 0x05, 0xee, 0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // mov qword ptr [rip + counter], 1
+    ///     0xc3,                                                       // ret
+    /// ]);
+    /// assert_eq!(product.bss_offset(), BSS_ALIGN);
+    /// assert_eq!(product.bss_size(), 0x1010);
+    /// assert_eq!(product.bss_label_offset(buffer), Some(BSS_ALIGN + 0x10));
+    ///
+    /// // the flat binary leaves the uninitialized data to the loader.
+    /// let (image, bss) = product.to_flat_binary_with_bss(0x100000, None).unwrap();
+    /// assert_eq!(image.len(), 19);
+    /// assert_eq!(bss, BSS_ALIGN..BSS_ALIGN + 0x1010);
+    /// ```
+    ///
+    /// The uninitialized data of a loaded product reads as zeroes, and is writable:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let value = stream.reserve_bss(8, 8);
+    ///
+    /// stream.lea_reg64_label(Reg64::Rax, value);
+    /// stream.mov_reg64_mem64(Reg64::Rax, Mem::new(Reg64::Rax, 0));
+    /// stream.ret_near();
+    ///
+    /// // store: at 0xb.
+    /// stream.lea_reg64_label(Reg64::Rax, value);
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::Rax, 0), Reg64::Rdi);
+    /// stream.ret_near();
+    ///
+    /// let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    /// assert_eq!(buffer.bss_size(), 8);
+    ///
+    /// let load: extern "C" fn() -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    /// let store: extern "C" fn(u64) = unsafe { std::mem::transmute(buffer.as_ptr().add(0xb)) };
+    ///
+    /// assert_eq!(load(), 0);
+    /// store(0x1234_5678_9abc);
+    /// assert_eq!(load(), 0x1234_5678_9abc);
+    /// assert_eq!(unsafe { *buffer.bss_ptr().cast::<u64>() }, 0x1234_5678_9abc);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn reserve_bss(&mut self, size: usize, align: usize) -> LabelRef {
+        let label = self.create_label();
+        self.bss.reserve(label, size, align);
+        label
+    }
+
+    /// Writes `length` bytes of `nop`s, as few as possible.
+    pub(crate) fn write_nops(&mut self, mut length: usize) {
+        while length > 0 {
+            let nop = NOPS[length.min(self.longest_nop()) - 1];
+            self.write_bytes(nop);
+            length -= nop.len();
+        }
+    }
+
+    /// The length of the longest `nop` available in the mode of the stream.
+    fn longest_nop(&self) -> usize {
+        // the longer sequences address through a SIB byte, which the 16-bit addressing of real mode has none of.
+        if self.mode == Mode::Real { 4 } else { NOPS.len() }
+    }
+
+    /// Sets how addresses are materialized by the instruction stream.
+    #[inline(always)]
+    pub fn set_code_model(&mut self, code_model: CodeModel) {
+        self.code_model = code_model;
+    }
+
+    /// How addresses are materialized by the instruction stream, [`CodeModel::Pic`] by default.
+    #[inline(always)]
+    pub fn code_model(&self) -> CodeModel {
+        self.code_model
+    }
+
+    /// The CPU features of the target, [`CpuFeatures::ALL`] by default.
+    #[inline(always)]
+    pub fn features(&self) -> CpuFeatures {
+        self.features
+    }
+
+    /// Checks that the CPU feature `feature`, required by `mnemonic`, is enabled for the target.
+    ///
+    /// # Errors
+    /// Returns [`AsmError::FeatureNotEnabled`] if `feature` isn't enabled.
+    pub fn check_feature(&self, feature: CpuFeatures, mnemonic: &'static str) -> Result<(), AsmError> {
+        if self.features.contains(feature) {
+            Ok(())
+        } else {
+            Err(AsmError::FeatureNotEnabled(feature, mnemonic))
+        }
+    }
+
+    /// Checks that the CPU feature `feature`, required by `mnemonic`, is enabled for the target, for the emitters.
+    ///
+    /// # Panics
+    /// Panics if `feature` isn't enabled.
+    pub(crate) fn require_feature(&self, feature: CpuFeatures, mnemonic: &'static str) {
+        if let Err(error) = self.check_feature(feature, mnemonic) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Sets the target the code is generated for, whose calling convention is the default of the helpers, see
+    /// [`x86_64InstructionStream::call_conv`].
+    ///
+    /// # Panics
+    /// Panics if the architecture of `target` isn't x86-64.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::target::Target;
+    /// use asmkit_x86_64::{callconv::CallConv, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// assert_eq!(stream.call_conv(), CallConv::SysV);
+    ///
+    /// stream.set_target(Some(Target::parse("x86_64-pc-windows-msvc").unwrap()));
+    /// assert_eq!(stream.call_conv(), CallConv::Win64);
+    /// let saved = stream.save_caller_saved(stream.call_conv(), false);
+    /// stream.restore_caller_saved(saved);
+    /// ```
+    pub fn set_target(&mut self, target: Option<Target>) {
+        if let Some(target) = target {
+            assert_eq!(target.architecture, Architecture::X86_64, "{} isn't an x86-64 target", target.architecture);
+        }
+
+        self.target = target;
+    }
+
+    /// The target the code is generated for, if set by [`x86_64InstructionStream::set_target`].
+    #[inline(always)]
+    pub fn target(&self) -> Option<Target> {
+        self.target
+    }
+
+    /// The calling convention of the target, [`CallConv::SysV`] if no target is set.
+    pub fn call_conv(&self) -> CallConv {
+        self.target.map_or(CallConv::SysV, |target| CallConv::from_target(&target))
+    }
+
+    /// Sets the `__chkstk` routine called by [`x86_64InstructionStream::intrinsic_alloc_stack_probed`] to probe the frames larger than a page
+    /// under [`CallConv::Win64`], or `None` to probe them inline.
+    #[inline(always)]
+    pub fn set_chkstk_symbol(&mut self, symbol: Option<SymRef>) {
+        self.chkstk = symbol;
+    }
+
+    /// Writes the opcode of `mov r32, imm32` for the lower half of *r64*, which zero extends the immediate into the whole register.
+    fn write_mov_reg32_imm32_opcode(&mut self, dest: Reg64) {
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B);
+        }
+
+        self.write_byte(0xb8 + dest.offset());
+    }
+
+    /// Writes the opcode of `movabs r64, imm64`.
+    fn write_mov_reg64_imm64_opcode(&mut self, dest: Reg64) {
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_B;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0xb8 + dest.offset());
+    }
+
+    /// Loads the address of `label` into *r64*, as chosen by the [`CodeModel`]:
+    ///
+    /// | Code model  | Sequence                   | Relocation                          |
+    /// |-------------|----------------------------|-------------------------------------|
+    /// | `Pic`       | `lea dest, [rip + label]`  | none, resolved when finished        |
+    /// | `Small`     | `mov dest32, imm32`        | [`RelocationKind::Abs32`] on `Base` |
+    /// | `LargeAbs`  | `movabs dest, imm64`       | [`RelocationKind::Abs64`] on `Base` |
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{CodeModel, x86_64InstructionStream}};
+    ///
+    /// let load = |code_model| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.set_code_model(code_model);
+    ///
+    ///     let data = stream.create_label();
+    ///     stream.load_label_address(Reg64::R8, data);
+    ///     stream.ret_near();
+    ///     stream.attach_label(data);
+    ///     stream.finish()
+    /// };
+    ///
+    /// let pic = load(CodeModel::Pic);
+    /// assert_eq!(pic.bytes(), [0x4c, 0x8d, 0x05, 0x01, 0x00, 0x00, 0x00, 0xc3]); // lea r8, [rip + data]
+    /// assert_eq!(pic.relocations(), []);
+    ///
+    /// let small = load(CodeModel::Small);
+    /// assert_eq!(small.bytes(), [0x41, 0xb8, 0x00, 0x00, 0x00, 0x00, 0xc3]); // mov r8d, data
+    /// assert_eq!(small.relocations(), [Relocation { offset: 2, kind: RelocationKind::Abs32, target: RelocationTarget::Base, addend: 7 }]);
+    ///
+    /// let large = load(CodeModel::LargeAbs);
+    /// assert_eq!(large.bytes(), [0x49, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0xc3]); // movabs r8, data
+    /// assert_eq!(large.relocations(), [Relocation { offset: 2, kind: RelocationKind::Abs64, target: RelocationTarget::Base, addend: 11 }]);
+    /// assert_eq!(large.relocate(0x7f00_0000_0000, |_| unreachable!())[2..10], 0x7f00_0000_000bu64.to_le_bytes());
+    /// ```
+    pub fn load_label_address(&mut self, dest: Reg64, label: LabelRef) {
+        match self.code_model {
+            CodeModel::Pic => self.lea_reg64_label(dest, label),
+            CodeModel::Small => {
+                let instruction = self.begin_instruction();
+                self.write_mov_reg32_imm32_opcode(dest);
+                self.write_label(label, FixupKind::Abs32);
+                self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+                self.end_instruction(instruction, InstructionFamily::Mov);
+            },
+            CodeModel::LargeAbs => {
+                let instruction = self.begin_instruction();
+                self.write_mov_reg64_imm64_opcode(dest);
+                self.write_label(label, FixupKind::Abs64);
+                self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+                self.end_instruction(instruction, InstructionFamily::Mov);
+            },
+        }
+    }
+
+    /// Loads the address of the external symbol `symbol` into *r64*, as chosen by the [`CodeModel`]:
+    ///
+    /// | Code model  | Sequence                    | Relocation                                 |
+    /// |-------------|-----------------------------|--------------------------------------------|
+    /// | `Pic`       | `lea dest, [rip + symbol]`  | [`RelocationKind::Rel32`], addend `-4`     |
+    /// | `Small`     | `mov dest32, imm32`         | [`RelocationKind::Abs32`]                  |
+    /// | `LargeAbs`  | `movabs dest, imm64`        | [`RelocationKind::Abs64`]                  |
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{CodeModel, x86_64InstructionStream}};
+    ///
+    /// let load = |code_model| {
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.set_code_model(code_model);
+    ///
+    ///     let puts = stream.create_symbol("puts");
+    ///     stream.load_symbol_address(Reg64::Rax, puts);
+    ///     let product = stream.finish();
+    ///
+    ///     assert_eq!(product.symbol(puts).name, "puts");
+    ///     let relocation = product.relocations()[0];
+    ///     assert_eq!(relocation.target, RelocationTarget::Symbol(puts));
+    ///     (product.bytes().to_vec(), relocation.offset, relocation.kind, relocation.addend)
+    /// };
+    ///
+    /// assert_eq!(load(CodeModel::Pic), (vec![0x48, 0x8d, 0x05, 0, 0, 0, 0], 3, RelocationKind::Rel32, -4)); // lea rax, [rip + puts]
+    /// assert_eq!(load(CodeModel::Small), (vec![0xb8, 0, 0, 0, 0], 1, RelocationKind::Abs32, 0)); // mov eax, puts
+    /// assert_eq!(load(CodeModel::LargeAbs), (vec![0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0], 2, RelocationKind::Abs64, 0)); // movabs rax, puts
+    /// ```
+    pub fn load_symbol_address(&mut self, dest: Reg64, symbol: SymRef) {
+        let instruction = self.begin_instruction();
+
+        match self.code_model {
+            CodeModel::Pic => {
+                let mut prefix = REX | REX_W;
+
+                if dest.is_extension() {
+                    prefix |= REX_R;
+                }
+
+                self.write_byte(prefix);
+                self.write_byte(0x8d);
+                self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+
+                // the displacement is relative to the end of the instruction, 4 bytes past the field.
+                self.write_symbol(symbol, RelocationKind::Rel32, -4);
+            },
+            CodeModel::Small => {
+                self.write_mov_reg32_imm32_opcode(dest);
+                self.write_symbol(symbol, RelocationKind::Abs32, 0);
+            },
+            CodeModel::LargeAbs => {
+                self.write_mov_reg64_imm64_opcode(dest);
+                self.write_symbol(symbol, RelocationKind::Abs64, 0);
+            },
+        }
+
+        let family = if self.code_model == CodeModel::Pic { InstructionFamily::Lea } else { InstructionFamily::Mov };
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, family);
+    }
+
+    /// Loads the address of `symbol` from its global offset table entry into *r64*, `mov dest, qword ptr [rip + symbol@GOTPCREL]`, through a
+    /// [`RelocationKind::RexGotPcRelX`] relocation, as position-independent code addresses the symbols of other images.
+    pub fn mov_reg64_got_symbol(&mut self, dest: Reg64, symbol: SymRef) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_R;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0x8b);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+
+        // the displacement is relative to the end of the instruction, 4 bytes past the field.
+        self.write_symbol(symbol, RelocationKind::RexGotPcRelX, -4);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Loads the address of `symbol` into *r64*, with a `rip`-relative `lea` of the symbol, or through its global offset table entry with
+    /// [`x86_64InstructionStream::mov_reg64_got_symbol`] when it may be defined in another image:
+    ///
+    /// | Code model          | Symbol                                                  | Sequence                              | Relocation                          |
+    /// |---------------------|---------------------------------------------------------|---------------------------------------|-------------------------------------|
+    /// | `Pic`               | [local](x86_64InstructionStream::create_local_symbol)   | `lea dest, [rip + symbol]`            | [`RelocationKind::Rel32`]           |
+    /// | `Pic`               | external                                                | `mov dest, [rip + symbol@GOTPCREL]`   | [`RelocationKind::RexGotPcRelX`]    |
+    /// | `Small`, `LargeAbs` | any                                                     | `lea dest, [rip + symbol]`            | [`RelocationKind::Rel32`]           |
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::{CodeModel, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let counter = stream.create_local_symbol("counter");
+    /// let environ = stream.create_symbol("environ");
+    /// stream.lea_reg64_symbol(Reg64::Rax, counter);
+    /// stream.lea_reg64_symbol(Reg64::R9, environ);
+    /// stream.set_code_model(CodeModel::Small);
+    /// stream.lea_reg64_symbol(Reg64::Rcx, environ);
+    /// let product = stream.finish();
+    ///
+    /// assert_eq!(product.bytes(), [
+    ///     0x48, 0x8d, 0x05, 0x00, 0x00, 0x00, 0x00, // lea rax, [rip + counter]
+    ///     0x4c, 0x8b, 0x0d, 0x00, 0x00, 0x00, 0x00, // mov r9, qword ptr [rip + environ@GOTPCREL]
+    ///     0x48, 0x8d, 0x0d, 0x00, 0x00, 0x00, 0x00, // lea rcx, [rip + environ]
+    /// ]);
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 3, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(counter), addend: -4 },
+    ///     Relocation { offset: 10, kind: RelocationKind::RexGotPcRelX, target: RelocationTarget::Symbol(environ), addend: -4 },
+    ///     Relocation { offset: 17, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(environ), addend: -4 },
+    /// ]);
+    /// ```
+    pub fn lea_reg64_symbol(&mut self, dest: Reg64, symbol: SymRef) {
+        if self.code_model == CodeModel::Pic && !self.is_local_symbol(symbol) {
+            self.mov_reg64_got_symbol(dest, symbol);
+            return;
+        }
+
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_R;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0x8d);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+
+        self.write_symbol(symbol, RelocationKind::Rel32, -4);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Lea);
+    }
+
+    /// Pushes the stack base pointer onto the stack.
+    #[inline(always)]
+    pub fn intrinsic_push_rbp(&mut self) {
+        self.push_reg64(Reg64::Rbp);
+    }
+
+    /// Sets up the stack frame `frame`: saves `rbp` and points it at the frame if it uses a frame pointer, then reserves the frame on the stack.
+    ///
+    /// The frame stays current for the rest of the function, for [`x86_64InstructionStream::intrinsic_epilogue`] and the slot accesses.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{frame::FrameBuilder, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// let spill = builder.alloc_spill_64();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.mov_slot_reg64(spill, Reg64::R12);
+    /// stream.mov_reg64_slot(Reg64::Rax, spill);
+    /// stream.intrinsic_epilogue();
+    /// stream.ret_near();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x55,                   // push rbp
+    ///     0x48, 0x89, 0xe5,       // mov rbp, rsp
+    ///     0x48, 0x83, 0xec, 0x10, // sub rsp, 16
+    ///     0x4c, 0x89, 0x65, 0xf8, // mov qword ptr [rbp - 8], r12
+    ///     0x48, 0x8b, 0x45, 0xf8, // mov rax, qword ptr [rbp - 8]
+    ///     0x48, 0x89, 0xec,       // mov rsp, rbp
+    ///     0x5d,                   // pop rbp
+    ///     0xc3,                   // ret
+    /// ]);
+    ///
+    /// let mut builder = FrameBuilder::without_frame_pointer();
+    /// let spill = builder.alloc_spill_64();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.mov_slot_reg64(spill, Reg64::Rbx);
+    /// stream.mov_reg64_slot(Reg64::R13, spill);
+    /// stream.intrinsic_epilogue();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x83, 0xec, 0x08,       // sub rsp, 8
+    ///     0x48, 0x89, 0x1c, 0x24,       // mov qword ptr [rsp], rbx
+    ///     0x4c, 0x8b, 0x2c, 0x24,       // mov r13, qword ptr [rsp]
+    ///     0x48, 0x83, 0xc4, 0x08,       // add rsp, 8
+    /// ]);
+    /// ```
+    pub fn intrinsic_prologue(&mut self, frame: Frame) {
+        self.write_prologue(frame, None);
+    }
+
+    /// Sets up the stack frame `frame` as [`x86_64InstructionStream::intrinsic_prologue`] does, reserving it with
+    /// [`x86_64InstructionStream::intrinsic_alloc_stack_probed`] so each page of a large frame is touched in order, as Windows requires and as
+    /// stack clash protection does on Linux.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{callconv::CallConv, frame::FrameBuilder, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_slot(0x2000, 16);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let chkstk = stream.create_symbol("__chkstk");
+    /// stream.set_chkstk_symbol(Some(chkstk));
+    /// stream.intrinsic_prologue_probed(builder.freeze(), CallConv::Win64);
+    /// stream.intrinsic_epilogue();
+    /// stream.ret_near();
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x55,                         // push rbp
+    ///     0x48, 0x89, 0xe5,             // mov rbp, rsp
+    ///     0xb8, 0x00, 0x20, 0x00, 0x00, // mov eax, 0x2000
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00, // call __chkstk
+    ///     0x48, 0x29, 0xc4,             // sub rsp, rax
+    ///     0x48, 0x89, 0xec,             // mov rsp, rbp
+    ///     0x5d,                         // pop rbp
+    ///     0xc3,                         // ret
+    /// ]);
+    /// assert_eq!(product.relocations()[0].offset, 10);
+    /// ```
+    pub fn intrinsic_prologue_probed(&mut self, frame: Frame, convention: CallConv) {
+        self.write_prologue(frame, Some(convention));
+    }
+
+    /// Writes the prologue of `frame`, probing the stack under `probe` if it is set.
+    fn write_prologue(&mut self, frame: Frame, probe: Option<CallConv>) {
+        self.begin_atomic_region();
+        if frame.uses_frame_pointer() {
+            self.push_reg64(Reg64::Rbp);
+            self.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+        }
+
+        match probe {
+            Some(convention) => self.intrinsic_alloc_stack_probed(frame.size(), convention),
+            None if frame.size() != 0 => self.sub_reg64_imm32_unsigned(Reg64::Rsp, frame.size()),
+            None => {},
+        }
+
+        self.end_atomic_region();
+        self.frame = Some(frame);
+    }
+
+    /// Tears down the current stack frame, restoring `rsp` and `rbp` to their values on entry.  Doesn't return from the function.
+    ///
+    /// # Panics
+    /// Panics if no frame was set up by [`x86_64InstructionStream::intrinsic_prologue`].
+    pub fn intrinsic_epilogue(&mut self) {
+        let frame = self.frame.as_ref().expect("no stack frame was set up");
+        let (frame_pointer, size) = (frame.uses_frame_pointer(), frame.size());
+
+        self.begin_atomic_region();
+        if frame_pointer {
+            self.mov_reg64_reg64(Reg64::Rsp, Reg64::Rbp);
+            self.pop_reg64(Reg64::Rbp);
+        } else if size != 0 {
+            self.add_reg64_imm32_unsigned(Reg64::Rsp, size);
+        }
+        self.end_atomic_region();
+    }
+
+    /// Tears down the current stack frame, if one was set up by [`x86_64InstructionStream::intrinsic_prologue`], and jumps to `target`, so the callee
+    /// returns directly to the caller of the function instead of through a `call` and a `ret`.  The arguments of the callee are passed in registers
+    /// set up beforehand.
+    ///
+    /// # Panics
+    /// Panics if a frame was set up and `target` is `rsp` or `rbp` or addresses memory through them, as they are restored by the epilogue before the
+    /// jump.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{frame::FrameBuilder, memory::Mem, register::Reg64, stream::{CallTarget, x86_64InstructionStream}};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// builder.alloc_spill_64();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.intrinsic_tail_call(CallTarget::Mem(Mem::new(Reg64::Rdi, 8)));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x55,                   // push rbp
+    ///     0x48, 0x89, 0xe5,       // mov rbp, rsp
+    ///     0x48, 0x83, 0xec, 0x10, // sub rsp, 16
+    ///     0x48, 0x89, 0xec,       // mov rsp, rbp
+    ///     0x5d,                   // pop rbp
+    ///     0xff, 0x67, 0x08,       // jmp qword ptr [rdi + 8]
+    /// ]);
+    /// ```
+    ///
+    /// Tail calling through a vtable:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{frame::FrameBuilder, memory::Mem, register::Reg64, stream::{CallTarget, x86_64InstructionStream}};
+    ///
+    /// extern "C" fn negate(_: *const u64, x: i64) -> i64 { -x }
+    /// extern "C" fn increment(_: *const u64, x: i64) -> i64 { x + 1 }
+    ///
+    /// let vtable: [extern "C" fn(*const u64, i64) -> i64; 2] = [negate, increment];
+    ///
+    /// // spill the argument, then reload it and tail call the second slot of the vtable.
+    /// let mut builder = FrameBuilder::new();
+    /// let spill = builder.alloc_spill_64();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue(builder.freeze());
+    /// stream.mov_slot_reg64(spill, Reg64::Rsi);
+    /// stream.mov_reg64_slot(Reg64::Rsi, spill);
+    /// stream.intrinsic_tail_call(CallTarget::Mem(Mem::new(Reg64::Rdi, 8)));
+    ///
+    /// let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    /// let call: extern "C" fn(*const (), i64) -> i64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///
+    /// assert_eq!(call(vtable.as_ptr() as *const (), 41), 42);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn intrinsic_tail_call(&mut self, target: CallTarget) {
+        if self.frame.is_some() {
+            let regs = match target {
+                CallTarget::Reg(reg) => RegSet::of(reg),
+                CallTarget::Mem(mem) => RegSet::from(mem),
+                CallTarget::Label(_) | CallTarget::Symbol(_) => RegSet::EMPTY,
+            };
+
+            for reg in [Reg64::Rsp, Reg64::Rbp] {
+                assert!(!regs.contains(reg), "the target of a tail call can't go through `{}`, which the epilogue restores", reg.name());
+            }
+
+            self.intrinsic_epilogue();
+        }
+
+        match target {
+            CallTarget::Label(label) => self.jmp_label(label),
+            CallTarget::Symbol(symbol) => self.jmp_symbol(symbol),
+            CallTarget::Reg(reg) => self.jmp_reg64(reg),
+            CallTarget::Mem(mem) => self.jmp_mem(mem),
+        }
+    }
+
+    /// Reserves `size` bytes on the stack, touching each page of the reserved memory in order from the top so the guard page of the stack is
+    /// never skipped.  A size of up to [`PAGE_SIZE`] is reserved with a plain `sub rsp, size`.
+    ///
+    /// Larger sizes are reserved page by page by a loop, `or`ing zero into each page as GCC does under `-fstack-clash-protection`, and the
+    /// remainder below a page is reserved unprobed.  The loop counts down to the final `rsp` held in `r11`, which is clobbered: it holds no
+    /// argument and is saved by neither calling convention.  Under [`CallConv::Win64`], the `__chkstk` routine set by
+    /// [`x86_64InstructionStream::set_chkstk_symbol`] is called instead if there is one, as MSVC does, clobbering `rax`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{callconv::CallConv, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_alloc_stack_probed(0x1000, CallConv::SysV);
+    /// stream.intrinsic_alloc_stack_probed(0x3000, CallConv::SysV);
+    /// stream.intrinsic_alloc_stack_probed(0x2010, CallConv::Win64);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x81, 0xec, 0x00, 0x10, 0x00, 0x00, // sub rsp, 0x1000
+    ///
+    ///     0x49, 0x89, 0xe3,                         // mov r11, rsp
+    ///     0x49, 0x81, 0xeb, 0x00, 0x30, 0x00, 0x00, // sub r11, 0x3000
+    ///     0x48, 0x81, 0xec, 0x00, 0x10, 0x00, 0x00, // probe: sub rsp, 0x1000
+    ///     0x48, 0x83, 0x0c, 0x24, 0x00,             // or qword ptr [rsp], 0
+    ///     0x4c, 0x39, 0xdc,                         // cmp rsp, r11
+    ///     0x0f, 0x85, 0xeb, 0xff, 0xff, 0xff,       // jne probe
+    ///
+    ///     0x49, 0x89, 0xe3,                         // mov r11, rsp
+    ///     0x49, 0x81, 0xeb, 0x00, 0x20, 0x00, 0x00, // sub r11, 0x2000
+    ///     0x48, 0x81, 0xec, 0x00, 0x10, 0x00, 0x00, // probe: sub rsp, 0x1000
+    ///     0x48, 0x83, 0x0c, 0x24, 0x00,             // or qword ptr [rsp], 0
+    ///     0x4c, 0x39, 0xdc,                         // cmp rsp, r11
+    ///     0x0f, 0x85, 0xeb, 0xff, 0xff, 0xff,       // jne probe
+    ///     0x48, 0x83, 0xec, 0x10,                   // sub rsp, 16
+    /// ]);
+    /// ```
+    ///
+    /// A frame of 5 pages, allocated and touched from the bottom:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{callconv::CallConv, frame::FrameBuilder, memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// let bottom = builder.alloc_spill_64();
+    /// builder.alloc_slot(5 * 4096, 16);
+    /// let frame = builder.freeze();
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_prologue_probed(frame, CallConv::SysV);
+    /// stream.mov_slot_reg64(bottom, Reg64::Rdi);
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::Rsp, 0), Reg64::Rdi);
+    /// stream.mov_reg64_mem64(Reg64::Rax, Mem::new(Reg64::Rsp, 0));
+    /// stream.add_reg64_imm32(Reg64::Rax, 1);
+    /// stream.intrinsic_epilogue();
+    /// stream.ret_near();
+    ///
+    /// let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    /// let function: extern "C" fn(u64) -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    /// assert_eq!(function(41), 42);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn intrinsic_alloc_stack_probed(&mut self, size: u32, convention: CallConv) {
+        self.begin_atomic_region();
+        self.write_stack_probes(size, convention);
+        self.end_atomic_region();
+    }
+
+    /// Writes the allocation of [`x86_64InstructionStream::intrinsic_alloc_stack_probed`].
+    fn write_stack_probes(&mut self, size: u32, convention: CallConv) {
+        if size <= PAGE_SIZE {
+            if size != 0 {
+                self.sub_reg64_imm32_unsigned(Reg64::Rsp, size);
+            }
+
+            return;
+        }
+
+        if let (CallConv::Win64, Some(chkstk)) = (convention, self.chkstk) {
+            self.mov_reg32_imm32(Reg32::Eax, size);
+            self.call_symbol(chkstk);
+            self.sub_reg64_reg64(Reg64::Rsp, Reg64::Rax);
+            return;
+        }
+
+        let probed = size & !(PAGE_SIZE - 1);
+        self.mov_reg64_reg64(Reg64::R11, Reg64::Rsp);
+        self.sub_reg64_imm32_unsigned(Reg64::R11, probed);
+
+        let probe = self.create_label_attached();
+        self.sub_reg64_imm32_unsigned(Reg64::Rsp, PAGE_SIZE);
+        self.or_mem64_imm8(Mem::new(Reg64::Rsp, 0), 0);
+        self.cmp_reg64_reg64(Reg64::Rsp, Reg64::R11);
+        self.jcc_label(Condition::Ne, probe);
+
+        if size != probed {
+            self.sub_reg64_imm32_unsigned(Reg64::Rsp, size - probed);
+        }
+    }
+
+    /// The current stack frame, set up by [`x86_64InstructionStream::intrinsic_prologue`].
+    #[inline(always)]
+    pub fn frame(&self) -> Option<&Frame> {
+        self.frame.as_ref()
+    }
+
+    /// The memory operand of `slot` in the current stack frame.
+    fn slot(&self, slot: SlotRef) -> Mem {
+        self.frame.as_ref().expect("no stack frame was set up").slot(slot)
+    }
+
+    /// Enables or disables the alignment checks: the instructions requiring a 16-byte aligned memory operand, such as `movaps`, `movdqa`,
+    /// `movntdq` and the packed SSE arithmetic, then panic when emitted with an operand known to be misaligned, rather than faulting at runtime.
+    ///
+    /// The alignment of a memory operand is known when it addresses the current frame, see [`x86_64InstructionStream::intrinsic_prologue`]:
+    /// relative to `rbp` if the frame uses a frame pointer, or relative to `rsp` outside of the register saves of
+    /// [`x86_64InstructionStream::save_caller_saved`], assuming `rsp` is not moved otherwise.  The alignment of a constant loaded from the
+    /// constant pool is the alignment it was added with.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{frame::FrameBuilder, memory::Mem, register::{Reg64, Xmm}, stream::x86_64InstructionStream};
+    ///
+    /// let mut builder = FrameBuilder::new();
+    /// let vector = builder.alloc_slot(16, 16);
+    /// let frame = builder.freeze();
+    /// let slot = frame.slot(vector);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_alignment_checks(true);
+    /// stream.intrinsic_prologue(frame);
+    /// stream.movaps_mem_xmm(slot, Xmm::Xmm0);
+    /// stream.movaps_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rdi, 8)); // the alignment of `rdi` is unknown
+    /// stream.movups_xmm_mem(Xmm::Xmm1, Mem::new(Reg64::Rbp, -8)); // `movups` doesn't require alignment
+    /// ```
+    pub fn enable_alignment_checks(&mut self, enable: bool) {
+        self.alignment_checks = enable;
+    }
+
+    /// The alignment of the address of `mem`, if it is known, see [`x86_64InstructionStream::enable_alignment_checks`].
+    fn known_alignment(&self, mem: Mem) -> Option<u32> {
+        let frame = self.frame.as_ref()?;
+
+        // `rsp` is 16-byte aligned once the frame is reserved, and `rbp` points 16 bytes below the canonical frame address.  The index of an
+        // operand is unknown.
+        let aligned_base = mem.index.is_none() && match mem.base {
+            Reg64::Rbp => frame.uses_frame_pointer(),
+            Reg64::Rsp => self.save_depth == 0,
+            _ => false,
+        };
+
+        if !aligned_base {
+            return None;
+        }
+
+        match mem.displacement {
+            0 => Some(16),
+            displacement => Some(16.min(1 << displacement.trailing_zeros())),
+        }
+    }
+
+    /// Checks that the memory operand `mem` of `mnemonic` is aligned to `align` bytes, if the alignment checks are enabled and its alignment is
+    /// known.  The frame is only known to be 16-byte aligned, so larger alignments are checked to 16 bytes.
+    ///
+    /// # Panics
+    /// Panics if `mem` is known to be misaligned.
+    pub(crate) fn check_alignment(&self, mnemonic: &str, mem: Mem, align: u32) {
+        if !self.alignment_checks {
+            return;
+        }
+
+        if let Some(known) = self.known_alignment(mem) {
+            assert!(known >= align.min(16), "`{}` requires a {}-byte aligned operand, but {:?} is only {}-byte aligned", mnemonic, align, mem, known);
+        }
+    }
+
+    /// Checks that the constant at `label`, loaded by `mnemonic`, is aligned to `align` bytes, if the alignment checks are enabled.
+    ///
+    /// # Panics
+    /// Panics if the constant is added to the pool with a smaller alignment.
+    pub(crate) fn check_label_alignment(&self, mnemonic: &str, label: LabelRef, align: usize) {
+        if !self.alignment_checks {
+            return;
+        }
+
+        if let Some(known) = self.constants.align(label) {
+            assert!(known >= align, "`{}` requires a {}-byte aligned operand, but the constant is only {}-byte aligned", mnemonic, align, known);
+        }
+    }
+
+    /// Move *r64* to the stack slot `slot` of the current frame.
+    ///
+    /// # Panics
+    /// Panics if no frame was set up by [`x86_64InstructionStream::intrinsic_prologue`].
+    pub fn mov_slot_reg64(&mut self, slot: SlotRef, src: Reg64) {
+        let mem = self.slot(slot);
+        self.mov_mem64_reg64(mem, src);
+    }
+
+    /// Move the stack slot `slot` of the current frame to *r64*.
+    ///
+    /// # Panics
+    /// Panics if no frame was set up by [`x86_64InstructionStream::intrinsic_prologue`].
+    pub fn mov_reg64_slot(&mut self, dest: Reg64, slot: SlotRef) {
+        let mem = self.slot(slot);
+        self.mov_reg64_mem64(dest, mem);
+    }
+
+    /// Writes the REX prefix, the opcode, the ModRM byte, and the SIB byte and displacement if needed, of an instruction with a 64-bit operand size, the
+    /// register field `reg` and the memory operand `mem`.
+    #[inline(always)]
+    fn write_rex_w_modrm_mem(&mut self, opcode: &[u8], reg: Reg64, mem: Mem) {
+        self.write_rex_modrm_mem(true, opcode, (reg.offset(), reg.is_extension()), mem);
+    }
+
+    /// Writes the REX prefix if needed, the opcode, the ModRM byte, and the SIB byte and displacement if needed, of an instruction with the register
+    /// field `reg` (its offset, and whether it is an extension register) and the memory operand `mem`.  Mandatory prefixes are written by the caller.
+    pub(crate) fn write_rex_modrm_mem(&mut self, rex_w: bool, opcode: &[u8], reg: (u8, bool), mem: Mem) {
+        self.write_rex_modrm_mem_with_options(rex_w, opcode, reg, mem, EncodingOptions::NONE);
+    }
+
+    /// Writes an instruction with a memory operand like [`x86_64InstructionStream::write_rex_modrm_mem`], honoring the [`EncodingOptions::FORCE_REX`]
+    /// and [`EncodingOptions::FORCE_DISP32`] options of `options`.  A forced displacement is recorded as a patchable field.
+    pub(crate) fn write_rex_modrm_mem_with_options(&mut self, rex_w: bool, opcode: &[u8], reg: (u8, bool), mem: Mem, options: EncodingOptions) {
+        let mut prefix = REX;
+
+        if rex_w {
+            prefix |= REX_W;
+        }
+
+        if reg.1 {
+            prefix |= REX_R;
+        }
+
+        if mem.index.is_some_and(|(index, _)| index.is_extension()) {
+            prefix |= REX_X;
+        }
+
+        if mem.base.is_extension() {
+            prefix |= REX_B;
+        }
+
+        if prefix != REX || options.contains(EncodingOptions::FORCE_REX) {
+            self.write_byte(prefix);
+        }
+
+        self.write_bytes(opcode);
+        self.write_modrm_mem(reg.0, mem, options);
+    }
+
+    /// Writes the ModRM byte, and the SIB byte and displacement if needed, of the register field `reg` and the memory operand `mem`, honoring the
+    /// [`EncodingOptions::FORCE_DISP32`] option of `options`.  The prefixes and the opcode are written by the caller.
+    pub(crate) fn write_modrm_mem(&mut self, reg: u8, mem: Mem, options: EncodingOptions) {
+        if self.mode == Mode::Real {
+            self.require_address_size_32();
+        }
+
+        // `rbp` and `r13` have no displacement-free form, their encoding is taken by rip-relative addressing, or by the absence of a base in a
+        // SIB byte.
+        let base = mem.base.offset();
+        let mode = if options.contains(EncodingOptions::FORCE_DISP32) {
+            0b10
+        } else if mem.displacement == 0 && base != 0b101 {
+            0b00
+        } else if i8::try_from(mem.displacement).is_ok() {
+            0b01
+        } else {
+            0b10
+        };
+
+        match mem.index {
+            Some((index, scale)) => {
+                self.write_byte((mode << 6) | (reg << 3) | 0b100);
+                self.write_byte((scale.bits() << 6) | (index.offset() << 3) | base);
+            },
+            None => {
+                self.write_byte((mode << 6) | (reg << 3) | base);
+
+                // `rsp` and `r12` can only be encoded as a base through a SIB byte, with no index.
+                if base == 0b100 {
+                    self.write_byte(0x24);
+                }
+            },
+        }
+
+        match mode {
+            0b01 => self.write_byte(mem.displacement as u8),
+            0b10 if options.contains(EncodingOptions::FORCE_DISP32) => {
+                self.write_patchable_double_word(mem.displacement as u32, PatchFieldKind::Displacement);
+            },
+            0b10 => self.write_double_word(mem.displacement as u32),
+            _ => {},
+        }
+    }
+
+    /// Records the call instruction started at `start` and ending at the current offset as a call site of the product.
+    fn record_call_site(&mut self, start: usize, target: CallSiteTarget, kind: CallKind) {
+        self.call_sites.push(CallSite { offset: start, len: self.bytes.len() - start, target, kind });
+    }
+
+    /// Writes a 32-bit field and records it as patchable, for the fields emitted wider than needed, see [`EncodingOptions`].
+    pub(crate) fn write_patchable_double_word(&mut self, value: u32, kind: PatchFieldKind) {
+        self.patch_fields.push(PatchField { offset: self.bytes.len(), size: 4, kind });
+        self.write_double_word(value);
+    }
+
+    /// Writes a VEX prefix, selecting the opcode `map` (`1` for `0F`, `2` for `0F 38`, `3` for `0F 3A`) and the implied prefix `pp` (`1` for `66`,
+    /// `2` for `F3`, `3` for `F2`).  The 2-byte form is picked when it can encode the fields.  The `R`, `X` and `B` extensions and the `vvvv`
+    /// register are stored inverted.
+    pub(crate) fn write_vex(&mut self, (r, x, b): (bool, bool, bool), map: u8, w: bool, vvvv: u8, l: bool, pp: u8) {
+        if !x && !b && !w && map == 1 {
+            self.write_byte(0xc5);
+            self.write_byte(((!r as u8) << 7) | ((!vvvv & 0xf) << 3) | ((l as u8) << 2) | pp);
+        } else {
+            self.write_byte(0xc4);
+            self.write_byte(((!r as u8) << 7) | ((!x as u8) << 6) | ((!b as u8) << 5) | map);
+            self.write_byte(((w as u8) << 7) | ((!vvvv & 0xf) << 3) | ((l as u8) << 2) | pp);
+        }
+    }
+
+    /// Move *r64* to *m64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::Rax, 0), Reg64::Rcx);
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::R13, 0), Reg64::Rcx);
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::R12, -0x80), Reg64::R9);
+    /// stream.mov_mem64_reg64(Mem::new(Reg64::Rsp, 0x1000), Reg64::Rdx);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x89, 0x08,                               // mov qword ptr [rax], rcx
+    ///     0x49, 0x89, 0x4d, 0x00,                         // mov qword ptr [r13], rcx
+    ///     0x4d, 0x89, 0x4c, 0x24, 0x80,                   // mov qword ptr [r12 - 0x80], r9
+    ///     0x48, 0x89, 0x94, 0x24, 0x00, 0x10, 0x00, 0x00, // mov qword ptr [rsp + 0x1000], rdx
+    /// ]);
+    /// ```
+    pub fn mov_mem64_reg64(&mut self, dest: Mem, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x89], src, dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *m64* to *r64*.
+    pub fn mov_reg64_mem64(&mut self, dest: Reg64, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x8b], dest, src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the quadword at `label` to *r64*, through a `rip`-relative operand.
+    pub fn mov_reg64_label(&mut self, dest: Reg64, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_R;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0x8b);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r32* to *m32*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg8, Reg16, Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_mem32_reg32(Mem::new(Reg64::Rax, 0), Reg32::Ecx);
+    /// stream.mov_mem32_reg32(Mem::new(Reg64::R13, 8), Reg32::R11d);
+    /// stream.mov_reg32_mem32(Reg32::R11d, Mem::new(Reg64::Rsi, 4));
+    /// stream.mov_mem16_reg16(Mem::new(Reg64::Rdi, 2), Reg16::R11w);
+    /// stream.mov_reg16_mem16(Reg16::Cx, Mem::new(Reg64::R12, 0));
+    /// stream.mov_mem8_reg8(Mem::new(Reg64::Rdi, 7), Reg8::R11b);
+    /// stream.mov_mem8_reg8(Mem::new(Reg64::Rax, 0), Reg8::Sil);
+    /// stream.mov_reg8_mem8(Reg8::R11b, Mem::new(Reg64::Rsi, 0));
+    /// stream.mov_reg8_mem8(Reg8::Ah, Mem::new(Reg64::Rbx, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x89, 0x08,                   // mov dword ptr [rax], ecx
+    ///     0x45, 0x89, 0x5d, 0x08,       // mov dword ptr [r13 + 8], r11d
+    ///     0x44, 0x8b, 0x5e, 0x04,       // mov r11d, dword ptr [rsi + 4]
+    ///     0x66, 0x44, 0x89, 0x5f, 0x02, // mov word ptr [rdi + 2], r11w
+    ///     0x66, 0x41, 0x8b, 0x0c, 0x24, // mov cx, word ptr [r12]
+    ///     0x44, 0x88, 0x5f, 0x07,       // mov byte ptr [rdi + 7], r11b
+    ///     0x40, 0x88, 0x30,             // mov byte ptr [rax], sil
+    ///     0x44, 0x8a, 0x1e,             // mov r11b, byte ptr [rsi]
+    ///     0x8a, 0x23,                   // mov ah, byte ptr [rbx]
+    /// ]);
+    /// ```
+    pub fn mov_mem32_reg32(&mut self, dest: Mem, src: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0x89], (src.offset(), src.is_extension()), dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *m32* to *r32*, zeroing the upper half of the 64-bit register.
+    pub fn mov_reg32_mem32(&mut self, dest: Reg32, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0x8b], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r16* to *m16*.
+    pub fn mov_mem16_reg16(&mut self, dest: Mem, src: Reg16) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_rex_modrm_mem(false, &[0x89], (src.offset(), src.is_extension()), dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *m16* to *r16*.
+    pub fn mov_reg16_mem16(&mut self, dest: Reg16, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_rex_modrm_mem(false, &[0x8b], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src).with(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r8* to *m8*.
+    ///
+    /// # Panics
+    /// Panics if *r8* is a high byte register and the base of *m8* is an extension register, as a high byte register has no encoding along with the
+    /// REX prefix the base requires, see [`Reg8::check_encodable_with_mem`].
+    pub fn mov_mem8_reg8(&mut self, dest: Mem, src: Reg8) {
+        let instruction = self.begin_instruction();
+        self.write_reg8_mem(0x88, src, dest);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *m8* to *r8*.
+    ///
+    /// # Panics
+    /// Panics if *r8* is a high byte register and the base of *m8* is an extension register, as a high byte register has no encoding along with the
+    /// REX prefix the base requires, see [`Reg8::check_encodable_with_mem`].
+    ///
+    /// # Example
+    /// ```should_panic
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg64, Reg8}, stream::x86_64InstructionStream};
+    ///
+    /// // `ah` has no encoding along with a REX prefix, which the base `r12` requires.
+    /// x86_64InstructionStream::new().mov_reg8_mem8(Reg8::Ah, Mem::new(Reg64::R12, 8));
+    /// ```
+    pub fn mov_reg8_mem8(&mut self, dest: Reg8, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_reg8_mem(0x8a, dest, src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src).with(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Writes an instruction with the 8-bit register field `reg` and the memory operand `mem`, with a REX prefix if `reg` requires one.
+    ///
+    /// # Panics
+    /// Panics if `reg` is a high byte register and `mem` requires a REX prefix, see [`Reg8::check_encodable_with_mem`].
+    fn write_reg8_mem(&mut self, opcode: u8, reg: Reg8, mem: Mem) {
+        if let Err(error) = reg.check_encodable_with_mem(mem) {
+            panic!("{}", error);
+        }
+
+        let options = if reg.requires_rex() { EncodingOptions::FORCE_REX } else { EncodingOptions::NONE };
+        self.write_rex_modrm_mem_with_options(false, &[opcode], (reg.offset(), reg.is_extension()), mem, options);
+    }
+
+    /// Writes the `C6`/`C7` move of an immediate to the memory operand `mem`, whose immediate follows the MODRM byte, the SIB byte and the displacement.
+    pub(crate) fn write_mov_mem_imm(&mut self, prefix: Option<u8>, rex_w: bool, opcode: u8, mem: Mem, options: EncodingOptions) {
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        self.write_rex_modrm_mem_with_options(rex_w, &[opcode], (0, false), mem, options); // /0
+    }
+
+    /// Writes the `C6`/`C7` move of an immediate of `size` bytes to the `rip`-relative `label`, up to the displacement.  The displacement is relative
+    /// to the end of the instruction, past the immediate.
+    fn write_mov_label_imm(&mut self, prefix: Option<u8>, rex_w: bool, opcode: u8, label: LabelRef, size: usize) {
+        if let Some(prefix) = prefix {
+            self.write_byte(prefix);
+        }
+
+        if rex_w {
+            self.write_byte(REX | REX_W);
+        }
+
+        self.write_byte(opcode);
+        self.write_byte(0b101); // `[rip + disp32]` /0 MODRM encoded
+        self.write_label(label, FixupKind::Rel32Imm(size));
+    }
+
+    /// Move *imm8* to *m8*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_mem8_imm8(Mem::new(Reg64::Rax, 0), 0xff);
+    /// stream.mov_mem16_imm16(Mem::new(Reg64::R13, 0), 0x1234);
+    /// stream.mov_mem32_imm32(Mem::new(Reg64::Rsp, 8), 0xdeadbeef);
+    /// stream.mov_mem64_imm32(Mem::new(Reg64::Rbp, -8), 0);
+    /// stream.mov_mem64_imm32(Mem::new(Reg64::R12, 0x1000), -1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xc6, 0x00, 0xff,                                                       // mov byte ptr [rax], 0xff
+    ///     0x66, 0x41, 0xc7, 0x45, 0x00, 0x34, 0x12,                               // mov word ptr [r13], 0x1234
+    ///     0xc7, 0x44, 0x24, 0x08, 0xef, 0xbe, 0xad, 0xde,                         // mov dword ptr [rsp + 8], 0xdeadbeef
+    ///     0x48, 0xc7, 0x45, 0xf8, 0x00, 0x00, 0x00, 0x00,                         // mov qword ptr [rbp - 8], 0
+    ///     0x49, 0xc7, 0x84, 0x24, 0x00, 0x10, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, // mov qword ptr [r12 + 0x1000], -1
+    /// ]);
+    /// ```
+    pub fn mov_mem8_imm8(&mut self, dest: Mem, src: u8) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(None, false, 0xc6, dest, EncodingOptions::NONE);
+        self.write_byte(src);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm16* to *m16*.
+    pub fn mov_mem16_imm16(&mut self, dest: Mem, src: u16) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(self.word_operand_prefix(), false, 0xc7, dest, EncodingOptions::NONE);
+        self.write_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32* to *m32*.
+    pub fn mov_mem32_imm32(&mut self, dest: Mem, src: u32) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(None, false, 0xc7, dest, EncodingOptions::NONE);
+        self.write_double_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32*, sign extended to 64 bits, to *m64*.  There is no form with a 64-bit immediate: a value outside of the `i32` range must go
+    /// through a register, see [`x86_64InstructionStream::mov_reg64_imm64`].
+    pub fn mov_mem64_imm32(&mut self, dest: Mem, src: i32) {
+        self.mov_mem64_imm32_unsigned(dest, src as u32);
+    }
+
+    /// Move the bits of *imm32*, sign extended to 64 bits, to *m64*: `0x8000_0000` and above store a negative quadword, see
+    /// [`x86_64InstructionStream::mov_mem64_imm32`].
+    pub fn mov_mem64_imm32_unsigned(&mut self, dest: Mem, src: u32) {
+        let instruction = self.begin_instruction();
+        self.write_mov_mem_imm(None, true, 0xc7, dest, EncodingOptions::NONE);
+        self.write_double_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm8* to the byte at `label`, through a `rip`-relative operand.
+    ///
+    /// The displacement is relative to the end of the instruction, which lies past the immediate rather than right after the displacement.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let data = stream.create_label();
+    /// stream.mov_label8_imm8(data, 1);
+    /// stream.mov_label16_imm16(data, 2);
+    /// stream.mov_label32_imm32(data, 3);
+    /// stream.mov_label64_imm32(data, -4);
+    /// stream.attach_label(data); // at 0x25
+    /// stream.write_bytes(&[0; 8]);
+    ///
+    /// assert_eq!(stream.finish().bytes()[..0x25], [
+    ///     0xc6, 0x05, 0x1e, 0x00, 0x00, 0x00, 0x01,                         // mov byte ptr [rip + 0x1e], 1
+    ///     0x66, 0xc7, 0x05, 0x15, 0x00, 0x00, 0x00, 0x02, 0x00,             // mov word ptr [rip + 0x15], 2
+    ///     0xc7, 0x05, 0x0b, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,       // mov dword ptr [rip + 0x0b], 3
+    ///     0x48, 0xc7, 0x05, 0x00, 0x00, 0x00, 0x00, 0xfc, 0xff, 0xff, 0xff, // mov qword ptr [rip + 0x00], -4
+    /// ]);
+    /// ```
+    pub fn mov_label8_imm8(&mut self, label: LabelRef, src: u8) {
+        let instruction = self.begin_instruction();
+        self.write_mov_label_imm(None, false, 0xc6, label, 1);
+        self.write_byte(src);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm16* to the word at `label`, through a `rip`-relative operand, see [`x86_64InstructionStream::mov_label8_imm8`].
+    pub fn mov_label16_imm16(&mut self, label: LabelRef, src: u16) {
+        let instruction = self.begin_instruction();
+        self.write_mov_label_imm(self.word_operand_prefix(), false, 0xc7, label, 2);
+        self.write_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32* to the doubleword at `label`, through a `rip`-relative operand, see [`x86_64InstructionStream::mov_label8_imm8`].
+    pub fn mov_label32_imm32(&mut self, label: LabelRef, src: u32) {
+        let instruction = self.begin_instruction();
+        self.write_mov_label_imm(None, false, 0xc7, label, 4);
+        self.write_double_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32*, sign extended to 64 bits, to the quadword at `label`, through a `rip`-relative operand, see
+    /// [`x86_64InstructionStream::mov_label8_imm8`] and [`x86_64InstructionStream::mov_mem64_imm32`].
+    pub fn mov_label64_imm32(&mut self, label: LabelRef, src: i32) {
+        self.mov_label64_imm32_unsigned(label, src as u32);
+    }
+
+    /// Move the bits of *imm32*, sign extended to 64 bits, to the quadword at `label`, see [`x86_64InstructionStream::mov_label64_imm32`].
+    pub fn mov_label64_imm32_unsigned(&mut self, label: LabelRef, src: u32) {
+        let instruction = self.begin_instruction();
+        self.write_mov_label_imm(None, true, 0xc7, label, 4);
+        self.write_double_word(src);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Exchange *r64* with *m64*.
+    ///
+    /// **NOTE:** an exchange with a memory operand is always locked, with or without a `lock` prefix.
+    pub fn xchg_mem64_reg64(&mut self, dest: Mem, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x87], src, dest);
+        self.record_registers(RegSet::of(src), RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Atomic);
+    }
+
+    /// Exchange *r64* with *r/m64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.xchg_reg64_reg64(Reg64::Rsi, Reg64::Rdi);
+    /// stream.xchg_reg64_reg64(Reg64::R8, Reg64::Rax);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x87, 0xfe, // xchg rsi, rdi
+    ///     0x49, 0x87, 0xc0, // xchg r8, rax
+    /// ]);
+    /// ```
+    pub fn xchg_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x87, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.record_registers(RegSet::of(dest).with(src), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Exchange *r64* and *m64*, and load their sum into *m64*.  Usually preceded by [`x86_64InstructionStream::lock`].
+    pub fn xadd_mem64_reg64(&mut self, dest: Mem, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x0f, 0xc1], src, dest);
+        self.record_registers(RegSet::of(src), RegSet::from(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Atomic);
+    }
+
+    /// Compare `rax` with *m64*.  If equal, load *r64* into *m64*, otherwise load *m64* into `rax`.  Usually preceded by
+    /// [`x86_64InstructionStream::lock`].
+    pub fn cmpxchg_mem64_reg64(&mut self, dest: Mem, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x0f, 0xb1], src, dest);
+        self.record_registers(RegSet::of(Reg64::Rax), RegSet::from(dest).with(src).with(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::Atomic);
+    }
+
+    /// Writes the `lock` prefix, making the following read-modify-write instruction atomic.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.lock();
+    /// stream.xadd_mem64_reg64(Mem::new(Reg64::Rdi, 8), Reg64::Rax);
+    /// stream.lock();
+    /// stream.cmpxchg_mem64_reg64(Mem::new(Reg64::R8, 0), Reg64::R9);
+    /// stream.xchg_mem64_reg64(Mem::new(Reg64::Rsp, 0), Reg64::Rbx);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf0, 0x48, 0x0f, 0xc1, 0x47, 0x08, // lock xadd qword ptr [rdi + 8], rax
+    ///     0xf0, 0x4d, 0x0f, 0xb1, 0x08,       // lock cmpxchg qword ptr [r8], r9
+    ///     0x48, 0x87, 0x1c, 0x24,             // xchg qword ptr [rsp], rbx
+    /// ]);
+    /// ```
+    pub fn lock(&mut self) {
+        self.prefix_start.get_or_insert(self.bytes.len());
+        self.write_byte(0xf0);
+    }
+
+    /// Writes the operand size prefix in [real mode](crate::mode), making the following instruction take 32-bit operands rather than 16-bit ones,
+    /// as NASM's `o32` does.  Writes nothing in protected and long mode, where the 32-bit operands are the default.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, memory::Mem, mode::Mode, register::{Reg16, Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+    /// stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.o32();
+    /// stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.a32();
+    /// stream.mov_reg16_mem16(Reg16::Ax, Mem::new(Reg64::Rbx, 4));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x31, 0xc0,             // xor ax, ax
+    ///     0x66, 0x31, 0xc0,       // xor eax, eax
+    ///     0x67, 0x8b, 0x43, 0x04, // mov ax, word ptr [ebx + 4]
+    /// ]);
+    /// ```
+    pub fn o32(&mut self) {
+        if self.mode == Mode::Real {
+            self.prefix_start.get_or_insert(self.bytes.len());
+            self.write_byte(0x66);
+        }
+    }
+
+    /// Writes the address size prefix in [real mode](crate::mode) and long mode, making the memory operand of the following instruction address
+    /// through the lower 32 bits of its registers, as NASM's `a32` does.  Writes nothing in protected mode, where the 32-bit addresses are the
+    /// default.
+    ///
+    /// Real mode addresses through 16-bit registers, with an encoding of its own, so its memory operands are only emitted after `a32`.
+    pub fn a32(&mut self) {
+        if self.mode != Mode::Protected {
+            self.prefix_start.get_or_insert(self.bytes.len());
+            self.write_byte(0x67);
+            self.address_size_32 = true;
+        }
+    }
+
+    /// Checks that a memory operand is emitted after [`x86_64InstructionStream::a32`] in real mode.
+    ///
+    /// # Panics
+    /// Panics if the instruction isn't prefixed by `a32`.
+    fn require_address_size_32(&self) {
+        assert!(self.address_size_32, "memory operands require the `a32` prefix in real mode");
+    }
+
+    /// Serializes all loads and stores issued before it.
+    pub fn mfence(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0xae, 0xf0]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Fence);
+    }
+
+    /// Serializes all loads issued before it.
+    pub fn lfence(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0xae, 0xe8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Fence);
+    }
+
+    /// Serializes all stores issued before it.
+    pub fn sfence(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0xae, 0xf8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Fence);
+    }
+
+    /// Writes an arithmetic instruction of the `0x81`/`0x83` group with the opcode extension `extension`, picking the sign-extended *imm8* form when
+    /// *imm32* fits in it, unless `options` forces the *imm32* form.
+    pub(crate) fn write_arith_reg64_imm32(&mut self, extension: u8, dest: Reg64, imm32: u32, options: EncodingOptions) {
+        let force_imm32 = options.contains(EncodingOptions::FORCE_IMM32);
+
+        if self.peephole_active() && !force_imm32 && imm32 == 0 && (extension == 0 || extension == 5) {
+            self.peephole_counters.zero_arithmetic += 1;
+            return;
+        }
+
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_B;
+        }
+
+        self.write_byte(prefix);
+        self.write_arith_imm32((0b11 << 6) | (extension << 3) | dest.offset(), imm32, force_imm32);
+    }
+
+    /// Writes an arithmetic instruction of the `0x81`/`0x83` group on a 32-bit register, picking the *imm8* form like
+    /// [`x86_64InstructionStream::write_arith_reg64_imm32`].
+    pub(crate) fn write_arith_reg32_imm32(&mut self, extension: u8, dest: Reg32, imm32: u32, options: EncodingOptions) {
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        } else if options.contains(EncodingOptions::FORCE_REX) {
+            self.write_byte(REX);
+        }
+
+        self.write_arith_imm32((0b11 << 6) | (extension << 3) | dest.offset(), imm32, options.contains(EncodingOptions::FORCE_IMM32));
+    }
+
+    /// Writes the opcode, the MODRM byte `modrm` and the immediate of an arithmetic instruction of the `0x81`/`0x83` group.  A forced *imm32* is
+    /// recorded as a patchable field.
+    fn write_arith_imm32(&mut self, modrm: u8, imm32: u32, force_imm32: bool) {
+        match i8::try_from(imm32 as i32) {
+            Ok(imm8) if !force_imm32 => {
+                self.write_byte(0x83);
+                self.write_byte(modrm);
+                self.write_byte(imm8 as u8);
+            },
+            _ => {
+                self.write_byte(0x81);
+                self.write_byte(modrm);
+
+                if force_imm32 {
+                    self.write_patchable_double_word(imm32, PatchFieldKind::Immediate);
+                } else {
+                    self.write_double_word(imm32);
+                }
+            },
+        }
+    }
+
+    /// Writes a two register instruction of the `op r/m, r` form, with the register operands given as their offset and whether they are extensions.
+    pub(crate) fn write_reg_reg(&mut self, rex_w: bool, opcode: u8, rm: (u8, bool), reg: (u8, bool)) {
+        let mut prefix = REX;
+
+        if rex_w {
+            prefix |= REX_W;
+        }
+
+        if rm.1 {
+            prefix |= REX_B;
+        }
+
+        if reg.1 {
+            prefix |= REX_R;
+        }
+
+        if prefix != REX {
+            self.write_byte(prefix);
+        }
+
+        self.write_byte(opcode);
+        self.write_byte((0b11 << 6) | (reg.0 << 3) | rm.0);
+    }
+
+    /// Add *imm32*, sign extended to 64 bits, to *r64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.add_reg64_imm32(Reg64::Rax, 1);
+    /// stream.add_reg64_imm32(Reg64::R10, 0x1000);
+    /// stream.sub_reg64_imm32(Reg64::Rsp, -1);
+    /// stream.add_reg64_imm32(Reg64::Rdi, -0x1000);
+    /// stream.add_reg64_imm32_unsigned(Reg64::Rdi, 0xffff_f000);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x83, 0xc0, 0x01,                   // add rax, 1
+    ///     0x49, 0x81, 0xc2, 0x00, 0x10, 0x00, 0x00, // add r10, 0x1000
+    ///     0x48, 0x83, 0xec, 0xff,                   // sub rsp, -1
+    ///     0x48, 0x81, 0xc7, 0x00, 0xf0, 0xff, 0xff, // add rdi, -0x1000
+    ///     0x48, 0x81, 0xc7, 0x00, 0xf0, 0xff, 0xff, // add rdi, -0x1000
+    /// ]);
+    /// ```
+    ///
+    /// A 64-bit constant past the `i32` range doesn't compile, rather than being silently truncated:
+    /// ```compile_fail
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// x86_64InstructionStream::new().add_reg64_imm32(Reg64::Rax, 0xffff_f000);
+    /// ```
+    pub fn add_reg64_imm32(&mut self, dest: Reg64, imm32: i32) {
+        self.add_reg64_imm32_unsigned(dest, imm32 as u32);
+    }
+
+    /// Add the bits of *imm32*, sign extended to 64 bits, to *r64*: `0x8000_0000` and above subtract, see
+    /// [`x86_64InstructionStream::add_reg64_imm32`].
+    pub fn add_reg64_imm32_unsigned(&mut self, dest: Reg64, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg64_imm32(0, dest, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Subtract *imm32*, sign extended to 64 bits, from *r64*.
+    pub fn sub_reg64_imm32(&mut self, dest: Reg64, imm32: i32) {
+        self.sub_reg64_imm32_unsigned(dest, imm32 as u32);
+    }
+
+    /// Subtract the bits of *imm32*, sign extended to 64 bits, from *r64*: `0x8000_0000` and above add, see
+    /// [`x86_64InstructionStream::sub_reg64_imm32`].
+    pub fn sub_reg64_imm32_unsigned(&mut self, dest: Reg64, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg64_imm32(5, dest, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Writes the REX prefix, if needed, of an instruction on the 8-bit registers `reg` and `rm`.
+    ///
+    /// # Panics
+    /// Panics if a high byte register is used along with a register requiring a REX prefix, see [`Reg8::check_encodable_with`].
+    fn write_rex_reg8(&mut self, reg: Reg8, rm: Reg8) {
+        if let Err(error) = reg.check_encodable_with(rm) {
+            panic!("{}", error);
+        }
+
+        if reg.requires_rex() || rm.requires_rex() {
+            let mut prefix = REX;
+
+            if rm.is_extension() {
+                prefix |= REX_B;
+            }
+
+            if reg.is_extension() {
+                prefix |= REX_R;
+            }
+
+            self.write_byte(prefix); // REX prefix
+        }
+    }
+
+    /// Move *r8* to *r/m8*.
+    ///
+    /// # Panics
+    /// Panics if a high byte register, such as `ah`, is used along with a register requiring a REX prefix, such as `sil` or `r9b`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg8, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg8_reg8(Reg8::Ah, Reg8::Bl);
+    /// stream.mov_reg8_reg8(Reg8::Sil, Reg8::Al);
+    /// stream.mov_reg8_reg8(Reg8::R9b, Reg8::Dil);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x88, 0xdc,       // mov ah, bl
+    ///     0x40, 0x88, 0xc6, // mov sil, al
+    ///     0x41, 0x88, 0xf9, // mov r9b, dil
+    /// ]);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::{register::Reg8, stream::x86_64InstructionStream};
+    ///
+    /// // `ah` has no encoding along with a REX prefix, which `r9b` requires.
+    /// x86_64InstructionStream::new().mov_reg8_reg8(Reg8::Ah, Reg8::R9b);
+    /// ```
+    pub fn mov_reg8_reg8(&mut self, dest: Reg8, src: Reg8) {
+        if self.peephole_active() && dest == src {
+            self.peephole_counters.self_moves += 1;
+            return;
+        }
+
+        let instruction = self.begin_instruction();
+        self.write_rex_reg8(src, dest);
+
+        self.write_byte(0x88); // opcode
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset())); // value MODRM encoded
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r16* to *r/m16*.
+    pub fn mov_reg16_reg16(&mut self, dest: Reg16, src: Reg16) {
+        if self.peephole_active() && dest == src {
+            self.peephole_counters.self_moves += 1;
+            return;
+        }
+
+        let instruction = self.begin_instruction();
+
+        self.write_word_operand_prefix();
+
+        let is_dest_extension = dest.is_extension();
+        let is_src_extension = src.is_extension();
+        if is_dest_extension || is_src_extension {
+            let mut prefix = REX;
+
+            if is_dest_extension {
+                prefix |= REX_B;
+            }
+    
+            if is_src_extension {
+                prefix |= REX_R;
+            }
+
+            self.write_byte(prefix);
+        }
+
+        self.write_byte(0x89);
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset()));
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r32* to *r/m32*.
+    pub fn mov_reg32_reg32(&mut self, dest: Reg32, src: Reg32) {
+        let instruction = self.begin_instruction();
+
+        let is_dest_extension = dest.is_extension();
+        let is_src_extension = src.is_extension();
+        if is_dest_extension || is_src_extension {
+            let mut prefix = REX;
+
+            if is_dest_extension {
+                prefix |= REX_B;
+            }
+    
+            if is_src_extension {
+                prefix |= REX_R;
+            }
+
+            self.write_byte(prefix);
+        }
+
+        self.write_byte(0x89);
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset()));
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *r64* to *r/m64*.
+    pub fn mov_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
+        if self.peephole_active() && dest == src {
+            self.peephole_counters.self_moves += 1;
+            return;
+        }
+
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W; // REX.W prefix
+
+        if dest.is_extension() {
+            prefix |= REX_B;
+        }
+
+        if src.is_extension() {
+            prefix |= REX_R;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0x89); // opcode
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset())); // value MODRM encoded
+
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm8* to *r8*.
+    pub fn mov_reg8_imm8(&mut self, dest: Reg8, src: u8) {
+        let instruction = self.begin_instruction();
+
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B);
+        } else if dest.requires_rex() {
+            self.write_byte(REX);
+        }
+
+        self.write_byte(0xb0 + dest.offset()); // opcode
+        self.write_byte(src);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm16* to *r16*.
+    pub fn mov_reg16_imm16(&mut self, dest: Reg16, src: u16) {
+        let instruction = self.begin_instruction();
+
+        self.write_word_operand_prefix();
+
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+
+        self.write_byte(0xb8 + dest.offset()); // opcode
+        self.write_word(src);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32* to *r32*.
+    pub fn mov_reg32_imm32(&mut self, dest: Reg32, src: u32) {
+        let instruction = self.begin_instruction();
+
+        if dest.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+
+        self.write_byte(0xb8 + dest.offset()); // opcode
+        self.write_double_word(src);
+
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm32*, sign extended to 64 bits, to *r64*.  A value from `0x8000_0000` to `u32::MAX` is loaded by the zero extending
+    /// [`x86_64InstructionStream::mov_reg32_imm32`] instead.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg64_imm32(Reg64::Rax, -2);
+    /// stream.mov_reg64_imm32(Reg64::R8, i32::MIN);
+    /// stream.mov_reg32_imm32(Reg32::Eax, 0xffff_fffe);
+    /// stream.mov_reg64_imm32_unsigned(Reg64::Rax, 0xffff_fffe);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0xc7, 0xc0, 0xfe, 0xff, 0xff, 0xff, // mov rax, -2
+    ///     0x49, 0xc7, 0xc0, 0x00, 0x00, 0x00, 0x80, // mov r8, -0x80000000
+    ///     0xb8, 0xfe, 0xff, 0xff, 0xff,             // mov eax, 0xfffffffe
+    ///     0x48, 0xc7, 0xc0, 0xfe, 0xff, 0xff, 0xff, // mov rax, -2
+    /// ]);
+    /// ```
+    ///
+    /// The constant `0xffff_fffe`, which would load `-2` rather than `0xfffffffe`, doesn't compile:
+    /// ```compile_fail
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// x86_64InstructionStream::new().mov_reg64_imm32(Reg64::Rax, 0xffff_fffe);
+    /// ```
+    pub fn mov_reg64_imm32(&mut self, dest: Reg64, src: i32) {
+        self.mov_reg64_imm32_unsigned(dest, src as u32);
+    }
+
+    /// Move the bits of *imm32*, sign extended to 64 bits, to *r64*: `0x8000_0000` and above load a negative quadword, see
+    /// [`x86_64InstructionStream::mov_reg64_imm32`].
+    pub fn mov_reg64_imm32_unsigned(&mut self, dest: Reg64, src: u32) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W; // REX.W prefix
+
+        if dest.is_extension() {
+            prefix |= REX_B;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0xc7); // opcode
+        self.write_byte((0b11 << 6) | dest.offset()); // /0 MODRM encoded
+        self.write_double_word(src);
+
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move *imm64* to *r64*.
+    pub fn mov_reg64_imm64(&mut self, dest: Reg64, src: u64) {
+        let instruction = self.begin_instruction();
+
+        // REX prefix
+        let mut prefix = REX | REX_W;
+
+        if dest.is_extension() {
+            prefix |= REX_B;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0xb8 + dest.offset()); // opcode
+        self.write_quad_word(src);
+
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Push *r/m16*.
+    pub fn push_reg16(&mut self, reg16: Reg16) {
+        let instruction = self.begin_instruction();
+
+        self.write_word_operand_prefix();
+
+        if reg16.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+
+        self.write_byte(0x50 + reg16.offset()); // opcode
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(reg16));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Push *r/m64*.
+    pub fn push_reg64(&mut self, reg64: Reg64) {
+        self.require_long_mode("push");
+
+        let instruction = self.begin_instruction();
+
+        if reg64.is_extension() {
+            self.write_byte(0x41); // prefix
+        }
+        self.write_byte(0x50 + reg64.offset()); // opcode
+
+        if self.peephole_active() {
+            self.last_push = Some((instruction, self.bytes.len(), reg64));
+        }
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(reg64));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Pop top of stack into *r64*.
+    pub fn pop_reg64(&mut self, reg64: Reg64) {
+        self.require_long_mode("pop");
+
+        if let Some((start, end, pushed)) = self.last_push.take() {
+            if self.peephole_active() && end == self.bytes.len() && pushed == reg64 {
+                self.bytes.truncate(start);
+                self.peephole_counters.push_pops += 1;
+                self.forget_instruction(InstructionFamily::Push, end - start);
+                return;
+            }
+        }
+
+        let instruction = self.begin_instruction();
+
+        if reg64.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+        self.write_byte(0x58 + reg64.offset()); // opcode
+
+        self.record_registers(RegSet::of(Reg64::Rsp).with(reg64), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Pop);
+    }
+
+    /// Push *imm8*, sign extended to 64 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.push_imm8(-56);
+    /// stream.push_imm8(i8::MAX);
+    /// stream.push_imm32(-0x1000);
+    /// stream.push_imm8_unsigned(200);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x6a, 0xc8,                   // push -56
+    ///     0x6a, 0x7f,                   // push 0x7f
+    ///     0x68, 0x00, 0xf0, 0xff, 0xff, // push -0x1000
+    ///     0x6a, 0xc8,                   // push -56
+    /// ]);
+    /// ```
+    ///
+    /// `200`, which would push `-56`, doesn't compile:
+    /// ```compile_fail
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// x86_64InstructionStream::new().push_imm8(200);
+    /// ```
+    ///
+    /// A value computed at runtime is checked with [`TryFrom`] instead:
+    /// ```
+    /// let vector: u32 = 200;
+    /// assert!(i8::try_from(vector).is_err());
+    /// ```
+    pub fn push_imm8(&mut self, imm8: i8) {
+        self.push_imm8_unsigned(imm8 as u8);
+    }
+
+    /// Push the bits of *imm8*, sign extended to 64 bits: `0x80` and above push a negative quadword, see [`x86_64InstructionStream::push_imm8`].
+    pub fn push_imm8_unsigned(&mut self, imm8: u8) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x6a);
+        self.write_byte(imm8);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Push *imm16*.
+    /// 
+    /// **NOTE:** extends *imm16* into an *imm32*.
+    pub fn push_imm16(&mut self, imm16: u16) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x68);
+        self.write_double_word(imm16 as u32);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Push *imm32*, sign extended to 64 bits, see [`x86_64InstructionStream::push_imm8`].
+    pub fn push_imm32(&mut self, imm32: i32) {
+        self.push_imm32_unsigned(imm32 as u32);
+    }
+
+    /// Push the bits of *imm32*, sign extended to 64 bits: `0x8000_0000` and above push a negative quadword.
+    pub fn push_imm32_unsigned(&mut self, imm32: u32) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x68);
+        self.write_double_word(imm32);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Push FS.
+    pub fn push_fs(&mut self) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x0f);
+        self.write_byte(0xa0);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// Push GS.
+    pub fn push_gs(&mut self) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x0f);
+        self.write_byte(0xa8);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Push);
+    }
+
+    /// No operation, taking `length` bytes with as few instructions as possible.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.nop(1);
+    /// stream.nop(5);
+    /// stream.nop(11);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x90,                                                 // nop
+    ///     0x0f, 0x1f, 0x44, 0x00, 0x00,                         // nop dword ptr [rax + rax + 0]
+    ///     0x66, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00, // nop word ptr [rax + rax + 0]
+    ///     0x66, 0x90,                                           // xchg ax, ax
+    /// ]);
+    /// ```
+    pub fn nop(&mut self, mut length: usize) {
+        // each `nop` is an instruction of its own, as an instruction can't take more than 15 bytes.
+        while length > 0 {
+            let nop = length.min(self.longest_nop());
+            let instruction = self.begin_instruction();
+            self.write_nops(nop);
+            self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+            self.end_instruction(instruction, InstructionFamily::Nop);
+            length -= nop;
+        }
+    }
+
+    /// Near return to calling procedure.
+    pub fn ret_near(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xc3); // opcode
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with an offset of the default operand size: 32 bits, or 16 bits in
+    /// [real mode](crate::mode).
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.ret_far();
+    /// stream.ret_far_imm16(8);
+    /// stream.ret_far16();
+    /// stream.ret_far16_imm16(4);
+    /// stream.ret_far64();
+    /// stream.ret_far64_imm16(16);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xcb,                   // retf
+    ///     0xca, 0x08, 0x00,       // retf 8
+    ///     0x66, 0xcb,             // retfw
+    ///     0x66, 0xca, 0x04, 0x00, // retfw 4
+    ///     0x48, 0xcb,             // retfq
+    ///     0x48, 0xca, 0x10, 0x00, // retfq 16
+    /// ]);
+    /// ```
+    pub fn ret_far(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xcb); // opcode
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Near return to calling procedure and pop *imm16* bytes from stack.
+    pub fn ret_near_imm16(&mut self, imm16: u16) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xc2);
+        self.write_word(imm16);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with an offset of the default operand size, and pop *imm16* bytes from stack.
+    pub fn ret_far_imm16(&mut self, imm16: u16) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xca);
+        self.write_word(imm16);
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with a 16-bit offset.
+    pub fn ret_far16(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_byte(0xcb);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with a 16-bit offset, and pop *imm16* bytes from stack.
+    pub fn ret_far16_imm16(&mut self, imm16: u16) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_byte(0xca);
+        self.write_word(imm16);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with a 64-bit offset.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    pub fn ret_far64(&mut self) {
+        self.require_long_mode("ret far");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[REX | REX_W, 0xcb]);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Far return to calling procedure, popping a far pointer with a 64-bit offset, and pop *imm16* bytes from stack.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    pub fn ret_far64_imm16(&mut self, imm16: u16) {
+        self.require_long_mode("ret far");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[REX | REX_W, 0xca]);
+        self.write_word(imm16);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Jump near, relative to the end of the instruction, by *rel32*.
+    pub fn jmp_rel32(&mut self, rel32: i32) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xe9);
+        self.write_double_word(rel32 as u32);
+
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Jump near to `label`, with a 32-bit displacement, or a 16-bit one in [real mode](crate::mode).
+    pub fn jmp_label(&mut self, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xe9);
+        self.write_label(label, self.near_branch_fixup());
+
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Jump near to `label` if `condition` holds, with a 32-bit displacement, or a 16-bit one in [real mode](crate::mode).
+    pub fn jcc_label(&mut self, condition: Condition, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0x0f);
+        self.write_byte(0x80 | condition.bits());
+        self.write_label(label, self.near_branch_fixup());
+
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Decrement `rcx`, without changing the flags, and jump short to `label` if it isn't zero.
+    ///
+    /// The 8-bit displacement reaches 128 bytes back and 127 bytes forward of the end of the instruction; a label out of reach is reported by
+    /// [`x86_64InstructionStream::validate`] as a [`LabelDiagnostic::OutOfRange`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    ///
+    /// # Example
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// // sum the integers from 1 to `rdi`, counted down by `loop`.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let (head, exit) = (stream.create_label(), stream.create_label());
+    /// stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.mov_reg64_reg64(Reg64::Rcx, Reg64::Rdi);
+    /// stream.jrcxz_label(exit);
+    /// stream.attach_label(head);
+    /// stream.add_reg64_reg64(Reg64::Rax, Reg64::Rcx);
+    /// stream.loop_label(head);
+    /// stream.attach_label(exit);
+    /// stream.ret_near();
+    ///
+    /// let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    /// assert_eq!(&buffer.bytes()[5..12], [
+    ///     0xe3, 0x05,       // jrcxz exit
+    ///     0x48, 0x01, 0xc8, // head: add rax, rcx
+    ///     0xe2, 0xfb,       // loop head
+    /// ]);
+    ///
+    /// let sum: extern "C" fn(u64) -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    /// assert_eq!(sum(0), 0);
+    /// assert_eq!(sum(1), 1);
+    /// assert_eq!(sum(100), 5050);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// A label out of reach is reported rather than wrapped:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{diagnostics::LabelDiagnostic, stream::{DisplacementOutOfRange, x86_64InstructionStream}};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let (near, far) = (stream.create_label(), stream.create_label());
+    /// stream.name_label(far, "far");
+    /// stream.loop_label(near);
+    /// stream.loop_label(far);
+    /// stream.nop(125);
+    /// stream.attach_label(near);
+    /// stream.nop(3);
+    /// stream.attach_label(far);
+    ///
+    /// let diagnostics = stream.validate();
+    /// assert_eq!(diagnostics, [
+    ///     LabelDiagnostic::OutOfRange { label: far, name: Some("far".to_string()), error: DisplacementOutOfRange { offset: 3, displacement: 128, bits: 8 } },
+    /// ]);
+    /// assert_eq!(diagnostics[0].to_string(), "label `far` is out of reach of the 8-bit displacement at 0x3, 128 bytes away");
+    /// ```
+    ///
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let far = stream.create_label();
+    /// stream.loop_label(far);
+    /// stream.nop(128);
+    /// stream.attach_label(far);
+    /// let _ = stream.finish();
+    /// ```
+    pub fn loop_label(&mut self, label: LabelRef) {
+        self.write_short_branch(0xe2, label);
+    }
+
+    /// Decrement `rcx`, without changing the flags, and jump short to `label` if it isn't zero and `ZF` is set, see
+    /// [`x86_64InstructionStream::loop_label`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    pub fn loope_label(&mut self, label: LabelRef) {
+        self.write_short_branch(0xe1, label);
+    }
+
+    /// Decrement `rcx`, without changing the flags, and jump short to `label` if it isn't zero and `ZF` is clear, see
+    /// [`x86_64InstructionStream::loop_label`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    pub fn loopne_label(&mut self, label: LabelRef) {
+        self.write_short_branch(0xe0, label);
+    }
+
+    /// Jump short to `label` if `rcx` is zero, see [`x86_64InstructionStream::loop_label`].
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    pub fn jrcxz_label(&mut self, label: LabelRef) {
+        self.write_short_branch(0xe3, label);
+    }
+
+    /// Jump short to `label`, with an 8-bit displacement, see [`x86_64InstructionStream::loop_label`] for its reach.
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    pub fn jmp_short_label(&mut self, label: LabelRef) {
+        self.write_short_branch(0xeb, label);
+    }
+
+    /// Jump short to `label` if `condition` holds, with an 8-bit displacement, see [`x86_64InstructionStream::loop_label`] for its reach.
+    ///
+    /// # Panics
+    /// [`InstructionStream::finish`] panics if `label` is out of reach.
+    pub fn jcc_short_label(&mut self, condition: Condition, label: LabelRef) {
+        self.write_short_branch(0x70 | condition.bits(), label);
+    }
+
+    /// Writes a branch with an 8-bit displacement to `label`.
+    fn write_short_branch(&mut self, opcode: u8, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(opcode);
+        self.write_label(label, FixupKind::Rel8);
+
+        // the loops count `rcx` down, and `jrcxz` tests it.
+        match opcode {
+            0xe0..=0xe2 => self.record_registers(RegSet::of(Reg64::Rcx), RegSet::of(Reg64::Rcx)),
+            0xe3 => self.record_registers(RegSet::EMPTY, RegSet::of(Reg64::Rcx)),
+            _ => self.record_registers(RegSet::EMPTY, RegSet::EMPTY),
+        }
+
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
+
+    /// Call near to `label`, with a 32-bit displacement, or a 16-bit one in [real mode](crate::mode).
+    ///
+    /// The calls to labels and symbols, direct or through memory, are recorded as call sites of the product, see
+    /// [`Product::call_sites`](asmkit_core::Product::call_sites).
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{calls::{CallKind, CallSite, CallSiteTarget}, InstructionStream};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let puts = stream.create_symbol("puts");
+    /// let exit = stream.create_symbol("exit@got");
+    /// let helper = stream.create_label();
+    ///
+    /// stream.call_symbol(puts);
+    /// stream.call_mem_symbol(exit);
+    /// stream.call_symbol(puts);
+    /// stream.call_label(helper);
+    /// stream.attach_label(helper);
+    /// stream.ret_near();
+    /// let product = stream.finish();
+    ///
+    /// assert_eq!(product.bytes(), [
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00,       // call puts
+    ///     0xff, 0x15, 0x00, 0x00, 0x00, 0x00, // call qword ptr [rip + exit@got]
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00,       // call puts
+    ///     0xe8, 0x00, 0x00, 0x00, 0x00,       // call helper
+    ///     0xc3,                               // helper: ret
+    /// ]);
+    /// assert_eq!(product.call_sites(), [
+    ///     CallSite { offset: 0, len: 5, target: CallSiteTarget::Symbol(puts), kind: CallKind::Direct },
+    ///     CallSite { offset: 5, len: 6, target: CallSiteTarget::Symbol(exit), kind: CallKind::Indirect },
+    ///     CallSite { offset: 11, len: 5, target: CallSiteTarget::Symbol(puts), kind: CallKind::Direct },
+    ///     CallSite { offset: 16, len: 5, target: CallSiteTarget::Label(helper), kind: CallKind::Direct },
+    /// ]);
+    /// assert_eq!(product.call_sites_to("puts").map(|site| site.offset).collect::<Vec<_>>(), [0, 11]);
+    /// assert_eq!(product.call_sites_to("exit@got").count(), 1);
+    /// ```
+    pub fn call_label(&mut self, label: LabelRef) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xe8);
+        self.write_label(label, self.near_branch_fixup());
+        self.record_call_site(instruction, CallSiteTarget::Label(label), CallKind::Direct);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Call);
+    }
 
-    /// The labels written to the instruction stream.
-    labels: EntityList<Label, LabelRef>,
-}
+    /// Call near to the external `symbol`, with a 32-bit displacement left to a relocation, or resolved to the stub of the symbol under
+    /// [`StubMode::Table`](crate::stub::StubMode::Table), see [`x86_64InstructionStream::set_stub_mode`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let puts = stream.create_symbol("puts");
+    /// stream.call_symbol(puts);
+    /// let product = stream.finish();
+    ///
+    /// assert_eq!(product.bytes(), [0xe8, 0, 0, 0, 0]); // call puts
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 1, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(puts), addend: -4 },
+    /// ]);
+    /// ```
+    pub fn call_symbol(&mut self, symbol: SymRef) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xe8);
+        self.write_symbol_branch(symbol);
+        self.record_call_site(instruction, CallSiteTarget::Symbol(symbol), CallKind::Direct);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Call);
+    }
 
-impl InstructionStream for x86_64InstructionStream {
-    #[inline(always)]
-    fn create_label(&mut self) -> LabelRef {
-        self.labels.push(Label::Unattached)
+    /// Jump near to the external `symbol`, with a 32-bit displacement relocated once the symbol's address is known, or resolved to the stub of the
+    /// symbol under [`StubMode::Table`](crate::stub::StubMode::Table), see [`x86_64InstructionStream::set_stub_mode`].
+    pub fn jmp_symbol(&mut self, symbol: SymRef) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xe9);
+        self.write_symbol_branch(symbol);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
     }
 
-    #[inline(always)]
-    fn create_label_attached(&mut self) -> LabelRef {
-        self.labels.push(Label::Attached(self.labels.len()))
+    /// Call near, absolute indirect, to the address in *r64*.
+    pub fn call_reg64(&mut self, reg64: Reg64) {
+        self.require_long_mode("call");
+
+        let instruction = self.begin_instruction();
+
+        if reg64.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+
+        self.write_byte(0xff);
+        self.write_byte((0b11 << 6) | (2 << 3) | reg64.offset()); // /2 MODRM encoded
+
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(reg64));
+        self.end_instruction(instruction, InstructionFamily::Call);
     }
 
-    #[inline(always)]
-    fn attach_label(&mut self, label: LabelRef) {
-        *self.labels.get_mut(label) = Label::Attached(self.labels.len());
+    /// Jump near, absolute indirect, to the address in *r64*.
+    pub fn jmp_reg64(&mut self, reg64: Reg64) {
+        self.require_long_mode("jmp");
+
+        let instruction = self.begin_instruction();
+
+        if reg64.is_extension() {
+            self.write_byte(REX | REX_B); // extension prefix
+        }
+
+        self.write_byte(0xff);
+        self.write_byte((0b11 << 6) | (4 << 3) | reg64.offset()); // /4 MODRM encoded
+
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg64));
+        self.end_instruction(instruction, InstructionFamily::Branch);
     }
 
-    #[inline(always)]
-    fn write_byte(&mut self, byte: u8) {
-        self.bytes.push(byte);
+    /// Call near, absolute indirect, to the address in *m64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.call_mem(Mem::new(Reg64::Rax, 0x10));
+    /// stream.call_mem(Mem::new(Reg64::Rsp, 0));
+    /// stream.call_mem(Mem::new(Reg64::R13, 0));
+    /// stream.call_mem(Mem::new(Reg64::R12, 0x1000));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xff, 0x50, 0x10,                               // call qword ptr [rax + 0x10]
+    ///     0xff, 0x14, 0x24,                               // call qword ptr [rsp]
+    ///     0x41, 0xff, 0x55, 0x00,                         // call qword ptr [r13]
+    ///     0x41, 0xff, 0x94, 0x24, 0x00, 0x10, 0x00, 0x00, // call qword ptr [r12 + 0x1000]
+    /// ]);
+    /// ```
+    pub fn call_mem(&mut self, mem: Mem) {
+        self.require_long_mode("call");
+
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0xff], (2, false), mem); // /2 MODRM encoded
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::from(mem).with(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Call);
     }
 
-    #[inline(always)]
-    fn write_word(&mut self, word: u16) {
-        self.bytes.append(&mut word.to_le_bytes().to_vec());
+    /// Jump near, absolute indirect, to the address in *m64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.jmp_mem(Mem::new(Reg64::Rdi, 8));
+    /// stream.jmp_mem(Mem::new(Reg64::Rbp, -0x80));
+    /// stream.jmp_mem(Mem::new(Reg64::R11, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xff, 0x67, 0x08,       // jmp qword ptr [rdi + 8]
+    ///     0xff, 0x65, 0x80,       // jmp qword ptr [rbp - 0x80]
+    ///     0x41, 0xff, 0x23,       // jmp qword ptr [r11]
+    /// ]);
+    /// ```
+    pub fn jmp_mem(&mut self, mem: Mem) {
+        self.require_long_mode("jmp");
+
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0xff], (4, false), mem); // /4 MODRM encoded
+        self.record_registers(RegSet::EMPTY, RegSet::from(mem));
+        self.end_instruction(instruction, InstructionFamily::Branch);
     }
 
-    #[inline(always)]
-    fn write_double_word(&mut self, word: u32) {
-        self.bytes.append(&mut word.to_le_bytes().to_vec());
+    /// Call near, absolute indirect, to the address at `[base + index*8]`, the entry `index` of a table of function pointers at `base`.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`, which can't be encoded as an index register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.call_mem_scaled(Reg64::Rdi, Reg64::Rsi);
+    /// stream.call_mem_scaled(Reg64::R13, Reg64::R9);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xff, 0x14, 0xf7,             // call qword ptr [rdi + rsi*8]
+    ///     0x43, 0xff, 0x54, 0xcd, 0x00, // call qword ptr [r13 + r9*8]
+    /// ]);
+    /// ```
+    pub fn call_mem_scaled(&mut self, base: Reg64, index: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_indirect_scaled(2, base, index);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp).with(base).with(index));
+        self.end_instruction(instruction, InstructionFamily::Call);
     }
 
-    #[inline(always)]
-    fn write_quad_word(&mut self, word: u64) {
-        self.bytes.append(&mut word.to_le_bytes().to_vec());
+    /// Jump near, absolute indirect, to the address at `[base + index*8]`, the entry `index` of a table of function pointers at `base`.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`, which can't be encoded as an index register.
+    ///
+    /// # Example
+    /// Dispatching through a table of function pointers:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// extern "C" fn double(_: *const (), _: usize, x: u64) -> u64 { x * 2 }
+    /// extern "C" fn square(_: *const (), _: usize, x: u64) -> u64 { x * x }
+    ///
+    /// let table: [extern "C" fn(*const (), usize, u64) -> u64; 2] = [double, square];
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.jmp_mem_scaled(Reg64::Rdi, Reg64::Rsi);
+    ///
+    /// let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    /// let dispatch: extern "C" fn(*const (), usize, u64) -> u64 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///
+    /// assert_eq!(dispatch(table.as_ptr() as *const (), 0, 7), 14);
+    /// assert_eq!(dispatch(table.as_ptr() as *const (), 1, 7), 49);
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn jmp_mem_scaled(&mut self, base: Reg64, index: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_indirect_scaled(4, base, index);
+        self.record_registers(RegSet::EMPTY, RegSet::of(base).with(index));
+        self.end_instruction(instruction, InstructionFamily::Branch);
     }
 
-    #[inline(always)]
-    fn write_double_quad_word(&mut self, word: u128) {
-        self.bytes.append(&mut word.to_le_bytes().to_vec());
+    /// Writes the `FF /digit` indirect branch through `[base + index*8]`.
+    fn write_indirect_scaled(&mut self, digit: u8, base: Reg64, index: Reg64) {
+        assert!(index != Reg64::Rsp, "`rsp` can't be used as an index register");
+
+        let mut prefix = REX;
+
+        if index.is_extension() {
+            prefix |= REX_X;
+        }
+
+        if base.is_extension() {
+            prefix |= REX_B;
+        }
+
+        if prefix != REX {
+            self.write_byte(prefix);
+        }
+
+        self.write_byte(0xff);
+
+        // `rbp` and `r13` have no displacement-free form as a base, so they take a zero 8-bit displacement.
+        let mode = if base.offset() == 0b101 { 0b01 } else { 0b00 };
+        self.write_byte((mode << 6) | (digit << 3) | 0b100);
+        self.write_byte((0b11 << 6) | (index.offset() << 3) | base.offset()); // SIB with a scale of 8
+
+        if mode == 0b01 {
+            self.write_byte(0);
+        }
     }
 
-    #[inline(always)]
-    fn finish(self) -> Product {
-        Product::new(self.bytes)
+    /// Call near, absolute indirect, to the address stored at `label`, such as a slot of a table of function pointers laid out in the stream.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let slot = stream.create_label();
+    /// stream.call_mem_label(slot);
+    /// stream.jmp_mem_label(slot);
+    /// stream.attach_label(slot);
+    /// stream.write_quad_word(0);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xff, 0x15, 0x06, 0x00, 0x00, 0x00,             // call qword ptr [rip + slot]
+    ///     0xff, 0x25, 0x00, 0x00, 0x00, 0x00,             // jmp qword ptr [rip + slot]
+    ///     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // slot
+    /// ]);
+    /// ```
+    pub fn call_mem_label(&mut self, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xff);
+        self.write_byte((2 << 3) | 0b101); // /2 `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+
+        self.record_call_site(instruction, CallSiteTarget::Label(label), CallKind::Indirect);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Call);
     }
-}
 
-impl x86_64InstructionStream {
-    /// Creates a new, empty instruction stream.
-    #[inline(always)]
-    pub fn new() -> Self {
-        Self { bytes: Vec::new(), labels: EntityList::new() }
+    /// Jump near, absolute indirect, to the address stored at `label`, see [`x86_64InstructionStream::call_mem_label`].
+    pub fn jmp_mem_label(&mut self, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xff);
+        self.write_byte((4 << 3) | 0b101); // /4 `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
     }
 
-    /// Pushes the stack base pointer onto the stack.
-    #[inline(always)]
-    pub fn intrinsic_push_rbp(&mut self) {
-        self.push_reg64(Reg64::Rbp);
+    /// Call near, absolute indirect, to the address stored at the external `symbol`, with a 32-bit displacement left to a relocation.  This is
+    /// how code calls through a global offset table rather than a procedure linkage table.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let slot = stream.create_symbol("puts@got");
+    /// stream.call_mem_symbol(slot);
+    /// stream.jmp_mem_symbol(slot);
+    /// let product = stream.finish();
+    ///
+    /// assert_eq!(product.bytes(), [
+    ///     0xff, 0x15, 0x00, 0x00, 0x00, 0x00, // call qword ptr [rip + puts@got]
+    ///     0xff, 0x25, 0x00, 0x00, 0x00, 0x00, // jmp qword ptr [rip + puts@got]
+    /// ]);
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 2, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(slot), addend: -4 },
+    ///     Relocation { offset: 8, kind: RelocationKind::Rel32, target: RelocationTarget::Symbol(slot), addend: -4 },
+    /// ]);
+    /// ```
+    pub fn call_mem_symbol(&mut self, symbol: SymRef) {
+        let instruction = self.begin_instruction();
+
+        self.write_byte(0xff);
+        self.write_byte((2 << 3) | 0b101); // /2 `[rip + disp32]` MODRM encoded
+        self.write_symbol(symbol, RelocationKind::Rel32, -4);
+
+        self.record_call_site(instruction, CallSiteTarget::Symbol(symbol), CallKind::Indirect);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Call);
     }
 
-    /// Move *r8* to *r/m8*.
-    pub fn mov_reg8_reg8(&mut self, dest: Reg8, src: Reg8) {
-        let is_dest_extension = dest.is_extension();
-        let is_src_extension = src.is_extension();
-        let is_dest_reserved = dest.is_reserved();
-        let is_src_reserved = src.is_reserved();
-        
-        if is_dest_extension | is_src_extension | is_dest_reserved | is_src_reserved {
-            let mut prefix = REX;
+    /// Jump near, absolute indirect, to the address stored at the external `symbol`, see [`x86_64InstructionStream::call_mem_symbol`].
+    pub fn jmp_mem_symbol(&mut self, symbol: SymRef) {
+        let instruction = self.begin_instruction();
 
-            if is_dest_extension || is_dest_reserved {
-                prefix |= REX_B;
-            }
-    
-            if is_src_extension || is_src_reserved {
-                prefix |= REX_R;
-            }
+        self.write_byte(0xff);
+        self.write_byte((4 << 3) | 0b101); // /4 `[rip + disp32]` MODRM encoded
+        self.write_symbol(symbol, RelocationKind::Rel32, -4);
 
-            self.write_byte(prefix); // REX prefix
-        }
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Branch);
+    }
 
-        self.write_byte(88); // opcode
-        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset())); // value MODRM encoded
+    /// Compare *imm32*, sign extended to 64 bits, with *r64*.
+    pub fn cmp_reg64_imm32(&mut self, reg64: Reg64, imm32: i32) {
+        self.cmp_reg64_imm32_unsigned(reg64, imm32 as u32);
     }
 
-    /// Move *r16* to *r/m16*.
-    pub fn mov_reg16_reg16(&mut self, dest: Reg16, src: Reg16) {
-        self.write_byte(0x66);
+    /// Compare the bits of *imm32*, sign extended to 64 bits, with *r64*: `0x8000_0000` and above compare with a negative quadword.
+    pub fn cmp_reg64_imm32_unsigned(&mut self, reg64: Reg64, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg64_imm32(7, reg64, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg64));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-        let is_dest_extension = dest.is_extension();
-        let is_src_extension = src.is_extension();
-        if is_dest_extension || is_src_extension {
-            let mut prefix = REX;
+    /// Compare *imm32* with *r32*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.cmp_reg32_imm32(Reg32::R9d, 5);
+    /// stream.cmp_reg32_imm32(Reg32::Ecx, 0x1000);
+    /// stream.cmp_reg32_reg32(Reg32::R10d, Reg32::R11d);
+    /// stream.cmp_reg64_reg64(Reg64::Rcx, Reg64::Rdx);
+    /// stream.test_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.test_reg64_reg64(Reg64::R8, Reg64::R8);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x41, 0x83, 0xf9, 0x05,             // cmp r9d, 5
+    ///     0x81, 0xf9, 0x00, 0x10, 0x00, 0x00, // cmp ecx, 0x1000
+    ///     0x45, 0x39, 0xda,                   // cmp r10d, r11d
+    ///     0x48, 0x39, 0xd1,                   // cmp rcx, rdx
+    ///     0x85, 0xc0,                         // test eax, eax
+    ///     0x4d, 0x85, 0xc0,                   // test r8, r8
+    /// ]);
+    /// ```
+    pub fn cmp_reg32_imm32(&mut self, reg32: Reg32, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg32_imm32(7, reg32, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::EMPTY, RegSet::of(reg32));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-            if is_dest_extension {
-                prefix |= REX_B;
-            }
-    
-            if is_src_extension {
-                prefix |= REX_R;
-            }
+    /// Add *imm32* to *r32*, clearing the upper half of the 64-bit register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg32, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.add_reg32_imm32(Reg32::Eax, 1);
+    /// stream.add_reg32_imm32(Reg32::R8d, 0x1000);
+    /// stream.sub_reg32_imm32(Reg32::Ecx, 8);
+    /// stream.sub_reg32_imm32(Reg32::Edx, 0xffff_ffff);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x83, 0xc0, 0x01,                         // add eax, 1
+    ///     0x41, 0x81, 0xc0, 0x00, 0x10, 0x00, 0x00, // add r8d, 0x1000
+    ///     0x83, 0xe9, 0x08,                         // sub ecx, 8
+    ///     0x83, 0xea, 0xff,                         // sub edx, -1
+    /// ]);
+    /// ```
+    pub fn add_reg32_imm32(&mut self, dest: Reg32, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg32_imm32(0, dest, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-            self.write_byte(prefix);
-        }
+    /// Subtract *imm32* from *r32*, clearing the upper half of the 64-bit register.
+    pub fn sub_reg32_imm32(&mut self, dest: Reg32, imm32: u32) {
+        let instruction = self.begin_instruction();
+        self.write_arith_reg32_imm32(5, dest, imm32, EncodingOptions::NONE);
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-        self.write_byte(0x89);
-        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset()));
+    /// Compare *r64* with *r/m64*.
+    pub fn cmp_reg64_reg64(&mut self, lhs: Reg64, rhs: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x39, (lhs.offset(), lhs.is_extension()), (rhs.offset(), rhs.is_extension()));
+        self.record_registers(RegSet::EMPTY, RegSet::of(lhs).with(rhs));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *r32* to *r/m32*.
-    pub fn mov_reg32_reg32(&mut self, dest: Reg32, src: Reg32) {
-        let is_dest_extension = dest.is_extension();
-        let is_src_extension = src.is_extension();
-        if is_dest_extension || is_src_extension {
-            let mut prefix = REX;
+    /// Compare *r32* with *r/m32*.
+    pub fn cmp_reg32_reg32(&mut self, lhs: Reg32, rhs: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(false, 0x39, (lhs.offset(), lhs.is_extension()), (rhs.offset(), rhs.is_extension()));
+        self.record_registers(RegSet::EMPTY, RegSet::of(lhs).with(rhs));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-            if is_dest_extension {
-                prefix |= REX_B;
-            }
-    
-            if is_src_extension {
-                prefix |= REX_R;
-            }
+    /// AND *imm8* with *m8*, setting the flags and discarding the result.
+    pub fn test_mem8_imm8(&mut self, mem: Mem, imm8: u8) {
+        let instruction = self.begin_instruction();
 
-            self.write_byte(prefix);
-        }
+        self.write_rex_modrm_mem(false, &[0xf6], (0, false), mem); // /0
+        self.write_byte(imm8);
 
-        self.write_byte(0x89);
-        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset()));
+        self.record_registers(RegSet::EMPTY, RegSet::from(mem));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *r64* to *r/m64*.
-    pub fn mov_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
-        let mut prefix = REX | REX_W; // REX.W prefix
+    /// AND *r64* with *r/m64*, setting the flags and discarding the result.
+    pub fn test_reg64_reg64(&mut self, lhs: Reg64, rhs: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x85, (lhs.offset(), lhs.is_extension()), (rhs.offset(), rhs.is_extension()));
+        self.record_registers(RegSet::EMPTY, RegSet::of(lhs).with(rhs));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// AND *r32* with *r/m32*, setting the flags and discarding the result.
+    pub fn test_reg32_reg32(&mut self, lhs: Reg32, rhs: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(false, 0x85, (lhs.offset(), lhs.is_extension()), (rhs.offset(), rhs.is_extension()));
+        self.record_registers(RegSet::EMPTY, RegSet::of(lhs).with(rhs));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// AND *r8* with *r/m8*, setting the flags and discarding the result.
+    ///
+    /// # Panics
+    /// Panics if the operands can't be encoded together, see [`Reg8::check_encodable_with`].
+    pub fn test_reg8_reg8(&mut self, lhs: Reg8, rhs: Reg8) {
+        let instruction = self.begin_instruction();
+        self.write_rex_reg8(rhs, lhs);
+        self.write_byte(0x84);
+        self.write_byte((0b11 << 6) | (rhs.offset() << 3) | lhs.offset());
+        self.record_registers(RegSet::EMPTY, RegSet::of(lhs).with(rhs));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Add *r64* to *r/m64*.
+    pub fn add_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
 
         if dest.is_extension() {
             prefix |= REX_B;
@@ -164,136 +5490,522 @@ impl x86_64InstructionStream {
         }
 
         self.write_byte(prefix);
-        self.write_byte(0x89); // opcode
-        self.write_byte((0b11 << 6) | (src.offset() << 3) | (dest.offset())); // value MODRM encoded
+        self.write_byte(0x01);
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | dest.offset());
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *imm8* to *r8*.
-    pub fn mov_reg8_imm8(&mut self, dest: Reg8, src: u8) {
-        if dest.is_extension() {
-            self.write_byte(REX | REX_B);
-        } else if dest.is_reserved() {
-            self.write_byte(REX);
-        }
+    /// Add *r64* and the carry flag to *r/m64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.adc_reg64_reg64(Reg64::Rdx, Reg64::R9);
+    /// stream.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// stream.xor_reg32_reg32(Reg32::R8d, Reg32::Ecx);
+    /// stream.shl_reg64_imm8(Reg64::Rdi, 3);
+    /// stream.shl_reg64_imm8(Reg64::R11, 63);
+    /// stream.shld_reg64_reg64_imm8(Reg64::Rsi, Reg64::Rdi, 12);
+    /// stream.shld_reg64_reg64_imm8(Reg64::R10, Reg64::R12, 1);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x4c, 0x11, 0xca,             // adc rdx, r9
+    ///     0x31, 0xc0,                   // xor eax, eax
+    ///     0x41, 0x31, 0xc8,             // xor r8d, ecx
+    ///     0x48, 0xc1, 0xe7, 0x03,       // shl rdi, 3
+    ///     0x49, 0xc1, 0xe3, 0x3f,       // shl r11, 63
+    ///     0x48, 0x0f, 0xa4, 0xfe, 0x0c, // shld rsi, rdi, 12
+    ///     0x4d, 0x0f, 0xa4, 0xe2, 0x01, // shld r10, r12, 1
+    /// ]);
+    /// ```
+    pub fn adc_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x11, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
 
-        self.write_byte(0xb0 + dest.offset()); // opcode
-        self.write_byte(src);
+    /// Subtract *r64* from *r/m64*.
+    pub fn sub_reg64_reg64(&mut self, dest: Reg64, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x29, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.record_registers(RegSet::of(dest), zeroing_uses(dest, src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *imm16* to *r16*.
-    pub fn mov_reg16_imm16(&mut self, dest: Reg16, src: u16) {
-        self.write_byte(0x66); // prefix
+    /// Subtract *r32* from *r/m32*.
+    pub fn sub_reg32_reg32(&mut self, dest: Reg32, src: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(false, 0x29, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.record_registers(RegSet::of(dest), zeroing_uses(dest, src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// OR *imm8*, sign extended to 64 bits, with *m64*.
+    pub fn or_mem64_imm8(&mut self, dest: Mem, imm8: i8) {
+        self.or_mem64_imm8_unsigned(dest, imm8 as u8);
+    }
+
+    /// OR the bits of *imm8*, sign extended to 64 bits, with *m64*: `0x80` and above set the upper 56 bits.
+    pub fn or_mem64_imm8_unsigned(&mut self, dest: Mem, imm8: u8) {
+        let instruction = self.begin_instruction();
+
+        self.write_rex_modrm_mem(true, &[0x83], (1, false), dest); // /1
+        self.write_byte(imm8);
+
+        self.record_registers(RegSet::EMPTY, RegSet::from(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// XOR *r32* with *r/m32*.  `xor` of a register with itself is the usual way of zeroing it, clearing the upper half of the 64-bit register.
+    pub fn xor_reg32_reg32(&mut self, dest: Reg32, src: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(false, 0x31, (dest.offset(), dest.is_extension()), (src.offset(), src.is_extension()));
+        self.record_registers(RegSet::of(dest), zeroing_uses(dest, src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Shift *r/m64* left by *imm8*, masked to 6 bits.
+    pub fn shl_reg64_imm8(&mut self, dest: Reg64, imm8: u8) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
 
         if dest.is_extension() {
-            self.write_byte(REX | REX_B); // extension prefix
+            prefix |= REX_B;
         }
 
-        self.write_byte(0xb8 + dest.offset()); // opcode
-        self.write_word(src);
+        self.write_byte(prefix);
+        self.write_byte(0xc1);
+        self.write_byte((0b11 << 6) | (4 << 3) | dest.offset()); // /4 MODRM encoded
+        self.write_byte(imm8);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *imm32* to *r32*.
-    pub fn mov_reg32_imm32(&mut self, dest: Reg32, src: u32) {
+    /// Shift *r/m64* left by `cl`, masked to 6 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{defuse::RegSet, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.shl_reg64_cl(Reg64::Rax);
+    /// stream.shr_reg64_cl(Reg64::R9);
+    /// stream.sar_reg64_cl(Reg64::Rdx);
+    /// stream.sar_reg64_cl(Reg64::Rcx);
+    ///
+    /// // the count is an implicit use of `rcx`.
+    /// for (instruction, dest) in stream.instructions().zip([Reg64::Rax, Reg64::R9, Reg64::Rdx, Reg64::Rcx]) {
+    ///     assert_eq!(instruction.defs(), Some(RegSet::of(dest)));
+    ///     assert_eq!(instruction.uses(), Some(RegSet::of(dest).with(Reg64::Rcx)));
+    /// }
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0xd3, 0xe0, // shl rax, cl
+    ///     0x49, 0xd3, 0xe9, // shr r9, cl
+    ///     0x48, 0xd3, 0xfa, // sar rdx, cl
+    ///     0x48, 0xd3, 0xf9, // sar rcx, cl
+    /// ]);
+    /// ```
+    pub fn shl_reg64_cl(&mut self, dest: Reg64) {
+        self.write_shift_reg64_cl(4, dest);
+    }
+
+    /// Shift *r/m64* right by `cl`, masked to 6 bits, shifting in zeros.
+    pub fn shr_reg64_cl(&mut self, dest: Reg64) {
+        self.write_shift_reg64_cl(5, dest);
+    }
+
+    /// Shift *r/m64* right by `cl`, masked to 6 bits, shifting in copies of the sign bit.
+    pub fn sar_reg64_cl(&mut self, dest: Reg64) {
+        self.write_shift_reg64_cl(7, dest);
+    }
+
+    /// Writes a shift of *r/m64* by `cl`, `D3 /extension`.
+    fn write_shift_reg64_cl(&mut self, extension: u8, dest: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0xd3, (dest.offset(), dest.is_extension()), (extension, false));
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(Reg64::Rcx));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Shift *r/m64* left by *imm8*, masked to 6 bits, shifting in the high bits of *r64*.
+    pub fn shld_reg64_reg64_imm8(&mut self, dest: Reg64, src: Reg64, imm8: u8) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
+
         if dest.is_extension() {
-            self.write_byte(REX | REX_B); // extension prefix
+            prefix |= REX_B;
         }
 
-        self.write_byte(0xb8 + dest.offset()); // opcode
-        self.write_double_word(src);
+        if src.is_extension() {
+            prefix |= REX_R;
+        }
+
+        self.write_byte(prefix);
+        self.write_bytes(&[0x0f, 0xa4]);
+        self.write_byte((0b11 << 6) | (src.offset() << 3) | dest.offset());
+        self.write_byte(imm8);
+
+        self.record_registers(RegSet::of(dest), RegSet::of(dest).with(src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
     }
 
-    /// Move *imm32* to *r64*.
-    pub fn mov_reg64_imm32(&mut self, dest: Reg64, src: u32) {
-        let mut prefix = REX | REX_W; // REX.W prefix
+    /// Store the effective address of *m* in *r64*.
+    pub fn lea_reg64_mem(&mut self, dest: Reg64, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_w_modrm_mem(&[0x8d], dest, src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Lea);
+    }
+
+    /// Store the address of `label` in *r64*, through a `rip`-relative operand.
+    pub fn lea_reg64_label(&mut self, dest: Reg64, label: LabelRef) {
+        let instruction = self.begin_instruction();
+
+        let mut prefix = REX | REX_W;
 
         if dest.is_extension() {
-            prefix = prefix | REX_B;
+            prefix |= REX_R;
         }
 
         self.write_byte(prefix);
-        self.write_byte(0xc7 + dest.offset()); // opcode
-        self.write_double_word(src);
+        self.write_byte(0x8d);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+        self.write_label(label, FixupKind::Rel32);
+
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Lea);
     }
 
-    /// Move *imm64* to *r64*.
-    pub fn mov_reg64_imm64(&mut self, dest: Reg64, src: u64) {
-        // REX prefix
+    /// Move the doubleword at `[base + index*4]`, sign extended to 64 bits, to *r64*.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`, which can't be encoded as an index register.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movsxd_reg64_mem32_scaled(Reg64::Rdi, Reg64::Rax, Reg64::Rdi);
+    /// stream.movsxd_reg64_mem32_scaled(Reg64::R9, Reg64::R13, Reg64::R10);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x63, 0x3c, 0xb8,       // movsxd rdi, dword ptr [rax + rdi*4]
+    ///     0x4f, 0x63, 0x4c, 0x95, 0x00, // movsxd r9, dword ptr [r13 + r10*4]
+    /// ]);
+    /// ```
+    pub fn movsxd_reg64_mem32_scaled(&mut self, dest: Reg64, base: Reg64, index: Reg64) {
+        assert!(index != Reg64::Rsp, "`rsp` can't be used as an index register");
+
+        let instruction = self.begin_instruction();
+
         let mut prefix = REX | REX_W;
 
         if dest.is_extension() {
+            prefix |= REX_R;
+        }
+
+        if index.is_extension() {
+            prefix |= REX_X;
+        }
+
+        if base.is_extension() {
             prefix |= REX_B;
         }
 
         self.write_byte(prefix);
-        self.write_byte(0xb8 + dest.offset()); // opcode
-        self.write_quad_word(src);
+        self.write_byte(0x63);
+
+        // `rbp` and `r13` have no displacement-free form as a base, so they take a zero 8-bit displacement.
+        let mode = if base.offset() == 0b101 { 0b01 } else { 0b00 };
+        self.write_byte((mode << 6) | (dest.offset() << 3) | 0b100);
+        self.write_byte((0b10 << 6) | (index.offset() << 3) | base.offset()); // SIB with a scale of 4
+
+        if mode == 0b01 {
+            self.write_byte(0);
+        }
+
+        self.record_registers(RegSet::of(dest), RegSet::of(base).with(index));
+        self.end_instruction(instruction, InstructionFamily::Mov);
     }
 
-    /// Push *r/m16*.
-    pub fn push_reg16(&mut self, reg16: Reg16) {
-        self.write_byte(REX | REX_X); // rex prefix
-        self.write_byte(0x50 + reg16.offset()); // opcode
+    /// Move *r32*, sign extended, to *r64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movsxd_reg64_reg32(Reg64::Rax, Reg32::Eax);
+    /// stream.movsxd_reg64_reg32(Reg64::R9, Reg32::Ecx);
+    /// stream.movzx_reg32_mem8(Reg32::Eax, Mem::new(Reg64::Rsi, -1));
+    /// stream.movzx_reg32_mem8(Reg32::R11d, Mem::new(Reg64::Rdi, -1));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x63, 0xc0,             // movsxd rax, eax
+    ///     0x4c, 0x63, 0xc9,             // movsxd r9, ecx
+    ///     0x0f, 0xb6, 0x46, 0xff,       // movzx eax, byte ptr [rsi - 1]
+    ///     0x44, 0x0f, 0xb6, 0x5f, 0xff, // movzx r11d, byte ptr [rdi - 1]
+    /// ]);
+    /// ```
+    pub fn movsxd_reg64_reg32(&mut self, dest: Reg64, src: Reg32) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x63, (src.offset(), src.is_extension()), (dest.offset(), dest.is_extension()));
+        self.record_registers(RegSet::of(dest), RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
     }
 
-    /// Push *r/m64*.
-    pub fn push_reg64(&mut self, reg64: Reg64) {
-        if reg64.is_extension() {
-            self.write_byte(0x41); // prefix
-        }
-        self.write_byte(0x50 + reg64.offset()); // opcode
+    /// Move *m32*, sign extended, to *r64*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::{Mem, Scale}, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.movsxd_reg64_mem32(Reg64::Rax, Mem::new(Reg64::Rbx, 8));
+    /// stream.movsxd_reg64_mem32(Reg64::R10, Mem::indexed(Reg64::Rdi, Reg64::Rsi, Scale::X4, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x63, 0x43, 0x08,       // movsxd rax, dword ptr [rbx + 8]
+    ///     0x4c, 0x63, 0x14, 0xb7,       // movsxd r10, dword ptr [rdi + rsi*4]
+    /// ]);
+    /// ```
+    pub fn movsxd_reg64_mem32(&mut self, dest: Reg64, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(true, &[0x63], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
     }
 
-    /// Push *imm8*.
-    pub fn push_imm8(&mut self, imm8: u8) {
-        self.write_byte(0x6a);
-        self.write_byte(imm8);
+    /// Move *m8*, zero extended, to *r32*.
+    pub fn movzx_reg32_mem8(&mut self, dest: Reg32, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0x0f, 0xb6], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
     }
 
-    /// Push *imm16*.
-    /// 
-    /// **NOTE:** extends *imm16* into an *imm32*.
-    pub fn push_imm16(&mut self, imm16: u16) {
-        self.write_byte(0x68);
-        self.write_double_word(imm16 as u32);
+    /// Emits a jump table of 4-byte entries, each holding the offset of its label from the start of the table, and returns the label of the table.
+    ///
+    /// The table is aligned to 4 bytes, padding with `int3`.  It is data, so it should be placed where it isn't executed, such as after an unconditional
+    /// jump or return.  See [`x86_64InstructionStream::attach_jump_table`] to emit a table whose label was already used.
+    pub fn emit_jump_table(&mut self, entries: &[LabelRef]) -> LabelRef {
+        let table = self.create_label();
+        self.attach_jump_table(table, entries);
+        table
     }
 
-    /// Push *imm32*.
-    pub fn push_imm32(&mut self, imm32: u32) {
-        self.write_byte(0x68);
-        self.write_double_word(imm32);
+    /// Emits a jump table like [`x86_64InstructionStream::emit_jump_table`], attaching the existing label `table` to it.  Useful when the dispatch code
+    /// comes before the table.
+    pub fn attach_jump_table(&mut self, table: LabelRef, entries: &[LabelRef]) {
+        while !self.bytes.len().is_multiple_of(4) {
+            self.write_byte(0xcc); // int3
+        }
+
+        self.attach_label(table);
+
+        for entry in entries {
+            self.write_label(*entry, FixupKind::TableEntry(table));
+        }
     }
 
-    /// Push FS.
-    pub fn push_fs(&mut self) {
-        self.write_byte(0x0f);
-        self.write_byte(0xa0);
+    /// Emits a data field of `size` bytes holding the offset of `b` from `a`, `offset(b) - offset(a)`, shifted right by `shift` bits, such as the
+    /// lengths of DWARF-style tables or the scaled entries of an interpreter's dispatch table.  The field is unsigned and resolved when the stream is
+    /// finished.
+    ///
+    /// A product holds a single section, so the difference of two labels is always known once the stream is finished, and never left to a
+    /// relocation.
+    ///
+    /// # Panics
+    /// Panics if `size` is not 1, 2, 4 or 8, or if `shift` is not below 64.  Finishing the stream panics if the difference isn't a multiple of
+    /// `1 << shift`, or if the scaled difference is negative or doesn't fit in the field, see [`Fixup::difference`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let (start, end) = (stream.create_label(), stream.create_label());
+    /// stream.data_label_diff_u32(start, end);
+    /// stream.attach_label(start);
+    /// stream.write_bytes(&[0x90; 12]);
+    /// stream.attach_label(end);
+    /// stream.data_label_diff_u8(start, end);
+    /// stream.data_label_diff_u16(start, end);
+    /// stream.data_label_diff_u64(start, end);
+    /// stream.data_label_diff(start, end, 2, 2); // (end - start) / 4
+    ///
+    /// let code = stream.finish().emit();
+    /// assert_eq!(code[..4], [0x0c, 0x00, 0x00, 0x00]);
+    /// assert_eq!(code[16..], [
+    ///     0x0c,                                           // db end - start
+    ///     0x0c, 0x00,                                     // dw end - start
+    ///     0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // dq end - start
+    ///     0x03, 0x00,                                     // dw (end - start) / 4
+    /// ]);
+    /// ```
+    ///
+    /// A negative difference doesn't fit in the unsigned field:
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let (start, end) = (stream.create_label_attached(), stream.create_label());
+    /// stream.write_bytes(&[0x90; 12]);
+    /// stream.attach_label(end);
+    /// stream.data_label_diff_u32(end, start);
+    /// stream.finish();
+    /// ```
+    ///
+    /// Neither does a scaled difference with a remainder:
+    /// ```should_panic
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let (start, end) = (stream.create_label_attached(), stream.create_label());
+    /// stream.write_bytes(&[0x90; 10]);
+    /// stream.attach_label(end);
+    /// stream.data_label_diff(start, end, 4, 2);
+    /// stream.finish();
+    /// ```
+    pub fn data_label_diff(&mut self, a: LabelRef, b: LabelRef, size: usize, shift: u32) {
+        assert!(matches!(size, 1 | 2 | 4 | 8), "label differences are 1, 2, 4 or 8 bytes wide, not {}", size);
+        assert!(shift < 64, "label differences can't be shifted by {} bits", shift);
+
+        self.write_label(b, FixupKind::LabelDiff { from: a, size, shift });
     }
 
-    /// Push GS.
-    pub fn push_gs(&mut self) {
-        self.write_byte(0x0f);
-        self.write_byte(0xa8);
+    /// Emits a 1-byte data field holding `offset(b) - offset(a)`, see [`x86_64InstructionStream::data_label_diff`].
+    pub fn data_label_diff_u8(&mut self, a: LabelRef, b: LabelRef) {
+        self.data_label_diff(a, b, 1, 0);
     }
 
-    /// Near return to calling procedure.
-    pub fn ret_near(&mut self) {
-        self.write_byte(0xc3); // opcode
+    /// Emits a 2-byte data field holding `offset(b) - offset(a)`, see [`x86_64InstructionStream::data_label_diff`].
+    pub fn data_label_diff_u16(&mut self, a: LabelRef, b: LabelRef) {
+        self.data_label_diff(a, b, 2, 0);
     }
 
-    /// Far return to calling procedure.
-    pub fn ret_far(&mut self) {
-        self.write_byte(0xcb); // opcode
+    /// Emits a 4-byte data field holding `offset(b) - offset(a)`, see [`x86_64InstructionStream::data_label_diff`].
+    pub fn data_label_diff_u32(&mut self, a: LabelRef, b: LabelRef) {
+        self.data_label_diff(a, b, 4, 0);
     }
 
-    /// Far return to calling procedure and pop *imm16* bytes from stack.
-    pub fn ret_near_imm16(&mut self, imm16: u16) {
-        self.write_byte(0xc2);
-        self.write_word(imm16);
+    /// Emits an 8-byte data field holding `offset(b) - offset(a)`, see [`x86_64InstructionStream::data_label_diff`].
+    pub fn data_label_diff_u64(&mut self, a: LabelRef, b: LabelRef) {
+        self.data_label_diff(a, b, 8, 0);
     }
 
-    /// Far return to calling procedure and pop *imm16* bytes from stack.
-    pub fn ret_far_imm16(&mut self, imm16: u16) {
-        self.write_byte(0xca);
-        self.write_word(imm16);
+    /// Jumps to the entry `index` of the jump table `table`, emitted by [`x86_64InstructionStream::emit_jump_table`].
+    ///
+    /// Emits `lea scratch, [rip + table]; movsxd index, dword ptr [scratch + index*4]; add scratch, index; jmp scratch`, clobbering both `index` and
+    /// `scratch`.  If `bounds` is given as `(len, default)`, an `index` which is not below `len`, as an unsigned value, jumps to `default` instead of reading
+    /// past the table.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let table = stream.create_label();
+    /// let default = stream.create_label();
+    /// let cases = [(); 4].map(|_| stream.create_label());
+    ///
+    /// stream.switch_via_table(Reg64::Rdi, table, Reg64::Rax, Some((4, default)));
+    /// for (value, case) in cases.iter().enumerate() {
+    ///     stream.attach_label(*case);
+    ///     stream.mov_reg32_imm32(Reg32::Eax, value as u32 * 10);
+    ///     stream.ret_near();
+    /// }
+    /// stream.attach_label(default);
+    /// stream.mov_reg64_imm32(Reg64::Rax, -1);
+    /// stream.ret_near();
+    /// stream.attach_jump_table(table, &cases);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x83, 0xff, 0x04,                   // 0x00: cmp rdi, 4
+    ///     0x0f, 0x83, 0x28, 0x00, 0x00, 0x00,       // 0x04: jae default
+    ///     0x48, 0x8d, 0x05, 0x2b, 0x00, 0x00, 0x00, // 0x0a: lea rax, [rip + table]
+    ///     0x48, 0x63, 0x3c, 0xb8,                   // 0x11: movsxd rdi, dword ptr [rax + rdi*4]
+    ///     0x48, 0x01, 0xf8,                         // 0x15: add rax, rdi
+    ///     0xff, 0xe0,                               // 0x18: jmp rax
+    ///     0xb8, 0x00, 0x00, 0x00, 0x00, 0xc3,       // 0x1a: case 0
+    ///     0xb8, 0x0a, 0x00, 0x00, 0x00, 0xc3,       // 0x20: case 1
+    ///     0xb8, 0x14, 0x00, 0x00, 0x00, 0xc3,       // 0x26: case 2
+    ///     0xb8, 0x1e, 0x00, 0x00, 0x00, 0xc3,       // 0x2c: case 3
+    ///     0x48, 0xc7, 0xc0, 0xff, 0xff, 0xff, 0xff, // 0x32: default
+    ///     0xc3,
+    ///     0xcc, 0xcc,                               // 0x3a: padding
+    ///     0xde, 0xff, 0xff, 0xff,                   // 0x3c: table, case 0 - table
+    ///     0xe4, 0xff, 0xff, 0xff,                   //        case 1 - table
+    ///     0xea, 0xff, 0xff, 0xff,                   //        case 2 - table
+    ///     0xf0, 0xff, 0xff, 0xff,                   //        case 3 - table
+    /// ]);
+    /// ```
+    pub fn switch_via_table(&mut self, index: Reg64, table: LabelRef, scratch: Reg64, bounds: Option<(u32, LabelRef)>) {
+        if let Some((len, default)) = bounds {
+            self.cmp_reg64_imm32_unsigned(index, len);
+            self.jcc_label(Condition::Ae, default);
+        }
+
+        self.lea_reg64_label(scratch, table);
+        self.movsxd_reg64_mem32_scaled(index, scratch, index);
+        self.add_reg64_reg64(scratch, index);
+        self.jmp_reg64(scratch);
+    }
+
+    /// Jumps to the entry `index` of the jump table `table` as [`x86_64InstructionStream::switch_via_table`] does, through a scratch register
+    /// acquired from `pool`.
+    ///
+    /// # Panics
+    /// Panics if `index` is `rsp`, or if every candidate of `pool` is either acquired or `index`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, scratch::ScratchPool, stream::x86_64InstructionStream};
+    ///
+    /// let pool = ScratchPool::new(&[Reg64::Rdi, Reg64::R10]);
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let table = stream.create_label();
+    /// let case = stream.create_label_attached();
+    ///
+    /// stream.switch_via_table_with_pool(Reg64::Rdi, table, &pool, None);
+    /// stream.attach_jump_table(table, &[case]);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x4c, 0x8d, 0x15, 0x0d, 0x00, 0x00, 0x00, // 0x00: lea r10, [rip + table]
+    ///     0x49, 0x63, 0x3c, 0xba,                   // 0x07: movsxd rdi, dword ptr [r10 + rdi*4]
+    ///     0x49, 0x01, 0xfa,                         // 0x0b: add r10, rdi
+    ///     0x41, 0xff, 0xe2,                         // 0x0e: jmp r10
+    ///     0xcc, 0xcc, 0xcc,                         // 0x11: padding
+    ///     0xec, 0xff, 0xff, 0xff,                   // 0x14: table, case - table
+    /// ]);
+    /// ```
+    pub fn switch_via_table_with_pool(&mut self, index: Reg64, table: LabelRef, pool: &ScratchPool, bounds: Option<(u32, LabelRef)>) {
+        let scratch = pool.acquire(&[index]).expect("no scratch register is free for `switch_via_table`");
+        self.switch_via_table(index, table, *scratch, bounds);
     }
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+//! Call stubs, reaching the external symbols past the ±2 GiB of a direct `call` or `jmp`, see the [`stub`](asmkit_core::stub) module of
+//! `asmkit-core`.
+//!
+//! Depending on the [`StubMode`] set by [`set_stub_mode`](x86_64InstructionStream::set_stub_mode), [`call_symbol`](x86_64InstructionStream::call_symbol)
+//! and [`jmp_symbol`](x86_64InstructionStream::jmp_symbol) get a stub for their symbol.  The stubs are shared by all the branches to the same
+//! symbol, and laid out when the stream is finished, after the cold text and before the constant pool:
+//!
+//! ```text
+//! movabs r11, symbol ; 49 bb imm64, relocated against the symbol
+//! jmp r11            ; 41 ff e3
+//! ```
+//!
+//! `r11` is scratch across calls under both calling conventions.  The stubs are [`STUB_SIZE`] bytes apart, each placed so its 64-bit address is
+//! aligned to 8 bytes and can be rewritten atomically while the code runs.
+//!
+//! # Example
+//! Three calls to two symbols go through a table of two stubs:
+//! ```
+//! use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+//! use asmkit_x86_64::{stream::{Fill, x86_64InstructionStream}, stub::StubMode};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.set_stub_mode(StubMode::Table);
+//! stream.set_align_fill(Fill::Int3);
+//!
+//! let puts = stream.create_symbol("puts");
+//! let exit = stream.create_symbol("exit");
+//! stream.call_symbol(puts);
+//! stream.call_symbol(puts);
+//! stream.jmp_symbol(exit);
+//! let product = stream.finish();
+//!
+//! assert_eq!(product.bytes(), [
+//!     0xe8, 0x11, 0x00, 0x00, 0x00,                               // call puts@stub
+//!     0xe8, 0x0c, 0x00, 0x00, 0x00,                               // call puts@stub
+//!     0xe9, 0x17, 0x00, 0x00, 0x00,                               // jmp exit@stub
+//!     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+//!     0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // puts@stub: movabs r11, puts
+//!     0x41, 0xff, 0xe3,                                           // jmp r11
+//!     0xcc, 0xcc, 0xcc,
+//!     0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit@stub: movabs r11, exit
+//!     0x41, 0xff, 0xe3,                                           // jmp r11
+//! ]);
+//!
+//! // the stubs hold the only relocations, one per symbol.
+//! assert_eq!(product.relocations(), [
+//!     Relocation { offset: 24, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(puts), addend: 0 },
+//!     Relocation { offset: 40, kind: RelocationKind::Abs64, target: RelocationTarget::Symbol(exit), addend: 0 },
+//! ]);
+//! assert_eq!(product.stub("puts"), Some(22));
+//! assert_eq!(product.stub("exit"), Some(38));
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, entity::{LabelRef, SymRef}, reloc::RelocationKind, stub::CallStub, InstructionStream};
+
+use crate::{mode::Mode, stream::{x86_64InstructionStream, Fill}};
+
+/// The distance between the stubs, in bytes.
+pub const STUB_SIZE: usize = 16;
+
+/// The offset of a stub from the start of its [`STUB_SIZE`] bytes, aligning its 64-bit address.
+const STUB_OFFSET: usize = 6;
+
+/// How the direct branches to external symbols reach them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StubMode {
+    /// The branches reach the symbols directly, and relocating a displacement out of reach panics.
+    #[default]
+    Direct,
+
+    /// The branches reach the symbols directly, and a stub is laid out for each symbol as a veneer, which the branches are retargeted to when
+    /// their displacement doesn't fit.
+    Veneer,
+
+    /// The branches always go through the stub of their symbol, laid out as a table holding the address of each symbol in one place.
+    Table,
+}
+
+/// The stubs of an instruction stream.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stubs {
+    /// How the branches to external symbols reach them.
+    pub(crate) mode: StubMode,
+
+    /// The symbols with a stub and the labels of their stubs, in the order they were first branched to.
+    pub(crate) labels: Vec<(SymRef, LabelRef)>,
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Sets how the direct branches emitted from now on reach the external symbols, [`StubMode::Direct`] by default.
+    ///
+    /// # Panics
+    /// Panics if `mode` lays stubs out in [protected mode](crate::mode), where a direct branch reaches the whole address space.
+    ///
+    /// # Example
+    /// A call to a symbol past 2 GiB goes through the veneer of the symbol:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{stream::x86_64InstructionStream, stub::StubMode};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.set_stub_mode(StubMode::Veneer);
+    ///
+    /// let puts = stream.create_symbol("puts");
+    /// stream.call_symbol(puts);
+    /// stream.ret_near();
+    /// let product = stream.finish();
+    /// assert_eq!(product.stub("puts"), Some(6));
+    ///
+    /// // within reach, the call is direct.
+    /// let bytes = product.clone().relocate(0x1000, |_| 0x2000);
+    /// assert_eq!(bytes[..6], [0xe8, 0xfb, 0x0f, 0x00, 0x00, 0xc3]); // call puts; ret
+    ///
+    /// // out of reach, the call goes through the veneer.
+    /// let bytes = product.relocate(0x1000, |_| 0x7fff_0000_0000);
+    /// assert_eq!(bytes, [
+    ///     0xe8, 0x01, 0x00, 0x00, 0x00,                               // call puts@stub
+    ///     0xc3,                                                       // ret
+    ///     0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0xff, 0x7f, 0x00, 0x00, // puts@stub: movabs r11, 0x7fff00000000
+    ///     0x41, 0xff, 0xe3,                                           // jmp r11
+    /// ]);
+    /// ```
+    pub fn set_stub_mode(&mut self, mode: StubMode) {
+        assert!(mode == StubMode::Direct || self.mode() == Mode::Long, "call stubs are unavailable in protected mode");
+        self.stubs().mode = mode;
+    }
+
+    /// How the direct branches reach the external symbols.
+    pub fn stub_mode(&self) -> StubMode {
+        self.stubs_ref().mode
+    }
+
+    /// Returns the label of the stub `symbol` is branched to through, creating the stub if the symbol has none yet, or `None` if the branch
+    /// reaches the symbol directly.
+    pub(crate) fn stub_target(&mut self, symbol: SymRef) -> Option<LabelRef> {
+        let mode = self.stubs().mode;
+        if mode == StubMode::Direct {
+            return None;
+        }
+
+        let label = match self.stubs().labels.iter().find(|(other, _)| *other == symbol) {
+            Some(&(_, label)) => label,
+            None => {
+                let label = self.create_label();
+                self.stubs().labels.push((symbol, label));
+                label
+            },
+        };
+
+        (mode == StubMode::Table).then_some(label)
+    }
+
+    /// Lays the stubs out at the end of the code, and returns them.
+    pub(crate) fn lay_out_stubs(&mut self) -> Vec<CallStub> {
+        let labels = std::mem::take(&mut self.stubs().labels);
+        let mut stubs = Vec::with_capacity(labels.len());
+
+        for (symbol, label) in labels {
+            let padding = (STUB_OFFSET.wrapping_sub(self.code().len())) & (STUB_SIZE - 1);
+            match self.align_fill() {
+                Fill::Nop => self.write_nops(padding),
+                Fill::Int3 => self.write_bytes(&vec![0xcc; padding]),
+                Fill::Zero => self.write_bytes(&vec![0; padding]),
+            }
+
+            stubs.push(CallStub { symbol, offset: self.code().len() });
+            self.attach_label(label);
+            self.write_bytes(&[0x49, 0xbb]);
+            self.write_symbol(symbol, RelocationKind::Abs64, 0);
+            self.write_bytes(&[0x41, 0xff, 0xe3]);
+        }
+
+        stubs
+    }
+}
@@ -0,0 +1,547 @@
+//! System instructions, and the intrinsics of interrupt service routines.
+//!
+//! On an interrupt, the CPU aligns `rsp` to 16 bytes, pushes the interrupt frame (`ss`, `rsp`, `rflags`, `cs` and `rip`, 5 quadwords) and, for some
+//! exceptions, an error code, then jumps to the handler of the vector.  The handler returns with `iretq`, once the error code is popped.
+//!
+//! The usual layout routes every vector through a stub, generated by [`x86_64InstructionStream::isr_stub`], which evens out the stack by pushing a
+//! zero error code for the vectors without one, pushes the vector number, and jumps to a common handler.  The common handler is then wrapped by
+//! [`x86_64InstructionStream::intrinsic_isr_prologue`] and [`x86_64InstructionStream::intrinsic_isr_epilogue`] with [`InterruptEntry::Stub`].
+
+use asmkit_core::{fixed::CodeStorage, entity::SymRef, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::{Reg16, Reg32, Reg64, SegmentReg},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, Condition, REX, REX_B, REX_W},
+};
+
+/// The general purpose registers saved by [`x86_64InstructionStream::intrinsic_isr_prologue`], in the order they are pushed.
+const ISR_SAVED: [Reg64; 15] = [
+    Reg64::Rax, Reg64::Rbx, Reg64::Rcx, Reg64::Rdx, Reg64::Rsi, Reg64::Rdi, Reg64::Rbp,
+    Reg64::R8, Reg64::R9, Reg64::R10, Reg64::R11, Reg64::R12, Reg64::R13, Reg64::R14, Reg64::R15,
+];
+
+/// Returns true if the CPU pushes an error code when raising the exception `vector`.
+///
+/// # Example
+/// ```
+/// use asmkit_x86_64::system::pushes_error_code;
+///
+/// assert!(pushes_error_code(14)); // #PF
+/// assert!(!pushes_error_code(3)); // #BP
+/// assert!(!pushes_error_code(32));
+/// ```
+pub fn pushes_error_code(vector: u8) -> bool {
+    matches!(vector, 8 | 10..=14 | 17 | 21 | 29 | 30)
+}
+
+/// What lies on top of the interrupt frame when an interrupt service routine is entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterruptEntry {
+    /// Nothing, the handler of a vector without an error code.
+    NoErrorCode,
+
+    /// The error code pushed by the CPU.
+    ErrorCode,
+
+    /// The error code and the vector number, pushed by an [`x86_64InstructionStream::isr_stub`].
+    Stub,
+}
+
+impl InterruptEntry {
+    /// The number of bytes on top of the interrupt frame.
+    fn size(&self) -> i32 {
+        match self {
+            Self::NoErrorCode => 0,
+            Self::ErrorCode => 8,
+            Self::Stub => 16,
+        }
+    }
+
+    /// The number of bytes padding the saved registers, so `rsp` is 16-byte aligned once they are pushed.
+    fn padding(&self) -> i32 {
+        let pushed = 5 * 8 + self.size() + 8 * ISR_SAVED.len() as i32;
+        pushed % 16
+    }
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Swap the `gs` base register with the kernel `gs` base.
+    pub fn swapgs(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xf8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Clear the direction flag.
+    pub fn cld(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xfc);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Raise the breakpoint exception.
+    pub fn int3(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xcc);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Raise the software interrupt *imm8*, such as the BIOS services of real mode code.
+    pub fn int_imm8(&mut self, imm8: u8) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0xcd, imm8]);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Halt the processor until the next interrupt.  Privileged.
+    pub fn hlt(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xf4);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Clear the interrupt flag, masking the maskable interrupts.
+    pub fn cli(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xfa);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Set the interrupt flag, unmasking the maskable interrupts after the next instruction.
+    pub fn sti(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xfb);
+        self.record_registers(RegSet::EMPTY, RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Return the processor identification and feature information of the leaf `eax` and the subleaf `ecx` in `eax`, `ebx`, `ecx` and `edx`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{defuse::RegSet, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.mov_reg32_imm32(Reg32::Eax, 7);
+    /// stream.xor_reg32_reg32(Reg32::Ecx, Reg32::Ecx);
+    /// stream.cpuid();
+    ///
+    /// let cpuid = stream.instructions().last().unwrap();
+    /// assert_eq!(cpuid.defs(), Some(RegSet::of(Reg64::Rax).with(Reg64::Rbx).with(Reg64::Rcx).with(Reg64::Rdx)));
+    /// assert_eq!(cpuid.uses(), Some(RegSet::of(Reg64::Rax).with(Reg64::Rcx)));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xb8, 0x07, 0x00, 0x00, 0x00, // mov eax, 7
+    ///     0x31, 0xc9,                   // xor ecx, ecx
+    ///     0x0f, 0xa2,                   // cpuid
+    /// ]);
+    /// ```
+    pub fn cpuid(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0xa2]);
+
+        // the 32-bit results zero extend into the 64-bit registers.
+        let results = RegSet::of(Reg64::Rax).with(Reg64::Rbx).with(Reg64::Rcx).with(Reg64::Rdx);
+        self.record_registers(results, RegSet::of(Reg64::Rax).with(Reg64::Rcx));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Call the operating system kernel: the CPU saves the return address in `rcx` and `rflags` in `r11`, and the kernel returns with `sysret`.
+    ///
+    /// The registers are recorded as the Linux convention has them, see the [`defuse`](crate::defuse) module: the system call number in `rax`
+    /// and its arguments in `rdi`, `rsi`, `rdx`, `r10`, `r8` and `r9` are used, and the result in `rax`, `rcx` and `r11` are defined.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{defuse::RegSet, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// // exit_group(0)
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.mov_reg32_imm32(Reg32::Eax, 231);
+    /// stream.xor_reg32_reg32(Reg32::Edi, Reg32::Edi);
+    /// stream.syscall();
+    ///
+    /// let syscall = stream.instructions().last().unwrap();
+    /// assert_eq!(syscall.defs(), Some(RegSet::of(Reg64::Rax).with(Reg64::Rcx).with(Reg64::R11)));
+    ///
+    /// let arguments = [Reg64::Rdi, Reg64::Rsi, Reg64::Rdx, Reg64::R10, Reg64::R8, Reg64::R9];
+    /// assert_eq!(syscall.uses(), Some(arguments.into_iter().collect::<RegSet>().with(Reg64::Rax)));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xb8, 0xe7, 0x00, 0x00, 0x00, // mov eax, 231
+    ///     0x31, 0xff,                   // xor edi, edi
+    ///     0x0f, 0x05,                   // syscall
+    /// ]);
+    /// ```
+    pub fn syscall(&mut self) {
+        self.require_long_mode("syscall");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x05]);
+
+        let arguments = RegSet::of(Reg64::Rdi).with(Reg64::Rsi).with(Reg64::Rdx).with(Reg64::R10).with(Reg64::R8).with(Reg64::R9);
+        self.record_registers(RegSet::of(Reg64::Rax).with(Reg64::Rcx).with(Reg64::R11), arguments.with(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Write the value of *r64* to the Intel processor trace, as a `PTW` packet.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.ptwrite_reg64(Reg64::Rax);
+    /// stream.ptwrite_reg64(Reg64::R9);
+    /// stream.ptwrite_mem64(Mem::new(Reg64::Rdi, 8));
+    /// stream.ptwrite_mem64(Mem::new(Reg64::R12, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf3, 0x48, 0x0f, 0xae, 0xe0,       // ptwrite rax
+    ///     0xf3, 0x49, 0x0f, 0xae, 0xe1,       // ptwrite r9
+    ///     0xf3, 0x48, 0x0f, 0xae, 0x67, 0x08, // ptwrite qword ptr [rdi + 8]
+    ///     0xf3, 0x49, 0x0f, 0xae, 0x24, 0x24, // ptwrite qword ptr [r12]
+    /// ]);
+    /// ```
+    pub fn ptwrite_reg64(&mut self, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xf3);
+        self.write_byte(REX | REX_W | if src.is_extension() { REX_B } else { 0 });
+        self.write_bytes(&[0x0f, 0xae, (0b11 << 6) | (4 << 3) | src.offset()]);
+        self.record_registers(RegSet::EMPTY, RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Write the quadword at *m64* to the Intel processor trace, as a `PTW` packet.
+    pub fn ptwrite_mem64(&mut self, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_byte(0xf3);
+        self.write_rex_modrm_mem(true, &[0x0f, 0xae], (4, false), src);
+        self.record_registers(RegSet::EMPTY, RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Move the segment selector in *sreg* to *r16*.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg16, Reg64, SegmentReg}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mov_reg16_sreg(Reg16::Ax, SegmentReg::Fs);
+    /// stream.mov_reg16_sreg(Reg16::R9w, SegmentReg::Es);
+    /// stream.mov_reg64_sreg(Reg64::R9, SegmentReg::Gs);
+    /// stream.mov_sreg_reg16(SegmentReg::Gs, Reg16::Cx);
+    /// stream.mov_sreg_reg16(SegmentReg::Ss, Reg16::R10w);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0x8c, 0xe0,       // mov ax, fs
+    ///     0x66, 0x41, 0x8c, 0xc1, // mov r9w, es
+    ///     0x49, 0x8c, 0xe9,       // mov r9, gs
+    ///     0x66, 0x8e, 0xe9,       // mov gs, cx
+    ///     0x66, 0x41, 0x8e, 0xd2, // mov ss, r10w
+    /// ]);
+    /// ```
+    pub fn mov_reg16_sreg(&mut self, dest: Reg16, src: SegmentReg) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_reg_reg(false, 0x8c, (dest.offset(), dest.is_extension()), (src.offset(), false));
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Move the segment selector in *sreg*, zero extended, to *r64*.
+    pub fn mov_reg64_sreg(&mut self, dest: Reg64, src: SegmentReg) {
+        let instruction = self.begin_instruction();
+        self.write_reg_reg(true, 0x8c, (dest.offset(), dest.is_extension()), (src.offset(), false));
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Load the segment selector in *r16* into *sreg*.
+    ///
+    /// # Panics
+    /// Panics if `dest` is [`SegmentReg::Cs`], see [`SegmentReg::check_writable`].
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::{register::{Reg16, SegmentReg}, stream::x86_64InstructionStream};
+    ///
+    /// x86_64InstructionStream::new().mov_sreg_reg16(SegmentReg::Cs, Reg16::Ax);
+    /// ```
+    pub fn mov_sreg_reg16(&mut self, dest: SegmentReg, src: Reg16) {
+        if let Err(error) = dest.check_writable() {
+            panic!("{}", error);
+        }
+
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_reg_reg(false, 0x8e, (src.offset(), src.is_extension()), (dest.offset(), false));
+        self.record_registers(RegSet::EMPTY, RegSet::of(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Load the far pointer at *m16:32* into `fs` and *r32*.
+    ///
+    /// Only the 32-bit offset form is emitted: AMD processors ignore `REX.W` and never load a 64-bit offset.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{memory::Mem, register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.lfs_reg32_mem(Reg32::Eax, Mem::new(Reg64::Rdi, 0));
+    /// stream.lgs_reg32_mem(Reg32::Ecx, Mem::new(Reg64::Rdi, 8));
+    /// stream.lss_reg32_mem(Reg32::R12d, Mem::new(Reg64::Rbx, 0));
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x0f, 0xb4, 0x07,       // lfs eax, [rdi]
+    ///     0x0f, 0xb5, 0x4f, 0x08, // lgs ecx, [rdi + 8]
+    ///     0x44, 0x0f, 0xb2, 0x23, // lss r12d, [rbx]
+    /// ]);
+    /// ```
+    pub fn lfs_reg32_mem(&mut self, dest: Reg32, src: Mem) {
+        self.write_far_pointer_load(0xb4, dest, src);
+    }
+
+    /// Load the far pointer at *m16:32* into `gs` and *r32*, see [`x86_64InstructionStream::lfs_reg32_mem`].
+    pub fn lgs_reg32_mem(&mut self, dest: Reg32, src: Mem) {
+        self.write_far_pointer_load(0xb5, dest, src);
+    }
+
+    /// Load the far pointer at *m16:32* into `ss` and *r32*, see [`x86_64InstructionStream::lfs_reg32_mem`].
+    pub fn lss_reg32_mem(&mut self, dest: Reg32, src: Mem) {
+        self.write_far_pointer_load(0xb2, dest, src);
+    }
+
+    /// Writes a far pointer load, `0F opcode /r`.
+    fn write_far_pointer_load(&mut self, opcode: u8, dest: Reg32, src: Mem) {
+        let instruction = self.begin_instruction();
+        self.write_rex_modrm_mem(false, &[0x0f, opcode], (dest.offset(), dest.is_extension()), src);
+        self.record_registers(RegSet::of(dest), RegSet::from(src));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+
+    /// Return from an interrupt, popping the 16-bit interrupt frame of real mode: `ip`, `cs` and `flags`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{features::CpuFeatures, mode::Mode, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.iret();
+    /// stream.iretd();
+    /// stream.iretq();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x66, 0xcf, // iret
+    ///     0xcf,       // iretd
+    ///     0x48, 0xcf, // iretq
+    /// ]);
+    ///
+    /// // the 16-bit operands are the default in real mode.
+    /// let mut stream = x86_64InstructionStream::new_with_mode(Mode::Real, CpuFeatures::ALL);
+    /// stream.iret();
+    /// stream.iretd();
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xcf,       // iret
+    ///     0x66, 0xcf, // iretd
+    /// ]);
+    /// ```
+    pub fn iret(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_word_operand_prefix();
+        self.write_byte(0xcf);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Return from an interrupt, popping the 32-bit interrupt frame of protected mode.
+    pub fn iretd(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_dword_operand_prefix();
+        self.write_byte(0xcf);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Return from an interrupt, popping the 64-bit interrupt frame.
+    ///
+    /// # Panics
+    /// Panics outside long mode.
+    pub fn iretq(&mut self) {
+        self.require_long_mode("iret");
+
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x48, 0xcf]);
+        self.record_registers(RegSet::of(Reg64::Rsp), RegSet::of(Reg64::Rsp));
+        self.end_instruction(instruction, InstructionFamily::Ret);
+    }
+
+    /// Executes `swapgs` if the interrupted code ran in user mode, as told by the privilege level of the `cs` saved at `[rsp + cs]`.
+    fn swapgs_from_user(&mut self, cs: i32) {
+        let kernel = self.create_label();
+
+        self.test_mem8_imm8(Mem::new(Reg64::Rsp, cs), 0b11);
+        self.jcc_label(Condition::E, kernel);
+        self.swapgs();
+        self.attach_label(kernel);
+    }
+
+    /// Enters an interrupt service routine: executes `swapgs` if `swapgs` is set and the interrupt came from user mode, pushes every general
+    /// purpose register (`rax`, `rbx`, `rcx`, `rdx`, `rsi`, `rdi`, `rbp`, then `r8` to `r15`), pads the stack to 16 bytes, and clears the direction
+    /// flag as the System V ABI expects.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{stream::x86_64InstructionStream, system::InterruptEntry};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_isr_prologue(InterruptEntry::Stub, true);
+    /// stream.intrinsic_isr_epilogue(InterruptEntry::Stub, true);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0xf6, 0x44, 0x24, 0x18, 0x03,       // test byte ptr [rsp + 24], 3
+    ///     0x0f, 0x84, 0x03, 0x00, 0x00, 0x00, // je kernel
+    ///     0x0f, 0x01, 0xf8,                   // swapgs
+    ///     0x50, 0x53, 0x51, 0x52,             // kernel: push rax, rbx, rcx, rdx
+    ///     0x56, 0x57, 0x55,                   // push rsi, rdi, rbp
+    ///     0x41, 0x50, 0x41, 0x51,             // push r8, r9
+    ///     0x41, 0x52, 0x41, 0x53,             // push r10, r11
+    ///     0x41, 0x54, 0x41, 0x55,             // push r12, r13
+    ///     0x41, 0x56, 0x41, 0x57,             // push r14, r15
+    ///     0xfc,                               // cld
+    ///     0x41, 0x5f, 0x41, 0x5e,             // pop r15, r14
+    ///     0x41, 0x5d, 0x41, 0x5c,             // pop r13, r12
+    ///     0x41, 0x5b, 0x41, 0x5a,             // pop r11, r10
+    ///     0x41, 0x59, 0x41, 0x58,             // pop r9, r8
+    ///     0x5d, 0x5f, 0x5e,                   // pop rbp, rdi, rsi
+    ///     0x5a, 0x59, 0x5b, 0x58,             // pop rdx, rcx, rbx, rax
+    ///     0x48, 0x83, 0xc4, 0x10,             // add rsp, 16
+    ///     0xf6, 0x44, 0x24, 0x08, 0x03,       // test byte ptr [rsp + 8], 3
+    ///     0x0f, 0x84, 0x03, 0x00, 0x00, 0x00, // je kernel
+    ///     0x0f, 0x01, 0xf8,                   // swapgs
+    ///     0x48, 0xcf,                         // kernel: iretq
+    /// ]);
+    ///
+    /// // with the error code of the CPU, the registers are padded to keep the stack aligned.
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_isr_prologue(InterruptEntry::ErrorCode, false);
+    /// stream.intrinsic_isr_epilogue(InterruptEntry::ErrorCode, false);
+    /// let code = stream.finish().emit();
+    ///
+    /// assert_eq!(code[23..28], [0x48, 0x83, 0xec, 0x08, 0xfc]); // sub rsp, 8; cld
+    /// assert_eq!(code[28..32], [0x48, 0x83, 0xc4, 0x08]);       // add rsp, 8
+    /// assert_eq!(code[55..], [0x48, 0x83, 0xc4, 0x08, 0x48, 0xcf]); // add rsp, 8; iretq
+    /// ```
+    pub fn intrinsic_isr_prologue(&mut self, entry: InterruptEntry, swapgs: bool) {
+        self.begin_atomic_region();
+        if swapgs {
+            self.swapgs_from_user(entry.size() + 8);
+        }
+
+        for reg in ISR_SAVED {
+            self.push_reg64(reg);
+        }
+
+        if entry.padding() != 0 {
+            self.sub_reg64_imm32(Reg64::Rsp, entry.padding());
+        }
+
+        self.cld();
+        self.end_atomic_region();
+    }
+
+    /// Leaves an interrupt service routine entered with [`x86_64InstructionStream::intrinsic_isr_prologue`]: pops the general purpose registers,
+    /// drops the error code and vector number of `entry`, executes `swapgs` if `swapgs` is set and the interrupt came from user mode, and returns
+    /// with `iretq`.
+    pub fn intrinsic_isr_epilogue(&mut self, entry: InterruptEntry, swapgs: bool) {
+        self.begin_atomic_region();
+        if entry.padding() != 0 {
+            self.add_reg64_imm32(Reg64::Rsp, entry.padding());
+        }
+
+        for reg in ISR_SAVED.into_iter().rev() {
+            self.pop_reg64(reg);
+        }
+
+        if entry.size() != 0 {
+            self.add_reg64_imm32(Reg64::Rsp, entry.size());
+        }
+
+        if swapgs {
+            self.swapgs_from_user(8);
+        }
+
+        self.iretq();
+        self.end_atomic_region();
+    }
+
+    /// Emits the stub of the interrupt `vector`: pushes a zero error code if the CPU doesn't push one for `vector` (see [`pushes_error_code`]),
+    /// pushes the vector number, and jumps to `handler`, which is entered as [`InterruptEntry::Stub`].
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::{InstructionStream, reloc::{Relocation, RelocationKind, RelocationTarget}};
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let handler = stream.create_symbol("isr_common");
+    /// stream.isr_stub(3, handler);   // #BP, without an error code
+    /// stream.isr_stub(14, handler);  // #PF, with an error code
+    /// stream.isr_stub(200, handler); // an interrupt, with a vector past `i8::MAX`
+    ///
+    /// let product = stream.finish();
+    /// assert_eq!(product.bytes(), [
+    ///     0x6a, 0x00,                   // push 0
+    ///     0x6a, 0x03,                   // push 3
+    ///     0xe9, 0x00, 0x00, 0x00, 0x00, // jmp isr_common
+    ///     0x6a, 0x0e,                   // push 14
+    ///     0xe9, 0x00, 0x00, 0x00, 0x00, // jmp isr_common
+    ///     0x6a, 0x00,                   // push 0
+    ///     0x68, 0xc8, 0x00, 0x00, 0x00, // push 200
+    ///     0xe9, 0x00, 0x00, 0x00, 0x00, // jmp isr_common
+    /// ]);
+    ///
+    /// let target = RelocationTarget::Symbol(handler);
+    /// assert_eq!(product.relocations(), [
+    ///     Relocation { offset: 5, kind: RelocationKind::Rel32, target, addend: -4 },
+    ///     Relocation { offset: 12, kind: RelocationKind::Rel32, target, addend: -4 },
+    ///     Relocation { offset: 24, kind: RelocationKind::Rel32, target, addend: -4 },
+    /// ]);
+    /// ```
+    pub fn isr_stub(&mut self, vector: u8, handler: SymRef) {
+        if !pushes_error_code(vector) {
+            self.push_imm8(0);
+        }
+
+        // `push imm8` sign extends, so the vectors past 127 take the imm32 form.
+        match i8::try_from(vector) {
+            Ok(imm8) => self.push_imm8(imm8),
+            Err(_) => self.push_imm32(vector.into()),
+        }
+
+        self.jmp_symbol(handler);
+    }
+}
@@ -0,0 +1,679 @@
+//! An Intel-syntax text assembler, enabled by the `text-asm` feature.
+//!
+//! Source lines are parsed into mnemonics and [`Operand`]s, and each line is emitted by the first form of the [`registry`](crate::registry) with
+//! the same mnemonic which accepts the parsed operands.  An emitter becomes available to the text assembler by adding its forms to
+//! [`INSTRUCTIONS`].
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use asmkit_core::{entity::{LabelRef, SymRef}, InstructionStream};
+
+pub use crate::registry::{check_operands, InstructionForm, Memory, MemoryTarget, Operand, OperandKind, Register, INSTRUCTIONS, PREFIXES};
+use crate::{register::Reg64, registry::{self, MEMORY_SIZES}, stream::x86_64InstructionStream};
+
+/// An error produced while assembling text, pointing at the offending line and column (both starting at 1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssembleError {
+    /// The line of the error.
+    pub line: usize,
+
+    /// The column of the error.
+    pub column: usize,
+
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+/// A label referenced by name in the source.
+struct NamedLabel {
+    label: LabelRef,
+    defined: bool,
+
+    /// The line and column of the first use of the label, reported if it is never defined.
+    first_use: Option<(usize, usize)>,
+}
+
+/// The state of a single [`x86_64InstructionStream::assemble`] call.
+struct Assembler<'a> {
+    stream: &'a mut x86_64InstructionStream,
+    labels: HashMap<String, NamedLabel>,
+
+    /// The external symbols declared by `extern`.
+    symbols: HashMap<String, SymRef>,
+
+    line: usize,
+}
+
+fn is_identifier_start(character: char) -> bool {
+    character.is_ascii_alphabetic() || character == '_' || character == '.'
+}
+
+fn is_identifier(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_' || character == '.'
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal integer, with an optional sign.  Unsigned values up to `u64::MAX` wrap into an `i64`.
+fn parse_integer(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits.trim_start()),
+        None => (false, text.strip_prefix('+').unwrap_or(text).trim_start()),
+    };
+
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|character: char| character.is_ascii_digit()) => digits.parse::<u64>().ok()?,
+        None => return None,
+    };
+
+    if negative {
+        (magnitude <= i64::MIN.unsigned_abs()).then(|| (magnitude as i64).wrapping_neg())
+    } else {
+        Some(magnitude as i64)
+    }
+}
+
+impl<'a> Assembler<'a> {
+    fn error<T>(&self, column: usize, message: impl Into<String>) -> Result<T, AssembleError> {
+        Err(AssembleError { line: self.line, column, message: message.into() })
+    }
+
+    /// Returns the label named `name`, creating it if it was never referenced before.
+    fn label(&mut self, name: &str) -> &mut NamedLabel {
+        let stream = &mut *self.stream;
+        self.labels.entry(name.to_string()).or_insert_with(|| NamedLabel { label: stream.create_label(), defined: false, first_use: None })
+    }
+
+    fn define_label(&mut self, name: &str, column: usize) -> Result<(), AssembleError> {
+        if self.labels.get(name).is_some_and(|label| label.defined) {
+            return self.error(column, format!("label `{}` is defined twice", name));
+        }
+
+        let label = self.label(name);
+        label.defined = true;
+        let label = label.label;
+        self.stream.attach_label(label);
+        Ok(())
+    }
+
+    fn assemble_line(&mut self, line: &str) -> Result<(), AssembleError> {
+        let line = line.split(';').next().unwrap_or_default();
+        let mut position = 0;
+
+        // label definitions, any number of them, optionally followed by an instruction.
+        loop {
+            let rest = &line[position..];
+            let start = position + (rest.len() - rest.trim_start().len());
+            let rest = &line[start..];
+            let length = rest.find(|character: char| !is_identifier(character)).unwrap_or(rest.len());
+
+            if length == 0 {
+                if rest.is_empty() {
+                    return Ok(());
+                }
+
+                return self.error(start + 1, format!("unexpected character `{}`", rest.chars().next().unwrap_or_default()));
+            }
+
+            let name = &rest[..length];
+            if !name.starts_with(is_identifier_start) {
+                return self.error(start + 1, format!("expected a mnemonic or a label, found `{}`", name));
+            }
+
+            if rest[length..].starts_with(':') {
+                self.define_label(name, start + 1)?;
+                position = start + length + 1;
+                continue;
+            }
+
+            return self.instruction(line, start);
+        }
+    }
+
+    /// Assembles the instruction of `line` starting at `position`, its mnemonic then its operands.
+    fn instruction(&mut self, line: &str, position: usize) -> Result<(), AssembleError> {
+        let word = |position: usize| {
+            let rest = &line[position..];
+            let start = position + (rest.len() - rest.trim_start().len());
+            let length = line[start..].find(|character: char| !is_identifier(character)).unwrap_or(line.len() - start);
+            (start, &line[start..start + length])
+        };
+
+        let (start, name) = word(position);
+        if !name.starts_with(is_identifier_start) {
+            return self.error(start + 1, "expected a mnemonic");
+        }
+
+        let column = start + 1;
+        let mut mnemonic = name.to_ascii_lowercase();
+        let mut position = start + name.len();
+
+        // the mnemonics of two words, such as `rep movsb` and `jmp far`.
+        let (next, second) = word(position);
+        let joined = format!("{} {}", mnemonic, second.to_ascii_lowercase());
+        if !second.is_empty() && registry::forms(&joined).next().is_some() {
+            mnemonic = joined;
+            position = next + second.len();
+        }
+
+        if mnemonic == "extern" {
+            return self.declare_symbol(line, position);
+        }
+
+        // a prefix is emitted on its own, before the instruction following it.
+        let operand_text = line[position..].trim();
+        if PREFIXES.contains(&mnemonic.as_str()) && !operand_text.is_empty() {
+            self.emit(&mnemonic, column, &[], "")?;
+            return self.instruction(line, position);
+        }
+
+        let mut operands = Vec::new();
+        let rest = &line[position..];
+        if !operand_text.is_empty() {
+            let mut depth = 0;
+            let mut start = position;
+
+            for (index, character) in rest.char_indices().map(|(index, character)| (index + position, character)).chain([(line.len(), ',')]) {
+                match character {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    ',' if depth == 0 => {
+                        let text = &line[start..index];
+                        let column = start + (text.len() - text.trim_start().len()) + 1;
+                        operands.push(self.parse_operand(text.trim(), column)?);
+                        start = index + 1;
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        self.emit(&mnemonic, column, &operands, operand_text)
+    }
+
+    /// Emits the instruction `mnemonic` with `operands`, written `operand_text`, by the first form of the mode of the stream accepting them.
+    fn emit(&mut self, mnemonic: &str, column: usize, operands: &[Operand], operand_text: &str) -> Result<(), AssembleError> {
+        let mut forms = registry::forms(mnemonic).peekable();
+        if forms.peek().is_none() {
+            return self.error(column, format!("unknown mnemonic `{}`", mnemonic));
+        }
+
+        let mode = self.stream.mode();
+        let mut accepting = forms.filter(|form| form.modes.contains(&mode) && form.accepts(operands));
+        match accepting.next() {
+            Some(form) => {
+                if let Err(error) = check_operands(operands) {
+                    return self.error(column, error.to_string());
+                }
+
+                // a memory operand without a size must only match forms accessing the same size.
+                let size = |form: &InstructionForm| form.operands.iter().map(OperandKind::memory_size).collect::<Vec<_>>();
+                if accepting.any(|other| size(other) != size(form)) {
+                    return self.error(column, format!("ambiguous operand size for `{}`, the memory operand needs a size such as `qword ptr`", mnemonic));
+                }
+
+                // the text is taken by the first instruction the form emits.
+                self.stream.instruction_listing_mut().next_text = Some((form, operand_text.to_string()));
+                (form.emit)(self.stream, operands);
+                self.stream.instruction_listing_mut().next_text = None;
+                Ok(())
+            },
+            None => self.error(column, format!("invalid operands for `{}`", mnemonic)),
+        }
+    }
+
+    /// Declares the external symbols named by the `extern` directive of `line`, whose names start at `position`.
+    fn declare_symbol(&mut self, line: &str, position: usize) -> Result<(), AssembleError> {
+        let rest = &line[position..];
+        let name = rest.trim();
+        let column = position + (rest.len() - rest.trim_start().len()) + 1;
+
+        if !name.starts_with(is_identifier_start) || !name.chars().all(is_identifier) {
+            return self.error(column, format!("expected a symbol name, found `{}`", name));
+        }
+
+        if self.symbols.contains_key(name) || self.labels.contains_key(name) {
+            return self.error(column, format!("`{}` is declared twice", name));
+        }
+
+        let symbol = self.stream.create_symbol(name);
+        self.symbols.insert(name.to_string(), symbol);
+        Ok(())
+    }
+
+    /// Returns the label named `name`, recording `column` as its first use.
+    fn use_label(&mut self, name: &str, column: usize) -> LabelRef {
+        let line = self.line;
+        let label = self.label(name);
+        label.first_use.get_or_insert((line, column));
+        label.label
+    }
+
+    fn parse_operand(&mut self, text: &str, column: usize) -> Result<Operand, AssembleError> {
+        if text.is_empty() {
+            return self.error(column, "expected an operand");
+        }
+
+        let lowercase = text.to_ascii_lowercase();
+        for (prefix, size) in MEMORY_SIZES {
+            if let Some(rest) = lowercase.strip_prefix(prefix).and_then(|rest| rest.trim_start().strip_prefix("ptr")) {
+                let offset = text.len() - rest.trim_start().len();
+                let mut memory = self.parse_memory(&text[offset..], column + offset)?;
+                memory.size = Some(size);
+                return Ok(Operand::Memory(memory));
+            }
+        }
+
+        if text.starts_with('[') {
+            return self.parse_memory(text, column).map(Operand::Memory);
+        }
+
+        if let Some(rest) = lowercase.strip_prefix("short").filter(|rest| rest.starts_with(char::is_whitespace)) {
+            let offset = text.len() - rest.trim_start().len();
+            let name = &text[offset..];
+            if !name.starts_with(is_identifier_start) || !name.chars().all(is_identifier) {
+                return self.error(column + offset, format!("expected a label, found `{}`", name));
+            }
+
+            return Ok(Operand::ShortLabel(self.use_label(name, column + offset)));
+        }
+
+        if let Some(displacement) = text.strip_prefix('$') {
+            return match parse_integer(displacement.trim_start()).filter(|_| displacement.starts_with(['+', '-'])) {
+                Some(displacement) => Ok(Operand::Relative(displacement)),
+                None => self.error(column, format!("invalid displacement `{}`, such as `$+8` or `$-2`", text)),
+            };
+        }
+
+        if let Some((segment, offset)) = text.split_once(':') {
+            return match (parse_integer(segment.trim()).and_then(|segment| u16::try_from(segment).ok()), parse_integer(offset.trim()).and_then(|offset| u32::try_from(offset).ok())) {
+                (Some(segment), Some(offset)) => Ok(Operand::FarPointer { segment, offset }),
+                _ => self.error(column, format!("invalid far pointer `{}`, such as `0x8:0x1000`", text)),
+            };
+        }
+
+        if text.starts_with(|character: char| character.is_ascii_digit() || character == '-' || character == '+') {
+            return match parse_integer(text) {
+                Some(value) => Ok(Operand::Immediate(value)),
+                None => self.error(column, format!("invalid immediate `{}`", text)),
+            };
+        }
+
+        if let Ok(register) = lowercase.parse() {
+            return Ok(Operand::Register(register));
+        }
+
+        if let Some(symbol) = self.symbols.get(text) {
+            return Ok(Operand::Symbol(*symbol));
+        }
+
+        if text.starts_with(is_identifier_start) && text.chars().all(is_identifier) {
+            return Ok(Operand::Label(self.use_label(text, column)));
+        }
+
+        self.error(column, format!("invalid operand `{}`", text))
+    }
+
+    fn parse_memory(&mut self, text: &str, column: usize) -> Result<Memory, AssembleError> {
+        let inner = match text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) {
+            Some(inner) => inner,
+            None => return self.error(column, format!("expected a memory operand, found `{}`", text)),
+        };
+
+        let mut memory = Memory::default();
+        let mut displacement = 0i64;
+        let mut start = 0;
+
+        // split into terms, each carrying its sign.
+        let boundaries = inner.char_indices().filter(|(index, character)| *index > 0 && (*character == '+' || *character == '-')).map(|(index, _)| index);
+        for end in boundaries.chain([inner.len()]) {
+            let term = inner[start..end].trim();
+            let (negative, body) = match term.strip_prefix('-') {
+                Some(body) => (true, body.trim()),
+                None => (false, term.strip_prefix('+').unwrap_or(term).trim()),
+            };
+            let term_column = column + 1 + (body.as_ptr() as usize - inner.as_ptr() as usize);
+            start = end;
+
+            if body.is_empty() {
+                return self.error(term_column, "expected a register or a displacement");
+            }
+
+            if let Some(value) = parse_integer(body) {
+                displacement += if negative { -value } else { value };
+                continue;
+            }
+
+            let (register, scale) = match body.split_once('*') {
+                Some((left, right)) => {
+                    let (left, right) = (left.trim(), right.trim());
+                    match (parse_integer(left), parse_integer(right)) {
+                        (_, Some(scale)) => (left, scale),
+                        (Some(scale), _) => (right, scale),
+                        _ => return self.error(term_column, format!("invalid scaled index `{}`", body)),
+                    }
+                },
+                None => (body, 0),
+            };
+
+            let register = match register.to_ascii_lowercase().parse() {
+                Ok(register @ (Register::Reg64(_) | Register::Xmm(_) | Register::Ymm(_))) => register,
+                Ok(_) => return self.error(term_column, format!("expected a 64-bit register, found `{}`", register)),
+
+                // a label or an external symbol, `symbol@gotpcrel` for its entry of the global offset table.
+                Err(_) if scale == 0 && memory.target.is_none() => {
+                    let (name, got) = match body.split_once('@') {
+                        Some((name, relocation)) if relocation.eq_ignore_ascii_case("gotpcrel") => (name, true),
+                        _ => (body, false),
+                    };
+
+                    if negative || !name.starts_with(is_identifier_start) || !name.chars().all(is_identifier) {
+                        return self.error(term_column, format!("expected a register, a label or a symbol, found `{}`", body));
+                    }
+
+                    memory.target = Some(match (self.symbols.get(name), got) {
+                        (Some(symbol), true) => MemoryTarget::Got(*symbol),
+                        (Some(symbol), false) => MemoryTarget::Symbol(*symbol),
+                        (None, true) => return self.error(term_column, format!("`{}` isn't declared by `extern`", name)),
+                        (None, false) => MemoryTarget::Label(self.use_label(name, term_column)),
+                    });
+                    continue;
+                },
+                Err(_) => return self.error(term_column, format!("expected a 64-bit register, found `{}`", register)),
+            };
+
+            if negative {
+                return self.error(term_column, "registers can not be subtracted");
+            }
+
+            match register {
+                Register::Reg64(base) if scale == 0 && memory.base.is_none() => memory.base = Some(base),
+                _ if memory.index.is_none() => {
+                    let scale = if scale == 0 { 1 } else { scale };
+                    if ![1, 2, 4, 8].contains(&scale) {
+                        return self.error(term_column, format!("scale {} is not one of 1, 2, 4 or 8", scale));
+                    }
+
+                    if register == Register::Reg64(Reg64::Rsp) {
+                        return self.error(term_column, "`rsp` can't be used as an index register");
+                    }
+
+                    memory.index = Some((register, scale as u8));
+                },
+                _ => return self.error(term_column, "too many registers in memory operand"),
+            }
+        }
+
+        memory.displacement = match i32::try_from(displacement) {
+            Ok(displacement) => displacement,
+            Err(_) => return self.error(column, format!("displacement {} does not fit in 32 bits", displacement)),
+        };
+
+        if memory.target.is_some() && memory.target() != memory.target {
+            return self.error(column, "a label or a symbol can't be used with registers or a displacement");
+        }
+
+        Ok(memory)
+    }
+}
+
+impl x86_64InstructionStream {
+    /// Assembles Intel-syntax source text into the instruction stream.
+    ///
+    /// Each line holds any number of `name:` label definitions, optionally followed by an instruction; comments start with `;`.  Immediates are written
+    /// in decimal or in `0x` prefixed hexadecimal, and memory operands as `[base + index*scale + displacement]`, optionally preceded by a size such as
+    /// `qword ptr`.  Only the instructions listed in [`INSTRUCTIONS`] for the mode of the stream are available, and the [`PREFIXES`] may precede
+    /// an instruction on the same line.
+    ///
+    /// A label is written by name, `short label` for a short branch, and `[label]` for the memory at the label.  The `extern name` directive
+    /// declares an external symbol, written `name`, `[name]`, or `[name@gotpcrel]` for its entry of the global offset table.  A far pointer is
+    /// written `segment:offset`, and the displacement of a branch from its end `$+n`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::{Reg16, Reg32, Reg64, Reg8}, stream::x86_64InstructionStream};
+    ///
+    /// let mut text = x86_64InstructionStream::new();
+    /// text.assemble("
+    ///     start:
+    ///         push rbp
+    ///         mov rbp, rsp        ; set up a frame
+    ///         mov al, 0xff
+    ///         mov sil, r9b
+    ///         mov cx, dx
+    ///         mov r11w, 0x1234
+    ///         mov ebx, 3735928559
+    ///         mov r8d, eax
+    ///         mov rax, -1
+    ///         mov rax, 0x123456789
+    ///         push 8
+    ///         push 0x12345
+    ///         push r9w
+    ///         push fs
+    ///     end: ret 16
+    /// ").unwrap();
+    ///
+    /// let mut direct = x86_64InstructionStream::new();
+    /// direct.push_reg64(Reg64::Rbp);
+    /// direct.mov_reg64_reg64(Reg64::Rbp, Reg64::Rsp);
+    /// direct.mov_reg8_imm8(Reg8::Al, 0xff);
+    /// direct.mov_reg8_reg8(Reg8::Sil, Reg8::R9b);
+    /// direct.mov_reg16_reg16(Reg16::Cx, Reg16::Dx);
+    /// direct.mov_reg16_imm16(Reg16::R11w, 0x1234);
+    /// direct.mov_reg32_imm32(Reg32::Ebx, 0xdeadbeef);
+    /// direct.mov_reg32_reg32(Reg32::R8d, Reg32::Eax);
+    /// direct.mov_reg64_imm32(Reg64::Rax, -1);
+    /// direct.mov_reg64_imm64(Reg64::Rax, 0x123456789);
+    /// direct.push_imm8(8);
+    /// direct.push_imm32(0x12345);
+    /// direct.push_reg16(Reg16::R9w);
+    /// direct.push_fs();
+    /// direct.ret_near_imm16(16);
+    ///
+    /// let bytes = text.finish().emit();
+    /// assert_eq!(bytes, direct.finish().emit());
+    /// assert_eq!(bytes, [
+    ///     0x55,
+    ///     0x48, 0x89, 0xe5,
+    ///     0xb0, 0xff,
+    ///     0x44, 0x88, 0xce,
+    ///     0x66, 0x89, 0xd1,
+    ///     0x66, 0x41, 0xbb, 0x34, 0x12,
+    ///     0xbb, 0xef, 0xbe, 0xad, 0xde,
+    ///     0x41, 0x89, 0xc0,
+    ///     0x48, 0xc7, 0xc0, 0xff, 0xff, 0xff, 0xff,
+    ///     0x48, 0xb8, 0x89, 0x67, 0x45, 0x23, 0x01, 0x00, 0x00, 0x00,
+    ///     0x6a, 0x08,
+    ///     0x68, 0x45, 0x23, 0x01, 0x00,
+    ///     0x66, 0x41, 0x51,
+    ///     0x0f, 0xa0,
+    ///     0xc2, 0x10, 0x00,
+    /// ]);
+    /// ```
+    ///
+    /// Errors point at the offending line and column:
+    /// ```
+    /// use asmkit_x86_64::stream::x86_64InstructionStream;
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let error = stream.assemble("ret\nmov rax, 12abc").unwrap_err();
+    /// assert_eq!(error.to_string(), "2:10: invalid immediate `12abc`");
+    ///
+    /// let error = stream.assemble("mov eax, rbx").unwrap_err();
+    /// assert_eq!((error.line, error.column, error.message.as_str()), (1, 1, "invalid operands for `mov`"));
+    ///
+    /// let error = stream.assemble("mov rax, [rbx + rcx*3]").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:17: scale 3 is not one of 1, 2, 4 or 8");
+    ///
+    /// let error = stream.assemble("mov ah, r9b").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: `ah` can't be encoded along with `r9b`, which requires a REX prefix");
+    ///
+    /// let error = stream.assemble("mov [rbx + rsp*2], eax").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:12: `rsp` can't be used as an index register");
+    ///
+    /// let error = stream.assemble("mov [rbx], 1").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: ambiguous operand size for `mov`, the memory operand needs a size such as `qword ptr`");
+    ///
+    /// let error = stream.assemble("mov eax, qword ptr [rbx]").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: invalid operands for `mov`");
+    /// ```
+    ///
+    /// Memory operands and conditional jumps, with their aliases such as `jnz` for `jne`:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{
+    ///     memory::{Mem, Scale},
+    ///     register::{Reg32, Reg64, Reg8},
+    ///     stream::{x86_64InstructionStream, Condition},
+    /// };
+    ///
+    /// let mut text = x86_64InstructionStream::new();
+    /// text.assemble("
+    ///         xor eax, eax
+    ///     loop_start:
+    ///         mov rdx, [rbx]
+    ///         add rax, rdx
+    ///         mov ecx, dword ptr [rbx + rsi*4 + 8]
+    ///         mov byte ptr [rdi + r12*8 - 0x10], cl
+    ///         mov qword ptr [rsp + 8], -1
+    ///         movzx ecx, byte ptr [r13]
+    ///         lea rbx, [rbx + 16]
+    ///         add eax, 1
+    ///         sub rax, rbx
+    ///         cmp rax, 0x100
+    ///         je done
+    ///         jnz loop_start
+    ///     done:
+    ///         call [rax + 8]
+    ///         ret
+    /// ").unwrap();
+    ///
+    /// let mut direct = x86_64InstructionStream::new();
+    /// let (loop_start, done) = (direct.create_label(), direct.create_label());
+    /// direct.xor_reg32_reg32(Reg32::Eax, Reg32::Eax);
+    /// direct.attach_label(loop_start);
+    /// direct.mov_reg64_mem64(Reg64::Rdx, Mem::new(Reg64::Rbx, 0));
+    /// direct.add_reg64_reg64(Reg64::Rax, Reg64::Rdx);
+    /// direct.mov_reg32_mem32(Reg32::Ecx, Mem::indexed(Reg64::Rbx, Reg64::Rsi, Scale::X4, 8));
+    /// direct.mov_mem8_reg8(Mem::indexed(Reg64::Rdi, Reg64::R12, Scale::X8, -0x10), Reg8::Cl);
+    /// direct.mov_mem64_imm32(Mem::new(Reg64::Rsp, 8), -1);
+    /// direct.movzx_reg32_mem8(Reg32::Ecx, Mem::new(Reg64::R13, 0));
+    /// direct.lea_reg64_mem(Reg64::Rbx, Mem::new(Reg64::Rbx, 16));
+    /// direct.add_reg32_imm32(Reg32::Eax, 1);
+    /// direct.sub_reg64_reg64(Reg64::Rax, Reg64::Rbx);
+    /// direct.cmp_reg64_imm32(Reg64::Rax, 0x100);
+    /// direct.jcc_label(Condition::E, done);
+    /// direct.jcc_label(Condition::Ne, loop_start);
+    /// direct.attach_label(done);
+    /// direct.call_mem(Mem::new(Reg64::Rax, 8));
+    /// direct.ret_near();
+    ///
+    /// let bytes = text.finish().emit();
+    /// assert_eq!(bytes, direct.finish().emit());
+    /// assert_eq!(bytes, [
+    ///     0x31, 0xc0,                                           // xor eax, eax
+    ///     0x48, 0x8b, 0x13,                                     // mov rdx, qword ptr [rbx]
+    ///     0x48, 0x01, 0xd0,                                     // add rax, rdx
+    ///     0x8b, 0x4c, 0xb3, 0x08,                               // mov ecx, dword ptr [rbx + rsi*4 + 8]
+    ///     0x42, 0x88, 0x4c, 0xe7, 0xf0,                         // mov byte ptr [rdi + r12*8 - 0x10], cl
+    ///     0x48, 0xc7, 0x44, 0x24, 0x08, 0xff, 0xff, 0xff, 0xff, // mov qword ptr [rsp + 8], -1
+    ///     0x41, 0x0f, 0xb6, 0x4d, 0x00,                         // movzx ecx, byte ptr [r13]
+    ///     0x48, 0x8d, 0x5b, 0x10,                               // lea rbx, [rbx + 16]
+    ///     0x83, 0xc0, 0x01,                                     // add eax, 1
+    ///     0x48, 0x29, 0xd8,                                     // sub rax, rbx
+    ///     0x48, 0x81, 0xf8, 0x00, 0x01, 0x00, 0x00,             // cmp rax, 0x100
+    ///     0x0f, 0x84, 0x06, 0x00, 0x00, 0x00,                   // je done
+    ///     0x0f, 0x85, 0xc6, 0xff, 0xff, 0xff,                   // jne loop_start
+    ///     0xff, 0x50, 0x08,                                     // call qword ptr [rax + 8]
+    ///     0xc3,                                                 // ret
+    /// ]);
+    /// ```
+    ///
+    /// Labels and symbols in memory, short branches, prefixes and the other kinds of operands:
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{
+    ///     memory::{Mem, Scale, VsibMem},
+    ///     register::{Reg64, Reg8, Xmm, Ymm},
+    ///     sse41::RoundingControl,
+    ///     stream::{x86_64InstructionStream, Condition},
+    /// };
+    ///
+    /// let mut text = x86_64InstructionStream::new();
+    /// text.assemble("
+    ///         extern counter
+    ///     top:
+    ///         movaps xmm1, [constant]
+    ///         mov rax, [counter@gotpcrel]
+    ///         lock xadd [rax], rcx
+    ///         shl rdx, cl
+    ///         roundsd xmm0, xmm1, 9
+    ///         vgatherdps ymm0, [rdi + ymm2*4 + 0x40], ymm1
+    ///         mov qword ptr [constant], 0
+    ///         rep movsb
+    ///         jz short top
+    ///         call counter
+    ///         jmp $+2
+    ///     constant:
+    /// ").unwrap();
+    ///
+    /// let mut direct = x86_64InstructionStream::new();
+    /// let counter = direct.create_symbol("counter");
+    /// let (top, constant) = (direct.create_label_attached(), direct.create_label());
+    /// direct.movaps_xmm_label(Xmm::Xmm1, constant);
+    /// direct.mov_reg64_got_symbol(Reg64::Rax, counter);
+    /// direct.lock();
+    /// direct.xadd_mem64_reg64(Mem::new(Reg64::Rax, 0), Reg64::Rcx);
+    /// direct.shl_reg64_cl(Reg64::Rdx);
+    /// direct.roundsd_xmm_xmm(Xmm::Xmm0, Xmm::Xmm1, RoundingControl::Floor);
+    /// direct.vgatherdps_ymm_vm32y_ymm(Ymm::Ymm0, VsibMem::new(Reg64::Rdi, Ymm::Ymm2, Scale::X4, 0x40), Ymm::Ymm1);
+    /// direct.mov_label64_imm32(constant, 0);
+    /// direct.rep_movsb();
+    /// direct.jcc_short_label(Condition::E, top);
+    /// direct.call_symbol(counter);
+    /// direct.jmp_rel32(2);
+    /// direct.attach_label(constant);
+    ///
+    /// assert_eq!(text.finish().emit(), direct.finish().emit());
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// let error = stream.assemble("mov rax, [rbx + label]").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:10: a label or a symbol can't be used with registers or a displacement");
+    ///
+    /// let error = stream.assemble("jmp far [rax]").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: ambiguous operand size for `jmp far`, the memory operand needs a size such as `qword ptr`");
+    ///
+    /// let error = stream.assemble("jmp far 0x8:0x1000").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: invalid operands for `jmp far`");
+    ///
+    /// let error = stream.assemble("vgatherdps ymm0, [rdi + ymm0*4], ymm1").unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: the destination and the index of a gather are both vector register 0, but must be distinct");
+    /// ```
+    pub fn assemble(&mut self, source: &str) -> Result<(), AssembleError> {
+        let mut assembler = Assembler { stream: self, labels: HashMap::new(), symbols: HashMap::new(), line: 0 };
+
+        for (index, line) in source.lines().enumerate() {
+            assembler.line = index + 1;
+            assembler.assemble_line(line)?;
+        }
+
+        let mut undefined = assembler.labels.iter().filter(|(_, label)| !label.defined).collect::<Vec<_>>();
+        undefined.sort_by_key(|(_, label)| label.first_use);
+
+        match undefined.first() {
+            Some((name, label)) => {
+                let (line, column) = label.first_use.unwrap_or_default();
+                Err(AssembleError { line, column, message: format!("label `{}` is used but never defined", name) })
+            },
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Jump thunks and call trampolines, the indirections of dynamic binding.
+//!
+//! A jump thunk is a [`THUNK_SIZE`]-byte piece of code jumping to a target, which callers branch to instead of the target itself, so the target can
+//! be rebound by [`patch_jump_thunk`] while the code runs.  A thunk jumps with `jmp rel32` when the target is within ±2 GiB of the address the thunk
+//! is loaded at, and with `movabs r11, target; jmp r11` otherwise; both shapes are padded with `int3` to the same size, so a thunk can switch
+//! shapes when patched.
+//!
+//! A call trampoline loads a context value into [`CONTEXT_REGISTER`] before jumping to its target, binding a closure-like context to a function
+//! taking it in that register.
+//!
+//! # Example
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! unsafe {
+//!     use asmkit_x86_64::thunk::{make_jump_thunk, patch_jump_thunk};
+//!
+//!     extern "C" {
+//!         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+//!         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+//!         fn munmap(address: *mut u8, length: usize) -> i32;
+//!     }
+//!
+//!     extern "C" fn double(x: u64) -> u64 { x * 2 }
+//!     extern "C" fn square(x: u64) -> u64 { x * x }
+//!
+//!     // maps a page `distance` bytes past `double`, if the kernel takes the hint.
+//!     let map_near = |distance: usize| {
+//!         let hint = (double as *const () as usize & !0xfff).wrapping_add(distance) as *mut u8;
+//!         let memory = mmap(hint, 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+//!         assert_ne!(memory as isize, -1);
+//!         memory
+//!     };
+//!
+//!     // 1 GiB away, a `jmp rel32` reaches the functions, 64 GiB away they are jumped to through `r11`.
+//!     for (distance, opcode) in [(1 << 30, 0xe9), (1 << 36, 0x49)] {
+//!         let memory = map_near(distance);
+//!         let code = make_jump_thunk(double as *const u8, memory).emit();
+//!         std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+//!         assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+//!
+//!         let thunk: extern "C" fn(u64) -> u64 = std::mem::transmute(memory);
+//!         if memory as usize == (double as *const () as usize & !0xfff).wrapping_add(distance) {
+//!             assert_eq!(code[0], opcode);
+//!         }
+//!         assert_eq!(thunk(7), 14);
+//!
+//!         assert_eq!(mprotect(memory, 4096, 0x1 | 0x2), 0);
+//!         patch_jump_thunk(std::slice::from_raw_parts_mut(memory, 4096), 0, square as *const u8);
+//!         assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0);
+//!         assert_eq!(thunk(7), 49);
+//!
+//!         munmap(memory, 4096);
+//!     }
+//! }
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream, Product};
+
+use crate::{register::Reg64, stream::x86_64InstructionStream};
+
+/// The size of a jump thunk, whatever its shape.
+pub const THUNK_SIZE: usize = 16;
+
+/// The register a call trampoline loads its context into, the static chain register of the System V ABI.
+pub const CONTEXT_REGISTER: Reg64 = Reg64::R10;
+
+/// The register the thunks and trampolines jump through when their target is out of reach of a 32-bit displacement.
+pub const SCRATCH_REGISTER: Reg64 = Reg64::R11;
+
+/// Returns the displacement of a `jmp rel32` at `address` to `target`, if it is within reach.
+fn rel32(address: *const u8, target: *const u8) -> Option<i32> {
+    let end = (address as i64).wrapping_add(5);
+    i32::try_from((target as i64).wrapping_sub(end)).ok()
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Jumps to `target` from an instruction loaded at `address`, with `jmp rel32` if it is within reach and through [`SCRATCH_REGISTER`] otherwise.
+    /// Returns the size of the jump.
+    fn jmp_absolute(&mut self, address: *const u8, target: *const u8) -> usize {
+        match rel32(address, target) {
+            Some(rel32) => {
+                self.jmp_rel32(rel32);
+                5
+            },
+            None => {
+                self.mov_reg64_imm64(SCRATCH_REGISTER, target as u64);
+                self.jmp_reg64(SCRATCH_REGISTER);
+                13
+            },
+        }
+    }
+}
+
+/// Creates a jump thunk to `target`, to be loaded at `hint`.  The thunk jumps with `jmp rel32` if `target` is within ±2 GiB of `hint`, in which case
+/// it must be loaded at `hint` exactly, and with `movabs r11, target; jmp r11` otherwise, in which case it may be loaded anywhere.
+///
+/// # Example
+/// ```
+/// let near = asmkit_x86_64::thunk::make_jump_thunk(0x7000_1000 as *const u8, 0x4000_0000 as *const u8);
+/// assert_eq!(near.emit(), [
+///     0xe9, 0xfb, 0x0f, 0x00, 0x30, // jmp 0x70001000
+///     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, // int3 (x11)
+///     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+/// ]);
+///
+/// let far = asmkit_x86_64::thunk::make_jump_thunk(0x7f12_3456_789a as *const u8, 0x4000_0000 as *const u8);
+/// assert_eq!(far.emit(), [
+///     0x49, 0xbb, 0x9a, 0x78, 0x56, 0x34, 0x12, 0x7f, 0x00, 0x00, // movabs r11, 0x7f123456789a
+///     0x41, 0xff, 0xe3,                                           // jmp r11
+///     0xcc, 0xcc, 0xcc,                                           // int3 (x3)
+/// ]);
+/// ```
+pub fn make_jump_thunk(target: *const u8, hint: *const u8) -> Product {
+    let mut stream = x86_64InstructionStream::new();
+
+    let size = stream.jmp_absolute(hint, target);
+    for _ in size..THUNK_SIZE {
+        stream.int3();
+    }
+
+    stream.finish()
+}
+
+/// Creates a call trampoline to `target`, to be loaded at `hint`, which loads `context` into [`CONTEXT_REGISTER`] and tail-jumps to `target`,
+/// leaving the arguments and the return address of the caller untouched.  The jump is shaped as in [`make_jump_thunk`], and the trampoline must
+/// likewise be loaded at `hint` exactly if `target` is within ±2 GiB of it.
+///
+/// # Example
+/// ```
+/// let trampoline = asmkit_x86_64::thunk::make_call_trampoline(0x4000_2000 as *const u8, 0xdead_beef, 0x4000_0000 as *const u8);
+/// assert_eq!(trampoline.emit(), [
+///     0x49, 0xba, 0xef, 0xbe, 0xad, 0xde, 0x00, 0x00, 0x00, 0x00, // movabs r10, 0xdeadbeef
+///     0xe9, 0xf1, 0x1f, 0x00, 0x00,                               // jmp 0x40002000
+/// ]);
+/// ```
+///
+/// Executed, with a target reading its context:
+/// ```
+/// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+/// unsafe {
+///     use asmkit_core::InstructionStream;
+///     use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream, thunk::{make_call_trampoline, CONTEXT_REGISTER}};
+///
+///     extern "C" {
+///         fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+///         fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+///         fn munmap(address: *mut u8, length: usize) -> i32;
+///     }
+///
+///     let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+///     assert_ne!(memory as isize, -1);
+///
+///     // the target adds its context to its argument.
+///     let mut target = x86_64InstructionStream::new();
+///     target.mov_reg64_reg64(Reg64::Rax, CONTEXT_REGISTER);
+///     target.add_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+///     target.ret_near();
+///
+///     let target = target.finish().emit();
+///     std::ptr::copy_nonoverlapping(target.as_ptr(), memory.add(64), target.len());
+///
+///     let trampoline = make_call_trampoline(memory.add(64), 35, memory).emit();
+///     std::ptr::copy_nonoverlapping(trampoline.as_ptr(), memory, trampoline.len());
+///     assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+///
+///     let trampoline: extern "C" fn(u64) -> u64 = std::mem::transmute(memory);
+///     assert_eq!(trampoline(7), 42);
+///
+///     munmap(memory, 4096);
+/// }
+/// ```
+pub fn make_call_trampoline(target: *const u8, context: u64, hint: *const u8) -> Product {
+    let mut stream = x86_64InstructionStream::new();
+
+    stream.mov_reg64_imm64(CONTEXT_REGISTER, context);
+    stream.jmp_absolute(hint.wrapping_add(10), target);
+
+    stream.finish()
+}
+
+/// Rebinds the jump thunk at `offset` in `buffer` to `new_target`, picking the shape of the thunk for its address in `buffer` as
+/// [`make_jump_thunk`] does.
+///
+/// The thunk is rewritten atomically: with one aligned 8-byte store if only its first 8 bytes change, as when a `jmp rel32` is retargeted, and
+/// with `lock cmpxchg16b` otherwise.  A thread running the thunk thus never fetches half of the old thunk and half of the new one, but the
+/// Intel and AMD manuals only guarantee that a processor fetches the new thunk once it has executed a serializing instruction (such as `cpuid`)
+/// after the store; until then, other threads may still jump to the old target.  Callers needing every thread to see the new target at once must
+/// have them serialize, for example with `membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE)` on Linux.
+///
+/// `buffer` must be writable while the thunk is patched.
+///
+/// # Panics
+/// Panics if the thunk overruns `buffer`, if it isn't 16-byte aligned, if there is no jump thunk at `offset`, or if the 16-byte store is needed
+/// but the processor doesn't support `cmpxchg16b`.
+#[cfg(target_arch = "x86_64")]
+pub fn patch_jump_thunk(buffer: &mut [u8], offset: usize, new_target: *const u8) {
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    assert!(offset.checked_add(THUNK_SIZE).is_some_and(|end| end <= buffer.len()), "jump thunk at {:#x} overruns the buffer of {:#x} bytes", offset, buffer.len());
+
+    let thunk = &mut buffer[offset..offset + THUNK_SIZE];
+    let address = thunk.as_mut_ptr();
+    assert!((address as usize).is_multiple_of(16), "jump thunk at {:p} isn't 16-byte aligned", address);
+    assert!(matches!(thunk, [0xe9, ..] | [0x49, 0xbb, ..]), "no jump thunk at {:#x}", offset);
+
+    let old = u128::from_le_bytes(thunk.try_into().unwrap());
+    let new = u128::from_le_bytes(make_jump_thunk(new_target, address).emit().try_into().unwrap());
+
+    if old >> 64 == new >> 64 {
+        // SAFETY: the thunk is 16-byte aligned, hence its first 8 bytes are aligned for an `AtomicU64`, and borrowed mutably.
+        let head = unsafe { &*(address as *const AtomicU64) };
+        head.store(new as u64, Ordering::Release);
+    } else {
+        assert!(std::arch::is_x86_feature_detected!("cmpxchg16b"), "patching a jump thunk into another shape needs `cmpxchg16b`");
+
+        // SAFETY: the thunk is 16-byte aligned and borrowed mutably, so the exchange succeeds at once.  `rbx` is reserved by LLVM, so the low
+        // quadword of `new` is swapped into it around the exchange.
+        unsafe {
+            core::arch::asm!(
+                "xchg {low}, rbx",
+                "lock cmpxchg16b xmmword ptr [{address}]",
+                "mov rbx, {low}",
+                address = in(reg) address,
+                low = inout(reg) new as u64 => _,
+                in("rcx") (new >> 64) as u64,
+                inout("rax") old as u64 => _,
+                inout("rdx") (old >> 64) as u64 => _,
+                options(nostack),
+            );
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Thread-local storage, addressed from the thread pointer held in the `fs` base on Linux.
+//!
+//! [`intrinsic_load_tls_ie`](x86_64InstructionStream::intrinsic_load_tls_ie) loads a thread-local variable of the initial-exec model, whose
+//! offset from the thread pointer is read from the global offset table entry the loader fills, through a [`RelocationKind::GotTpOff`] relocation.
+//! [`intrinsic_read_fs_base_offset`](x86_64InstructionStream::intrinsic_read_fs_base_offset) reads at a constant offset from the thread
+//! pointer, for the runtimes laying out their own thread-local block.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::{reloc::{Relocation, RelocationKind, RelocationTarget}, InstructionStream};
+//! use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! let errno = stream.create_symbol("errno");
+//! stream.intrinsic_load_tls_ie(Reg64::Rax, errno);
+//! stream.intrinsic_load_tls_ie(Reg64::R12, errno);
+//! stream.intrinsic_read_fs_base_offset(Reg64::Rax, 0x28);
+//! stream.intrinsic_read_fs_base_offset(Reg64::R9, -8);
+//! let product = stream.finish();
+//!
+//! assert_eq!(product.bytes(), [
+//!     0x48, 0x8b, 0x05, 0x00, 0x00, 0x00, 0x00,             // mov rax, qword ptr [rip + errno@gottpoff]
+//!     0x64, 0x48, 0x8b, 0x00,                               // mov rax, qword ptr fs:[rax]
+//!     0x4c, 0x8b, 0x25, 0x00, 0x00, 0x00, 0x00,             // mov r12, qword ptr [rip + errno@gottpoff]
+//!     0x64, 0x4d, 0x8b, 0x24, 0x24,                         // mov r12, qword ptr fs:[r12]
+//!     0x64, 0x48, 0x8b, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00, // mov rax, qword ptr fs:[0x28]
+//!     0x64, 0x4c, 0x8b, 0x0c, 0x25, 0xf8, 0xff, 0xff, 0xff, // mov r9, qword ptr fs:[-8]
+//! ]);
+//! assert_eq!(product.relocations(), [
+//!     Relocation { offset: 3, kind: RelocationKind::GotTpOff, target: RelocationTarget::Symbol(errno), addend: -4 },
+//!     Relocation { offset: 14, kind: RelocationKind::GotTpOff, target: RelocationTarget::Symbol(errno), addend: -4 },
+//! ]);
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, entity::SymRef, reloc::RelocationKind, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    memory::Mem,
+    register::Reg64,
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, REX, REX_R, REX_W},
+};
+
+/// The `fs` segment override prefix.
+const FS: u8 = 0x64;
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Loads the 64-bit thread-local variable `symbol` into *r64*, with the initial-exec sequence: its offset from the thread pointer is loaded
+    /// from its global offset table entry, through a [`RelocationKind::GotTpOff`] relocation, and the variable is then read through `fs`.
+    pub fn intrinsic_load_tls_ie(&mut self, dest: Reg64, symbol: SymRef) {
+        // the linker relaxes the sequence as a whole.
+        self.begin_atomic_region();
+
+        let instruction = self.begin_instruction();
+        self.write_byte(REX | REX_W | if dest.is_extension() { REX_R } else { 0 });
+        self.write_byte(0x8b);
+        self.write_byte((dest.offset() << 3) | 0b101); // `[rip + disp32]` MODRM encoded
+
+        // the displacement is relative to the end of the instruction, 4 bytes past the field.
+        self.write_symbol(symbol, RelocationKind::GotTpOff, -4);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+
+        let instruction = self.begin_instruction();
+        self.write_byte(FS);
+        self.write_rex_modrm_mem(true, &[0x8b], (dest.offset(), dest.is_extension()), Mem::new(dest, 0));
+        self.record_registers(RegSet::of(dest), RegSet::of(dest));
+        self.end_instruction(instruction, InstructionFamily::Mov);
+        self.end_atomic_region();
+    }
+
+    /// Loads the quadword at `offset` bytes from the thread pointer into *r64*, `mov dest, qword ptr fs:[offset]`.
+    pub fn intrinsic_read_fs_base_offset(&mut self, dest: Reg64, offset: i32) {
+        let instruction = self.begin_instruction();
+        self.write_byte(FS);
+        self.write_byte(REX | REX_W | if dest.is_extension() { REX_R } else { 0 });
+        self.write_byte(0x8b);
+        self.write_byte((dest.offset() << 3) | 0b100); // SIB byte follows
+        self.write_byte(0x25); // no base, no index: `[disp32]`
+        self.write_double_word(offset as u32);
+        self.record_registers(RegSet::of(dest), RegSet::EMPTY);
+        self.end_instruction(instruction, InstructionFamily::Mov);
+    }
+}
@@ -0,0 +1,98 @@
+//! The instructions waiting for a store to an address or for a deadline, putting the processor in an optimized state meanwhile.
+//!
+//! [`x86_64InstructionStream::umonitor_reg64`] arms the monitoring of the address range holding the address in a register, and
+//! [`x86_64InstructionStream::umwait_reg32`] waits for a store to it, or until the time stamp counter reaches the deadline in `edx:eax`.
+//! [`x86_64InstructionStream::tpause_reg32`] only waits for the deadline.  The register operand of both selects the optimized state: bit 0 clear
+//! for the deeper C0.2 state, set for the C0.1 state waking up faster.  These user-mode instructions require the WAITPKG extension, which isn't
+//! tracked by [`CpuFeatures`](crate::features::CpuFeatures) as the standard library can't detect it.
+//!
+//! [`x86_64InstructionStream::monitor`] and [`x86_64InstructionStream::mwait`] are their privileged counterparts, taking their operands in `rax`,
+//! `ecx` and `edx`.
+//!
+//! The three user-mode instructions share the `0F AE /6` encoding, and are told apart by their mandatory prefix only.
+//!
+//! # Example
+//! ```
+//! use asmkit_core::InstructionStream;
+//! use asmkit_x86_64::{register::{Reg32, Reg64}, stream::x86_64InstructionStream};
+//!
+//! let mut stream = x86_64InstructionStream::new();
+//! stream.umonitor_reg64(Reg64::Rax);
+//! stream.umwait_reg32(Reg32::Eax);
+//! stream.tpause_reg32(Reg32::Eax);
+//! stream.umonitor_reg64(Reg64::R9);
+//! stream.umwait_reg32(Reg32::R9d);
+//! stream.tpause_reg32(Reg32::R9d);
+//! stream.monitor();
+//! stream.mwait();
+//!
+//! assert_eq!(stream.finish().emit(), [
+//!     0xf3, 0x0f, 0xae, 0xf0,       // umonitor rax
+//!     0xf2, 0x0f, 0xae, 0xf0,       // umwait eax
+//!     0x66, 0x0f, 0xae, 0xf0,       // tpause eax
+//!     0xf3, 0x41, 0x0f, 0xae, 0xf1, // umonitor r9
+//!     0xf2, 0x41, 0x0f, 0xae, 0xf1, // umwait r9d
+//!     0x66, 0x41, 0x0f, 0xae, 0xf1, // tpause r9d
+//!     0x0f, 0x01, 0xc8,             // monitor
+//!     0x0f, 0x01, 0xc9,             // mwait
+//! ]);
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::{RegId, RegSet},
+    register::{Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, REX, REX_B},
+};
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Arm the monitoring of the address range holding the address in *r64*, for [`x86_64InstructionStream::umwait_reg32`].
+    pub fn umonitor_reg64(&mut self, src: Reg64) {
+        self.write_wait(0xf3, src.offset(), src.is_extension());
+    }
+
+    /// Wait for a store to the monitored address range, or until the time stamp counter reaches `edx:eax`, in the optimized state selected by
+    /// *r32*.  The carry flag is set if the deadline was reached.
+    pub fn umwait_reg32(&mut self, control: Reg32) {
+        self.write_wait(0xf2, control.offset(), control.is_extension());
+    }
+
+    /// Wait until the time stamp counter reaches `edx:eax`, in the optimized state selected by *r32*.
+    pub fn tpause_reg32(&mut self, control: Reg32) {
+        self.write_wait(0x66, control.offset(), control.is_extension());
+    }
+
+    /// Arm the monitoring of the address range holding the address in `rax`, with the extensions in `ecx` and the hints in `edx`.  Privileged.
+    pub fn monitor(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xc8]);
+        self.record_registers(RegSet::EMPTY, RegSet::of(Reg64::Rax).with(Reg64::Rcx).with(Reg64::Rdx));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Wait for a store to the monitored address range, with the hints in `eax` and the extensions in `ecx`.  Privileged.
+    pub fn mwait(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[0x0f, 0x01, 0xc9]);
+        self.record_registers(RegSet::EMPTY, RegSet::of(Reg64::Rax).with(Reg64::Rcx));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+
+    /// Writes an instruction of the `0F AE /6` group with the register operand `rm`, selected by its mandatory `prefix`.
+    fn write_wait(&mut self, prefix: u8, rm: u8, is_extension: bool) {
+        let instruction = self.begin_instruction();
+        self.write_byte(prefix);
+
+        if is_extension {
+            self.write_byte(REX | REX_B);
+        }
+
+        self.write_bytes(&[0x0f, 0xae, (0b11 << 6) | (6 << 3) | rm]);
+        // `umwait` and `tpause` take the deadline in `edx:eax`.
+        let deadline = if prefix == 0xf3 { RegSet::EMPTY } else { RegSet::of(Reg64::Rax).with(Reg64::Rdx) };
+        self.record_registers(RegSet::EMPTY, deadline.with(RegId::gp(rm + if is_extension { 8 } else { 0 })));
+        self.end_instruction(instruction, InstructionFamily::System);
+    }
+}
@@ -0,0 +1,361 @@
+//! Widening multiplication and division through the `rdx:rax` register pair, and 128-bit integer intrinsics.
+//!
+//! The one-operand `mul`, `imul`, `div` and `idiv` implicitly use `rax` and `rdx`: the multiplications leave the 128-bit product in `rdx:rax`,
+//! and the divisions divide `rdx:rax`, raising a divide error when the quotient doesn't fit in 64 bits, which a stale `rdx` easily causes.
+//! [`x86_64InstructionStream::div_wide`] spells out how `rdx` is set up with a [`Dividend`].
+//!
+//! A 128-bit integer is held in a pair of registers, its low and high halves.
+//!
+//! # Example
+//! The intrinsics checked against the 128-bit arithmetic of Rust, for random inputs:
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # fn main() {
+//! use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+//! use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream, wide::Dividend};
+//!
+//! fn load(build: impl FnOnce(&mut x86_64InstructionStream)) -> ExecutableBuffer {
+//!     let mut stream = x86_64InstructionStream::new();
+//!     build(&mut stream);
+//!     stream.ret_near();
+//!     ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap()
+//! }
+//!
+//! // (a_lo, a_hi, b_lo, b_hi) in (rdi, rsi, rdx, rcx), the sum returned in rdx:rax.
+//! let add = load(|stream| {
+//!     stream.intrinsic_add_u128(Reg64::Rdi, Reg64::Rsi, Reg64::Rdx, Reg64::Rcx);
+//!     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+//!     stream.mov_reg64_reg64(Reg64::Rdx, Reg64::Rsi);
+//! });
+//!
+//! // (a, b) in (rdi, rsi), the product returned in rdx:rax.
+//! let mul = load(|stream| stream.intrinsic_mul_u64_u64_to_u128(Reg64::Rdi, Reg64::Rsi));
+//!
+//! // (lo, hi, divisor) in (rdi, rsi, rdx), the quotient returned in rax.
+//! let div = load(|stream| {
+//!     stream.mov_reg64_reg64(Reg64::Rcx, Reg64::Rdx);
+//!     stream.mov_reg64_reg64(Reg64::Rdx, Reg64::Rsi);
+//!     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+//!     stream.div_wide(Reg64::Rcx, Dividend::Unsigned);
+//! });
+//!
+//! // (dividend, divisor) in (rdi, rsi), the signed quotient returned in rax.
+//! let idiv = load(|stream| {
+//!     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+//!     stream.div_wide(Reg64::Rsi, Dividend::SignExtended);
+//! });
+//!
+//! let add: extern "C" fn(u64, u64, u64, u64) -> u128 = unsafe { std::mem::transmute(add.as_ptr()) };
+//! let mul: extern "C" fn(u64, u64) -> u128 = unsafe { std::mem::transmute(mul.as_ptr()) };
+//! let div: extern "C" fn(u64, u64, u64) -> u64 = unsafe { std::mem::transmute(div.as_ptr()) };
+//! let idiv: extern "C" fn(i64, i64) -> i64 = unsafe { std::mem::transmute(idiv.as_ptr()) };
+//!
+//! let mut state = 0x2545_f491_4f6c_dd1d_u64;
+//! let mut random = move || {
+//!     state ^= state << 13;
+//!     state ^= state >> 7;
+//!     state ^= state << 17;
+//!     state
+//! };
+//!
+//! for _ in 0..1000 {
+//!     let (a, b) = ((random() as u128) << 64 | random() as u128, (random() as u128) << 64 | random() as u128);
+//!     assert_eq!(add(a as u64, (a >> 64) as u64, b as u64, (b >> 64) as u64), a.wrapping_add(b));
+//!
+//!     let (x, y) = (random(), random());
+//!     assert_eq!(mul(x, y), x as u128 * y as u128);
+//!
+//!     // the high half of the dividend must be below the divisor for the quotient to fit in 64 bits.
+//!     let divisor = random() | 1;
+//!     let dividend = ((random() % divisor) as u128) << 64 | random() as u128;
+//!     assert_eq!(div(dividend as u64, (dividend >> 64) as u64, divisor) as u128, dividend / divisor as u128);
+//!
+//!     let (n, d) = (random() as i64, (random() as i64 >> 40) | 1);
+//!     assert_eq!(idiv(n, d), n / d);
+//! }
+//! # }
+//! # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+//! # fn main() {}
+//! ```
+
+use asmkit_core::{fixed::CodeStorage, InstructionStream};
+
+use crate::{
+    defuse::RegSet,
+    register::{Reg32, Reg64},
+    stats::InstructionFamily,
+    stream::{x86_64InstructionStream, REX, REX_B, REX_W},
+};
+
+/// How the dividend of [`x86_64InstructionStream::div_wide`] is set up in `rdx:rax`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dividend {
+    /// An unsigned dividend in `rax`, zero extended into `rdx` with `xor edx, edx` before the `div`.
+    ZeroExtended,
+
+    /// A signed dividend in `rax`, sign extended into `rdx` with `cqo` before the `idiv`.
+    SignExtended,
+
+    /// An unsigned 128-bit dividend already set up in `rdx:rax` by the caller, divided by `div`.  `rdx` must be below the divisor for the
+    /// quotient to fit in 64 bits.
+    Unsigned,
+
+    /// A signed 128-bit dividend already set up in `rdx:rax` by the caller, divided by `idiv`.
+    Signed,
+}
+
+impl<S: CodeStorage> x86_64InstructionStream<S> {
+    /// Writes an instruction of the `F7` group with the opcode extension `extension` on the 64-bit register `reg`.
+    fn write_f7_reg64(&mut self, extension: u8, reg: Reg64) {
+        let mut prefix = REX | REX_W;
+
+        if reg.is_extension() {
+            prefix |= REX_B;
+        }
+
+        self.write_byte(prefix);
+        self.write_byte(0xf7);
+        self.write_byte((0b11 << 6) | (extension << 3) | reg.offset());
+    }
+
+    /// Unsigned multiply `rax` by *r64*, leaving the 128-bit product in `rdx:rax`: `rdx:rax = rax * src`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream, wide::Dividend};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.mul_wide(Reg64::Rcx);
+    /// stream.imul_wide(Reg64::R9);
+    /// stream.div_wide(Reg64::Rcx, Dividend::ZeroExtended);
+    /// stream.div_wide(Reg64::R8, Dividend::SignExtended);
+    /// stream.div_wide(Reg64::Rsi, Dividend::Unsigned);
+    /// stream.div_wide(Reg64::Rsi, Dividend::Signed);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0xf7, 0xe1, // mul rcx
+    ///     0x49, 0xf7, 0xe9, // imul r9
+    ///     0x31, 0xd2,       // xor edx, edx
+    ///     0x48, 0xf7, 0xf1, // div rcx
+    ///     0x48, 0x99,       // cqo
+    ///     0x49, 0xf7, 0xf8, // idiv r8
+    ///     0x48, 0xf7, 0xf6, // div rsi
+    ///     0x48, 0xf7, 0xfe, // idiv rsi
+    /// ]);
+    /// ```
+    ///
+    /// The multiplications define `rdx:rax`, and the divisions use it as well:
+    /// ```
+    /// use asmkit_x86_64::{defuse::RegSet, register::Reg64, stream::x86_64InstructionStream, wide::Dividend};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.enable_listing(true);
+    /// stream.mul_wide(Reg64::Rcx);
+    /// stream.imul_wide(Reg64::R9);
+    /// stream.div_wide(Reg64::Rcx, Dividend::ZeroExtended);
+    /// stream.div_wide(Reg64::R8, Dividend::SignExtended);
+    ///
+    /// let rdx_rax = RegSet::of(Reg64::Rax).with(Reg64::Rdx);
+    /// let registers = stream.instructions().map(|instruction| (instruction.defs().unwrap(), instruction.uses().unwrap())).collect::<Vec<_>>();
+    /// assert_eq!(registers, [
+    ///     (rdx_rax, RegSet::of(Reg64::Rax).with(Reg64::Rcx)),     // mul rcx
+    ///     (rdx_rax, RegSet::of(Reg64::Rax).with(Reg64::R9)),      // imul r9
+    ///     (RegSet::of(Reg64::Rdx), RegSet::EMPTY),                // xor edx, edx
+    ///     (rdx_rax, rdx_rax.with(Reg64::Rcx)),                    // div rcx
+    ///     (RegSet::of(Reg64::Rdx), RegSet::of(Reg64::Rax)),       // cqo
+    ///     (rdx_rax, rdx_rax.with(Reg64::R8)),                     // idiv r8
+    /// ]);
+    /// ```
+    pub fn mul_wide(&mut self, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_f7_reg64(4, src);
+        self.record_registers(RegSet::of(Reg64::Rax).with(Reg64::Rdx), RegSet::of(Reg64::Rax).with(src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Signed multiply `rax` by *r64*, leaving the 128-bit product in `rdx:rax`: `rdx:rax = rax * src`.
+    pub fn imul_wide(&mut self, src: Reg64) {
+        let instruction = self.begin_instruction();
+        self.write_f7_reg64(5, src);
+        self.record_registers(RegSet::of(Reg64::Rax).with(Reg64::Rdx), RegSet::of(Reg64::Rax).with(src));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Sign extend `rax` into `rdx:rax`.
+    pub fn cqo(&mut self) {
+        let instruction = self.begin_instruction();
+        self.write_bytes(&[REX | REX_W, 0x99]);
+        self.record_registers(RegSet::of(Reg64::Rdx), RegSet::of(Reg64::Rax));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Divide `rdx:rax` by *r64*, leaving the quotient in `rax` and the remainder in `rdx`, with the dividend set up as `dividend` describes:
+    /// `rdx` is zeroed or sign extended from `rax` first, or taken as set up by the caller.  The division is signed for the signed dividends.
+    ///
+    /// A quotient which doesn't fit in 64 bits, or a zero divisor, raises a divide error when the code runs.
+    ///
+    /// # Panics
+    /// Panics if `divisor` is `rdx`, which holds the high half of the dividend.
+    ///
+    /// ```should_panic
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream, wide::Dividend};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.div_wide(Reg64::Rdx, Dividend::ZeroExtended);
+    /// ```
+    pub fn div_wide(&mut self, divisor: Reg64, dividend: Dividend) {
+        assert!(divisor != Reg64::Rdx, "the divisor of a wide division can't be `rdx`, which holds the high half of the dividend");
+
+        match dividend {
+            Dividend::ZeroExtended => self.xor_reg32_reg32(Reg32::Edx, Reg32::Edx),
+            Dividend::SignExtended => self.cqo(),
+            Dividend::Unsigned | Dividend::Signed => {},
+        }
+
+        let instruction = self.begin_instruction();
+
+        match dividend {
+            Dividend::ZeroExtended | Dividend::Unsigned => self.write_f7_reg64(6, divisor),
+            Dividend::SignExtended | Dividend::Signed => self.write_f7_reg64(7, divisor),
+        }
+
+        self.record_registers(RegSet::of(Reg64::Rax).with(Reg64::Rdx), RegSet::of(Reg64::Rax).with(Reg64::Rdx).with(divisor));
+        self.end_instruction(instruction, InstructionFamily::Arith);
+    }
+
+    /// Add the 128-bit integer `hi_src:lo_src` to `hi_dst:lo_dst`, with `add` and `adc`.
+    ///
+    /// # Panics
+    /// Panics if `lo_dst` and `hi_dst` are the same register, or if `hi_src` is `lo_dst`, which is overwritten before it is read.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_add_u128(Reg64::Rax, Reg64::Rdx, Reg64::R8, Reg64::R9);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x4c, 0x01, 0xc0, // add rax, r8
+    ///     0x4c, 0x11, 0xca, // adc rdx, r9
+    /// ]);
+    /// ```
+    pub fn intrinsic_add_u128(&mut self, lo_dst: Reg64, hi_dst: Reg64, lo_src: Reg64, hi_src: Reg64) {
+        assert!(lo_dst != hi_dst, "the halves of a 128-bit integer must be different registers");
+        assert!(hi_src != lo_dst, "the high half of the source is overwritten by the low half of the destination");
+
+        self.begin_atomic_region();
+        self.add_reg64_reg64(lo_dst, lo_src);
+        self.adc_reg64_reg64(hi_dst, hi_src);
+        self.end_atomic_region();
+    }
+
+    /// Unsigned multiply `lhs` by `rhs`, leaving the 128-bit product in `rdx:rax`.  `lhs` is moved to `rax` first, unless either operand
+    /// already is `rax`.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_mul_u64_u64_to_u128(Reg64::Rdi, Reg64::Rsi);
+    /// stream.intrinsic_mul_u64_u64_to_u128(Reg64::Rcx, Reg64::Rax);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x89, 0xf8, // mov rax, rdi
+    ///     0x48, 0xf7, 0xe6, // mul rsi
+    ///     0x48, 0xf7, 0xe1, // mul rcx
+    /// ]);
+    /// ```
+    pub fn intrinsic_mul_u64_u64_to_u128(&mut self, lhs: Reg64, rhs: Reg64) {
+        self.begin_atomic_region();
+        if lhs == Reg64::Rax {
+            self.mul_wide(rhs);
+        } else if rhs == Reg64::Rax {
+            self.mul_wide(lhs);
+        } else {
+            self.mov_reg64_reg64(Reg64::Rax, lhs);
+            self.mul_wide(rhs);
+        }
+        self.end_atomic_region();
+    }
+
+    /// Shift the 128-bit integer `hi:lo` left by `amount` bits.  Shifts below 64 bits use `shld` and `shl`, and the others move the low half
+    /// into the high half.
+    ///
+    /// # Panics
+    /// Panics if `lo` and `hi` are the same register, or if `amount` is 128 or more.
+    ///
+    /// # Example
+    /// ```
+    /// use asmkit_core::InstructionStream;
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let mut stream = x86_64InstructionStream::new();
+    /// stream.intrinsic_shl_u128(Reg64::Rax, Reg64::Rdx, 4);
+    /// stream.intrinsic_shl_u128(Reg64::Rax, Reg64::Rdx, 64);
+    /// stream.intrinsic_shl_u128(Reg64::Rax, Reg64::Rdx, 100);
+    ///
+    /// assert_eq!(stream.finish().emit(), [
+    ///     0x48, 0x0f, 0xa4, 0xc2, 0x04, // shld rdx, rax, 4
+    ///     0x48, 0xc1, 0xe0, 0x04,       // shl rax, 4
+    ///     0x48, 0x89, 0xc2,             // mov rdx, rax
+    ///     0x31, 0xc0,                   // xor eax, eax
+    ///     0x48, 0x89, 0xc2,             // mov rdx, rax
+    ///     0x48, 0xc1, 0xe2, 0x24,       // shl rdx, 36
+    ///     0x31, 0xc0,                   // xor eax, eax
+    /// ]);
+    /// ```
+    ///
+    /// Checked against the 128-bit shifts of Rust, for every amount:
+    /// ```
+    /// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    /// # fn main() {
+    /// use asmkit_core::{exec::ExecutableBuffer, InstructionStream};
+    /// use asmkit_x86_64::{register::Reg64, stream::x86_64InstructionStream};
+    ///
+    /// let value = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210_u128;
+    ///
+    /// for amount in 0..128 {
+    ///     // (lo, hi) in (rdi, rsi), the result returned in rdx:rax.
+    ///     let mut stream = x86_64InstructionStream::new();
+    ///     stream.intrinsic_shl_u128(Reg64::Rdi, Reg64::Rsi, amount);
+    ///     stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+    ///     stream.mov_reg64_reg64(Reg64::Rdx, Reg64::Rsi);
+    ///     stream.ret_near();
+    ///
+    ///     let buffer = ExecutableBuffer::new(stream.finish(), |_| unreachable!()).unwrap();
+    ///     let shl: extern "C" fn(u64, u64) -> u128 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    ///     assert_eq!(shl(value as u64, (value >> 64) as u64), value << amount, "shift by {}", amount);
+    /// }
+    /// # }
+    /// # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    /// # fn main() {}
+    /// ```
+    pub fn intrinsic_shl_u128(&mut self, lo: Reg64, hi: Reg64, amount: u8) {
+        assert!(lo != hi, "the halves of a 128-bit integer must be different registers");
+        assert!(amount < 128, "a 128-bit integer can't be shifted by {} bits", amount);
+
+        self.begin_atomic_region();
+        match amount {
+            0 => {},
+            1..=63 => {
+                self.shld_reg64_reg64_imm8(hi, lo, amount);
+                self.shl_reg64_imm8(lo, amount);
+            },
+            _ => {
+                self.mov_reg64_reg64(hi, lo);
+
+                if amount > 64 {
+                    self.shl_reg64_imm8(hi, amount - 64);
+                }
+
+                self.xor_reg32_reg32(lo.into(), lo.into());
+            },
+        }
+        self.end_atomic_region();
+    }
+}
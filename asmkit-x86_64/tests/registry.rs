@@ -0,0 +1,120 @@
+//! Checks that the registry is complete: every public method of `x86_64InstructionStream` taking `&mut self` in the sources of the crate is the
+//! emitter of a form of `INSTRUCTIONS`, a variant of such an emitter, or listed as something other than an emitter.
+
+use std::{fs, path::PathBuf};
+
+use asmkit_x86_64::registry::INSTRUCTIONS;
+
+/// The emitters left out of the registry, each with the registered emitter writing the same instruction, which the text assembler and the
+/// `asm_x64!` macro use instead.
+const VARIANTS: &[(&str, &str)] = &[
+    ("add_reg64_imm32_unsigned", "add_reg64_imm32"),
+    ("sub_reg64_imm32_unsigned", "sub_reg64_imm32"),
+    ("cmp_reg64_imm32_unsigned", "cmp_reg64_imm32"),
+    ("mov_reg64_imm32_unsigned", "mov_reg64_imm32"),
+    ("mov_mem64_imm32_unsigned", "mov_mem64_imm32"),
+    ("mov_label64_imm32_unsigned", "mov_label64_imm32"),
+    ("or_mem64_imm8_unsigned", "or_mem64_imm8"),
+    ("push_imm8_unsigned", "push_imm8"),
+    ("push_imm32_unsigned", "push_imm32"),
+    ("push_imm16", "push_imm32"),
+    ("mov_reg64_mem64_with_options", "mov_reg64_mem64"),
+    ("mov_mem64_reg64_with_options", "mov_mem64_reg64"),
+    ("mov_mem32_imm32_with_options", "mov_mem32_imm32"),
+    ("mov_mem64_imm32_with_options", "mov_mem64_imm32"),
+    ("add_reg64_imm32_with_options", "add_reg64_imm32"),
+    ("cmp_reg64_imm32_with_options", "cmp_reg64_imm32"),
+    ("cmp_reg32_imm32_with_options", "cmp_reg32_imm32"),
+    ("mov_reg64_reg64_with_options", "mov_reg64_reg64"),
+    ("mov_reg32_reg32_with_options", "mov_reg32_reg32"),
+    ("lea_reg64_mem_with_options", "lea_reg64_mem"),
+    ("nop_with_options", "nop"),
+    ("call_mem_scaled", "call_mem"),
+    ("jmp_mem_scaled", "jmp_mem"),
+    ("movsxd_reg64_mem32_scaled", "movsxd_reg64_mem32"),
+    ("mov_slot_reg64", "mov_mem64_reg64"),
+    ("mov_reg64_slot", "mov_reg64_mem64"),
+    ("bitcast_f64_to_u64", "movq_reg64_xmm"),
+    ("bitcast_u64_to_f64", "movq_xmm_reg64"),
+    ("bitcast_f32_to_u32", "movd_reg32_xmm"),
+    ("bitcast_u32_to_f32", "movd_xmm_reg32"),
+];
+
+/// The public methods taking `&mut self` which aren't emitters of a single instruction: the configuration of the stream, its symbols, labels,
+/// blocks and data, and the intrinsics emitting sequences of instructions.
+const NOT_EMITTERS: &[&str] = &[
+    // configuration.
+    "prefer_small_encodings", "set_canonicalization", "enable_peephole", "enable_stats", "enable_listing", "enable_alignment_checks",
+    "enable_patch_point_padding", "set_align_fill", "align_branch_targets", "avoid_jcc_erratum_mitigation", "function_alignment", "set_code_model",
+    "set_target", "set_chkstk_symbol", "set_stub_mode", "switch_section", "set_cold_text_align", "emit_cold", "fluent", "assemble",
+
+    // symbols, labels and annotations.
+    "create_symbol", "create_raw_symbol", "create_local_symbol", "define_symbol", "define_raw_symbol", "bind_label_absolute", "name_label",
+    "attach_label_aligned", "begin_function", "reserve_bss", "comment", "set_source_location", "clear_source_location", "register_source_file",
+    "push_region", "pop_region", "begin_atomic_region", "end_atomic_region", "emit_patch_point", "record_safepoint",
+
+    // blocks.
+    "create_block", "block_label", "switch_to_block", "br", "br_cond", "ret", "seal_and_layout", "seal_and_layout_by_weight",
+
+    // data.
+    "add_const", "add_const_xmm", "add_const_ymm", "add_const_splat_f32", "add_const_splat_f64", "add_const_splat_u32", "add_const_splat_u64",
+    "emit_jump_table", "attach_jump_table", "data_label_diff", "data_label_diff_u8", "data_label_diff_u16", "data_label_diff_u32",
+    "data_label_diff_u64",
+
+    // sequences of instructions.
+    "load_label_address", "load_symbol_address", "div_wide", "cmp_jcc", "cmp_jcc32", "switch_via_table", "switch_via_table_with_pool",
+    "save_caller_saved", "restore_caller_saved", "isr_stub",
+    "atomic_load_reg64_mem", "atomic_store_mem_reg64", "atomic_fetch_add", "atomic_compare_exchange", "atomic_fence", "intrinsic_push_rbp",
+    "intrinsic_prologue", "intrinsic_prologue_probed", "intrinsic_epilogue", "intrinsic_tail_call", "intrinsic_alloc_stack_probed",
+    "intrinsic_call", "intrinsic_memcpy", "intrinsic_memcpy_with_pool", "intrinsic_memset", "intrinsic_memset_with_pool", "intrinsic_memcmp",
+    "intrinsic_isr_prologue", "intrinsic_isr_epilogue", "intrinsic_load_tls_ie", "intrinsic_read_fs_base_offset", "intrinsic_add_u128",
+    "intrinsic_mul_u64_u64_to_u128", "intrinsic_shl_u128",
+];
+
+/// The names of the public methods of `x86_64InstructionStream` taking `&mut self`, from the inherent `impl` blocks of the sources.
+fn public_methods() -> Vec<String> {
+    let sources = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut paths = fs::read_dir(&sources).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+    paths.sort();
+
+    let mut methods = Vec::new();
+    for path in paths.iter().filter(|path| path.extension().is_some_and(|extension| extension == "rs")) {
+        let mut inherent = false;
+
+        for line in fs::read_to_string(path).unwrap().lines() {
+            if line.starts_with("impl") {
+                inherent = line.contains("x86_64InstructionStream") && !line.contains(" for ");
+            }
+
+            let Some(signature) = line.strip_prefix("    pub fn ").filter(|_| inherent) else { continue };
+            if signature.contains("&mut self") {
+                let name = signature.split(['(', '<']).next().unwrap();
+                methods.push(name.to_string());
+            }
+        }
+    }
+
+    methods
+}
+
+#[test]
+fn every_emitter_is_registered() {
+    let methods = public_methods();
+    assert!(methods.len() > 400, "only {} methods found in the sources", methods.len());
+
+    let registered = |emitter: &str| INSTRUCTIONS.iter().any(|form| form.emitter == emitter);
+    let missing = methods.iter()
+        .filter(|method| !registered(method) && !NOT_EMITTERS.contains(&method.as_str()) && !VARIANTS.iter().any(|(variant, _)| variant == method))
+        .collect::<Vec<_>>();
+    assert!(missing.is_empty(), "emitters missing from `INSTRUCTIONS`: {:?}", missing);
+
+    for (variant, emitter) in VARIANTS {
+        assert!(methods.iter().any(|method| method == variant), "`{}` isn't a method of the stream", variant);
+        assert!(registered(emitter), "`{}`, the registered emitter of `{}`, isn't registered", emitter, variant);
+    }
+
+    for method in NOT_EMITTERS {
+        assert!(methods.iter().any(|other| other == method), "`{}` isn't a method of the stream", method);
+        assert!(!registered(method), "`{}` is registered", method);
+    }
+}
@@ -1,4 +1,181 @@
 //! An assembler written in Rust.
+//!
+//! This crate re-exports [`asmkit_core`] and the backends, each backend being enabled by the feature of the same name (`x86_64`, `aarch64`, `arm` and
+//! `riscv`).  The backend of the host architecture is always enabled, and its instruction stream is available from the [`prelude`].
+//! The WebAssembly and eBPF backends, targeting virtual machines rather than a host, are enabled by the `wasm` and `bpf` features.
+//!
+//! # Example
+//! Assembling a function on the host and calling it:
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # fn main() {
+//! use asmkit::{prelude::*, x86_64::register::Reg64};
+//!
+//! extern "C" {
+//!     fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+//!     fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+//!     fn munmap(address: *mut u8, length: usize) -> i32;
+//! }
+//!
+//! // fn(u64) -> u64, returning its argument.
+//! let mut stream = HostInstructionStream::new();
+//! stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi);
+//! stream.ret_near();
+//! let code = stream.finish().emit();
+//!
+//! unsafe {
+//!     let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+//!     assert_ne!(memory as isize, -1);
+//!
+//!     std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+//!     assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+//!
+//!     let identity: extern "C" fn(u64) -> u64 = std::mem::transmute(memory);
+//!     assert_eq!(identity(42), 42);
+//!     assert_eq!(identity(u64::MAX), u64::MAX);
+//!
+//!     munmap(memory, 4096);
+//! }
+//! # }
+//! # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+//! # fn main() {}
+//! ```
+//!
+//! # Linking
+//! Functions generated by separate instruction streams are linked together with [`core::link()`], resolving the calls between them:
+//! ```
+//! # #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+//! # fn main() {
+//! use asmkit::{core::{link, LinkOptions, Product}, prelude::*, x86_64::register::Reg64};
+//!
+//! extern "C" {
+//!     fn mmap(address: *mut u8, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut u8;
+//!     fn mprotect(address: *mut u8, length: usize, protection: i32) -> i32;
+//!     fn munmap(address: *mut u8, length: usize) -> i32;
+//! }
+//!
+//! // fn(u64) -> u64, calling `callee` (or returning its argument) and adding `increment` to the result.
+//! let function = |name: &str, callee: Option<&str>, increment: i32| -> Product {
+//!     let mut stream = HostInstructionStream::new();
+//!     let start = stream.create_label_attached();
+//!     stream.define_symbol(name, start);
+//!
+//!     match callee {
+//!         Some(callee) => {
+//!             let callee = stream.create_symbol(callee);
+//!             stream.call_symbol(callee);
+//!         },
+//!         None => stream.mov_reg64_reg64(Reg64::Rax, Reg64::Rdi),
+//!     }
+//!
+//!     stream.add_reg64_imm32(Reg64::Rax, increment);
+//!     stream.ret_near();
+//!     stream.finish()
+//! };
+//!
+//! let a = function("a", Some("b"), 100);
+//! let b = function("b", Some("c"), 10);
+//! let c = function("c", None, 1);
+//! let linked = link(vec![a, b, c], LinkOptions { alignment: 16, padding: 0xcc }).unwrap();
+//! assert!(linked.relocations().is_empty());
+//!
+//! let entry = linked.definition("a").unwrap();
+//! let code = linked.emit();
+//!
+//! unsafe {
+//!     let memory = mmap(std::ptr::null_mut(), 4096, 0x1 | 0x2, 0x2 | 0x20, -1, 0); // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+//!     assert_ne!(memory as isize, -1);
+//!
+//!     std::ptr::copy_nonoverlapping(code.as_ptr(), memory, code.len());
+//!     assert_eq!(mprotect(memory, 4096, 0x1 | 0x4), 0); // PROT_READ | PROT_EXEC
+//!
+//!     let a: extern "C" fn(u64) -> u64 = std::mem::transmute(memory.add(entry));
+//!     assert_eq!(a(5), 116);
+//!
+//!     munmap(memory, 4096);
+//! }
+//! # }
+//! # #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+//! # fn main() {}
+//! ```
 
+pub mod prelude;
+
+#[cfg(any(feature = "aarch64", target_arch = "aarch64"))]
+pub use asmkit_aarch64 as aarch64;
+#[cfg(any(feature = "arm", target_arch = "arm"))]
+pub use asmkit_arm as arm;
+#[cfg(feature = "bpf")]
+pub use asmkit_bpf as bpf;
 pub use asmkit_core as core;
-pub use asmkit_x86_64 as x86_64;
\ No newline at end of file
+#[cfg(any(feature = "riscv", target_arch = "riscv64"))]
+pub use asmkit_riscv as riscv;
+#[cfg(feature = "wasm")]
+pub use asmkit_wasm as wasm;
+#[cfg(any(feature = "x86_64", target_arch = "x86_64"))]
+pub use asmkit_x86_64 as x86_64;
+
+use asmkit_core::{Architecture, InstructionStream};
+
+/// Returns true if the backend of `architecture` is enabled, either by its feature or by being the host architecture.
+pub fn is_supported(architecture: Architecture) -> bool {
+    match architecture {
+        Architecture::X86_64 => cfg!(any(feature = "x86_64", target_arch = "x86_64")),
+        Architecture::Aarch64 => cfg!(any(feature = "aarch64", target_arch = "aarch64")),
+        Architecture::Thumb => cfg!(any(feature = "arm", target_arch = "arm")),
+        Architecture::Riscv64 => cfg!(any(feature = "riscv", target_arch = "riscv64")),
+    }
+}
+
+/// Creates a new, empty instruction stream for an architecture picked at runtime.
+///
+/// # Panics
+/// Panics if the backend of `architecture` is not enabled, see [`is_supported`].
+///
+/// # Example
+/// ```
+/// use asmkit::{core::Architecture, is_supported, new_stream, prelude::*};
+///
+/// let emit = |architecture: Architecture| {
+///     let mut stream = new_stream(architecture);
+///     let start = stream.create_label_attached();
+///
+///     stream.write_bytes(b"asm");
+///     stream.write_byte(0);
+///     stream.write_word(0x1234);
+///     stream.write_double_word(0xdeadbeef);
+///     stream.write_quad_word(u64::MAX);
+///     stream.attach_label(start);
+///
+///     stream.finish().emit()
+/// };
+///
+/// let expected = [
+///     b'a', b's', b'm', 0x00,
+///     0x34, 0x12,
+///     0xef, 0xbe, 0xad, 0xde,
+///     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+/// ];
+///
+/// let architecture: Architecture = "riscv64".parse().unwrap();
+/// assert_eq!(emit(architecture), expected);
+/// assert_eq!(emit(Architecture::X86_64), expected);
+///
+/// for architecture in Architecture::ALL.into_iter().filter(|architecture| is_supported(*architecture)) {
+///     assert_eq!(emit(architecture), expected);
+/// }
+/// ```
+pub fn new_stream(architecture: Architecture) -> Box<dyn InstructionStream> {
+    match architecture {
+        #[cfg(any(feature = "x86_64", target_arch = "x86_64"))]
+        Architecture::X86_64 => Box::new(x86_64::stream::x86_64InstructionStream::new()),
+        #[cfg(any(feature = "aarch64", target_arch = "aarch64"))]
+        Architecture::Aarch64 => Box::new(aarch64::stream::Aarch64InstructionStream::new()),
+        #[cfg(any(feature = "arm", target_arch = "arm"))]
+        Architecture::Thumb => Box::new(arm::stream::ThumbInstructionStream::new()),
+        #[cfg(any(feature = "riscv", target_arch = "riscv64"))]
+        Architecture::Riscv64 => Box::new(riscv::stream::Riscv64InstructionStream::new()),
+        #[allow(unreachable_patterns)]
+        _ => panic!("the {} backend is not enabled", architecture),
+    }
+}
@@ -0,0 +1,39 @@
+//! The commonly used items of AsmKit, along with the instruction stream of the host architecture.
+//!
+//! ```
+//! use asmkit::prelude::*;
+//!
+//! let mut stream = HostInstructionStream::new();
+//! let label: LabelRef = stream.create_label_attached();
+//! stream.write_double_word(0);
+//!
+//! let product: Product = stream.finish();
+//! assert_eq!(product.emit(), [0, 0, 0, 0]);
+//! ```
+
+pub use asmkit_core::{entity::LabelRef, InstructionStream, Product};
+
+#[cfg(target_arch = "aarch64")]
+pub use asmkit_aarch64::stream::Aarch64InstructionStream;
+#[cfg(target_arch = "arm")]
+pub use asmkit_arm::stream::ThumbInstructionStream;
+#[cfg(target_arch = "riscv64")]
+pub use asmkit_riscv::stream::Riscv64InstructionStream;
+#[cfg(target_arch = "x86_64")]
+pub use asmkit_x86_64::stream::x86_64InstructionStream;
+
+/// The instruction stream of the host architecture.
+#[cfg(target_arch = "aarch64")]
+pub type HostInstructionStream = Aarch64InstructionStream;
+
+/// The instruction stream of the host architecture.
+#[cfg(target_arch = "arm")]
+pub type HostInstructionStream = ThumbInstructionStream;
+
+/// The instruction stream of the host architecture.
+#[cfg(target_arch = "riscv64")]
+pub type HostInstructionStream = Riscv64InstructionStream;
+
+/// The instruction stream of the host architecture.
+#[cfg(target_arch = "x86_64")]
+pub type HostInstructionStream = x86_64InstructionStream;